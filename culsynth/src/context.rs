@@ -1,5 +1,6 @@
 //! This module provides objects to reason about the processing context.
-//! Currently, the only information wrapped is the current audio sample rate.
+//! This wraps the current audio sample rate, as well as the level below which
+//! a voice is considered silent (see [crate::voice::Voice::is_silent]).
 
 use crate::{Float, ScalarFxP};
 
@@ -15,6 +16,7 @@ impl<Smp: Float> GetContext for Context<Smp> {
     }
 }
 
+#[cfg(feature = "fixed")]
 impl GetContext for ContextFxP {
     fn get_context(&self) -> &dyn GenericContext {
         self
@@ -28,6 +30,13 @@ pub trait GenericContext {
     fn sample_rate(&self) -> u32;
     /// Returns true if processing using fixed-point logic.
     fn is_fixed_point(&self) -> bool;
+    /// Change the sample rate, in Hz.  Devices derive all of their
+    /// rate-dependent coefficients from the context on each sample, so this
+    /// may be called at any time without resetting any device state.
+    ///
+    /// Returns false, leaving the context unchanged, if the sample rate is not
+    /// supported by this context.
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool;
 }
 
 #[derive(Clone, Copy)]
@@ -35,12 +44,35 @@ pub trait GenericContext {
 pub struct Context<Smp: Float> {
     /// The sample rate, in Hz, with the same type as a processing type
     pub sample_rate: Smp,
+    silence_threshold: Smp,
 }
 
 impl<Smp: Float> Context<Smp> {
     /// Create a new `Context`
     pub fn new(sample_rate: Smp) -> Self {
-        Self { sample_rate }
+        Self {
+            sample_rate,
+            silence_threshold: num_traits::cast(DEFAULT_SILENCE_THRESHOLD.to_num::<f64>())
+                .unwrap_or_default(),
+        }
+    }
+    /// The envelope level below which a releasing voice is considered
+    /// silent.  Defaults to [DEFAULT_SILENCE_THRESHOLD]
+    pub fn silence_threshold(&self) -> Smp {
+        self.silence_threshold
+    }
+    /// Set the level below which a releasing voice is considered silent (see
+    /// [Context::silence_threshold])
+    pub fn with_silence_threshold(mut self, threshold: Smp) -> Self {
+        self.silence_threshold = threshold;
+        self
+    }
+    /// Create a copy of this context at a different sample rate (in Hz),
+    /// keeping all other settings.  Returns `None` if the sample rate can't
+    /// be represented by `Smp`.
+    pub fn clone_with_adjusted_sr(&self, sample_rate: u32) -> Option<Self> {
+        let mut ret = *self;
+        ret.set_sample_rate(sample_rate).then_some(ret)
     }
 }
 
@@ -57,9 +89,22 @@ impl<Smp: Float> GenericContext for Context<Smp> {
     fn is_fixed_point(&self) -> bool {
         false
     }
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool {
+        match num_traits::cast(sample_rate) {
+            Some(sr) => {
+                self.sample_rate = sr;
+                true
+            }
+            None => false,
+        }
+    }
 }
 
-#[derive(Default, Clone, Copy)]
+/// The default silence threshold, about -72dB
+pub const DEFAULT_SILENCE_THRESHOLD: ScalarFxP = ScalarFxP::from_bits(16);
+
+#[cfg(feature = "fixed")]
+#[derive(Clone, Copy)]
 /// A fixed-point processing context.  Currently this is only supported for a
 /// handful of different sample rates, as properly implementing the fixed-point
 /// arithmetic requires some assumptions about the ranges of parameters, and
@@ -68,32 +113,62 @@ impl<Smp: Float> GenericContext for Context<Smp> {
 pub struct ContextFxP {
     /// The sample rate, as one of the supported FixedSampleRates:
     pub sample_rate: FixedSampleRate,
+    silence_threshold: ScalarFxP,
 }
 
+#[cfg(feature = "fixed")]
 impl ContextFxP {
-    /// Create a new fixed-point context with a sample rate of 44.1kHz
-    pub const fn new_441() -> Self {
+    const fn with_sample_rate(sample_rate: FixedSampleRate) -> Self {
         Self {
-            sample_rate: FixedSampleRate::Khz44_1,
+            sample_rate,
+            silence_threshold: DEFAULT_SILENCE_THRESHOLD,
         }
     }
+    /// The envelope level below which a releasing voice is considered
+    /// silent.  Defaults to [DEFAULT_SILENCE_THRESHOLD]
+    pub const fn silence_threshold(&self) -> ScalarFxP {
+        self.silence_threshold
+    }
+    /// Set the level below which a releasing voice is considered silent (see
+    /// [ContextFxP::silence_threshold])
+    pub const fn with_silence_threshold(mut self, threshold: ScalarFxP) -> Self {
+        self.silence_threshold = threshold;
+        self
+    }
+    /// Create a new fixed-point context with a sample rate of 44.1kHz
+    pub const fn new_441() -> Self {
+        Self::with_sample_rate(FixedSampleRate::Khz44_1)
+    }
     /// Create a new fixed-point context with a sample rate of 48kHz
     pub const fn new_480() -> Self {
-        Self {
-            sample_rate: FixedSampleRate::Khz48_0,
-        }
+        Self::with_sample_rate(FixedSampleRate::Khz48_0)
     }
     /// Create a fixed-point processing context if the sample rate provided is
     /// a supported sample rate, or return `None` otherwise.
     pub fn maybe_create(value: u32) -> Option<Self> {
         if let Ok(val) = FixedSampleRate::try_from(value) {
-            Some(Self { sample_rate: val })
+            Some(Self::with_sample_rate(val))
         } else {
             None
         }
     }
+    /// Create a copy of this context at a different sample rate (in Hz),
+    /// keeping all other settings.  Returns `None` if the sample rate is not
+    /// one of the supported [FixedSampleRate]s.
+    pub fn clone_with_adjusted_sr(&self, sample_rate: u32) -> Option<Self> {
+        let mut ret = *self;
+        ret.set_sample_rate(sample_rate).then_some(ret)
+    }
 }
 
+#[cfg(feature = "fixed")]
+impl Default for ContextFxP {
+    fn default() -> Self {
+        Self::new_441()
+    }
+}
+
+#[cfg(feature = "fixed")]
 impl GenericContext for ContextFxP {
     /// The sample rate of this fixed-point context
     fn sample_rate(&self) -> u32 {
@@ -103,8 +178,19 @@ impl GenericContext for ContextFxP {
     fn is_fixed_point(&self) -> bool {
         true
     }
+    /// Change the sample rate, if it is one of the supported [FixedSampleRate]s
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool {
+        match FixedSampleRate::try_from(sample_rate) {
+            Ok(sr) => {
+                self.sample_rate = sr;
+                true
+            }
+            Err(_) => false,
+        }
+    }
 }
 
+#[cfg(feature = "fixed")]
 #[derive(Default, Clone, Copy)]
 /// An enum representing all of the supported sample rates for fixed-point logic
 pub enum FixedSampleRate {
@@ -115,6 +201,7 @@ pub enum FixedSampleRate {
     Khz48_0,
 }
 
+#[cfg(feature = "fixed")]
 impl FixedSampleRate {
     /// Converts this sample rate to a u16
     pub const fn value(&self) -> u16 {
@@ -135,6 +222,7 @@ impl FixedSampleRate {
     }
 }
 
+#[cfg(feature = "fixed")]
 impl TryFrom<u32> for FixedSampleRate {
     type Error = &'static str;
     fn try_from(value: u32) -> Result<Self, Self::Error> {
@@ -0,0 +1,136 @@
+//! Conversions between floating point values and the fixed point types used
+//! by the fixed point synth engine (e.g. [NoteFxP], [ScalarFxP], and
+//! [SampleFxP]).
+//!
+//! Conversions into a fixed point type saturate at the limits of that type
+//! rather than wrapping, so (for example) a frequency above the top of the
+//! MIDI range converts to the highest representable note.  `NaN` converts to
+//! zero.  Conversions back to `f32` are exact, except for [hz_from_note],
+//! which uses the same approximation as the synth engine itself.
+
+use crate::fixedmath::midi_note_to_frequency;
+use crate::{EnvParamFxP, Float, IScalarFxP, LfoFreqFxP, NoteFxP, SampleFxP, ScalarFxP};
+use fixed::traits::Fixed;
+
+/// Convert a float to a fixed point type, saturating at the limits of the
+/// type and converting NaN to zero
+fn saturate<T: Fixed>(value: f32) -> T {
+    if value.is_nan() {
+        T::ZERO
+    } else {
+        T::saturating_from_num(value)
+    }
+}
+
+/// Convert a frequency in Hz to a [NoteFxP] (a MIDI note number, where 69.0
+/// is A440).  The result is clamped to `[0, 128)`, so frequencies below
+/// about 8.18Hz (MIDI note 0) convert to note 0 and frequencies above about
+/// 13.3kHz convert to just below note 128.
+pub fn note_from_hz(hz: f32) -> NoteFxP {
+    if hz.is_nan() || hz <= 0f32 {
+        return NoteFxP::ZERO;
+    }
+    saturate(69f32 + 12f32 * (hz / 440f32).flog2())
+}
+
+/// Convert a [NoteFxP] to its frequency in Hz.  This uses the same fixed
+/// point approximation as the oscillators, so it is accurate to about a cent.
+pub fn hz_from_note(note: NoteFxP) -> f32 {
+    midi_note_to_frequency(note).to_num()
+}
+
+/// Convert a float to a [ScalarFxP], clamping to `[0, 1)`.  Values of 1 or
+/// more convert to [ScalarFxP::MAX], which is slightly less than 1.
+pub fn scalar_from_f32(value: f32) -> ScalarFxP {
+    saturate(value)
+}
+
+/// Convert a [ScalarFxP] to a float in `[0, 1)`
+pub fn f32_from_scalar(value: ScalarFxP) -> f32 {
+    value.to_num()
+}
+
+/// Convert a float to an [IScalarFxP], clamping to `[-1, 1)`
+pub fn iscalar_from_f32(value: f32) -> IScalarFxP {
+    saturate(value)
+}
+
+/// Convert an [IScalarFxP] to a float in `[-1, 1)`
+pub fn f32_from_iscalar(value: IScalarFxP) -> f32 {
+    value.to_num()
+}
+
+/// Convert a float to a [SampleFxP], clamping to `[-8, 8)`.  The reference
+/// (0dB) level is an amplitude of 1, so this leaves 3 bits of headroom.
+pub fn sample_from_f32(value: f32) -> SampleFxP {
+    saturate(value)
+}
+
+/// Convert a [SampleFxP] to a float in `[-8, 8)`
+pub fn f32_from_sample(value: SampleFxP) -> f32 {
+    value.to_num()
+}
+
+/// Convert a time in seconds to an [EnvParamFxP], clamping to `[0, 8)`
+pub fn env_param_from_secs(secs: f32) -> EnvParamFxP {
+    saturate(secs)
+}
+
+/// Convert an [EnvParamFxP] to a time in seconds
+pub fn secs_from_env_param(value: EnvParamFxP) -> f32 {
+    value.to_num()
+}
+
+/// Convert a frequency in Hz to an [LfoFreqFxP], clamping to `[0, 128)`
+pub fn lfo_freq_from_hz(hz: f32) -> LfoFreqFxP {
+    saturate(hz)
+}
+
+/// Convert an [LfoFreqFxP] to a frequency in Hz
+pub fn hz_from_lfo_freq(value: LfoFreqFxP) -> f32 {
+    value.to_num()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::calculate_cents;
+
+    #[test]
+    fn note_hz_round_trip() {
+        assert_eq!(note_from_hz(440f32), NoteFxP::lit("69"));
+        for note in [21u8, 48, 60, 69, 100, 127] {
+            let note = NoteFxP::from_num(note);
+            let hz = hz_from_note(note);
+            let expected = 440f32 * f32::powf(2.0, (note.to_num::<f32>() - 69f32) / 12f32);
+            assert!(calculate_cents(expected, hz).abs() < 1f32, "{}", note);
+            let back = note_from_hz(hz).to_num::<f32>() - note.to_num::<f32>();
+            assert!(back.abs() < 0.01, "{}: {}", note, back);
+        }
+    }
+
+    #[test]
+    fn clamps_at_limits() {
+        assert_eq!(note_from_hz(0f32), NoteFxP::ZERO);
+        assert_eq!(note_from_hz(-1f32), NoteFxP::ZERO);
+        assert_eq!(note_from_hz(1f32), NoteFxP::ZERO);
+        assert_eq!(note_from_hz(20000f32), NoteFxP::MAX);
+        assert_eq!(note_from_hz(f32::NAN), NoteFxP::ZERO);
+        assert_eq!(scalar_from_f32(1.5f32), ScalarFxP::MAX);
+        assert_eq!(scalar_from_f32(-0.5f32), ScalarFxP::ZERO);
+        assert_eq!(iscalar_from_f32(-2f32), IScalarFxP::MIN);
+        assert_eq!(sample_from_f32(100f32), SampleFxP::MAX);
+        assert_eq!(sample_from_f32(f32::NEG_INFINITY), SampleFxP::MIN);
+        assert_eq!(env_param_from_secs(10f32), EnvParamFxP::MAX);
+        assert_eq!(lfo_freq_from_hz(f32::NAN), LfoFreqFxP::ZERO);
+    }
+
+    #[test]
+    fn exact_round_trips() {
+        assert_eq!(f32_from_scalar(scalar_from_f32(0.25f32)), 0.25f32);
+        assert_eq!(f32_from_iscalar(iscalar_from_f32(-0.75f32)), -0.75f32);
+        assert_eq!(f32_from_sample(sample_from_f32(-1.5f32)), -1.5f32);
+        assert_eq!(secs_from_env_param(env_param_from_secs(0.5f32)), 0.5f32);
+        assert_eq!(hz_from_lfo_freq(lfo_freq_from_hz(10f32)), 10f32);
+    }
+}
@@ -1,22 +1,41 @@
 //! This module contains definitions of several different DSP primitives.
 
-use crate::{DspFloat, DspFormat, DspFormatBase, DspType};
+use crate::{DspFloat, DspFormat, DspFormatBase, DspFormatContext, DspSerde, DspType};
 use core::iter::{repeat, Iterator, Repeat};
 
 pub(crate) mod amp;
+pub(crate) mod chain;
+pub(crate) mod compressor;
+pub(crate) mod drift;
 pub(crate) mod env;
 pub(crate) mod filt;
+pub(crate) mod filtstereo;
+pub(crate) mod glide;
+pub(crate) mod guard;
 pub(crate) mod lfo;
 pub(crate) mod mixer;
 pub(crate) mod mixosc;
 pub(crate) mod modfilt;
 pub(crate) mod osc;
+pub(crate) mod pan;
+pub(crate) mod phasereset;
+pub(crate) mod resampler;
+pub(crate) mod reset;
 pub(crate) mod ringmod;
+pub(crate) mod stretch;
+pub(crate) mod testtone;
+pub(crate) mod tremolo;
+pub(crate) mod vibrato;
+pub(crate) mod xfade;
 
 mod iter;
 
-use crate::context::{Context, ContextFxP};
-use crate::{fixedmath, EnvParamFxP, NoteFxP, SampleFxP, ScalarFxP};
+use crate::context::Context;
+#[cfg(feature = "fixed")]
+use crate::context::ContextFxP;
+#[cfg(feature = "fixed")]
+use crate::{fixedmath, NoteFxP, SampleFxP};
+use crate::{EnvParamFxP, ScalarFxP};
 
 /// A DSP Device
 ///
@@ -51,6 +70,18 @@ pub trait Device<T: DspFormat> {
     type Output;
     /// Within the provided `context`, take one sample of `input` and execute
     /// the design's DSP logic using `params`, then return a sample of output.
+    ///
+    /// Discarding the output is almost certainly a mistake, so it must be
+    /// used (or explicitly ignored with `let _ =`):
+    ///
+    /// ```compile_fail
+    /// #![deny(unused_must_use)]
+    /// use culsynth::context::Context;
+    /// use culsynth::devices::{Amp, Device};
+    /// let mut amp = Amp::<f32>::default();
+    /// amp.next(&Context::new(48000f32), 1f32, 0.5f32);
+    /// ```
+    #[must_use]
     fn next(
         &mut self,
         context: &T::Context,
@@ -59,6 +90,13 @@ pub trait Device<T: DspFormat> {
     ) -> Self::Output;
     /// This is similar to [Device::next], but works on iterators and returns
     /// an iterator to the results
+    ///
+    /// The returned [DeviceIter] is lazy (each output is only calculated when
+    /// it is requested) and does not allocate, so it is safe to use on a
+    /// realtime thread.  It holds a mutable borrow of the device for as long
+    /// as it exists.  All state is kept in the device itself, so a signal may
+    /// be processed in any number of pieces with successive calls to this
+    /// function and the result will be identical to processing it at once.
     fn process<'a, InputIt: Iterator<Item = Self::Input>, ParamIt: Iterator<Item = Self::Params>>(
         &'a mut self,
         context: &'a T::Context,
@@ -75,9 +113,31 @@ pub trait Device<T: DspFormat> {
             params,
         }
     }
+    /// Connect the output of this device to the input of `next`, returning
+    /// a [DeviceChain] that runs both devices in series
+    fn then<B: Device<T, Input = Self::Output>>(self, next: B) -> DeviceChain<T, Self, B>
+    where
+        Self: Sized,
+    {
+        DeviceChain::new(self, |out| out, next)
+    }
+    /// Like [Device::then], but use `adapt` to convert the output of this
+    /// device to the input of `next` (e.g. to select one output of a filter)
+    fn then_map<B: Device<T>>(
+        self,
+        adapt: fn(Self::Output) -> B::Input,
+        next: B,
+    ) -> DeviceChain<T, Self, B>
+    where
+        Self: Sized,
+    {
+        DeviceChain::new(self, adapt, next)
+    }
 }
 
-/// An iterator over a [Device] returned by [Device::process]
+/// An iterator over a [Device] returned by [Device::process].  This ends
+/// when either the input or the parameter iterator ends.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct DeviceIter<
     'a,
     T: DspFormat,
@@ -106,8 +166,16 @@ impl<
 }
 
 pub use amp::Amp;
-pub use env::{Env, EnvParams};
-pub use filt::{Filt, FiltOutput, FiltParams};
+pub use chain::DeviceChain;
+pub use compressor::{Compressor, CompressorParams, COMP_MAKEUP_RANGE_DB, COMP_THRESHOLD_RANGE_DB};
+pub use drift::{AnalogDrift, AnalogDriftParams, DRIFT_BLOCK_SIZE};
+pub use env::{Env, EnvIter, EnvParams, EnvStage};
+pub use filt::{
+    q_to_resonance, q_to_resonance_fxp, resonance_to_q, Filt, FiltOutput, FiltParams, FiltResponse,
+    Resonance,
+};
+pub use filtstereo::{FiltStereo, FiltStereoOutput, FiltStereoParams};
+pub use glide::{Glide, GlideParams};
 pub use iter::env::{new_env_param_iter, EnvParamIter};
 pub use iter::filt::{new_filt_param_iter, FiltParamIter};
 pub use iter::lfo::{new_lfo_param_iter, LfoParamIter};
@@ -127,5 +195,16 @@ pub use lfo::{Lfo, LfoOptions, LfoParams, LfoWave};
 pub use mixer::Mixer;
 pub use mixosc::{MixOsc, MixOscParams, SyncedMixOscs, SyncedMixOscsOutput, SyncedMixOscsParams};
 pub use modfilt::{ModFilt, ModFiltInput, ModFiltParams};
-pub use osc::{Osc, OscOutput, OscParams, SyncedOscs, SyncedOscsOutput, SyncedOscsParams};
+pub use osc::{
+    Osc, OscOutput, OscParams, OscRatio, SyncedOscs, SyncedOscsOutput, SyncedOscsParams,
+};
+pub use pan::{Pan, PanOutput, PanParams, PAN_GAIN_RANGE_DB};
+pub use phasereset::PhaseReset;
+pub use resampler::{Resampler, ResamplerParams};
+pub use reset::ResetMode;
 pub use ringmod::{RingMod, RingModInput, RingModParams};
+pub use stretch::{StretchTuning, StretchTuningParams};
+pub use testtone::{TestTone, TestToneParams};
+pub use tremolo::{Tremolo, TremoloParams};
+pub use vibrato::{Vibrato, VibratoParams};
+pub use xfade::{Xfade, XfadeInput};
@@ -16,6 +16,24 @@ pub struct Amp<T: DspFormat> {
     phantom: core::marker::PhantomData<T>,
 }
 
+impl<T: DspFormat> Amp<T> {
+    /// Crossfade between a dry and a wet signal, returning
+    /// `dry * (1 - mix) + wet * mix` (saturating for fixed point types).
+    ///
+    /// A mix of zero returns `dry` unchanged, and a mix of one (or the
+    /// maximum Scalar, for fixed point) returns `wet` unchanged.
+    pub fn mix(dry: T::Sample, wet: T::Sample, mix: T::Scalar) -> T::Sample {
+        if mix == T::Scalar::zero() {
+            dry
+        } else if mix >= T::Scalar::one() {
+            wet
+        } else {
+            let dry_gain = T::Scalar::one() - mix;
+            dry.scale(dry_gain).dsp_saturating_add(wet.scale(mix))
+        }
+    }
+}
+
 impl<T: DspFormat> Device<T> for Amp<T> {
     type Input = T::Sample;
     type Params = T::Scalar;
@@ -0,0 +1,53 @@
+use super::*;
+
+/// Two [Device]s connected in series, returned by [Device::then] and
+/// [Device::then_map]
+///
+/// The output of the first device is passed (via an adapter function, for
+/// devices with multiple outputs) as the input of the second.  The chain is
+/// itself a [Device], taking the input of the first device, a tuple of the
+/// parameters of both devices, and producing the output of the second, so
+/// chains may be extended to any length.
+#[derive(Clone)]
+pub struct DeviceChain<T: DspFormat, A: Device<T>, B: Device<T>> {
+    first: A,
+    second: B,
+    adapt: fn(A::Output) -> B::Input,
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: DspFormat, A: Device<T>, B: Device<T>> DeviceChain<T, A, B> {
+    /// Connect the output of `first` to the input of `second`, using `adapt`
+    /// to convert between them
+    pub fn new(first: A, adapt: fn(A::Output) -> B::Input, second: B) -> Self {
+        Self {
+            first,
+            second,
+            adapt,
+            phantom: Default::default(),
+        }
+    }
+    /// The first device in the chain
+    pub fn first(&self) -> &A {
+        &self.first
+    }
+    /// The second device in the chain
+    pub fn second(&self) -> &B {
+        &self.second
+    }
+}
+
+impl<T: DspFormat, A: Device<T>, B: Device<T>> Device<T> for DeviceChain<T, A, B> {
+    type Input = A::Input;
+    type Params = (A::Params, B::Params);
+    type Output = B::Output;
+    fn next(
+        &mut self,
+        context: &T::Context,
+        input: A::Input,
+        params: (A::Params, B::Params),
+    ) -> B::Output {
+        let out = self.first.next(context, input, params.0);
+        self.second.next(context, (self.adapt)(out), params.1)
+    }
+}
@@ -0,0 +1,263 @@
+use super::*;
+#[cfg(feature = "fixed")]
+use crate::fixedmath::{I16F16, I1F15, I5F27, U16F0, U16F16, U5F11};
+use crate::fixedmath::{I5F11, I8F24, U2F14, U4F12};
+
+/// The range of the compressor threshold, in decibels: a threshold parameter
+/// of 0 is 0dBFS, and 1 is this many dB below full scale.
+pub const COMP_THRESHOLD_RANGE_DB: u16 = 60;
+
+/// The range of the compressor makeup gain, in decibels: a makeup parameter
+/// of 1 boosts the output by this many dB.
+pub const COMP_MAKEUP_RANGE_DB: u16 = 24;
+
+/// [COMP_THRESHOLD_RANGE_DB] in units of log2 (i.e. divided by 20*log10(2))
+const THRESHOLD_RANGE_LOG2: U4F12 = U4F12::lit("9.965784");
+/// [COMP_MAKEUP_RANGE_DB] in units of log2 (i.e. divided by 20*log10(2))
+const MAKEUP_RANGE_LOG2: U2F14 = U2F14::lit("3.986314");
+/// Half the width of the soft knee, in units of log2.  The knee is 1 (about
+/// 6dB) wide, which simplifies the knee calculation.
+const HALF_KNEE_LOG2: I5F11 = I5F11::lit("0.5");
+/// The limit of the total gain (including the makeup gain), in units of
+/// log2 (about 34dB)
+const GAIN_LIMIT_LOG2: I5F11 = I5F11::lit("5.75");
+/// ln(2), to calculate 2^x as e^(x*ln(2))
+const LN_2: I8F24 = I8F24::lit("0.693147");
+/// 20*log10(2), the number of decibels in one unit of log2
+const DB_PER_LOG2: f32 = 6.020_6;
+
+pub(crate) mod detail {
+    use super::*;
+    pub trait CompressorOps: DspFormatContext {
+        /// A type representing a level or gain in units of log2 (~6dB)
+        type Log2: Copy + Default + Send + PartialOrd + DspSerde;
+        /// The state of the gain reduction smoother, which may have more
+        /// precision than a Log2 to allow for long release times
+        type Log2Acc: Copy + Default + Send + DspSerde;
+        /// The level of `signal`, as log2 of its absolute value
+        fn comp_level(signal: Self::Sample) -> Self::Log2;
+        /// The (unsmoothed) gain reduction for a signal at `level`
+        fn comp_reduction(
+            level: Self::Log2,
+            threshold: Self::Scalar,
+            ratio: Self::Scalar,
+        ) -> Self::Log2;
+        /// Move the gain reduction from `last` towards `target`, using the
+        /// attack time if it is increasing and the release time otherwise
+        fn comp_smooth(
+            context: &Self::Context,
+            last: Self::Log2Acc,
+            target: Self::Log2,
+            attack: Self::EnvParam,
+            release: Self::EnvParam,
+        ) -> Self::Log2Acc;
+        /// Apply the gain reduction and makeup gain to `signal`
+        fn comp_apply(
+            signal: Self::Sample,
+            reduction: Self::Log2Acc,
+            makeup: Self::Scalar,
+        ) -> Self::Sample;
+        /// Convert a gain reduction to decibels
+        fn comp_reduction_db(reduction: Self::Log2Acc) -> f32;
+    }
+}
+
+/// Parameters for a [Compressor]
+#[derive(Clone, Default)]
+pub struct CompressorParams<T: DspFormatBase> {
+    /// The threshold, from 0 (0dBFS) to 1 ([COMP_THRESHOLD_RANGE_DB] below
+    /// full scale)
+    pub threshold: T::Scalar,
+    /// The compression ratio, expressed as the fraction of the level above
+    /// the threshold that is removed.  A ratio of `r:1` is `1 - 1/r`, so 0 is
+    /// no compression, 0.5 is 2:1, and 1 is a limiter.
+    pub ratio: T::Scalar,
+    /// The attack time constant, in seconds
+    pub attack: T::EnvParam,
+    /// The release time constant, in seconds
+    pub release: T::EnvParam,
+    /// The makeup gain, from 0 (unity) to 1 ([COMP_MAKEUP_RANGE_DB] of boost)
+    pub makeup: T::Scalar,
+}
+
+impl<T: DspFloat> From<&CompressorParams<i16>> for CompressorParams<T> {
+    fn from(value: &CompressorParams<i16>) -> Self {
+        Self {
+            threshold: value.threshold.to_num(),
+            ratio: value.ratio.to_num(),
+            attack: value.attack.to_num(),
+            release: value.release.to_num(),
+            makeup: value.makeup.to_num(),
+        }
+    }
+}
+
+/// A soft-knee feed-forward compressor
+///
+/// This uses a peak detector, and calculates the gain reduction in the log
+/// domain, with a knee about 6dB wide centered on the threshold.  The gain
+/// reduction is then smoothed by a one pole filter, with separate attack and
+/// release time constants.  The total gain (including the makeup gain) is
+/// limited to about +/-34dB.
+///
+/// This implements [Device], taking a Sample as input and [CompressorParams]
+/// as parameters, and outputting a Sample.  A stereo signal can be
+/// compressed with [Compressor::next_stereo], which applies the same gain to
+/// both channels.
+#[derive(Clone, Default)]
+pub struct Compressor<T: DspFormat> {
+    reduction: T::Log2Acc,
+}
+
+impl<T: DspFormat> Compressor<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// The current gain reduction, in decibels (not including the makeup
+    /// gain).  This is positive when the signal is being compressed.
+    pub fn gain_reduction(&self) -> f32 {
+        T::comp_reduction_db(self.reduction)
+    }
+    fn update(&mut self, context: &T::Context, level: T::Log2, params: &CompressorParams<T>) {
+        let target = T::comp_reduction(level, params.threshold, params.ratio);
+        self.reduction = T::comp_smooth(
+            context,
+            self.reduction,
+            target,
+            params.attack,
+            params.release,
+        );
+    }
+    /// Compress a stereo signal, detecting the level from the louder of the
+    /// two channels and applying the same gain to both so the stereo image
+    /// does not shift.
+    pub fn next_stereo(
+        &mut self,
+        context: &T::Context,
+        left: T::Sample,
+        right: T::Sample,
+        params: CompressorParams<T>,
+    ) -> (T::Sample, T::Sample) {
+        let (left_level, right_level) = (T::comp_level(left), T::comp_level(right));
+        let level = if left_level > right_level {
+            left_level
+        } else {
+            right_level
+        };
+        self.update(context, level, &params);
+        (
+            T::comp_apply(left, self.reduction, params.makeup),
+            T::comp_apply(right, self.reduction, params.makeup),
+        )
+    }
+}
+
+impl<T: DspFormat> Device<T> for Compressor<T> {
+    type Input = T::Sample;
+    type Params = CompressorParams<T>;
+    type Output = T::Sample;
+    fn next(
+        &mut self,
+        context: &T::Context,
+        signal: T::Sample,
+        params: CompressorParams<T>,
+    ) -> T::Sample {
+        self.update(context, T::comp_level(signal), &params);
+        T::comp_apply(signal, self.reduction, params.makeup)
+    }
+}
+
+impl<T: DspFloat> detail::CompressorOps for T {
+    type Log2 = T;
+    type Log2Acc = T;
+    fn comp_level(signal: T) -> T {
+        // Limit to the smallest fixed point level to avoid log2(0)
+        signal.abs().max(ScalarFxP::DELTA.to_num()).flog2()
+    }
+    fn comp_reduction(level: T, threshold: T, ratio: T) -> T {
+        let half_knee = HALF_KNEE_LOG2.to_num::<T>();
+        let over = level + threshold * THRESHOLD_RANGE_LOG2.to_num::<T>();
+        if over <= -half_knee {
+            T::ZERO
+        } else if over < half_knee {
+            // Quadratic interpolation across the knee, which has a width of 1
+            let x = over + half_knee;
+            ratio * x * x * T::ONE_HALF
+        } else {
+            ratio * over
+        }
+    }
+    fn comp_smooth(context: &Context<T>, last: T, target: T, attack: T, release: T) -> T {
+        let time = if target > last { attack } else { release };
+        last + (target - last) / (time * context.sample_rate + T::ONE)
+    }
+    fn comp_apply(signal: T, reduction: T, makeup: T) -> T {
+        let limit = GAIN_LIMIT_LOG2.to_num::<T>();
+        let gain_log2 = makeup * MAKEUP_RANGE_LOG2.to_num::<T>() - reduction;
+        let gain_log2 = gain_log2.max(limit.neg()).min(limit);
+        signal * (gain_log2 * LN_2.to_num::<T>()).fexp()
+    }
+    fn comp_reduction_db(reduction: T) -> f32 {
+        reduction.as_f32() * DB_PER_LOG2
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl detail::CompressorOps for i16 {
+    type Log2 = I5F11;
+    type Log2Acc = I5F27;
+    fn comp_level(signal: SampleFxP) -> I5F11 {
+        use crate::fixedmath::log2_fixed_wide;
+        // log2(0) is -16, the minimum of an I5F11
+        I5F11::from_num(log2_fixed_wide(U16F16::from_num(signal.unsigned_abs())))
+    }
+    fn comp_reduction(level: I5F11, threshold: ScalarFxP, ratio: ScalarFxP) -> I5F11 {
+        let over = level + I5F11::from_num(threshold.wide_mul(THRESHOLD_RANGE_LOG2));
+        if over <= -HALF_KNEE_LOG2 {
+            I5F11::ZERO
+        } else if over < HALF_KNEE_LOG2 {
+            // Quadratic interpolation across the knee, which has a width of 1
+            let x = ScalarFxP::from_num(over + HALF_KNEE_LOG2);
+            let x2 = ScalarFxP::from_num(x.wide_mul(x));
+            I5F11::from_num(ratio.wide_mul(x2).unwrapped_shr(1))
+        } else {
+            I5F11::from_num(ratio.wide_mul(U5F11::from_num(over)))
+        }
+    }
+    fn comp_smooth(
+        context: &ContextFxP,
+        last: I5F27,
+        target: I5F11,
+        attack: EnvParamFxP,
+        release: EnvParamFxP,
+    ) -> I5F27 {
+        use crate::fixedmath::{one_over_one_plus, scale_shr_round};
+        let target = I5F27::from_num(target);
+        let time = if target > last { attack } else { release };
+        let sr = U16F0::from_bits(context.sample_rate.value());
+        let (gain, shift) = one_over_one_plus(time.wide_mul(sr));
+        // Keep full precision, since for long release times the change per
+        // sample is only a few LSBs
+        last + scale_shr_round(target - last, gain, shift)
+    }
+    fn comp_apply(signal: SampleFxP, reduction: I5F27, makeup: ScalarFxP) -> SampleFxP {
+        use crate::fixedmath::exp2_fixed;
+        let makeup = I5F11::from_num(makeup.wide_mul(MAKEUP_RANGE_LOG2));
+        let gain_log2 =
+            (makeup - I5F11::from_num(reduction)).clamp(-GAIN_LIMIT_LOG2, GAIN_LIMIT_LOG2);
+        // 2^x = 2^int(x) * 2^frac(x), and the integer part is just a shift
+        let gain = exp2_fixed(I1F15::from_num(gain_log2.frac()));
+        let smp = I16F16::from_num(signal.wide_mul_unsigned(gain));
+        let shift = gain_log2.int().to_num::<i32>();
+        let smp = if shift < 0 {
+            smp.unwrapped_shr(shift.unsigned_abs())
+        } else {
+            smp.unwrapped_shl(shift as u32)
+        };
+        SampleFxP::saturating_from_num(smp)
+    }
+    fn comp_reduction_db(reduction: I5F27) -> f32 {
+        reduction.to_num::<f32>() * DB_PER_LOG2
+    }
+}
@@ -0,0 +1,220 @@
+use super::*;
+#[cfg(feature = "fixed")]
+use crate::fixedmath::I2F14;
+use crate::IScalarFxP;
+use crate::LfoFreqFxP;
+#[cfg(feature = "fixed")]
+use crate::SignedNoteFxP;
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+
+/// The number of samples between steps of the random walk when an
+/// [AnalogDrift] is used as a [Device]
+pub const DRIFT_BLOCK_SIZE: usize = 256;
+
+pub(crate) mod detail {
+    use super::*;
+    /// The defaults of [AnalogDriftParams].  This is separate from
+    /// [DriftOps] so the fixed point parameters can be used without the
+    /// `fixed` feature.
+    pub trait DriftParamOps: DspFormatBase {
+        const DRIFT_RATE_DEFAULT: Self::LfoFreq;
+        const DRIFT_RANGE_DEFAULT: Self::Scalar;
+    }
+
+    pub trait DriftOps: DspFormatContext + DriftParamOps {
+        /// The type of the drift accumulator, in semitones
+        type DriftAcc: Copy + Default + Send + DspSerde;
+        fn drift_step(
+            context: &Self::Context,
+            acc: Self::DriftAcc,
+            params: &AnalogDriftParams<Self>,
+            rand: IScalarFxP,
+            samples: usize,
+        ) -> Self::DriftAcc;
+        fn drift_offset(acc: Self::DriftAcc) -> Self::NoteOffset;
+        fn drift_to_float(acc: Self::DriftAcc) -> f32;
+    }
+}
+
+/// Parameters for an [AnalogDrift]
+#[derive(Clone)]
+pub struct AnalogDriftParams<T: DspFormatBase> {
+    /// The rate of the random walk, in Hz.  At a rate of 1Hz, the drift can
+    /// move across its full range (from `-drift_range` to `drift_range`) in
+    /// about one second.
+    pub drift_rate: T::LfoFreq,
+    /// The maximum deviation from the played note, in semitones (so the
+    /// maximum range is 100 cents in either direction)
+    pub drift_range: T::Scalar,
+}
+
+impl<T: DspFormatBase + detail::DriftParamOps> Default for AnalogDriftParams<T> {
+    fn default() -> Self {
+        Self {
+            drift_rate: T::DRIFT_RATE_DEFAULT,
+            drift_range: T::DRIFT_RANGE_DEFAULT,
+        }
+    }
+}
+
+impl<T: DspFloat> From<&AnalogDriftParams<i16>> for AnalogDriftParams<T> {
+    fn from(value: &AnalogDriftParams<i16>) -> Self {
+        Self {
+            drift_rate: value.drift_rate.to_num(),
+            drift_range: value.drift_range.to_num(),
+        }
+    }
+}
+
+/// Simulates the slow pitch drift of an analog oscillator
+///
+/// The drift is a bounded random walk, taking one step for each block of
+/// samples (see [AnalogDrift::step]).  Steps that would leave the range set
+/// by [AnalogDriftParams::drift_range] are reflected back into it.
+///
+/// This implements [Device], taking a note as input and [AnalogDriftParams]
+/// as parameters, and outputting the note offset by the current drift.  When
+/// used as a device, the random walk takes one step every [DRIFT_BLOCK_SIZE]
+/// samples, independent of the host's buffer size.
+#[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct AnalogDrift<T: DspFormatBase + detail::DriftOps> {
+    rng: Xoshiro256PlusPlus,
+    acc: T::DriftAcc,
+    samples: usize,
+}
+
+impl<T: DspFormat> AnalogDrift<T> {
+    /// Constructor
+    ///
+    /// The drift is driven by a Xoshiro256++ generator seeded from `seed`.
+    /// Older versions used `SmallRng`, which is a different generator
+    /// on 32 bit targets, so a given seed drifts differently there now.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+            acc: Default::default(),
+            samples: 0,
+        }
+    }
+    /// Take one step of the random walk, for a block of `samples` samples
+    pub fn step(&mut self, context: &T::Context, params: &AnalogDriftParams<T>, samples: usize) {
+        let rand = IScalarFxP::from_bits(self.rng.next_u32() as u16 as i16);
+        self.acc = T::drift_step(context, self.acc, params, rand, samples);
+    }
+    /// The current drift, as an offset to a note
+    pub fn offset(&self) -> T::NoteOffset {
+        T::drift_offset(self.acc)
+    }
+    /// The current drift, in cents
+    pub fn cents(&self) -> f32 {
+        T::drift_to_float(self.acc) * 100f32
+    }
+}
+
+impl<T: DspFormat> Default for AnalogDrift<T> {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+impl<T: DspFormat> Device<T> for AnalogDrift<T> {
+    type Input = T::Note;
+    type Params = AnalogDriftParams<T>;
+    type Output = T::Note;
+    fn next(
+        &mut self,
+        context: &T::Context,
+        note: T::Note,
+        params: AnalogDriftParams<T>,
+    ) -> T::Note {
+        self.samples += 1;
+        if self.samples >= DRIFT_BLOCK_SIZE {
+            self.samples = 0;
+            self.step(context, &params, DRIFT_BLOCK_SIZE);
+        }
+        T::apply_note_offset(note, self.offset())
+    }
+}
+
+impl<T: DspFloat> detail::DriftParamOps for T {
+    const DRIFT_RATE_DEFAULT: T = T::POINT_ONE;
+    const DRIFT_RANGE_DEFAULT: T = T::POINT_ONE;
+}
+
+impl<T: DspFloat> detail::DriftOps for T {
+    type DriftAcc = T;
+    fn drift_step(
+        context: &Context<T>,
+        acc: T,
+        params: &AnalogDriftParams<T>,
+        rand: IScalarFxP,
+        samples: usize,
+    ) -> T {
+        let range = params.drift_range;
+        let samples: T = num_traits::cast(samples).unwrap_or(T::ZERO);
+        let max_step = T::TWO * range * params.drift_rate * samples / context.sample_rate;
+        let acc = acc + max_step * rand.to_num::<T>();
+        if acc > range {
+            (range + range - acc).max(-range)
+        } else if acc < -range {
+            (-range - range - acc).min(range)
+        } else {
+            acc
+        }
+    }
+    fn drift_offset(acc: T) -> T {
+        acc
+    }
+    fn drift_to_float(acc: T) -> f32 {
+        acc.to_f32().unwrap_or_default()
+    }
+}
+
+impl detail::DriftParamOps for i16 {
+    const DRIFT_RATE_DEFAULT: LfoFreqFxP = LfoFreqFxP::lit("0.1");
+    const DRIFT_RANGE_DEFAULT: ScalarFxP = ScalarFxP::lit("0.1");
+}
+
+#[cfg(feature = "fixed")]
+impl detail::DriftOps for i16 {
+    type DriftAcc = I2F14;
+    fn drift_step(
+        context: &ContextFxP,
+        acc: I2F14,
+        params: &AnalogDriftParams<i16>,
+        rand: IScalarFxP,
+        samples: usize,
+    ) -> I2F14 {
+        use crate::context::GenericContext;
+        let range = I2F14::from_num(params.drift_range).to_bits() as i32;
+        // The maximum step is 2 * range * rate * samples / sample_rate.  Keep
+        // 16 extra fractional bits, since the step is only a few LSBs of the
+        // accumulator at slow rates.
+        let max_step = ((range as u64 * params.drift_rate.to_bits() as u64 * samples as u64)
+            << (17 - LfoFreqFxP::FRAC_NBITS))
+            / context.sample_rate() as u64;
+        // Round to nearest so the walk is not biased towards negative values
+        let step = ((rand.to_bits() as i64 * max_step as i64 + (1 << 30)) >> 31) as i32;
+        let acc = acc.to_bits() as i32 + step;
+        let acc = if acc > range {
+            2 * range - acc
+        } else if acc < -range {
+            -2 * range - acc
+        } else {
+            acc
+        };
+        I2F14::from_bits(acc.clamp(-range, range) as i16)
+    }
+    fn drift_offset(acc: I2F14) -> SignedNoteFxP {
+        SignedNoteFxP::from_num(acc)
+    }
+    fn drift_to_float(acc: I2F14) -> f32 {
+        acc.to_num()
+    }
+}
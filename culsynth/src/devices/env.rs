@@ -4,14 +4,6 @@ pub(crate) mod detail {
 
     pub use crate::fixedmath::I3F29 as EnvSignalFxP;
 
-    #[derive(Eq, PartialEq, Clone, Copy, Default)]
-    pub enum EnvMode {
-        #[default]
-        Release,
-        Attack,
-        Decay,
-    }
-
     pub trait EnvType<T: DspFormatBase>: Copy + Default + From<T::Scalar> + PartialOrd {
         fn to_scalar(self) -> T::Scalar;
     }
@@ -22,7 +14,7 @@ pub(crate) mod detail {
         }
     }
 
-    impl<T: crate::Float + Send> EnvType<T> for T
+    impl<T: crate::Float + Send + DspSerde> EnvType<T> for T
     where
         T: From<crate::IScalarFxP> + From<crate::NoteFxP>,
     {
@@ -31,11 +23,20 @@ pub(crate) mod detail {
         }
     }
 
-    pub trait EnvOps: crate::DspFormatBase {
+    /// The defaults of [EnvParams].  This is separate from [EnvOps] so the
+    /// fixed point parameters can be used without the `fixed` feature.
+    pub trait EnvParamOps: DspFormatBase {
+        const ADR_DEFAULT: Self::EnvParam;
+    }
+
+    pub trait EnvOps: crate::DspFormatContext + EnvParamOps {
         const SIGNAL_MIN: Self::EnvSignal;
         const SIGNAL_MAX: Self::EnvSignal;
         const ATTACK_THRESHOLD: Self::EnvSignal;
-        const ADR_DEFAULT: Self::EnvParam;
+        /// The level at which an attack towards `peak` is considered complete
+        fn attack_threshold(peak: Self::EnvSignal) -> Self::EnvSignal;
+        /// Returns true if `signal` has (practically) reached `target`
+        fn env_settled(signal: Self::EnvSignal, target: Self::EnvSignal) -> bool;
         fn calc_env(
             context: &Self::Context,
             setpoint: Self::EnvSignal,
@@ -46,7 +47,51 @@ pub(crate) mod detail {
     }
 }
 
-use detail::{EnvMode, EnvSignalFxP, EnvType};
+#[cfg(feature = "fixed")]
+use detail::EnvSignalFxP;
+use detail::EnvType;
+
+/// The current stage of an [Env]
+///
+/// Internally, there is no separate sustain stage - the envelope remains in
+/// the decay stage until the gate is released, and an envelope that has
+/// finished releasing remains in the release stage.  [Env::stage] reports
+/// these as [EnvStage::Sustain] and [EnvStage::Idle] once the output has
+/// settled at the sustain level or zero, respectively.
+#[derive(Eq, PartialEq, Clone, Copy, Default, Debug)]
+#[repr(u8)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnvStage {
+    /// Releasing towards zero
+    #[default]
+    Release,
+    /// Attacking towards the peak level (see [EnvParams::attack_peak])
+    Attack,
+    /// Decaying towards the sustain level
+    Decay,
+    /// Holding at the sustain level
+    Sustain,
+    /// Finished releasing, or never triggered
+    Idle,
+}
+
+impl EnvStage {
+    /// Provides the name of the envelope stage
+    pub const fn to_str(&self) -> &'static str {
+        ["Release", "Attack", "Decay", "Sustain", "Idle"][*self as usize]
+    }
+}
+
+impl TryFrom<u8> for EnvStage {
+    type Error = &'static str;
+    fn try_from(value: u8) -> Result<Self, &'static str> {
+        if value <= EnvStage::Idle as u8 {
+            unsafe { Ok(core::mem::transmute::<u8, EnvStage>(value)) }
+        } else {
+            Err("Conversion of u8 to EnvStage Overflowed")
+        }
+    }
+}
 
 /// Parameters for an [Env].  Note that the time parameters are not
 /// strictly time-accurate - the goal here is to give more of a qualitative feel
@@ -54,26 +99,42 @@ use detail::{EnvMode, EnvSignalFxP, EnvType};
 /// timing is desired, the data displayed to the user can be refined on the UI
 /// side.
 ///
+/// Each stage is recalculated every sample from the current output level, so
+/// changing a time parameter partway through a stage (e.g. shortening the
+/// release while a note is releasing) changes the slope from that point on
+/// without any jump in the output.
+///
 /// TODO:  Determine formula for converting to a precise rise/fall time
 #[derive(Clone)]
 pub struct EnvParams<T: DspFormatBase> {
     /// Attack time, in seconds (approx)
     pub attack: T::EnvParam,
+    /// The level the attack rises to before decaying to the sustain level,
+    /// between 0 and 1.  This defaults to full scale, and scales the whole
+    /// envelope (see [EnvParams::sustain]).
+    pub attack_peak: T::Scalar,
     /// Decay time, in seconds (approx)
     pub decay: T::EnvParam,
-    /// Sustain level, between 0 and 1
+    /// Sustain level, as a fraction (between 0 and 1) of the attack peak.
+    /// A sustain below one gives a percussive bump at the start of each
+    /// note, and a sustain of one holds a flat tone at the peak with no
+    /// decay.
     pub sustain: T::Scalar,
     /// Release time, in seconds (approx)
     pub release: T::EnvParam,
+    /// How the envelope restarts on a new note (see [ResetMode])
+    pub reset: ResetMode,
 }
 
-impl<T: DspFormatBase + detail::EnvOps> Default for EnvParams<T> {
+impl<T: DspFormatBase + detail::EnvParamOps> Default for EnvParams<T> {
     fn default() -> Self {
         Self {
             attack: T::ADR_DEFAULT,
+            attack_peak: T::Scalar::one(),
             decay: T::ADR_DEFAULT,
             sustain: T::Scalar::one(),
             release: T::ADR_DEFAULT,
+            reset: ResetMode::default(),
         }
     }
 }
@@ -82,19 +143,140 @@ impl<T: DspFloat> From<&EnvParams<i16>> for EnvParams<T> {
     fn from(value: &EnvParams<i16>) -> Self {
         EnvParams::<T> {
             attack: value.attack.to_num(),
+            attack_peak: value.attack_peak.to_num(),
             decay: value.decay.to_num(),
             sustain: value.sustain.to_num(),
             release: value.release.to_num(),
+            reset: value.reset,
+        }
+    }
+}
+
+impl EnvParams<i16> {
+    /// Convert parameters where `sustain` is an absolute level (as it was
+    /// before the sustain level was made relative to the attack peak) to
+    /// parameters giving the same envelope.  The old attack always rose at
+    /// least to the sustain level, so the peak is raised to it if needed.
+    pub fn from_absolute_sustain(self) -> Self {
+        let attack_peak = if self.attack_peak > self.sustain {
+            self.attack_peak
+        } else {
+            self.sustain
+        };
+        let sustain = if self.sustain < attack_peak {
+            self.sustain / attack_peak
+        } else {
+            ScalarFxP::MAX
+        };
+        Self {
+            attack_peak,
+            sustain,
+            ..self
         }
     }
 }
 
 /// An ADSR Envelope Generator
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct Env<T: DspFormatBase + detail::EnvOps> {
     setpoint: T::EnvSignal,
     signal: T::EnvSignal,
-    mode: EnvMode,
+    mode: EnvStage,
+}
+
+impl<T: DspFormat> Env<T> {
+    /// The current stage of the envelope, as of the last call to
+    /// [Device::next]
+    pub fn stage(&self) -> EnvStage {
+        match self.mode {
+            EnvStage::Decay if T::env_settled(self.signal, self.setpoint) => EnvStage::Sustain,
+            EnvStage::Release if T::env_settled(self.signal, T::SIGNAL_MIN) => EnvStage::Idle,
+            mode => mode,
+        }
+    }
+    /// Process a single sample, as [Device::next], but also return the stage
+    /// of the envelope (see [Env::stage]) alongside its output
+    pub fn next_with_stage(
+        &mut self,
+        context: &T::Context,
+        gate: bool,
+        params: EnvParams<T>,
+    ) -> (T::Scalar, EnvStage) {
+        let out = self.next(context, gate, params);
+        (out, self.stage())
+    }
+    /// Returns an iterator that drives this envelope with a constant gate and
+    /// parameters, yielding one output sample per call to `next()`.
+    ///
+    /// This is mainly intended to make tests and signal generation simpler -
+    /// the iterator never ends, so use e.g. `take()` to limit it.
+    pub fn iter<'a>(
+        &'a mut self,
+        context: &'a T::Context,
+        gate: bool,
+        params: EnvParams<T>,
+    ) -> EnvIter<'a, T> {
+        EnvIter {
+            env: self,
+            context,
+            gate,
+            params,
+        }
+    }
+}
+
+/// An iterator over the output of an [Env] returned by [Env::iter].  This
+/// holds a mutable borrow of the envelope for as long as it exists.
+pub struct EnvIter<'a, T: DspFormat> {
+    env: &'a mut Env<T>,
+    context: &'a T::Context,
+    gate: bool,
+    params: EnvParams<T>,
+}
+
+impl<'a, T: DspFormat> EnvIter<'a, T> {
+    /// The envelope being driven, to inspect its state
+    pub fn env(&self) -> &Env<T> {
+        self.env
+    }
+    /// Change the gate for subsequent samples
+    pub fn set_gate(&mut self, gate: bool) {
+        self.gate = gate;
+    }
+    /// Process samples until the envelope finishes its attack, returning the
+    /// number of samples processed (or `None` if that did not happen within
+    /// `limit` samples)
+    pub fn skip_until_peak(&mut self, limit: usize) -> Option<usize> {
+        self.skip_until(limit, |stage| stage != EnvStage::Attack)
+    }
+    /// Process samples until the envelope goes idle after a release,
+    /// returning the number of samples processed (or `None` if that did not
+    /// happen within `limit` samples).  This will not happen while the gate
+    /// is held.
+    pub fn skip_until_silent(&mut self, limit: usize) -> Option<usize> {
+        self.skip_until(limit, |stage| stage == EnvStage::Idle)
+    }
+    fn skip_until(&mut self, limit: usize, done: impl Fn(EnvStage) -> bool) -> Option<usize> {
+        for i in 1..=limit {
+            self.next();
+            if done(self.env.stage()) {
+                return Some(i);
+            }
+        }
+        None
+    }
+}
+
+impl<'a, T: DspFormat> Iterator for EnvIter<'a, T> {
+    type Item = T::Scalar;
+    fn next(&mut self) -> Option<T::Scalar> {
+        Some(self.env.next(self.context, self.gate, self.params.clone()))
+    }
 }
 
 impl<T: DspFormat> Device<T> for Env<T> {
@@ -102,36 +284,64 @@ impl<T: DspFormat> Device<T> for Env<T> {
     type Params = EnvParams<T>;
     type Output = T::Scalar;
     fn next(&mut self, context: &T::Context, gate: bool, params: EnvParams<T>) -> T::Scalar {
-        let setpoint_old = self.setpoint;
+        let mut setpoint_old = self.setpoint;
+        let sustain = params.sustain.scale(params.attack_peak);
+        let peak = match T::EnvSignal::from(params.attack_peak) {
+            peak if peak < T::SIGNAL_MAX => peak,
+            _ => T::SIGNAL_MAX,
+        };
         if !gate {
-            self.mode = EnvMode::Release;
+            self.mode = EnvStage::Release;
             self.setpoint = T::SIGNAL_MIN;
-        } else if self.mode == EnvMode::Release {
-            self.mode = EnvMode::Attack;
-            self.setpoint = T::SIGNAL_MAX;
-        } else if self.mode == EnvMode::Attack && self.signal > T::ATTACK_THRESHOLD {
-            self.mode = EnvMode::Decay;
+        } else if self.mode == EnvStage::Release {
+            self.mode = EnvStage::Attack;
+            if params.reset == ResetMode::Hard {
+                // Start from exactly the same (idle) state every time
+                self.signal = T::SIGNAL_MIN;
+                setpoint_old = T::SIGNAL_MIN;
+            }
+        } else if self.mode == EnvStage::Attack
+            && (self.signal > T::attack_threshold(peak) || T::env_settled(self.signal, peak))
+        {
+            // The decay starts from the peak, so it will pull the output
+            // down towards the sustain level from here
+            self.mode = EnvStage::Decay;
         }
+        // Only the Release, Attack, and Decay stages are used internally
         let rise = match self.mode {
-            EnvMode::Attack => params.attack,
-            EnvMode::Decay => {
+            EnvStage::Attack => {
+                // As with the sustain level below, track the peak in case it
+                // is being modulated
+                self.setpoint = peak;
+                params.attack
+            }
+            EnvStage::Decay | EnvStage::Sustain => {
                 // Need setpoint control here since the state transition will only
                 // fire once, and we might be modulated
-                self.setpoint = params.sustain.into();
+                self.setpoint = sustain.into();
                 params.decay
             }
-            EnvMode::Release => params.release,
+            EnvStage::Release | EnvStage::Idle => params.release,
         };
         self.signal = T::calc_env(context, self.setpoint, setpoint_old, self.signal, rise);
         self.signal.to_scalar()
     }
 }
 
+impl<T: DspFloat> detail::EnvParamOps for T {
+    const ADR_DEFAULT: T = T::POINT_ONE;
+}
+
 impl<T: DspFloat> detail::EnvOps for T {
     const SIGNAL_MIN: T = T::ZERO;
     const SIGNAL_MAX: T = T::ONE;
     const ATTACK_THRESHOLD: T = T::POINT_NINE_EIGHT;
-    const ADR_DEFAULT: T = T::POINT_ONE;
+    fn attack_threshold(peak: T) -> T {
+        peak * Self::ATTACK_THRESHOLD
+    }
+    fn env_settled(signal: T, target: T) -> bool {
+        (signal - target).abs() < T::ONE / T::from_u16(1024)
+    }
     fn calc_env(context: &Context<T>, setpoint: T, setpoint_old: T, last: T, rise_time: T) -> T {
         // This is equivalen to saying rise time = 4 time constants...
         let k = rise_time * (context.sample_rate / T::TWO) + T::ONE;
@@ -141,11 +351,25 @@ impl<T: DspFloat> detail::EnvOps for T {
     }
 }
 
+impl detail::EnvParamOps for i16 {
+    const ADR_DEFAULT: EnvParamFxP = EnvParamFxP::lit("0.1");
+}
+
+#[cfg(feature = "fixed")]
 impl detail::EnvOps for i16 {
     const ATTACK_THRESHOLD: EnvSignalFxP = EnvSignalFxP::lit("0.98");
     const SIGNAL_MAX: EnvSignalFxP = EnvSignalFxP::lit("0x0.FFFC");
     const SIGNAL_MIN: EnvSignalFxP = EnvSignalFxP::lit("0x0.0004");
-    const ADR_DEFAULT: EnvParamFxP = EnvParamFxP::lit("0.1");
+    fn attack_threshold(peak: EnvSignalFxP) -> EnvSignalFxP {
+        if peak < Self::SIGNAL_MAX {
+            peak.saturating_mul(Self::ATTACK_THRESHOLD)
+        } else {
+            Self::ATTACK_THRESHOLD
+        }
+    }
+    fn env_settled(signal: EnvSignalFxP, target: EnvSignalFxP) -> bool {
+        signal.abs_diff(target) < crate::fixedmath::U3F29::lit("0x0.004")
+    }
     fn calc_env(
         context: &ContextFxP,
         setpoint: EnvSignalFxP,
@@ -153,14 +377,16 @@ impl detail::EnvOps for i16 {
         last: EnvSignalFxP,
         rise_time: EnvParamFxP,
     ) -> EnvSignalFxP {
-        use crate::fixedmath::{one_over_one_plus, I2F14, U16F0};
+        use crate::fixedmath::{one_over_one_plus, scale_shr_round, U16F0};
         // This is equivalent to saying rise time = 4 time constants...
         let sr = U16F0::from_bits(context.sample_rate.value() >> 1);
         let k = rise_time.wide_mul(sr);
         let (gain, shift) = one_over_one_plus(k);
         // Need saturating here to avoid panic if A == 0 && S == 0:
-        let pro = I2F14::saturating_from_num(setpoint_old + setpoint - last.unwrapped_shl(1));
-        let delta = pro.wide_mul_unsigned(gain).unwrapped_shr(shift);
-        last + delta
+        let pro = (setpoint_old + setpoint).saturating_sub(last.unwrapped_shl(1));
+        // For long rise times, delta is only a few hundred LSBs, so keep full
+        // precision and round to nearest to avoid a systematic (truncation)
+        // bias that would noticeably slow down the envelope
+        last + scale_shr_round(pro, gain, shift)
     }
 }
@@ -2,19 +2,133 @@ use super::*;
 
 pub(crate) mod detail {
     use super::*;
-    pub trait FiltOps: DspFormatBase {
+    /// The range of a [Resonance].  This is separate from [FiltOps] so the
+    /// fixed point parameters can be used without the `fixed` feature.
+    pub trait ResonanceOps: DspFormatBase {
         const RES_MAX: Self::Scalar;
+    }
+    pub trait FiltOps: DspFormatContext + ResonanceOps {
         type FiltGain;
-        type FiltFeedback: Default + Clone + Send;
+        type FiltFeedback: Default + Clone + Send + DspSerde;
         fn prewarped_gain(context: &Self::Context, cutoff: Self::Note) -> Self::FiltGain;
-        fn calc_filt(
+        /// The filter coefficients, which depend only on the parameters (and
+        /// not the filter state), so may be shared between filters
+        type FiltCoeffs: Copy;
+        /// Calculate the filter coefficients for a cutoff and resonance.
+        /// Note that the resonance here is the damping, `1 - resonance`.
+        fn calc_coeffs(
             context: &Self::Context,
-            signal: Self::Sample,
             cutoff: Self::Note,
             resonance: Self::Scalar,
+        ) -> Self::FiltCoeffs;
+        /// The prewarped gain and the damping of a set of coefficients, for
+        /// calculating the frequency response (see [Filt::response])
+        fn coeffs_to_f32(coeffs: &Self::FiltCoeffs) -> (f32, f32);
+        /// Filter one sample using precalculated coefficients, setting
+        /// `state_reset` if the state had to be reset (see
+        /// [Filt::take_state_reset])
+        fn apply_filt(
+            coeffs: &Self::FiltCoeffs,
+            signal: Self::Sample,
             low_z: &mut Self::FiltFeedback,
             band_z: &mut Self::FiltFeedback,
+            state_reset: &mut bool,
         ) -> filt::FiltOutput<Self>;
+        /// State used to correct for DC drift (see [Filt::correct_dc_drift])
+        type FiltDcState: Default + Clone + Send + DspSerde;
+        /// Apply the current DC correction to `out` and accumulate the
+        /// residual error for the next correction.  Returns true
+        /// once [DC_CORRECT_PERIOD] samples have been accumulated.
+        fn track_dc(
+            state: &mut Self::FiltDcState,
+            signal: Self::Sample,
+            out: &mut filt::FiltOutput<Self>,
+        ) -> bool;
+        /// Update the DC correction from the accumulated samples
+        fn correct_dc(state: &mut Self::FiltDcState, cutoff: Self::Note, resonance: Self::Scalar);
+    }
+
+    /// The number of samples between DC drift corrections
+    #[cfg(feature = "fixed")]
+    pub const DC_CORRECT_PERIOD: u16 = 64;
+}
+
+/// A filter resonance, between 0 and [Resonance::MAX]
+///
+/// The filter is limited to a resonance of 15/16 = 0.9375 to avoid unbounded
+/// self-oscillation and mathematical issues as the resonance approaches 1.
+/// This may change in the future.  Constructing a [Resonance] enforces this
+/// limit, so the filter does not need to check it on every sample.
+#[derive(Clone, Copy, Default, PartialEq, PartialOrd)]
+pub struct Resonance<T: DspFormatBase>(T::Scalar);
+
+impl<T: DspFormatBase + detail::ResonanceOps> Resonance<T> {
+    /// The maximum resonance of the filter
+    pub const MAX: Self = Self(T::RES_MAX);
+    /// Create a new resonance, clamping `value` to between 0 and
+    /// [Resonance::MAX]
+    pub fn new(value: T::Scalar) -> Self {
+        if value > T::RES_MAX {
+            Self::MAX
+        } else if value < T::Scalar::zero() {
+            Self(T::Scalar::zero())
+        } else {
+            Self(value)
+        }
+    }
+    /// Create a new resonance without checking that `value` is in range.
+    ///
+    /// # Safety
+    ///
+    /// `value` must be between 0 and [Resonance::MAX].  The filter relies on
+    /// this to remain stable, and its output is unspecified otherwise.
+    pub const unsafe fn new_unchecked(value: T::Scalar) -> Self {
+        Self(value)
+    }
+    /// The value of this resonance
+    pub fn get(self) -> T::Scalar {
+        self.0
+    }
+}
+
+impl From<Resonance<i16>> for ScalarFxP {
+    fn from(value: Resonance<i16>) -> Self {
+        value.0
+    }
+}
+
+impl From<Resonance<f32>> for f32 {
+    fn from(value: Resonance<f32>) -> Self {
+        value.0
+    }
+}
+
+impl From<Resonance<f64>> for f64 {
+    fn from(value: Resonance<f64>) -> Self {
+        value.0
+    }
+}
+
+impl TryFrom<ScalarFxP> for Resonance<i16> {
+    type Error = &'static str;
+    fn try_from(value: ScalarFxP) -> Result<Self, Self::Error> {
+        if value > <i16 as detail::ResonanceOps>::RES_MAX {
+            Err("Resonance out of range")
+        } else {
+            Ok(Self(value))
+        }
+    }
+}
+
+impl From<f32> for Resonance<i16> {
+    fn from(value: f32) -> Self {
+        Self::new(ScalarFxP::saturating_from_num(value))
+    }
+}
+
+impl<T: DspFloat> From<f32> for Resonance<T> {
+    fn from(value: f32) -> Self {
+        Self::new(num_traits::cast(value).unwrap_or(T::ZERO))
     }
 }
 
@@ -23,24 +137,48 @@ pub(crate) mod detail {
 pub struct FiltParams<T: DspFormatBase> {
     /// Cutoff frequency, as a MIDI note number
     pub cutoff: T::Note,
-    /// Resonance, as a value between 0 and 1
-    ///
-    /// This will cut off at 15/16 = 0.9375 to avoid unbounded self-oscillation
-    /// and mathematical issues as the resonance approaches 1.  This may change
-    /// in the future.
-    pub resonance: T::Scalar,
+    /// Resonance, as a value between 0 and [Resonance::MAX]
+    pub resonance: Resonance<T>,
 }
 
 impl<T: DspFloat> From<&FiltParams<i16>> for FiltParams<T> {
     fn from(value: &FiltParams<i16>) -> Self {
         FiltParams::<T> {
             cutoff: value.cutoff.to_num(),
-            resonance: value.resonance.to_num(),
+            // The floating point limit is (very slightly) above the fixed
+            // point one, so this stays in range
+            resonance: Resonance(value.resonance.0.to_num()),
         }
     }
 }
 
+/// Convert a filter Q factor into a resonance parameter (see
+/// [FiltParams::resonance])
+///
+/// The state-variable filter is damped by `1 - resonance`, so
+/// `Q = 1 / (2 * (1 - resonance))`.  A Q of 0.5 (critically damped) is a
+/// resonance of zero, and the resonance approaches 1 as Q approaches
+/// infinity (self-oscillation).  Values of Q below 0.5 are clamped to a
+/// resonance of zero.  Note that the filter itself limits the resonance to
+/// 0.9375, which is a maximum Q of 8.
+pub fn q_to_resonance<Smp: crate::Float>(q: Smp) -> Smp {
+    (Smp::ONE - Smp::ONE / (Smp::TWO * q)).max(Smp::ZERO).min(Smp::ONE)
+}
+
+/// Convert a resonance parameter into the filter's Q factor.  This is the
+/// inverse of [q_to_resonance].
+pub fn resonance_to_q<Smp: crate::Float>(resonance: Smp) -> Smp {
+    Smp::ONE / (Smp::TWO * (Smp::ONE - resonance))
+}
+
+/// Convert a filter Q factor into a fixed point resonance parameter (see
+/// [q_to_resonance])
+pub fn q_to_resonance_fxp(q: f32) -> ScalarFxP {
+    ScalarFxP::saturating_from_num(q_to_resonance(q))
+}
+
 /// Output of a [Filt]
+#[must_use]
 #[derive(Clone, Default)]
 pub struct FiltOutput<T: DspFormatBase> {
     /// The low-pass signal
@@ -51,6 +189,73 @@ pub struct FiltOutput<T: DspFormatBase> {
     pub high: T::Sample,
 }
 
+/// The frequency response of a [Filt] at a single frequency, calculated from
+/// its coefficients (see [Filt::response]).
+///
+/// All three outputs share a denominator, so the response of any mix of the
+/// outputs may be found from this (see [FiltResponse::mix_db]).
+#[derive(Clone, Copy, Default)]
+pub struct FiltResponse {
+    // The numerators of the low and high pass outputs (which are real) and
+    // of the band pass output (which is imaginary)
+    low: f32,
+    band: f32,
+    high: f32,
+    // The real and imaginary parts of the denominator
+    denom: (f32, f32),
+}
+
+impl FiltResponse {
+    /// The response of a filter with the given prewarped gain and damping at
+    /// `ratio` times the sample rate.
+    ///
+    /// The analog prototype (with a cutoff of 1) is `1 / (s^2 + 2Rs + 1)`, and
+    /// the bilinear transform maps the frequency `f` to
+    /// `s = j * tan(pi * f / sr) / gain`.  Multiplying through by
+    /// `(gain * cos(pi * f / sr))^2` keeps everything finite up to Nyquist.
+    fn new(gain: f32, damping: f32, ratio: f32) -> Self {
+        use crate::Float;
+        let half_omega = core::f32::consts::PI * ratio.clamp(0f32, 0.5f32);
+        let (sin, cos) = (half_omega.fsin(), half_omega.fcos());
+        let gain_cos = gain * cos;
+        Self {
+            low: gain_cos * gain_cos,
+            band: gain_cos * sin,
+            high: -sin * sin,
+            denom: (
+                gain_cos * gain_cos - sin * sin,
+                2f32 * damping * gain_cos * sin,
+            ),
+        }
+    }
+    /// The gain, in dB, of the given mix of the low, band, and high pass
+    /// outputs (as in [crate::devices::ModFiltParams]).  This is limited to
+    /// -120dB.
+    pub fn mix_db(&self, low: f32, band: f32, high: f32) -> f32 {
+        use crate::Float;
+        let real = low * self.low + high * self.high;
+        let imag = band * self.band;
+        let (denom_real, denom_imag) = self.denom;
+        let power =
+            (real * real + imag * imag) / (denom_real * denom_real + denom_imag * denom_imag);
+        // A NaN power (from a zero denominator) is also raised to the floor
+        const FLOOR_POWER: f32 = 1e-12f32;
+        (10f32 / core::f32::consts::LOG2_10) * power.max(FLOOR_POWER).flog2()
+    }
+    /// The gain of the low pass output, in dB
+    pub fn low_db(&self) -> f32 {
+        self.mix_db(1f32, 0f32, 0f32)
+    }
+    /// The gain of the band pass output, in dB
+    pub fn band_db(&self) -> f32 {
+        self.mix_db(0f32, 1f32, 0f32)
+    }
+    /// The gain of the high pass output, in dB
+    pub fn high_db(&self) -> f32 {
+        self.mix_db(0f32, 0f32, 1f32)
+    }
+}
+
 /// A State-Variable Filter implementation
 ///
 /// This emulates a state-variable filter with low, band, and high-pass outputs.
@@ -60,10 +265,31 @@ pub struct FiltOutput<T: DspFormatBase> {
 /// This implements [Device] with an Input type of Sample and a Parameter type
 /// of [FiltParams], and outputs a [FiltOutput], which consists of Samples for
 /// the low, band, and high pass signals.
+///
+/// Fixed-point filters accumulate rounding errors in their state, which
+/// appear as a small DC offset in the low pass output.  If DC correction is
+/// enabled (see [Filt::set_dc_correct]), every 64 samples the mean of the low
+/// pass output is compared to its ideal steady state (the mean input) and the
+/// deviation is slowly integrated into a correction that is subtracted from
+/// subsequent outputs.  Floating-point filters ignore this setting.
+///
+/// Floating-point filters instead reset their state if a NaN or infinite
+/// input ever makes it non-finite, so the filter recovers on the next sample
+/// (see [Filt::take_state_reset]).  This requires the
+/// `state_guard` feature, which is enabled by default.
 #[derive(Default, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct Filt<T: DspFormat> {
     low_z: T::FiltFeedback,
     band_z: T::FiltFeedback,
+    dc_correct: bool,
+    dc: T::FiltDcState,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    state_reset: bool,
 }
 
 impl<T: DspFormat> Filt<T> {
@@ -71,6 +297,83 @@ impl<T: DspFormat> Filt<T> {
     pub fn new() -> Self {
         Default::default()
     }
+    /// Enable or disable DC drift correction
+    pub fn set_dc_correct(&mut self, dc_correct: bool) {
+        self.dc_correct = dc_correct;
+    }
+    /// Is DC drift correction enabled?
+    pub fn dc_correct(&self) -> bool {
+        self.dc_correct
+    }
+    /// Returns true if this (floating point) filter has reset its state
+    /// because it became NaN or infinite since the last call.  This is
+    /// intended for logging, and is always false for fixed point filters.
+    pub fn take_state_reset(&mut self) -> bool {
+        core::mem::take(&mut self.state_reset)
+    }
+    /// Update the DC drift correction from the samples processed since the
+    /// last correction, given the current filter parameters.
+    ///
+    /// This is called automatically every 64 samples when DC correction is
+    /// enabled, so it should not normally need to be called directly.
+    pub fn correct_dc_drift(&mut self, cutoff: T::Note, resonance: T::Scalar) {
+        T::correct_dc(&mut self.dc, cutoff, resonance)
+    }
+    /// Calculate the filter coefficients for a set of parameters
+    pub(crate) fn coeffs(context: &T::Context, params: &FiltParams<T>) -> T::FiltCoeffs {
+        let resonance = T::Scalar::one() - params.resonance.get();
+        T::calc_coeffs(context, params.cutoff, resonance)
+    }
+    /// The frequency response of a filter with the given parameters at
+    /// `freq` Hz.  This is calculated from the same coefficients used to
+    /// process samples (not by processing a signal), so it is cheap enough to
+    /// draw a transfer curve in a user interface.
+    ///
+    /// This ignores the fixed point filter's soft clipping and DC correction,
+    /// so it is only exact for small signals.
+    pub fn response(context: &T::Context, params: &FiltParams<T>, freq: f32) -> FiltResponse {
+        use crate::context::GenericContext;
+        let (gain, damping) = T::coeffs_to_f32(&Self::coeffs(context, params));
+        FiltResponse::new(gain, damping, freq / context.sample_rate() as f32)
+    }
+    /// Filter one sample using coefficients from [Filt::coeffs].  This allows
+    /// several filters with the same parameters to share the (relatively
+    /// expensive) coefficient calculation.
+    pub(crate) fn next_with_coeffs(
+        &mut self,
+        coeffs: &T::FiltCoeffs,
+        signal: T::Sample,
+        params: &FiltParams<T>,
+    ) -> FiltOutput<T> {
+        let mut out = T::apply_filt(
+            coeffs,
+            signal,
+            &mut self.low_z,
+            &mut self.band_z,
+            &mut self.state_reset,
+        );
+        if self.dc_correct && T::track_dc(&mut self.dc, signal, &mut out) {
+            self.correct_dc_drift(params.cutoff, params.resonance.get());
+        }
+        out
+    }
+    /// Filter four samples with the same parameters, which is identical to
+    /// calling [Device::next] four times.
+    ///
+    /// The coefficients are only calculated once, and since the filter state
+    /// is only two one-sample delays, the remaining work for each sample is
+    /// short enough to be unrolled.  On in-order CPUs this allows the
+    /// coefficient calculation to be overlapped with the filter updates.
+    #[cfg(feature = "pipeline4")]
+    pub fn process_pipelined_4(
+        &mut self,
+        context: &T::Context,
+        signal: [T::Sample; 4],
+        params: FiltParams<T>,
+    ) -> [FiltOutput<T>; 4] {
+        let coeffs = Self::coeffs(context, &params);
+        signal.map(|smp| self.next_with_coeffs(&coeffs, smp, &params))
+    }
 }
 
 impl<T: DspFormat> Device<T> for Filt<T> {
@@ -83,42 +386,42 @@ impl<T: DspFormat> Device<T> for Filt<T> {
         signal: T::Sample,
         params: FiltParams<T>,
     ) -> FiltOutput<T> {
-        let resonance = T::Scalar::one()
-            - if params.resonance < T::RES_MAX {
-                params.resonance
-            } else {
-                T::RES_MAX
-            };
-        T::calc_filt(
-            context,
-            signal,
-            params.cutoff,
-            resonance,
-            &mut self.low_z,
-            &mut self.band_z,
-        )
+        let coeffs = Self::coeffs(context, &params);
+        self.next_with_coeffs(&coeffs, signal, &params)
     }
 }
 
-impl<T: DspFloat> detail::FiltOps for T {
+impl<T: DspFloat> detail::ResonanceOps for T {
     const RES_MAX: T = T::RES_MAX;
+}
+
+impl<T: DspFloat> detail::FiltOps for T {
     type FiltGain = T;
     type FiltFeedback = T;
     fn prewarped_gain(context: &Context<Self>, cutoff: T) -> T {
         let f_c = cutoff.midi_to_freq();
         T::ftan(T::PI * f_c / context.sample_rate)
     }
-    fn calc_filt(
-        context: &Self::Context,
+    /// The gain, `2 * res + gain`, and the denominator of the high pass
+    type FiltCoeffs = (T, T, T);
+    fn calc_coeffs(context: &Self::Context, cutoff: Self::Note, res: Self::Scalar) -> (T, T, T) {
+        let gain = Self::prewarped_gain(context, cutoff);
+        let denom = gain * gain + Self::TWO * res * gain + Self::ONE;
+        (gain, Self::TWO * res + gain, denom)
+    }
+    fn coeffs_to_f32(coeffs: &(T, T, T)) -> (f32, f32) {
+        let (gain, gain_plus_2r, _) = *coeffs;
+        (gain.as_f32(), ((gain_plus_2r - gain) / Self::TWO).as_f32())
+    }
+    fn apply_filt(
+        coeffs: &(T, T, T),
         signal: Self::Sample,
-        cutoff: Self::Note,
-        res: Self::Scalar,
         low_z: &mut Self::FiltFeedback,
         band_z: &mut Self::FiltFeedback,
+        state_reset: &mut bool,
     ) -> filt::FiltOutput<T> {
-        let gain = Self::prewarped_gain(context, cutoff);
-        let denom = gain * gain + Self::TWO * res * gain + Self::ONE;
-        let high = (signal - (Self::TWO * res + gain) * (*band_z) - (*low_z)) / denom;
+        let (gain, gain_plus_2r, denom) = *coeffs;
+        let high = (signal - gain_plus_2r * (*band_z) - (*low_z)) / denom;
 
         let band_gain = gain * high;
         let band = band_gain + *band_z;
@@ -128,12 +431,45 @@ impl<T: DspFloat> detail::FiltOps for T {
         let low = low_gain + *low_z;
         *low_z = low + low_gain;
 
+        *state_reset |= super::guard::guard_state(signal.is_finite(), [low_z, band_z]);
         FiltOutput { low, band, high }
     }
+    // Floating point filters don't suffer from appreciable drift
+    type FiltDcState = ();
+    fn track_dc(_: &mut (), _: T, _: &mut FiltOutput<T>) -> bool {
+        false
+    }
+    fn correct_dc(_: &mut (), _: T, _: T) {}
 }
 
-impl detail::FiltOps for i16 {
+/// Coefficients of the fixed point filter
+#[cfg(feature = "fixed")]
+#[derive(Clone, Copy)]
+pub struct FiltCoeffsFxP {
+    gain: crate::fixedmath::U1F15,
+    gain_plus_2r: crate::fixedmath::U3F13,
+    denom_inv: crate::fixedmath::U1F15,
+    shift: u32,
+}
+
+/// State for DC drift correction of the fixed point filter
+#[cfg(feature = "fixed")]
+#[derive(Default, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FiltDcStateFxP {
+    count: u16,
+    /// Sum of (corrected low pass output - input) over the current window
+    error_acc: crate::fixedmath::I16F16,
+    /// The current correction to the low pass output
+    offset: crate::fixedmath::I16F16,
+}
+
+impl detail::ResonanceOps for i16 {
     const RES_MAX: ScalarFxP = ScalarFxP::lit("0x0.F000");
+}
+
+#[cfg(feature = "fixed")]
+impl detail::FiltOps for i16 {
     type FiltGain = crate::fixedmath::U1F15;
     type FiltFeedback = crate::fixedmath::I12F20;
     fn prewarped_gain(context: &ContextFxP, cutoff: NoteFxP) -> Self::FiltGain {
@@ -144,15 +480,9 @@ impl detail::FiltOps for i16 {
         );
         tan_fixed(omega_d)
     }
-    fn calc_filt(
-        context: &Self::Context,
-        signal: Self::Sample,
-        cutoff: Self::Note,
-        res: Self::Scalar,
-        low_z: &mut Self::FiltFeedback,
-        band_z: &mut Self::FiltFeedback,
-    ) -> filt::FiltOutput<i16> {
-        use crate::fixedmath::{one_over_one_plus, I5F27, I7F25, U3F13, U3F29};
+    type FiltCoeffs = FiltCoeffsFxP;
+    fn calc_coeffs(context: &Self::Context, cutoff: NoteFxP, res: ScalarFxP) -> FiltCoeffsFxP {
+        use crate::fixedmath::{one_over_one_plus, U3F13, U3F29};
 
         let gain = Self::prewarped_gain(context, cutoff);
         let gain2 = U3F29::from_num(gain.wide_mul(gain));
@@ -163,15 +493,51 @@ impl detail::FiltOps for i16 {
         let (denom_inv, shift) = one_over_one_plus(k);
 
         let gain_plus_2r = U3F29::from_num(res).unwrapped_shl(1) + U3F29::from_num(gain);
+        FiltCoeffsFxP {
+            gain,
+            gain_plus_2r: U3F13::from_num(gain_plus_2r),
+            denom_inv,
+            shift,
+        }
+    }
+    fn coeffs_to_f32(coeffs: &FiltCoeffsFxP) -> (f32, f32) {
+        let gain = coeffs.gain.to_num::<f32>();
+        (gain, (coeffs.gain_plus_2r.to_num::<f32>() - gain) / 2f32)
+    }
+    fn apply_filt(
+        coeffs: &FiltCoeffsFxP,
+        signal: Self::Sample,
+        low_z: &mut Self::FiltFeedback,
+        band_z: &mut Self::FiltFeedback,
+        _: &mut bool,
+    ) -> filt::FiltOutput<i16> {
+        use crate::fixedmath::{SampleClip, I5F27, I7F25, U3F13};
+
+        let FiltCoeffsFxP {
+            gain,
+            gain_plus_2r,
+            denom_inv,
+            shift,
+        } = *coeffs;
         let band_high_feedback: I7F25 =
-            U3F13::from_num(gain_plus_2r).wide_mul_signed(SampleFxP::saturating_from_num(*band_z));
+            gain_plus_2r.wide_mul_signed(SampleFxP::saturating_from_num(*band_z));
         let high_num = SampleFxP::saturating_from_num(
             Self::FiltFeedback::from_num(signal)
                 - Self::FiltFeedback::from_num(band_high_feedback)
                 - *low_z,
         );
         let high_unshifted: I5F27 = high_num.wide_mul_unsigned(denom_inv);
-        let high = SampleFxP::saturating_from_num(high_unshifted.unwrapped_shr(shift));
+        // Soft clip large high pass outputs instead of saturating them.  This
+        // is done at 1/8 scale so the knee (at 6.0) is near the limit of a
+        // SampleFxP, and anything below the knee keeps its full precision.
+        const HIGH_CLIP_KNEE: ScalarFxP = ScalarFxP::lit("0.75");
+        let high = high_unshifted.unwrapped_shr(shift);
+        let high_scaled = SampleFxP::from_num(high.unwrapped_shr(3));
+        let high = if high_scaled.unsigned_abs() < U3F13::from_num(HIGH_CLIP_KNEE) {
+            SampleFxP::from_num(high)
+        } else {
+            high_scaled.soft_clip(HIGH_CLIP_KNEE).saturating_mul_int(8)
+        };
 
         let band_gain = Self::FiltFeedback::from_num(gain.wide_mul_signed(high));
         let band = band_gain + *band_z;
@@ -185,4 +551,32 @@ impl detail::FiltOps for i16 {
 
         FiltOutput { low, band, high }
     }
+    type FiltDcState = FiltDcStateFxP;
+    fn track_dc(state: &mut FiltDcStateFxP, signal: SampleFxP, out: &mut FiltOutput<i16>) -> bool {
+        use crate::fixedmath::I16F16;
+        // The band and high pass outputs have no DC gain, and their errors
+        // are well below 1 LSB, so only the low pass output is corrected.
+        let corrected = I16F16::from_num(out.low) - state.offset;
+        state.error_acc += corrected - I16F16::from_num(signal);
+        out.low = SampleFxP::saturating_from_num(corrected);
+        state.count += 1;
+        state.count >= detail::DC_CORRECT_PERIOD
+    }
+    fn correct_dc(state: &mut FiltDcStateFxP, cutoff: NoteFxP, resonance: ScalarFxP) {
+        // The mean of the low pass output over a single window only matches
+        // the mean input if there is little content below the window rate,
+        // so integrate the error slowly to average this out over many
+        // windows.  Adapt more slowly still at low cutoff (longer impulse
+        // response) and high resonance (more ringing).
+        let mut shift = 10;
+        if cutoff < NoteFxP::lit("60") {
+            shift += 2;
+        }
+        if resonance > ScalarFxP::lit("0.5") {
+            shift += 1;
+        }
+        state.offset = state.offset.saturating_add(state.error_acc.unwrapped_shr(shift));
+        state.error_acc = crate::fixedmath::I16F16::ZERO;
+        state.count = 0;
+    }
 }
@@ -0,0 +1,157 @@
+use super::*;
+use crate::context::GenericContext;
+#[cfg(feature = "fixed")]
+use fixed::types::U7F25;
+
+pub(crate) mod detail {
+    use super::*;
+    pub trait GlideOps: DspFormatContext {
+        /// The type of the gliding note, which may have more precision than
+        /// a Note to allow for slow glides
+        type GlideAcc: Copy + Default + Send + DspSerde;
+        fn glide_start(note: Self::Note) -> Self::GlideAcc;
+        /// The change in the gliding note per sample for a (nonzero) glide
+        /// time
+        fn glide_rate(context: &Self::Context, time: Self::EnvParam) -> Self::GlideAcc;
+        fn glide_step(
+            acc: Self::GlideAcc,
+            target: Self::Note,
+            rate: Self::GlideAcc,
+        ) -> Self::GlideAcc;
+        fn glide_note(acc: Self::GlideAcc, target: Self::Note, quantize: bool) -> Self::Note;
+    }
+}
+
+/// Parameters for a [Glide]
+#[derive(Clone, Default)]
+pub struct GlideParams<T: DspFormatBase> {
+    /// The time to glide one octave, in seconds.  The glide moves at a
+    /// constant rate, so a glide across two octaves takes twice as long.
+    /// A time of zero disables the glide.
+    pub time: T::EnvParam,
+    /// Quantize the glide to semitone steps relative to the target note
+    /// (i.e. a glissando rather than a portamento)
+    pub quantize: bool,
+}
+
+impl<T: DspFloat> From<&GlideParams<i16>> for GlideParams<T> {
+    fn from(value: &GlideParams<i16>) -> Self {
+        Self {
+            time: value.time.to_num(),
+            quantize: value.quantize,
+        }
+    }
+}
+
+/// A portamento/glide
+///
+/// This moves a note towards its target at a constant rate (in semitones per
+/// second).  When quantized, the output steps through each semitone between
+/// the starting note and the target at the same rate.
+///
+/// This implements [Device], taking the target Note as input and
+/// [GlideParams] as parameters, and outputting the gliding Note.
+#[derive(Clone, Default)]
+pub struct Glide<T: DspFormat> {
+    acc: T::GlideAcc,
+    started: bool,
+    // The change in the note per sample, and the time and sample rate it was
+    // calculated for, so it is only recalculated when they change
+    rate: T::GlideAcc,
+    rate_time: T::EnvParam,
+    rate_sample_rate: u32,
+}
+
+impl<T: DspFormat> Glide<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Jump straight to the next target note instead of gliding to it (e.g.
+    /// when a new note is played without legato)
+    pub fn reset(&mut self) {
+        self.started = false;
+    }
+}
+
+impl<T: DspFormat> Device<T> for Glide<T> {
+    type Input = T::Note;
+    type Params = GlideParams<T>;
+    type Output = T::Note;
+    fn next(&mut self, context: &T::Context, target: T::Note, params: GlideParams<T>) -> T::Note {
+        if !self.started || params.time <= T::EnvParam::zero() {
+            self.acc = T::glide_start(target);
+            self.started = true;
+        } else {
+            let sample_rate = context.sample_rate();
+            if params.time != self.rate_time || sample_rate != self.rate_sample_rate {
+                self.rate = T::glide_rate(context, params.time);
+                self.rate_time = params.time;
+                self.rate_sample_rate = sample_rate;
+            }
+            self.acc = T::glide_step(self.acc, target, self.rate);
+        }
+        T::glide_note(self.acc, target, params.quantize)
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl detail::GlideOps for i16 {
+    type GlideAcc = U7F25;
+    fn glide_start(note: NoteFxP) -> U7F25 {
+        U7F25::from_num(note)
+    }
+    fn glide_rate(context: &ContextFxP, time: EnvParamFxP) -> U7F25 {
+        // 12 semitones / (time * sample_rate), as the bits of a U7F25
+        let num = 12u64 << (U7F25::FRAC_NBITS + EnvParamFxP::FRAC_NBITS);
+        let den = time.to_bits() as u64 * context.sample_rate.value() as u64;
+        U7F25::from_bits((num / den).min(u32::MAX as u64) as u32)
+    }
+    fn glide_step(acc: U7F25, target: NoteFxP, rate: U7F25) -> U7F25 {
+        let target = U7F25::from_num(target);
+        if acc < target {
+            acc.saturating_add(rate).min(target)
+        } else {
+            acc.saturating_sub(rate).max(target)
+        }
+    }
+    fn glide_note(acc: U7F25, target: NoteFxP, quantize: bool) -> NoteFxP {
+        if !quantize {
+            return NoteFxP::from_num(acc);
+        }
+        // Round the distance from the target to the nearest whole semitone
+        let target_acc = U7F25::from_num(target);
+        let diff = acc.dist(target_acc).to_bits();
+        let steps = (((diff >> (U7F25::FRAC_NBITS - 1)) + 1) >> 1) as u16;
+        let offset = NoteFxP::from_bits(steps << NoteFxP::FRAC_NBITS);
+        if acc < target_acc {
+            target.saturating_sub(offset)
+        } else {
+            target.saturating_add(offset)
+        }
+    }
+}
+
+impl<T: DspFloat> detail::GlideOps for T {
+    type GlideAcc = T;
+    fn glide_start(note: T) -> T {
+        note
+    }
+    fn glide_rate(context: &Context<T>, time: T) -> T {
+        T::from_u16(12) / (time * context.sample_rate)
+    }
+    fn glide_step(acc: T, target: T, rate: T) -> T {
+        if acc < target {
+            (acc + rate).min(target)
+        } else {
+            (acc - rate).max(target)
+        }
+    }
+    fn glide_note(acc: T, target: T, quantize: bool) -> T {
+        if quantize {
+            target + (acc - target).round()
+        } else {
+            acc
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! This module contains guards for the recursive state of floating point
+//! devices (see [guard_state]).
+//!
+//! A NaN or infinity that reaches the feedback state of a recursive device,
+//! like the state variable filter, would otherwise stay there forever and
+//! silence the device.  This can come from a non-finite input, or from a
+//! finite input or parameter extreme enough to overflow the state.  Fixed
+//! point devices saturate instead, so they don't need these guards.
+//!
+//! A non-finite input is a bug upstream of the device, so debug builds
+//! assert that the input is finite.  The state is still recovered in release
+//! builds, or if a finite input overflows it.
+
+use crate::Float;
+
+/// Check the recursive `state` of a device after an update from an input,
+/// which debug builds assert is finite (`input_finite`).
+///
+/// With the `state_guard` feature (the default), subnormal state is flushed
+/// to zero, and if any of the state is non-finite it is all reset to zero.
+/// Returns true if the state was reset, so the device can report it (e.g.
+/// [crate::devices::Filt::take_state_reset]).
+#[inline]
+pub(crate) fn guard_state<T: Float, const N: usize>(
+    input_finite: bool,
+    state: [&mut T; N],
+) -> bool {
+    debug_assert!(input_finite, "non-finite input to a floating point device");
+    if !cfg!(feature = "state_guard") {
+        return false;
+    }
+    let finite = state.iter().all(|x| x.is_finite());
+    for x in state {
+        if !finite || !x.is_normal() {
+            *x = T::ZERO;
+        }
+    }
+    !finite
+}
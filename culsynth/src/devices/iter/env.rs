@@ -92,9 +92,11 @@ where
     fn next(&mut self) -> Option<EnvParams<T>> {
         Some(EnvParams {
             attack: self.a.next()?,
+            attack_peak: T::Scalar::one(),
             decay: self.d.next()?,
             sustain: self.s.next()?,
             release: self.r.next()?,
+            reset: ResetMode::default(),
         })
     }
 }
@@ -50,7 +50,7 @@ impl<T: DspFormat, Cutoff: Iterator<Item = T::Note>, Resonance: Iterator<Item =
     fn next(&mut self) -> Option<FiltParams<T>> {
         Some(FiltParams {
             cutoff: self.cutoff.next()?,
-            resonance: self.resonance.next()?,
+            resonance: filt::Resonance::new(self.resonance.next()?),
         })
     }
 }
@@ -4,33 +4,37 @@ use super::*;
 ///
 /// Use this to easily build iterators to [LfoParams] out of iterators to
 /// its constituent parts.
-pub struct LfoParamIter<T: DspFormatBase, F, D, O>
+pub struct LfoParamIter<T: DspFormatBase, F, D, O, S>
 where
     F: Iterator<Item = T::LfoFreq>,
     D: Iterator<Item = T::Scalar>,
     O: Iterator<Item = LfoOptions>,
+    S: Iterator<Item = T::EnvParam>,
 {
     f: F,
     d: D,
     o: O,
+    s: S,
     phantom: core::marker::PhantomData<T>,
 }
 
-impl<T: DspFormatBase, F, D, O> LfoParamIter<T, F, D, O>
+impl<T: DspFormatBase, F, D, O, S> LfoParamIter<T, F, D, O, S>
 where
     F: Iterator<Item = T::LfoFreq>,
     D: Iterator<Item = T::Scalar>,
     O: Iterator<Item = LfoOptions>,
+    S: Iterator<Item = T::EnvParam>,
 {
     /// Replace the current frequenchy source with the one provided
     pub fn with_freq<New: Iterator<Item = T::LfoFreq>>(
         self,
         new: New,
-    ) -> LfoParamIter<T, New, D, O> {
+    ) -> LfoParamIter<T, New, D, O, S> {
         LfoParamIter {
             f: new,
             d: self.d,
             o: self.o,
+            s: self.s,
             phantom: self.phantom,
         }
     }
@@ -38,11 +42,12 @@ where
     pub fn with_depth<New: Iterator<Item = T::Scalar>>(
         self,
         new: New,
-    ) -> LfoParamIter<T, F, New, O> {
+    ) -> LfoParamIter<T, F, New, O, S> {
         LfoParamIter {
             f: self.f,
             d: new,
             o: self.o,
+            s: self.s,
             phantom: self.phantom,
         }
     }
@@ -50,21 +55,36 @@ where
     pub fn with_options<New: Iterator<Item = LfoOptions>>(
         self,
         new: New,
-    ) -> LfoParamIter<T, F, D, New> {
+    ) -> LfoParamIter<T, F, D, New, S> {
         LfoParamIter {
             f: self.f,
             d: self.d,
             o: new,
+            s: self.s,
+            phantom: self.phantom,
+        }
+    }
+    /// Replace the current slew source with the one provided
+    pub fn with_slew<New: Iterator<Item = T::EnvParam>>(
+        self,
+        new: New,
+    ) -> LfoParamIter<T, F, D, O, New> {
+        LfoParamIter {
+            f: self.f,
+            d: self.d,
+            o: self.o,
+            s: new,
             phantom: self.phantom,
         }
     }
 }
 
-impl<T: DspFormatBase, F, D, O> Iterator for LfoParamIter<T, F, D, O>
+impl<T: DspFormatBase, F, D, O, S> Iterator for LfoParamIter<T, F, D, O, S>
 where
     F: Iterator<Item = T::LfoFreq>,
     D: Iterator<Item = T::Scalar>,
     O: Iterator<Item = LfoOptions>,
+    S: Iterator<Item = T::EnvParam>,
 {
     type Item = LfoParams<T>;
     fn next(&mut self) -> Option<LfoParams<T>> {
@@ -72,20 +92,23 @@ where
             freq: self.f.next()?,
             depth: self.d.next()?,
             opts: self.o.next()?,
+            slew: self.s.next()?,
         })
     }
 }
 
 /// Create a new [LfoParamIter], which initially creates instances of
-/// [LfoParams] with frequency 1Hz, depth 1, and default [LfoOptions] until
-/// calling the `with_*()` methods.
+/// [LfoParams] with frequency 1Hz, depth 1, default [LfoOptions], and no slew
+/// until calling the `with_*()` methods.
 #[allow(clippy::type_complexity)]
 pub fn new_lfo_param_iter<T: DspFormatBase>(
-) -> LfoParamIter<T, Repeat<T::LfoFreq>, Repeat<T::Scalar>, Repeat<LfoOptions>> {
+) -> LfoParamIter<T, Repeat<T::LfoFreq>, Repeat<T::Scalar>, Repeat<LfoOptions>, Repeat<T::EnvParam>>
+{
     LfoParamIter {
         f: repeat(T::LfoFreq::one()),
         d: repeat(T::Scalar::one()),
         o: repeat(LfoOptions::default()),
+        s: repeat(T::EnvParam::zero()),
         phantom: Default::default(),
     }
 }
@@ -4,7 +4,7 @@ use super::*;
 ///
 /// Use this to easily build iterators to [MixOscParams] out of iterators to
 /// its constituent parts.
-pub struct MixOscParamIter<T, A, B, C, D, E, F>
+pub struct MixOscParamIter<T, A, B, C, D, E, F, G>
 where
     T: DspFormatBase,
     A: Iterator<Item = T::NoteOffset>,
@@ -13,6 +13,7 @@ where
     D: Iterator<Item = T::Scalar>,
     E: Iterator<Item = T::Scalar>,
     F: Iterator<Item = T::Scalar>,
+    G: Iterator<Item = T::Scalar>,
 {
     tune: A,
     shape: B,
@@ -20,10 +21,11 @@ where
     sq: D,
     tri: E,
     saw: F,
+    morph: G,
     phantom: core::marker::PhantomData<T>,
 }
 
-impl<T, A, B, C, D, E, F> MixOscParamIter<T, A, B, C, D, E, F>
+impl<T, A, B, C, D, E, F, G> MixOscParamIter<T, A, B, C, D, E, F, G>
 where
     T: DspFormatBase,
     A: Iterator<Item = T::NoteOffset>,
@@ -32,12 +34,13 @@ where
     D: Iterator<Item = T::Scalar>,
     E: Iterator<Item = T::Scalar>,
     F: Iterator<Item = T::Scalar>,
+    G: Iterator<Item = T::Scalar>,
 {
     /// Replace the current tuning source with the one provided
     pub fn with_tune<New: Iterator<Item = T::NoteOffset>>(
         self,
         new: New,
-    ) -> MixOscParamIter<T, New, B, C, D, E, F> {
+    ) -> MixOscParamIter<T, New, B, C, D, E, F, G> {
         MixOscParamIter {
             tune: new,
             shape: self.shape,
@@ -45,6 +48,7 @@ where
             sq: self.sq,
             tri: self.tri,
             saw: self.saw,
+            morph: self.morph,
             phantom: self.phantom,
         }
     }
@@ -53,7 +57,7 @@ where
     pub fn with_shape<New: Iterator<Item = T::Scalar>>(
         self,
         new: New,
-    ) -> MixOscParamIter<T, A, New, C, D, E, F> {
+    ) -> MixOscParamIter<T, A, New, C, D, E, F, G> {
         MixOscParamIter {
             tune: self.tune,
             shape: new,
@@ -61,6 +65,7 @@ where
             sq: self.sq,
             tri: self.tri,
             saw: self.saw,
+            morph: self.morph,
             phantom: self.phantom,
         }
     }
@@ -68,7 +73,7 @@ where
     pub fn with_sin<New: Iterator<Item = T::Scalar>>(
         self,
         new: New,
-    ) -> MixOscParamIter<T, A, B, New, D, E, F> {
+    ) -> MixOscParamIter<T, A, B, New, D, E, F, G> {
         MixOscParamIter {
             tune: self.tune,
             shape: self.shape,
@@ -76,6 +81,7 @@ where
             sq: self.sq,
             tri: self.tri,
             saw: self.saw,
+            morph: self.morph,
             phantom: self.phantom,
         }
     }
@@ -83,7 +89,7 @@ where
     pub fn with_sq<New: Iterator<Item = T::Scalar>>(
         self,
         new: New,
-    ) -> MixOscParamIter<T, A, B, C, New, E, F> {
+    ) -> MixOscParamIter<T, A, B, C, New, E, F, G> {
         MixOscParamIter {
             tune: self.tune,
             shape: self.shape,
@@ -91,6 +97,7 @@ where
             sq: new,
             tri: self.tri,
             saw: self.saw,
+            morph: self.morph,
             phantom: self.phantom,
         }
     }
@@ -98,7 +105,7 @@ where
     pub fn with_tri<New: Iterator<Item = T::Scalar>>(
         self,
         new: New,
-    ) -> MixOscParamIter<T, A, B, C, D, New, F> {
+    ) -> MixOscParamIter<T, A, B, C, D, New, F, G> {
         MixOscParamIter {
             tune: self.tune,
             shape: self.shape,
@@ -106,6 +113,7 @@ where
             sq: self.sq,
             tri: new,
             saw: self.saw,
+            morph: self.morph,
             phantom: self.phantom,
         }
     }
@@ -113,7 +121,7 @@ where
     pub fn with_saw<New: Iterator<Item = T::Scalar>>(
         self,
         new: New,
-    ) -> MixOscParamIter<T, A, B, C, D, E, New> {
+    ) -> MixOscParamIter<T, A, B, C, D, E, New, G> {
         MixOscParamIter {
             tune: self.tune,
             shape: self.shape,
@@ -121,12 +129,30 @@ where
             sq: self.sq,
             tri: self.tri,
             saw: new,
+            morph: self.morph,
+            phantom: self.phantom,
+        }
+    }
+    /// Replace the current triangle to sawtooth morph source with the one
+    /// provided
+    pub fn with_morph<New: Iterator<Item = T::Scalar>>(
+        self,
+        new: New,
+    ) -> MixOscParamIter<T, A, B, C, D, E, F, New> {
+        MixOscParamIter {
+            tune: self.tune,
+            shape: self.shape,
+            sin: self.sin,
+            sq: self.sq,
+            tri: self.tri,
+            saw: self.saw,
+            morph: new,
             phantom: self.phantom,
         }
     }
 }
 
-impl<T, A, B, C, D, E, F> Iterator for MixOscParamIter<T, A, B, C, D, E, F>
+impl<T, A, B, C, D, E, F, G> Iterator for MixOscParamIter<T, A, B, C, D, E, F, G>
 where
     T: DspFormatBase,
     A: Iterator<Item = T::NoteOffset>,
@@ -135,6 +161,7 @@ where
     D: Iterator<Item = T::Scalar>,
     E: Iterator<Item = T::Scalar>,
     F: Iterator<Item = T::Scalar>,
+    G: Iterator<Item = T::Scalar>,
 {
     type Item = MixOscParams<T>;
     fn next(&mut self) -> Option<MixOscParams<T>> {
@@ -145,6 +172,7 @@ where
             sq: self.sq.next()?,
             tri: self.tri.next()?,
             saw: self.saw.next()?,
+            morph: self.morph.next()?,
         })
     }
 }
@@ -161,6 +189,7 @@ pub fn new_mixosc_param_iter<T: DspFormatBase>() -> MixOscParamIter<
     Repeat<T::Scalar>,
     Repeat<T::Scalar>,
     Repeat<T::Scalar>,
+    Repeat<T::Scalar>,
 > {
     MixOscParamIter {
         tune: repeat(T::NoteOffset::zero()),
@@ -169,6 +198,7 @@ pub fn new_mixosc_param_iter<T: DspFormatBase>() -> MixOscParamIter<
         sq: repeat(T::Scalar::zero()),
         tri: repeat(T::Scalar::zero()),
         saw: repeat(T::Scalar::one()),
+        morph: repeat(T::Scalar::zero()),
         phantom: Default::default(),
     }
 }
@@ -177,35 +207,39 @@ pub fn new_mixosc_param_iter<T: DspFormatBase>() -> MixOscParamIter<
 ///
 /// Use this to easily build iterators to [SyncedMixOscsParams] out of iterators
 /// to its constituent parts.
-pub struct SyncedMixOscsParamIter<T, A, B, C>
+pub struct SyncedMixOscsParamIter<T, A, B, C, D>
 where
     T: DspFormatBase,
     A: Iterator<Item = MixOscParams<T>>,
     B: Iterator<Item = MixOscParams<T>>,
     C: Iterator<Item = bool>,
+    D: Iterator<Item = OscRatio>,
 {
     primary: A,
     secondary: B,
     sync: C,
+    ratio: D,
     phantom: core::marker::PhantomData<T>,
 }
 
-impl<T, A, B, C> SyncedMixOscsParamIter<T, A, B, C>
+impl<T, A, B, C, D> SyncedMixOscsParamIter<T, A, B, C, D>
 where
     T: DspFormatBase,
     A: Iterator<Item = MixOscParams<T>>,
     B: Iterator<Item = MixOscParams<T>>,
     C: Iterator<Item = bool>,
+    D: Iterator<Item = OscRatio>,
 {
     /// Replace the current tuning source with the one provided
     pub fn with_primary<New: Iterator<Item = MixOscParams<T>>>(
         self,
         new: New,
-    ) -> SyncedMixOscsParamIter<T, New, B, C> {
+    ) -> SyncedMixOscsParamIter<T, New, B, C, D> {
         SyncedMixOscsParamIter {
             primary: new,
             secondary: self.secondary,
             sync: self.sync,
+            ratio: self.ratio,
             phantom: self.phantom,
         }
     }
@@ -213,11 +247,12 @@ where
     pub fn with_secondary<New: Iterator<Item = MixOscParams<T>>>(
         self,
         new: New,
-    ) -> SyncedMixOscsParamIter<T, A, New, C> {
+    ) -> SyncedMixOscsParamIter<T, A, New, C, D> {
         SyncedMixOscsParamIter {
             primary: self.primary,
             secondary: new,
             sync: self.sync,
+            ratio: self.ratio,
             phantom: self.phantom,
         }
     }
@@ -225,22 +260,37 @@ where
     pub fn with_sync<New: Iterator<Item = bool>>(
         self,
         new: New,
-    ) -> SyncedMixOscsParamIter<T, A, B, New> {
+    ) -> SyncedMixOscsParamIter<T, A, B, New, D> {
         SyncedMixOscsParamIter {
             primary: self.primary,
             secondary: self.secondary,
             sync: new,
+            ratio: self.ratio,
+            phantom: self.phantom,
+        }
+    }
+    /// Replace the current oscillator ratio source with the one provided
+    pub fn with_ratio<New: Iterator<Item = OscRatio>>(
+        self,
+        new: New,
+    ) -> SyncedMixOscsParamIter<T, A, B, C, New> {
+        SyncedMixOscsParamIter {
+            primary: self.primary,
+            secondary: self.secondary,
+            sync: self.sync,
+            ratio: new,
             phantom: self.phantom,
         }
     }
 }
 
-impl<T, A, B, C> Iterator for SyncedMixOscsParamIter<T, A, B, C>
+impl<T, A, B, C, D> Iterator for SyncedMixOscsParamIter<T, A, B, C, D>
 where
     T: DspFormatBase,
     A: Iterator<Item = MixOscParams<T>>,
     B: Iterator<Item = MixOscParams<T>>,
     C: Iterator<Item = bool>,
+    D: Iterator<Item = OscRatio>,
 {
     type Item = SyncedMixOscsParams<T>;
     fn next(&mut self) -> Option<SyncedMixOscsParams<T>> {
@@ -248,6 +298,7 @@ where
             primary: self.primary.next()?,
             secondary: self.secondary.next()?,
             sync: self.sync.next()?,
+            ratio: self.ratio.next()?,
         })
     }
 }
@@ -255,8 +306,13 @@ where
 /// Create a new [SyncedMixOscsParamIter], which initially creates instances of
 /// [SyncedMixOscsParams] with the defaults for each
 #[allow(clippy::type_complexity)]
-pub fn new_synced_mixoscs_param_iter<T: DspFormatBase>(
-) -> SyncedMixOscsParamIter<T, Repeat<MixOscParams<T>>, Repeat<MixOscParams<T>>, Repeat<bool>> {
+pub fn new_synced_mixoscs_param_iter<T: DspFormatBase>() -> SyncedMixOscsParamIter<
+    T,
+    Repeat<MixOscParams<T>>,
+    Repeat<MixOscParams<T>>,
+    Repeat<bool>,
+    Repeat<OscRatio>,
+> {
     SyncedMixOscsParamIter {
         primary: repeat(MixOscParams {
             tune: T::NoteOffset::zero(),
@@ -265,6 +321,7 @@ pub fn new_synced_mixoscs_param_iter<T: DspFormatBase>(
             sq: T::Scalar::zero(),
             tri: T::Scalar::zero(),
             saw: T::Scalar::one(),
+            morph: T::Scalar::zero(),
         }),
         secondary: repeat(MixOscParams {
             tune: T::NoteOffset::zero(),
@@ -273,8 +330,10 @@ pub fn new_synced_mixoscs_param_iter<T: DspFormatBase>(
             sq: T::Scalar::zero(),
             tri: T::Scalar::zero(),
             saw: T::Scalar::one(),
+            morph: T::Scalar::zero(),
         }),
         sync: repeat(false),
+        ratio: repeat(OscRatio::Free),
         phantom: Default::default(),
     }
 }
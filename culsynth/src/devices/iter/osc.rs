@@ -4,68 +4,93 @@ use super::*;
 ///
 /// Use this to easily build iterators to [OscParams] out of iterators to
 /// its constituent parts.
-pub struct OscParamIter<T, A, B>
+pub struct OscParamIter<T, A, B, C>
 where
     T: DspFormatBase,
     A: Iterator<Item = T::NoteOffset>,
     B: Iterator<Item = T::Scalar>,
+    C: Iterator<Item = T::Scalar>,
 {
     tune: A,
     shape: B,
+    morph: C,
     phantom: core::marker::PhantomData<T>,
 }
 
-impl<T, A, B> OscParamIter<T, A, B>
+impl<T, A, B, C> OscParamIter<T, A, B, C>
 where
     T: DspFormatBase,
     A: Iterator<Item = T::NoteOffset>,
     B: Iterator<Item = T::Scalar>,
+    C: Iterator<Item = T::Scalar>,
 {
     /// Replace the current tuning source with the one provided
     pub fn with_tune<New: Iterator<Item = T::NoteOffset>>(
         self,
         new: New,
-    ) -> OscParamIter<T, New, B> {
+    ) -> OscParamIter<T, New, B, C> {
         OscParamIter {
             tune: new,
             shape: self.shape,
+            morph: self.morph,
             phantom: self.phantom,
         }
     }
     /// Replace the current wave shape (phase distortion) source with the
     /// one provided
-    pub fn with_shape<New: Iterator<Item = T::Scalar>>(self, new: New) -> OscParamIter<T, A, New> {
+    pub fn with_shape<New: Iterator<Item = T::Scalar>>(
+        self,
+        new: New,
+    ) -> OscParamIter<T, A, New, C> {
         OscParamIter {
             tune: self.tune,
             shape: new,
+            morph: self.morph,
+            phantom: self.phantom,
+        }
+    }
+    /// Replace the current triangle to sawtooth morph source with the one
+    /// provided
+    pub fn with_morph<New: Iterator<Item = T::Scalar>>(
+        self,
+        new: New,
+    ) -> OscParamIter<T, A, B, New> {
+        OscParamIter {
+            tune: self.tune,
+            shape: self.shape,
+            morph: new,
             phantom: self.phantom,
         }
     }
 }
 
-impl<T, A, B> Iterator for OscParamIter<T, A, B>
+impl<T, A, B, C> Iterator for OscParamIter<T, A, B, C>
 where
     T: DspFormatBase,
     A: Iterator<Item = T::NoteOffset>,
     B: Iterator<Item = T::Scalar>,
+    C: Iterator<Item = T::Scalar>,
 {
     type Item = OscParams<T>;
     fn next(&mut self) -> Option<OscParams<T>> {
         Some(OscParams {
             tune: self.tune.next()?,
             shape: self.shape.next()?,
+            morph: self.morph.next()?,
         })
     }
 }
 
 /// Create a new [OscParamIter], which initially creates instances of
-/// [OscParams] zero tuning offset and zero wave shaping (phase distortion)
-/// until calling the `with_*()` methods.
+/// [OscParams] zero tuning offset, zero wave shaping (phase distortion), and
+/// zero morph (a triangle wave) until calling the `with_*()` methods.
+#[allow(clippy::type_complexity)]
 pub fn new_osc_param_iter<T: DspFormatBase>(
-) -> OscParamIter<T, Repeat<T::NoteOffset>, Repeat<T::Scalar>> {
+) -> OscParamIter<T, Repeat<T::NoteOffset>, Repeat<T::Scalar>, Repeat<T::Scalar>> {
     OscParamIter {
         tune: repeat(T::NoteOffset::zero()),
         shape: repeat(T::Scalar::zero()),
+        morph: repeat(T::Scalar::zero()),
         phantom: Default::default(),
     }
 }
@@ -74,35 +99,39 @@ pub fn new_osc_param_iter<T: DspFormatBase>(
 ///
 /// Use this to easily build iterators to [SyncedOscsParams] out of iterators
 /// to its constituent parts.
-pub struct SyncedOscsParamIter<T, A, B, C>
+pub struct SyncedOscsParamIter<T, A, B, C, D>
 where
     T: DspFormatBase,
     A: Iterator<Item = OscParams<T>>,
     B: Iterator<Item = OscParams<T>>,
     C: Iterator<Item = bool>,
+    D: Iterator<Item = OscRatio>,
 {
     primary: A,
     secondary: B,
     sync: C,
+    ratio: D,
     phantom: core::marker::PhantomData<T>,
 }
 
-impl<T, A, B, C> SyncedOscsParamIter<T, A, B, C>
+impl<T, A, B, C, D> SyncedOscsParamIter<T, A, B, C, D>
 where
     T: DspFormatBase,
     A: Iterator<Item = OscParams<T>>,
     B: Iterator<Item = OscParams<T>>,
     C: Iterator<Item = bool>,
+    D: Iterator<Item = OscRatio>,
 {
     /// Replace the current primary OscParams source with the one provided
     pub fn with_primary<New: Iterator<Item = OscParams<T>>>(
         self,
         new: New,
-    ) -> SyncedOscsParamIter<T, New, B, C> {
+    ) -> SyncedOscsParamIter<T, New, B, C, D> {
         SyncedOscsParamIter {
             primary: new,
             secondary: self.secondary,
             sync: self.sync,
+            ratio: self.ratio,
             phantom: self.phantom,
         }
     }
@@ -110,11 +139,12 @@ where
     pub fn with_secondary<New: Iterator<Item = OscParams<T>>>(
         self,
         new: New,
-    ) -> SyncedOscsParamIter<T, A, New, C> {
+    ) -> SyncedOscsParamIter<T, A, New, C, D> {
         SyncedOscsParamIter {
             primary: self.primary,
             secondary: new,
             sync: self.sync,
+            ratio: self.ratio,
             phantom: self.phantom,
         }
     }
@@ -122,22 +152,37 @@ where
     pub fn with_sync<New: Iterator<Item = bool>>(
         self,
         new: New,
-    ) -> SyncedOscsParamIter<T, A, B, New> {
+    ) -> SyncedOscsParamIter<T, A, B, New, D> {
         SyncedOscsParamIter {
             primary: self.primary,
             secondary: self.secondary,
             sync: new,
+            ratio: self.ratio,
+            phantom: self.phantom,
+        }
+    }
+    /// Replace the current oscillator ratio source with the one provided
+    pub fn with_ratio<New: Iterator<Item = OscRatio>>(
+        self,
+        new: New,
+    ) -> SyncedOscsParamIter<T, A, B, C, New> {
+        SyncedOscsParamIter {
+            primary: self.primary,
+            secondary: self.secondary,
+            sync: self.sync,
+            ratio: new,
             phantom: self.phantom,
         }
     }
 }
 
-impl<T, A, B, C> Iterator for SyncedOscsParamIter<T, A, B, C>
+impl<T, A, B, C, D> Iterator for SyncedOscsParamIter<T, A, B, C, D>
 where
     T: DspFormatBase,
     A: Iterator<Item = OscParams<T>>,
     B: Iterator<Item = OscParams<T>>,
     C: Iterator<Item = bool>,
+    D: Iterator<Item = OscRatio>,
 {
     type Item = SyncedOscsParams<T>;
     fn next(&mut self) -> Option<SyncedOscsParams<T>> {
@@ -145,6 +190,7 @@ where
             primary: self.primary.next()?,
             secondary: self.secondary.next()?,
             sync: self.sync.next()?,
+            ratio: self.ratio.next()?,
         })
     }
 }
@@ -152,18 +198,26 @@ where
 /// Create a new [SyncedOscsParamIter], which initially creates instances of
 /// [SyncedOscsParams] with the defaults for each
 #[allow(clippy::type_complexity)]
-pub fn new_synced_oscs_param_iter<T: DspFormatBase>(
-) -> SyncedOscsParamIter<T, Repeat<OscParams<T>>, Repeat<OscParams<T>>, Repeat<bool>> {
+pub fn new_synced_oscs_param_iter<T: DspFormatBase>() -> SyncedOscsParamIter<
+    T,
+    Repeat<OscParams<T>>,
+    Repeat<OscParams<T>>,
+    Repeat<bool>,
+    Repeat<OscRatio>,
+> {
     SyncedOscsParamIter {
         primary: repeat(OscParams {
             tune: T::NoteOffset::zero(),
             shape: T::Scalar::zero(),
+            morph: T::Scalar::zero(),
         }),
         secondary: repeat(OscParams {
             tune: T::NoteOffset::zero(),
             shape: T::Scalar::zero(),
+            morph: T::Scalar::zero(),
         }),
         sync: repeat(false),
+        ratio: repeat(OscRatio::Free),
         phantom: Default::default(),
     }
 }
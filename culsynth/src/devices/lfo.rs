@@ -1,8 +1,13 @@
 use super::*;
-use crate::{IScalarFxP, PhaseFxP};
+#[cfg(feature = "fixed")]
+use crate::fixedmath::{one_over_one_plus, I16F16, U19F13, U1F15};
+use crate::IScalarFxP;
+#[cfg(feature = "fixed")]
+use crate::PhaseFxP;
 use core::mem::transmute;
 use core::option::Option;
-use rand::{rngs::SmallRng, RngCore, SeedableRng};
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
 
 /// Default random seed to use if not provided a seed
 const RANDOM_SEED: u64 = 0xce607a9d25ec3d88u64; //random 64 bit integer
@@ -10,7 +15,16 @@ const RANDOM_SEED: u64 = 0xce607a9d25ec3d88u64; //random 64 bit integer
 pub(crate) mod detail {
     use super::*;
 
-    pub trait LfoOps: crate::DspFormatBase {
+    pub trait LfoOps: crate::DspFormatContext {
+        /// The state of the output smoother, which may have more precision
+        /// than a Sample to allow for long slew times
+        type LfoSlewAcc: Copy + Default + Send + DspSerde;
+        fn lfo_slew(
+            context: &Self::Context,
+            acc: &mut Self::LfoSlewAcc,
+            value: Self::Sample,
+            time: Self::EnvParam,
+        ) -> Self::Sample;
         fn phase_per_smp(context: &Self::Context, frequency: Self::LfoFreq) -> Self::Phase;
         fn calc_lfo(
             phase: Self::Phase,
@@ -18,6 +32,45 @@ pub(crate) mod detail {
             rands: &[Self::Sample; 2],
         ) -> Self::Sample;
     }
+
+    /// The state of the fixed point output smoother, along with its
+    /// coefficient, which only changes with the slew time and sample rate
+    #[cfg(feature = "fixed")]
+    #[derive(Clone, Copy)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct LfoSlewFxP {
+        pub acc: I16F16,
+        /// The slew time multiplied by the sample rate that the coefficient
+        /// was calculated from
+        pub time_sr: U19F13,
+        /// The coefficient, `1 / (1 + time_sr)`, is `coeff * 2^-coeff_shift`
+        pub coeff: U1F15,
+        pub coeff_shift: u32,
+    }
+
+    #[cfg(feature = "fixed")]
+    impl LfoSlewFxP {
+        /// Update the coefficient if `time_sr` has changed
+        pub fn set_time_sr(&mut self, time_sr: U19F13) {
+            if self.time_sr != time_sr {
+                self.time_sr = time_sr;
+                (self.coeff, self.coeff_shift) = one_over_one_plus(time_sr);
+            }
+        }
+    }
+
+    #[cfg(feature = "fixed")]
+    impl Default for LfoSlewFxP {
+        fn default() -> Self {
+            let (coeff, coeff_shift) = one_over_one_plus(U19F13::ZERO);
+            Self {
+                acc: I16F16::ZERO,
+                time_sr: U19F13::ZERO,
+                coeff,
+                coeff_shift,
+            }
+        }
+    }
 }
 
 #[repr(transparent)]
@@ -31,7 +84,11 @@ pub struct LfoOptions {
 
 impl LfoOptions {
     const BIPOLAR: u16 = 1 << 8;
-    const RETRIGGER: u16 = 1 << 9;
+    /// The reset mode occupies two bits, with [ResetMode::Soft] in the same
+    /// position as the original (single bit) retrigger flag
+    const RESET_SHIFT: u16 = 9;
+    const RESET_MASK: u16 = 0b11 << Self::RESET_SHIFT;
+    const INVERT: u16 = 1 << 11;
     /// The LFO Waveform (Sine, Square, Sample+Hold, etc.)
     pub fn wave(&self) -> Option<LfoWave> {
         let value = (self.bits & 0xFF) as u8;
@@ -41,16 +98,44 @@ impl LfoOptions {
     pub fn bipolar(&self) -> bool {
         self.bits & Self::BIPOLAR != 0
     }
+    /// Is the output of this LFO inverted?  A bipolar LFO is negated, and a
+    /// unipolar LFO is reflected about its midpoint (so it still lies in 0:1)
+    pub fn invert(&self) -> bool {
+        self.bits & Self::INVERT != 0
+    }
     /// Does this LFO retrigger/reset on each gate?
     pub fn retrigger(&self) -> bool {
-        self.bits & Self::RETRIGGER != 0
+        self.reset_mode() != ResetMode::None
+    }
+    /// How this LFO resets on each gate (see [ResetMode])
+    pub fn reset_mode(&self) -> ResetMode {
+        let value = ((self.bits & Self::RESET_MASK) >> Self::RESET_SHIFT) as u8;
+        ResetMode::try_from(value).unwrap_or_default()
     }
-    /// Pack the LFO parameters into a `LfoOptions` value
+    /// Pack the LFO parameters into a `LfoOptions` value.  If `retrigger` is
+    /// set, the LFO uses [ResetMode::Soft].
     pub fn new(wave: LfoWave, bipolar: bool, retrigger: bool) -> Self {
+        let reset = if retrigger {
+            ResetMode::Soft
+        } else {
+            ResetMode::None
+        };
+        Self::new_with_reset(wave, bipolar, reset)
+    }
+    /// Pack the LFO parameters into a `LfoOptions` value, with an explicit
+    /// [ResetMode]
+    pub fn new_with_reset(wave: LfoWave, bipolar: bool, reset: ResetMode) -> Self {
         LfoOptions {
             bits: (wave as u16)
                 | if bipolar { Self::BIPOLAR } else { 0 }
-                | if retrigger { Self::RETRIGGER } else { 0 },
+                | ((reset as u16) << Self::RESET_SHIFT),
+        }
+    }
+    /// Returns these options with the output inverted (see
+    /// [LfoOptions::invert]) if `invert` is set
+    pub fn with_invert(self, invert: bool) -> Self {
+        LfoOptions {
+            bits: (self.bits & !Self::INVERT) | if invert { Self::INVERT } else { 0 },
         }
     }
 }
@@ -147,6 +232,11 @@ pub struct LfoParams<T: DspFormatBase> {
     pub depth: T::Scalar,
     /// The options, including waveform and retriggering (see [LfoOptions])
     pub opts: LfoOptions,
+    /// The time constant of a one-pole smoother applied to the waveform, in
+    /// seconds.  Zero outputs the raw waveform, and longer times round off
+    /// the edges of the stepped waveforms (e.g. to turn a sample and hold
+    /// into a smooth random modulation).
+    pub slew: T::EnvParam,
 }
 
 impl<T: DspFloat> From<&LfoParams<i16>> for LfoParams<T> {
@@ -155,32 +245,62 @@ impl<T: DspFloat> From<&LfoParams<i16>> for LfoParams<T> {
             freq: value.freq.to_num(),
             depth: value.depth.to_num(),
             opts: value.opts,
+            slew: value.slew.to_num(),
         }
     }
 }
 
 /// An LFO
+///
+/// The output can optionally be smoothed with a one-pole filter (see
+/// [LfoParams::slew]) before the depth is applied.
 #[derive(Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct Lfo<T: DspFormatBase + detail::LfoOps> {
-    rng: SmallRng,
+    seed: u64,
+    // The same generator as SmallRng on 64 bit platforms, but (unlike
+    // SmallRng) its state can be serialized
+    rng: Xoshiro256PlusPlus,
     phase: T::Phase,
     rand_smps: [T::Sample; 2],
     last_gate: bool,
+    slew_acc: T::LfoSlewAcc,
 }
 
 impl<T: DspFormatBase + detail::LfoOps> Lfo<T> {
     /// Constructor
+    ///
+    /// `seed` seeds the generator for the random waveforms.  The sequence
+    /// for a given seed is the same on every platform, including 32 bit
+    /// targets such as the RP2040 (where it changed from older versions of
+    /// this crate, which used `SmallRng`).
     pub fn new(seed: u64) -> Self {
         let mut retval = Self {
-            rng: SmallRng::seed_from_u64(seed),
+            seed,
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
             phase: T::Phase::zero(),
             rand_smps: [T::Sample::zero(); 2],
             last_gate: false,
+            slew_acc: Default::default(),
         };
-        retval.update_rands();
-        retval.update_rands();
+        retval.reset_rands();
         retval
     }
+    /// The current phase of the LFO, in radians from -pi to pi (i.e. the
+    /// phase that the next call to [Device::next] will output)
+    pub fn phase(&self) -> T::Phase {
+        self.phase
+    }
+    /// Restart the random sequence from the beginning
+    fn reset_rands(&mut self) {
+        self.rng = Xoshiro256PlusPlus::seed_from_u64(self.seed);
+        self.update_rands();
+        self.update_rands();
+    }
     fn update_rands(&mut self) {
         self.rand_smps[1] = self.rand_smps[0];
         let rand_num = self.rng.next_u32() & (u16::MAX as u32);
@@ -195,8 +315,15 @@ impl<T: DspFormat> Device<T> for Lfo<T> {
     type Output = T::Sample;
     /// Generate the LFO signal
     fn next(&mut self, context: &T::Context, gate: bool, params: LfoParams<T>) -> T::Sample {
-        if params.opts.retrigger() && gate && !self.last_gate {
-            self.phase = T::Phase::zero();
+        if gate && !self.last_gate {
+            match params.opts.reset_mode() {
+                ResetMode::None => {}
+                ResetMode::Soft => self.phase = T::Phase::zero(),
+                ResetMode::Hard => {
+                    self.phase = T::Phase::zero();
+                    self.reset_rands();
+                }
+            }
         }
         self.last_gate = gate;
         let mut value = T::calc_lfo(
@@ -204,9 +331,15 @@ impl<T: DspFormat> Device<T> for Lfo<T> {
             params.opts.wave().unwrap_or_default(),
             &self.rand_smps,
         );
+        // Inverting before the unipolar conversion reflects a unipolar LFO
+        // about its midpoint instead of making it negative
+        if params.opts.invert() {
+            value = T::Sample::zero() - value;
+        }
         if !params.opts.bipolar() {
             value = (value + T::Sample::one()).divide_by_two();
         }
+        value = T::lfo_slew(context, &mut self.slew_acc, value, params.slew);
         value = value.scale(params.depth);
         self.phase = self.phase + T::phase_per_smp(context, params.freq);
         // Check if we've crossed from positive phase back to negative:
@@ -224,7 +357,31 @@ impl<T: DspFormatBase + detail::LfoOps> Default for Lfo<T> {
     }
 }
 
+#[cfg(feature = "fixed")]
 impl detail::LfoOps for i16 {
+    type LfoSlewAcc = detail::LfoSlewFxP;
+    fn lfo_slew(
+        context: &ContextFxP,
+        state: &mut detail::LfoSlewFxP,
+        value: SampleFxP,
+        time: EnvParamFxP,
+    ) -> SampleFxP {
+        use crate::fixedmath::{scale_shr_round, U16F0};
+        // The coefficient is 1 / (1 + time * sample_rate), so a slew time of
+        // zero jumps straight to the new value
+        let sr = U16F0::from_bits(context.sample_rate.value());
+        state.set_time_sr(time.wide_mul(sr));
+        let diff = I16F16::from_num(value) - state.acc;
+        // Always move at least one LSB, or the output would stall short of
+        // the target once the step rounds to zero
+        let step = scale_shr_round(diff, state.coeff, state.coeff_shift);
+        state.acc += if step == I16F16::ZERO {
+            I16F16::from_bits(diff.to_bits().signum())
+        } else {
+            step
+        };
+        SampleFxP::saturating_from_num(state.acc)
+    }
     fn calc_lfo(phase: PhaseFxP, wave: lfo::LfoWave, rands: &[SampleFxP; 2]) -> SampleFxP {
         use crate::fixed_traits::Fixed16;
         use crate::fixedmath::{cos_fixed, sin_fixed};
@@ -250,16 +407,17 @@ impl detail::LfoOps for i16 {
             }
             LfoWave::Sine => {
                 if phase < PhaseFxP::FRAC_PI_2.unwrapped_neg() {
-                    // phase in [-pi, pi/2)
+                    // phase in [-pi, -pi/2)
                     // Use the identity sin(x) = -cos(x+pi/2) since our taylor series
                     // approximations are centered about zero and this will be more accurate
                     cos_fixed(SampleFxP::from_num(phase + PhaseFxP::FRAC_PI_2)).unwrapped_neg()
                 } else if phase < PhaseFxP::FRAC_PI_2 {
+                    // phase in [-pi/2, pi/2)
+                    sin_fixed(SampleFxP::from_num(phase))
+                } else {
                     // phase in [pi/2, pi)
                     // sin(x) = cos(x-pi/2)
                     cos_fixed(SampleFxP::from_num(phase - PhaseFxP::FRAC_PI_2))
-                } else {
-                    sin_fixed(SampleFxP::from_num(phase))
                 }
             }
             LfoWave::SampleHold => rands[0],
@@ -276,6 +434,16 @@ impl detail::LfoOps for i16 {
 }
 
 impl<T: DspFloat> detail::LfoOps for T {
+    type LfoSlewAcc = T;
+    fn lfo_slew(context: &Context<T>, acc: &mut T, value: T, time: T) -> T {
+        if time <= T::ZERO {
+            *acc = value;
+        } else {
+            let coeff = T::ONE - (T::ONE.neg() / (time * context.sample_rate)).fexp();
+            *acc = *acc + (value - *acc) * coeff;
+        }
+        *acc
+    }
     fn calc_lfo(phase: T, wave: lfo::LfoWave, rands: &[T; 2]) -> T {
         let frac_2phase_pi = (phase + phase) / T::PI;
         let pi_2 = T::FRAC_PI_2;
@@ -299,16 +467,17 @@ impl<T: DspFloat> detail::LfoOps for T {
             }
             LfoWave::Sine => {
                 if phase < pi_2.neg() {
-                    // phase in [-pi, pi/2)
+                    // phase in [-pi, -pi/2)
                     // Use the identity sin(x) = -cos(x+pi/2) since our taylor series
                     // approximations are centered about zero and this will be more accurate
                     T::fcos(phase + pi_2).neg()
                 } else if phase < pi_2 {
+                    // phase in [-pi/2, pi/2)
+                    T::fsin(phase)
+                } else {
                     // phase in [pi/2, pi)
                     // sin(x) = cos(x-pi/2)
                     T::fcos(phase - pi_2)
-                } else {
-                    T::fsin(phase)
                 }
             }
             LfoWave::SampleHold => rands[0],
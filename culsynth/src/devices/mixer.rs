@@ -12,6 +12,11 @@ use core::iter::zip;
 /// In the fixed-point case, this device performs saturation checking rather
 /// than wrapping or panicing on overflow.
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct Mixer<T: DspFormat, const N: usize> {
     phantom: core::marker::PhantomData<T>,
 }
@@ -1,4 +1,80 @@
 use super::*;
+use crate::IScalarFxP;
+
+/// A change in any waveform gain of more than this between two samples is
+/// treated as switching waveforms, and is crossfaded (see [MixOsc])
+const WAVE_JUMP: IScalarFxP = IScalarFxP::lit("0.0625");
+/// The step in the crossfade position per sample when switching waveforms,
+/// so the crossfade takes 128 samples (about 2.7ms at 48kHz)
+const WAVE_XFADE_STEP: IScalarFxP = IScalarFxP::lit("0.0078125");
+
+fn scalar_from_fixed<T: DspFormatBase>(value: IScalarFxP) -> T::Scalar {
+    T::scalar_from_sample(T::sample_from_fixed(value))
+}
+
+/// Mixes the waveforms of an [Osc].  When the gains jump (e.g. switching
+/// from one waveform to another), both the old and new mix are rendered for
+/// a short time and blended with a constant-power crossfade, so the output
+/// doesn't click.  Otherwise, this is just a [Mixer].
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+struct WaveMixer<T: DspFormat> {
+    mixer: Mixer<T, 4>,
+    xfade: Xfade<T>,
+    /// False until the first sample, so a new oscillator starts at its
+    /// gains rather than fading in from silence
+    started: bool,
+    /// The gains as of the last sample
+    gains: [T::Scalar; 4],
+    /// The gains being faded out and the position of the crossfade, if the
+    /// gains have jumped recently
+    fade: Option<([T::Scalar; 4], T::Scalar)>,
+}
+
+impl<T: DspFormat> WaveMixer<T> {
+    fn next(
+        &mut self,
+        context: &T::Context,
+        waves: [T::Sample; 4],
+        gains: [T::Scalar; 4],
+    ) -> T::Sample {
+        let jump = scalar_from_fixed::<T>(WAVE_JUMP);
+        let jumped = self.started
+            && self.gains.iter().zip(gains.iter()).any(|(old, new)| {
+                let diff = if old > new { *old - *new } else { *new - *old };
+                diff > jump
+            });
+        if jumped {
+            // If already crossfading, fade out whichever mix is louder
+            let from = match self.fade {
+                Some((old, mix)) if mix < T::Scalar::one().divide_by_two() => old,
+                _ => self.gains,
+            };
+            self.fade = Some((from, T::Scalar::zero()));
+        }
+        self.started = true;
+        self.gains = gains;
+        let out = self.mixer.next(context, waves, gains);
+        let Some((old, mix)) = self.fade else {
+            return out;
+        };
+        let step = scalar_from_fixed::<T>(WAVE_XFADE_STEP);
+        self.fade = if mix < T::Scalar::one() - step {
+            Some((old, mix.dsp_saturating_add(step)))
+        } else {
+            None
+        };
+        let input = XfadeInput {
+            a: self.mixer.next(context, waves, old),
+            b: out,
+        };
+        self.xfade.next(context, input, mix)
+    }
+}
 
 /// A parameter pack for [MixOsc].
 #[derive(Clone, Default)]
@@ -16,6 +92,9 @@ pub struct MixOscParams<T: DspFormatBase> {
     pub tri: T::Scalar,
     /// Sawtooth wave gain
     pub saw: T::Scalar,
+    /// The amount to morph the triangle wave towards a sawtooth, ranging
+    /// from 0-1 (see [OscParams::morph])
+    pub morph: T::Scalar,
 }
 
 impl<T: DspFloat> From<&MixOscParams<i16>> for MixOscParams<T> {
@@ -27,6 +106,7 @@ impl<T: DspFloat> From<&MixOscParams<i16>> for MixOscParams<T> {
             sq: value.sq.to_num(),
             tri: value.tri.to_num(),
             saw: value.saw.to_num(),
+            morph: value.morph.to_num(),
         }
     }
 }
@@ -37,6 +117,7 @@ impl<T: DspFormatBase> MixOscParams<T> {
         OscParams {
             tune: self.tune,
             shape: self.shape,
+            morph: self.morph,
         }
     }
 }
@@ -45,15 +126,31 @@ impl<T: DspFormatBase> MixOscParams<T> {
 /// wave shapes and taking the gain of each wave as a parameter.  This provides
 /// a pre-mixed output as a single signal.
 ///
+/// When the gains jump by more than a small amount from one sample to the
+/// next (e.g. when switching waveforms), the old and new mixes are
+/// crossfaded over about 128 samples to avoid a click.  Gradual changes
+/// (e.g. from modulation) are applied immediately.
+///
 /// This implements [Device], taking a Note as input and [MixOscParams] as
 /// parameters, and outputs a Sample representing the sum of the different
 /// waveforms scaled by their respective gains.
 #[derive(Clone, Default)]
 pub struct MixOsc<T: DspFormat> {
-    mixer: Mixer<T, 4>,
+    mixer: WaveMixer<T>,
     osc: Osc<T>,
 }
 
+impl<T: DspFormat> MixOsc<T> {
+    /// Reset the oscillator to its initial phase (see [Osc::reset_phase])
+    pub fn reset_phase(&mut self) {
+        self.osc.reset_phase();
+    }
+    /// Set the phase the oscillator resets to (see [Osc::set_initial_phase])
+    pub fn set_initial_phase(&mut self, phase: T::Scalar) {
+        self.osc.set_initial_phase(phase);
+    }
+}
+
 impl<T: DspFormat> Device<T> for MixOsc<T> {
     type Input = T::Note;
     type Params = MixOscParams<T>;
@@ -78,6 +175,9 @@ pub struct SyncedMixOscsParams<T: DspFormatBase> {
     /// True if oscillator sync has been enabled - when false, both oscillators
     /// will run independently
     pub sync: bool,
+    /// Locks the frequency of the secondary oscillator to a ratio of the
+    /// primary's (see [OscRatio])
+    pub ratio: OscRatio,
 }
 
 impl<T: DspFloat> From<&SyncedMixOscsParams<i16>> for SyncedMixOscsParams<T> {
@@ -86,11 +186,13 @@ impl<T: DspFloat> From<&SyncedMixOscsParams<i16>> for SyncedMixOscsParams<T> {
             primary: (&value.primary).into(),
             secondary: (&value.secondary).into(),
             sync: value.sync,
+            ratio: value.ratio,
         }
     }
 }
 
 /// The output of a [SyncedMixOscs] device.
+#[must_use]
 #[derive(Clone, Default)]
 pub struct SyncedMixOscsOutput<T: DspFormatBase> {
     /// The output of the primary oscillator
@@ -100,7 +202,8 @@ pub struct SyncedMixOscsOutput<T: DspFormatBase> {
 }
 
 /// A synced pair of [MixOsc]s.  The secondary oscillator will be synced
-/// to the primary oscillator.
+/// to the primary oscillator.  Like [MixOsc], jumps in the waveform gains
+/// of either oscillator are crossfaded.
 ///
 /// This implements [Device], taking a Note as input and a [SyncedMixOscsParams]
 /// as parameters.  It outputs a [SyncedMixOscsOutput], which is just the pair
@@ -108,10 +211,36 @@ pub struct SyncedMixOscsOutput<T: DspFormatBase> {
 ///
 /// See also: [SyncedOscs], [Osc]
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct SyncedMixOscs<T: DspFormat> {
     oscs: SyncedOscs<T>,
-    mixer_pri: Mixer<T, 4>,
-    mixer_sec: Mixer<T, 4>,
+    mixer_pri: WaveMixer<T>,
+    mixer_sec: WaveMixer<T>,
+}
+
+impl<T: DspFormat> SyncedMixOscs<T> {
+    /// Enable or disable band-limiting of oscillator sync (see
+    /// [SyncedOscs::set_sync_blep])
+    pub fn set_sync_blep(&mut self, sync_blep: bool) {
+        self.oscs.set_sync_blep(sync_blep);
+    }
+    /// Is oscillator sync band-limited?
+    pub fn sync_blep(&self) -> bool {
+        self.oscs.sync_blep()
+    }
+    /// Reset both oscillators to their initial phase (see [Osc::reset_phase])
+    pub fn reset_phase(&mut self) {
+        self.oscs.reset_phase();
+    }
+    /// Set the phase both oscillators reset to (see
+    /// [Osc::set_initial_phase])
+    pub fn set_initial_phase(&mut self, phase: T::Scalar) {
+        self.oscs.set_initial_phase(phase);
+    }
 }
 
 impl<T: DspFormat> Device<T> for SyncedMixOscs<T> {
@@ -128,6 +257,7 @@ impl<T: DspFormat> Device<T> for SyncedMixOscs<T> {
             primary: params.primary.to_osc_params(),
             secondary: params.secondary.to_osc_params(),
             sync: params.sync,
+            ratio: params.ratio,
         };
         let SyncedOscsOutput {
             primary: p,
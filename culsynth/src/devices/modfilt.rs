@@ -50,7 +50,7 @@ impl<T: DspFloat> From<&ModFiltParams<i16>> for ModFiltParams<T> {
     }
 }
 
-impl<T: DspFormatBase> ModFiltParams<T> {
+impl<T: DspFormatBase + filt::detail::ResonanceOps> ModFiltParams<T> {
     /// Extract the [FiltParams] from this parameter pack, taking into account
     /// any modulation from the [ModFiltInput].
     pub fn to_filt_params(&self, input: &ModFiltInput<T>) -> FiltParams<T> {
@@ -61,7 +61,7 @@ impl<T: DspFormatBase> ModFiltParams<T> {
         cutoff = cutoff.dsp_saturating_add(kbd).dsp_saturating_add(vel).dsp_saturating_add(env);
         FiltParams {
             cutoff,
-            resonance: self.resonance,
+            resonance: Resonance::new(self.resonance),
         }
     }
 }
@@ -72,11 +72,24 @@ impl<T: DspFormatBase> ModFiltParams<T> {
 /// parameters to modulate the cutoff frequency with keyboard tracking,
 /// envelope, and velocity modulation.
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct ModFilt<T: DspFormat> {
     filter: Filt<T>,
     mixer: Mixer<T, 3>,
 }
 
+impl<T: DspFormat> ModFilt<T> {
+    /// Returns true if the filter has reset its state since the last call
+    /// (see [Filt::take_state_reset])
+    pub fn take_state_reset(&mut self) -> bool {
+        self.filter.take_state_reset()
+    }
+}
+
 impl<T: DspFormat> Device<T> for ModFilt<T> {
     type Input = ModFiltInput<T>;
     type Params = ModFiltParams<T>;
@@ -1,7 +1,11 @@
 use super::*;
 
+use crate::fixedmath::I8F24;
 use crate::Float;
-use crate::{FrequencyFxP, PhaseFxP};
+#[cfg(feature = "fixed")]
+use crate::PhaseFxP;
+#[cfg(feature = "fixed")]
+use crate::{FrequencyFxP, SignedNoteFxP};
 
 /// Parameters for an [Osc]
 #[derive(Clone, Default)]
@@ -10,6 +14,9 @@ pub struct OscParams<T: DspFormatBase> {
     pub tune: T::NoteOffset,
     /// The amount of phase distortion to apply to the waveform, from 0 to 1
     pub shape: T::Scalar,
+    /// The amount to morph the triangle wave output towards a sawtooth, from
+    /// 0 (a symmetric triangle) to 1 (identical to the sawtooth output)
+    pub morph: T::Scalar,
 }
 
 impl<T: DspFloat> From<&OscParams<i16>> for OscParams<T> {
@@ -17,6 +24,7 @@ impl<T: DspFloat> From<&OscParams<i16>> for OscParams<T> {
         Self {
             tune: value.tune.to_num(),
             shape: value.shape.to_num(),
+            morph: value.morph.to_num(),
         }
     }
 }
@@ -31,6 +39,9 @@ pub struct SyncedOscsParams<T: DspFormatBase> {
     /// True if oscillator sync has been enabled - when false, both oscillators
     /// will run independently
     pub sync: bool,
+    /// Locks the frequency of the secondary oscillator to a ratio of the
+    /// primary's (see [OscRatio])
+    pub ratio: OscRatio,
 }
 
 impl<T: DspFloat> From<&SyncedOscsParams<i16>> for SyncedOscsParams<T> {
@@ -39,11 +50,92 @@ impl<T: DspFloat> From<&SyncedOscsParams<i16>> for SyncedOscsParams<T> {
             primary: (&value.primary).into(),
             secondary: (&value.secondary).into(),
             sync: value.sync,
+            ratio: value.ratio,
         }
     }
 }
+
+/// A frequency ratio between the secondary and primary oscillators of a
+/// [SyncedOscs].
+///
+/// When locked to a ratio, the secondary oscillator is tuned relative to the
+/// primary instead of using its own tuning, so the two stay in a fixed
+/// (harmonic, for integer ratios) relationship as the note changes.
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum OscRatio {
+    /// The secondary oscillator is tuned independently
+    #[default]
+    Free,
+    /// An octave below the primary
+    Half,
+    /// The same frequency as the primary
+    Unison,
+    /// An octave above the primary
+    Double,
+    /// An octave and a fifth above the primary
+    Triple,
+    /// 3.5 times the frequency of the primary
+    SevenHalves,
+}
+
+impl OscRatio {
+    const ELEM: [OscRatio; 6] = [
+        Self::Free,
+        Self::Half,
+        Self::Unison,
+        Self::Double,
+        Self::Triple,
+        Self::SevenHalves,
+    ];
+    /// Returns a slice to all of the possible OscRatios
+    pub const fn ratios() -> &'static [OscRatio] {
+        &Self::ELEM
+    }
+    /// Provides the name of the ratio
+    pub const fn to_str(&self) -> &'static str {
+        ["Free", "0.5", "1", "2", "3", "3.5"][*self as usize]
+    }
+    /// The interval from the primary to the secondary oscillator, in
+    /// semitones (i.e. `12 * log2(ratio)`), or `None` if [OscRatio::Free]
+    pub fn interval(&self) -> Option<I8F24> {
+        const INTERVALS: [I8F24; 5] = [
+            I8F24::lit("-12"),
+            I8F24::lit("0"),
+            I8F24::lit("12"),
+            I8F24::lit("19.019550008653873"),
+            I8F24::lit("21.688259064691248"),
+        ];
+        match self {
+            Self::Free => None,
+            _ => Some(INTERVALS[*self as usize - 1]),
+        }
+    }
+}
+
+impl From<OscRatio> for &'static str {
+    fn from(value: OscRatio) -> Self {
+        value.to_str()
+    }
+}
+
+impl TryFrom<u8> for OscRatio {
+    type Error = &'static str;
+    fn try_from(value: u8) -> Result<Self, &'static str> {
+        Self::ELEM
+            .get(value as usize)
+            .copied()
+            .ok_or("Conversion of u8 to OscRatio Overflowed")
+    }
+}
 /// The output of an oscillator.
+#[must_use]
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct OscOutput<T: DspFormatBase> {
     /// The sine wave output
     pub sin: T::Sample,
@@ -56,6 +148,7 @@ pub struct OscOutput<T: DspFormatBase> {
 }
 
 /// Output from [SyncedOscs]
+#[must_use]
 #[derive(Clone, Default)]
 pub struct SyncedOscsOutput<T: DspFormatBase> {
     /// Output from the primary oscillator
@@ -74,6 +167,16 @@ pub struct SyncedOscsOutput<T: DspFormatBase> {
 /// positive and negative phase portions of the waveform while maintaining the
 /// same overall fundamental frequency.
 ///
+/// The triangle output can be morphed into a sawtooth via the `morph`
+/// parameter (see [OscParams::morph]), which skews the ratio between its
+/// rising and falling edges.  The corners of the morphed wave are
+/// band-limited using PolyBLAMP.
+///
+/// When a secondary oscillator is reset by sync (see [SyncedOscs]), the
+/// resulting discontinuity in each waveform is band-limited using PolyBLEP,
+/// placed at the fractional sample position where the primary oscillator
+/// wrapped around.
+///
 /// This device returns each individual waveform as a separate output.  For
 /// convenience, devices are provided that premix these waveforms into a single
 /// output with parameterized gains (see [MixOsc] and [SyncedMixOscs]).
@@ -82,8 +185,23 @@ pub struct SyncedOscsOutput<T: DspFormatBase> {
 /// parameters and returning a [OscOutput], which contains all of the output
 /// waveforms from the oscillator.
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct Osc<T: DspFormat> {
     phase: T::Phase,
+    // The change in phase over the last sample, used for band-limiting
+    dphase: T::Phase,
+    // The second half of the PolyBLEP residual for a sync reset in the last
+    // sample, to be applied to the next output
+    sync_residual: Option<OscOutput<T>>,
+    // The phase that reset_phase() returns the oscillator to
+    initial_phase: T::Phase,
+    // The slopes of the morphed triangle wave, updated when the morph changes
+    #[cfg_attr(feature = "serde", serde(skip))]
+    tri_morph: T::TriMorph,
 }
 
 impl<T: DspFormat> Osc<T> {
@@ -91,20 +209,98 @@ impl<T: DspFormat> Osc<T> {
     pub fn new() -> Self {
         Self {
             phase: T::Phase::zero(),
+            dphase: T::Phase::zero(),
+            sync_residual: None,
+            initial_phase: T::Phase::zero(),
+            tri_morph: Default::default(),
         }
     }
+    /// Reset the oscillator to its initial phase (zero unless set with
+    /// [Osc::set_initial_phase]), so that the next output starts a new cycle
+    /// (e.g. for an identical attack on every note).  The reset is not
+    /// band-limited.
+    pub fn reset_phase(&mut self) {
+        self.phase = self.initial_phase;
+        self.sync_residual = None;
+    }
+    /// Set the phase that [Osc::reset_phase] resets the oscillator to, as a
+    /// fraction of a cycle from 0 to 1 (so 0.25 starts a sine wave at its
+    /// peak).  This does not affect the current phase.
+    pub fn set_initial_phase(&mut self, phase: T::Scalar) {
+        let mut phase = T::Phase::TAU.scale(phase);
+        if phase >= T::Phase::PI {
+            phase = phase - T::Phase::TAU;
+        }
+        self.initial_phase = phase;
+    }
     fn next_with_sync(
         &mut self,
         context: &T::Context,
         note: T::Note,
         params: OscParams<T>,
         mut sync: OscSync<T>,
+        sync_blep: bool,
     ) -> (OscOutput<T>, OscSync<T>) {
         let freq = T::note_to_freq(T::apply_note_offset(note, params.tune));
-        let out = T::calc_waveforms(self.phase);
-        (self.phase, sync) = T::advance_phase(context, freq, self.phase, params.shape, sync);
+        T::set_tri_morph(&mut self.tri_morph, params.morph);
+        let mut out = T::calc_waveforms(self.phase, &self.tri_morph, self.dphase);
+        if let Some(residual) = self.sync_residual.take() {
+            out = add_outputs(out, residual);
+        }
+        if let (OscSync::Secondary(frac), true) = (sync, sync_blep) {
+            out = self.blep_sync_reset(out, frac);
+        }
+        (self.phase, sync, self.dphase) =
+            T::advance_phase(context, freq, self.phase, params.shape, sync);
         (out, sync)
     }
+    // The primary oscillator wrapped around `frac` of a sample before the next
+    // sample, so this oscillator will be reset to zero phase `1 - frac` of a
+    // sample after the current one.  Add the PolyBLEP residual for the
+    // resulting step to the current output, and save the residual for the
+    // next output.
+    fn blep_sync_reset(&mut self, out: OscOutput<T>, frac: T::Scalar) -> OscOutput<T> {
+        let until_reset = T::Scalar::one() - frac;
+        // Estimate the phase that would have been reached without the reset:
+        let mut phase = self.phase + self.dphase.scale(until_reset);
+        if phase >= T::Phase::PI {
+            phase = phase - T::Phase::TAU;
+        }
+        let before = T::calc_waveforms(phase, &self.tri_morph, self.dphase);
+        let after = T::calc_waveforms(T::Phase::zero(), &self.tri_morph, self.dphase);
+        let pre = frac.scale(frac);
+        let post = until_reset.scale(until_reset);
+        // For a unit step, the residual is frac^2/2 before the step and
+        // -(1-frac)^2/2 after it:
+        let blep = |before: T::Sample, after: T::Sample, smp: T::Sample| {
+            let step = after - before;
+            let residual = T::Sample::zero() - step.scale(post).divide_by_two();
+            (
+                smp.dsp_saturating_add(step.scale(pre).divide_by_two()),
+                residual,
+            )
+        };
+        let (sin, next_sin) = blep(before.sin, after.sin, out.sin);
+        let (sq, next_sq) = blep(before.sq, after.sq, out.sq);
+        let (tri, next_tri) = blep(before.tri, after.tri, out.tri);
+        let (saw, next_saw) = blep(before.saw, after.saw, out.saw);
+        self.sync_residual = Some(OscOutput {
+            sin: next_sin,
+            sq: next_sq,
+            tri: next_tri,
+            saw: next_saw,
+        });
+        OscOutput { sin, sq, tri, saw }
+    }
+}
+
+fn add_outputs<T: DspFormatBase>(a: OscOutput<T>, b: OscOutput<T>) -> OscOutput<T> {
+    OscOutput {
+        sin: a.sin.dsp_saturating_add(b.sin),
+        sq: a.sq.dsp_saturating_add(b.sq),
+        tri: a.tri.dsp_saturating_add(b.tri),
+        saw: a.saw.dsp_saturating_add(b.saw),
+    }
 }
 
 impl<T: DspFormat> Device<T> for Osc<T> {
@@ -112,7 +308,7 @@ impl<T: DspFormat> Device<T> for Osc<T> {
     type Params = OscParams<T>;
     type Output = OscOutput<T>;
     fn next(&mut self, context: &T::Context, note: T::Note, params: OscParams<T>) -> Self::Output {
-        let (out, _) = self.next_with_sync(context, note, params, OscSync::Off);
+        let (out, _) = self.next_with_sync(context, note, params, OscSync::Off, false);
         out
     }
 }
@@ -123,10 +319,20 @@ impl<T: DspFormat> Device<T> for Osc<T> {
 /// This implements [Device], taking a Note as input and a [SyncedOscsParams]
 /// as parameters.  It outputs a [SyncedOscsOutput], which contains the output
 /// signals from both underlying oscillators.
+///
+/// The discontinuities caused by sync are band-limited by default (see
+/// [SyncedOscs::set_sync_blep]).
 #[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
 pub struct SyncedOscs<T: DspFormat> {
     primary: Osc<T>,
     secondary: Osc<T>,
+    // Inverted so that band-limiting is enabled by default
+    naive_sync: bool,
 }
 
 impl<T: DspFormat> SyncedOscs<T> {
@@ -134,6 +340,26 @@ impl<T: DspFormat> SyncedOscs<T> {
     pub fn new() -> Self {
         Default::default()
     }
+    /// Enable or disable band-limiting (PolyBLEP) of the secondary
+    /// oscillator's phase reset when sync is enabled
+    pub fn set_sync_blep(&mut self, sync_blep: bool) {
+        self.naive_sync = !sync_blep;
+    }
+    /// Is the secondary oscillator's phase reset band-limited?
+    pub fn sync_blep(&self) -> bool {
+        !self.naive_sync
+    }
+    /// Reset both oscillators to their initial phase (see [Osc::reset_phase])
+    pub fn reset_phase(&mut self) {
+        self.primary.reset_phase();
+        self.secondary.reset_phase();
+    }
+    /// Set the initial phase of both oscillators (see
+    /// [Osc::set_initial_phase])
+    pub fn set_initial_phase(&mut self, phase: T::Scalar) {
+        self.primary.set_initial_phase(phase);
+        self.secondary.set_initial_phase(phase);
+    }
 }
 
 impl<T: DspFormat> Device<T> for SyncedOscs<T> {
@@ -151,8 +377,14 @@ impl<T: DspFormat> Device<T> for SyncedOscs<T> {
         } else {
             OscSync::<T>::Off
         };
-        let (pri_out, sync) = self.primary.next_with_sync(context, note, params.primary, sync);
-        let (sec_out, _) = self.secondary.next_with_sync(context, note, params.secondary, sync);
+        let blep = self.sync_blep();
+        let mut secondary = params.secondary;
+        if let Some(interval) = T::ratio_interval(params.ratio) {
+            secondary.tune = params.primary.tune.dsp_saturating_add(interval);
+        }
+        let (pri_out, sync) =
+            self.primary.next_with_sync(context, note, params.primary, sync, blep);
+        let (sec_out, _) = self.secondary.next_with_sync(context, note, secondary, sync, blep);
         SyncedOscsOutput {
             primary: pri_out,
             secondary: sec_out,
@@ -162,6 +394,8 @@ impl<T: DspFormat> Device<T> for SyncedOscs<T> {
 
 pub(crate) mod detail {
     use super::*;
+    #[cfg(feature = "fixed")]
+    use crate::fixedmath::U1F15;
 
     #[derive(PartialEq, Clone, Copy)]
     pub enum OscSync<T: DspFormatBase> {
@@ -174,16 +408,68 @@ pub(crate) mod detail {
         Secondary(T::Scalar),
     }
 
-    pub trait OscOps: crate::DspFormatBase {
+    /// The parts of the fixed point triangle/sawtooth morph that only change
+    /// with the morph parameter (see `tri_morph_fixed()`)
+    #[cfg(feature = "fixed")]
+    #[derive(Clone, Copy)]
+    pub struct TriMorphFxP {
+        /// The morph parameter these were calculated from
+        pub morph: ScalarFxP,
+        /// The peak of the wave, p = 1 + morph
+        pub peak: PhaseFxP,
+        /// The slope of the rising edge, 1/p
+        pub rise: U1F15,
+        /// The (negated) slope of the falling edge, 1/(2 - p), is
+        /// `fall * 2^fall_shift`
+        pub fall: U1F15,
+        pub fall_shift: u32,
+    }
+
+    #[cfg(feature = "fixed")]
+    impl TriMorphFxP {
+        pub fn new(morph: ScalarFxP) -> Self {
+            const ONE: PhaseFxP = PhaseFxP::lit("1");
+            const TWO: PhaseFxP = PhaseFxP::lit("2");
+            let peak = ONE + PhaseFxP::from_num(morph);
+            // Normalize 2 - p (which is in (0, 1]) to [1, 2)
+            let fall_len = TWO - peak;
+            let fall_shift = fall_len.leading_zeros() - ONE.leading_zeros();
+            Self {
+                morph,
+                peak,
+                rise: U1F15::from_num(ONE / peak),
+                fall: U1F15::from_num(ONE / fall_len.unwrapped_shl(fall_shift)),
+                fall_shift,
+            }
+        }
+    }
+
+    #[cfg(feature = "fixed")]
+    impl Default for TriMorphFxP {
+        fn default() -> Self {
+            Self::new(ScalarFxP::ZERO)
+        }
+    }
+
+    pub trait OscOps: crate::DspFormatContext {
         const FRAC_2_PI: Self::Scalar;
+        /// Anything precalculated from the morph parameter
+        type TriMorph: Clone + Default;
+        /// Update `tri_morph` if `morph` has changed
+        fn set_tri_morph(tri_morph: &mut Self::TriMorph, morph: Self::Scalar);
         fn advance_phase(
             context: &Self::Context,
             freq: Self::Frequency,
             phase: Self::Phase,
             shape: Self::Scalar,
             sync: OscSync<Self>,
-        ) -> (Self::Phase, OscSync<Self>);
-        fn calc_waveforms(phase: Self::Phase) -> OscOutput<Self>;
+        ) -> (Self::Phase, OscSync<Self>, Self::Phase);
+        fn calc_waveforms(
+            phase: Self::Phase,
+            tri_morph: &Self::TriMorph,
+            dphase: Self::Phase,
+        ) -> OscOutput<Self>;
+        fn ratio_interval(ratio: OscRatio) -> Option<Self::NoteOffset>;
     }
 }
 
@@ -193,7 +479,15 @@ use detail::OscSync;
 
 impl<T: DspFloat> detail::OscOps for T {
     const FRAC_2_PI: T = <T as Float>::FRAC_2_PI;
-    fn calc_waveforms(phase: Self::Phase) -> OscOutput<Self> {
+    type TriMorph = T;
+    fn set_tri_morph(tri_morph: &mut T, morph: T) {
+        *tri_morph = morph;
+    }
+    fn ratio_interval(ratio: OscRatio) -> Option<T> {
+        ratio.interval().map(T::from_fixed)
+    }
+    fn calc_waveforms(phase: Self::Phase, morph: &T, dphase: T) -> OscOutput<Self> {
+        let morph = *morph;
         let mut out = osc::OscOutput::<T>::default();
         //generate waveforms (piecewise defined)
         let frac_2phase_pi = phase * <Self as detail::OscOps>::FRAC_2_PI;
@@ -225,6 +519,12 @@ impl<T: DspFloat> detail::OscOps for T {
                 out.tri = T::TWO - frac_2phase_pi;
             }
         }
+        if morph >= T::ONE {
+            out.tri = out.saw;
+        } else if morph > T::ZERO {
+            let df = dphase * <Self as detail::OscOps>::FRAC_2_PI;
+            out.tri = tri_morph(frac_2phase_pi, morph, df);
+        }
         out
     }
     fn advance_phase(
@@ -233,7 +533,7 @@ impl<T: DspFloat> detail::OscOps for T {
         mut phase: Self::Phase,
         shape: Self::Scalar,
         sync: OscSync<T>,
-    ) -> (Self::Phase, OscSync<T>) {
+    ) -> (Self::Phase, OscSync<T>, Self::Phase) {
         let phase_per_sample = freq * T::TAU / ctx.sample_rate;
         let mut sync_out = osc::OscSync::<T>::Off;
         let shp = if shape < T::SHAPE_CLIP {
@@ -286,13 +586,27 @@ impl<T: DspFloat> detail::OscOps for T {
                 phase = delta - T::PI;
             }
         }
-        (phase, sync_out)
+        (phase, sync_out, phase_per_smp_adj)
     }
 }
 
+#[cfg(feature = "fixed")]
 impl detail::OscOps for i16 {
     const FRAC_2_PI: ScalarFxP = ScalarFxP::lit("0x0.a2fa");
-    fn calc_waveforms(phase: Self::Phase) -> OscOutput<Self> {
+    type TriMorph = detail::TriMorphFxP;
+    fn set_tri_morph(tri_morph: &mut detail::TriMorphFxP, morph: ScalarFxP) {
+        if tri_morph.morph != morph {
+            *tri_morph = detail::TriMorphFxP::new(morph);
+        }
+    }
+    fn ratio_interval(ratio: OscRatio) -> Option<SignedNoteFxP> {
+        ratio.interval().map(SignedNoteFxP::from_num)
+    }
+    fn calc_waveforms(
+        phase: PhaseFxP,
+        tri_morph: &detail::TriMorphFxP,
+        dphase: PhaseFxP,
+    ) -> OscOutput<Self> {
         use crate::fixed_traits::Fixed16;
         use fixedmath::{cos_fixed, sin_fixed};
         const TWO: SampleFxP = SampleFxP::lit("2");
@@ -329,6 +643,12 @@ impl detail::OscOps for i16 {
                 ret.tri = TWO - frac_2phase_pi;
             }
         }
+        if tri_morph.morph == ScalarFxP::MAX {
+            ret.tri = ret.saw;
+        } else if tri_morph.morph != ScalarFxP::ZERO {
+            let frac_2_pi = PhaseFxP::from_num(Self::FRAC_2_PI);
+            ret.tri = tri_morph_fixed(phase * frac_2_pi, tri_morph, dphase * frac_2_pi);
+        }
         ret
     }
     fn advance_phase(
@@ -337,7 +657,7 @@ impl detail::OscOps for i16 {
         mut phase: PhaseFxP,
         shape: ScalarFxP,
         sync: OscSync<i16>,
-    ) -> (PhaseFxP, OscSync<i16>) {
+    ) -> (PhaseFxP, OscSync<i16>, PhaseFxP) {
         // perform shape clipping:
         let shape = ShapeFxP::new(shape);
         let mut sync_out = OscSync::<i16>::Off;
@@ -409,14 +729,115 @@ impl detail::OscOps for i16 {
                 phase = (-PhaseFxP::PI).add_unsigned(delta);
             }
         }
-        (phase, sync_out)
+        (phase, sync_out, phase_per_smp_adj)
+    }
+}
+
+// The triangle/sawtooth morph is defined over x = 2*phase/pi, in [-2, 2).  The
+// wave rises from -1 at x = -p to 1 at x = p, where p = 1 + morph, and falls
+// for the rest of the cycle.  This is a triangle when p = 1, and approaches a
+// sawtooth as p approaches 2.  Each corner is a change in slope, so we add a
+// PolyBLAMP residual to the naive wave within one sample (dx) of each corner.
+fn tri_morph<T: DspFloat>(x: T, morph: T, dx: T) -> T {
+    let four = T::TWO + T::TWO;
+    let p = T::ONE + morph;
+    let fall = T::TWO - p;
+    let naive = if x > p {
+        (T::TWO - x) / fall
+    } else if x < -p {
+        (-T::TWO - x) / fall
+    } else {
+        x / p
+    };
+    if dx <= T::ZERO {
+        return naive;
+    }
+    // Distance from a corner, wrapped around the cycle, in samples
+    let dist = |d: T| {
+        let d = if d >= T::TWO {
+            d - four
+        } else if d < -T::TWO {
+            d + four
+        } else {
+            d
+        };
+        (d / dx).abs()
+    };
+    let blamp = |t: T| {
+        if t < T::ONE {
+            let u = T::ONE - t;
+            u * u * u / T::from_u16(6)
+        } else {
+            T::ZERO
+        }
+    };
+    // The slope changes by 2/(p*fall) at each corner (up at the trough and
+    // down at the peak), which is dx * 2/(p*fall) per sample:
+    let corr = (blamp(dist(x + p)) - blamp(dist(x - p))) * T::TWO * dx / (p * fall);
+    (naive + corr).max(-T::ONE).min(T::ONE)
+}
+
+// Fixed point version of tri_morph().  Everything is calculated as a PhaseFxP
+// for the extra precision (dx is only a few LSBs of a SampleFxP at low notes).
+// The slopes are precalculated (see TriMorphFxP), so away from the corners this
+// only takes 16x16->32 bit multiplies, and dx is only divided by within a
+// sample of a corner.
+#[cfg(feature = "fixed")]
+fn tri_morph_fixed(x: PhaseFxP, tri_morph: &detail::TriMorphFxP, dx: PhaseFxP) -> SampleFxP {
+    use crate::fixedmath::scale_shr_round;
+    const ONE: PhaseFxP = PhaseFxP::lit("1");
+    const TWO: PhaseFxP = PhaseFxP::lit("2");
+    const FOUR: PhaseFxP = PhaseFxP::lit("4");
+    let p = tri_morph.peak;
+    // Multiply by the falling slope.  The (saturated) result must be less
+    // than 4, so the shift can't overflow:
+    let fall = |d: PhaseFxP| {
+        let limit = PhaseFxP::MAX.unwrapped_shr(tri_morph.fall_shift + 1);
+        let d = d.clamp(-limit, limit).unwrapped_shl(tri_morph.fall_shift);
+        scale_shr_round(d, tri_morph.fall, 0)
+    };
+    let naive = if x > p {
+        fall(TWO - x)
+    } else if x < -p {
+        fall(-TWO - x)
+    } else {
+        scale_shr_round(x, tri_morph.rise, 0)
+    };
+    // The PolyBLAMP residual for a corner d away, wrapped around the cycle
+    let blamp = |d: PhaseFxP| {
+        let d = if d >= TWO {
+            d - FOUR
+        } else if d < -TWO {
+            d + FOUR
+        } else {
+            d
+        };
+        if d.abs() < dx {
+            let u = ONE - d.abs() / dx;
+            u * u * u / 6
+        } else {
+            PhaseFxP::ZERO
+        }
+    };
+    let blamps = blamp(x + p) - blamp(x - p);
+    if blamps == PhaseFxP::ZERO {
+        return SampleFxP::from_num(naive.clamp(-ONE, ONE));
     }
+    // The slope changes by 2/(p*fall) at each corner, or 2 * rise * fall
+    let corr = fall(scale_shr_round(
+        (blamps * 2).saturating_mul(dx),
+        tri_morph.rise,
+        0,
+    ));
+    SampleFxP::from_num(naive.saturating_add(corr).clamp(-ONE, ONE))
 }
 
 // Newtype around ScalarFxP with the invariant that clip_shape() was called
+#[cfg(feature = "fixed")]
 #[derive(Default, Clone, Copy)]
 struct ShapeFxP(ScalarFxP);
 
+#[cfg(feature = "fixed")]
 impl ShapeFxP {
     pub const fn new(value: ScalarFxP) -> Self {
         Self(clip_shape(value))
@@ -426,6 +847,7 @@ impl ShapeFxP {
     }
 }
 
+#[cfg(feature = "fixed")]
 impl core::ops::Deref for ShapeFxP {
     type Target = ScalarFxP;
     fn deref(&self) -> &Self::Target {
@@ -433,6 +855,7 @@ impl core::ops::Deref for ShapeFxP {
     }
 }
 
+#[cfg(feature = "fixed")]
 const fn clip_shape(x: ScalarFxP) -> ScalarFxP {
     const CLIP_MAX: ScalarFxP = ScalarFxP::lit("0x0.F");
     // equivalent to x > CLIP_MAX, but callable in const context
@@ -443,6 +866,7 @@ const fn clip_shape(x: ScalarFxP) -> ScalarFxP {
     }
 }
 
+#[cfg(feature = "fixed")]
 fn inverse(x: ScalarFxP) -> crate::fixedmath::U8F8 {
     // For brevity in defining the lookup table:
     const fn lit(x: &str) -> crate::fixedmath::U8F8 {
@@ -518,6 +942,7 @@ fn inverse(x: ScalarFxP) -> crate::fixedmath::U8F8 {
     LOOKUP_TABLE[(x.to_bits() >> 8) as usize]
 }
 
+#[cfg(feature = "fixed")]
 fn one_over_one_minus_x(x: ShapeFxP) -> crate::fixedmath::USample {
     // For brevity in defining the lookup table:
     const fn lit(x: &str) -> crate::fixedmath::USample {
@@ -0,0 +1,120 @@
+use super::*;
+use crate::fixedmath::{I3F13, U1F31};
+#[cfg(feature = "fixed")]
+use crate::fixedmath::{I16F16, U1F15};
+#[cfg(feature = "fixed")]
+use crate::IScalarFxP;
+
+/// The range of the master gain, in decibels: a gain parameter of -1 gives
+/// an attenuation of this many dB, and +1 gives a boost of this many dB.
+pub const PAN_GAIN_RANGE_DB: u16 = 12;
+
+/// `ln(10) * PAN_GAIN_RANGE_DB / 20`, to calculate `10^(x*range/20)` as `e^(x*k)`
+const GAIN_EXP_COEFF: I3F13 = I3F13::lit("1.381551");
+
+/// `sqrt(2)`, the gain that normalizes the pan law to unity at the center
+const PAN_NORM: U1F31 = U1F31::SQRT_2;
+#[cfg(feature = "fixed")]
+const PAN_NORM_FXP: U1F15 = U1F15::SQRT_2;
+
+pub(crate) mod detail {
+    use super::*;
+    pub trait PanOps: DspFormatContext {
+        fn calc_pan(
+            signal: Self::Sample,
+            gain: Self::IScalar,
+            pan: Self::IScalar,
+        ) -> PanOutput<Self>;
+    }
+}
+
+/// Parameters for a [Pan]
+#[derive(Clone, Default)]
+pub struct PanParams<T: DspFormatBase> {
+    /// The gain, from -1 to 1, scaled to +/- [PAN_GAIN_RANGE_DB] decibels.
+    /// A value of zero is unity gain.
+    pub gain: T::IScalar,
+    /// The pan position, from -1 (hard left) to 1 (hard right)
+    pub pan: T::IScalar,
+}
+
+impl<T: DspFloat> From<&PanParams<i16>> for PanParams<T> {
+    fn from(value: &PanParams<i16>) -> Self {
+        Self {
+            gain: value.gain.to_num(),
+            pan: value.pan.to_num(),
+        }
+    }
+}
+
+/// Output of a [Pan]
+#[must_use]
+#[derive(Clone, Default)]
+pub struct PanOutput<T: DspFormatBase> {
+    /// The left channel
+    pub left: T::Sample,
+    /// The right channel
+    pub right: T::Sample,
+}
+
+/// A master gain stage and constant-power panner
+///
+/// This converts a mono signal into a stereo pair.  The gain parameter is
+/// logarithmic (see [PanParams]).  The panner uses a sin/cos pan law, which
+/// keeps the total power constant across the stereo field.  It is
+/// normalized so that a centered signal passes through to each channel at
+/// unity gain, the same as an unpanned voice, and panning hard to one side
+/// boosts that channel by 3dB.
+///
+/// This implements [Device], taking a Sample as input and [PanParams] as
+/// parameters, and outputting a [PanOutput].
+#[derive(Clone, Default)]
+pub struct Pan<T: DspFormat> {
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: DspFormat> Pan<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<T: DspFormat> Device<T> for Pan<T> {
+    type Input = T::Sample;
+    type Params = PanParams<T>;
+    type Output = PanOutput<T>;
+    fn next(&mut self, _: &T::Context, signal: T::Sample, params: PanParams<T>) -> PanOutput<T> {
+        T::calc_pan(signal, params.gain, params.pan)
+    }
+}
+
+impl<T: DspFloat> detail::PanOps for T {
+    fn calc_pan(signal: T, gain: T, pan: T) -> PanOutput<T> {
+        let gain = (gain * GAIN_EXP_COEFF.to_num::<T>()).fexp();
+        let theta = (pan + T::ONE) * T::FRAC_PI_2 * T::ONE_HALF;
+        let smp = signal * gain * PAN_NORM.to_num::<T>();
+        PanOutput {
+            left: smp * theta.fcos(),
+            right: smp * theta.fsin(),
+        }
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl detail::PanOps for i16 {
+    fn calc_pan(signal: SampleFxP, gain: IScalarFxP, pan: IScalarFxP) -> PanOutput<i16> {
+        use crate::fixedmath::{cos_fixed, exp_fixed, sin_fixed};
+        const FRAC_PI_2: U1F15 = U1F15::lit("1.570796");
+        let gain = exp_fixed(I3F13::from_num(gain.wide_mul(GAIN_EXP_COEFF)));
+        // (pan + 1) / 2 as a U0F16 has the same bits as pan + 1 as a U1F15
+        let pos = ScalarFxP::from_bits((pan.to_bits() as u16) ^ 0x8000);
+        let theta = SampleFxP::from_num(pos.wide_mul(FRAC_PI_2));
+        let smp = I16F16::from_num(signal) * I16F16::from_num(gain);
+        let norm = |x: SampleFxP| I16F16::from_num(x.wide_mul_unsigned(PAN_NORM_FXP));
+        PanOutput {
+            left: SampleFxP::saturating_from_num(smp * norm(cos_fixed(theta))),
+            right: SampleFxP::saturating_from_num(smp * norm(sin_fixed(theta))),
+        }
+    }
+}
@@ -0,0 +1,43 @@
+use super::*;
+
+/// Detects the start of a note (a rising edge on a gate signal), so that
+/// oscillators can be reset to their initial phase at exactly that sample
+/// (see [Osc::reset_phase]).
+///
+/// This implements [Device], taking the gate as input and outputting true
+/// for the first sample of each note (i.e. when the gate goes from false to
+/// true).  It takes no parameters.
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct PhaseReset<T: DspFormat> {
+    last_gate: bool,
+    triggered: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: DspFormat> PhaseReset<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Did the gate open on the last sample passed to [Device::next]?
+    pub fn triggered(&self) -> bool {
+        self.triggered
+    }
+}
+
+impl<T: DspFormat> Device<T> for PhaseReset<T> {
+    type Input = bool;
+    type Params = ();
+    type Output = bool;
+    fn next(&mut self, _: &T::Context, gate: bool, _: ()) -> bool {
+        self.triggered = gate && !self.last_gate;
+        self.last_gate = gate;
+        self.triggered
+    }
+}
@@ -0,0 +1,85 @@
+use super::*;
+use crate::context::GenericContext;
+
+/// Parameters for a [Resampler]
+#[derive(Clone, Default)]
+pub struct ResamplerParams<T: DspFormatBase> {
+    /// Parameters for the inner oscillator
+    pub osc: OscParams<T>,
+    /// The rate at which the oscillator output is sampled, in Hz.  This is
+    /// clamped between 1Hz and the sample rate of the context.
+    pub target_rate: u16,
+}
+
+impl<T: DspFloat> From<&ResamplerParams<i16>> for ResamplerParams<T> {
+    fn from(value: &ResamplerParams<i16>) -> Self {
+        Self {
+            osc: (&value.osc).into(),
+            target_rate: value.target_rate,
+        }
+    }
+}
+
+/// An [Osc] running at a lower effective sample rate, for lo-fi ("bit
+/// crusher") effects.
+///
+/// The output of the oscillator is updated `target_rate` times per second,
+/// and each value is repeated (a zero-order hold) until the next update, so
+/// a low target rate aliases heavily.  When the target rate does not divide
+/// the sample rate, updates are spaced as evenly as possible (e.g. an 8kHz
+/// target at 44.1kHz alternates between holding for 5 and 6 samples).
+///
+/// The inner oscillator is still advanced on every sample, so its pitch is
+/// unaffected by the target rate, and fixed point contexts (which only
+/// support a few sample rates) work the same way as floating point.
+///
+/// This implements [Device], taking a Note as input and [ResamplerParams]
+/// as parameters, and outputting the held [OscOutput].
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct Resampler<T: DspFormat> {
+    osc: Osc<T>,
+    held: OscOutput<T>,
+    // The time until the next update, in units of 1/(sample_rate*target_rate)
+    // seconds.  The output is updated when this is less than the target rate.
+    clock: u32,
+}
+
+impl<T: DspFormat> Resampler<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Reset the inner oscillator to its initial phase (see
+    /// [Osc::reset_phase]) and update the output on the next sample
+    pub fn reset_phase(&mut self) {
+        self.osc.reset_phase();
+        self.clock = 0;
+    }
+}
+
+impl<T: DspFormat> Device<T> for Resampler<T> {
+    type Input = T::Note;
+    type Params = ResamplerParams<T>;
+    type Output = OscOutput<T>;
+    fn next(
+        &mut self,
+        context: &T::Context,
+        note: T::Note,
+        params: ResamplerParams<T>,
+    ) -> Self::Output {
+        let sample_rate = context.sample_rate();
+        let target_rate = (params.target_rate as u32).min(sample_rate).max(1);
+        let out = self.osc.next(context, note, params.osc);
+        if self.clock < target_rate {
+            self.held = out;
+            self.clock += sample_rate;
+        }
+        self.clock = self.clock.saturating_sub(target_rate);
+        self.held.clone()
+    }
+}
@@ -0,0 +1,49 @@
+use core::mem::transmute;
+
+/// How a modulator (an [Lfo](super::Lfo) or [Env](super::Env)) responds to
+/// a new note (a rising edge on its gate)
+#[derive(Default, Clone, Copy, Eq, PartialEq, Debug)]
+#[repr(u8)]
+pub enum ResetMode {
+    /// Keep running without resetting.  Envelopes always follow their gate,
+    /// so for an envelope this is the same as [ResetMode::Soft].
+    None,
+    /// Restart from the beginning, continuing smoothly from the current
+    /// output (an LFO restarts its phase, and an envelope attacks from its
+    /// current level)
+    #[default]
+    Soft,
+    /// Restart from exactly the same state every time, so every note has an
+    /// identical transient (an LFO also restarts its random sequence, and an
+    /// envelope attacks from zero)
+    Hard,
+}
+
+impl ResetMode {
+    const ELEM: [ResetMode; 3] = [Self::None, Self::Soft, Self::Hard];
+    /// Returns a slice to all of the possible ResetModes
+    pub const fn modes() -> &'static [ResetMode] {
+        &Self::ELEM
+    }
+    /// Provides the name of the reset mode
+    pub const fn to_str(&self) -> &'static str {
+        ["None", "Soft", "Hard"][*self as usize]
+    }
+}
+
+impl From<ResetMode> for &'static str {
+    fn from(value: ResetMode) -> Self {
+        value.to_str()
+    }
+}
+
+impl TryFrom<u8> for ResetMode {
+    type Error = &'static str;
+    fn try_from(value: u8) -> Result<Self, &'static str> {
+        if value <= ResetMode::Hard as u8 {
+            unsafe { Ok(transmute::<u8, ResetMode>(value)) }
+        } else {
+            Err("Conversion of u8 to ResetMode Overflowed")
+        }
+    }
+}
@@ -37,6 +37,10 @@ impl<T: DspFloat> From<&RingModParams<i16>> for RingModParams<T> {
 /// This supports ring modulation of two signals and control over the mix of
 /// both the input signals and the modulation signal in the final device output
 ///
+/// The inputs and the ring product are summed with a wider type (see
+/// [Mixer]), and only saturated once at the output, so several full scale
+/// sources keep their relative balance.
+///
 /// This implements [Device], taking a [RingModInput] as input and
 /// [RingModParams] as parameters and outputting a Sample.
 #[derive(Clone, Default)]
@@ -0,0 +1,91 @@
+use super::*;
+#[cfg(feature = "fixed")]
+use crate::{IScalarFxP, NoteFxP, SignedNoteFxP};
+
+pub(crate) mod detail {
+    use super::*;
+    pub trait StretchOps: DspFormatContext {
+        /// The offset to apply to `note`, in semitones, for the given stretch
+        fn stretch_offset(note: Self::Note, params: &StretchTuningParams<Self>)
+            -> Self::NoteOffset;
+    }
+}
+
+/// Parameters for a [StretchTuning]
+#[derive(Clone)]
+pub struct StretchTuningParams<T: DspFormatBase> {
+    /// The amount each octave is widened, in semitones (so the range is 100
+    /// cents per octave either way).  Negative values narrow the octaves,
+    /// and zero is 12 tone equal temperament.
+    pub stretch: T::IScalar,
+    /// The note that is left in tune, as a MIDI note number
+    pub reference: T::Note,
+}
+
+impl<T: DspFormatBase> Default for StretchTuningParams<T> {
+    fn default() -> Self {
+        Self {
+            stretch: T::IScalar::zero(),
+            reference: T::default_note(),
+        }
+    }
+}
+
+impl<T: DspFloat> From<&StretchTuningParams<i16>> for StretchTuningParams<T> {
+    fn from(value: &StretchTuningParams<i16>) -> Self {
+        Self {
+            stretch: value.stretch.to_num(),
+            reference: value.reference.to_num(),
+        }
+    }
+}
+
+/// Stretch tuning, where octaves are widened (or narrowed) by a constant
+/// number of cents, as a piano is tuned.  Each octave away from the
+/// reference note moves the pitch by another [StretchTuningParams::stretch],
+/// so with a stretch of 5 cents the octave above the reference is 5 cents
+/// sharp, two octaves above is 10 cents sharp, and the octave below is 5
+/// cents flat.
+///
+/// This implements [Device], taking a note as input and
+/// [StretchTuningParams] as parameters, and outputting the stretched note.
+#[derive(Clone, Default)]
+pub struct StretchTuning<T: DspFormat> {
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: DspFormat> StretchTuning<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<T: DspFormat> Device<T> for StretchTuning<T> {
+    type Input = T::Note;
+    type Params = StretchTuningParams<T>;
+    type Output = T::Note;
+    fn next(&mut self, _: &T::Context, note: T::Note, params: StretchTuningParams<T>) -> T::Note {
+        T::apply_note_offset(note, T::stretch_offset(note, &params))
+    }
+}
+
+impl<T: DspFloat> detail::StretchOps for T {
+    fn stretch_offset(note: T, params: &StretchTuningParams<T>) -> T {
+        (note - params.reference) * params.stretch / T::from_u16(12)
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl detail::StretchOps for i16 {
+    fn stretch_offset(note: NoteFxP, params: &StretchTuningParams<i16>) -> SignedNoteFxP {
+        if params.stretch == IScalarFxP::ZERO {
+            return SignedNoteFxP::ZERO;
+        }
+        // NoteFxP and SignedNoteFxP have the same number of fractional bits,
+        // so this is the distance from the reference in SignedNoteFxP bits
+        let distance = note.to_bits() as i64 - params.reference.to_bits() as i64;
+        let offset = (distance * params.stretch.to_bits() as i64 / 12) >> IScalarFxP::FRAC_NBITS;
+        SignedNoteFxP::from_bits(offset.clamp(i16::MIN as i64, i16::MAX as i64) as i16)
+    }
+}
@@ -0,0 +1,146 @@
+use super::*;
+#[cfg(feature = "fixed")]
+use crate::context::GenericContext;
+#[cfg(feature = "fixed")]
+use crate::fixedmath::{cos_fixed, sin_fixed, I4F28};
+#[cfg(feature = "fixed")]
+use crate::{FrequencyFxP, SampleFxP};
+
+/// The phase of a [TestTone] at the start of the second quarter of a cycle,
+/// i.e. pi/2
+const QUARTER_CYCLE: u32 = 1 << 30;
+
+pub(crate) mod detail {
+    use super::*;
+    pub trait TestToneOps: DspFormatContext {
+        /// The phase increment per sample, where a full cycle is 2^32
+        fn phase_increment(context: &Self::Context, freq: Self::Frequency) -> u32;
+        /// Calculate level * sin(x), where x is between 0 and [QUARTER_CYCLE]
+        /// (pi/2), rounding to the nearest sample
+        fn quarter_sine(x: u32, level: Self::Scalar) -> Self::Sample;
+    }
+}
+
+/// Parameters for a [TestTone]
+#[derive(Clone, Default)]
+pub struct TestToneParams<T: DspFormatBase> {
+    /// The frequency of the tone, in Hz
+    pub freq: T::Frequency,
+    /// The peak level of the tone, as a linear gain (so 0.5 is about -6dBFS)
+    pub level: T::Scalar,
+}
+
+impl<T: DspFloat> From<&TestToneParams<i16>> for TestToneParams<T> {
+    fn from(value: &TestToneParams<i16>) -> Self {
+        Self {
+            freq: value.freq.to_num(),
+            level: value.level.to_num(),
+        }
+    }
+}
+
+/// A sine wave calibration tone at a precise frequency and level, for
+/// checking signal chains and levels.
+///
+/// This is deliberately independent of [Osc]: the phase is a 32 bit
+/// accumulator (so the frequency is accurate to about 10 microhertz at
+/// 48kHz), and the sine is calculated a quarter cycle at a time so that the
+/// peak of the waveform is exactly 1 before it is scaled by
+/// [TestToneParams::level].
+///
+/// In floating point, the level is exact (up to the accuracy of the sine
+/// approximation, unless the `libm` feature is enabled).  In fixed point,
+/// the output is quantized to the 12 fractional bits of a [SampleFxP], so
+/// the peak is within 1/8192 of the requested level (about 0.002dB at
+/// -6dBFS, or 0.01dB at -20dBFS).  The level saturates at [ScalarFxP::MAX]
+/// (about 0.0001dB below 0dBFS), and the frequency saturates at
+/// [FrequencyFxP::MAX] (just under 16.4kHz).
+///
+/// This implements [Device], taking no input and [TestToneParams] as
+/// parameters, and outputting the tone.
+#[derive(Clone, Default)]
+pub struct TestTone<T: DspFormat> {
+    phase: u32,
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: DspFormat> TestTone<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+    /// Restart the tone at zero phase
+    pub fn reset(&mut self) {
+        self.phase = 0;
+    }
+}
+
+impl<T: DspFormat> Device<T> for TestTone<T> {
+    type Input = ();
+    type Params = TestToneParams<T>;
+    type Output = T::Sample;
+    fn next(&mut self, context: &T::Context, _: (), params: TestToneParams<T>) -> T::Sample {
+        let phase = self.phase;
+        self.phase = phase.wrapping_add(T::phase_increment(context, params.freq));
+        let within = phase % QUARTER_CYCLE;
+        // sin(x + pi/2) = sin(pi/2 - x), and sin(x + pi) = -sin(x)
+        let (x, negative) = match phase / QUARTER_CYCLE {
+            0 => (within, false),
+            1 => (QUARTER_CYCLE - within, false),
+            2 => (within, true),
+            _ => (QUARTER_CYCLE - within, true),
+        };
+        let value = T::quarter_sine(x, params.level);
+        if negative {
+            T::Sample::zero() - value
+        } else {
+            value
+        }
+    }
+}
+
+impl<T: DspFloat> detail::TestToneOps for T {
+    fn phase_increment(context: &Context<T>, freq: T) -> u32 {
+        let freq: f64 = num_traits::cast(freq).unwrap_or_default();
+        let sample_rate: f64 = num_traits::cast(context.sample_rate).unwrap_or(1f64);
+        (freq / sample_rate * 4294967296f64 + 0.5f64) as u64 as u32
+    }
+    fn quarter_sine(x: u32, level: T) -> T {
+        // Use sin near zero and cos near pi/2, where each is most accurate
+        let (x, sin) = if x < QUARTER_CYCLE / 2 {
+            (x, true)
+        } else {
+            (QUARTER_CYCLE - x, false)
+        };
+        let angle = x as f64 * (core::f64::consts::FRAC_PI_2 / QUARTER_CYCLE as f64);
+        let angle: T = num_traits::cast(angle).unwrap_or_default();
+        level * if sin { angle.fsin() } else { angle.fcos() }
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl detail::TestToneOps for i16 {
+    fn phase_increment(context: &ContextFxP, freq: FrequencyFxP) -> u32 {
+        // freq * 2^32 / sample_rate, where freq has 18 fractional bits
+        let inc = ((freq.to_bits() as u64) << (32 - FrequencyFxP::FRAC_NBITS))
+            + context.sample_rate() as u64 / 2;
+        (inc / context.sample_rate() as u64) as u32
+    }
+    fn quarter_sine(x: u32, level: ScalarFxP) -> SampleFxP {
+        // Use sin near zero and cos near pi/2, where each is most accurate
+        let (x, sin) = if x < QUARTER_CYCLE / 2 {
+            (x, true)
+        } else {
+            (QUARTER_CYCLE - x, false)
+        };
+        let angle = I4F28::from_bits(((x as u64 * I4F28::FRAC_PI_2.to_bits() as u64) >> 30) as i32);
+        let value = if sin {
+            sin_fixed(SampleFxP::from_num(angle))
+        } else {
+            cos_fixed(SampleFxP::from_num(angle))
+        };
+        // Round rather than truncate, so the level is as exact as possible
+        let bits = value.to_bits() as i32 * level.to_bits() as i32 + (1 << 15);
+        SampleFxP::from_bits((bits >> ScalarFxP::FRAC_NBITS) as i16)
+    }
+}
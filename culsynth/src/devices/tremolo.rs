@@ -0,0 +1,68 @@
+use super::*;
+
+/// Params for a [Tremolo]
+#[derive(Clone, Default)]
+pub struct TremoloParams<T: DspFormatBase> {
+    /// The rate of the tremolo, in Hz
+    pub rate: T::LfoFreq,
+    /// The depth of the tremolo, between 0 (no effect) and 1 (the gain
+    /// sweeps all the way from 0 to 1)
+    pub depth: T::Scalar,
+    /// The waveform of the gain modulation
+    pub wave: LfoWave,
+}
+
+impl<T: DspFloat> From<&TremoloParams<i16>> for TremoloParams<T> {
+    fn from(value: &TremoloParams<i16>) -> Self {
+        Self {
+            rate: value.rate.to_num(),
+            depth: value.depth.to_num(),
+            wave: value.wave,
+        }
+    }
+}
+
+/// A Tremolo effect
+///
+/// This is an [Amp] with its gain modulated by a free-running unipolar
+/// [Lfo].  The gain sweeps between `1 - depth` and 1, so a depth of zero
+/// leaves the signal unchanged and a depth of one sweeps the gain from
+/// silence to unity.
+///
+/// This implements [Device], taking a Sample as input and [TremoloParams]
+/// as parameters, and outputting a Sample.
+#[derive(Clone, Default)]
+pub struct Tremolo<T: DspFormat> {
+    lfo: Lfo<T>,
+    amp: Amp<T>,
+}
+
+impl<T: DspFormat> Tremolo<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<T: DspFormat> Device<T> for Tremolo<T> {
+    type Input = T::Sample;
+    type Params = TremoloParams<T>;
+    type Output = T::Sample;
+    fn next(
+        &mut self,
+        context: &T::Context,
+        signal: T::Sample,
+        params: TremoloParams<T>,
+    ) -> T::Sample {
+        let lfo_params = LfoParams {
+            freq: params.rate,
+            depth: params.depth,
+            opts: LfoOptions::new(params.wave, false, false),
+            slew: T::EnvParam::zero(),
+        };
+        // The unipolar LFO output is between zero and the depth
+        let sweep = T::scalar_from_sample(self.lfo.next(context, false, lfo_params));
+        let gain = (T::Scalar::one() - params.depth).dsp_saturating_add(sweep);
+        self.amp.next(context, signal, gain)
+    }
+}
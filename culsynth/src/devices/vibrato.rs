@@ -0,0 +1,55 @@
+use super::*;
+
+/// Params for a [Vibrato]
+#[derive(Clone, Default)]
+pub struct VibratoParams<T: DspFormatBase> {
+    /// The rate of the vibrato, in Hz
+    pub rate: T::LfoFreq,
+    /// The depth of the vibrato, in semitones (so the maximum depth is 100
+    /// cents either side of the input note)
+    pub depth: T::Scalar,
+}
+
+impl<T: DspFloat> From<&VibratoParams<i16>> for VibratoParams<T> {
+    fn from(value: &VibratoParams<i16>) -> Self {
+        Self {
+            rate: value.rate.to_num(),
+            depth: value.depth.to_num(),
+        }
+    }
+}
+
+/// A Vibrato
+///
+/// This modulates a note (e.g. the note driving an [Osc]) with a free-running
+/// sine [Lfo], so the output sweeps between `note - depth` and `note + depth`.
+///
+/// This implements [Device], taking a Note as input and [VibratoParams] as
+/// parameters, and outputting the modulated Note.
+#[derive(Clone, Default)]
+pub struct Vibrato<T: DspFormat> {
+    lfo: Lfo<T>,
+}
+
+impl<T: DspFormat> Vibrato<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<T: DspFormat> Device<T> for Vibrato<T> {
+    type Input = T::Note;
+    type Params = VibratoParams<T>;
+    type Output = T::Note;
+    fn next(&mut self, context: &T::Context, note: T::Note, params: VibratoParams<T>) -> T::Note {
+        let lfo_params = LfoParams {
+            freq: params.rate,
+            depth: params.depth,
+            opts: LfoOptions::new(LfoWave::Sine, true, false),
+            slew: T::EnvParam::zero(),
+        };
+        let offset = self.lfo.next(context, false, lfo_params);
+        T::apply_note_offset(note, T::note_offset_from_sample(offset))
+    }
+}
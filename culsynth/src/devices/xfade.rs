@@ -0,0 +1,74 @@
+use super::*;
+#[cfg(feature = "fixed")]
+use crate::fixedmath::{I16F16, U1F15};
+
+pub(crate) mod detail {
+    use super::*;
+    pub trait XfadeOps: DspFormatContext {
+        fn calc_xfade(a: Self::Sample, b: Self::Sample, mix: Self::Scalar) -> Self::Sample;
+    }
+}
+
+/// Input for an [Xfade]
+#[derive(Clone, Default)]
+pub struct XfadeInput<T: DspFormatBase> {
+    /// The signal output when the mix is zero
+    pub a: T::Sample,
+    /// The signal output when the mix is one
+    pub b: T::Sample,
+}
+
+/// A constant-power crossfader
+///
+/// This morphs between two signals using the same sin/cos law as [Pan]: the
+/// output is `a * cos(mix * pi/2) + b * sin(mix * pi/2)`, so each signal is
+/// attenuated by 3dB at the midpoint and the total power of two uncorrelated
+/// signals stays constant across the fade.  For a linear crossfade (which
+/// suits correlated signals, such as a dry and wet path), see [Amp::mix].
+///
+/// This implements [Device], taking an [XfadeInput] as input and a Scalar
+/// parameter (the mix) and outputting a Sample.
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct Xfade<T: DspFormat> {
+    phantom: core::marker::PhantomData<T>,
+}
+
+impl<T: DspFormat> Xfade<T> {
+    /// Constructor
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<T: DspFormat> Device<T> for Xfade<T> {
+    type Input = XfadeInput<T>;
+    type Params = T::Scalar;
+    type Output = T::Sample;
+    fn next(&mut self, _: &T::Context, input: XfadeInput<T>, mix: T::Scalar) -> T::Sample {
+        T::calc_xfade(input.a, input.b, mix)
+    }
+}
+
+impl<T: DspFloat> detail::XfadeOps for T {
+    fn calc_xfade(a: T, b: T, mix: T) -> T {
+        let theta = mix * T::FRAC_PI_2;
+        a * theta.fcos() + b * theta.fsin()
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl detail::XfadeOps for i16 {
+    fn calc_xfade(a: SampleFxP, b: SampleFxP, mix: ScalarFxP) -> SampleFxP {
+        use crate::fixedmath::{cos_fixed, sin_fixed};
+        const FRAC_PI_2: U1F15 = U1F15::lit("1.570796");
+        let theta = SampleFxP::from_num(mix.wide_mul(FRAC_PI_2));
+        let a = I16F16::from_num(a) * I16F16::from_num(cos_fixed(theta));
+        let b = I16F16::from_num(b) * I16F16::from_num(sin_fixed(theta));
+        SampleFxP::saturating_from_num(a + b)
+    }
+}
@@ -7,14 +7,37 @@ use fixedmath::scale_fixedfloat;
 /// 32 bit float, etc).
 pub trait DspFormat:
     DspFormatBase
+    + DspFormatContext
     + devices::osc::detail::OscOps
+    + devices::compressor::detail::CompressorOps
     + devices::env::detail::EnvOps
     + devices::filt::detail::FiltOps
+    + devices::glide::detail::GlideOps
     + devices::lfo::detail::LfoOps
+    + devices::pan::detail::PanOps
+    + devices::drift::detail::DriftOps
+    + devices::stretch::detail::StretchOps
+    + devices::testtone::detail::TestToneOps
+    + devices::xfade::detail::XfadeOps
     + voice::modulation::detail::ModulatorOps
 {
 }
 
+/// Bounds required for the state of a device to be serialized.  With the
+/// `serde` feature enabled, this is implemented for every type that
+/// implements [serde::Serialize] and [serde::de::DeserializeOwned].
+#[cfg(feature = "serde")]
+pub trait DspSerde: serde::Serialize + serde::de::DeserializeOwned {}
+#[cfg(feature = "serde")]
+impl<T: serde::Serialize + serde::de::DeserializeOwned> DspSerde for T {}
+
+/// Bounds required for the state of a device to be serialized.  Without the
+/// `serde` feature enabled, this is implemented for every type.
+#[cfg(not(feature = "serde"))]
+pub trait DspSerde {}
+#[cfg(not(feature = "serde"))]
+impl<T> DspSerde for T {}
+
 /// Type aliases defining the data types of various internal signals within the
 /// synthesizer.  This is primariliy to be generic over fixed/floating point
 pub trait DspFormatBase: Sized + Copy + Default + Send {
@@ -33,23 +56,23 @@ pub trait DspFormatBase: Sized + Copy + Default + Send {
     /// A type representing a parameter to an envelope
     type EnvParam: DspType<Self>;
     /// A type representing internal envelope signal levels
-    type EnvSignal: devices::env::detail::EnvType<Self> + Send;
+    type EnvSignal: devices::env::detail::EnvType<Self> + Send + DspSerde;
     /// A type representing the phase of a sinusoid
     type Phase: DspType<Self>;
     /// A type representing the frequency of a LFO
     type LfoFreq: DspType<Self>;
     /// A type representing a sample that *may* have higher precision/range
     type WideSample: Copy + Default + Add<Self::WideSample, Output = Self::WideSample>;
-    /// Type-specific context information
-    type Context: Send + crate::context::GetContext;
     /// Provide a value of the default note, definied as A440 (MIDI NN #69)
     fn default_note() -> Self::Note;
-    /// Convert a midi Note into a Frequency
-    fn note_to_freq(note: Self::Note) -> Self::Frequency;
     /// Convert a signed scalar to a Sample
     fn sample_from_fixed(value: crate::IScalarFxP) -> Self::Sample;
     /// Convert a sample to a 32 bit float
     fn sample_to_float(value: Self::Sample) -> f32;
+    /// Convert a note to a 32 bit float
+    fn note_to_float(value: Self::Note) -> f32;
+    /// Convert a scalar to a 32 bit float
+    fn scalar_to_float(value: Self::Scalar) -> f32;
     /// Widen a sample to a WideSample
     fn widen_sample(smp: Self::Sample) -> Self::WideSample;
     /// Narrow a WideSample to a Sample
@@ -59,6 +82,23 @@ pub trait DspFormatBase: Sized + Copy + Default + Send {
     fn note_from_scalar(scalar: Self::Scalar) -> Self::Note;
     /// Apply a note offset
     fn apply_note_offset(note: Self::Note, offset: Self::NoteOffset) -> Self::Note;
+    /// Convert a Sample to a Scalar, clamping it to the range of a Scalar
+    fn scalar_from_sample(smp: Self::Sample) -> Self::Scalar;
+    /// Convert a Sample to a NoteOffset (in semitones)
+    fn note_offset_from_sample(smp: Self::Sample) -> Self::NoteOffset;
+}
+
+/// The processing context of a sample data type, and the conversions that
+/// depend on the DSP engine for that type.  This is separate from
+/// [DspFormatBase] so the 16 bit fixed point types can still describe
+/// parameters in builds without the `fixed` feature.
+pub trait DspFormatContext: DspFormatBase {
+    /// Type-specific context information
+    type Context: Send + crate::context::GetContext + crate::context::GenericContext;
+    /// Convert a midi Note into a Frequency
+    fn note_to_freq(note: Self::Note) -> Self::Frequency;
+    /// The envelope level below which a releasing voice is silent
+    fn silence_threshold(ctx: &Self::Context) -> Self::Scalar;
 }
 
 ///Helper trait to make constraint bounds less painful for floating point types
@@ -66,6 +106,7 @@ pub trait DspFloat:
     crate::Float
     + Send
     + FromFixed
+    + DspSerde
     + DspFormatBase<
         Sample = Self,
         Note = Self,
@@ -78,15 +119,14 @@ pub trait DspFloat:
         Phase = Self,
         LfoFreq = Self,
         WideSample = Self,
-        Context = context::Context<Self>,
-    >
+    > + DspFormatContext<Context = context::Context<Self>>
 {
 }
 
 /// A trait to simplify common operations on DSP Types.  This is used to
 /// maximize the amount of code that can be agnostic to fixed and floating point
 pub trait DspType<T: DspFormatBase>:
-    Copy + Default + Send + Add<Self, Output = Self> + Sub<Self, Output = Self> + PartialOrd
+    Copy + Default + Send + Add<Self, Output = Self> + Sub<Self, Output = Self> + PartialOrd + DspSerde
 {
     /// A constant representing the value PI (3.14159...)
     const PI: Self;
@@ -101,8 +141,9 @@ pub trait DspType<T: DspFormatBase>:
     ///
     /// Used for when saturation is desired to avoid overflows, not correctness
     fn dsp_saturating_add(self, rhs: Self) -> Self;
-    /// Multiply this type with itself.  This trait does not provide any
-    /// specified behavior for fixed-point overflow.
+    /// Multiply this type with itself.  This saturates on overflow for the
+    /// 16 bit fixed-point types (e.g. samples and scalars), but otherwise this
+    /// trait does not provide any specified behavior for fixed-point overflow.
     fn multiply(self, rhs: Self) -> Self;
     /// Divide a value by two
     fn divide_by_two(self) -> Self;
@@ -115,7 +156,7 @@ pub trait DspType<T: DspFormatBase>:
 
 impl<T: DspFloat> DspFormat for T {}
 
-impl<T: Float + Send> DspFormatBase for T
+impl<T: Float + Send + DspSerde> DspFormatBase for T
 where
     T: From<crate::IScalarFxP> + From<crate::NoteFxP>,
 {
@@ -130,19 +171,21 @@ where
     type Phase = T;
     type LfoFreq = T;
     type WideSample = T;
-    type Context = context::Context<T>;
     fn default_note() -> Self::Note {
         Self::from_u16(69)
     }
-    fn note_to_freq(note: Self::Note) -> Self::Frequency {
-        T::midi_to_freq(note)
-    }
     fn sample_from_fixed(value: IScalarFxP) -> Self::Sample {
         value.into()
     }
     fn sample_to_float(value: Self::Sample) -> f32 {
         value.as_f32()
     }
+    fn note_to_float(value: Self::Note) -> f32 {
+        value.as_f32()
+    }
+    fn scalar_to_float(value: Self::Scalar) -> f32 {
+        value.as_f32()
+    }
     fn widen_sample(smp: Self::Sample) -> Self::WideSample {
         smp
     }
@@ -156,12 +199,31 @@ where
     fn apply_note_offset(note: Self::Note, offset: Self::NoteOffset) -> Self::Note {
         note + offset
     }
+    fn scalar_from_sample(smp: Self::Sample) -> Self::Scalar {
+        smp.max(T::ZERO).min(T::ONE)
+    }
+    fn note_offset_from_sample(smp: Self::Sample) -> Self::NoteOffset {
+        smp
+    }
+}
+
+impl<T: Float + Send + DspSerde> DspFormatContext for T
+where
+    T: From<crate::IScalarFxP> + From<crate::NoteFxP>,
+{
+    type Context = context::Context<T>;
+    fn note_to_freq(note: Self::Note) -> Self::Frequency {
+        T::midi_to_freq(note)
+    }
+    fn silence_threshold(ctx: &Self::Context) -> Self::Scalar {
+        ctx.silence_threshold()
+    }
 }
 
 impl DspFloat for f32 {}
 impl DspFloat for f64 {}
 
-impl<T: Float + Send> DspType<T> for T
+impl<T: Float + Send + DspSerde> DspType<T> for T
 where
     T: From<crate::IScalarFxP> + From<crate::NoteFxP>,
 {
@@ -190,6 +252,7 @@ where
 
 // 16-bit fixed point:
 
+#[cfg(feature = "fixed")]
 impl DspFormat for i16 {}
 
 impl DspFormatBase for i16 {
@@ -204,20 +267,22 @@ impl DspFormatBase for i16 {
     type Phase = PhaseFxP;
     type LfoFreq = LfoFreqFxP;
     type WideSample = WideSampleFxP;
-    type Context = context::ContextFxP;
     fn default_note() -> Self::Note {
         const DEFAULT: NoteFxP = NoteFxP::lit("69");
         DEFAULT
     }
-    fn note_to_freq(note: NoteFxP) -> FrequencyFxP {
-        crate::fixedmath::midi_note_to_frequency(note)
-    }
     fn sample_from_fixed(value: IScalarFxP) -> Self::Sample {
         SampleFxP::from_num(value)
     }
     fn sample_to_float(value: Self::Sample) -> f32 {
         value.into()
     }
+    fn note_to_float(value: Self::Note) -> f32 {
+        value.to_num()
+    }
+    fn scalar_to_float(value: Self::Scalar) -> f32 {
+        value.to_num()
+    }
     fn widen_sample(smp: Self::Sample) -> Self::WideSample {
         crate::fixedmath::widen_i(smp)
     }
@@ -230,9 +295,26 @@ impl DspFormatBase for i16 {
     fn apply_note_offset(note: NoteFxP, offset: SignedNoteFxP) -> NoteFxP {
         note.saturating_add_signed(offset)
     }
+    fn scalar_from_sample(smp: SampleFxP) -> ScalarFxP {
+        ScalarFxP::saturating_from_num(smp)
+    }
+    fn note_offset_from_sample(smp: SampleFxP) -> SignedNoteFxP {
+        SignedNoteFxP::from_num(smp)
+    }
+}
+
+#[cfg(feature = "fixed")]
+impl DspFormatContext for i16 {
+    type Context = context::ContextFxP;
+    fn note_to_freq(note: NoteFxP) -> FrequencyFxP {
+        crate::fixedmath::midi_note_to_frequency(note)
+    }
+    fn silence_threshold(ctx: &Self::Context) -> ScalarFxP {
+        ctx.silence_threshold()
+    }
 }
 
-impl<T: Fixed16 + Send> DspType<i16> for T {
+impl<T: Fixed16 + Send + DspSerde> DspType<i16> for T {
     const PI: Self = T::PI;
     const TAU: Self = T::TAU;
     fn zero() -> Self {
@@ -13,7 +13,7 @@ pub trait Fixed16: Fixed {
     } else {
         Self::MAX
     };
-    /// Multiply two fixed point numbers
+    /// Multiply two fixed point numbers, saturating on overflow
     fn multiply_fixed(self, rhs: Self) -> Self;
     /// Scale a fixed point number
     fn scale_fixed(self, rhs: ScalarFxP) -> Self;
@@ -32,7 +32,7 @@ where
     Sum<N, U16>: Unsigned + LeEqU32,
 {
     fn multiply_fixed(self, rhs: Self) -> Self {
-        Self::from_num(self.wide_mul(rhs))
+        Self::saturating_from_num(self.wide_mul(rhs))
     }
     fn scale_fixed(self, rhs: ScalarFxP) -> Self {
         Self::from_num(self.wide_mul_unsigned(rhs))
@@ -53,7 +53,7 @@ where
     Sum<N, U16>: Unsigned + LeEqU32,
 {
     fn multiply_fixed(self, rhs: Self) -> Self {
-        Self::from_num(self.wide_mul(rhs))
+        Self::saturating_from_num(self.wide_mul(rhs))
     }
     fn scale_fixed(self, rhs: ScalarFxP) -> Self {
         Self::from_num(self.wide_mul(rhs))
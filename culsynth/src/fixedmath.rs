@@ -5,7 +5,9 @@
 //! you have been warned!
 
 use core::ops::Add;
-use fixed::types::extra::{IsLessOrEqual, LeEqU16, LeEqU32, True, Unsigned, U16, U31};
+use fixed::types::extra::{IsLessOrEqual, LeEqU16, LeEqU32, Unsigned, U16};
+#[cfg(feature = "fixed")]
+use fixed::types::extra::{True, U31};
 pub use fixed::types::*;
 use fixed::{FixedI16, FixedI32, FixedU16, FixedU32};
 
@@ -41,18 +43,21 @@ pub type Scalar = U0F16;
 
 //for the following constants, we'll use as many bits as we can fit
 //in a couple cases, that means we'll buy a extra place shifting right
+#[cfg(feature = "fixed")]
 const FRAC_16_21: Scalar = Scalar::lit("0x0.c30c"); //0x0.c30 repeating
+#[cfg(feature = "fixed")]
 const FRAC_4_5: Scalar = Scalar::lit("0x0.cccd"); //0x0.c repeating
+#[cfg(feature = "fixed")]
 const FRAC_2_3: Scalar = Scalar::lit("0x0.aaab"); //0x0.a repeating
+#[cfg(feature = "fixed")]
 const FRAC_8_15: Scalar = Scalar::lit("0x0.8889"); //0x0.8 repeating
 
 //when to apply a small angle approximation
+#[cfg(feature = "fixed")]
 const SMALL_ANGLE_LESS: Sample = Sample::lit("0x0.1");
 
-// (4/3)*ln(2)
-const FRAC_4LN2_3: Scalar = Scalar::lit("0x0.ec98");
-// Frequency of E4 ~= 329.63 Hz
-const FREQ_E4: Frequency = Frequency::lit("329.627557");
+// Frequency of A4 (MIDI note 69)
+const FREQ_A4: Frequency = Frequency::lit("440");
 
 /// Take a 32 bit fixed point number A and a 16 bit fixed point number B, and return
 /// a 32 bit fixed point number representing the product of those two numbers with
@@ -76,6 +81,42 @@ where
     FixedU32::<FracA>::from_bits(res.to_bits())
 }
 
+/// Take a 32 bit signed fixed point number A and a 16 bit unsigned fixed point
+/// number B, and return `(A * B) >> shift`, rounded to nearest, in the same
+/// format as A.  The product is assembled from two 16x16->32 bit multiplies
+/// (one for each half of A), so this avoids a 64 bit multiply while keeping
+/// (nearly) the full precision of A.  The result must be representable in the
+/// format of A, and `FracB + shift` must be less than 48.
+#[cfg(feature = "fixed")]
+pub fn scale_shr_round<FracA, FracB>(
+    a: FixedI32<FracA>,
+    b: FixedU16<FracB>,
+    shift: u32,
+) -> FixedI32<FracA>
+where
+    FracA: Unsigned + LeEqU32,
+    FracB: Unsigned + LeEqU16,
+{
+    let shift = FracB::U32 + shift;
+    let b = b.to_bits();
+    // a * b == (hi << 16) + lo
+    let hi = (a.to_bits() >> 16) * b as i32;
+    let lo = (a.to_bits() as u32 & 0xFFFF) * b as u32;
+    let res = if shift == 0 {
+        hi.wrapping_shl(16).wrapping_add(lo as i32)
+    } else if shift <= 16 {
+        let lo = (lo + (1 << (shift - 1))) >> shift;
+        hi.wrapping_shl(16 - shift).wrapping_add(lo as i32)
+    } else {
+        // The low bits of lo are below the rounding point, so drop them, and
+        // shift hi before adding so the intermediate can't overflow
+        let shift = shift - 16;
+        let rem = (hi as u32 & ((1 << shift) - 1)) + (lo >> 16);
+        (hi >> shift) + ((rem + (1 << (shift - 1))) >> shift) as i32
+    };
+    FixedI32::<FracA>::from_bits(res)
+}
+
 /// Widen the given 16 bit fixed point number to a 32 bit fixed point number
 pub fn widen_i<Frac>(a: FixedI16<Frac>) -> FixedI32<Frac>
 where
@@ -84,10 +125,15 @@ where
     FixedI32::<Frac>::from_num(a)
 }
 
+#[cfg(feature = "fixed")]
 fn one_over_one_plus_helper<Frac>(n: FixedU32<Frac>) -> (U1F31, u32)
 where
     Frac: Unsigned + IsLessOrEqual<U31, Output = True> + LeEqU32,
 {
+    debug_assert!(
+        n <= FixedU32::<Frac>::MAX - FixedU32::<Frac>::ONE,
+        "one_over_one_plus: 1 + x overflows"
+    );
     let nbits = FixedU32::<Frac>::INT_NBITS;
     let x = n + FixedU32::<Frac>::ONE;
     let mut shift = x.leading_zeros();
@@ -100,13 +146,38 @@ where
     (x_shifted, nbits - shift)
 }
 
+/// Refine an estimate `y` of `1/x` using a single Newton-Raphson iteration,
+/// i.e. `y' = y * (2 - x * y)`.  This squares the relative error of `y`, up to
+/// the precision of the 16 bit operands.  The result is given with 30
+/// fractional bits so the caller may round it.
+///
+/// Both `x` and `y` must be in the interval `[sqrt(2)/2, sqrt(2)]`.  This only
+/// needs 16x16->32 bit multiplies.
+#[cfg(feature = "fixed")]
+fn reciprocal_newton_step(x: U1F15, y: U1F15) -> U2F30 {
+    const TWO: U2F30 = U2F30::lit("2");
+    let correction = U1F15::from_num(TWO - x.wide_mul(y));
+    y.wide_mul(correction)
+}
+
 /// Calculate 1/(1+x) for a 32 bit fixed point number and return the result as
 /// a tuple representing a sixteen bit number in scientific notation - the first
 /// element is a sixteen bit fixed point number with 1 integral bit, and the
 /// second element represents the negative of the exponent (base 2).
 ///
-/// Internally, this uses a quadratic taylor series expansion about 2^-x for the
-/// closest value of 2^-x on a logarithmic basis.
+/// The input may take any value such that `1 + x` is representable (i.e.
+/// `x <= MAX - 1`), and `Frac` must be at most 31 so that `1` is
+/// representable.  Internally, `1 + x` is normalized to the interval
+/// `[sqrt(2)/2, sqrt(2))`, a quadratic taylor series expansion about 1 is used
+/// as an initial estimate, and two Newton-Raphson iterations refine this to
+/// (nearly) the full precision of the 16 bit result, with a relative error of
+/// less than 1e-4 across the entire input range.  All of the multiplies are
+/// 16x16->32 bit, so this stays cheap on targets without a fast 64 bit
+/// multiply.
+///
+/// # Panics
+///
+/// In debug builds, this will panic if `1 + x` overflows.
 ///
 /// # Examples
 ///
@@ -116,24 +187,30 @@ where
 /// let (y, exp) = one_over_one_plus(x); // 1 / (1 + 1) == 1 / 2
 /// assert!(y.unwrapped_shr(exp) == U16F16::lit("0.5"));
 /// ```
+#[cfg(feature = "fixed")]
 pub fn one_over_one_plus<Frac>(x: FixedU32<Frac>) -> (U1F15, u32)
 where
     Frac: Unsigned + IsLessOrEqual<U31, Output = True> + LeEqU32,
 {
+    // Round to nearest when narrowing to 16 bits
+    const HALF_LSB_31: U1F31 = U1F31::from_bits(1 << 15);
+    const HALF_LSB_30: U2F30 = U2F30::from_bits(1 << 14);
     let (x_shifted, shift) = one_over_one_plus_helper(x);
-    let x_shifted_trunc = U1F15::from_num(x_shifted);
+    let x_shifted_trunc = U1F15::from_num(x_shifted + HALF_LSB_31);
     let x2 = I3F29::from_num(x_shifted_trunc.wide_mul(x_shifted_trunc));
     let one_minus_x = I3F29::ONE - I3F29::from_num(x_shifted);
-    (
-        U1F15::from_num(x2 + one_minus_x + one_minus_x.unwrapped_shl(1)),
-        shift,
-    )
+    let estimate = U1F15::from_num(x2 + one_minus_x + one_minus_x.unwrapped_shl(1));
+    let refined = reciprocal_newton_step(x_shifted_trunc, estimate);
+    let refined = U1F15::saturating_from_num(refined + HALF_LSB_30);
+    let refined = reciprocal_newton_step(x_shifted_trunc, refined);
+    (U1F15::saturating_from_num(refined + HALF_LSB_30), shift)
 }
 
 /// Perform the same calculation as [one_over_one_plus], but with a 16 bit
 /// fixed point number as the input, and use a quartic taylor series instead
 /// of a quadratic one.  This is significantly more accurate at the cost of an
 /// extra two 32-bit multiplies.
+#[cfg(feature = "fixed")]
 pub fn one_over_one_plus_highacc(x: U0F16) -> (U1F15, u32) {
     let (x_shifted, shift) = one_over_one_plus_helper(U16F16::from_num(x));
     const FIVE_NAR: U3F13 = U3F13::lit("5");
@@ -153,6 +230,7 @@ pub fn one_over_one_plus_highacc(x: U0F16) -> (U1F15, u32) {
 ///
 /// This function may panic due to fixed-point overflow if given an input ouside
 /// of the range -pi to pi, though it has been tested to be safe up to +/- 3.2
+#[cfg(feature = "fixed")]
 pub fn sin_fixed(x: Sample) -> Sample {
     //small angle approximation.  Faster and removes 0 as an edge case
     if x.abs() < SMALL_ANGLE_LESS {
@@ -186,6 +264,7 @@ pub fn sin_fixed(x: Sample) -> Sample {
 ///
 /// This function may panic due to fixed point overflow if given a number outside
 /// of the range -pi to pi, though it has been tested out to 3.2.
+#[cfg(feature = "fixed")]
 pub fn cos_fixed(x: Sample) -> Sample {
     let x2 = USample::from_num(x.wide_mul(x));
     //small angle approximation.  Faster and removes 0 as an edge case
@@ -208,6 +287,7 @@ pub fn cos_fixed(x: Sample) -> Sample {
 /// Used primarily in filter gain prewarping, where it's accurate enough for angles
 /// representing lower frequencies where precise tuning is more important.  Will be
 /// somewhat inaccurate at frequencies above about half the Nyquist frequency.
+#[cfg(feature = "fixed")]
 pub fn tan_fixed(x: U0F16) -> U1F15 {
     let x2 = x.wide_mul(x);
     let x2_over3 = U0F16::from_num(x2).wide_mul(FRAC_2_3).unwrapped_shr(1);
@@ -216,6 +296,7 @@ pub fn tan_fixed(x: U0F16) -> U1F15 {
 }
 
 /// calculate e^x in the range [-0.5, 0.5) using an order 4 Taylor series
+#[cfg(feature = "fixed")]
 fn exp_fixed_small(x: I0F16) -> U2F14 {
     // e^x ~= 1 + x + x^2/2! + x^3/3! + x^4/4!
     //     ~= 1 + x * { 1 + x/2 * [ 1 + x/3 * ( 1 + x/4 )]}
@@ -236,6 +317,7 @@ fn exp_fixed_small(x: I0F16) -> U2F14 {
 /// Calculate e^x of a 16 bit signed fixed point number with 13 fractional bits
 /// (that is to say, between -4 and 4), and return it as a unsigned 32 bit
 /// number with 24 fractional bits.
+#[cfg(feature = "fixed")]
 pub fn exp_fixed(x: I3F13) -> U8F24 {
     // going to use the fact that our input domain is limited to [-4, 4)
     // to calculate e^x as the product e^(int(x))*e^(frac(x)), then since
@@ -283,34 +365,183 @@ pub fn exp_fixed(x: I3F13) -> U8F24 {
     U8F24::from_num(retval.unwrapped_shl(left).unwrapped_shr(right))
 }
 
-// Take a MIDI note representation and put it closer to 1.0/octave
-// We'll leave it as 16 semitones/oct here for convenience (so this is
-// just bit twiddling)
-fn note_to_value(note: Note) -> I3F13 {
-    const C64: I19F13 = I19F13::lit("64");
-    //subtract 64 to make it centered about zero
-    let note_signed = I19F13::from_num(note) - C64;
-    //divide down to 16 semitones/octave
-    I3F13::from_num(note_signed.unwrapped_shr(4))
+/// Calculate 2^x of a 16 bit signed fixed point number with 15 fractional
+/// bits (that is to say, between -1 and 1), and return it as an unsigned 16
+/// bit number with 14 fractional bits.
+///
+/// This uses a degree 5 minimax polynomial, which has a relative error below
+/// 5e-6 (i.e. less than the precision of the result) over the whole domain.
+pub fn exp2_fixed(x: I1F15) -> U2F14 {
+    // Coefficients (highest order first) generated with the Remez exchange
+    // algorithm, minimizing the relative error of 2^x over [-1, 1]
+    const COEFFS: [I16F16; 6] = [
+        I16F16::lit("0.0013106546"),
+        I16F16::lit("0.0098436022"),
+        I16F16::lit("0.0555518007"),
+        I16F16::lit("0.2401465378"),
+        I16F16::lit("0.6931339872"),
+        I16F16::lit("1.0000039309"),
+    ];
+    // Evaluate with Horner's method on the raw bits: each product of an
+    // I16F16 and an I1F15 is rounded back down to 16 fractional bits.  The
+    // partial sums never exceed 1 before being multiplied, so every product
+    // fits in 32 bits.
+    let x = x.to_bits() as i32;
+    let poly = COEFFS[1..].iter().fold(COEFFS[0].to_bits(), |acc, c| {
+        ((acc * x + (1 << 14)) >> 15) + c.to_bits()
+    });
+    // Round to nearest when dropping down to 14 fractional bits
+    U2F14::saturating_from_num(I16F16::from_bits(poly + (1 << 1)))
 }
 
 /// Convert a MIDI note number to a frequency in Hz
 pub fn midi_note_to_frequency(note: Note) -> Frequency {
-    // f = f0 * e^(note/12)
-    let note_xform = note_to_value(note);
-    //this change in representation brings us to 1.0 = 16 semitones
-    //need to multiply by (4/3) in order to scale it to 1.0=12 semitones
-    //we also need to multiply by ln(2) so we'll do that in one step
-    let power = I3F13::from_num(note_xform.wide_mul_unsigned(FRAC_4LN2_3));
-    //Our note numbers are centered about E4, so that's our f0:
-    FREQ_E4 * U14F18::from_num(exp_fixed(power))
+    // f = 440 * 2^((note - 69)/12).  Split the note into a whole number of
+    // octaves and a fraction of an octave, so that 2^fraction can be found
+    // with exp2_fixed and the octaves are just a shift.  Offsetting by 3
+    // semitones puts each A at the start of an octave.
+    let semitones = (note.to_bits() >> Note::FRAC_NBITS) as u32 + 3;
+    // semitones / 12, exact for every semitone up to 130
+    let octave = (semitones * 0x2AB) >> 13;
+    let within = ((semitones - octave * 12) << Note::FRAC_NBITS) | note.frac().to_bits() as u32;
+    // within / 12 as a fraction of an octave with 15 fractional bits
+    let frac = I1F15::from_bits(((within * 0x5555 + (1 << 11)) >> 12) as i16);
+    // 440 * 2^frac with 18 fractional bits.  This is at most 880, and the
+    // octave shift below keeps it within the range of a valid MIDI note.
+    let a4 = FREQ_A4.to_num::<u32>();
+    let freq = Frequency::from_bits((exp2_fixed(frac).to_bits() as u32 * a4) << 4);
+    // A4 is in octave 6, so this is in the range [-6, 4] for a valid Note
+    let shift = octave as i32 - 6;
+    if shift < 0 {
+        freq.unwrapped_shr(-shift as u32)
+    } else {
+        freq.unwrapped_shl(shift as u32)
+    }
+}
+
+/// Calculate tanh(x) for a non-negative x, given as a U16F16, using a lookup
+/// table with 64 linearly interpolated segments over [0, 4).  Beyond that
+/// range the result is held at tanh(4) ~= 0.9993.
+#[cfg(feature = "fixed")]
+fn tanh_fixed(x: U16F16) -> Scalar {
+    // Lookup Table generated using the following python snippet:
+    //
+    // [round(tanh(i/16)*65536) for i in range(65)]
+    const LOOKUP_TABLE: [u16; 65] = [
+        0x0000, 0x0ffb, 0x1fd6, 0x2f72, 0x3eb3, 0x4d7e, 0x5bbd, 0x695d, //
+        0x764d, 0x8284, 0x8dfa, 0x98ac, 0xa299, 0xabc4, 0xb433, 0xbbec, //
+        0xc2f8, 0xc960, 0xcf2e, 0xd46e, 0xd929, 0xdd6b, 0xe13c, 0xe4a8, //
+        0xe7b8, 0xea73, 0xece3, 0xef0f, 0xf0fe, 0xf2b6, 0xf43c, 0xf597, //
+        0xf6cb, 0xf7db, 0xf8cd, 0xf9a3, 0xfa60, 0xfb07, 0xfb9c, 0xfc1f, //
+        0xfc93, 0xfcf9, 0xfd54, 0xfda4, 0xfdeb, 0xfe29, 0xfe60, 0xfe91, //
+        0xfebc, 0xfee2, 0xff03, 0xff21, 0xff3b, 0xff52, 0xff67, 0xff79, //
+        0xff89, 0xff97, 0xffa3, 0xffae, 0xffb8, 0xffc0, 0xffc8, 0xffce, //
+        0xffd4,
+    ];
+    // Each table segment is 1/16 wide, so the index is the top bits of 16x
+    // and the remaining fractional bits are used to interpolate
+    let pos = x.to_bits() >> 12;
+    let index = pos as usize;
+    if index >= LOOKUP_TABLE.len() - 1 {
+        return Scalar::from_bits(LOOKUP_TABLE[LOOKUP_TABLE.len() - 1]);
+    }
+    let frac = x.to_bits() & 0xFFF;
+    let (lo, hi) = (LOOKUP_TABLE[index] as u32, LOOKUP_TABLE[index + 1] as u32);
+    Scalar::from_bits((lo + (((hi - lo) * frac) >> 12)) as u16)
+}
+
+/// Calculate log2(x) of a 32 bit unsigned fixed point number with 16
+/// fractional bits, using a lookup table with 16 linearly interpolated
+/// segments for the mantissa.  This is accurate to better than 0.001, and
+/// returns -16 (the log of the smallest nonzero input) for zero.
+#[cfg(feature = "fixed")]
+pub fn log2_fixed_wide(x: U16F16) -> I16F16 {
+    // Lookup Table generated using the following python snippet:
+    //
+    // [hex(round(log2(1 + i/16)*32768)) for i in range(17)]
+    const LOOKUP_TABLE: [u16; 17] = [
+        0x0000, 0x0b32, 0x15c0, 0x1fbc, 0x2935, 0x3237, 0x3acf, 0x4304, //
+        0x4ae0, 0x526a, 0x59a8, 0x60a0, 0x6757, 0x6dd2, 0x7415, 0x7a23, //
+        0x8000,
+    ];
+    let bits = x.to_bits();
+    if bits == 0 {
+        return I16F16::lit("-16");
+    }
+    // Normalize so the leading one is the MSB, which gives the integer part
+    // of the result.  The next 4 bits are the table index, and the 16 bits
+    // after that are used to interpolate
+    let leading = bits.leading_zeros();
+    let mantissa = bits << leading;
+    let index = ((mantissa >> 27) & 0xF) as usize;
+    let frac = (mantissa >> 11) & 0xFFFF;
+    let (lo, hi) = (LOOKUP_TABLE[index] as u32, LOOKUP_TABLE[index + 1] as u32);
+    let mantissa_log = (lo << 1) + (((hi - lo) * frac) >> 15);
+    I16F16::from_num(15 - leading as i32) + I16F16::from_bits(mantissa_log as i32)
+}
+
+/// Calculate log2(x) of a 16 bit unsigned fixed point number with 2
+/// fractional bits (e.g. a frequency in Hz), and return it with 8 fractional
+/// bits.  This is a narrower version of [log2_fixed_wide] for converting
+/// frequencies back to pitches, and returns -16 for zero.
+#[cfg(feature = "fixed")]
+pub fn log2_fixed(x: U14F2) -> I8F8 {
+    // Add half an LSB of the result so the conversion rounds to nearest
+    let log = log2_fixed_wide(U16F16::from_num(x)) + I16F16::from_bits(1 << 7);
+    I8F8::from_num(log)
+}
+
+/// Clipping functions for [Sample]s
+///
+/// Since [Sample] is an alias of a type from the `fixed` crate, these are
+/// provided as an extension trait.
+#[cfg(feature = "fixed")]
+pub trait SampleClip {
+    /// Clip to the range `[-threshold, threshold]`
+    fn hard_clip(self, threshold: Scalar) -> Self;
+    /// Clip smoothly to the range `[-1, 1]`.
+    ///
+    /// Values with a magnitude below `threshold` are passed through
+    /// unchanged.  Above it, the excess is compressed with a tanh curve into
+    /// the remaining headroom up to 1.0, so the transfer function (and its
+    /// slope) is continuous at the threshold.
+    fn soft_clip(self, threshold: Scalar) -> Self;
+}
+
+#[cfg(feature = "fixed")]
+impl SampleClip for Sample {
+    fn hard_clip(self, threshold: Scalar) -> Self {
+        let limit = Sample::from_num(threshold);
+        self.clamp(-limit, limit)
+    }
+    fn soft_clip(self, threshold: Scalar) -> Self {
+        // Work with the magnitude in 16 fractional bits, like the threshold
+        let mag = (self.unsigned_abs().to_bits() as u32) << 4;
+        let knee = threshold.to_bits() as u32;
+        if mag <= knee {
+            return self;
+        }
+        // headroom is (1 - threshold), which is always nonzero
+        let headroom = (1u32 << 16) - knee;
+        let excess = (((mag - knee) as u64) << 16) / headroom as u64;
+        let excess = U16F16::from_bits(excess.min(u32::MAX as u64) as u32);
+        let clipped = knee as u64 + ((headroom as u64 * tanh_fixed(excess).to_bits() as u64) >> 16);
+        // round back to 12 fractional bits
+        let clipped = Sample::from_bits(((clipped + 8) >> 4) as i16);
+        if self.is_negative() {
+            -clipped
+        } else {
+            clipped
+        }
+    }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "fixed"))]
 mod tests {
     use super::super::util::calculate_cents;
     use super::*;
     use fixed::traits::ToFixed;
+    use fixed::types::extra::{U13, U29};
     //test for correctness of constants
     #[test]
     fn const_fraction_correctness() {
@@ -349,6 +580,35 @@ mod tests {
         let _b = sin_fixed(Sample::lit("-3.2"));
     }
     //
+    //ONE_OVER_ONE_PLUS TESTS:
+    //
+    fn one_over_one_plus_max_error<Frac>(max: f64) -> f64
+    where
+        Frac: Unsigned + IsLessOrEqual<U31, Output = True> + LeEqU32,
+    {
+        let numsteps = 10000;
+        let mut max_error = 0f64;
+        for i in 0..=numsteps {
+            // Sweep logarithmically to cover both small and large values of k
+            let k = (max + 1.0).powf(i as f64 / numsteps as f64) - 1.0;
+            let k_fixed = FixedU32::<Frac>::from_num(k);
+            let (y, shift) = one_over_one_plus(k_fixed);
+            let result = y.to_num::<f64>() / (1u64 << shift) as f64;
+            let expected = 1.0 / (1.0 + k_fixed.to_num::<f64>());
+            max_error = max_error.max(((result - expected) / expected).abs());
+        }
+        max_error
+    }
+    #[test]
+    fn one_over_one_plus_accuracy() {
+        // Filter coefficients (see FiltOps for i16)
+        assert!(one_over_one_plus_max_error::<U29>(6.99) < 1e-4);
+        // Envelope coefficients (see EnvOps for i16)
+        assert!(one_over_one_plus_max_error::<U13>(500000.0) < 1e-4);
+        // Full range of U16F16
+        assert!(one_over_one_plus_max_error::<U16>(65534.0) < 1e-4);
+    }
+    //
     //COS TESTS:
     //
     #[test]
@@ -387,4 +647,120 @@ mod tests {
             assert!(error < 1.0); //less than one cent per note
         }
     }
+    #[test]
+    fn midi_pitch_a4() {
+        let a4 = midi_note_to_frequency(Note::lit("69")).to_num::<f32>();
+        assert!((a4 - 440f32).abs() < 0.01, "{}", a4);
+    }
+    #[test]
+    fn exp2_fixed_error() {
+        // Within one LSB at the ends of the domain
+        let one = exp2_fixed(I1F15::ZERO).to_bits() as i32;
+        assert!((one - U2F14::ONE.to_bits() as i32).abs() <= 1);
+        let two = exp2_fixed(I1F15::MAX).to_bits() as i32;
+        assert!((two - U2F14::lit("2").to_bits() as i32).abs() <= 1);
+        for bits in i16::MIN..=i16::MAX {
+            let x = I1F15::from_bits(bits);
+            let error = exp2_fixed(x).to_num::<f64>() - x.to_num::<f64>().exp2();
+            assert!(
+                error.abs() <= U2F14::DELTA.to_num::<f64>(),
+                "{} {}",
+                x,
+                error
+            );
+        }
+    }
+    #[test]
+    fn log2_fixed_error() {
+        assert_eq!(log2_fixed(U14F2::ZERO), I8F8::lit("-16"));
+        assert_eq!(log2_fixed(U14F2::lit("440")), I8F8::lit("8.78125"));
+        for bits in 1..=u16::MAX {
+            let x = U14F2::from_bits(bits);
+            let error = log2_fixed(x).to_num::<f64>() - x.to_num::<f64>().log2();
+            assert!(error.abs() < 0.003, "{} {}", x, error);
+        }
+    }
+    #[test]
+    fn log2_fixed_wide_error() {
+        assert_eq!(log2_fixed_wide(U16F16::ZERO), I16F16::lit("-16"));
+        assert_eq!(log2_fixed_wide(U16F16::ONE), I16F16::ZERO);
+        for bits in (1..=u32::MAX).step_by(9973) {
+            let x = U16F16::from_bits(bits);
+            let error = log2_fixed_wide(x).to_num::<f64>() - x.to_num::<f64>().log2();
+            assert!(error.abs() < 0.001, "{} {}", x, error);
+        }
+    }
+    #[test]
+    fn scale_shr_round_error() {
+        for bits in (i32::MIN..=i32::MAX).step_by(99991) {
+            let a = I3F29::from_bits(bits);
+            for b in ["0", "0.5", "0.70711", "1", "1.41421", "1.99997"] {
+                let b = U1F15::from_str(b).unwrap();
+                for shift in [0, 1, 5, 16, 20] {
+                    if bits.unsigned_abs() >> shift > i32::MAX as u32 / 2 {
+                        // The result wouldn't be representable
+                        continue;
+                    }
+                    let expected = (a.to_num::<f64>() * b.to_num::<f64>()) / (1u32 << shift) as f64;
+                    let error = scale_shr_round(a, b, shift).to_num::<f64>() - expected;
+                    assert!(
+                        error.abs() <= I3F29::DELTA.to_num::<f64>(),
+                        "{} {} {} {}",
+                        a,
+                        b,
+                        shift,
+                        error
+                    );
+                }
+            }
+        }
+    }
+    //
+    //CLIP TESTS:
+    //
+    #[test]
+    fn soft_clip_bounds() {
+        for threshold in ["0", "0.5", "0.75", "0.9", "0.999"] {
+            let threshold = Scalar::from_str(threshold).unwrap();
+            let mut last = Sample::MIN.soft_clip(threshold);
+            for bits in i16::MIN..=i16::MAX {
+                let x = Sample::from_bits(bits);
+                let y = x.soft_clip(threshold);
+                assert!(y >= -Sample::ONE && y <= Sample::ONE);
+                // Monotonic, and the identity below the threshold
+                assert!(y >= last);
+                last = y;
+                if x.unsigned_abs() < threshold {
+                    assert!(y.abs_diff(x) <= Sample::DELTA.unsigned_abs());
+                }
+            }
+        }
+    }
+    #[test]
+    fn soft_clip_tanh_error() {
+        let threshold = Scalar::lit("0.5");
+        for bits in i16::MIN..=i16::MAX {
+            let x = Sample::from_bits(bits).to_num::<f64>();
+            let excess = (x.abs() - 0.5).max(0.0);
+            let expected = x.signum() * (x.abs().min(0.5) + 0.5 * f64::tanh(excess / 0.5));
+            let fixed = Sample::from_num(x).soft_clip(threshold).to_num::<f64>();
+            assert!(
+                (fixed - expected).abs() < 0.001,
+                "{} {} {}",
+                x,
+                fixed,
+                expected
+            );
+        }
+    }
+    #[test]
+    fn hard_clip() {
+        let threshold = Scalar::lit("0.5");
+        assert_eq!(
+            Sample::lit("0.25").hard_clip(threshold),
+            Sample::lit("0.25")
+        );
+        assert_eq!(Sample::lit("3").hard_clip(threshold), Sample::lit("0.5"));
+        assert_eq!(Sample::MIN.hard_clip(threshold), Sample::lit("-0.5"));
+    }
 }
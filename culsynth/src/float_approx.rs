@@ -102,6 +102,44 @@ mod detail {
         frac_exp * LOOKUP_TABLE[index].into()
     }
 
+    /// Approximate log2(x) for a positive, finite x, using a lookup table with
+    /// 16 linearly interpolated segments for the mantissa.  Like `log2`, this
+    /// returns negative infinity if x is zero.
+    pub fn log2_approx<T: Float + From<f32>>(x: T) -> T {
+        const LOOKUP_TABLE: [f32; 17] = [
+            0.0,
+            0.087_462_84,
+            0.169_925,
+            0.247_927_51,
+            0.321_928_1,
+            0.392_317_4,
+            0.459_431_62,
+            0.523_561_96,
+            0.584_962_5,
+            0.643_856_2,
+            0.700_439_7,
+            0.754_887_5,
+            0.807_354_9,
+            0.857_981,
+            0.906_890_6,
+            0.954_196_3,
+            1.0,
+        ];
+        // Normalize the mantissa so its leading one is the MSB.  The next 4
+        // bits are the table index, and the 32 bits after that interpolate
+        let (mantissa, exponent, _) = x.integer_decode();
+        if mantissa == 0 {
+            return T::neg_infinity();
+        }
+        let leading = mantissa.leading_zeros();
+        let mantissa = mantissa << leading;
+        let index = ((mantissa >> 59) & 0xF) as usize;
+        let frac = ((mantissa >> 27) as u32) as f32 / 4_294_967_296f32;
+        let (lo, hi) = (LOOKUP_TABLE[index], LOOKUP_TABLE[index + 1]);
+        let int = exponent as i32 + 63 - leading as i32;
+        (int as f32 + lo + (hi - lo) * frac).into()
+    }
+
     /// Convert a MIDI note number to a frequency in Hz
     pub fn midi_note_to_frequency<T: Float + From<f32> + AsPrimitive<isize>>(note: T) -> T {
         const FRAC_LN2_12: f32 = 0.057_762_265;
@@ -151,6 +189,16 @@ mod tests {
         assert!(error < 0.06); //RMS error on interval (-pi, pi)
     }
     #[test]
+    fn log2_approx_error() {
+        for i in 1..=2000 {
+            let x = i as f32 / 100.0;
+            let error = log2_approx(x) - x.log2();
+            assert!(error.abs() < 0.001, "{} {}", x, error);
+        }
+        assert_eq!(log2_approx(0.25f64), -2f64);
+        assert_eq!(log2_approx(0f32), f32::NEG_INFINITY);
+    }
+    #[test]
     fn midi_pitch_calculations_float_approx() {
         for i in 0..=127 {
             let pitch = 440.0 * f32::powf(2.0, ((i - 69) as f32) / 12.0);
@@ -45,6 +45,10 @@ pub trait Float: NumTraitsFloat + From<u16> + Default + Copy {
     fn fcos(self) -> Self;
     /// Returns the tangent of self
     fn ftan(self) -> Self;
+    /// Returns e^self.  Without libm, this is only valid in the range `[-4, 4)`
+    fn fexp(self) -> Self;
+    /// Returns log2(self), which must be positive
+    fn flog2(self) -> Self;
     /// Convert a MIDI note number to a frequency
     fn midi_to_freq(self) -> Self;
     /// Convert to a f32
@@ -87,6 +91,20 @@ impl Float for f32 {
         let ret = <Self as NumTraitsFloat>::tan(self);
         ret
     }
+    fn fexp(self) -> Self {
+        #[cfg(not(feature = "libm"))]
+        let ret = crate::float_approx::exp_approx(self);
+        #[cfg(feature = "libm")]
+        let ret = <Self as NumTraitsFloat>::exp(self);
+        ret
+    }
+    fn flog2(self) -> Self {
+        #[cfg(not(feature = "libm"))]
+        let ret = crate::float_approx::log2_approx(self);
+        #[cfg(feature = "libm")]
+        let ret = <Self as NumTraitsFloat>::log2(self);
+        ret
+    }
     fn midi_to_freq(self) -> Self {
         #[cfg(not(feature = "libm"))]
         let ret = crate::float_approx::midi_note_to_frequency(self);
@@ -135,6 +153,20 @@ impl Float for f64 {
         let ret = <Self as NumTraitsFloat>::tan(self);
         ret
     }
+    fn fexp(self) -> Self {
+        #[cfg(not(feature = "libm"))]
+        let ret = crate::float_approx::exp_approx(self);
+        #[cfg(feature = "libm")]
+        let ret = <Self as NumTraitsFloat>::exp(self);
+        ret
+    }
+    fn flog2(self) -> Self {
+        #[cfg(not(feature = "libm"))]
+        let ret = crate::float_approx::log2_approx(self);
+        #[cfg(feature = "libm")]
+        let ret = <Self as NumTraitsFloat>::log2(self);
+        ret
+    }
     fn midi_to_freq(self) -> Self {
         #[cfg(not(feature = "libm"))]
         let ret = crate::float_approx::midi_note_to_frequency(self);
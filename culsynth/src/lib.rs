@@ -7,6 +7,16 @@
 //! embedded platforms without native hardware support for these primitives.
 //!
 //! Most of the relevant code for users can be found in the [devices] module.
+//! The [convert] module converts between floating point values and the fixed
+//! point types used as parameters for fixed point devices.
+//!
+//! The fixed point devices are built with the `fixed` feature, which is on by
+//! default.  Without it, only floating point devices can be run, but the fixed
+//! point types are still used to describe parameters.
+//!
+//! This crate is pure Rust and does not export any C symbols, so it can be
+//! statically linked alongside other crates without any risk of symbol
+//! clashes.  The C API is built separately by the `culsynth_bindings` crate.
 //!
 //! This crate uses the (somewhat regrettably hungarian-style) convention of
 //! having all fixed-point structs and traits be the same as their floating-point
@@ -39,6 +49,7 @@ pub const USE_LIBM: bool = cfg!(feature = "libm");
 pub mod midi_const;
 
 pub mod context;
+pub mod convert;
 pub mod devices;
 
 pub mod voice;
@@ -47,9 +58,14 @@ pub use fixedmath::midi_note_to_frequency;
 pub use fixedmath::Frequency as FrequencyFxP;
 pub use fixedmath::Note as NoteFxP;
 pub use fixedmath::Sample as SampleFxP;
+#[cfg(feature = "fixed")]
+pub use fixedmath::SampleClip;
 pub use fixedmath::Scalar as ScalarFxP;
 pub use fixedmath::SignedNote as SignedNoteFxP;
 pub use fixedmath::USample as USampleFxP;
+pub use fixedmath::exp2_fixed;
+#[cfg(feature = "fixed")]
+pub use fixedmath::log2_fixed;
 /// An envelope rise/fall time parameter, represented in seconds as an unsigned
 /// 16 bit fixed point number with 13 fractional bits and 3 integral bits.  This
 /// yields a range of 0 to 8 seconds - though as implemented this timing is not
@@ -72,6 +88,6 @@ mod float_traits;
 pub use float_traits::Float;
 
 mod dsp_format;
-pub use dsp_format::{DspFloat, DspFormat, DspFormatBase, DspType};
+pub use dsp_format::{DspFloat, DspFormat, DspFormatBase, DspFormatContext, DspSerde, DspType};
 
 type WideSampleFxP = FixedI32<<SampleFxP as Fixed>::Frac>;
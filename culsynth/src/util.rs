@@ -1,11 +1,33 @@
 //! Various utility functions and helpful constants
 
+pub mod fft;
+pub mod fracdelay;
+pub mod lerp;
+pub mod midi;
+pub mod src_conv;
+pub use fft::Fft;
+pub use fracdelay::{FracDelayReader, InterpMode};
+pub use lerp::{Lerp, LerpBuffer};
+pub use midi::MidiEvent;
+pub use src_conv::SampleRateConverter;
+
 // currently the only users of this function are unit tests... shut up dead code warning
 #[cfg(test)]
 pub fn calculate_cents(base: f32, freq: f32) -> f32 {
     1200.0 * f32::log2(freq / base)
 }
 
+/// Returns the length of the shortest buffer, given the lengths of each
+/// buffer (or zero if there are no buffers).  This is useful for sizing the
+/// output of slice-based processing - note that [Device::process] already
+/// stops at the end of the shortest of its inputs.
+///
+/// [Device::process]: crate::devices::Device::process
+#[must_use]
+pub fn min_size(sizes: &[usize]) -> usize {
+    sizes.iter().copied().min().unwrap_or(0)
+}
+
 /*
 // Is this the right place for this?
 pub fn midi_note_pretty(note: i8) -> String {
@@ -27,3 +49,15 @@ pub const SQ_CHARSTR: &str = "\u{238D}";
 pub const TRI_CHARSTR: &str = "\u{039B}";
 /// A character depicting a sawtooth wave (⩘).  This is the "sloping large and".
 pub const SAW_CHARSTR: &str = "\u{2A58}";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn min_size_of_mixed_lengths() {
+        assert_eq!(min_size(&[64, 16, 128, 32]), 16);
+        assert_eq!(min_size(&[7]), 7);
+        assert_eq!(min_size(&[16, 0, 16]), 0);
+        assert_eq!(min_size(&[]), 0);
+    }
+}
@@ -0,0 +1,234 @@
+//! A simple power-of-two FFT for spectral analysis (e.g. displaying the
+//! spectrum of the synth's output or the response of a filter).
+//!
+//! This is a straightforward in-place radix-2 Cooley-Tukey implementation.
+//! It does not allocate, and is intended for analysis rather than for use in
+//! the audio path, so it only supports `f32`.
+
+use crate::Float;
+
+/// A complex number with `f32` real and imaginary parts
+#[derive(Clone, Copy, PartialEq, Default, Debug)]
+pub struct Complex32 {
+    /// The real part
+    pub re: f32,
+    /// The imaginary part
+    pub im: f32,
+}
+
+impl Complex32 {
+    /// Create a new complex number `re + j*im`
+    pub const fn new(re: f32, im: f32) -> Self {
+        Self { re, im }
+    }
+    /// The magnitude (absolute value) of this complex number
+    pub fn norm(self) -> f32 {
+        sqrt(self.norm_sqr())
+    }
+    /// The square of the magnitude of this complex number
+    pub fn norm_sqr(self) -> f32 {
+        self.re * self.re + self.im * self.im
+    }
+    fn mul(self, rhs: Self) -> Self {
+        Self {
+            re: self.re * rhs.re - self.im * rhs.im,
+            im: self.re * rhs.im + self.im * rhs.re,
+        }
+    }
+    fn add(self, rhs: Self) -> Self {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+    fn sub(self, rhs: Self) -> Self {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl From<f32> for Complex32 {
+    fn from(re: f32) -> Self {
+        Self { re, im: 0f32 }
+    }
+}
+
+/// Square root for use in `no_std` builds: without libm, a bit-level
+/// initial estimate is refined with a few Newton-Raphson iterations, which
+/// is accurate to about the full `f32` precision
+fn sqrt(x: f32) -> f32 {
+    #[cfg(feature = "libm")]
+    let ret = num_traits::Float::sqrt(x);
+    #[cfg(not(feature = "libm"))]
+    let ret = if x > 0f32 && x.is_finite() {
+        // Halving the exponent gets us within about 6% of the answer
+        let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1FC0_0000);
+        for _ in 0..3 {
+            y = (y + x / y) * 0.5f32;
+        }
+        y
+    } else if x < 0f32 {
+        f32::NAN
+    } else {
+        x
+    };
+    ret
+}
+
+/// A forward FFT of a fixed, power-of-two size
+#[derive(Clone, Copy, Debug)]
+pub struct Fft {
+    size: usize,
+    bits: u32,
+}
+
+impl Fft {
+    /// Create a new FFT of `size` points, or `None` if `size` is not a power
+    /// of two
+    pub fn new(size: usize) -> Option<Self> {
+        if !size.is_power_of_two() {
+            return None;
+        }
+        Some(Self {
+            size,
+            bits: size.trailing_zeros(),
+        })
+    }
+    /// The number of points in this FFT
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    /// The number of bins in the magnitude spectrum, from DC to Nyquist
+    /// (inclusive)
+    pub fn num_bins(&self) -> usize {
+        self.size / 2 + 1
+    }
+    /// The twiddle factor `e^(-j*2*pi/len)` for a butterfly span of `len`
+    fn twiddle(len: usize) -> Complex32 {
+        match len {
+            // The approximations are only accurate for small angles, so use
+            // exact values for the two largest angles
+            2 => Complex32::new(-1f32, 0f32),
+            4 => Complex32::new(0f32, -1f32),
+            _ => {
+                let theta = -f32::TAU / len as f32;
+                Complex32::new(theta.fcos(), theta.fsin())
+            }
+        }
+    }
+    /// Calculate the (unnormalized) forward FFT of `buf` in place.
+    ///
+    /// Returns false, leaving `buf` unchanged, if the length of `buf` is not
+    /// the size of this FFT.
+    pub fn forward(&self, buf: &mut [Complex32]) -> bool {
+        if buf.len() != self.size {
+            return false;
+        }
+        if self.size < 2 {
+            return true;
+        }
+        // Put the input in bit-reversed order
+        for i in 0..self.size {
+            let j = i.reverse_bits() >> (usize::BITS - self.bits);
+            if i < j {
+                buf.swap(i, j);
+            }
+        }
+        let mut len = 2;
+        while len <= self.size {
+            let w_len = Self::twiddle(len);
+            for chunk in buf.chunks_exact_mut(len) {
+                let (lower, upper) = chunk.split_at_mut(len / 2);
+                let mut w = Complex32::new(1f32, 0f32);
+                for (a, b) in lower.iter_mut().zip(upper.iter_mut()) {
+                    let t = b.mul(w);
+                    *b = a.sub(t);
+                    *a = a.add(t);
+                    w = w.mul(w_len);
+                }
+            }
+            len <<= 1;
+        }
+        true
+    }
+    /// Calculate the magnitude spectrum of the real signal `signal`, using
+    /// `scratch` as working space.  The magnitude of each bin from DC to
+    /// Nyquist (see [Fft::num_bins]) is written to `mags`, or as many bins
+    /// as will fit.  Bin `k` is centered on `k * sample_rate / size` Hz.
+    ///
+    /// Returns false, leaving `mags` unchanged, if the length of `signal` or
+    /// `scratch` is not the size of this FFT.
+    pub fn magnitude_spectrum(
+        &self,
+        signal: &[f32],
+        scratch: &mut [Complex32],
+        mags: &mut [f32],
+    ) -> bool {
+        if signal.len() != self.size || scratch.len() != self.size {
+            return false;
+        }
+        for (x, smp) in scratch.iter_mut().zip(signal) {
+            *x = Complex32::from(*smp);
+        }
+        self.forward(scratch);
+        for (mag, x) in mags.iter_mut().zip(&scratch[..self.num_bins()]) {
+            *mag = x.norm();
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert!(Fft::new(0).is_none());
+        assert!(Fft::new(100).is_none());
+        assert!(Fft::new(4097).is_none());
+        for bits in 6..=12 {
+            let fft = Fft::new(1 << bits).unwrap();
+            assert_eq!(fft.size(), 1 << bits);
+            assert_eq!(fft.num_bins(), (1 << (bits - 1)) + 1);
+        }
+        let mut buf = [Complex32::default(); 32];
+        assert!(!Fft::new(64).unwrap().forward(&mut buf));
+    }
+
+    #[test]
+    fn impulse_is_flat() {
+        let fft = Fft::new(64).unwrap();
+        let mut buf = [Complex32::default(); 64];
+        buf[0] = Complex32::from(1f32);
+        assert!(fft.forward(&mut buf));
+        assert!(buf.iter().all(|x| (x.re - 1f32).abs() < 1e-5 && x.im.abs() < 1e-5));
+    }
+
+    #[test]
+    fn sine_peak_bin() {
+        const SAMPLE_RATE: f32 = 44100f32;
+        const SIZE: usize = 4096;
+        let fft = Fft::new(SIZE).unwrap();
+        let mut signal = [0f32; SIZE];
+        for (i, smp) in signal.iter_mut().enumerate() {
+            *smp = (core::f32::consts::TAU * 440f32 * i as f32 / SAMPLE_RATE).sin();
+        }
+        let mut scratch = [Complex32::default(); SIZE];
+        let mut mags = [0f32; SIZE / 2 + 1];
+        assert!(fft.magnitude_spectrum(&signal, &mut scratch, &mut mags));
+        let peak = (0..mags.len()).max_by(|a, b| mags[*a].total_cmp(&mags[*b])).unwrap();
+        // 440Hz is bin 40.87
+        let expected = (440f32 * SIZE as f32 / SAMPLE_RATE).round() as usize;
+        assert_eq!(peak, expected);
+        // Most of the energy is near the peak (some leaks, as there's no window)
+        let near: f32 = mags[peak - 2..=peak + 2].iter().map(|m| m * m).sum();
+        let total: f32 = mags.iter().map(|m| m * m).sum();
+        assert!(near / total > 0.9, "{}", near / total);
+    }
+
+    #[test]
+    fn sqrt_accuracy() {
+        for x in [1e-6f32, 0.01, 0.5, 1., 2., 440., 1e6, 3.3e12] {
+            assert!((sqrt(x) / x.sqrt() - 1f32).abs() < 1e-6, "{}", x);
+        }
+        assert_eq!(sqrt(0f32), 0f32);
+        assert!(sqrt(-1f32).is_nan());
+    }
+}
@@ -0,0 +1,175 @@
+//! Reading a delay line at a fractional delay.
+//!
+//! Modulated delay effects (e.g. chorus, or a Karplus-Strong string tuned
+//! between samples) need to read their delay line between samples.  Linear
+//! interpolation is cheap, but acts as a low pass filter at fractional
+//! delays, which is audible as a modulated loss of high frequencies.
+//! [InterpMode] selects a more accurate (and more expensive) alternative.
+
+use crate::Float;
+use core::mem::transmute;
+
+/// The interpolation used to read between the samples of a delay line
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum InterpMode {
+    /// Linear interpolation between the two nearest samples
+    #[default]
+    Linear,
+    /// 4 point, 3rd order Hermite interpolation
+    Cubic,
+    /// First order allpass interpolation.  This has a flat magnitude
+    /// response, but is stateful, so it is best suited to delays that change
+    /// slowly
+    Allpass,
+}
+
+impl InterpMode {
+    const ELEM: [InterpMode; 3] = [Self::Linear, Self::Cubic, Self::Allpass];
+    /// Returns a slice of all of the possible interpolation modes
+    pub const fn modes() -> &'static [InterpMode] {
+        &Self::ELEM
+    }
+    /// Provides the name of the interpolation mode
+    pub const fn to_str(&self) -> &'static str {
+        ["Linear", "Cubic", "Allpass"][*self as usize]
+    }
+}
+
+impl TryFrom<u8> for InterpMode {
+    type Error = &'static str;
+    fn try_from(value: u8) -> Result<Self, &'static str> {
+        if value <= InterpMode::Allpass as u8 {
+            unsafe { Ok(transmute::<u8, InterpMode>(value)) }
+        } else {
+            Err("Conversion of u8 to InterpMode Overflowed")
+        }
+    }
+}
+
+/// Reads a circular delay line at a fractional delay, using an [InterpMode]
+#[derive(Default, Clone)]
+pub struct FracDelayReader<T: Float> {
+    mode: InterpMode,
+    /// The last output, for allpass interpolation
+    last: T,
+    /// Set when the allpass state was reset (see [Self::take_state_reset])
+    state_reset: bool,
+}
+
+impl<T: Float> FracDelayReader<T> {
+    /// Create a new reader using `mode` to interpolate
+    pub fn new(mode: InterpMode) -> Self {
+        Self {
+            mode,
+            last: T::ZERO,
+            state_reset: false,
+        }
+    }
+    /// The interpolation mode in use
+    pub fn mode(&self) -> InterpMode {
+        self.mode
+    }
+    /// Change the interpolation mode, resetting any interpolator state
+    pub fn set_mode(&mut self, mode: InterpMode) {
+        self.mode = mode;
+        self.last = T::ZERO;
+    }
+    /// Returns true if the allpass interpolator has reset its state because
+    /// it became NaN or infinite since the last call (see
+    /// [crate::devices::Filt::take_state_reset])
+    pub fn take_state_reset(&mut self) -> bool {
+        core::mem::take(&mut self.state_reset)
+    }
+    /// Read the sample `delay` samples before the most recent sample of
+    /// `buf`, a circular buffer whose most recent sample is at `head`.
+    ///
+    /// The delay is limited to `[0, buf.len() - 3]` so that every mode has
+    /// the samples it needs, so `buf` must hold at least 3 samples.  Allpass
+    /// interpolation also needs a delay of at least one sample.
+    pub fn read(&mut self, buf: &[T], head: usize, delay: T) -> T {
+        let len = buf.len();
+        let max_delay: T = num_traits::cast(len - 3).unwrap_or(T::ZERO);
+        let delay = delay.max(T::ZERO).min(max_delay);
+        let int = delay.floor();
+        let frac = delay - int;
+        let int = int.to_usize().unwrap_or(0);
+        // The sample `k` samples before the most recent one
+        let tap = |k: usize| buf[(head + len - k) % len];
+        let (x0, x1) = (tap(int), tap(int + 1));
+        match self.mode {
+            InterpMode::Linear => x0 + (x1 - x0) * frac,
+            InterpMode::Cubic => {
+                // Repeat the most recent sample if there is no newer one
+                let (xm1, x2) = (tap(int.saturating_sub(1)), tap(int + 2));
+                let c1 = (x1 - xm1) * T::ONE_HALF;
+                let c2 = xm1 - (T::TWO + T::ONE_HALF) * x0 + T::TWO * x1 - x2 * T::ONE_HALF;
+                let c3 = (x2 - xm1) * T::ONE_HALF + (x0 - x1) * (T::ONE + T::ONE_HALF);
+                ((c3 * frac + c2) * frac + c1) * frac + x0
+            }
+            InterpMode::Allpass => {
+                // Filter the sample one newer by between 1 and 2 samples,
+                // where the phase response is closest to linear and a whole
+                // number of samples is exact
+                let xm1 = tap(int.saturating_sub(1));
+                let eta = -frac / (T::TWO + frac);
+                self.last = eta * (xm1 - self.last) + x0;
+                let last = self.last;
+                let input_finite = x0.is_finite() && xm1.is_finite();
+                self.state_reset |=
+                    crate::devices::guard::guard_state(input_finite, [&mut self.last]);
+                last
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEN: usize = 64;
+    const DELAY: f32 = 10.37;
+    /// Cycles per sample
+    const FREQ: f32 = 0.05;
+
+    /// The RMS error reading a sine wave through a delay line at a
+    /// fractional delay, after giving the interpolator time to settle
+    fn rms_error(mode: InterpMode) -> f32 {
+        let mut buf = [0f32; LEN];
+        let mut reader = FracDelayReader::new(mode);
+        let phase = |n: f32| core::f32::consts::TAU * FREQ * n;
+        let mut error = 0f32;
+        for n in 0..2000 {
+            let head = n % LEN;
+            buf[head] = phase(n as f32).sin();
+            let out = reader.read(&buf, head, DELAY);
+            if n >= 1000 {
+                let expected = phase(n as f32 - DELAY).sin();
+                error += (out - expected) * (out - expected);
+            }
+        }
+        (error / 1000f32).sqrt()
+    }
+
+    #[test]
+    fn cubic_beats_linear() {
+        let linear = rms_error(InterpMode::Linear);
+        let cubic = rms_error(InterpMode::Cubic);
+        let allpass = rms_error(InterpMode::Allpass);
+        assert!(cubic < linear / 4f32, "{} {}", cubic, linear);
+        assert!(allpass < linear, "{} {}", allpass, linear);
+    }
+
+    #[test]
+    fn integer_delay_is_exact() {
+        let buf: [f32; 8] = core::array::from_fn(|i| i as f32);
+        for mode in InterpMode::modes() {
+            let mut reader = FracDelayReader::new(*mode);
+            assert_eq!(reader.read(&buf, 7, 3f32), 4f32, "{}", mode.to_str());
+        }
+        // Wraps around the start of the buffer
+        let mut reader = FracDelayReader::new(InterpMode::Linear);
+        assert_eq!(reader.read(&buf, 1, 2.5f32), 6.5f32);
+    }
+}
@@ -0,0 +1,135 @@
+//! Linear interpolation of a parameter across a buffer of samples.
+//!
+//! When a parameter is only updated once per buffer, stepping straight to the
+//! new value at the start of each buffer can cause audible "zipper noise".
+//! [LerpBuffer] instead ramps linearly from the value at the end of the last
+//! buffer to the new value over the length of the next one.
+
+use fixed::types::extra::LeEqU16;
+use fixed::{FixedI16, FixedU16};
+
+/// A value that can be linearly interpolated.  Fixed point values are
+/// interpolated on their raw bits, so the result is always between the two
+/// endpoints and never overflows.
+pub trait Lerp: Copy {
+    /// Returns `start + (end - start) * num / den`, where `num <= den`
+    fn lerp(start: Self, end: Self, num: usize, den: usize) -> Self;
+}
+
+impl<T: crate::Float> Lerp for T {
+    fn lerp(start: T, end: T, num: usize, den: usize) -> T {
+        let frac: T = num_traits::cast(num as f64 / den as f64).unwrap_or_default();
+        start + (end - start) * frac
+    }
+}
+
+fn lerp_bits(start: i64, end: i64, num: usize, den: usize) -> i64 {
+    start + (end - start) * num as i64 / den as i64
+}
+
+impl<N: LeEqU16> Lerp for FixedU16<N> {
+    fn lerp(start: Self, end: Self, num: usize, den: usize) -> Self {
+        let bits = lerp_bits(start.to_bits().into(), end.to_bits().into(), num, den);
+        Self::from_bits(bits as u16)
+    }
+}
+
+impl<N: LeEqU16> Lerp for FixedI16<N> {
+    fn lerp(start: Self, end: Self, num: usize, den: usize) -> Self {
+        let bits = lerp_bits(start.to_bits().into(), end.to_bits().into(), num, den);
+        Self::from_bits(bits as i16)
+    }
+}
+
+/// Interpolates a parameter linearly from one buffer to the next
+///
+/// Each call to [LerpBuffer::next_block] fills a buffer with a ramp from the
+/// last value of the previous buffer to a new target, ending exactly on the
+/// target.  This is much cheaper than a per-sample slew limiter, at the cost
+/// of only changing direction at buffer boundaries.
+#[derive(Clone, Default)]
+pub struct LerpBuffer<T: Lerp> {
+    last: T,
+}
+
+impl<T: Lerp> LerpBuffer<T> {
+    /// Create a new `LerpBuffer`, starting at `initial`
+    pub fn new(initial: T) -> Self {
+        Self { last: initial }
+    }
+    /// The value at the end of the last buffer
+    pub fn last(&self) -> T {
+        self.last
+    }
+    /// Jump to `value` without interpolating (e.g. when a voice is reset)
+    pub fn reset(&mut self, value: T) {
+        self.last = value;
+    }
+    /// Fill `buf` with a ramp from the last value towards `target`, with the
+    /// last element set to `target`.  The step from the end of the previous
+    /// buffer to the first element is the same as the step between elements.
+    pub fn next_block(&mut self, target: T, buf: &mut [T]) {
+        let len = buf.len();
+        for (i, smp) in buf.iter_mut().enumerate() {
+            *smp = T::lerp(self.last, target, i + 1, len);
+        }
+        if len > 0 {
+            self.last = target;
+        }
+    }
+    /// Fill `buf` with a ramp from `start` (the first element) to `end` (the
+    /// last element), with uniform spacing.  A buffer with a single element
+    /// is set to `end`.
+    pub fn fill(start: T, end: T, buf: &mut [T]) {
+        let den = buf.len().saturating_sub(1);
+        for (i, smp) in buf.iter_mut().enumerate() {
+            *smp = if den == 0 {
+                end
+            } else {
+                T::lerp(start, end, i, den)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{NoteFxP, ScalarFxP};
+
+    #[test]
+    fn fill_uniform() {
+        let mut buf = [0f32; 256];
+        LerpBuffer::fill(0f32, 1f32, &mut buf);
+        assert!(buf[0].abs() < 1e-6);
+        assert!((buf[255] - 1f32).abs() < 1e-6);
+        for pair in buf.windows(2) {
+            assert!((pair[1] - pair[0] - 1f32 / 255f32).abs() < 1e-6);
+        }
+        LerpBuffer::fill(0.25f32, 0.25f32, &mut buf);
+        assert!(buf.iter().all(|x| *x == 0.25f32));
+    }
+
+    #[test]
+    fn fill_fixed() {
+        let mut buf = [ScalarFxP::ZERO; 256];
+        LerpBuffer::fill(ScalarFxP::MAX, ScalarFxP::ZERO, &mut buf);
+        assert_eq!((buf[0], buf[255]), (ScalarFxP::MAX, ScalarFxP::ZERO));
+        assert!(buf.windows(2).all(|pair| pair[1] < pair[0]));
+    }
+
+    #[test]
+    fn blocks_are_continuous() {
+        let start = NoteFxP::lit("60");
+        let mut lerp = LerpBuffer::new(start);
+        let mut buf = [NoteFxP::ZERO; 64];
+        lerp.next_block(NoteFxP::lit("64"), &mut buf[..32]);
+        lerp.next_block(NoteFxP::lit("68"), &mut buf[32..]);
+        assert_eq!(buf[31], NoteFxP::lit("64"));
+        assert_eq!(lerp.last(), NoteFxP::lit("68"));
+        // Two blocks with the same slope are the same as one long ramp
+        let mut expected = [NoteFxP::ZERO; 65];
+        LerpBuffer::fill(start, NoteFxP::lit("68"), &mut expected);
+        assert_eq!(buf[..], expected[1..]);
+    }
+}
@@ -0,0 +1,215 @@
+//! Parsing of raw MIDI channel messages
+
+/// A MIDI channel message.  Channels are numbered from 0 to 15.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MidiEvent {
+    /// Note on.  A note on with zero velocity is parsed as a [MidiEvent::NoteOff]
+    NoteOn {
+        /// The MIDI channel
+        channel: u8,
+        /// The MIDI note number
+        note: u8,
+        /// The note on velocity (1-127)
+        velocity: u8,
+    },
+    /// Note off
+    NoteOff {
+        /// The MIDI channel
+        channel: u8,
+        /// The MIDI note number
+        note: u8,
+        /// The note off (release) velocity
+        velocity: u8,
+    },
+    /// Control change
+    ControlChange {
+        /// The MIDI channel
+        channel: u8,
+        /// The controller number
+        cc: u8,
+        /// The new value of the controller
+        value: u8,
+    },
+    /// Pitch bend
+    PitchBend {
+        /// The MIDI channel
+        channel: u8,
+        /// The 14 bit pitch bend amount, from -8192 to 8191 (centered at zero)
+        amount: i16,
+    },
+    /// Channel pressure (aftertouch)
+    ChannelPressure {
+        /// The MIDI channel
+        channel: u8,
+        /// The pressure value
+        pressure: u8,
+    },
+    /// Polyphonic key pressure (aftertouch)
+    PolyPressure {
+        /// The MIDI channel
+        channel: u8,
+        /// The MIDI note number
+        note: u8,
+        /// The pressure value
+        pressure: u8,
+    },
+}
+
+impl MidiEvent {
+    const NOTE_OFF: u8 = 0x80;
+    const NOTE_ON: u8 = 0x90;
+    const POLY_PRESSURE: u8 = 0xA0;
+    const CONTROL_CHANGE: u8 = 0xB0;
+    const CHANNEL_PRESSURE: u8 = 0xD0;
+    const PITCH_BEND: u8 = 0xE0;
+    /// The value of a centered pitch bend
+    const PITCH_BEND_CENTER: i16 = 0x2000;
+
+    /// Parse a MIDI channel message from its status byte and (up to) two data
+    /// bytes.  Unused data bytes are ignored (e.g. `data2` for a channel
+    /// pressure message).
+    ///
+    /// Returns `None` for messages that are not represented by a [MidiEvent]
+    /// (e.g. program changes or system messages), if `status` is not a status
+    /// byte, or if a data byte has its high bit set.
+    pub fn parse(status: u8, data1: u8, data2: u8) -> Option<MidiEvent> {
+        if (data1 | data2) & 0x80 != 0 {
+            return None;
+        }
+        let channel = status & 0x0F;
+        match status & 0xF0 {
+            Self::NOTE_ON if data2 != 0 => Some(Self::NoteOn {
+                channel,
+                note: data1,
+                velocity: data2,
+            }),
+            Self::NOTE_OFF | Self::NOTE_ON => Some(Self::NoteOff {
+                channel,
+                note: data1,
+                velocity: data2,
+            }),
+            Self::POLY_PRESSURE => Some(Self::PolyPressure {
+                channel,
+                note: data1,
+                pressure: data2,
+            }),
+            Self::CONTROL_CHANGE => Some(Self::ControlChange {
+                channel,
+                cc: data1,
+                value: data2,
+            }),
+            Self::CHANNEL_PRESSURE => Some(Self::ChannelPressure {
+                channel,
+                pressure: data1,
+            }),
+            Self::PITCH_BEND => {
+                // The LSB is sent first
+                let value = ((data2 as i16) << 7) | data1 as i16;
+                Some(Self::PitchBend {
+                    channel,
+                    amount: value - Self::PITCH_BEND_CENTER,
+                })
+            }
+            _ => None,
+        }
+    }
+    /// The MIDI channel of this event
+    pub fn channel(&self) -> u8 {
+        match *self {
+            Self::NoteOn { channel, .. }
+            | Self::NoteOff { channel, .. }
+            | Self::ControlChange { channel, .. }
+            | Self::PitchBend { channel, .. }
+            | Self::ChannelPressure { channel, .. }
+            | Self::PolyPressure { channel, .. } => channel,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn parse_notes() {
+        assert_eq!(
+            MidiEvent::parse(0x93, 60, 100),
+            Some(MidiEvent::NoteOn {
+                channel: 3,
+                note: 60,
+                velocity: 100
+            })
+        );
+        assert_eq!(
+            MidiEvent::parse(0x80, 60, 64),
+            Some(MidiEvent::NoteOff {
+                channel: 0,
+                note: 60,
+                velocity: 64
+            })
+        );
+        // A note on with zero velocity is commonly used as a note off
+        assert_eq!(
+            MidiEvent::parse(0x9F, 61, 0),
+            Some(MidiEvent::NoteOff {
+                channel: 15,
+                note: 61,
+                velocity: 0
+            })
+        );
+    }
+    #[test]
+    fn parse_controls() {
+        assert_eq!(
+            MidiEvent::parse(0xB1, 1, 127),
+            Some(MidiEvent::ControlChange {
+                channel: 1,
+                cc: 1,
+                value: 127
+            })
+        );
+        assert_eq!(
+            MidiEvent::parse(0xD2, 90, 0),
+            Some(MidiEvent::ChannelPressure {
+                channel: 2,
+                pressure: 90
+            })
+        );
+        assert_eq!(
+            MidiEvent::parse(0xA4, 60, 33),
+            Some(MidiEvent::PolyPressure {
+                channel: 4,
+                note: 60,
+                pressure: 33
+            })
+        );
+    }
+    #[test]
+    fn parse_pitch_bend() {
+        let bend = |lsb, msb| match MidiEvent::parse(0xE5, lsb, msb) {
+            Some(MidiEvent::PitchBend { channel: 5, amount }) => amount,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(bend(0x00, 0x40), 0);
+        assert_eq!(bend(0x00, 0x00), -8192);
+        assert_eq!(bend(0x7F, 0x7F), 8191);
+        // The LSB carries the low 7 bits
+        assert_eq!(bend(0x01, 0x40), 1);
+        assert_eq!(bend(0x7F, 0x3F), -1);
+        assert_eq!(bend(0x00, 0x41), 128);
+    }
+    #[test]
+    fn parse_invalid() {
+        // Program change and system messages aren't represented
+        assert_eq!(MidiEvent::parse(0xC0, 5, 0), None);
+        assert_eq!(MidiEvent::parse(0xF8, 0, 0), None);
+        // Not a status byte
+        assert_eq!(MidiEvent::parse(0x40, 60, 100), None);
+        // Data bytes can't have the high bit set
+        assert_eq!(MidiEvent::parse(0x90, 0x80, 100), None);
+        assert_eq!(MidiEvent::parse(0x90, 60, 0xFF), None);
+        assert_eq!(
+            MidiEvent::parse(0x97, 60, 100).map(|e| e.channel()),
+            Some(7)
+        );
+    }
+}
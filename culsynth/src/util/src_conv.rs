@@ -0,0 +1,243 @@
+//! Sample rate conversion by a rational ratio (e.g. 48kHz to 44.1kHz, which
+//! is 147:160).
+//!
+//! The fixed point engine only supports a handful of sample rates (see
+//! [crate::context::FixedSampleRate]), so this can be used to run it at a
+//! supported rate and convert to or from the rate of the rest of the system.
+//!
+//! [SampleRateConverter] is a polyphase windowed-sinc FIR resampler.  To
+//! avoid storing a table of coefficients for each of the (possibly hundreds
+//! of) phases, the coefficients for each output sample are calculated as
+//! they are needed.  It does not allocate, and only supports `f32`.
+
+/// The number of input samples used to calculate each output sample.  This
+/// is also twice the latency of the converter, in input samples.
+pub const SRC_TAPS: usize = 32;
+
+const HALF_TAPS: i32 = (SRC_TAPS / 2) as i32;
+
+/// The cutoff of the anti-aliasing filter, as a fraction of the lower of
+/// the two Nyquist frequencies, leaving room for the transition band
+const CUTOFF: f32 = 0.9;
+
+/// `floor(x)` for `no_std` builds, for values well within the range of `i64`
+fn floor(x: f32) -> i64 {
+    let trunc = x as i64;
+    if trunc as f32 > x {
+        trunc - 1
+    } else {
+        trunc
+    }
+}
+
+/// `sin(pi * x)`.  `Float::fsin` is only accurate for small angles (and
+/// rounds very small angles) without libm, so reduce the angle to
+/// `[-pi/2, pi/2]` and use a longer Taylor series
+fn sin_pi(x: f32) -> f32 {
+    // sin(pi * (n + r)) = (-1)^n * sin(pi * r), where |r| <= 0.5
+    let n = floor(x + 0.5f32);
+    let y = core::f32::consts::PI * (x - n as f32);
+    let y2 = y * y;
+    // y - y^3/3! + y^5/5! - ... - y^11/11!, which is accurate to about 1e-7
+    let sin = [10, 8, 6, 4, 2]
+        .iter()
+        .fold(1f32, |acc, k| 1f32 - acc * y2 / (k * (k + 1)) as f32)
+        * y;
+    if n % 2 == 0 {
+        sin
+    } else {
+        -sin
+    }
+}
+
+/// `cos(pi * x)`
+fn cos_pi(x: f32) -> f32 {
+    sin_pi(x + 0.5f32)
+}
+
+const fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Converts a stream of samples from one sample rate to another
+///
+/// Each output sample is calculated from the [SRC_TAPS] input samples
+/// around it, using a Blackman windowed sinc filter with its cutoff just
+/// below the lower of the two Nyquist frequencies.  This both interpolates
+/// between input samples and removes any content that would alias in the
+/// output.  The output is delayed by `SRC_TAPS / 2` input samples.
+#[derive(Clone)]
+pub struct SampleRateConverter {
+    /// Output samples per input sample is `up / down`, in lowest terms
+    up: u32,
+    down: u32,
+    /// The cutoff of the filter, relative to the input Nyquist frequency
+    cutoff: f32,
+    /// The most recent input samples, as a ring buffer
+    history: [f32; SRC_TAPS],
+    /// The index of the most recent input sample in `history`
+    newest: usize,
+    /// The time of the next output sample, relative to the most recent
+    /// input sample, is `next_int + next_frac / up` input samples
+    next_int: i32,
+    next_frac: u32,
+}
+
+impl SampleRateConverter {
+    /// Create a new converter from `from_rate` to `to_rate` (in Hz), or
+    /// `None` if either rate is zero
+    pub fn new(from_rate: u32, to_rate: u32) -> Option<Self> {
+        if from_rate == 0 || to_rate == 0 {
+            return None;
+        }
+        let div = gcd(from_rate, to_rate);
+        let (up, down) = (to_rate / div, from_rate / div);
+        let cutoff = if up < down {
+            CUTOFF * up as f32 / down as f32
+        } else {
+            CUTOFF
+        };
+        Some(Self {
+            up,
+            down,
+            cutoff,
+            history: [0f32; SRC_TAPS],
+            newest: 0,
+            next_int: 1,
+            next_frac: 0,
+        })
+    }
+    /// The conversion ratio (output samples per input sample), as
+    /// `(numerator, denominator)` in lowest terms.  For example, converting
+    /// from 48kHz to 44.1kHz is `(147, 160)`.
+    pub fn ratio(&self) -> (u32, u32) {
+        (self.up, self.down)
+    }
+    /// The largest number of output samples that processing `input_len`
+    /// input samples can produce
+    pub fn max_output_len(&self, input_len: usize) -> usize {
+        (input_len * self.up as usize).div_ceil(self.down as usize)
+    }
+    /// Clear the history of the converter, as if it had just been created
+    pub fn reset(&mut self) {
+        self.history = [0f32; SRC_TAPS];
+        self.newest = 0;
+        self.next_int = 1;
+        self.next_frac = 0;
+    }
+    /// The filter kernel, `x` input samples from its center
+    fn kernel(&self, x: f32) -> f32 {
+        let half = HALF_TAPS as f32;
+        if x.abs() >= half {
+            return 0f32;
+        }
+        let window = 0.42f32 + 0.5f32 * cos_pi(x / half) + 0.08f32 * cos_pi(2f32 * x / half);
+        let arg = self.cutoff * x;
+        let sinc = if arg.abs() < 1e-6 {
+            1f32
+        } else {
+            sin_pi(arg) / (core::f32::consts::PI * arg)
+        };
+        self.cutoff * sinc * window
+    }
+    /// Calculate the output sample at the time given by `next_int` and
+    /// `next_frac`
+    fn output(&self) -> f32 {
+        let frac = self.next_frac as f32 / self.up as f32;
+        (0..SRC_TAPS)
+            .map(|j| {
+                let smp = self.history[(self.newest + SRC_TAPS - j) % SRC_TAPS];
+                // Input sample `j` before the newest, relative to the output
+                smp * self.kernel(-(j as f32) - self.next_int as f32 - frac)
+            })
+            .sum()
+    }
+    /// Convert the samples in `input`, writing the result to `output`, and
+    /// return the number of samples written.
+    ///
+    /// The number of samples written varies from call to call, but is never
+    /// more than [SampleRateConverter::max_output_len].  If `output` is too
+    /// small, any output that doesn't fit is discarded.
+    pub fn process(&mut self, input: &[f32], output: &mut [f32]) -> usize {
+        let mut written = 0;
+        for smp in input {
+            self.newest = (self.newest + 1) % SRC_TAPS;
+            self.history[self.newest] = *smp;
+            self.next_int -= 1;
+            // Wait until all of the samples around the output have arrived
+            while self.next_int + HALF_TAPS <= 0 {
+                if let Some(out) = output.get_mut(written) {
+                    *out = self.output();
+                }
+                written += 1;
+                self.next_frac += self.down;
+                self.next_int += (self.next_frac / self.up) as i32;
+                self.next_frac %= self.up;
+            }
+        }
+        super::min_size(&[written, output.len()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ratios() {
+        assert_eq!(
+            SampleRateConverter::new(48000, 44100).unwrap().ratio(),
+            (147, 160)
+        );
+        assert_eq!(
+            SampleRateConverter::new(44100, 48000).unwrap().ratio(),
+            (160, 147)
+        );
+        assert_eq!(
+            SampleRateConverter::new(96000, 48000).unwrap().ratio(),
+            (1, 2)
+        );
+        assert!(SampleRateConverter::new(0, 48000).is_none());
+    }
+
+    #[test]
+    fn sin_pi_accuracy() {
+        for i in -400..=400 {
+            let x = i as f32 / 37f32;
+            let expected = (core::f64::consts::PI * x as f64).sin() as f32;
+            assert!((sin_pi(x) - expected).abs() < 1e-5, "{}", x);
+        }
+    }
+
+    #[test]
+    fn output_length() {
+        let mut src = SampleRateConverter::new(48000, 44100).unwrap();
+        let input = [0f32; 480];
+        let mut output = [0f32; 441];
+        let mut total = 0;
+        for _ in 0..100 {
+            let n = src.process(&input, &mut output);
+            assert!(n <= src.max_output_len(input.len()));
+            total += n;
+        }
+        // All but the latency of the filter has been output
+        let expected = 44100 - (HALF_TAPS as usize * 147 / 160);
+        assert!(total.abs_diff(expected) <= 1, "{}", total);
+    }
+
+    #[test]
+    fn dc_gain() {
+        for (from, to) in [(48000, 44100), (44100, 48000), (96000, 48000)] {
+            let mut src = SampleRateConverter::new(from, to).unwrap();
+            let mut output = [0f32; 1024];
+            let n = src.process(&[0.5f32; 1024], &mut output);
+            for out in &output[SRC_TAPS..n] {
+                assert!((out - 0.5f32).abs() < 1e-3, "{} {} {}", from, to, out);
+            }
+        }
+    }
+}
@@ -1,17 +1,19 @@
 //! This module contains a struct composing various devices together as a
 //! single voice unit for a basic subtractive synthesizer.
 
-use crate::{devices::*, DspFloat, DspFormat};
+use crate::{devices::*, DspFloat, DspFormat, DspFormatBase, DspType, IScalarFxP};
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
 
-use self::modulation::{ModMatrix, ModSection};
+use self::modulation::{ModMatrix, ModSection, ModSectionState};
 
 pub mod cc;
 pub mod modulation;
 pub mod nrpn;
 
 /// A parameter pack for a [Voice]
-#[derive(Clone, Default)]
-pub struct VoiceParams<T: DspFormat> {
+#[derive(Clone)]
+pub struct VoiceParams<T: DspFormatBase> {
     /// Oscillator section parameters
     pub oscs_p: SyncedMixOscsParams<T>,
     /// Ring-Mod
@@ -30,6 +32,44 @@ pub struct VoiceParams<T: DspFormat> {
     pub env1_p: EnvParams<T>,
     /// Modulation Envelope 2
     pub env2_p: EnvParams<T>,
+    /// Monitor the raw oscillator/ring mod output, bypassing the filter and
+    /// VCA (e.g. for tuning and calibration).  The filter and envelopes
+    /// continue to run so that disabling this resumes normally.
+    pub raw_osc: bool,
+    /// Analog pitch drift (only applied if `analog_drift` is set)
+    pub drift_p: AnalogDriftParams<T>,
+    /// Slowly drift the pitch of the oscillators to simulate an analog synth
+    pub analog_drift: bool,
+    /// Stretch tuning of the oscillators (the default is equal temperament)
+    pub stretch_p: StretchTuningParams<T>,
+    /// Reset the oscillators to their initial phase (zero unless set with
+    /// [Voice::set_initial_phase]) on the first sample of each note, rather
+    /// than letting them run freely
+    pub phase_reset: bool,
+}
+
+impl<T> Default for VoiceParams<T>
+where
+    T: DspFormatBase + env::detail::EnvParamOps + drift::detail::DriftParamOps,
+{
+    fn default() -> Self {
+        Self {
+            oscs_p: Default::default(),
+            ring_p: Default::default(),
+            filt_p: Default::default(),
+            filt_env_p: Default::default(),
+            amp_env_p: Default::default(),
+            lfo1_p: Default::default(),
+            lfo2_p: Default::default(),
+            env1_p: Default::default(),
+            env2_p: Default::default(),
+            raw_osc: false,
+            drift_p: Default::default(),
+            analog_drift: false,
+            stretch_p: Default::default(),
+            phase_reset: false,
+        }
+    }
 }
 
 impl<T: DspFloat> From<&VoiceParams<i16>> for VoiceParams<T> {
@@ -44,6 +84,11 @@ impl<T: DspFloat> From<&VoiceParams<i16>> for VoiceParams<T> {
             lfo2_p: (&value.lfo2_p).into(),
             env1_p: (&value.env1_p).into(),
             env2_p: (&value.env2_p).into(),
+            raw_osc: value.raw_osc,
+            drift_p: (&value.drift_p).into(),
+            analog_drift: value.analog_drift,
+            stretch_p: (&value.stretch_p).into(),
+            phase_reset: value.phase_reset,
         }
     }
 }
@@ -54,15 +99,47 @@ impl From<&VoiceParams<i16>> for VoiceParams<i16> {
     }
 }
 
+/// Parameters for the output stage of a [Voice]
+#[derive(Clone, Default)]
+pub struct VoiceOutputParams<T: DspFormatBase> {
+    /// The mix between the dry (pre-effect) and wet (post-effect) output of
+    /// the voice, from 0 (fully dry) to 1 (fully wet)
+    pub dry_wet: T::Scalar,
+}
+
+impl<T: DspFloat> From<&VoiceOutputParams<i16>> for VoiceOutputParams<T> {
+    fn from(value: &VoiceOutputParams<i16>) -> Self {
+        Self {
+            dry_wet: value.dry_wet.to_num(),
+        }
+    }
+}
+
+/// The output of [Voice::next_dry_wet]
+#[must_use]
+#[derive(Clone, Default)]
+pub struct VoiceDryWetOutput<T: DspFormat> {
+    /// The output of the voice before any built-in effects
+    pub dry: PanOutput<T>,
+    /// The output of the voice after any built-in effects
+    pub wet: PanOutput<T>,
+    /// The dry and wet outputs mixed according to
+    /// [VoiceOutputParams::dry_wet]
+    pub mix: PanOutput<T>,
+}
+
 /// Inputs for a [Voice] that are note-specific
 #[derive(Clone, Default)]
-pub struct VoiceInput<T: DspFormat> {
+pub struct VoiceInput<T: DspFormatBase> {
     /// The note itself, as a MIDI note number
     pub note: T::Note,
     /// The velocity this note was played with
     pub velocity: T::Scalar,
     /// The gate signal
     pub gate: bool,
+    /// The pan position of this voice, from -1 (hard left) to 1 (hard
+    /// right), which is offset by any pan modulation
+    pub pan: T::IScalar,
 }
 
 impl<T: DspFloat> From<&VoiceInput<i16>> for VoiceInput<T> {
@@ -71,6 +148,7 @@ impl<T: DspFloat> From<&VoiceInput<i16>> for VoiceInput<T> {
             note: value.note.to_num(),
             gate: value.gate,
             velocity: value.velocity.to_num(),
+            pan: value.pan.to_num(),
         }
     }
 }
@@ -83,11 +161,13 @@ impl From<&VoiceInput<i16>> for VoiceInput<i16> {
 
 /// Channel-wide (i.e. affecting all notes) inputs for a given [Voice]
 #[derive(Clone, Default)]
-pub struct VoiceChannelInput<T: DspFormat> {
+pub struct VoiceChannelInput<T: DspFormatBase> {
     /// Aftertouch (e.g. for a MIDI Channel Pressure Message)
     pub aftertouch: T::Scalar,
     /// Modulation Wheel (MIDI CC #1)
     pub modwheel: T::Scalar,
+    /// Level of an external sidechain signal (e.g. an envelope follower)
+    pub sidechain: T::Scalar,
 }
 
 impl<T: DspFloat> From<&VoiceChannelInput<i16>> for VoiceChannelInput<T> {
@@ -95,6 +175,7 @@ impl<T: DspFloat> From<&VoiceChannelInput<i16>> for VoiceChannelInput<T> {
         Self {
             aftertouch: value.aftertouch.to_num(),
             modwheel: value.modwheel.to_num(),
+            sidechain: value.sidechain.to_num(),
         }
     }
 }
@@ -105,18 +186,91 @@ impl From<&VoiceChannelInput<i16>> for VoiceChannelInput<i16> {
     }
 }
 
+/// A snapshot of the internal (post-modulation) state of a [Voice], for
+/// monitoring and display purposes
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct VoiceMonitor<T: DspFormat> {
+    /// The filter cutoff, after modulation
+    pub cutoff: T::Note,
+    /// The filter resonance, after modulation
+    pub resonance: T::Scalar,
+    /// The note played by the primary oscillator, after tuning, modulation,
+    /// and analog drift
+    pub note: T::Note,
+    /// The output of the VCA envelope
+    pub env_vca: T::Scalar,
+    /// The stage of the VCA envelope
+    pub env_vca_stage: EnvStage,
+    /// The output of the VCF envelope
+    pub env_vcf: T::Scalar,
+    /// The stage of the VCF envelope
+    pub env_vcf_stage: EnvStage,
+    /// The output of the VCF envelope, scaled by the filter's envelope
+    /// amount
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub env_vcf_scaled: T::Scalar,
+    /// The output of LFO 1
+    pub lfo1: T::Sample,
+    /// The output of LFO 2
+    pub lfo2: T::Sample,
+}
+
+/// The complete internal state of a [Voice] (oscillator phases, filter and
+/// envelope state, LFO and drift random number generators, and so on), so
+/// that a voice can be saved mid-note and resumed later from the same point.
+///
+/// This does not include the modulation matrix, which is part of the patch
+/// rather than the voice: [Voice::load_state] leaves it unchanged.  With the
+/// `serde` feature enabled, this implements `Serialize` and `Deserialize`.
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub struct VoiceState<T: DspFormat> {
+    // The ring modulator, VCA, and panner are stateless
+    oscs: SyncedMixOscs<T>,
+    phase_reset: PhaseReset<T>,
+    filt: ModFilt<T>,
+    env_amp: Env<T>,
+    env_filt: Env<T>,
+    drift: AnalogDrift<T>,
+    modsection: ModSectionState<T>,
+    monitor: VoiceMonitor<T>,
+}
+
 /// This struct encapsulates a single voice unit, containing a single oscillator,
 /// a single VCF (with modulation inputs and mixing of low/band/high pass outputs),
-/// a VCA, and two envelopes (one for the VCA and one for the VCF).
+/// a VCA, two envelopes (one for the VCA and one for the VCF), and a final
+/// master gain and stereo panner, controlled through the modulation matrix
+/// ([ModDest::MasterGain] and [ModDest::Pan]).  Optionally, the pitch of the
+/// oscillators can drift slowly (see [AnalogDrift]), the octaves can be
+/// stretched (see [StretchTuning]), and the oscillators can be reset to a
+/// fixed phase at the start of each note (see [PhaseReset] and
+/// [Voice::set_initial_phase]).
+///
+/// [ModDest::MasterGain]: modulation::ModDest::MasterGain
+/// [ModDest::Pan]: modulation::ModDest::Pan
 #[derive(Clone, Default)]
 pub struct Voice<T: DspFormat> {
     oscs: SyncedMixOscs<T>,
+    phase_reset: PhaseReset<T>,
     ringmod: RingMod<T>,
     filt: ModFilt<T>,
     env_amp: Env<T>,
     env_filt: Env<T>,
     vca: Amp<T>,
+    pan: Pan<T>,
+    drift: AnalogDrift<T>,
+    stretch: StretchTuning<T>,
     modsection: ModSection<T>,
+    monitor: VoiceMonitor<T>,
 }
 
 impl<T: DspFormat> Voice<T> {
@@ -128,10 +282,68 @@ impl<T: DspFormat> Voice<T> {
     pub fn new_with_seeds(seeda: u64, seedb: u64) -> Self {
         Self {
             modsection: ModSection::new_with_seeds(seeda, seedb),
+            drift: AnalogDrift::new(seeda ^ seedb.rotate_left(32)),
             ..Default::default()
         }
     }
-    /// Get the next sample from this voice.
+    /// Get the post-modulation state of this voice as of the last call to
+    /// [Voice::next]
+    pub fn monitor(&self) -> &VoiceMonitor<T> {
+        &self.monitor
+    }
+    /// Capture the internal state of this voice (see [VoiceState])
+    pub fn dump_state(&self) -> VoiceState<T> {
+        VoiceState {
+            oscs: self.oscs.clone(),
+            phase_reset: self.phase_reset.clone(),
+            filt: self.filt.clone(),
+            env_amp: self.env_amp.clone(),
+            env_filt: self.env_filt.clone(),
+            drift: self.drift.clone(),
+            modsection: self.modsection.dump_state(),
+            monitor: self.monitor.clone(),
+        }
+    }
+    /// Restore the internal state of this voice from a [VoiceState] captured
+    /// by [Voice::dump_state], so that (given the same inputs) it produces
+    /// exactly the same output as the voice it was captured from
+    pub fn load_state(&mut self, state: &VoiceState<T>) {
+        self.oscs = state.oscs.clone();
+        self.phase_reset = state.phase_reset.clone();
+        self.filt = state.filt.clone();
+        self.env_amp = state.env_amp.clone();
+        self.env_filt = state.env_filt.clone();
+        self.drift = state.drift.clone();
+        self.modsection.load_state(&state.modsection);
+        self.monitor = state.monitor.clone();
+    }
+    /// Set the phase, as a fraction of a cycle, that the oscillators reset to
+    /// at the start of each note when [VoiceParams::phase_reset] is set (see
+    /// [Osc::set_initial_phase] and [OscPhaseOffset])
+    pub fn set_initial_phase(&mut self, phase: T::Scalar) {
+        self.oscs.set_initial_phase(phase);
+    }
+    /// Returns true if this voice has finished releasing, i.e. the VCA
+    /// envelope (as of the last call to [Voice::next]) is releasing or idle
+    /// and has fallen below the silence threshold of the context.
+    ///
+    /// The oscillators run continuously, so the filter never settles - it is
+    /// the VCA envelope alone that determines whether a voice is audible.  A
+    /// silent voice will start sounding again as soon as its gate is opened,
+    /// so callers should also check the gate before skipping a voice.
+    pub fn is_silent(&self, ctx: &T::Context) -> bool {
+        matches!(
+            self.monitor.env_vca_stage,
+            EnvStage::Release | EnvStage::Idle
+        ) && self.monitor.env_vca < T::silence_threshold(ctx)
+    }
+    /// Returns true if a device in this voice has reset its state because it
+    /// became NaN or infinite since the last call (see
+    /// [Filt::take_state_reset])
+    pub fn take_state_reset(&mut self) -> bool {
+        self.filt.take_state_reset()
+    }
+    /// Get the next (stereo) sample from this voice.
     ///
     /// If matrix is not `None`, this will update the internal modulation
     /// matrix - otherwise, this will reuse the last modulation matrix.  It
@@ -144,12 +356,16 @@ impl<T: DspFormat> Voice<T> {
         input: &VoiceInput<T>,
         ch_input: &VoiceChannelInput<T>,
         mut params: VoiceParams<T>,
-    ) -> T::Sample {
+    ) -> PanOutput<T> {
         // Build the ModMatrix
         let modparams = modulation::ModSectionParams::<T> {
             velocity: input.velocity,
             aftertouch: ch_input.aftertouch,
             modwheel: ch_input.modwheel,
+            sidechain: ch_input.sidechain,
+            env_filt: self.monitor.env_vcf,
+            env_amp: self.monitor.env_vca,
+            env_filt_scaled: self.monitor.env_vcf_scaled,
             lfo1_params: params.lfo1_p,
             lfo2_params: params.lfo2_p,
             env1_params: params.env1_p,
@@ -163,8 +379,27 @@ impl<T: DspFormat> Voice<T> {
         m.modulate_env(&mut params.filt_env_p, &modulation::ENV_FILT_MOD_DEST);
         m.modulate_env(&mut params.amp_env_p, &modulation::ENV_AMP_MOD_DEST);
         m.modulate_mod_filt(&mut params.filt_p);
+        let mut pan_p = PanParams {
+            pan: input.pan,
+            ..Default::default()
+        };
+        m.modulate_pan(&mut pan_p);
+        self.monitor.lfo1 = m.lfo1();
+        self.monitor.lfo2 = m.lfo2();
+        self.monitor.cutoff = params.filt_p.cutoff;
+        self.monitor.resonance = params.filt_p.resonance;
+        let note = self.stretch.next(ctx, input.note, params.stretch_p);
+        let note = if params.analog_drift {
+            self.drift.next(ctx, note, params.drift_p)
+        } else {
+            note
+        };
+        self.monitor.note = T::apply_note_offset(note, params.oscs_p.primary.tune);
 
-        let oscs_out = self.oscs.next(ctx, input.note, params.oscs_p);
+        if self.phase_reset.next(ctx, input.gate, ()) && params.phase_reset {
+            self.oscs.reset_phase();
+        }
+        let oscs_out = self.oscs.next(ctx, note, params.oscs_p);
 
         let ring_mod_out = self.ringmod.next(
             ctx,
@@ -175,7 +410,11 @@ impl<T: DspFormat> Voice<T> {
             params.ring_p,
         );
 
-        let filt_env_out = self.env_filt.next(ctx, input.gate, params.filt_env_p);
+        let (filt_env_out, filt_env_stage) =
+            self.env_filt.next_with_stage(ctx, input.gate, params.filt_env_p);
+        self.monitor.env_vcf = filt_env_out;
+        self.monitor.env_vcf_stage = filt_env_stage;
+        self.monitor.env_vcf_scaled = filt_env_out.scale(params.filt_p.env_mod);
         let filt_out = self.filt.next(
             ctx,
             ModFiltInput {
@@ -186,7 +425,85 @@ impl<T: DspFormat> Voice<T> {
             },
             params.filt_p,
         );
-        let vca_env_out = self.env_amp.next(ctx, input.gate, params.amp_env_p);
-        self.vca.next(ctx, filt_out, vca_env_out)
+        let (vca_env_out, vca_env_stage) =
+            self.env_amp.next_with_stage(ctx, input.gate, params.amp_env_p);
+        self.monitor.env_vca = vca_env_out;
+        self.monitor.env_vca_stage = vca_env_stage;
+        let vca_out = self.vca.next(ctx, filt_out, vca_env_out);
+        let out = self.pan.next(ctx, vca_out, pan_p);
+        if params.raw_osc {
+            PanOutput {
+                left: ring_mod_out,
+                right: ring_mod_out,
+            }
+        } else {
+            out
+        }
+    }
+    /// Get the next (stereo) sample from this voice, both before and after
+    /// its built-in effects, as well as the two mixed together according to
+    /// `out_params`.  This allows the caller to either use the internal mix
+    /// or blend the dry and wet outputs externally (see [Amp::mix]).
+    ///
+    /// The voice does not yet have any built-in effects, so the wet output is
+    /// currently identical to the dry output.  See [Voice::next] for the
+    /// other arguments.
+    pub fn next_dry_wet(
+        &mut self,
+        ctx: &T::Context,
+        matrix: Option<&ModMatrix<T>>,
+        input: &VoiceInput<T>,
+        ch_input: &VoiceChannelInput<T>,
+        params: VoiceParams<T>,
+        out_params: &VoiceOutputParams<T>,
+    ) -> VoiceDryWetOutput<T> {
+        let dry = self.next(ctx, matrix, input, ch_input, params);
+        let wet = dry.clone();
+        let mix = PanOutput {
+            left: Amp::<T>::mix(dry.left, wet.left, out_params.dry_wet),
+            right: Amp::<T>::mix(dry.right, wet.right, out_params.dry_wet),
+        };
+        VoiceDryWetOutput { dry, wet, mix }
+    }
+}
+
+/// Gives each voice of a polyphonic synth its own oscillator starting phase
+/// (see [Voice::set_initial_phase]).  When [VoiceParams::phase_reset] is set,
+/// notes played together on different voices would otherwise start in phase,
+/// so a unison or octave sums to a single louder waveform rather than the
+/// thicker sound of separate oscillators.
+///
+/// By default, the voices are spread evenly around the cycle, so voice `i` of
+/// `n` starts `i/n` of a cycle in.  Alternatively, each offset can be drawn
+/// from a random number generator seeded separately for each voice.
+#[derive(Clone)]
+pub struct OscPhaseOffset {
+    spread: IScalarFxP,
+    rng: Xoshiro256PlusPlus,
+}
+
+impl OscPhaseOffset {
+    /// Create the phase offset for voice `voice_index` out of `num_voices`,
+    /// seeding its random number generator with `seed`
+    pub fn new(voice_index: usize, num_voices: usize, seed: u64) -> Self {
+        let num_voices = num_voices.max(1);
+        let spread = ((voice_index % num_voices) << IScalarFxP::FRAC_NBITS) / num_voices;
+        Self {
+            spread: IScalarFxP::from_bits(spread as i16),
+            rng: Xoshiro256PlusPlus::seed_from_u64(seed),
+        }
+    }
+    /// The phase offset, as a fraction of a cycle, to pass to
+    /// [Voice::set_initial_phase] before the next note.  If `randomize` is
+    /// set, this is a new random offset each time it is called; otherwise it
+    /// is this voice's fixed share of the cycle.
+    pub fn offset<T: DspFormatBase>(&mut self, randomize: bool) -> T::Scalar {
+        let fraction = if randomize {
+            // Keep the sign bit clear, so the offset is between 0 and 1
+            IScalarFxP::from_bits((self.rng.next_u32() >> 17) as i16)
+        } else {
+            self.spread
+        };
+        T::scalar_from_sample(T::sample_from_fixed(fraction))
     }
 }
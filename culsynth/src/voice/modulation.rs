@@ -2,8 +2,10 @@
 use arrayvec::ArrayVec;
 
 use crate::{devices::*, EnvParamFxP, LfoFreqFxP};
-use crate::{DspFloat, DspFormat, DspFormatBase, DspType};
-use crate::{ScalarFxP, SignedNoteFxP};
+use crate::{DspFloat, DspFormat, DspFormatBase, DspFormatContext, DspType};
+use crate::ScalarFxP;
+#[cfg(feature = "fixed")]
+use crate::SignedNoteFxP;
 
 mod types;
 pub use types::*;
@@ -47,6 +49,18 @@ impl<T: DspFormatBase> ModMatrix<T> {
             .iter()
             .find_map(|x| if x.0 == dest { Some(x.1) } else { None })
     }
+    /// Check that every route in this matrix is allowed (see
+    /// [ModSrc::can_modulate]).  Routes that are not allowed, such as a
+    /// modulation source modulating its own rate, are ignored when the
+    /// matrix is used.
+    pub fn validate(&self) -> Result<(), &'static str> {
+        for (src, entries) in self.rows.iter() {
+            if entries.iter().any(|(dest, _)| !src.can_modulate(*dest)) {
+                return Err("ModMatrix contains a circular modulation route");
+            }
+        }
+        Ok(())
+    }
 }
 
 impl<T: DspFloat> From<&ModMatrix<i16>> for ModMatrix<T> {
@@ -74,6 +88,15 @@ pub struct ModSectionParams<T: DspFormatBase> {
     pub aftertouch: T::Scalar,
     /// Modulation wheel (MIDI CC #1)
     pub modwheel: T::Scalar,
+    /// Level of the external sidechain signal
+    pub sidechain: T::Scalar,
+    /// The output of the VCF envelope as of the previous sample
+    pub env_filt: T::Scalar,
+    /// The output of the VCA envelope as of the previous sample
+    pub env_amp: T::Scalar,
+    /// The output of the VCF envelope as of the previous sample, scaled by
+    /// the filter's envelope amount
+    pub env_filt_scaled: T::Scalar,
     /// Parameters for LFO 1
     pub lfo1_params: LfoParams<T>,
     /// Parameters for LFO 2
@@ -94,7 +117,7 @@ impl<T: DspFormatBase> From<&ModMatrix<T>> for ModMatrixExpanded<T> {
         let mut ret = Self::default();
         for (src, entries) in value.rows {
             for (dest, depth) in entries {
-                if dest == ModDest::Null {
+                if dest == ModDest::Null || !src.can_modulate(dest) {
                     continue;
                 }
                 // FIXME: is it worth making these bigger for pathological
@@ -120,8 +143,12 @@ pub struct Modulator<'a, T: DspFormatBase> {
     velocity: T::Scalar,
     aftertouch: T::Scalar,
     modwheel: T::Scalar,
+    sidechain: T::Scalar,
     env1: T::Scalar,
     env2: T::Scalar,
+    env_filt: T::Scalar,
+    env_amp: T::Scalar,
+    env_filt_scaled: T::Scalar,
     lfo1: T::Sample,
     lfo2: T::Sample,
     matrix: &'a ModMatrixExpanded<T>,
@@ -146,6 +173,10 @@ impl<'a, T: DspFormatBase + ModulatorOps> Modulator<'a, T> {
     pub fn modulate_mod_filt(&self, params: &mut ModFiltParams<T>) {
         T::modulate_filt(self, params)
     }
+    /// Apply modulation to the [PanParams]
+    pub fn modulate_pan(&self, params: &mut PanParams<T>) {
+        T::modulate_pan(self, params)
+    }
     /// Apply modulation to a singular `EnvParam` for a given [ModDest]
     pub fn modulate_env_param(&self, param: &mut T::EnvParam, dest: ModDest) {
         T::modulate_env_param(self, param, dest)
@@ -158,6 +189,29 @@ impl<'a, T: DspFormatBase + ModulatorOps> Modulator<'a, T> {
     pub fn modulate_lfo_freq(&self, param: &mut T::LfoFreq, dest: ModDest) {
         T::modulate_lfo_freq(self, param, dest)
     }
+    /// The current output of LFO 1
+    pub fn lfo1(&self) -> T::Sample {
+        self.lfo1
+    }
+    /// The current output of LFO 2
+    pub fn lfo2(&self) -> T::Sample {
+        self.lfo2
+    }
+}
+
+/// The internal state of the LFOs and envelopes of a [ModSection], as part
+/// of a [VoiceState](crate::voice::VoiceState)
+#[derive(Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound = "")
+)]
+pub(crate) struct ModSectionState<T: DspFormat> {
+    lfo1: Lfo<T>,
+    lfo2: Lfo<T>,
+    env1: Env<T>,
+    env2: Env<T>,
 }
 
 /// The actual modulation section, containing the modulation LFOs and Envelopes and
@@ -188,9 +242,30 @@ impl<T: DspFormat> ModSection<T> {
             expanded_matrix: Default::default(),
         }
     }
+    /// Capture the state of the LFOs and envelopes
+    pub(crate) fn dump_state(&self) -> ModSectionState<T> {
+        ModSectionState {
+            lfo1: self.lfo1.clone(),
+            lfo2: self.lfo2.clone(),
+            env1: self.env1.clone(),
+            env2: self.env2.clone(),
+        }
+    }
+    /// Restore the state of the LFOs and envelopes, leaving the modulation
+    /// matrix unchanged
+    pub(crate) fn load_state(&mut self, state: &ModSectionState<T>) {
+        self.lfo1 = state.lfo1.clone();
+        self.lfo2 = state.lfo2.clone();
+        self.env1 = state.env1.clone();
+        self.env2 = state.env2.clone();
+    }
     /// Build a [Modulator] from all the required data, to include the
     /// processing context, the gate signal, the [ModSectionParams], and
     /// the actual [ModMatrix].
+    ///
+    /// The modulation sources are evaluated in order (see
+    /// [ModSrc::can_modulate]), so that envelope 1 may modulate the rate of
+    /// LFO 1, and both may modulate LFO 2 and envelope 2.
     pub fn next<'a>(
         &'a mut self,
         context: &T::Context,
@@ -198,22 +273,30 @@ impl<T: DspFormat> ModSection<T> {
         mut params: ModSectionParams<T>,
         entries: Option<&ModMatrix<T>>,
     ) -> Modulator<'a, T> {
-        let lfo1_out = self.lfo1.next(context, gate, params.lfo1_params);
         let env1_out = self.env1.next(context, gate, params.env1_params);
         if let Some(matrix) = entries {
             self.expanded_matrix = matrix.into();
         }
-        // LFO2/ENV2 are default here, so empty slices.
+        // LFO1/LFO2/ENV2 are default here, so empty slices.
         let modulator = Modulator {
             velocity: params.velocity,
             aftertouch: params.aftertouch,
             modwheel: params.modwheel,
-            lfo1: lfo1_out,
+            sidechain: params.sidechain,
+            lfo1: T::Sample::zero(),
             lfo2: T::Sample::zero(),
             env1: env1_out,
             env2: T::Scalar::zero(),
+            env_filt: params.env_filt,
+            env_amp: params.env_amp,
+            env_filt_scaled: params.env_filt_scaled,
             matrix: &self.expanded_matrix,
         };
+        T::modulate_lfo_freq(&modulator, &mut params.lfo1_params.freq, ModDest::Lfo1Rate);
+        let modulator = Modulator {
+            lfo1: self.lfo1.next(context, gate, params.lfo1_params),
+            ..modulator
+        };
         T::modulate_lfo_freq(&modulator, &mut params.lfo2_params.freq, ModDest::Lfo2Rate);
         T::modulate_scalar(
             &modulator,
@@ -236,7 +319,7 @@ impl<T: DspFormat> ModSection<T> {
 
 pub(crate) mod detail {
     use super::*;
-    pub trait ModulatorOps: DspFormatBase {
+    pub trait ModulatorOps: DspFormatContext {
         fn modulate_env(
             modulator: &Modulator<Self>,
             params: &mut EnvParams<Self>,
@@ -249,6 +332,7 @@ pub(crate) mod detail {
         );
         fn modulate_ring(modulator: &Modulator<Self>, params: &mut RingModParams<Self>);
         fn modulate_filt(modulator: &Modulator<Self>, params: &mut ModFiltParams<Self>);
+        fn modulate_pan(modulator: &Modulator<Self>, params: &mut PanParams<Self>);
         fn modulate_env_param(
             modulator: &Modulator<Self>,
             param: &mut Self::EnvParam,
@@ -260,6 +344,7 @@ pub(crate) mod detail {
     /// Apply all modulation to the parameter passed in `dest`
     ///
     /// Returns true if any modulation was performed, or false otherwise
+    #[cfg(feature = "fixed")]
     pub fn modulate<T: crate::Fixed16>(modulator: &Modulator<i16>, dest: ModDest, value: T) -> T {
         use crate::fixedmath::{I16F16, I17F15, I1F31};
         let mut acc = value.widen();
@@ -272,6 +357,10 @@ pub(crate) mod detail {
                 ModSrc::Env2 => modulator.env2.wide_mul_signed(depth),
                 ModSrc::Lfo1 => I1F31::saturating_from_num(modulator.lfo1.wide_mul(depth)),
                 ModSrc::Lfo2 => I1F31::saturating_from_num(modulator.lfo2.wide_mul(depth)),
+                ModSrc::Sidechain => modulator.sidechain.wide_mul_signed(depth),
+                ModSrc::EnvFilt => modulator.env_filt.wide_mul_signed(depth),
+                ModSrc::EnvAmp => modulator.env_amp.wide_mul_signed(depth),
+                ModSrc::EnvFiltScaled => modulator.env_filt_scaled.wide_mul_signed(depth),
             };
             acc += T::widened_from_bits(if T::IS_SIGNED {
                 I17F15::from_num(mod_amt).to_bits()
@@ -310,6 +399,10 @@ pub(crate) mod detail {
                         ModSrc::Env2 => modulator.env2,
                         ModSrc::Lfo1 => modulator.lfo1,
                         ModSrc::Lfo2 => modulator.lfo2,
+                        ModSrc::Sidechain => modulator.sidechain,
+                        ModSrc::EnvFilt => modulator.env_filt,
+                        ModSrc::EnvAmp => modulator.env_amp,
+                        ModSrc::EnvFiltScaled => modulator.env_filt_scaled,
                     });
         }
         acc = value + (acc * coeff);
@@ -322,6 +415,7 @@ pub(crate) mod detail {
     }
 }
 
+#[cfg(feature = "fixed")]
 impl detail::ModulatorOps for i16 {
     /// Modulate all of the parameters in `params` for the envelope specified by
     /// `dest`, which should be either [ENV_AMP_MOD_DEST] or [ENV_FILT_MOD_DEST]
@@ -348,6 +442,7 @@ impl detail::ModulatorOps for i16 {
         params.sq = detail::modulate(m, dest.sq, params.sq);
         params.tri = detail::modulate(m, dest.tri, params.tri);
         params.saw = detail::modulate(m, dest.saw, params.saw);
+        params.morph = detail::modulate(m, dest.morph, params.morph);
     }
     /// Modulate the ring modulator parameters
     fn modulate_ring(m: &Modulator<i16>, params: &mut RingModParams<i16>) {
@@ -366,6 +461,11 @@ impl detail::ModulatorOps for i16 {
         params.band_mix = detail::modulate(m, ModDest::FiltBand, params.band_mix);
         params.high_mix = detail::modulate(m, ModDest::FiltHigh, params.high_mix);
     }
+    /// Modulate the master gain and pan
+    fn modulate_pan(m: &Modulator<i16>, params: &mut PanParams<i16>) {
+        params.gain = detail::modulate(m, ModDest::MasterGain, params.gain);
+        params.pan = detail::modulate(m, ModDest::Pan, params.pan);
+    }
     fn modulate_env_param(m: &Modulator<i16>, param: &mut EnvParamFxP, dest: ModDest) {
         *param = detail::modulate(m, dest, *param);
     }
@@ -398,6 +498,7 @@ impl<T: DspFloat> detail::ModulatorOps for T {
         params.sq = detail::modulate_float(m, dest.sq, params.sq, coeff);
         params.tri = detail::modulate_float(m, dest.tri, params.tri, coeff);
         params.saw = detail::modulate_float(m, dest.saw, params.saw, coeff);
+        params.morph = detail::modulate_float(m, dest.morph, params.morph, coeff);
     }
     /// Modulate the ring modulator parameters
     fn modulate_ring(m: &Modulator<T>, params: &mut RingModParams<T>) {
@@ -414,12 +515,22 @@ impl<T: DspFloat> detail::ModulatorOps for T {
         params.vel_mod = detail::modulate_float(m, ModDest::FiltVel, params.vel_mod, coeff);
         params.kbd_tracking =
             detail::modulate_float(m, ModDest::FiltKbd, params.kbd_tracking, coeff);
+        // Saturate at zero like the fixed point implementation
+        if params.kbd_tracking < T::ZERO {
+            params.kbd_tracking = T::ZERO;
+        }
         params.cutoff = detail::modulate_float(m, ModDest::FiltCutoff, params.cutoff, filt_coeff);
         params.resonance = detail::modulate_float(m, ModDest::FiltRes, params.resonance, coeff);
         params.low_mix = detail::modulate_float(m, ModDest::FiltLow, params.low_mix, coeff);
         params.band_mix = detail::modulate_float(m, ModDest::FiltBand, params.band_mix, coeff);
         params.high_mix = detail::modulate_float(m, ModDest::FiltHigh, params.high_mix, coeff);
     }
+    /// Modulate the master gain and pan
+    fn modulate_pan(m: &Modulator<T>, params: &mut PanParams<T>) {
+        let coeff = detail::coeff_from_fixed::<crate::IScalarFxP, T>();
+        params.gain = detail::modulate_float(m, ModDest::MasterGain, params.gain, coeff);
+        params.pan = detail::modulate_float(m, ModDest::Pan, params.pan, coeff);
+    }
     fn modulate_env_param(m: &Modulator<T>, param: &mut T, dest: ModDest) {
         let coeff = detail::coeff_from_fixed::<EnvParamFxP, T>();
         *param = detail::modulate_float(m, dest, *param, coeff);
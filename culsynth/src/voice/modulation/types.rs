@@ -17,6 +17,17 @@ pub enum ModSrc {
     Lfo1,
     /// LFO #2
     Lfo2,
+    /// The level of an external sidechain signal, from 0 to 1
+    Sidechain,
+    /// The VCF envelope, as of the previous sample
+    EnvFilt,
+    /// The VCA envelope, as of the previous sample
+    EnvAmp,
+    /// The VCF envelope after it is scaled by the filter's envelope amount
+    /// (i.e. as it is applied to the cutoff), as of the previous sample.
+    /// The VCA envelope has no amount, so [ModSrc::EnvAmp] is the level
+    /// applied by the VCA.
+    EnvFiltScaled,
 }
 
 impl ModSrc {
@@ -29,6 +40,10 @@ impl ModSrc {
         ModSrc::Env2,
         ModSrc::Lfo1,
         ModSrc::Lfo2,
+        ModSrc::Sidechain,
+        ModSrc::EnvFilt,
+        ModSrc::EnvAmp,
+        ModSrc::EnvFiltScaled,
     ];
     /// An iterator over all the different elements in `ModSrc`
     pub const fn elements() -> &'static [ModSrc] {
@@ -40,12 +55,42 @@ impl ModSrc {
     }
     /// The last value in elements
     pub const fn max() -> Self {
-        Self::Lfo2
+        Self::EnvFiltScaled
     }
     /// The number of different modualtion sources
     pub const fn numel() -> usize {
         1 + Self::max() as usize - Self::min() as usize
     }
+    /// Can this source modulate `dest`?
+    ///
+    /// The modulation sources are evaluated in order: envelope 1, then LFO 1
+    /// (which [ModDest::Lfo1Rate] applies to), then LFO 2 and envelope 2
+    /// (which the other secondary destinations apply to).  A source may only
+    /// modulate parameters of the sources evaluated after it, so routes that
+    /// would make a source modulate itself or form a cycle (e.g. LFO 1 to
+    /// LFO 2 and LFO 2 to LFO 1) are not allowed.
+    ///
+    /// The VCF and VCA envelopes are part of the voice rather than the
+    /// modulation section, and are evaluated after all modulation is
+    /// applied, so they modulate with their output from the previous sample.
+    /// This means they may modulate anything, including their own
+    /// parameters.
+    pub const fn can_modulate(&self, dest: ModDest) -> bool {
+        match self {
+            Self::Lfo1 => !matches!(dest, ModDest::Lfo1Rate),
+            _ if self.is_secondary() => !dest.is_secondary(),
+            _ => true,
+        }
+    }
+    /// Is this one of the secondary sources (envelope 2 and LFO 2)?
+    ///
+    /// The secondary sources are evaluated last, so they can't modulate any
+    /// of the secondary destinations (see [ModDest::is_secondary]).  The
+    /// destinations a secondary source may select are given by
+    /// [ModDest::elements_secondary_if].
+    pub const fn is_secondary(&self) -> bool {
+        matches!(self, Self::Env2 | Self::Lfo2)
+    }
     /// The string representation of the modulation source
     pub const fn to_str(&self) -> &'static str {
         match self {
@@ -56,10 +101,25 @@ impl ModSrc {
             Self::Env2 => "Envelope 2",
             Self::Lfo1 => "LFO 1",
             Self::Lfo2 => "LFO 2",
+            Self::Sidechain => "Sidechain",
+            Self::EnvFilt => "VCF Envelope",
+            Self::EnvAmp => "VCA Envelope",
+            Self::EnvFiltScaled => "VCF Envelope (Scaled)",
         }
     }
 }
 
+impl TryFrom<&str> for ModSrc {
+    type Error = &'static str;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::ELEM
+            .iter()
+            .find(|elem| value == elem.to_str())
+            .copied()
+            .ok_or("ModSrc::try_from::<&str> parse failure")
+    }
+}
+
 /// An enum representing a modulation destination
 #[repr(u16)]
 #[derive(Clone, Copy, PartialEq, Default)]
@@ -102,7 +162,8 @@ pub enum ModDest {
     /// ring modulation section
     RingOsc2,
     /// The mix of the wet (modulated) signal in the output of the ring
-    /// modulation section
+    /// modulation section, i.e. the amount of ring modulation.  This is
+    /// clamped between 0 and 1 after modulation.
     RingMod,
     /// The filter cutoff frequency
     FiltCutoff,
@@ -110,7 +171,9 @@ pub enum ModDest {
     FiltRes,
     /// The filter envelope modulation
     FiltEnv,
-    /// The filter keyboard tracking
+    /// The filter keyboard tracking.  This is clamped between 0 (the cutoff
+    /// ignores the note) and 1 (1:1 tracking) after modulation, so e.g. an
+    /// envelope can blend keyboard tracking in over the course of a note.
     FiltKbd,
     /// The filter velocity modulation
     FiltVel,
@@ -149,6 +212,16 @@ pub enum ModDest {
     Env2S,
     /// The release of modulation envelope 2
     Env2R,
+    /// The master gain of the voice output (see [crate::devices::Pan])
+    MasterGain,
+    /// The stereo pan position of the voice output
+    Pan,
+    /// The triangle to sawtooth morph of oscillator 1
+    Osc1Morph,
+    /// The triangle to sawtooth morph of oscillator 2
+    Osc2Morph,
+    /// The rate/frequency of LFO 1, in Hz
+    Lfo1Rate,
 }
 
 impl ModDest {
@@ -156,14 +229,10 @@ impl ModDest {
     /// when evaluating their modulation matrices to remap these invalid routes
     /// to `Self::Null`
     pub const fn remove_secondary_invalid_dest(self) -> Self {
-        match self {
-            Self::Lfo2Rate => Self::Null,
-            Self::Lfo2Depth => Self::Null,
-            Self::Env2A => Self::Null,
-            Self::Env2D => Self::Null,
-            Self::Env2S => Self::Null,
-            Self::Env2R => Self::Null,
-            val => val,
+        if self.is_secondary() {
+            Self::Null
+        } else {
+            self
         }
     }
     /// The string representation of this modulation destination.
@@ -209,6 +278,11 @@ impl ModDest {
             Self::Env2D => "Env2D",
             Self::Env2S => "Env2S",
             Self::Env2R => "Env2R",
+            Self::MasterGain => "MasterGain",
+            Self::Pan => "Pan",
+            Self::Osc1Morph => "Osc1Morph",
+            Self::Osc2Morph => "Osc2Morph",
+            Self::Lfo1Rate => "Lfo1Rate",
         }
     }
     /// The first modulation destination, in order
@@ -217,18 +291,30 @@ impl ModDest {
     }
     /// The last modulation destination, in order
     pub const fn max() -> Self {
-        Self::Env2R
+        Self::Lfo1Rate
     }
     /// The number of modulation destinations
     pub const fn numel() -> usize {
         Self::max() as usize + 1
     }
-    /// The last modulation destination before the secondary destinations
+    /// Is this one of the secondary destinations?  These modulate the
+    /// modulation sources themselves, so they can't be selected by the
+    /// secondary sources (see [ModSrc::is_secondary]).
     ///
-    /// The secondary modulation destinations are invalid destinations from
-    /// LFO2/ENV2 to avoid self/co-modulation
-    pub const fn max_secondary() -> Self {
-        Self::EnvAmpR
+    /// New destinations are always added at the end, so that the numbering of
+    /// existing destinations (e.g. in saved patches) never changes.  This
+    /// means the secondary destinations aren't a contiguous range.
+    pub const fn is_secondary(&self) -> bool {
+        matches!(
+            self,
+            Self::Lfo2Rate
+                | Self::Lfo2Depth
+                | Self::Env2A
+                | Self::Env2D
+                | Self::Env2S
+                | Self::Env2R
+                | Self::Lfo1Rate
+        )
     }
     /// An iterator over all modulation destinations
     pub fn elements() -> impl core::iter::Iterator<Item = ModDest> {
@@ -241,14 +327,16 @@ impl ModDest {
         Self::elements_secondary_if(true)
     }
     /// An iterator that excludes the secondary modulation destinations if the
-    /// argument is true, and includes them if it is false
+    /// argument is true, and includes them if it is false.
+    ///
+    /// `ModDest::elements_secondary_if(src.is_secondary())` gives every
+    /// destination a row of the modulation matrix for `src` may select,
+    /// although [ModSrc::can_modulate] should still be checked (e.g. LFO 1
+    /// can't modulate its own rate).
     pub fn elements_secondary_if(sec: bool) -> impl core::iter::Iterator<Item = ModDest> {
-        let max = if sec {
-            Self::max_secondary()
-        } else {
-            Self::max()
-        };
-        ((Self::min() as u16)..=(max as u16)).map(|x| unsafe { core::mem::transmute(x) })
+        ((Self::min() as u16)..=(Self::max() as u16))
+            .map(|x| unsafe { core::mem::transmute::<u16, ModDest>(x) })
+            .filter(move |dest| !(sec && dest.is_secondary()))
     }
 }
 
@@ -290,6 +378,8 @@ pub struct OscModDest {
     pub tri: ModDest,
     /// Sawtooth output
     pub saw: ModDest,
+    /// Triangle to sawtooth morph
+    pub morph: ModDest,
 }
 
 /// The modulation destinations corresponding to oscillator 1
@@ -301,6 +391,7 @@ pub const OSC1_MOD_DEST: OscModDest = OscModDest {
     sq: ModDest::Osc1Sq,
     tri: ModDest::Osc1Tri,
     saw: ModDest::Osc1Saw,
+    morph: ModDest::Osc1Morph,
 };
 
 /// The modulation destinations corresponding to oscillator 2
@@ -312,6 +403,7 @@ pub const OSC2_MOD_DEST: OscModDest = OscModDest {
     sq: ModDest::Osc2Sq,
     tri: ModDest::Osc2Tri,
     saw: ModDest::Osc2Saw,
+    morph: ModDest::Osc2Morph,
 };
 
 /// A struct to allow expressing the different modulation destinations for a
@@ -0,0 +1,41 @@
+//! Verify the dry/wet crossfade of [Amp::mix] at either end of its range and
+//! at the midpoint.
+
+use culsynth::devices::Amp;
+#[cfg(feature = "fixed")]
+use culsynth::{SampleFxP, ScalarFxP};
+
+/// Pairs of (dry, wet) samples covering the full range of the signal
+fn signals() -> impl Iterator<Item = (f32, f32)> {
+    (0..=20).flat_map(|i| (0..=20).map(move |j| ((i - 10) as f32 / 10., (j - 10) as f32 / 10.)))
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn amp_mix_fixed() {
+    for (dry, wet) in signals() {
+        let dry = SampleFxP::from_num(dry);
+        let wet = SampleFxP::from_num(wet);
+        assert_eq!(Amp::<i16>::mix(dry, wet, ScalarFxP::ZERO), dry);
+        assert_eq!(Amp::<i16>::mix(dry, wet, ScalarFxP::MAX), wet);
+        let avg = (dry + wet) / 2;
+        let half = Amp::<i16>::mix(dry, wet, ScalarFxP::lit("0.5"));
+        // Allow for truncation in each half of the mix
+        assert!(
+            half.abs_diff(avg) <= SampleFxP::DELTA * 2,
+            "{} != {}",
+            half,
+            avg
+        );
+    }
+}
+
+#[test]
+fn amp_mix_float() {
+    for (dry, wet) in signals() {
+        assert_eq!(Amp::<f32>::mix(dry, wet, 0f32), dry);
+        assert_eq!(Amp::<f32>::mix(dry, wet, 1f32), wet);
+        let avg = (dry + wet) / 2f32;
+        assert!((Amp::<f32>::mix(dry, wet, 0.5f32) - avg).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,90 @@
+//! Property tests for the VCA: the fixed point output must stay within the
+//! range of a SampleFxP, the floating point output must stay finite, and
+//! zero and unity gains must behave exactly as expected.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Amp, Device};
+#[cfg(feature = "fixed")]
+use culsynth::{SampleFxP, ScalarFxP};
+use proptest::prelude::*;
+
+const CASES: u32 = 10000;
+
+#[cfg(feature = "fixed")]
+fn amp_fixed(signal: SampleFxP, gain: ScalarFxP) -> SampleFxP {
+    Amp::<i16>::default().next(&ContextFxP::new_480(), signal, gain)
+}
+
+fn amp_float(signal: f32, gain: f32) -> f32 {
+    Amp::<f32>::default().next(&Context::new(48000f32), signal, gain)
+}
+
+#[cfg(feature = "fixed")]
+fn sample() -> impl Strategy<Value = SampleFxP> {
+    any::<i16>().prop_map(SampleFxP::from_bits)
+}
+
+#[cfg(feature = "fixed")]
+fn gain() -> impl Strategy<Value = ScalarFxP> {
+    any::<u16>().prop_map(ScalarFxP::from_bits)
+}
+
+fn finite() -> impl Strategy<Value = f32> {
+    prop::num::f32::NORMAL | prop::num::f32::SUBNORMAL | prop::num::f32::ZERO
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    #[cfg(feature = "fixed")]
+    fn fixed_output_in_range(signal in sample(), gain in gain()) {
+        let out = amp_fixed(signal, gain);
+        prop_assert!((SampleFxP::MIN..=SampleFxP::MAX).contains(&out));
+        // A gain below one can only attenuate
+        prop_assert!(out.unsigned_abs() <= signal.unsigned_abs());
+    }
+
+    #[test]
+    fn float_output_finite(signal in finite(), gain in 0f32..=1f32) {
+        prop_assert!(amp_float(signal, gain).is_finite());
+    }
+
+    #[test]
+    #[cfg(feature = "fixed")]
+    fn fixed_zero_signal(gain in gain()) {
+        prop_assert_eq!(amp_fixed(SampleFxP::ZERO, gain), SampleFxP::ZERO);
+    }
+
+    #[test]
+    fn float_zero_signal(gain in 0f32..=1f32) {
+        prop_assert_eq!(amp_float(0f32, gain), 0f32);
+    }
+
+    #[test]
+    #[cfg(feature = "fixed")]
+    fn fixed_zero_gain(signal in sample()) {
+        prop_assert_eq!(amp_fixed(signal, ScalarFxP::ZERO), SampleFxP::ZERO);
+    }
+
+    #[test]
+    fn float_zero_gain(signal in finite()) {
+        prop_assert_eq!(amp_float(signal, 0f32), 0f32);
+    }
+
+    #[test]
+    #[cfg(feature = "fixed")]
+    fn fixed_unity_gain(signal in sample()) {
+        // The largest fixed point gain is one LSB short of one, and the
+        // product is truncated, so the output may be one LSB towards zero
+        let out = amp_fixed(signal, ScalarFxP::MAX);
+        prop_assert!(signal.dist(out) <= SampleFxP::DELTA, "{} {}", signal, out);
+    }
+
+    #[test]
+    fn float_unity_gain(signal in finite()) {
+        prop_assert_eq!(amp_float(signal, 1f32), signal);
+    }
+}
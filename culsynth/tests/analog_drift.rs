@@ -0,0 +1,59 @@
+//! Verify that the analog drift random walk stays within its range while still
+//! wandering around within it.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{AnalogDrift, AnalogDriftParams};
+use culsynth::{DspFormat, LfoFreqFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+const BUFFER_SIZE: usize = 512;
+const NUM_BUFFERS: usize = 10_000;
+
+fn params() -> AnalogDriftParams<i16> {
+    AnalogDriftParams {
+        // Fast enough to hit both ends of the range many times during the test
+        drift_rate: LfoFreqFxP::lit("2"),
+        drift_range: ScalarFxP::lit("0.15"),
+    }
+}
+
+fn check_drift<T: DspFormat>(ctx: &T::Context, drift_p: AnalogDriftParams<T>, seed: u64) {
+    let range_cents = T::scalar_to_float(drift_p.drift_range) * 100f32;
+    let mut drift = AnalogDrift::<T>::new(seed);
+    let cents: Vec<f32> = (0..NUM_BUFFERS)
+        .map(|_| {
+            drift.step(ctx, &drift_p, BUFFER_SIZE);
+            drift.cents()
+        })
+        .collect();
+    for c in cents.iter() {
+        assert!(c.abs() <= range_cents + 1e-3, "drift of {} cents", c);
+    }
+    let mean = cents.iter().sum::<f32>() / NUM_BUFFERS as f32;
+    let var = cents.iter().map(|c| (c - mean) * (c - mean)).sum::<f32>() / NUM_BUFFERS as f32;
+    assert!(var.sqrt() > 1f32, "std dev of {} cents", var.sqrt());
+    // The walk should explore a good part of its range in both directions
+    let max = cents.iter().copied().fold(f32::MIN, f32::max);
+    let min = cents.iter().copied().fold(f32::MAX, f32::min);
+    assert!(max > range_cents * 0.5, "max drift of {} cents", max);
+    assert!(min < -range_cents * 0.5, "min drift of {} cents", min);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn analog_drift_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    for seed in 0..4 {
+        check_drift::<i16>(&ctx, params(), seed);
+    }
+}
+
+#[test]
+fn analog_drift_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    for seed in 0..4 {
+        check_drift::<f32>(&ctx, (&params()).into(), seed);
+    }
+}
@@ -0,0 +1,95 @@
+//! Verify that the output of a voice doesn't depend on the host's buffer
+//! size.  Hosts pass a new modulation matrix at the start of each buffer,
+//! so the voice is rendered in blocks of different sizes, with the matrix
+//! passed at the start of each block, and the outputs must be identical.
+//!
+//! The patch uses analog drift (which steps once per internal block), a
+//! sample and hold LFO, and an envelope, so any per-block state would show up.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{LfoOptions, LfoWave};
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, EnvParamFxP, IScalarFxP, LfoFreqFxP, NoteFxP, ScalarFxP};
+
+const LEN: usize = 12000;
+const BLOCK_SIZES: [usize; 4] = [1, 64, 256, 1000];
+
+fn params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16>::default();
+    params.oscs_p.primary.saw = ScalarFxP::MAX;
+    params.ring_p.mix_a = ScalarFxP::MAX;
+    params.filt_p.cutoff = NoteFxP::lit("90");
+    params.filt_p.low_mix = ScalarFxP::MAX;
+    params.amp_env_p.attack = EnvParamFxP::lit("0.01");
+    params.amp_env_p.sustain = ScalarFxP::MAX;
+    params.amp_env_p.release = EnvParamFxP::lit("0.05");
+    params.env1_p.attack = EnvParamFxP::lit("0.1");
+    params.lfo1_p.freq = LfoFreqFxP::lit("20");
+    params.lfo1_p.depth = ScalarFxP::MAX;
+    params.lfo1_p.opts = LfoOptions::new(LfoWave::SampleHold, true, false);
+    params.analog_drift = true;
+    params
+}
+
+fn matrix() -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[ModSrc::Lfo1 as usize].1[0] = (ModDest::FiltCutoff, IScalarFxP::lit("0.25"));
+    matrix.rows[ModSrc::Env1 as usize].1[0] = (ModDest::Osc1Shape, IScalarFxP::lit("0.5"));
+    matrix
+}
+
+fn render<T: DspFormat>(
+    ctx: &T::Context,
+    matrix: &ModMatrix<T>,
+    params: VoiceParams<T>,
+    block: usize,
+) -> Vec<f32> {
+    let mut voice = Voice::<T>::new_with_seeds(1, 2);
+    let ch_input = VoiceChannelInput::<T>::default();
+    let mut out = Vec::with_capacity(LEN);
+    for start in (0..LEN).step_by(block) {
+        let mut matrix = Some(matrix);
+        for smp in start..LEN.min(start + block) {
+            let input = VoiceInput::<T> {
+                note: T::default_note(),
+                velocity: T::Scalar::one(),
+                gate: smp < LEN / 2,
+                ..Default::default()
+            };
+            let smp_out = voice.next(ctx, matrix.take(), &input, &ch_input, params.clone());
+            out.push(T::sample_to_float(smp_out.left));
+        }
+    }
+    out
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn block_size_independent_fixed() {
+    let ctx = ContextFxP::new_480();
+    let reference = render(&ctx, &matrix(), params(), BLOCK_SIZES[0]);
+    for block in BLOCK_SIZES {
+        assert!(
+            reference == render(&ctx, &matrix(), params(), block),
+            "{}",
+            block
+        );
+    }
+}
+
+#[test]
+fn block_size_independent_float() {
+    let ctx = Context::<f32>::new(48000f32);
+    let matrix: ModMatrix<f32> = (&matrix()).into();
+    let reference = render(&ctx, &matrix, (&params()).into(), BLOCK_SIZES[0]);
+    for block in BLOCK_SIZES {
+        assert!(
+            reference == render(&ctx, &matrix, (&params()).into(), block),
+            "{}",
+            block
+        );
+    }
+}
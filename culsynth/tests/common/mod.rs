@@ -0,0 +1,80 @@
+//! Shared infrastructure for the integration tests.
+//!
+//! Timing in culsynth is derived entirely from the sample rate and the number
+//! of samples processed, so [TestClock] drives a [Device] one sample at a time
+//! and keeps count of the samples, allowing tests to assert on exactly which
+//! sample something happens at (e.g. an envelope stage transition).
+
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use culsynth::devices::Device;
+use culsynth::DspFormat;
+
+pub mod spectrum;
+
+/// Drives a [Device] with a deterministic sample clock
+pub struct TestClock<T: DspFormat, D: Device<T>> {
+    ctx: T::Context,
+    dev: D,
+    now: usize,
+}
+
+impl<T: DspFormat, D: Device<T>> TestClock<T, D>
+where
+    D::Input: Clone,
+    D::Params: Clone,
+{
+    /// Start a new clock at sample zero, driving `dev`
+    pub fn new(ctx: T::Context, dev: D) -> Self {
+        Self { ctx, dev, now: 0 }
+    }
+    /// The number of samples processed so far, which is also the index of
+    /// the next sample to be processed
+    pub fn now(&self) -> usize {
+        self.now
+    }
+    /// The context used to run the device
+    pub fn context(&self) -> &T::Context {
+        &self.ctx
+    }
+    /// The device being driven, to inspect its internal state
+    pub fn device(&self) -> &D {
+        &self.dev
+    }
+    /// Process a single sample
+    pub fn tick(&mut self, input: D::Input, params: &D::Params) -> D::Output {
+        self.now += 1;
+        self.dev.next(&self.ctx, input, params.clone())
+    }
+    /// Process `samples` samples with a constant input, returning the output
+    /// from each
+    pub fn advance(
+        &mut self,
+        samples: usize,
+        input: D::Input,
+        params: &D::Params,
+    ) -> Vec<D::Output> {
+        (0..samples).map(|_| self.tick(input.clone(), params)).collect()
+    }
+    /// Process samples with a constant input until `done` returns true,
+    /// returning the index of the sample at which that happened (or `None`
+    /// if it did not happen within `limit` samples).  `done` is passed the
+    /// device and its output after each sample.
+    pub fn run_until(
+        &mut self,
+        limit: usize,
+        input: D::Input,
+        params: &D::Params,
+        mut done: impl FnMut(&D, &D::Output) -> bool,
+    ) -> Option<usize> {
+        for _ in 0..limit {
+            let idx = self.now;
+            let out = self.tick(input.clone(), params);
+            if done(&self.dev, &out) {
+                return Some(idx);
+            }
+        }
+        None
+    }
+}
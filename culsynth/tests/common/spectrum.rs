@@ -0,0 +1,104 @@
+//! Spectral analysis for the integration tests, built on [culsynth::util::fft].
+//!
+//! [Spectrum] applies a Hann window to a buffer of samples and calculates its
+//! magnitude spectrum, then provides assertions about the frequency content
+//! of the signal (e.g. the frequency of the fundamental, or the level of any
+//! partials above a given frequency).
+
+use culsynth::util::fft::{Complex32, Fft};
+
+/// The magnitude spectrum of a windowed signal
+pub struct Spectrum {
+    mags: Vec<f32>,
+    sample_rate: f32,
+    size: usize,
+}
+
+impl Spectrum {
+    /// Calculate the spectrum of `signal`, sampled at `sample_rate`.  Only the
+    /// first power-of-two number of samples are used, so pass (at least) as
+    /// many samples as the frequency resolution requires.
+    pub fn new(signal: &[f32], sample_rate: f32) -> Self {
+        let size = 1 << signal.len().ilog2();
+        let fft = Fft::new(size).unwrap();
+        let window = |i: usize| {
+            let x = std::f32::consts::PI * i as f32 / size as f32;
+            x.sin() * x.sin()
+        };
+        let windowed: Vec<f32> =
+            signal[..size].iter().enumerate().map(|(i, smp)| smp * window(i)).collect();
+        let mut scratch = vec![Complex32::default(); size];
+        let mut mags = vec![0f32; fft.num_bins()];
+        assert!(fft.magnitude_spectrum(&windowed, &mut scratch, &mut mags));
+        Self {
+            mags,
+            sample_rate,
+            size,
+        }
+    }
+    /// The number of bins, from DC to the Nyquist frequency
+    pub fn num_bins(&self) -> usize {
+        self.mags.len()
+    }
+    /// The center frequency of `bin`, in Hz
+    pub fn bin_freq(&self, bin: usize) -> f32 {
+        bin as f32 * self.sample_rate / self.size as f32
+    }
+    /// The bin containing `freq`
+    fn freq_bin(&self, freq: f32) -> usize {
+        (freq * self.size as f32 / self.sample_rate).round() as usize
+    }
+    /// The bin with the largest magnitude (ignoring DC)
+    pub fn peak_bin(&self) -> usize {
+        (1..self.mags.len())
+            .max_by(|a, b| self.mags[*a].total_cmp(&self.mags[*b]))
+            .unwrap()
+    }
+    /// The frequency of the largest peak in the spectrum, in Hz, estimated to
+    /// a fraction of a bin by fitting a parabola to the log magnitudes
+    pub fn peak_freq(&self) -> f32 {
+        let bin = self.peak_bin();
+        if bin + 1 >= self.mags.len() {
+            return self.bin_freq(bin);
+        }
+        let [a, b, c] = [bin - 1, bin, bin + 1].map(|i| self.mags[i].max(f32::MIN_POSITIVE).ln());
+        let offset = 0.5 * (a - c) / (a - 2. * b + c);
+        (bin as f32 + offset) * self.sample_rate / self.size as f32
+    }
+    /// The level of `bin` in dB, relative to the largest peak
+    pub fn db(&self, bin: usize) -> f32 {
+        20. * (self.mags[bin] / self.mags[self.peak_bin()]).log10()
+    }
+    /// The level, in dB relative to the largest peak, of the loudest partial
+    /// at or above `freq`
+    pub fn max_db_above(&self, freq: f32) -> f32 {
+        (self.freq_bin(freq)..self.mags.len())
+            .map(|bin| self.db(bin))
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+    /// Assert that the fundamental (largest peak) is within `cents` of `freq`
+    pub fn assert_fundamental(&self, freq: f32, cents: f32) {
+        let peak = self.peak_freq();
+        let error = 1200. * (peak / freq).log2();
+        assert!(
+            error.abs() <= cents,
+            "fundamental at {}Hz is {} cents from {}Hz",
+            peak,
+            error,
+            freq
+        );
+    }
+    /// Assert that no partial at or above `freq` is louder than `db`,
+    /// relative to the largest peak.  The window spreads each partial over
+    /// a few bins, so `freq` should not be too close to the fundamental.
+    pub fn assert_quiet_above(&self, freq: f32, db: f32) {
+        let level = self.max_db_above(freq);
+        assert!(
+            level <= db,
+            "partial above {}Hz at {}dB exceeds {}dB",
+            freq,
+            level,
+            db
+        );
+    }
+}
@@ -0,0 +1,77 @@
+//! Verify that a compressor responds to a loud transient with gain
+//! reduction that kicks in at the attack rate and recovers at the release
+//! rate.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Compressor, CompressorParams, Device};
+use culsynth::{DspFormat, EnvParamFxP, IScalarFxP, ScalarFxP};
+
+/// Samples per time constant at 48kHz
+const ATTACK_SAMPLES: usize = 240;
+const RELEASE_SAMPLES: usize = 2400;
+/// The length of each section (quiet, loud, quiet) of the test signal
+const SECTION: usize = 10 * RELEASE_SAMPLES;
+
+fn params() -> CompressorParams<i16> {
+    CompressorParams {
+        // -20dB, 4:1
+        threshold: ScalarFxP::lit("0.333333"),
+        ratio: ScalarFxP::lit("0.75"),
+        attack: EnvParamFxP::lit("0.005"),
+        release: EnvParamFxP::lit("0.05"),
+        makeup: ScalarFxP::ZERO,
+    }
+}
+
+/// Run a quiet/loud/quiet signal through a compressor, and return the gain
+/// reduction and the output at every sample
+fn run<T: DspFormat>(ctx: &T::Context, params: CompressorParams<T>) -> (Vec<f32>, Vec<f32>) {
+    // -26dB and -1dB
+    let quiet = T::sample_from_fixed(IScalarFxP::lit("0.05"));
+    let loud = T::sample_from_fixed(IScalarFxP::lit("0.9"));
+    let mut comp = Compressor::<T>::new();
+    (0..3 * SECTION)
+        .map(|i| {
+            let signal = if i / SECTION == 1 { loud } else { quiet };
+            let out = comp.next(ctx, signal, params.clone());
+            (comp.gain_reduction(), T::sample_to_float(out))
+        })
+        .unzip()
+}
+
+fn check_transient<T: DspFormat>(ctx: &T::Context, params: CompressorParams<T>) {
+    let (reduction, output) = run::<T>(ctx, params);
+    // Below the knee, the signal is untouched
+    assert!(
+        reduction[SECTION - 1].abs() < 0.01,
+        "{}",
+        reduction[SECTION - 1]
+    );
+    assert!((output[SECTION - 1] - 0.05).abs() < 0.001);
+    // 19dB over the threshold at 4:1
+    let expected = 19.085f32 * 0.75;
+    let peak = reduction[2 * SECTION - 1];
+    assert!((peak - expected).abs() < 0.1, "{}", peak);
+    let expected_out = 0.9 * 10f32.powf(-expected / 20f32);
+    assert!((output[2 * SECTION - 1] - expected_out).abs() < 0.005);
+    // One time constant into the attack and release
+    let attack = reduction[SECTION + ATTACK_SAMPLES - 1] / peak;
+    assert!((attack - 0.632).abs() < 0.02, "{}", attack);
+    let release = reduction[2 * SECTION + RELEASE_SAMPLES - 1] / peak;
+    assert!((release - 0.368).abs() < 0.02, "{}", release);
+    // ...and fully recovered by the end
+    assert!(reduction[3 * SECTION - 1].abs() < 0.01);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn transient_fixed() {
+    check_transient::<i16>(&ContextFxP::new_480(), params());
+}
+
+#[test]
+fn transient_float() {
+    check_transient::<f32>(&Context::new(48000f32), (&params()).into());
+}
@@ -0,0 +1,65 @@
+//! Verify that a [DeviceChain] gives the same output as running each device
+//! by hand.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Amp, Device, Filt, FiltParams, Osc, OscParams, Resonance};
+#[cfg(feature = "fixed")]
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP};
+
+/// A noise-like test signal, to give the filter something to do
+fn input(i: usize) -> f64 {
+    (((i * 7919) % 97) as f64 / 97.) - 0.5
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn filt_amp_chain_fixed() {
+    let ctx = ContextFxP::new_480();
+    let filt_p = FiltParams::<i16> {
+        cutoff: NoteFxP::lit("80"),
+        resonance: Resonance::new(ScalarFxP::lit("0.5")),
+    };
+    let gain = ScalarFxP::lit("0.75");
+    let mut filt = Filt::<i16>::default();
+    let mut amp = Amp::<i16>::default();
+    let mut chain = Filt::<i16>::default().then_map(|out| out.low, Amp::<i16>::default());
+    for i in 0..4800 {
+        let smp = SampleFxP::from_num(input(i));
+        let expected = amp.next(&ctx, filt.next(&ctx, smp, filt_p.clone()).low, gain);
+        assert_eq!(chain.next(&ctx, smp, (filt_p.clone(), gain)), expected);
+    }
+}
+
+#[test]
+fn filt_amp_chain_float() {
+    let ctx = Context::new(48000f64);
+    let filt_p = FiltParams::<f64> {
+        cutoff: 80.,
+        resonance: Resonance::new(0.5),
+    };
+    let mut filt = Filt::<f64>::default();
+    let mut amp = Amp::<f64>::default();
+    let mut chain = Filt::<f64>::default().then_map(|out| out.band, Amp::<f64>::default());
+    let inputs = (0..4800).map(input);
+    let params = core::iter::repeat((filt_p.clone(), 0.75));
+    for (i, out) in chain.process(&ctx, inputs, params).enumerate() {
+        let expected = amp.next(&ctx, filt.next(&ctx, input(i), filt_p.clone()).band, 0.75);
+        assert_eq!(out, expected);
+    }
+}
+
+#[test]
+fn chain_of_chains() {
+    // osc -> amp -> amp, with the osc output selected by the adapter
+    let ctx = Context::new(48000f64);
+    let mut chain = Osc::<f64>::default()
+        .then_map(|out| out.saw, Amp::default())
+        .then(Amp::default());
+    let params = (OscParams::<f64>::default(), 0.5);
+    for _ in 0..480 {
+        let out = chain.next(&ctx, 60., (params.clone(), 0.5));
+        assert!(out.abs() <= 0.25);
+    }
+}
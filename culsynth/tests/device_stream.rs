@@ -0,0 +1,67 @@
+//! Verify that processing a signal in several pieces with [Device::process]
+//! gives the same result as processing it all at once, i.e. that all of the
+//! device state carries across the boundaries.
+#![cfg(feature = "fixed")]
+
+use core::iter::repeat;
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Amp, Device, Filt, FiltParams, Osc, OscParams, Resonance};
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP};
+
+const LEN: usize = 4800;
+
+/// Odd chunk sizes, so that boundaries fall at arbitrary points in the signal
+const CHUNKS: [usize; 4] = [1, 37, 256, 1000];
+
+/// Process `input` through a default `D` all at once, and again in pieces of
+/// each size in [CHUNKS], checking that the outputs (selected by `out`) are
+/// identical
+fn check_chunks<D: Device<i16> + Default>(
+    input: &[D::Input],
+    params: D::Params,
+    out: fn(D::Output) -> SampleFxP,
+) where
+    D::Input: Clone,
+    D::Params: Clone,
+{
+    let ctx = ContextFxP::new_480();
+    let mut dev = D::default();
+    let whole: Vec<SampleFxP> = dev
+        .process(&ctx, input.iter().cloned(), repeat(params.clone()))
+        .map(out)
+        .collect();
+    assert_eq!(whole.len(), input.len());
+    for size in CHUNKS {
+        let mut dev = D::default();
+        let mut pieces = Vec::with_capacity(input.len());
+        for chunk in input.chunks(size) {
+            let iter = dev.process(&ctx, chunk.iter().cloned(), repeat(params.clone()));
+            pieces.extend(iter.map(out));
+        }
+        assert_eq!(pieces, whole, "chunk size {}", size);
+    }
+}
+
+fn saw() -> Vec<SampleFxP> {
+    (0..LEN).map(|i| SampleFxP::from_num(((i % 100) as f32 / 50.) - 1.)).collect()
+}
+
+#[test]
+fn filt_stream() {
+    let params = FiltParams::<i16> {
+        cutoff: NoteFxP::lit("70"),
+        resonance: Resonance::new(ScalarFxP::lit("0.7")),
+    };
+    check_chunks::<Filt<i16>>(&saw(), params, |out| out.low);
+}
+
+#[test]
+fn amp_stream() {
+    check_chunks::<Amp<i16>>(&saw(), ScalarFxP::lit("0.3"), |out| out);
+}
+
+#[test]
+fn osc_stream() {
+    let notes = vec![NoteFxP::lit("57"); LEN];
+    check_chunks::<Osc<i16>>(&notes, OscParams::default(), |out| out.saw);
+}
@@ -0,0 +1,85 @@
+//! Verify that [EnvIter] gives sample-identical output to [Device::process],
+//! and that its convenience methods stop at the right points.
+
+use core::iter::repeat;
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+#[cfg(feature = "fixed")]
+use culsynth::devices::EnvStage;
+use culsynth::devices::{Device, Env, EnvParams};
+#[cfg(feature = "fixed")]
+use culsynth::{EnvParamFxP, ScalarFxP};
+
+/// Give up on anything taking longer than 10 seconds
+#[cfg(feature = "fixed")]
+const LIMIT: usize = 480_000;
+
+#[cfg(feature = "fixed")]
+fn params_fixed() -> EnvParams<i16> {
+    EnvParams {
+        attack: EnvParamFxP::lit("0.01"),
+        decay: EnvParamFxP::lit("0.05"),
+        sustain: ScalarFxP::lit("0.5"),
+        release: EnvParamFxP::lit("0.1"),
+        ..Default::default()
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn env_iter_matches_process_fixed() {
+    let ctx = ContextFxP::new_480();
+    let params = params_fixed();
+    let gates: Vec<bool> = (0..9600).map(|i| i < 4800).collect();
+    let mut env = Env::<i16>::default();
+    let batch: Vec<ScalarFxP> =
+        env.process(&ctx, gates.iter().copied(), repeat(params.clone())).collect();
+    let mut env = Env::<i16>::default();
+    let mut iter = env.iter(&ctx, true, params);
+    let mut streamed: Vec<ScalarFxP> = iter.by_ref().take(4800).collect();
+    iter.set_gate(false);
+    streamed.extend(iter.take(4800));
+    assert_eq!(streamed, batch);
+}
+
+#[test]
+fn env_iter_matches_process_float() {
+    let ctx = Context::new(48000f64);
+    let params = EnvParams::<f64> {
+        attack: 0.01,
+        decay: 0.05,
+        sustain: 0.5,
+        release: 0.1,
+        ..Default::default()
+    };
+    let mut env = Env::<f64>::default();
+    let batch: Vec<f64> = env
+        .process(
+            &ctx,
+            core::iter::repeat_n(true, 4800),
+            repeat(params.clone()),
+        )
+        .collect();
+    let mut env = Env::<f64>::default();
+    let streamed: Vec<f64> = env.iter(&ctx, true, params).take(4800).collect();
+    assert_eq!(streamed, batch);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn env_iter_skip() {
+    let ctx = ContextFxP::new_480();
+    let mut env = Env::<i16>::default();
+    let mut iter = env.iter(&ctx, true, params_fixed());
+    // The attack is about 10ms
+    let peak = iter.skip_until_peak(LIMIT).unwrap();
+    assert!((300..600).contains(&peak), "{}", peak);
+    assert_eq!(iter.env().stage(), EnvStage::Decay);
+    // The envelope never goes silent while the gate is held
+    assert_eq!(iter.skip_until_silent(48000), None);
+    iter.set_gate(false);
+    let silent = iter.skip_until_silent(LIMIT).unwrap();
+    assert!(silent > 0 && silent < 48000, "{}", silent);
+    assert_eq!(iter.env().stage(), EnvStage::Idle);
+}
@@ -0,0 +1,112 @@
+//! Verify the attack peak of the envelope: the attack should rise to the
+//! peak and then decay down to the sustain level (as a fraction of the
+//! peak), and a sustain of one should hold a flat tone at the peak.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Env, EnvParams, EnvStage};
+use culsynth::{DspFormat, EnvParamFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 48000;
+/// Long enough for the attack and decay to settle
+const NUM_SAMPLES: usize = 24000;
+
+fn params(attack_peak: ScalarFxP, sustain: ScalarFxP) -> EnvParams<i16> {
+    EnvParams {
+        attack: EnvParamFxP::lit("0.05"),
+        attack_peak,
+        decay: EnvParamFxP::lit("0.1"),
+        sustain,
+        ..Default::default()
+    }
+}
+
+/// Run the envelope with the gate held, returning the output and the sample
+/// at which the attack finished
+fn run<T: DspFormat>(ctx: &T::Context, params: EnvParams<T>) -> (Vec<f32>, usize) {
+    let mut env = Env::<T>::default();
+    let mut end_of_attack = None;
+    let out = (0..NUM_SAMPLES)
+        .map(|i| {
+            let (smp, stage) = env.next_with_stage(ctx, true, params.clone());
+            if stage != EnvStage::Attack && end_of_attack.is_none() {
+                end_of_attack = Some(i);
+            }
+            T::scalar_to_float(smp)
+        })
+        .collect();
+    (out, end_of_attack.unwrap())
+}
+
+fn check_peak<T: DspFormat>(ctx: &T::Context, params: EnvParams<T>, peak: f32, sustain: f32) {
+    let (out, end_of_attack) = run::<T>(ctx, params);
+    let (max_idx, max) = out.iter().enumerate().max_by(|a, b| a.1.total_cmp(b.1)).unwrap();
+    // The peak is the last sample of the attack...
+    assert_eq!(max_idx + 1, end_of_attack);
+    assert!((max - peak).abs() < 0.03 * peak, "{} {}", max, peak);
+    // ...and the output decays down to the sustain level from there
+    assert!(out[end_of_attack..].windows(2).all(|w| w[1] <= w[0]));
+    assert!(
+        (out[NUM_SAMPLES - 1] - sustain).abs() < 0.002,
+        "{}",
+        out[NUM_SAMPLES - 1]
+    );
+}
+
+fn check_flat<T: DspFormat>(ctx: &T::Context, params: EnvParams<T>, sustain: f32) {
+    let (out, _) = run::<T>(ctx, params);
+    // No bump: the output rises to the sustain level and stays there
+    assert!(out.windows(2).all(|w| w[1] >= w[0]));
+    assert!(out.iter().all(|x| *x < sustain + 0.002));
+    assert!(
+        (out[NUM_SAMPLES - 1] - sustain).abs() < 0.002,
+        "{}",
+        out[NUM_SAMPLES - 1]
+    );
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn attack_peak_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let (peak, sustain) = (ScalarFxP::lit("0.8"), ScalarFxP::lit("0.625"));
+    check_peak::<i16>(&ctx, params(peak, sustain), 0.8, 0.5);
+    check_flat::<i16>(&ctx, params(peak, ScalarFxP::MAX), 0.8);
+}
+
+#[test]
+fn attack_peak_float() {
+    let ctx = Context::new(SAMPLE_RATE as f32);
+    let (peak, sustain) = (ScalarFxP::lit("0.8"), ScalarFxP::lit("0.625"));
+    check_peak::<f32>(&ctx, (&params(peak, sustain)).into(), 0.8, 0.5);
+    check_flat::<f32>(&ctx, (&params(peak, ScalarFxP::MAX)).into(), 0.8);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn sustain_relative_to_full_peak() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let (out, _) = run::<i16>(&ctx, params(ScalarFxP::MAX, ScalarFxP::lit("0.5")));
+    let half = ScalarFxP::MAX.to_num::<f32>() / 2f32;
+    let last = out[NUM_SAMPLES - 1];
+    assert!(
+        (last - half).abs() <= 2f32 * ScalarFxP::DELTA.to_num::<f32>(),
+        "{}",
+        last
+    );
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn absolute_sustain_migration() {
+    let old = params(ScalarFxP::lit("0.8"), ScalarFxP::lit("0.5")).from_absolute_sustain();
+    assert_eq!(old.attack_peak, ScalarFxP::lit("0.8"));
+    assert!(old.sustain.dist(ScalarFxP::lit("0.625")) <= ScalarFxP::DELTA);
+    // The old attack rose at least to the sustain level
+    let old = params(ScalarFxP::lit("0.2"), ScalarFxP::lit("0.5")).from_absolute_sustain();
+    assert_eq!(old.attack_peak, ScalarFxP::lit("0.5"));
+    assert_eq!(old.sustain, ScalarFxP::MAX);
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    check_flat::<i16>(&ctx, old, 0.5);
+}
@@ -0,0 +1,125 @@
+//! Verify that changing the release time partway through a release changes
+//! the slope of the envelope from its current level, without any jump in the
+//! output or restarting the release.
+//!
+//! Each sample of the release moves `2/k` of the remaining distance towards
+//! zero, where `k = 1 + time * sample_rate / 2` is calculated from the
+//! current parameters, so the new slope takes effect on the very next sample.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Env, EnvParams};
+use culsynth::{DspFormat, EnvParamFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 48000;
+/// Samples to hold the gate, and to release at each release time
+const STAGE: usize = 4800;
+
+fn params(release: EnvParamFxP) -> EnvParams<i16> {
+    EnvParams {
+        attack: EnvParamFxP::lit("0.01"),
+        decay: EnvParamFxP::lit("0.01"),
+        sustain: ScalarFxP::MAX,
+        release,
+        ..Default::default()
+    }
+}
+
+/// The fraction of the remaining level released each sample
+fn step(release: EnvParamFxP) -> f64 {
+    2f64 / (1f64 + release.to_num::<f64>() * (SAMPLE_RATE / 2) as f64)
+}
+
+/// Hold the gate, then release with `before` for [STAGE] samples and then
+/// with `after` for [STAGE] samples, returning the release.  `params`
+/// converts a release time into the parameters for the envelope.
+fn release<T: DspFormat>(
+    ctx: &T::Context,
+    params: impl Fn(EnvParamFxP) -> EnvParams<T>,
+    before: EnvParamFxP,
+    after: EnvParamFxP,
+) -> Vec<f64> {
+    let mut env = Env::<T>::default();
+    for _ in 0..STAGE {
+        let _ = env.next(ctx, true, params(before));
+    }
+    let mut out = Vec::new();
+    for release in [before, after] {
+        for _ in 0..STAGE {
+            let smp = env.next(ctx, false, params(release));
+            out.push(T::scalar_to_float(smp) as f64);
+        }
+    }
+    out
+}
+
+fn check_retarget(out: &[f64], before: EnvParamFxP, after: EnvParamFxP, lsb: f64) {
+    // The release never turns around or restarts
+    assert!(out.windows(2).all(|w| w[1] <= w[0]));
+    // The step on either side of the change is set by the release time in
+    // effect, starting from the level where the change happened
+    let level = out[STAGE - 1];
+    let step_before = out[STAGE - 2] - level;
+    let step_after = level - out[STAGE];
+    let expected_before = out[STAGE - 2] * step(before);
+    let expected_after = level * step(after);
+    assert!(
+        (step_before - expected_before).abs() <= expected_before * 0.05 + lsb,
+        "{} {}",
+        step_before,
+        expected_before
+    );
+    assert!(
+        (step_after - expected_after).abs() <= expected_after * 0.05 + lsb,
+        "{} {}",
+        step_after,
+        expected_after
+    );
+    // ...and it stays on the new slope
+    let steps_after = (1..4).map(|i| out[STAGE + i - 1] - out[STAGE + i]);
+    for (i, s) in steps_after.enumerate() {
+        let expected = out[STAGE + i] * step(after);
+        assert!(
+            (s - expected).abs() <= expected * 0.05 + lsb,
+            "{} {}",
+            s,
+            expected
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn release_retargets_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let lsb = ScalarFxP::DELTA.to_num::<f64>() * 2f64;
+    for (before, after) in [
+        (EnvParamFxP::lit("2"), EnvParamFxP::lit("0.1")),
+        (EnvParamFxP::lit("0.2"), EnvParamFxP::lit("4")),
+    ] {
+        let out = release(&ctx, params, before, after);
+        check_retarget(&out, before, after, lsb);
+    }
+    // Shortening the release finishes it much sooner
+    let out = release(
+        &ctx,
+        params,
+        EnvParamFxP::lit("2"),
+        EnvParamFxP::lit("0.05"),
+    );
+    assert!(out[STAGE - 1] > 0.5);
+    assert!(out[2 * STAGE - 1] < 0.001);
+}
+
+#[test]
+fn release_retargets_float() {
+    let ctx = Context::new(SAMPLE_RATE as f64);
+    for (before, after) in [
+        (EnvParamFxP::lit("2"), EnvParamFxP::lit("0.1")),
+        (EnvParamFxP::lit("0.2"), EnvParamFxP::lit("4")),
+    ] {
+        let out = release::<f64>(&ctx, |r| (&params(r)).into(), before, after);
+        check_retarget(&out, before, after, 1e-9);
+    }
+}
@@ -0,0 +1,54 @@
+//! Verify that the stage reported by an envelope moves through attack, decay,
+//! sustain, release, and idle at the expected samples.
+
+mod common;
+
+use common::TestClock;
+use culsynth::context::Context;
+use culsynth::devices::{Env, EnvParams, EnvStage};
+
+const SUSTAIN: f64 = 0.5;
+/// How close the output must be to its setpoint to count as settled
+const SETTLED: f64 = 1f64 / 1024f64;
+/// Give up on anything taking longer than 10 seconds
+const LIMIT: usize = 480_000;
+
+#[test]
+fn env_stage_sequence() {
+    let params = EnvParams::<f64> {
+        attack: 0.01,
+        decay: 0.05,
+        sustain: SUSTAIN,
+        release: 0.1,
+        ..Default::default()
+    };
+    let mut clock = TestClock::new(Context::new(48000f64), Env::<f64>::default());
+    assert_eq!(clock.device().stage(), EnvStage::Idle);
+    let (_, stage) = clock.device().clone().next_with_stage(clock.context(), true, params.clone());
+    assert_eq!(stage, EnvStage::Attack);
+
+    // Find where the output crosses each threshold, then replay the same
+    // envelope to check that the stage changes on exactly the right sample
+    let mut levels = Vec::new();
+    let release_at = 20_000;
+    levels.extend(clock.advance(release_at, true, &params));
+    levels.extend(clock.advance(release_at, false, &params));
+    let first = |from: usize, pred: &dyn Fn(f64) -> bool| {
+        (from..levels.len()).find(|i| pred(levels[*i])).unwrap()
+    };
+    let peak = first(0, &|x| x > 0.98);
+    let sustain = first(peak, &|x| (x - SUSTAIN).abs() < SETTLED);
+    let idle = first(release_at, &|x| x.abs() < SETTLED);
+
+    let mut clock = TestClock::new(Context::new(48000f64), Env::<f64>::default());
+    let stage_is = |stage| move |env: &Env<f64>, _: &f64| env.stage() == stage;
+    let decay = clock.run_until(LIMIT, true, &params, stage_is(EnvStage::Decay));
+    assert_eq!(decay, Some(peak + 1));
+    let sus = clock.run_until(LIMIT, true, &params, stage_is(EnvStage::Sustain));
+    assert_eq!(sus, Some(sustain));
+    clock.advance(release_at - clock.now(), true, &params);
+    let release = clock.run_until(1, false, &params, stage_is(EnvStage::Release));
+    assert_eq!(release, Some(release_at));
+    let done = clock.run_until(LIMIT, false, &params, stage_is(EnvStage::Idle));
+    assert_eq!(done, Some(idle));
+}
@@ -0,0 +1,189 @@
+//! Verify the timing of each stage of the envelope generator against the
+//! duration predicted by its time parameters.
+//!
+//! Each stage is a one-pole (bilinear) filter approaching its setpoint, with
+//! `k = 1 + time * sample_rate / 2`.  After the first sample of a stage, the
+//! distance to the setpoint shrinks by a factor of `1 - 2/k` every sample, so
+//! a stage never actually reaches its setpoint (the rise time is ~4 time
+//! constants).  Instead, the attack completes when the output reaches 0.98
+//! (when the envelope switches to decay), and the decay and release complete
+//! when the output is within 2% of the setpoint, relative to the distance at
+//! the start of the stage.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Env, EnvParams, ResetMode};
+use culsynth::{DspFormat, DspType, EnvParamFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+const SUSTAIN: ScalarFxP = ScalarFxP::lit("0.5");
+const ATTACK_THRESHOLD: f64 = 0.98;
+/// The fraction of the initial distance to the setpoint remaining at the end
+/// of the decay and release stages
+const REMAINING: f64 = 0.02;
+/// Times spanning the full range of a U3F13
+const TIMES: [EnvParamFxP; 6] = [
+    EnvParamFxP::lit("0.001"),
+    EnvParamFxP::lit("0.05"),
+    EnvParamFxP::lit("0.5"),
+    EnvParamFxP::lit("2"),
+    EnvParamFxP::lit("5"),
+    EnvParamFxP::MAX,
+];
+
+/// The filter coefficient for a stage with the given time
+fn coeff(time: EnvParamFxP) -> f64 {
+    1f64 + time.to_num::<f64>() * (SAMPLE_RATE / 2) as f64
+}
+
+/// The number of samples for a stage starting at level `start` to come within
+/// `remaining` of its `setpoint`, where `prev` is the previous setpoint
+fn expected_samples(
+    time: EnvParamFxP,
+    start: f64,
+    prev: f64,
+    setpoint: f64,
+    remaining: f64,
+) -> f64 {
+    let k = coeff(time);
+    let first = setpoint - (start + (prev + setpoint - 2f64 * start) / k);
+    if first.abs() <= remaining {
+        return 1f64;
+    }
+    1f64 + ((remaining / first.abs()).ln() / (1f64 - 2f64 / k).ln()).ceil()
+}
+
+/// Run `env` until `done` returns true for its output, returning the number of
+/// samples processed and the final output
+fn run_until<T: DspFormat>(
+    env: &mut Env<T>,
+    ctx: &T::Context,
+    gate: bool,
+    params: &EnvParams<T>,
+    done: impl Fn(f64) -> bool,
+) -> (f64, f64) {
+    let mut n = 0;
+    loop {
+        n += 1;
+        let out = T::scalar_to_float(env.next(ctx, gate, params.clone())) as f64;
+        if done(out) || n > 10_000_000 {
+            return (n as f64, out);
+        }
+    }
+}
+
+/// Check all of the stages of an envelope with attack, decay, and release
+/// all set to `time`.  `min` and `max` are the envelope's internal setpoints
+/// (full scale is not quite 0 and 1 in fixed point), `lsb` is the resolution
+/// of the output, and `tolerance` is the allowed relative error in each
+/// duration (in addition to one sample).
+fn check_env<T: DspFormat>(
+    ctx: &T::Context,
+    params: EnvParams<T>,
+    time: EnvParamFxP,
+    (min, max, lsb): (f64, f64, f64),
+    tolerance: f64,
+) {
+    // The sustain level is relative to the (almost full scale) peak
+    let sustain = T::scalar_to_float(params.sustain.scale(params.attack_peak)) as f64;
+    let check = |stage: &str, measured: f64, expected: f64, remaining: f64| {
+        // The output approaches the end of the stage at a rate of about
+        // 2 * remaining / k per sample, so quantization of the output may
+        // move the end of the stage by lsb / rate samples
+        let quantization = lsb * coeff(time) / (2f64 * remaining);
+        assert!(
+            (measured - expected).abs() <= 1f64 + expected * tolerance + quantization,
+            "{} with time {}: {} samples, expected {}",
+            stage,
+            time,
+            measured,
+            expected
+        );
+    };
+    let mut env = Env::<T>::default();
+
+    // The attack is complete once the envelope starts to decay, which includes
+    // the first sample of the decay
+    let (mut attack, mut peak, mut out) = (0f64, 0f64, 0f64);
+    while out >= peak {
+        peak = out;
+        attack += 1f64;
+        out = T::scalar_to_float(env.next(ctx, true, params.clone())) as f64;
+    }
+    check(
+        "attack",
+        attack - 1f64,
+        expected_samples(time, 0f64, 0f64, max, max - ATTACK_THRESHOLD),
+        max - ATTACK_THRESHOLD,
+    );
+
+    let target = REMAINING * (peak - sustain);
+    let decay = if out - sustain <= target {
+        1f64
+    } else {
+        1f64 + run_until(&mut env, ctx, true, &params, |x| x - sustain <= target).0
+    };
+    check(
+        "decay",
+        decay,
+        expected_samples(time, peak, max, sustain, target),
+        target,
+    );
+
+    // Let the decay settle completely, then the output should hold at the
+    // sustain level for as long as the gate is held
+    let (_, mut level) = run_until(&mut env, ctx, true, &params, |x| (x - sustain).abs() < 1e-4);
+    for _ in 0..SAMPLE_RATE {
+        level = T::scalar_to_float(env.next(ctx, true, params.clone())) as f64;
+        assert!(
+            (level - sustain).abs() < 1e-4,
+            "sustain with time {}: {}",
+            time,
+            level
+        );
+    }
+
+    let target = REMAINING * (level - min);
+    let (release, _) = run_until(&mut env, ctx, false, &params, |x| x - min <= target);
+    check(
+        "release",
+        release,
+        expected_samples(time, level, sustain, min, target),
+        target,
+    );
+}
+
+fn params(time: EnvParamFxP) -> EnvParams<i16> {
+    EnvParams {
+        attack: time,
+        attack_peak: ScalarFxP::MAX,
+        decay: time,
+        sustain: SUSTAIN,
+        release: time,
+        reset: ResetMode::Soft,
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn env_timing_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    // The fixed point setpoints are slightly inside of [0, 1]
+    let min = ScalarFxP::lit("0x0.0004").to_num::<f64>();
+    let max = ScalarFxP::lit("0x0.FFFC").to_num::<f64>();
+    let lsb = ScalarFxP::DELTA.to_num::<f64>();
+    for time in TIMES {
+        // The gain of each step is calculated with ~16 bits of precision,
+        // so allow a small relative error for long stages
+        check_env(&ctx, params(time), time, (min, max, lsb), 1e-4);
+    }
+}
+
+#[test]
+fn env_timing_float() {
+    let ctx = Context::<f64>::new(SAMPLE_RATE as f64);
+    for time in TIMES {
+        check_env::<f64>(&ctx, (&params(time)).into(), time, (0f64, 1f64, 0f64), 0f64);
+    }
+}
@@ -0,0 +1,44 @@
+//! Verify that DC drift correction improves the accuracy of the fixed point
+//! filter relative to a double precision reference.
+#![cfg(feature = "fixed")]
+
+use culsynth::context::{Context, ContextFxP};
+use culsynth::devices::{Device, Filt, FiltParams, Resonance};
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+const NUM_SAMPLES: usize = 10000;
+
+/// RMS error of the low pass output of a fixed point filter, relative to a
+/// double precision reference, for a constant input (i.e. where all of the
+/// error is due to drift)
+fn low_pass_rms_error(dc_correct: bool) -> f64 {
+    let ctx_fxp = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let ctx_ref = Context::<f64>::new(SAMPLE_RATE as f64);
+    // 1kHz cutoff, resonance 0.9
+    let params = FiltParams::<i16> {
+        cutoff: NoteFxP::from_num(69.0 + 12.0 * (1000f64 / 440.0).log2()),
+        resonance: Resonance::new(ScalarFxP::from_num(0.9)),
+    };
+    let params_ref = FiltParams::<f64>::from(&params);
+    let mut filt = Filt::<i16>::new();
+    filt.set_dc_correct(dc_correct);
+    let mut filt_ref = Filt::<f64>::new();
+    let input = SampleFxP::from_num(0.25);
+    let mut error = 0f64;
+    for _ in 0..NUM_SAMPLES {
+        let out = filt.next(&ctx_fxp, input, params.clone());
+        let out_ref = filt_ref.next(&ctx_ref, input.to_num(), params_ref.clone());
+        let this_error = out.low.to_num::<f64>() - out_ref.low;
+        error += this_error * this_error;
+    }
+    (error / NUM_SAMPLES as f64).sqrt()
+}
+
+#[test]
+fn filt_dc_correction_improves_error() {
+    let uncorrected = low_pass_rms_error(false);
+    let corrected = low_pass_rms_error(true);
+    let improvement_db = 20.0 * (uncorrected / corrected).log10();
+    assert!(improvement_db >= 10.0, "improvement {} dB", improvement_db);
+}
@@ -0,0 +1,136 @@
+//! Verify that routing an envelope to [ModDest::FiltKbd] blends in keyboard
+//! tracking, so that the cutoff is at its base value while the envelope is
+//! at zero and follows the keyboard once the envelope opens.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{EnvParams, ModFiltInput, ModFiltParams};
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSection, ModSectionParams, ModSrc};
+use culsynth::{DspFormat, EnvParamFxP, IScalarFxP, NoteFxP, ScalarFxP};
+
+/// The base cutoff of the filter
+const CUTOFF: NoteFxP = NoteFxP::lit("40");
+/// The note being played
+const NOTE: NoteFxP = NoteFxP::lit("60");
+/// Long enough for Env1 to reach its sustain level
+const LEN: usize = 4800;
+
+/// Env1 rises to full scale almost immediately and holds there
+fn params() -> ModSectionParams<i16> {
+    ModSectionParams {
+        velocity: ScalarFxP::ZERO,
+        aftertouch: ScalarFxP::ZERO,
+        modwheel: ScalarFxP::ZERO,
+        sidechain: ScalarFxP::ZERO,
+        env_filt: ScalarFxP::ZERO,
+        env_amp: ScalarFxP::ZERO,
+        env_filt_scaled: ScalarFxP::ZERO,
+        lfo1_params: Default::default(),
+        lfo2_params: Default::default(),
+        env1_params: EnvParams {
+            attack: EnvParamFxP::lit("0.01"),
+            attack_peak: ScalarFxP::MAX,
+            sustain: ScalarFxP::MAX,
+            ..Default::default()
+        },
+        env2_params: Default::default(),
+    }
+}
+
+fn params_float() -> ModSectionParams<f32> {
+    let p = params();
+    ModSectionParams {
+        velocity: 0f32,
+        aftertouch: 0f32,
+        modwheel: 0f32,
+        sidechain: 0f32,
+        env_filt: 0f32,
+        env_amp: 0f32,
+        env_filt_scaled: 0f32,
+        lfo1_params: (&p.lfo1_params).into(),
+        lfo2_params: (&p.lfo2_params).into(),
+        env1_params: (&p.env1_params).into(),
+        env2_params: (&p.env2_params).into(),
+    }
+}
+
+fn matrix(depth: IScalarFxP) -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[ModSrc::Env1 as usize].1[0] = (ModDest::FiltKbd, depth);
+    matrix
+}
+
+/// The filter settings, with no keyboard tracking before modulation
+fn filt_params() -> ModFiltParams<i16> {
+    ModFiltParams {
+        cutoff: CUTOFF,
+        ..Default::default()
+    }
+}
+
+/// Returns the effective cutoff (as a MIDI note) before the gate opens and
+/// once Env1 has reached its sustain level
+fn cutoffs<T: DspFormat>(
+    ctx: &T::Context,
+    matrix: &ModMatrix<T>,
+    params: ModSectionParams<T>,
+    filt_params: ModFiltParams<T>,
+    note: T::Note,
+) -> (f32, f32) {
+    let mut section = ModSection::<T>::default();
+    let mut matrix = Some(matrix);
+    let mut cutoff = |gate: bool| {
+        let modulator = section.next(ctx, gate, params.clone(), matrix.take());
+        let mut filt_params = filt_params.clone();
+        modulator.modulate_mod_filt(&mut filt_params);
+        let input = ModFiltInput::<T> {
+            signal: Default::default(),
+            env: Default::default(),
+            vel: Default::default(),
+            kbd: note,
+        };
+        T::note_to_float(filt_params.to_filt_params(&input).cutoff)
+    };
+    let closed = cutoff(false);
+    let open = (0..LEN).map(|_| cutoff(true)).last().unwrap();
+    (closed, open)
+}
+
+/// Check that the cutoff tracks `tracking` (from 0 to 1) of the note at the
+/// envelope peak, and none of it at envelope zero
+fn check(cutoffs: (f32, f32), tracking: f32) {
+    let base = CUTOFF.to_num::<f32>();
+    let expected = base + tracking * NOTE.to_num::<f32>();
+    assert!((cutoffs.0 - base).abs() < 0.01, "{:?}", cutoffs);
+    assert!((cutoffs.1 - expected).abs() < 0.1, "{:?}", cutoffs);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn env1_kbd_tracking_fixed() {
+    let ctx = ContextFxP::new_480();
+    let run = |depth| cutoffs::<i16>(&ctx, &matrix(depth), params(), filt_params(), NOTE);
+    check(run(IScalarFxP::MAX), 1f32);
+    check(run(IScalarFxP::lit("0.5")), 0.5f32);
+    // Negative modulation can't take the tracking below zero
+    check(run(IScalarFxP::lit("-0.5")), 0f32);
+}
+
+#[test]
+fn env1_kbd_tracking_float() {
+    let ctx = Context::new(48000f32);
+    let run = |depth| {
+        cutoffs::<f32>(
+            &ctx,
+            &(&matrix(depth)).into(),
+            params_float(),
+            (&filt_params()).into(),
+            NOTE.to_num(),
+        )
+    };
+    check(run(IScalarFxP::MAX), 1f32);
+    check(run(IScalarFxP::lit("0.5")), 0.5f32);
+    // Negative modulation can't take the tracking below zero
+    check(run(IScalarFxP::lit("-0.5")), 0f32);
+}
@@ -0,0 +1,56 @@
+//! Verify that a floating point filter recovers from a NaN or infinite input
+//! (or a finite input large enough to overflow its state), rather than
+//! outputting NaN forever.  A non-finite input is a bug, so debug builds
+//! panic on it instead.
+#![cfg(feature = "state_guard")]
+
+use culsynth::context::Context;
+use culsynth::devices::{Device, Filt, FiltParams, Resonance};
+
+const SAMPLE_RATE: f32 = 48000f32;
+const BLOCK_SIZE: usize = 64;
+
+fn recovers_from(cutoff: f32, bad: &[f32]) {
+    let ctx = Context::<f32>::new(SAMPLE_RATE);
+    let params = FiltParams::<f32> {
+        cutoff,
+        resonance: Resonance::from(0.5f32),
+    };
+    let mut filt = Filt::<f32>::new();
+    let mut other = Filt::<f32>::new();
+    let input = |i: usize| (i as f32 * 0.05f32).sin() * 0.5f32;
+    for i in 0..BLOCK_SIZE {
+        let _ = filt.next(&ctx, input(i), params.clone());
+        let _ = other.next(&ctx, input(i), params.clone());
+    }
+    assert!(!filt.take_state_reset());
+    for smp in bad {
+        let _ = filt.next(&ctx, *smp, params.clone());
+    }
+    assert!(filt.take_state_reset());
+    // Only reported once, and only by the filter that was reset
+    assert!(!filt.take_state_reset());
+    assert!(!other.take_state_reset());
+    for i in 0..BLOCK_SIZE {
+        let out = filt.next(&ctx, input(i), params.clone());
+        for smp in [out.low, out.band, out.high] {
+            assert!(smp.is_finite() && smp.abs() < 4f32, "{}: {}", i, smp);
+        }
+    }
+}
+
+/// Debug builds assert that the input is finite, so this only recovers in
+/// release builds
+#[test]
+#[cfg_attr(debug_assertions, should_panic(expected = "non-finite input"))]
+fn nan_input() {
+    recovers_from(60f32, &[f32::NAN]);
+    recovers_from(60f32, &[f32::INFINITY]);
+}
+
+/// A finite input can still overflow the state of a filter with a high
+/// cutoff
+#[test]
+fn overflowing_input() {
+    recovers_from(127f32, &[f32::MAX; 2]);
+}
@@ -0,0 +1,51 @@
+//! Verify that filtering four samples at a time produces exactly the same
+//! output as filtering one sample at a time.
+#![cfg(all(feature = "fixed", feature = "pipeline4"))]
+
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Filt, FiltParams, Resonance};
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP};
+
+const NUM_SAMPLES: usize = 10000;
+
+/// Deterministic white noise, from a linear congruential generator
+fn noise(len: usize) -> Vec<SampleFxP> {
+    let mut state = 12345u32;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            SampleFxP::from_bits((state >> 16) as i16 >> 2)
+        })
+        .collect()
+}
+
+/// Sweep the cutoff and resonance, changing every four samples
+fn params(block: usize) -> FiltParams<i16> {
+    FiltParams {
+        cutoff: NoteFxP::from_num(20 + (block * 7) % 100),
+        resonance: Resonance::new(ScalarFxP::from_bits(((block * 997) % 0xF000) as u16)),
+    }
+}
+
+#[test]
+fn pipelined_matches_standard() {
+    let ctx = ContextFxP::new_480();
+    let input = noise(NUM_SAMPLES);
+    for dc_correct in [false, true] {
+        let mut standard = Filt::<i16>::new();
+        let mut pipelined = Filt::<i16>::new();
+        standard.set_dc_correct(dc_correct);
+        pipelined.set_dc_correct(dc_correct);
+        for (block, smps) in input.chunks_exact(4).enumerate() {
+            let p = params(block);
+            let outs = pipelined.process_pipelined_4(&ctx, smps.try_into().unwrap(), p.clone());
+            for (smp, out) in smps.iter().zip(outs) {
+                let expected = standard.next(&ctx, *smp, p.clone());
+                assert_eq!(out.low.to_bits(), expected.low.to_bits());
+                assert_eq!(out.band.to_bits(), expected.band.to_bits());
+                assert_eq!(out.high.to_bits(), expected.high.to_bits());
+            }
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! Verify the conversions between filter resonance and Q factor, and that
+//! [Resonance] stays within the usable range of the filter.
+
+use culsynth::devices::{q_to_resonance, q_to_resonance_fxp, resonance_to_q, Resonance};
+use culsynth::{Float, ScalarFxP};
+
+const NUM_VALUES: u16 = 20;
+
+/// Evenly spaced resonance values over the usable range of the filter
+fn resonances<Smp: Float>() -> impl Iterator<Item = Smp> {
+    (0..NUM_VALUES).map(|i| Smp::RES_MAX * Smp::from_u16(i) / Smp::from_u16(NUM_VALUES - 1))
+}
+
+fn check_round_trip<Smp: Float + core::fmt::Display>(epsilon: Smp) {
+    for res in resonances::<Smp>() {
+        let round_trip = q_to_resonance(resonance_to_q(res));
+        assert!(
+            (round_trip - res).abs() < epsilon,
+            "resonance {} became {}",
+            res,
+            round_trip
+        );
+    }
+}
+
+#[test]
+fn resonance_q_round_trip_f32() {
+    check_round_trip::<f32>(1e-6);
+}
+
+#[test]
+fn resonance_q_round_trip_f64() {
+    check_round_trip::<f64>(1e-12);
+}
+
+#[test]
+fn resonance_q_round_trip_fixed() {
+    for res in resonances::<f32>() {
+        let res = ScalarFxP::from_num(res);
+        let round_trip = q_to_resonance_fxp(resonance_to_q(res.to_num::<f32>()));
+        assert!(
+            round_trip.abs_diff(res) <= ScalarFxP::DELTA,
+            "resonance {} became {}",
+            res,
+            round_trip
+        );
+    }
+}
+
+#[test]
+fn resonance_q_range() {
+    // Zero resonance is critically damped, and the maximum is a Q of 8
+    assert_eq!(resonance_to_q(0f32), 0.5f32);
+    assert_eq!(resonance_to_q(0.9375f32), 8f32);
+    assert_eq!(q_to_resonance(0.25f32), 0f32);
+    assert_eq!(q_to_resonance(f32::INFINITY), 1f32);
+    assert_eq!(q_to_resonance_fxp(8f32), ScalarFxP::lit("0.9375"));
+}
+
+#[test]
+fn resonance_is_clamped() {
+    let max = ScalarFxP::lit("0.9375");
+    assert_eq!(ScalarFxP::from(Resonance::<i16>::new(ScalarFxP::MAX)), max);
+    assert_eq!(ScalarFxP::from(Resonance::<i16>::MAX), max);
+    assert_eq!(
+        ScalarFxP::from(Resonance::<i16>::from(0.5f32)),
+        ScalarFxP::lit("0.5")
+    );
+    assert_eq!(ScalarFxP::from(Resonance::<i16>::from(2f32)), max);
+    assert!(Resonance::<i16>::try_from(max).is_ok());
+    assert!(Resonance::<i16>::try_from(max + ScalarFxP::DELTA).is_err());
+    assert_eq!(f32::from(Resonance::<f32>::new(1f32)), f32::RES_MAX);
+    assert_eq!(f32::from(Resonance::<f32>::new(-1f32)), 0f32);
+    assert_eq!(f64::from(Resonance::<f64>::from(0.5f32)), 0.5f64);
+}
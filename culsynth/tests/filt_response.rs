@@ -0,0 +1,125 @@
+//! Verify that the frequency response calculated from the filter coefficients
+//! (`Filt::response`) matches the response measured by filtering sine waves,
+//! for each output and a mix of them, with and without resonance.
+
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::context::{Context, GenericContext};
+use culsynth::devices::{resonance_to_q, Device, Filt, FiltParams, Resonance};
+use culsynth::{DspFormat, NoteFxP};
+
+const SAMPLE_RATE: u32 = 48000;
+const CUTOFF_HZ: f64 = 1000.0;
+/// Test tones, relative to the cutoff
+const TONES: [f64; 5] = [0.25, 0.5, 1.0, 2.0, 4.0];
+const AMPLITUDE: f64 = 0.25;
+/// Mixes of the low, band, and high pass outputs to check
+const MIXES: [(f32, f32, f32); 4] = [(1., 0., 0.), (0., 1., 0.), (0., 0., 1.), (1., 0.5, 1.)];
+
+fn params(resonance: f32) -> FiltParams<i16> {
+    FiltParams {
+        cutoff: NoteFxP::from_num(69.0 + 12.0 * (CUTOFF_HZ / 440.0).log2()),
+        resonance: Resonance::from(resonance),
+    }
+}
+
+/// The measured gain (in dB) of `mix` at `freq`, as the ratio of output to
+/// input power after letting the filter settle for a quarter of a second
+fn measured_gain_db<T: DspFormat>(
+    ctx: &T::Context,
+    params: FiltParams<T>,
+    freq: f64,
+    mix: (f32, f32, f32),
+    to_sample: impl Fn(f64) -> T::Sample,
+) -> f64 {
+    let sample_rate = ctx.sample_rate() as f64;
+    let settle = (sample_rate / 4.0) as usize;
+    let mut filt = Filt::<T>::new();
+    let (power_in, power_out) = (0..3 * settle)
+        .map(|i| {
+            let phase = core::f64::consts::TAU * freq * i as f64 / sample_rate;
+            let smp = AMPLITUDE * phase.sin();
+            let out = filt.next(ctx, to_sample(smp), params.clone());
+            let out = mix.0 * T::sample_to_float(out.low)
+                + mix.1 * T::sample_to_float(out.band)
+                + mix.2 * T::sample_to_float(out.high);
+            (i, smp, out as f64)
+        })
+        .filter(|(i, _, _)| *i >= settle)
+        .fold((0f64, 0f64), |(power_in, power_out), (_, smp, out)| {
+            (power_in + smp * smp, power_out + out * out)
+        });
+    10.0 * (power_out / power_in).log10()
+}
+
+fn check_response<T: DspFormat>(
+    ctx: &T::Context,
+    params: FiltParams<T>,
+    to_sample: impl Fn(f64) -> T::Sample,
+    tolerance_db: f64,
+) {
+    for tone in TONES {
+        let freq = tone * CUTOFF_HZ;
+        let response = Filt::<T>::response(ctx, &params, freq as f32);
+        for mix in MIXES {
+            let expected = response.mix_db(mix.0, mix.1, mix.2) as f64;
+            let measured = measured_gain_db(ctx, params.clone(), freq, mix, &to_sample);
+            assert!(
+                (measured - expected).abs() < tolerance_db,
+                "{}Hz {:?}: measured {}dB, calculated {}dB",
+                freq,
+                mix,
+                measured,
+                expected
+            );
+        }
+    }
+}
+
+#[test]
+fn response_float() {
+    let ctx = Context::<f64>::new(SAMPLE_RATE as f64);
+    for resonance in [0.0, 0.75] {
+        check_response::<f64>(&ctx, (&params(resonance)).into(), |x| x, 0.1);
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn response_fixed() {
+    let ctx = ContextFxP::new_480();
+    for resonance in [0.0, 0.75] {
+        check_response(&ctx, params(resonance), culsynth::SampleFxP::from_num, 0.5);
+    }
+}
+
+#[test]
+fn resonant_peak() {
+    // At the cutoff, the gain of each output is the Q factor, and the low
+    // and high pass outputs cancel each other out in a notch
+    let ctx = Context::<f64>::new(SAMPLE_RATE as f64);
+    let params: FiltParams<f64> = (&params(0.75)).into();
+    let q = resonance_to_q(params.resonance.get());
+    let response = Filt::<f64>::response(&ctx, &params, CUTOFF_HZ as f32);
+    let q_db = 20.0 * q.log10() as f32;
+    assert!(
+        (response.band_db() - q_db).abs() < 0.05,
+        "{}",
+        response.band_db()
+    );
+    assert!(
+        (response.low_db() - q_db).abs() < 0.05,
+        "{}",
+        response.low_db()
+    );
+    assert!(
+        (response.high_db() - q_db).abs() < 0.05,
+        "{}",
+        response.high_db()
+    );
+    assert!(response.mix_db(1., 0., 1.) < -40.);
+    // The response stays finite all the way up to Nyquist
+    let nyquist = Filt::<f64>::response(&ctx, &params, SAMPLE_RATE as f32 / 2.0);
+    assert!(nyquist.high_db().abs() < 0.05);
+    assert!(nyquist.low_db() < -100.0);
+}
@@ -0,0 +1,122 @@
+//! Verify that the low pass response of the filter matches the (bilinear
+//! transformed) analog prototype at a range of sample rates, using
+//! `clone_with_adjusted_sr` to run the same context at each rate.
+//!
+//! With no resonance, the analog prototype is `1 / (s^2 + 2s + 1)`.  The
+//! cutoff is prewarped, so the digital response at `f` is the analog response
+//! at `tan(pi * f / sr) / tan(pi * f_c / sr)`.
+
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::context::{Context, GenericContext};
+use culsynth::devices::{Device, Filt, FiltParams, Resonance};
+use culsynth::{DspFormat, NoteFxP, ScalarFxP};
+
+const SAMPLE_RATES: [u32; 4] = [22050, 44100, 48000, 96000];
+/// Test tones, relative to the cutoff
+const TONES: [f64; 3] = [0.25, 1.0, 4.0];
+const AMPLITUDE: f64 = 0.5;
+
+fn params() -> FiltParams<i16> {
+    FiltParams {
+        cutoff: NoteFxP::from_num(69.0 + 12.0 * (1000f64 / 440.0).log2()),
+        resonance: Resonance::new(ScalarFxP::ZERO),
+    }
+}
+
+fn cutoff_freq() -> f64 {
+    440.0 * 2f64.powf((params().cutoff.to_num::<f64>() - 69.0) / 12.0)
+}
+
+/// The expected low pass gain (in dB) at `freq`
+fn expected_gain_db(freq: f64, sample_rate: u32) -> f64 {
+    let warp = |f: f64| (core::f64::consts::PI * f / sample_rate as f64).tan();
+    let w = warp(freq) / warp(cutoff_freq());
+    let mag = ((1.0 - w * w).powi(2) + (2.0 * w).powi(2)).sqrt();
+    -20.0 * mag.log10()
+}
+
+/// The measured low pass gain (in dB) at `freq`, after letting the filter
+/// settle for a quarter of a second
+fn measured_gain_db<T: DspFormat>(
+    ctx: &T::Context,
+    params: FiltParams<T>,
+    freq: f64,
+    to_sample: impl Fn(f64) -> T::Sample,
+) -> f64 {
+    let sample_rate = ctx.sample_rate() as f64;
+    let settle = (sample_rate / 4.0) as usize;
+    let mut filt = Filt::<T>::new();
+    let peak = (0..2 * settle)
+        .map(|i| {
+            let phase = core::f64::consts::TAU * freq * i as f64 / sample_rate;
+            let out = filt.next(ctx, to_sample(AMPLITUDE * phase.sin()), params.clone());
+            (i, T::sample_to_float(out.low).abs() as f64)
+        })
+        .filter(|(i, _)| *i >= settle)
+        .fold(0f64, |acc, (_, smp)| acc.max(smp));
+    20.0 * (peak / AMPLITUDE).log10()
+}
+
+fn check_response<T: DspFormat>(
+    ctx: &T::Context,
+    params: FiltParams<T>,
+    to_sample: impl Fn(f64) -> T::Sample,
+    tolerance_db: f64,
+) {
+    let sample_rate = ctx.sample_rate();
+    for tone in TONES {
+        let freq = tone * cutoff_freq();
+        let expected = expected_gain_db(freq, sample_rate);
+        let measured = measured_gain_db(ctx, params.clone(), freq, &to_sample);
+        assert!(
+            (measured - expected).abs() < tolerance_db,
+            "{}Hz at {}Hz: {}dB, expected {}dB",
+            freq,
+            sample_rate,
+            measured,
+            expected
+        );
+    }
+}
+
+#[test]
+fn adjusted_contexts_match_new_float() {
+    let base = Context::<f64>::new(48000.0).with_silence_threshold(0.25);
+    let ctx = base.clone_with_adjusted_sr(44100).unwrap();
+    assert_eq!(ctx.sample_rate, Context::<f64>::new(44100.0).sample_rate);
+    assert_eq!(ctx.silence_threshold(), 0.25);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn adjusted_contexts_match_new_fixed() {
+    let base = ContextFxP::new_441();
+    let ctx = base.clone_with_adjusted_sr(48000).unwrap();
+    let expected = ContextFxP::new_480().sample_rate.frac_2pi4096_sr();
+    assert_eq!(ctx.sample_rate.frac_2pi4096_sr(), expected);
+    assert_ne!(base.sample_rate.frac_2pi4096_sr(), expected);
+    // Fixed point only supports a few sample rates
+    assert!(base.clone_with_adjusted_sr(96000).is_none());
+}
+
+#[test]
+fn low_pass_response_float() {
+    let base = Context::<f64>::default();
+    for sample_rate in SAMPLE_RATES {
+        let ctx = base.clone_with_adjusted_sr(sample_rate).unwrap();
+        check_response::<f64>(&ctx, (&params()).into(), |x| x, 0.1);
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn low_pass_response_fixed() {
+    let base = ContextFxP::default();
+    let contexts: Vec<_> =
+        SAMPLE_RATES.iter().filter_map(|sr| base.clone_with_adjusted_sr(*sr)).collect();
+    assert_eq!(contexts.len(), 2);
+    for ctx in contexts {
+        check_response(&ctx, params(), culsynth::SampleFxP::from_num, 0.5);
+    }
+}
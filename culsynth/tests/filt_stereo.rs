@@ -0,0 +1,129 @@
+//! Verify that the stereo filter processes each channel independently, and
+//! that with no cutoff offset it matches a mono filter exactly.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+#[cfg(feature = "fixed")]
+use culsynth::devices::Filt;
+use culsynth::devices::{Device, FiltParams, FiltStereo, FiltStereoParams, PanOutput, Resonance};
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP, SignedNoteFxP};
+
+const NUM_SAMPLES: usize = 4800;
+
+/// A sawtooth wave, with a period of 100 samples
+fn saw(i: usize) -> SampleFxP {
+    SampleFxP::from_num(((i % 100) as f32 / 50.) - 1.)
+}
+
+fn params(offset: SignedNoteFxP) -> FiltStereoParams<i16> {
+    FiltStereoParams {
+        filt: FiltParams {
+            cutoff: NoteFxP::lit("70"),
+            resonance: Resonance::new(ScalarFxP::lit("0.7")),
+        },
+        offset,
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn mono_input_is_identical_fixed() {
+    let ctx = ContextFxP::new_480();
+    let p = params(SignedNoteFxP::ZERO);
+    let mut stereo = FiltStereo::<i16>::new();
+    let mut mono = Filt::<i16>::new();
+    for i in 0..NUM_SAMPLES {
+        let input = PanOutput {
+            left: saw(i),
+            right: saw(i),
+        };
+        let out = stereo.next(&ctx, input, p.clone());
+        let expected = mono.next(&ctx, saw(i), p.filt.clone());
+        assert_eq!(out.left.low, out.right.low);
+        assert_eq!(out.left.band, out.right.band);
+        assert_eq!(out.left.high, out.right.high);
+        assert_eq!(out.left.low, expected.low);
+    }
+}
+
+#[test]
+fn mono_input_is_identical_float() {
+    let ctx = Context::new(48000f32);
+    let p = FiltStereoParams::<f32>::from(&params(SignedNoteFxP::ZERO));
+    let mut stereo = FiltStereo::<f32>::new();
+    for i in 0..NUM_SAMPLES {
+        let smp = saw(i).to_num();
+        let out = stereo.next(
+            &ctx,
+            PanOutput {
+                left: smp,
+                right: smp,
+            },
+            p.clone(),
+        );
+        assert_eq!(out.left.low.to_bits(), out.right.low.to_bits());
+        assert_eq!(out.left.band.to_bits(), out.right.band.to_bits());
+        assert_eq!(out.left.high.to_bits(), out.right.high.to_bits());
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn offset_splits_cutoffs() {
+    let ctx = ContextFxP::new_480();
+    let p = params(SignedNoteFxP::lit("12"));
+    let mut stereo = FiltStereo::<i16>::new();
+    let mut low = Filt::<i16>::new();
+    let mut high = Filt::<i16>::new();
+    let mut low_p = p.filt.clone();
+    low_p.cutoff = NoteFxP::lit("64");
+    let mut high_p = p.filt.clone();
+    high_p.cutoff = NoteFxP::lit("76");
+    for i in 0..NUM_SAMPLES {
+        // Feed the channels different signals to check the state is separate
+        let input = PanOutput {
+            left: saw(i),
+            right: saw(i + 50),
+        };
+        let out = stereo.next(&ctx, input, p.clone());
+        assert_eq!(out.left.low, low.next(&ctx, saw(i), low_p.clone()).low);
+        assert_eq!(
+            out.right.low,
+            high.next(&ctx, saw(i + 50), high_p.clone()).low
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn shared_coefficients_keep_state_separate() {
+    let ctx = ContextFxP::new_480();
+    let p = params(SignedNoteFxP::ZERO);
+    let mut stereo = FiltStereo::<i16>::new();
+    stereo.set_dc_correct(true);
+    let mut left = Filt::<i16>::new();
+    left.set_dc_correct(true);
+    let mut right = Filt::<i16>::new();
+    right.set_dc_correct(true);
+    for i in 0..NUM_SAMPLES {
+        // Sweep the cutoff so the coefficients change every sample
+        let mut p = p.clone();
+        p.filt.cutoff = NoteFxP::from_num(40 + (i % 80));
+        let input = PanOutput {
+            left: saw(i),
+            right: saw(i + 50),
+        };
+        let out = stereo.next(&ctx, input, p.clone());
+        let l = left.next(&ctx, saw(i), p.filt.clone());
+        let r = right.next(&ctx, saw(i + 50), p.filt.clone());
+        assert_eq!(
+            (out.left.low, out.left.band, out.left.high),
+            (l.low, l.band, l.high)
+        );
+        assert_eq!(
+            (out.right.low, out.right.band, out.right.high),
+            (r.low, r.band, r.high)
+        );
+    }
+}
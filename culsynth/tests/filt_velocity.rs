@@ -0,0 +1,62 @@
+//! Verify the filter's built-in velocity modulation of the cutoff frequency
+//! (see [ModFiltParams::vel_mod]), which is independent of the mod matrix.
+
+use culsynth::devices::{ModFiltInput, ModFiltParams};
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP};
+
+fn input(vel: ScalarFxP) -> ModFiltInput<i16> {
+    ModFiltInput {
+        signal: SampleFxP::ZERO,
+        env: ScalarFxP::ZERO,
+        vel,
+        kbd: NoteFxP::ZERO,
+    }
+}
+
+fn params(cutoff: NoteFxP, vel_mod: ScalarFxP) -> ModFiltParams<i16> {
+    ModFiltParams {
+        cutoff,
+        vel_mod,
+        ..Default::default()
+    }
+}
+
+fn cutoff(cutoff: NoteFxP, vel_mod: ScalarFxP, vel: ScalarFxP) -> (NoteFxP, f32) {
+    let p = params(cutoff, vel_mod);
+    let fixed = p.to_filt_params(&input(vel)).cutoff;
+    let p_float = ModFiltParams::<f32>::from(&p);
+    let input_float = ModFiltInput::<f32> {
+        signal: 0f32,
+        env: 0f32,
+        vel: vel.to_num(),
+        kbd: 0f32,
+    };
+    (fixed, p_float.to_filt_params(&input_float).cutoff)
+}
+
+#[test]
+fn no_velocity_mod_by_default() {
+    let c = NoteFxP::lit("60");
+    let (fixed, float) = cutoff(c, ScalarFxP::ZERO, ScalarFxP::MAX);
+    assert_eq!(fixed, c);
+    assert_eq!(float, 60f32);
+}
+
+#[test]
+fn velocity_raises_cutoff() {
+    // Half of full velocity at half depth is a quarter of the note range
+    let (fixed, float) = cutoff(
+        NoteFxP::lit("40"),
+        ScalarFxP::lit("0.5"),
+        ScalarFxP::lit("0.5"),
+    );
+    let expected = 40f32 + 128f32 / 4f32;
+    assert!((fixed.to_num::<f32>() - expected).abs() < 0.1, "{}", fixed);
+    assert!((float - expected).abs() < 0.1, "{}", float);
+}
+
+#[test]
+fn velocity_mod_saturates() {
+    let (fixed, _) = cutoff(NoteFxP::lit("100"), ScalarFxP::MAX, ScalarFxP::MAX);
+    assert_eq!(fixed, NoteFxP::MAX);
+}
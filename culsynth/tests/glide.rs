@@ -0,0 +1,81 @@
+//! Verify that a [Glide] moves smoothly to its target, or steps through each
+//! semitone when quantized.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Glide, GlideParams};
+#[cfg(feature = "fixed")]
+use culsynth::{EnvParamFxP, NoteFxP};
+
+/// Glide from C4 to C5 in 0.1 seconds at 48kHz
+const GLIDE_SAMPLES: usize = 4800;
+
+/// Glide an octave up, returning the output notes
+fn glide_float(quantize: bool) -> Vec<f64> {
+    let ctx = Context::new(48000f64);
+    let mut glide = Glide::<f64>::new();
+    let params = GlideParams::<f64> {
+        time: 0.1,
+        quantize,
+    };
+    let _ = glide.next(&ctx, 60., params.clone());
+    (0..2 * GLIDE_SAMPLES).map(|_| glide.next(&ctx, 72., params.clone())).collect()
+}
+
+/// Glide an octave up, returning the output notes
+#[cfg(feature = "fixed")]
+fn glide_fixed(quantize: bool) -> Vec<f64> {
+    let ctx = ContextFxP::new_480();
+    let mut glide = Glide::<i16>::new();
+    let params = GlideParams::<i16> {
+        time: EnvParamFxP::lit("0.1"),
+        quantize,
+    };
+    let _ = glide.next(&ctx, NoteFxP::lit("60"), params.clone());
+    (0..2 * GLIDE_SAMPLES)
+        .map(|_| glide.next(&ctx, NoteFxP::lit("72"), params.clone()).to_num())
+        .collect()
+}
+
+/// Check that a glide is monotonic and arrives at the target on time
+fn check_glide(notes: &[f64]) {
+    assert!(notes.windows(2).all(|w| w[0] <= w[1]));
+    assert!(notes[GLIDE_SAMPLES * 9 / 10] < 72.);
+    assert!(notes[GLIDE_SAMPLES + 100..].iter().all(|x| *x == 72.));
+}
+
+#[test]
+fn glide_smooth() {
+    #[cfg_attr(not(feature = "fixed"), allow(unused_mut))]
+    let mut runs = vec![glide_float(false)];
+    #[cfg(feature = "fixed")]
+    runs.push(glide_fixed(false));
+    for notes in runs {
+        check_glide(&notes);
+        // A continuous glide passes through fractional notes
+        assert!(notes.iter().any(|x| x.fract() != 0.));
+    }
+}
+
+#[test]
+fn glide_quantized() {
+    #[cfg_attr(not(feature = "fixed"), allow(unused_mut))]
+    let mut runs = vec![glide_float(true)];
+    #[cfg(feature = "fixed")]
+    runs.push(glide_fixed(true));
+    for notes in runs {
+        check_glide(&notes);
+        // Every output is a whole semitone, and every semitone is played
+        assert!(notes.iter().all(|x| x.fract() == 0.));
+        let mut steps: Vec<f64> = notes.clone();
+        steps.dedup();
+        assert_eq!(steps, (60..=72).map(|x| x as f64).collect::<Vec<_>>());
+        // ...for roughly the same amount of time each
+        let len = GLIDE_SAMPLES / 12;
+        for note in 61..72 {
+            let n = notes.iter().filter(|x| **x == note as f64).count();
+            assert!(n.abs_diff(len) < 10, "{}: {} samples", note, n);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Verify that LFO 1 can modulate the rate of LFO 2, and that routes back
+//! from LFO 2 to LFO 1 (which would be circular) are ignored.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{LfoOptions, LfoParams, LfoWave};
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSection, ModSectionParams, ModSrc};
+use culsynth::{DspFormat, IScalarFxP, LfoFreqFxP, ScalarFxP};
+
+const SAMPLE_RATE: usize = 48000;
+
+fn lfo_params(freq: LfoFreqFxP) -> LfoParams<i16> {
+    LfoParams {
+        freq,
+        depth: ScalarFxP::MAX,
+        opts: LfoOptions::new(LfoWave::Sine, true, false),
+        ..Default::default()
+    }
+}
+
+/// LFO 1 at 1Hz and LFO 2 at 64Hz
+fn params() -> ModSectionParams<i16> {
+    ModSectionParams {
+        velocity: ScalarFxP::ZERO,
+        aftertouch: ScalarFxP::ZERO,
+        modwheel: ScalarFxP::ZERO,
+        sidechain: ScalarFxP::ZERO,
+        env_filt: ScalarFxP::ZERO,
+        env_amp: ScalarFxP::ZERO,
+        env_filt_scaled: ScalarFxP::ZERO,
+        lfo1_params: lfo_params(LfoFreqFxP::lit("1")),
+        lfo2_params: lfo_params(LfoFreqFxP::lit("64")),
+        env1_params: Default::default(),
+        env2_params: Default::default(),
+    }
+}
+
+fn params_float() -> ModSectionParams<f32> {
+    let p = params();
+    ModSectionParams {
+        velocity: 0f32,
+        aftertouch: 0f32,
+        modwheel: 0f32,
+        sidechain: 0f32,
+        env_filt: 0f32,
+        env_amp: 0f32,
+        env_filt_scaled: 0f32,
+        lfo1_params: (&p.lfo1_params).into(),
+        lfo2_params: (&p.lfo2_params).into(),
+        env1_params: (&p.env1_params).into(),
+        env2_params: (&p.env2_params).into(),
+    }
+}
+
+/// A matrix with each of `routes` at a depth of 0.5
+fn matrix(routes: &[(ModSrc, ModDest)]) -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    for (slot, (src, dest)) in routes.iter().enumerate() {
+        matrix.rows[*src as usize].1[slot] = (*dest, IScalarFxP::lit("0.5"));
+    }
+    matrix
+}
+
+/// Run the modulation section for one second, counting the cycles of LFO 2
+/// while LFO 1 is positive and while it is negative
+fn cycles<T: DspFormat>(
+    ctx: &T::Context,
+    params: ModSectionParams<T>,
+    matrix: &ModMatrix<T>,
+) -> (usize, usize) {
+    let mut section = ModSection::<T>::new_with_seeds(1, 2);
+    let mut matrix = Some(matrix);
+    let mut last = 0f32;
+    let (mut positive, mut negative) = (0, 0);
+    for _ in 0..SAMPLE_RATE {
+        let m = section.next(ctx, true, params.clone(), matrix.take());
+        let lfo1 = T::sample_to_float(m.lfo1());
+        let lfo2 = T::sample_to_float(m.lfo2());
+        if last < 0f32 && lfo2 >= 0f32 {
+            if lfo1 >= 0f32 {
+                positive += 1;
+            } else {
+                negative += 1;
+            }
+        }
+        last = lfo2;
+    }
+    (positive, negative)
+}
+
+fn check_lfo2_rate_mod<T: DspFormat>(ctx: &T::Context, params: ModSectionParams<T>)
+where
+    for<'a> ModMatrix<T>: From<&'a ModMatrix<i16>>,
+{
+    // Without modulation, LFO 2 runs at 64Hz throughout
+    let (positive, negative) = cycles(ctx, params.clone(), &ModMatrix::default());
+    assert!(positive.abs_diff(32) <= 1, "{}", positive);
+    assert!(negative.abs_diff(32) <= 1, "{}", negative);
+    // Modulating by +/- 64Hz with depth 0.5 averages about 64 +/- 41Hz
+    // over each half cycle of LFO 1
+    let lfo1_to_lfo2 = matrix(&[(ModSrc::Lfo1, ModDest::Lfo2Rate)]);
+    let (positive, negative) = cycles(ctx, params.clone(), &(&lfo1_to_lfo2).into());
+    assert!(positive.abs_diff(52) <= 2, "{}", positive);
+    assert!(negative.abs_diff(12) <= 2, "{}", negative);
+    // Closing the loop back to LFO 1 is ignored
+    let circular = matrix(&[
+        (ModSrc::Lfo1, ModDest::Lfo2Rate),
+        (ModSrc::Lfo2, ModDest::Lfo1Rate),
+    ]);
+    assert!(lfo1_to_lfo2.validate().is_ok());
+    assert!(circular.validate().is_err());
+    assert_eq!(
+        cycles(ctx, params, &(&circular).into()),
+        (positive, negative)
+    );
+}
+
+#[test]
+fn lfo1_modulates_lfo2_rate() {
+    #[cfg(feature = "fixed")]
+    check_lfo2_rate_mod::<i16>(&ContextFxP::new_480(), params());
+    check_lfo2_rate_mod::<f32>(&Context::new(SAMPLE_RATE as f32), params_float());
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn lfo_self_modulation_is_ignored() {
+    assert!(ModSrc::Env1.can_modulate(ModDest::Lfo1Rate));
+    assert!(!ModSrc::Lfo1.can_modulate(ModDest::Lfo1Rate));
+    assert!(ModSrc::Lfo1.can_modulate(ModDest::Lfo2Rate));
+    assert!(!ModSrc::Lfo2.can_modulate(ModDest::Lfo2Rate));
+    assert!(!ModSrc::Lfo2.can_modulate(ModDest::Lfo1Rate));
+    let self_mod = matrix(&[(ModSrc::Lfo1, ModDest::Lfo1Rate)]);
+    assert!(self_mod.validate().is_err());
+    let ctx = ContextFxP::new_480();
+    assert_eq!(
+        cycles(&ctx, params(), &self_mod),
+        cycles(&ctx, params(), &ModMatrix::default())
+    );
+}
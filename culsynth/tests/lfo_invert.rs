@@ -0,0 +1,74 @@
+//! Verify that inverting an LFO flips its polarity at the source: a bipolar
+//! LFO is negated, while a unipolar LFO is reflected about its midpoint so
+//! that it stays in the range 0:1.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Lfo, LfoOptions, LfoParams, LfoWave};
+use culsynth::{DspFormat, EnvParamFxP, LfoFreqFxP, ScalarFxP};
+
+const SAMPLES: usize = 2000;
+const WAVES: [LfoWave; 4] = [
+    LfoWave::Sine,
+    LfoWave::Triangle,
+    LfoWave::Saw,
+    LfoWave::SampleHold,
+];
+
+fn params(wave: LfoWave, bipolar: bool, invert: bool) -> LfoParams<i16> {
+    LfoParams {
+        // Fast enough to cover several periods (and random samples)
+        freq: LfoFreqFxP::lit("100"),
+        depth: ScalarFxP::MAX,
+        opts: LfoOptions::new(wave, bipolar, false).with_invert(invert),
+        slew: EnvParamFxP::ZERO,
+    }
+}
+
+fn run<T: DspFormat>(ctx: &T::Context, params: LfoParams<T>) -> Vec<f32> {
+    let mut lfo = Lfo::<T>::new(0);
+    (0..SAMPLES)
+        .map(|_| T::sample_to_float(lfo.next(ctx, false, params.clone())))
+        .collect()
+}
+
+/// Check that for each waveform, the inverted output is `offset - normal`
+fn check_inverted<T: DspFormat>(
+    ctx: &T::Context,
+    bipolar: bool,
+    tolerance: f32,
+    convert: impl Fn(&LfoParams<i16>) -> LfoParams<T>,
+) {
+    let offset = if bipolar { 0f32 } else { 1f32 };
+    for wave in WAVES {
+        let normal = run::<T>(ctx, convert(&params(wave, bipolar, false)));
+        let inverted = run::<T>(ctx, convert(&params(wave, bipolar, true)));
+        for (x, y) in normal.iter().zip(inverted.iter()) {
+            assert!(
+                (offset - x - y).abs() < tolerance,
+                "{}: {} {}",
+                wave.to_str(),
+                x,
+                y
+            );
+            if !bipolar {
+                assert!(*y >= 0f32 && *y <= 1f32);
+            }
+        }
+    }
+}
+
+#[test]
+fn invert_bipolar() {
+    #[cfg(feature = "fixed")]
+    check_inverted(&ContextFxP::default(), true, 0.001, LfoParams::clone);
+    check_inverted::<f32>(&Context::default(), true, 0.001, |p| p.into());
+}
+
+#[test]
+fn invert_unipolar() {
+    #[cfg(feature = "fixed")]
+    check_inverted(&ContextFxP::default(), false, 0.001, LfoParams::clone);
+    check_inverted::<f32>(&Context::default(), false, 0.001, |p| p.into());
+}
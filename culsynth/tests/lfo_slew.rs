@@ -0,0 +1,67 @@
+//! Verify that the LFO slew control rounds off the edges of a square wave:
+//! with no slew the output jumps straight between -1 and 1, and as the slew
+//! time increases, the largest change between two samples gets smaller.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Lfo, LfoOptions, LfoParams, LfoWave};
+use culsynth::{DspFormat, EnvParamFxP, LfoFreqFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 48000;
+/// One second, or two full cycles at [FREQ]
+const SAMPLES: usize = 48000;
+const FREQ: LfoFreqFxP = LfoFreqFxP::lit("2");
+/// Slew times in seconds, all well below the half-period of the LFO
+const SLEWS: [&str; 4] = ["0", "0.005", "0.01", "0.02"];
+
+fn params(slew: EnvParamFxP) -> LfoParams<i16> {
+    LfoParams {
+        freq: FREQ,
+        depth: ScalarFxP::MAX,
+        opts: LfoOptions::new(LfoWave::Square, true, false),
+        slew,
+    }
+}
+
+/// Run a square LFO for [SAMPLES], returning the largest change between two
+/// consecutive samples and the peak output
+fn run<T: DspFormat>(ctx: &T::Context, params: LfoParams<T>) -> (f32, f32) {
+    let mut lfo = Lfo::<T>::new(0);
+    let out: Vec<f32> = (0..SAMPLES)
+        .map(|_| T::sample_to_float(lfo.next(ctx, false, params.clone())))
+        .collect();
+    let max_step = out.windows(2).map(|w| (w[1] - w[0]).abs()).fold(0f32, f32::max);
+    (max_step, out.iter().fold(0f32, |acc, x| acc.max(*x)))
+}
+
+fn check_slews(results: &[(f32, f32)]) {
+    // No slew is the raw square wave
+    assert!((results[0].0 - 2.0).abs() < 0.01, "{:?}", results[0]);
+    for pair in results.windows(2) {
+        assert!(pair[1].0 < pair[0].0 * 0.75, "{:?}", pair);
+    }
+    // The rounded pulse still settles at full depth between edges
+    for (_, peak) in results {
+        assert!(*peak > 0.99, "{}", peak);
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn square_slew_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let results: Vec<_> =
+        SLEWS.iter().map(|slew| run(&ctx, params(EnvParamFxP::lit(slew)))).collect();
+    check_slews(&results);
+}
+
+#[test]
+fn square_slew_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let results: Vec<_> = SLEWS
+        .iter()
+        .map(|slew| run::<f32>(&ctx, (&params(EnvParamFxP::lit(slew))).into()))
+        .collect();
+    check_slews(&results);
+}
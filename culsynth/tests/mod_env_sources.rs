@@ -0,0 +1,172 @@
+//! Verify that all four envelopes are available as modulation sources, and
+//! that the VCF and VCA envelopes modulate with their full range (their
+//! output from the previous sample), independent of where they are wired in
+//! the voice.  The VCF envelope is also available after it is scaled by the
+//! filter's envelope amount.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+#[cfg(feature = "fixed")]
+use culsynth::voice::modulation::{ModSection, ModSectionParams};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, EnvParamFxP, IScalarFxP, NoteFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+
+#[test]
+fn all_envelopes_are_sources() {
+    let sources: Vec<&str> = ModSrc::elements().iter().map(|src| src.to_str()).collect();
+    let envs = [
+        ModSrc::Env1,
+        ModSrc::Env2,
+        ModSrc::EnvFilt,
+        ModSrc::EnvAmp,
+        ModSrc::EnvFiltScaled,
+    ];
+    for env in envs {
+        assert!(sources.contains(&env.to_str()));
+        assert!(ModSrc::try_from(env.to_str()).is_ok());
+        assert!(!env.is_secondary() || env.to_str() == ModSrc::Env2.to_str());
+    }
+    assert_eq!(sources.len(), ModSrc::numel());
+    // The voice envelopes may even modulate their own parameters
+    assert!(ModSrc::EnvAmp.can_modulate(ModDest::EnvAmpA));
+    assert!(ModSrc::EnvFilt.can_modulate(ModDest::Lfo2Rate));
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn envelope_levels_modulate() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[ModSrc::EnvFilt as usize].1[0] = (ModDest::Osc1Sin, IScalarFxP::MAX);
+    matrix.rows[ModSrc::EnvAmp as usize].1[0] = (ModDest::Osc1Sq, IScalarFxP::MAX);
+    matrix.rows[ModSrc::EnvFiltScaled as usize].1[0] = (ModDest::Osc1Tri, IScalarFxP::MAX);
+    let params = ModSectionParams::<i16> {
+        velocity: ScalarFxP::ZERO,
+        aftertouch: ScalarFxP::ZERO,
+        modwheel: ScalarFxP::ZERO,
+        sidechain: ScalarFxP::ZERO,
+        env_filt: ScalarFxP::lit("0.5"),
+        env_amp: ScalarFxP::lit("0.25"),
+        env_filt_scaled: ScalarFxP::lit("0.125"),
+        lfo1_params: Default::default(),
+        lfo2_params: Default::default(),
+        env1_params: Default::default(),
+        env2_params: Default::default(),
+    };
+    let mut section = ModSection::<i16>::default();
+    let modulator = section.next(&ctx, true, params, Some(&matrix));
+    let (mut sin, mut sq, mut tri) = (ScalarFxP::ZERO, ScalarFxP::ZERO, ScalarFxP::ZERO);
+    modulator.modulate_scalar(&mut sin, ModDest::Osc1Sin);
+    modulator.modulate_scalar(&mut sq, ModDest::Osc1Sq);
+    modulator.modulate_scalar(&mut tri, ModDest::Osc1Tri);
+    assert!(
+        sin.dist(ScalarFxP::lit("0.5")) <= ScalarFxP::lit("0.001"),
+        "{}",
+        sin
+    );
+    assert!(
+        sq.dist(ScalarFxP::lit("0.25")) <= ScalarFxP::lit("0.001"),
+        "{}",
+        sq
+    );
+    assert!(
+        tri.dist(ScalarFxP::lit("0.125")) <= ScalarFxP::lit("0.001"),
+        "{}",
+        tri
+    );
+}
+
+fn voice_params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16>::default();
+    params.oscs_p.primary.saw = ScalarFxP::MAX;
+    params.ring_p.mix_a = ScalarFxP::MAX;
+    params.filt_p.cutoff = NoteFxP::lit("127");
+    params.filt_p.low_mix = ScalarFxP::MAX;
+    params.amp_env_p.attack = EnvParamFxP::lit("0.01");
+    params
+}
+
+/// Route `src` to the master gain at `depth`
+fn env_to_gain(src: ModSrc, depth: IScalarFxP) -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[src as usize].1[0] = (ModDest::MasterGain, depth);
+    matrix
+}
+
+/// Route the VCA envelope to the master gain at `depth`
+fn vca_to_gain(depth: IScalarFxP) -> ModMatrix<i16> {
+    env_to_gain(ModSrc::EnvAmp, depth)
+}
+
+/// The peak level of the left channel over the second half of one second of
+/// a held note
+fn run<T: DspFormat>(ctx: &T::Context, matrix: &ModMatrix<T>, params: VoiceParams<T>) -> f32 {
+    let mut voice = Voice::<T>::new();
+    let input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let ch_input = VoiceChannelInput::<T>::default();
+    let mut matrix = Some(matrix);
+    (0..SAMPLE_RATE)
+        .map(|_| voice.next(ctx, matrix.take(), &input, &ch_input, params.clone()))
+        .skip(SAMPLE_RATE as usize / 2)
+        .fold(0f32, |acc, out| acc.max(T::sample_to_float(out.left).abs()))
+}
+
+/// The VCA envelope sustains at full scale, so the full depth of -1
+/// attenuates the output by 12dB
+fn check_gain(modulated: f32, unmodulated: f32) {
+    check_gain_db(modulated, unmodulated, -12f32);
+}
+
+fn check_gain_db(modulated: f32, unmodulated: f32, db: f32) {
+    let expected = 10f32.powf(db / 20f32);
+    let ratio = modulated / unmodulated;
+    assert!((ratio - expected).abs() < 0.02, "{}", ratio);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn vca_env_source_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let modulated = run(&ctx, &vca_to_gain(IScalarFxP::NEG_ONE), voice_params());
+    let unmodulated = run(&ctx, &vca_to_gain(IScalarFxP::ZERO), voice_params());
+    check_gain(modulated, unmodulated);
+}
+
+#[test]
+fn vca_env_source_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let params = || (&voice_params()).into();
+    let modulated = run::<f32>(&ctx, &(&vca_to_gain(IScalarFxP::NEG_ONE)).into(), params());
+    let unmodulated = run::<f32>(&ctx, &(&vca_to_gain(IScalarFxP::ZERO)).into(), params());
+    check_gain(modulated, unmodulated);
+}
+
+/// The VCF envelope sustains at full scale, and with an envelope amount of
+/// one half the scaled envelope attenuates the output by only 6dB
+#[test]
+#[cfg(feature = "fixed")]
+fn vcf_env_scaled_source_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let mut params = voice_params();
+    params.filt_env_p.attack = EnvParamFxP::lit("0.01");
+    params.filt_p.env_mod = ScalarFxP::lit("0.5");
+    let depth = IScalarFxP::NEG_ONE;
+    let unmodulated = run(
+        &ctx,
+        &env_to_gain(ModSrc::EnvFilt, IScalarFxP::ZERO),
+        params.clone(),
+    );
+    let raw = run(&ctx, &env_to_gain(ModSrc::EnvFilt, depth), params.clone());
+    check_gain_db(raw, unmodulated, -12f32);
+    let scaled = run(&ctx, &env_to_gain(ModSrc::EnvFiltScaled, depth), params);
+    check_gain_db(scaled, unmodulated, -6f32);
+}
@@ -0,0 +1,64 @@
+//! Verify that the secondary modulation sources and destinations are
+//! consistent with the routes that are allowed, so that the destinations
+//! offered for each row of the matrix are exactly the ones it can use.
+
+use culsynth::voice::modulation::{ModDest, ModSrc};
+
+#[test]
+fn secondary_sources() {
+    let secondary: Vec<&str> = ModSrc::elements()
+        .iter()
+        .filter(|src| src.is_secondary())
+        .map(|src| src.to_str())
+        .collect();
+    assert_eq!(secondary, [ModSrc::Env2.to_str(), ModSrc::Lfo2.to_str()]);
+}
+
+#[test]
+fn secondary_destinations() {
+    let all: Vec<ModDest> = ModDest::elements().collect();
+    assert_eq!(all.len(), ModDest::numel());
+    let primary: Vec<ModDest> = ModDest::elements_secondary_if(true).collect();
+    assert!(primary.iter().all(|dest| !dest.is_secondary()));
+    // The secondary destinations are all of the others
+    let secondary = all.iter().filter(|dest| dest.is_secondary()).count();
+    assert_eq!(primary.len() + secondary, all.len());
+    assert!(ModDest::Lfo1Rate.is_secondary() && ModDest::Lfo2Rate.is_secondary());
+    // Destinations added after the secondary ones are still primary
+    assert!(!ModDest::MasterGain.is_secondary() && !ModDest::Pan.is_secondary());
+    assert!(primary.iter().any(|dest| *dest as u16 == ModDest::Pan as u16));
+}
+
+#[test]
+fn destination_numbering_is_stable() {
+    // Patches store destinations by number, so existing destinations must
+    // never be renumbered when new ones are added
+    assert_eq!(ModDest::EnvAmpR as u16, 33);
+    assert_eq!(ModDest::Env2R as u16, 39);
+    assert_eq!(ModDest::MasterGain as u16, 40);
+    assert_eq!(ModDest::Pan as u16, 41);
+    assert_eq!(ModDest::Osc1Morph as u16, 42);
+    assert_eq!(ModDest::Osc2Morph as u16, 43);
+    assert_eq!(ModDest::Lfo1Rate as u16, 44);
+    assert_eq!(ModDest::max() as u16, 44);
+}
+
+#[test]
+fn rows_offer_allowed_destinations() {
+    for src in ModSrc::elements() {
+        // The destinations the editor offers for this source's row...
+        let offered: Vec<u16> = ModDest::elements_secondary_if(src.is_secondary())
+            .filter(|dest| src.can_modulate(*dest))
+            .map(|dest| dest as u16)
+            .collect();
+        // ...are exactly the destinations this source may modulate
+        let allowed: Vec<u16> = ModDest::elements()
+            .filter(|dest| src.can_modulate(*dest))
+            .map(|dest| dest as u16)
+            .collect();
+        assert_eq!(offered, allowed, "{}", src.to_str());
+        if src.is_secondary() {
+            assert!(offered.iter().all(|dest| !ModDest::try_from(*dest).unwrap().is_secondary()));
+        }
+    }
+}
@@ -0,0 +1,93 @@
+//! Verify that the sidechain level given to a voice modulates it through the
+//! modulation matrix, and that it stops modulating once the level drops back
+//! to zero (e.g. when the sidechain input is disconnected).
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, EnvParamFxP, IScalarFxP, NoteFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+
+fn voice_params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16>::default();
+    params.oscs_p.primary.saw = ScalarFxP::MAX;
+    params.ring_p.mix_a = ScalarFxP::MAX;
+    params.filt_p.cutoff = NoteFxP::lit("127");
+    params.filt_p.low_mix = ScalarFxP::MAX;
+    params.amp_env_p.attack = EnvParamFxP::lit("0.01");
+    params
+}
+
+/// Route the sidechain to the master gain with a depth of -1
+fn sidechain_to_gain() -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[ModSrc::Sidechain as usize].1[0] = (ModDest::MasterGain, IScalarFxP::NEG_ONE);
+    matrix
+}
+
+/// The peak level of the left channel over each half second of a held note,
+/// with the sidechain at `levels.0` for the first half and `levels.1` for the
+/// second
+fn run<T: DspFormat>(
+    ctx: &T::Context,
+    matrix: &ModMatrix<T>,
+    params: VoiceParams<T>,
+    levels: (T::Scalar, T::Scalar),
+) -> (f32, f32) {
+    let mut voice = Voice::<T>::new();
+    let input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let mut matrix = Some(matrix);
+    let half = SAMPLE_RATE as usize / 2;
+    let mut peak = |sidechain: T::Scalar| {
+        let ch_input = VoiceChannelInput::<T> {
+            sidechain,
+            ..Default::default()
+        };
+        // Skip the attack of the note, and let the level settle
+        (0..half)
+            .map(|_| voice.next(ctx, matrix.take(), &input, &ch_input, params.clone()))
+            .skip(half / 2)
+            .fold(0f32, |acc, out| acc.max(T::sample_to_float(out.left).abs()))
+    };
+    let first = peak(levels.0);
+    (first, peak(levels.1))
+}
+
+/// A full sidechain level attenuates the output by 12dB, and the output
+/// returns to unity gain when the level drops to zero
+fn check_gain((modulated, released): (f32, f32)) {
+    let expected = 10f32.powf(-12f32 / 20f32);
+    let ratio = modulated / released;
+    assert!((ratio - expected).abs() < 0.02, "{}", ratio);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn sidechain_source_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let levels = (ScalarFxP::MAX, ScalarFxP::ZERO);
+    check_gain(run(&ctx, &sidechain_to_gain(), voice_params(), levels));
+    // Without the sidechain the route does nothing
+    let unmodulated = run(&ctx, &ModMatrix::default(), voice_params(), levels);
+    assert!(
+        (unmodulated.0 - unmodulated.1).abs() < 0.01,
+        "{:?}",
+        unmodulated
+    );
+}
+
+#[test]
+fn sidechain_source_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let matrix = (&sidechain_to_gain()).into();
+    let levels = (1f32, 0f32);
+    check_gain(run::<f32>(&ctx, &matrix, (&voice_params()).into(), levels));
+}
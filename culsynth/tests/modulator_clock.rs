@@ -0,0 +1,69 @@
+//! Verify that envelope stage transitions and LFO phase wraps happen at
+//! exactly the expected sample indices.
+
+mod common;
+
+use common::TestClock;
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Env, EnvParams, EnvStage};
+#[cfg(feature = "fixed")]
+use culsynth::devices::{Lfo, LfoOptions, LfoParams, LfoWave};
+#[cfg(feature = "fixed")]
+use culsynth::{DspFormatBase, LfoFreqFxP, ScalarFxP};
+
+/// Give up on anything taking longer than 10 seconds
+const LIMIT: usize = 480_000;
+
+#[test]
+fn env_stage_transitions() {
+    let params = EnvParams::<f64> {
+        attack: 0.01,
+        decay: 0.05,
+        sustain: 0.5,
+        release: 0.1,
+        ..Default::default()
+    };
+    let mut clock = TestClock::new(Context::new(48000f64), Env::<f64>::default());
+    clock.tick(true, &params);
+    assert!(clock.device().stage() == EnvStage::Attack);
+    // The envelope switches to decay on the sample after the attack crosses
+    // the threshold
+    let threshold = clock.run_until(LIMIT, true, &params, |_, out| *out > 0.98).unwrap();
+    let decay = clock
+        .run_until(LIMIT, true, &params, |env, _| {
+            env.stage() == EnvStage::Decay
+        })
+        .unwrap();
+    assert_eq!(decay, threshold + 1);
+    // ...and releases on the first sample without the gate
+    clock.advance(10_000 - clock.now(), true, &params);
+    let release = clock.run_until(1, false, &params, |env, _| env.stage() == EnvStage::Release);
+    assert_eq!(release, Some(10_000));
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn lfo_phase_wraps() {
+    let params = LfoParams::<i16> {
+        freq: LfoFreqFxP::lit("1.5"),
+        depth: ScalarFxP::MAX,
+        opts: LfoOptions::new(LfoWave::Saw, true, false),
+        ..Default::default()
+    };
+    let mut clock = TestClock::new(ContextFxP::new_480(), Lfo::<i16>::new(0));
+    clock.tick(false, &params);
+    // The fixed point phase increment is exact, so the phase wraps from pi
+    // to -pi after exactly ceil(pi / increment) increments
+    let pi = <i16 as DspFormatBase>::Phase::PI.to_bits() as i64;
+    let step = clock.device().phase().to_bits() as i64;
+    let expected = (pi + step - 1) / step - 1;
+    let wrap = clock.run_until(LIMIT, false, &params, |lfo, _| lfo.phase() < 0).unwrap();
+    assert_eq!(wrap as i64, expected);
+    let tau = <i16 as DspFormatBase>::Phase::TAU.to_bits() as i64;
+    assert_eq!(
+        clock.device().phase().to_bits() as i64,
+        (expected + 1) * step - tau
+    );
+}
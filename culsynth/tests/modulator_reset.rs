@@ -0,0 +1,97 @@
+//! Verify that a hard reset gives bit-identical transients from the LFOs and
+//! envelopes on every note, no matter what state they were left in by the
+//! previous note, while a soft reset continues from where the last note left
+//! off.
+#![cfg(feature = "fixed")]
+
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Env, EnvParams, Lfo, LfoOptions, LfoParams, LfoWave, ResetMode};
+use culsynth::{EnvParamFxP, LfoFreqFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+/// The length of each note
+const NOTE_LEN: usize = 10_000;
+/// The gaps between notes, short enough that the envelope is still releasing
+const GAPS: [usize; 3] = [1000, 2345, 4321];
+
+/// Play a note for each entry in [GAPS] (separated by that many samples),
+/// returning the transient from each note
+fn run_notes<D: Device<i16, Input = bool>>(
+    ctx: &ContextFxP,
+    dev: &mut D,
+    params: &D::Params,
+) -> Vec<Vec<D::Output>>
+where
+    D::Params: Clone,
+{
+    GAPS.iter()
+        .map(|gap| {
+            let transient = (0..NOTE_LEN).map(|_| dev.next(ctx, true, params.clone())).collect();
+            for _ in 0..*gap {
+                let _ = dev.next(ctx, false, params.clone());
+            }
+            transient
+        })
+        .collect()
+}
+
+fn lfo_params(reset: ResetMode) -> LfoParams<i16> {
+    LfoParams {
+        freq: LfoFreqFxP::lit("7.5"),
+        depth: ScalarFxP::MAX,
+        opts: LfoOptions::new_with_reset(LfoWave::SampleGlide, true, reset),
+        ..Default::default()
+    }
+}
+
+fn env_params(reset: ResetMode) -> EnvParams<i16> {
+    EnvParams {
+        attack: EnvParamFxP::lit("0.05"),
+        attack_peak: ScalarFxP::MAX,
+        decay: EnvParamFxP::lit("0.1"),
+        sustain: ScalarFxP::lit("0.5"),
+        release: EnvParamFxP::lit("1"),
+        reset,
+    }
+}
+
+#[test]
+fn lfo_hard_reset_is_repeatable() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let notes = run_notes(
+        &ctx,
+        &mut Lfo::<i16>::new(1234),
+        &lfo_params(ResetMode::Hard),
+    );
+    for note in &notes[1..] {
+        assert!(*note == notes[0]);
+    }
+    // A soft reset restarts the phase, but not the random sequence
+    let notes = run_notes(
+        &ctx,
+        &mut Lfo::<i16>::new(1234),
+        &lfo_params(ResetMode::Soft),
+    );
+    assert!(notes[1] != notes[0]);
+}
+
+#[test]
+fn env_hard_reset_is_repeatable() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    // Start from a fresh envelope to check against its very first note
+    let notes = run_notes(
+        &ctx,
+        &mut Env::<i16>::default(),
+        &env_params(ResetMode::Hard),
+    );
+    for note in &notes[1..] {
+        assert!(*note == notes[0]);
+    }
+    // A soft reset attacks from the level the release left off at
+    let notes = run_notes(
+        &ctx,
+        &mut Env::<i16>::default(),
+        &env_params(ResetMode::Soft),
+    );
+    assert!(notes[1] != notes[0]);
+}
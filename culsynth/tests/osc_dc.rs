@@ -0,0 +1,83 @@
+//! Verify that none of the oscillator waveforms carry a DC offset.
+//!
+//! Each test runs an oscillator for exactly 100 full cycles and checks that
+//! the mean of each waveform is zero, within a tolerance.  None of the
+//! waveforms are intentionally DC-biased.
+//!
+//! The square and sawtooth waves are not band-limited, so the mean of their
+//! samples depends on where the samples fall relative to the discontinuity.
+//! To measure the waveforms themselves, each cycle is exactly
+//! [SAMPLES_PER_CYCLE] samples long and starts half a sample after the
+//! discontinuity, so the samples are placed symmetrically within the cycle.
+//! The phase is reset at the start of every cycle so that rounding in the
+//! phase accumulator can't slowly shift the samples.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Osc, OscParams};
+use culsynth::Float;
+#[cfg(feature = "fixed")]
+use culsynth::{NoteFxP, ScalarFxP};
+
+/// MIDI note 69 is A440
+const NOTE: u8 = 69;
+const CYCLES: usize = 100;
+const SAMPLES_PER_CYCLE: usize = 100;
+
+const WAVES: [&str; 4] = ["sin", "sq", "tri", "saw"];
+
+#[test]
+#[cfg(feature = "fixed")]
+fn osc_fixed_dc_free() {
+    let tolerance = ScalarFxP::from_bits(16).to_num::<f64>();
+    // The fixed point engine only supports fixed sample rates, so tune the
+    // oscillator to 441Hz instead to get a whole number of samples per cycle
+    let sample_rate = 44100;
+    let freq = (sample_rate / SAMPLES_PER_CYCLE as u32) as f64;
+    let note = NoteFxP::from_num(NOTE as f64 + 12. * (freq / 440.).log2());
+    let ctx = ContextFxP::maybe_create(sample_rate).unwrap();
+    let mut osc = Osc::<i16>::new();
+    osc.set_initial_phase(ScalarFxP::from_num(0.5 / SAMPLES_PER_CYCLE as f64));
+    let n = CYCLES * SAMPLES_PER_CYCLE;
+    let mut sums = [0f64; 4];
+    for i in 0..n {
+        if i % SAMPLES_PER_CYCLE == 0 {
+            osc.reset_phase();
+        }
+        let out = osc.next(&ctx, note, OscParams::default());
+        for (sum, smp) in sums.iter_mut().zip([out.sin, out.sq, out.tri, out.saw]) {
+            *sum += smp.to_num::<f64>();
+        }
+    }
+    for (name, sum) in WAVES.iter().zip(sums) {
+        let mean = sum / n as f64;
+        assert!(mean.abs() <= tolerance, "{} has DC offset {}", name, mean);
+    }
+}
+
+#[test]
+fn osc_float_dc_free() {
+    let tolerance = 1e-6;
+    // Pick the sample rate from the oscillator's own frequency, as the
+    // approximation used without `libm` isn't exactly 440Hz
+    let sample_rate = SAMPLES_PER_CYCLE as f32 * f32::midi_to_freq(NOTE as f32);
+    let ctx = Context::<f32>::new(sample_rate);
+    let mut osc = Osc::<f32>::new();
+    osc.set_initial_phase(0.5 / SAMPLES_PER_CYCLE as f32);
+    let n = CYCLES * SAMPLES_PER_CYCLE;
+    let mut sums = [0f64; 4];
+    for i in 0..n {
+        if i % SAMPLES_PER_CYCLE == 0 {
+            osc.reset_phase();
+        }
+        let out = osc.next(&ctx, NOTE as f32, OscParams::default());
+        for (sum, smp) in sums.iter_mut().zip([out.sin, out.sq, out.tri, out.saw]) {
+            *sum += smp as f64;
+        }
+    }
+    for (name, sum) in WAVES.iter().zip(sums) {
+        let mean = sum / n as f64;
+        assert!(mean.abs() <= tolerance, "{} has DC offset {}", name, mean);
+    }
+}
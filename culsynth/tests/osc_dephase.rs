@@ -0,0 +1,125 @@
+//! Verify that voices given different phase offsets start their notes at
+//! different points in the oscillator cycle.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Osc, OscParams};
+use culsynth::voice::{OscPhaseOffset, Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, ScalarFxP};
+
+const NUM_VOICES: usize = 4;
+
+#[test]
+fn offsets_spread_evenly() {
+    for i in 0..NUM_VOICES {
+        let mut offset = OscPhaseOffset::new(i, NUM_VOICES, 0);
+        let expected = i as f32 / NUM_VOICES as f32;
+        assert_eq!(offset.offset::<i16>(false), ScalarFxP::from_num(expected));
+        assert_eq!(offset.offset::<f32>(false), expected);
+    }
+}
+
+#[test]
+fn offsets_randomize() {
+    let offsets: Vec<f32> = (0..NUM_VOICES)
+        .map(|i| OscPhaseOffset::new(i, NUM_VOICES, i as u64).offset::<f32>(true))
+        .collect();
+    for (i, x) in offsets.iter().enumerate() {
+        assert!((0f32..1f32).contains(x), "{}", x);
+        assert!(!offsets[..i].contains(x), "{:?}", offsets);
+    }
+    // Each call gives a new offset
+    let mut offset = OscPhaseOffset::new(0, NUM_VOICES, 0);
+    assert!(offset.offset::<i16>(true) != offset.offset::<i16>(true));
+}
+
+fn check_osc_initial_phase<T: DspFormat>(ctx: &T::Context) {
+    let mut osc = Osc::<T>::new();
+    // A quarter of a cycle in, the sine is at its peak
+    osc.set_initial_phase(T::Scalar::one().divide_by_two().divide_by_two());
+    osc.reset_phase();
+    let out = osc.next(ctx, T::default_note(), OscParams::default());
+    assert!((T::sample_to_float(out.sin) - 1f32).abs() < 0.01);
+    // ...and after three quarters it is at its trough
+    osc.set_initial_phase(T::scalar_from_sample(T::sample_from_fixed(
+        culsynth::IScalarFxP::lit("0.75"),
+    )));
+    osc.reset_phase();
+    let out = osc.next(ctx, T::default_note(), OscParams::default());
+    assert!((T::sample_to_float(out.sin) + 1f32).abs() < 0.01);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn osc_initial_phase_fixed() {
+    check_osc_initial_phase::<i16>(&ContextFxP::new_480());
+}
+
+#[test]
+fn osc_initial_phase_float() {
+    check_osc_initial_phase::<f32>(&Context::new(48000f32));
+}
+
+/// Start a note on voice `index`, returning the first sample
+fn first_sample<T: DspFormat>(
+    ctx: &T::Context,
+    index: usize,
+    dephase: bool,
+    params: VoiceParams<T>,
+) -> f32 {
+    let mut voice = Voice::<T>::new();
+    let mut offset = OscPhaseOffset::new(index, NUM_VOICES, 0);
+    if dephase {
+        voice.set_initial_phase(offset.offset::<T>(false));
+    }
+    let input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let out = voice.next(
+        ctx,
+        Some(&Default::default()),
+        &input,
+        &VoiceChannelInput::default(),
+        params,
+    );
+    T::sample_to_float(out.left)
+}
+
+fn check_voices<T: DspFormat>(ctx: &T::Context, params: impl Fn() -> VoiceParams<T>) {
+    // Without de-phasing, every voice starts identically...
+    let a = first_sample::<T>(ctx, 0, false, params());
+    let b = first_sample::<T>(ctx, 1, false, params());
+    assert_eq!(a, b);
+    // ...but with it, each starts at a different point in the cycle
+    let outputs: Vec<f32> =
+        (0..NUM_VOICES).map(|i| first_sample::<T>(ctx, i, true, params())).collect();
+    for (i, x) in outputs.iter().enumerate() {
+        assert!(!outputs[..i].contains(x), "{:?}", outputs);
+    }
+}
+
+fn voice_params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16> {
+        raw_osc: true,
+        phase_reset: true,
+        ..Default::default()
+    };
+    params.oscs_p.primary.saw = ScalarFxP::MAX;
+    params.ring_p.mix_a = ScalarFxP::MAX;
+    params
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn voices_fixed() {
+    check_voices::<i16>(&ContextFxP::new_480(), voice_params);
+}
+
+#[test]
+fn voices_float() {
+    check_voices::<f32>(&Context::new(48000f32), || (&voice_params()).into());
+}
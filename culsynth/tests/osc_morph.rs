@@ -0,0 +1,128 @@
+//! Verify the triangle to sawtooth morph of the oscillator's triangle output.
+//!
+//! At either end of its range the morphed wave should match the existing
+//! triangle and sawtooth outputs, and in between it should follow the naive
+//! (non band-limited) skewed triangle, apart from the PolyBLAMP corrections
+//! near its corners.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Osc, OscParams};
+#[cfg(feature = "fixed")]
+use culsynth::{NoteFxP, SampleFxP, ScalarFxP};
+
+/// MIDI note 69 is A440
+const NOTE: u8 = 69;
+const FREQ: f32 = 440.0;
+const SAMPLE_RATE: u32 = 44100;
+const NUM_SAMPLES: usize = 10_000;
+
+/// Run an oscillator with the given morph, returning (saw, tri) for each sample
+#[cfg(feature = "fixed")]
+fn run_fixed(morph: ScalarFxP) -> Vec<(f32, f32)> {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let mut osc = Osc::<i16>::new();
+    let params = OscParams {
+        morph,
+        ..Default::default()
+    };
+    (0..NUM_SAMPLES)
+        .map(|_| {
+            let out = osc.next(&ctx, NoteFxP::from_num(NOTE), params.clone());
+            (out.saw.to_num(), out.tri.to_num())
+        })
+        .collect()
+}
+
+/// Run an oscillator with the given morph, returning (saw, tri) for each sample
+fn run_float(morph: f32) -> Vec<(f32, f32)> {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let mut osc = Osc::<f32>::new();
+    let params = OscParams {
+        morph,
+        ..Default::default()
+    };
+    (0..NUM_SAMPLES)
+        .map(|_| {
+            let out = osc.next(&ctx, NOTE as f32, params.clone());
+            (out.saw, out.tri)
+        })
+        .collect()
+}
+
+/// The naive morphed triangle, calculated from the sawtooth output
+fn naive_morph(saw: f32, morph: f32) -> f32 {
+    let x = 2f32 * saw;
+    let p = 1f32 + morph;
+    if x > p {
+        (2f32 - x) / (2f32 - p)
+    } else if x < -p {
+        (-2f32 - x) / (2f32 - p)
+    } else {
+        x / p
+    }
+}
+
+/// The largest PolyBLAMP correction for the given morph: 1/6 of the change
+/// in slope per sample at each corner
+fn max_correction(morph: f32) -> f32 {
+    let dx = 4f32 * FREQ / SAMPLE_RATE as f32;
+    let p = 1f32 + morph;
+    2f32 * dx / (p * (2f32 - p)) / 6f32
+}
+
+fn check_morph(samples: &[(f32, f32)], morph: f32, tolerance: f32) {
+    let bound = max_correction(morph) + tolerance;
+    let mut max_diff = 0f32;
+    for (saw, tri) in samples {
+        assert!(tri.abs() <= 1f32, "triangle out of range: {}", tri);
+        let diff = (tri - naive_morph(*saw, morph)).abs();
+        assert!(diff <= bound, "triangle {} differs by {}", tri, diff);
+        max_diff = max_diff.max(diff);
+    }
+    // The corners should have been smoothed
+    assert!(max_diff > max_correction(morph) / 2f32);
+    let mean = samples.iter().map(|(_, tri)| tri).sum::<f32>() / samples.len() as f32;
+    assert!(mean.abs() < 0.01, "morphed triangle has DC offset {}", mean);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn osc_morph_endpoints_fixed() {
+    // The sawtooth loses its least significant bit, and the triangle is
+    // calculated with different rounding, so allow a few LSBs of error
+    let tolerance = 4f32 * SampleFxP::DELTA.to_num::<f32>();
+    for (saw, tri) in run_fixed(ScalarFxP::ZERO) {
+        assert!((tri - naive_morph(saw, 0f32)).abs() <= tolerance);
+    }
+    for (saw, tri) in run_fixed(ScalarFxP::MAX) {
+        assert_eq!(tri, saw);
+    }
+}
+
+#[test]
+fn osc_morph_endpoints_float() {
+    for (saw, tri) in run_float(0f32) {
+        assert!((tri - naive_morph(saw, 0f32)).abs() < 1e-5);
+    }
+    for (saw, tri) in run_float(1f32) {
+        assert_eq!(tri, saw);
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn osc_morph_fixed() {
+    for morph in ["0.25", "0.5", "0.75"] {
+        let morph = ScalarFxP::lit(morph);
+        check_morph(&run_fixed(morph), morph.to_num(), 0.002);
+    }
+}
+
+#[test]
+fn osc_morph_float() {
+    for morph in [0.25f32, 0.5f32, 0.75f32] {
+        check_morph(&run_float(morph), morph, 1e-5);
+    }
+}
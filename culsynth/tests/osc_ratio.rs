@@ -0,0 +1,91 @@
+//! Verify that locking the secondary oscillator to a ratio of the primary
+//! keeps it at that ratio regardless of the note played or its own tuning.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, OscParams, OscRatio, SyncedOscs, SyncedOscsParams};
+#[cfg(feature = "fixed")]
+use culsynth::NoteFxP;
+use culsynth::{DspFormat, SignedNoteFxP};
+
+/// One second at 48kHz
+const SAMPLES: usize = 48000;
+const NOTES: [u8; 4] = [40, 57, 69, 81];
+
+fn params(ratio: OscRatio) -> SyncedOscsParams<i16> {
+    SyncedOscsParams {
+        primary: OscParams::default(),
+        // The secondary oscillator's own tuning is ignored when locked
+        secondary: OscParams {
+            tune: SignedNoteFxP::lit("7"),
+            ..Default::default()
+        },
+        sync: false,
+        ratio,
+    }
+}
+
+/// Count the rising zero crossings of a signal, interpolated to a fraction
+/// of a sample, returning the average frequency between the first and last
+fn frequency(signal: &[f32]) -> f32 {
+    let crossings: Vec<f32> = signal
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| w[0] < 0f32 && w[1] >= 0f32)
+        .map(|(i, w)| i as f32 + w[0] / (w[0] - w[1]))
+        .collect();
+    let span = crossings.last().unwrap() - crossings.first().unwrap();
+    (crossings.len() - 1) as f32 * SAMPLES as f32 / span
+}
+
+/// The ratio of the frequency of the secondary to the primary oscillator
+fn measure_ratio<T: DspFormat>(
+    ctx: &T::Context,
+    note: T::Note,
+    params: SyncedOscsParams<T>,
+) -> f32 {
+    let mut oscs = SyncedOscs::<T>::new();
+    let (primary, secondary): (Vec<f32>, Vec<f32>) = (0..SAMPLES)
+        .map(|_| {
+            let out = oscs.next(ctx, note, params.clone());
+            (
+                T::sample_to_float(out.primary.sin),
+                T::sample_to_float(out.secondary.sin),
+            )
+        })
+        .unzip();
+    frequency(&secondary) / frequency(&primary)
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn ratio_locked_fixed() {
+    let ctx = ContextFxP::new_480();
+    for note in NOTES {
+        let note = NoteFxP::from_num(note);
+        let double = measure_ratio::<i16>(&ctx, note, params(OscRatio::Double));
+        assert!((double - 2f32).abs() < 0.002, "{} {}", note, double);
+        let seven_halves = measure_ratio::<i16>(&ctx, note, params(OscRatio::SevenHalves));
+        assert!(
+            (seven_halves - 3.5f32).abs() < 0.004,
+            "{} {}",
+            note,
+            seven_halves
+        );
+        // A fifth above when free
+        let free = measure_ratio::<i16>(&ctx, note, params(OscRatio::Free));
+        assert!((free - 1.4983f32).abs() < 0.002, "{} {}", note, free);
+    }
+}
+
+#[test]
+fn ratio_locked_float() {
+    let ctx = Context::<f32>::new(48000f32);
+    for note in NOTES {
+        let double = measure_ratio::<f32>(&ctx, note as f32, (&params(OscRatio::Double)).into());
+        assert!((double - 2f32).abs() < 0.001, "{} {}", note, double);
+        let half = measure_ratio::<f32>(&ctx, note as f32, (&params(OscRatio::Half)).into());
+        assert!((half - 0.5f32).abs() < 0.001, "{} {}", note, half);
+    }
+}
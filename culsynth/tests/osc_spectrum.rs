@@ -0,0 +1,89 @@
+//! Verify the frequency content of the oscillators: the fundamental is at
+//! the expected frequency, and the sine wave is free of harmonics.
+
+mod common;
+
+use common::spectrum::Spectrum;
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Osc, OscParams};
+#[cfg(feature = "fixed")]
+use culsynth::NoteFxP;
+
+/// MIDI note 69 is A440
+const NOTE: u8 = 69;
+const FREQ: f32 = 440.0;
+const SAMPLE_RATE: u32 = 48000;
+/// A little over a third of a second, for a resolution of about 3Hz
+const SAMPLES: usize = 16384;
+
+#[cfg(feature = "fixed")]
+fn run_fixed() -> [Vec<f32>; 4] {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let mut osc = Osc::<i16>::new();
+    let mut out: [Vec<f32>; 4] = Default::default();
+    for _ in 0..SAMPLES {
+        let smp = osc.next(&ctx, NoteFxP::from_num(NOTE), OscParams::default());
+        for (buf, x) in out.iter_mut().zip([smp.sin, smp.sq, smp.tri, smp.saw]) {
+            buf.push(x.to_num());
+        }
+    }
+    out
+}
+
+fn run_float() -> [Vec<f32>; 4] {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let mut osc = Osc::<f32>::new();
+    let mut out: [Vec<f32>; 4] = Default::default();
+    for _ in 0..SAMPLES {
+        let smp = osc.next(&ctx, NOTE as f32, OscParams::default());
+        for (buf, x) in out.iter_mut().zip([smp.sin, smp.sq, smp.tri, smp.saw]) {
+            buf.push(x);
+        }
+    }
+    out
+}
+
+/// The output of each oscillator in each number format
+fn runs() -> Vec<(&'static str, [Vec<f32>; 4])> {
+    #[cfg_attr(not(feature = "fixed"), allow(unused_mut))]
+    let mut runs = vec![("float", run_float())];
+    #[cfg(feature = "fixed")]
+    runs.push(("fixed", run_fixed()));
+    runs
+}
+
+#[test]
+fn osc_fundamentals() {
+    for (fmt, waves) in runs() {
+        for (name, wave) in ["sin", "sq", "tri", "saw"].iter().zip(waves) {
+            let peak = Spectrum::new(&wave, SAMPLE_RATE as f32).peak_freq();
+            let cents = 1200. * (peak / FREQ).log2();
+            assert!(
+                cents.abs() <= 1.,
+                "{} {}: fundamental at {}Hz is {} cents from {}Hz",
+                fmt,
+                name,
+                peak,
+                cents,
+                FREQ
+            );
+        }
+    }
+}
+
+#[test]
+fn osc_sine_purity() {
+    for (fmt, [sin, ..]) in runs() {
+        // Leave room for the window to spread the fundamental over a few bins
+        let level = Spectrum::new(&sin, SAMPLE_RATE as f32).max_db_above(1.5 * FREQ);
+        assert!(
+            level <= -60.,
+            "{} sin: partial above {}Hz at {}dB",
+            fmt,
+            1.5 * FREQ,
+            level
+        );
+    }
+}
@@ -0,0 +1,117 @@
+//! Verify that band-limiting the phase reset of a synced oscillator reduces
+//! aliasing across a sweep of high notes.
+//!
+//! The secondary oscillator is periodic at the frequency of the primary, so
+//! any energy that isn't near a harmonic of the primary is aliasing.  The
+//! sine output is used because it has no discontinuities other than those
+//! caused by sync.
+
+mod common;
+
+use common::spectrum::Spectrum;
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, OscParams, SyncedOscs, SyncedOscsParams};
+#[cfg(feature = "fixed")]
+use culsynth::NoteFxP;
+use culsynth::SignedNoteFxP;
+
+/// C6, which doesn't divide the sample rate
+const NOTE: u8 = 84;
+/// The frequency of [NOTE], in Hz
+const NOTE_FREQ: f32 = 1046.502;
+const SAMPLE_RATE: u32 = 48000;
+const SAMPLES: usize = 16384;
+/// Tuning of the secondary oscillator, in semitones above the primary.  These
+/// are all well away from integer frequency ratios, where the secondary would
+/// already be close to zero phase when it's reset.
+const SWEEP: [i16; 6] = [3, 5, 8, 10, 15, 17];
+/// Bins on either side of a harmonic that aren't counted as aliasing, to
+/// allow for the window spreading each harmonic over a few bins
+const GUARD_BINS: usize = 4;
+
+fn params(tune: i16) -> SyncedOscsParams<i16> {
+    SyncedOscsParams {
+        primary: OscParams::default(),
+        secondary: OscParams {
+            tune: SignedNoteFxP::from_num(tune),
+            ..Default::default()
+        },
+        sync: true,
+        ..Default::default()
+    }
+}
+
+/// The power of everything but the harmonics of the primary, in dB relative
+/// to the total power of the signal
+fn aliasing_db(signal: &[f32]) -> f32 {
+    let spectrum = Spectrum::new(signal, SAMPLE_RATE as f32);
+    // The fundamental, in bins
+    let fundamental = NOTE_FREQ * SAMPLES as f32 / SAMPLE_RATE as f32;
+    let is_harmonic = |bin: usize| {
+        let nearest = (bin as f32 / fundamental).round() * fundamental;
+        (bin as f32 - nearest).abs() <= GUARD_BINS as f32
+    };
+    let (mut total, mut alias) = (0f32, 0f32);
+    for bin in 1..spectrum.num_bins() {
+        let power = 10f32.powf(spectrum.db(bin) / 10.);
+        total += power;
+        if !is_harmonic(bin) {
+            alias += power;
+        }
+    }
+    10. * (alias / total).log10()
+}
+
+#[cfg(feature = "fixed")]
+fn run_fixed(tune: i16, sync_blep: bool) -> Vec<f32> {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let mut oscs = SyncedOscs::<i16>::new();
+    oscs.set_sync_blep(sync_blep);
+    (0..SAMPLES)
+        .map(|_| oscs.next(&ctx, NoteFxP::from_num(NOTE), params(tune)).secondary.sin.to_num())
+        .collect()
+}
+
+fn run_float(tune: i16, sync_blep: bool) -> Vec<f32> {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let mut oscs = SyncedOscs::<f32>::new();
+    oscs.set_sync_blep(sync_blep);
+    let params = SyncedOscsParams::<f32>::from(&params(tune));
+    (0..SAMPLES)
+        .map(|_| oscs.next(&ctx, NOTE as f32, params.clone()).secondary.sin)
+        .collect()
+}
+
+fn check_sweep(run: impl Fn(i16, bool) -> Vec<f32>, min_improvement_db: f32) {
+    for tune in SWEEP {
+        let naive = aliasing_db(&run(tune, false));
+        let blep = aliasing_db(&run(tune, true));
+        assert!(
+            naive - blep >= min_improvement_db,
+            "+{} semitones: {}dB naive, {}dB band-limited",
+            tune,
+            naive,
+            blep
+        );
+    }
+}
+
+#[test]
+fn sync_blep_on_by_default() {
+    #[cfg(feature = "fixed")]
+    assert!(SyncedOscs::<i16>::new().sync_blep());
+    assert!(SyncedOscs::<f32>::new().sync_blep());
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn sync_blep_reduces_aliasing_fixed() {
+    check_sweep(run_fixed, 10.);
+}
+
+#[test]
+fn sync_blep_reduces_aliasing_float() {
+    check_sweep(run_float, 10.);
+}
@@ -0,0 +1,65 @@
+//! Verify that switching an oscillator's waveform mid-note crossfades
+//! between the waveforms instead of jumping.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, MixOsc, MixOscParams};
+use culsynth::{DspFormat, NoteFxP, ScalarFxP};
+
+/// A low note (about 33Hz), so the waveforms change slowly
+const NOTE: NoteFxP = NoteFxP::lit("24");
+/// Switch from a sine to a square about 1/12 of a cycle in, where the sine
+/// is about 0.5 and the square is 1, far from the next edge of the square
+const SWITCH: usize = 122;
+const LEN: usize = 400;
+/// The largest change allowed between two samples.  The sine changes by at
+/// most about 0.005 per sample at this pitch.
+const MAX_STEP: f32 = 0.05;
+
+fn params(sine: bool) -> MixOscParams<i16> {
+    MixOscParams {
+        sin: if sine {
+            ScalarFxP::MAX
+        } else {
+            ScalarFxP::ZERO
+        },
+        sq: if sine {
+            ScalarFxP::ZERO
+        } else {
+            ScalarFxP::MAX
+        },
+        ..Default::default()
+    }
+}
+
+fn check_switch<T: DspFormat>(
+    ctx: &T::Context,
+    note: T::Note,
+    params: impl Fn(bool) -> MixOscParams<T>,
+) {
+    let mut osc = MixOsc::<T>::default();
+    let out: Vec<f32> = (0..LEN)
+        .map(|smp| T::sample_to_float(osc.next(ctx, note, params(smp < SWITCH))))
+        .collect();
+    assert!(out[SWITCH - 1] > 0.4 && out[SWITCH - 1] < 0.6);
+    for smp in 1..LEN {
+        let step = (out[smp] - out[smp - 1]).abs();
+        assert!(step < MAX_STEP, "sample {}: {}", smp, step);
+    }
+    // ...and the crossfade has finished
+    assert!(out[LEN - 1] > 0.99, "{}", out[LEN - 1]);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn switch_fixed() {
+    check_switch::<i16>(&ContextFxP::new_480(), NOTE, params);
+}
+
+#[test]
+fn switch_float() {
+    check_switch::<f32>(&Context::new(48000f32), NOTE.to_num(), |sine| {
+        (&params(sine)).into()
+    });
+}
@@ -0,0 +1,98 @@
+//! Verify that oscillators are reset to zero phase on exactly the sample
+//! that a gate opens.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Osc, OscParams, PhaseReset};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType};
+
+const LEN: usize = 256;
+/// The sample at which the gate opens
+const TRIGGER: usize = 128;
+
+fn check_osc_reset<T: DspFormat>(ctx: &T::Context) {
+    let note = T::default_note();
+    let mut reset = PhaseReset::<T>::new();
+    let mut osc = Osc::<T>::new();
+    let mut fresh = Osc::<T>::new();
+    for smp in 0..LEN {
+        if reset.next(ctx, smp >= TRIGGER, ()) {
+            assert_eq!(smp, TRIGGER);
+            osc.reset_phase();
+        }
+        assert_eq!(reset.triggered(), smp == TRIGGER);
+        let out = osc.next(ctx, note, OscParams::default());
+        if smp == TRIGGER - 1 {
+            assert!(T::sample_to_float(out.saw) != 0f32);
+        }
+        // From the reset on, the output matches a newly started oscillator
+        if smp >= TRIGGER {
+            let expected = fresh.next(ctx, note, OscParams::default());
+            assert!(
+                T::sample_to_float(out.saw) == T::sample_to_float(expected.saw),
+                "sample {}",
+                smp
+            );
+        }
+    }
+}
+
+fn check_voice_reset<T: DspFormat>(ctx: &T::Context, params: VoiceParams<T>) {
+    let mut voice = Voice::<T>::new();
+    let ch_input = VoiceChannelInput::<T>::default();
+    let mut matrix = Some(Default::default());
+    for smp in 0..LEN {
+        let input = VoiceInput::<T> {
+            note: T::default_note(),
+            velocity: T::Scalar::one(),
+            gate: smp >= TRIGGER,
+            ..Default::default()
+        };
+        let out = voice.next(
+            ctx,
+            matrix.take().as_ref(),
+            &input,
+            &ch_input,
+            params.clone(),
+        );
+        // The raw sawtooth is zero at zero phase, which is also where a new
+        // voice starts
+        let is_zero = T::sample_to_float(out.left) == 0f32;
+        assert_eq!(is_zero, smp == 0 || smp == TRIGGER, "sample {}", smp);
+    }
+}
+
+fn voice_params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16> {
+        raw_osc: true,
+        phase_reset: true,
+        ..Default::default()
+    };
+    params.oscs_p.primary.saw = culsynth::ScalarFxP::MAX;
+    params.ring_p.mix_a = culsynth::ScalarFxP::MAX;
+    params
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn osc_reset_fixed() {
+    check_osc_reset::<i16>(&ContextFxP::new_480());
+}
+
+#[test]
+fn osc_reset_float() {
+    check_osc_reset::<f32>(&Context::new(48000f32));
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn voice_reset_fixed() {
+    check_voice_reset::<i16>(&ContextFxP::new_480(), voice_params());
+}
+
+#[test]
+fn voice_reset_float() {
+    check_voice_reset::<f32>(&Context::new(48000f32), (&voice_params()).into());
+}
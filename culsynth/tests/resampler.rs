@@ -0,0 +1,87 @@
+//! Verify that the resampler holds each output of its oscillator for
+//! `sample_rate / target_rate` samples.
+
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::context::{Context, GenericContext};
+use culsynth::devices::{Device, Osc, OscOutput, OscParams, Resampler, ResamplerParams};
+use culsynth::DspFormat;
+#[cfg(feature = "fixed")]
+use culsynth::DspFormatBase;
+
+const SAMPLES: usize = 1000;
+
+fn samples<T: DspFormat>(out: OscOutput<T>) -> [f32; 4] {
+    [out.sin, out.sq, out.tri, out.saw].map(T::sample_to_float)
+}
+
+fn resampled<T: DspFormat>(ctx: &T::Context, target_rate: u16) -> Vec<[f32; 4]> {
+    let mut resampler = Resampler::<T>::new();
+    (0..SAMPLES)
+        .map(|_| {
+            let params = ResamplerParams {
+                osc: OscParams::default(),
+                target_rate,
+            };
+            samples(resampler.next(ctx, T::default_note(), params))
+        })
+        .collect()
+}
+
+fn check_resampler<T: DspFormat>(ctx: &T::Context) {
+    let sample_rate = ctx.sample_rate() as u16;
+    let mut osc = Osc::<T>::new();
+    let direct: Vec<[f32; 4]> = (0..SAMPLES)
+        .map(|_| samples(osc.next(ctx, T::default_note(), OscParams::default())))
+        .collect();
+    // At the full sample rate, the output is the oscillator's...
+    assert_eq!(resampled::<T>(ctx, sample_rate), direct);
+    // ...and a higher target rate can't add any samples
+    assert_eq!(resampled::<T>(ctx, u16::MAX), direct);
+    // At half the sample rate, every other sample is repeated
+    for (i, pair) in resampled::<T>(ctx, sample_rate / 2).chunks(2).enumerate() {
+        assert_eq!(pair[0], direct[2 * i]);
+        assert_eq!(pair[1], direct[2 * i]);
+    }
+    // At a sixth of the sample rate, each sample is held for six samples
+    for (i, run) in resampled::<T>(ctx, sample_rate / 6).chunks(6).enumerate() {
+        assert!(run.iter().all(|x| *x == direct[6 * i]));
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn resampler_fixed() {
+    check_resampler::<i16>(&ContextFxP::new_480());
+}
+
+#[test]
+fn resampler_float() {
+    check_resampler::<f32>(&Context::new(48000f32));
+}
+
+/// When the target rate doesn't divide the sample rate, the hold time
+/// alternates so that the average rate is correct
+#[test]
+#[cfg(feature = "fixed")]
+fn uneven_hold() {
+    let ctx = ContextFxP::new_441();
+    let mut resampler = Resampler::<i16>::new();
+    let params = ResamplerParams {
+        osc: OscParams::default(),
+        target_rate: 8000,
+    };
+    let out: Vec<[f32; 4]> = (0..44100)
+        .map(|_| samples(resampler.next(&ctx, i16::default_note(), params.clone())))
+        .collect();
+    let mut runs = vec![1];
+    for pair in out.windows(2) {
+        if pair[0] == pair[1] {
+            *runs.last_mut().unwrap() += 1;
+        } else {
+            runs.push(1);
+        }
+    }
+    assert!(runs.iter().all(|len| (5..=6).contains(len)), "{:?}", runs);
+    assert_eq!(runs.len(), 8000);
+}
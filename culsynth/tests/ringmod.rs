@@ -0,0 +1,135 @@
+//! Verify that ring modulating two sines produces the sum and difference
+//! frequencies (and not the originals), and that the fixed point multiply
+//! saturates instead of overflowing.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, RingMod, RingModInput, RingModParams};
+#[cfg(feature = "fixed")]
+use culsynth::{SampleFxP, ScalarFxP};
+
+const SAMPLE_RATE: usize = 48000;
+const FREQ_A: f64 = 440.;
+const FREQ_B: f64 = 100.;
+
+fn sine(freq: f64, i: usize) -> f64 {
+    (core::f64::consts::TAU * freq * i as f64 / SAMPLE_RATE as f64).sin()
+}
+
+/// The magnitude of `freq` in `signal`, normalized so that a sine with
+/// amplitude one has magnitude one.  `signal` is exactly one second long,
+/// so every integer frequency falls in its own bin without any leakage.
+fn magnitude(signal: &[f64], freq: f64) -> f64 {
+    let (re, im) = signal.iter().enumerate().fold((0., 0.), |(re, im), (i, x)| {
+        let phase = core::f64::consts::TAU * freq * i as f64 / SAMPLE_RATE as f64;
+        (re + x * phase.cos(), im + x * phase.sin())
+    });
+    2. * (re * re + im * im).sqrt() / signal.len() as f64
+}
+
+/// Check that `signal` (the product of the two sines) contains half of each
+/// of the sum and difference frequencies, and neither of the originals
+fn check_spectrum(signal: &[f64]) {
+    assert_eq!(signal.len(), SAMPLE_RATE);
+    for freq in [FREQ_A - FREQ_B, FREQ_A + FREQ_B] {
+        let mag = magnitude(signal, freq);
+        assert!((mag - 0.5).abs() < 0.01, "{} Hz: {}", freq, mag);
+    }
+    for freq in [FREQ_A, FREQ_B] {
+        let mag = magnitude(signal, freq);
+        assert!(mag < 0.01, "{} Hz: {}", freq, mag);
+    }
+}
+
+#[test]
+fn ringmod_sum_difference_float() {
+    let ctx = Context::new(SAMPLE_RATE as f64);
+    let mut ringmod = RingMod::<f64>::default();
+    let params = RingModParams::<f64> {
+        mix_a: 0.,
+        mix_b: 0.,
+        mix_mod: 1.,
+    };
+    let out: Vec<f64> = (0..SAMPLE_RATE)
+        .map(|i| {
+            let input = RingModInput {
+                signal_a: sine(FREQ_A, i),
+                signal_b: sine(FREQ_B, i),
+            };
+            ringmod.next(&ctx, input, params.clone())
+        })
+        .collect();
+    check_spectrum(&out);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn ringmod_sum_difference_fixed() {
+    let ctx = ContextFxP::new_480();
+    let mut ringmod = RingMod::<i16>::default();
+    let params = RingModParams::<i16> {
+        mix_a: ScalarFxP::ZERO,
+        mix_b: ScalarFxP::ZERO,
+        mix_mod: ScalarFxP::MAX,
+    };
+    let out: Vec<f64> = (0..SAMPLE_RATE)
+        .map(|i| {
+            let input = RingModInput {
+                signal_a: SampleFxP::from_num(sine(FREQ_A, i)),
+                signal_b: SampleFxP::from_num(sine(FREQ_B, i)),
+            };
+            ringmod.next(&ctx, input, params.clone()).to_num()
+        })
+        .collect();
+    check_spectrum(&out);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn ringmod_fixed_saturates() {
+    let ctx = ContextFxP::new_480();
+    let mut ringmod = RingMod::<i16>::default();
+    let params = RingModParams::<i16> {
+        mix_a: ScalarFxP::ZERO,
+        mix_b: ScalarFxP::ZERO,
+        mix_mod: ScalarFxP::MAX,
+    };
+    let mut ring = |a, b| {
+        let input = RingModInput {
+            signal_a: a,
+            signal_b: b,
+        };
+        ringmod.next(&ctx, input, params.clone())
+    };
+    // Full scale squared is well beyond the range of a sample, so this should
+    // saturate (and then be scaled down very slightly by the mixer)
+    let limit = SampleFxP::lit("7.99");
+    assert!(ring(SampleFxP::MAX, SampleFxP::MAX) > limit);
+    assert!(ring(SampleFxP::MIN, SampleFxP::MAX) < -limit);
+    assert!(ring(SampleFxP::MIN, SampleFxP::MIN) > limit);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn ringmod_gain_staging() {
+    let ctx = ContextFxP::new_480();
+    let mut ringmod = RingMod::<i16>::default();
+    let mut ring = |a: f32, b: f32, mix_a: f32, mix_b: f32, mix_mod: f32| -> f32 {
+        let input = RingModInput {
+            signal_a: SampleFxP::from_num(a),
+            signal_b: SampleFxP::from_num(b),
+        };
+        let params = RingModParams {
+            mix_a: ScalarFxP::from_num(mix_a),
+            mix_b: ScalarFxP::from_num(mix_b),
+            mix_mod: ScalarFxP::from_num(mix_mod),
+        };
+        ringmod.next(&ctx, input, params).to_num()
+    };
+    // Three full scale sources sum without clipping
+    assert!((ring(1., 1., 0.99, 0.99, 0.99) - 2.97).abs() < 0.01);
+    assert!((ring(-1., 1., 0.99, 0.99, 0.99) + 0.99).abs() < 0.01);
+    // ...and keep their relative balance
+    assert!((ring(1., 1., 0.5, 0.25, 0.125) - 0.875).abs() < 0.01);
+}
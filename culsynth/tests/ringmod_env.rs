@@ -0,0 +1,113 @@
+//! Verify that routing an envelope to [ModDest::RingMod] opens and closes the
+//! ring modulator, for "auto-wah" style effects.
+//!
+//! Both oscillators are routed only to the ring modulator, with a base mix of
+//! zero, so the output of the voice is entirely due to Env1 (depth 1).  Env1
+//! rises to full scale and then decays to silence, and the level of the ring
+//! modulated signal should follow it.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, EnvParamFxP, IScalarFxP, NoteFxP, ScalarFxP, SignedNoteFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+/// Number of samples in each window when measuring the output level
+const WINDOW: usize = 441;
+const NUM_WINDOWS: usize = 80;
+
+fn params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16>::default();
+    params.oscs_p.primary.sin = ScalarFxP::MAX;
+    params.oscs_p.secondary.sin = ScalarFxP::MAX;
+    params.oscs_p.secondary.tune = SignedNoteFxP::lit("7");
+    params.ring_p.mix_a = ScalarFxP::ZERO;
+    params.ring_p.mix_b = ScalarFxP::ZERO;
+    params.ring_p.mix_mod = ScalarFxP::ZERO;
+    params.filt_p.cutoff = NoteFxP::lit("127");
+    params.filt_p.low_mix = ScalarFxP::MAX;
+    params.amp_env_p.attack = EnvParamFxP::lit("0.001");
+    params.amp_env_p.sustain = ScalarFxP::MAX;
+    params.env1_p.attack = EnvParamFxP::lit("0.1");
+    params.env1_p.attack_peak = ScalarFxP::MAX;
+    params.env1_p.decay = EnvParamFxP::lit("0.1");
+    params.env1_p.sustain = ScalarFxP::ZERO;
+    params
+}
+
+fn matrix() -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[ModSrc::Env1 as usize].1[0] = (ModDest::RingMod, IScalarFxP::MAX);
+    matrix
+}
+
+/// Returns the peak level of the left channel for each window
+fn run<T: DspFormat>(ctx: &T::Context, matrix: &ModMatrix<T>, params: VoiceParams<T>) -> Vec<f32> {
+    let mut voice = Voice::<T>::new();
+    let input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let ch_input = VoiceChannelInput::<T>::default();
+    let mut matrix = Some(matrix);
+    (0..NUM_WINDOWS)
+        .map(|_| {
+            (0..WINDOW)
+                .map(|_| {
+                    let out = voice.next(ctx, matrix.take(), &input, &ch_input, params.clone());
+                    T::sample_to_float(out.left).abs()
+                })
+                .fold(0f32, f32::max)
+        })
+        .collect()
+}
+
+fn check_follows_envelope(levels: &[f32]) {
+    let (peak_idx, peak) =
+        levels
+            .iter()
+            .copied()
+            .enumerate()
+            .fold((0, 0f32), |a, b| if b.1 > a.1 { b } else { a });
+    assert!(peak > 0.1, "ring modulator never opened: {:?}", levels);
+    // The ring modulator starts (nearly) closed...
+    assert!(
+        levels[0] < 0.5 * peak,
+        "initial level {} (peak {})",
+        levels[0],
+        peak
+    );
+    // ...opens during the attack, which lasts roughly 100ms (10 windows)...
+    assert!((5..20).contains(&peak_idx), "peak at window {}", peak_idx);
+    for pair in levels[..peak_idx].windows(2) {
+        assert!(pair[1] >= pair[0] - 0.01 * peak, "not rising: {:?}", pair);
+    }
+    // ...and closes again as the envelope decays
+    for pair in levels[peak_idx..].windows(2) {
+        assert!(pair[1] <= pair[0] + 0.01 * peak, "not falling: {:?}", pair);
+    }
+    let last = levels[NUM_WINDOWS - 1];
+    assert!(last < 0.1 * peak, "final level {} (peak {})", last, peak);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn env1_ring_mod_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    check_follows_envelope(&run(&ctx, &matrix(), params()));
+    // Without the modulation, the voice is silent
+    let unmodulated = run(&ctx, &ModMatrix::default(), params());
+    assert!(unmodulated.iter().all(|x| *x < 0.01), "{:?}", unmodulated);
+}
+
+#[test]
+fn env1_ring_mod_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    check_follows_envelope(&run::<f32>(&ctx, &(&matrix()).into(), (&params()).into()));
+    let unmodulated = run::<f32>(&ctx, &ModMatrix::default(), (&params()).into());
+    assert!(unmodulated.iter().all(|x| *x < 0.01), "{:?}", unmodulated);
+}
@@ -0,0 +1,105 @@
+//! Verify that stretch tuning widens each octave away from the reference note
+//! by the configured number of cents, and that a voice applies it to the
+//! oscillators.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, StretchTuning, StretchTuningParams};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, IScalarFxP, NoteFxP};
+
+/// The stretch, in semitones per octave (i.e. 10 cents)
+const STRETCH: IScalarFxP = IScalarFxP::lit("0.1");
+const REFERENCE: NoteFxP = NoteFxP::lit("60");
+
+fn params() -> StretchTuningParams<i16> {
+    StretchTuningParams {
+        stretch: STRETCH,
+        reference: REFERENCE,
+    }
+}
+
+/// How far the output of the stretch is from the unstretched note, in cents
+fn cents<T: DspFormat>(ctx: &T::Context, note: T::Note, params: StretchTuningParams<T>) -> f32 {
+    let out = StretchTuning::<T>::new().next(ctx, note, params);
+    (T::note_to_float(out) - T::note_to_float(note)) * 100f32
+}
+
+fn check_stretch<T: DspFormat>(
+    ctx: &T::Context,
+    note: impl Fn(f32) -> T::Note,
+    params: impl Fn() -> StretchTuningParams<T>,
+) {
+    let reference = REFERENCE.to_num::<f32>();
+    let stretch = STRETCH.to_num::<f32>() * 100f32;
+    // The reference itself is unchanged...
+    assert!(cents::<T>(ctx, note(reference), params()).abs() < 0.1);
+    // ...the octave above is sharp by the stretch, and the octave below flat...
+    let up = cents::<T>(ctx, note(reference + 12f32), params());
+    assert!((up - stretch).abs() < 0.5, "{}", up);
+    let down = cents::<T>(ctx, note(reference - 12f32), params());
+    assert!((down + stretch).abs() < 0.5, "{}", down);
+    // ...and the stretch accumulates over each octave
+    let up2 = cents::<T>(ctx, note(reference + 24f32), params());
+    assert!((up2 - 2f32 * stretch).abs() < 0.5, "{}", up2);
+    // Without a stretch, this is 12 tone equal temperament
+    let equal = StretchTuningParams::<T>::default();
+    assert!(cents::<T>(ctx, note(reference + 12f32), equal).abs() < 0.1);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn stretch_fixed() {
+    check_stretch::<i16>(&ContextFxP::new_480(), NoteFxP::from_num, params);
+}
+
+#[test]
+fn stretch_float() {
+    check_stretch::<f32>(&Context::new(48000f32), |note| note, || (&params()).into());
+}
+
+fn voice_cents<T: DspFormat>(ctx: &T::Context, note: T::Note, params: VoiceParams<T>) -> f32 {
+    let mut voice = Voice::<T>::new();
+    let input = VoiceInput::<T> {
+        note,
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let _ = voice.next(
+        ctx,
+        Some(&Default::default()),
+        &input,
+        &VoiceChannelInput::default(),
+        params,
+    );
+    (T::note_to_float(voice.monitor().note) - T::note_to_float(note)) * 100f32
+}
+
+fn voice_params() -> VoiceParams<i16> {
+    VoiceParams::<i16> {
+        stretch_p: params(),
+        ..Default::default()
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn voice_stretch_fixed() {
+    let note = REFERENCE + NoteFxP::lit("12");
+    let ctx = ContextFxP::new_480();
+    let fixed = voice_cents::<i16>(&ctx, note, voice_params());
+    assert!((fixed - 10f32).abs() < 0.5, "{}", fixed);
+    // Equal temperament by default
+    let fixed = voice_cents::<i16>(&ctx, note, Default::default());
+    assert_eq!(fixed, 0f32);
+}
+
+#[test]
+fn voice_stretch_float() {
+    let note = REFERENCE + NoteFxP::lit("12");
+    let ctx = Context::new(48000f32);
+    let float = voice_cents::<f32>(&ctx, note.to_num(), (&voice_params()).into());
+    assert!((float - 10f32).abs() < 0.5, "{}", float);
+}
@@ -0,0 +1,53 @@
+//! Verify that the calibration tone has the requested frequency and level.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, TestTone, TestToneParams};
+use culsynth::{DspFormat, FrequencyFxP, ScalarFxP};
+
+const SAMPLE_RATE: usize = 48000;
+const FREQ: FrequencyFxP = FrequencyFxP::lit("1000");
+
+fn params(level: f32) -> TestToneParams<i16> {
+    TestToneParams {
+        freq: FREQ,
+        level: ScalarFxP::from_num(level),
+    }
+}
+
+/// Run the tone for one second, checking the peak and RMS levels and the
+/// number of cycles.  `tolerance` is the largest error allowed in a sample.
+fn check_tone<T: DspFormat>(ctx: &T::Context, params: TestToneParams<T>, tolerance: f32) {
+    let level = T::scalar_to_float(params.level);
+    let mut tone = TestTone::<T>::new();
+    let out: Vec<f32> = (0..SAMPLE_RATE)
+        .map(|_| T::sample_to_float(tone.next(ctx, (), params.clone())))
+        .collect();
+    let peak = out.iter().copied().fold(0f32, f32::max);
+    let trough = out.iter().copied().fold(0f32, f32::min);
+    assert!((peak - level).abs() <= tolerance, "{} {}", peak, level);
+    assert!((trough + level).abs() <= tolerance, "{} {}", trough, level);
+    let rms = (out.iter().map(|x| x * x).sum::<f32>() / SAMPLE_RATE as f32).sqrt();
+    let expected = level * core::f32::consts::FRAC_1_SQRT_2;
+    assert!((rms - expected).abs() <= tolerance, "{} {}", rms, expected);
+    let cycles = out.windows(2).filter(|pair| pair[0] < 0f32 && pair[1] >= 0f32).count();
+    assert_eq!(cycles, FREQ.to_num::<usize>() - 1);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn tone_fixed() {
+    let ctx = ContextFxP::new_480();
+    // Half a LSB of a SampleFxP
+    let tolerance = 1f32 / 8192f32;
+    check_tone::<i16>(&ctx, params(0.5), tolerance);
+    check_tone::<i16>(&ctx, params(0.1), tolerance);
+}
+
+#[test]
+fn tone_float() {
+    let ctx = Context::new(SAMPLE_RATE as f32);
+    check_tone::<f32>(&ctx, (&params(0.5)).into(), 1e-5);
+    check_tone::<f32>(&ctx, (&params(0.1)).into(), 1e-5);
+}
@@ -0,0 +1,81 @@
+//! Verify that a [Tremolo] sweeps the gain of a DC signal between zero and
+//! one at the expected rate, and leaves the signal alone with zero depth.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, LfoWave, Tremolo, TremoloParams};
+#[cfg(feature = "fixed")]
+use culsynth::{LfoFreqFxP, SampleFxP, ScalarFxP};
+
+const SAMPLE_RATE: usize = 48000;
+
+/// Count the number of times `signal` swings from below 0.25 to above 0.75
+/// (the hysteresis ignores any jitter around the midpoint)
+fn cycles(signal: &[f64]) -> usize {
+    let mut low = false;
+    let mut count = 0;
+    for x in signal {
+        if *x < 0.25 {
+            low = true;
+        } else if *x > 0.75 && low {
+            low = false;
+            count += 1;
+        }
+    }
+    count
+}
+
+#[test]
+fn tremolo_full_depth_float() {
+    let ctx = Context::new(SAMPLE_RATE as f64);
+    let mut tremolo = Tremolo::<f64>::new();
+    let params = TremoloParams::<f64> {
+        rate: 4.,
+        depth: 1.,
+        wave: LfoWave::Sine,
+    };
+    // Two seconds of a DC signal
+    let out: Vec<f64> =
+        (0..2 * SAMPLE_RATE).map(|_| tremolo.next(&ctx, 1., params.clone())).collect();
+    let max = out.iter().copied().fold(f64::MIN, f64::max);
+    let min = out.iter().copied().fold(f64::MAX, f64::min);
+    assert!(max > 0.999 && max <= 1., "max: {}", max);
+    assert!((0. ..0.001).contains(&min), "min: {}", min);
+    // The gain starts at 0.5 and rising, so the first cycle isn't counted
+    assert_eq!(cycles(&out), 7);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn tremolo_full_depth_fixed() {
+    let ctx = ContextFxP::new_480();
+    let mut tremolo = Tremolo::<i16>::new();
+    let params = TremoloParams::<i16> {
+        rate: LfoFreqFxP::lit("4"),
+        depth: ScalarFxP::MAX,
+        wave: LfoWave::Sine,
+    };
+    let out: Vec<f64> = (0..2 * SAMPLE_RATE)
+        .map(|_| tremolo.next(&ctx, SampleFxP::ONE, params.clone()).to_num())
+        .collect();
+    let max = out.iter().copied().fold(f64::MIN, f64::max);
+    let min = out.iter().copied().fold(f64::MAX, f64::min);
+    assert!(max > 0.99 && max <= 1., "max: {}", max);
+    assert!((0. ..0.01).contains(&min), "min: {}", min);
+    assert_eq!(cycles(&out), 7);
+}
+
+#[test]
+fn tremolo_zero_depth() {
+    let ctx = Context::new(SAMPLE_RATE as f64);
+    let mut tremolo = Tremolo::<f64>::new();
+    let params = TremoloParams::<f64> {
+        rate: 4.,
+        depth: 0.,
+        wave: LfoWave::Sine,
+    };
+    for _ in 0..SAMPLE_RATE {
+        assert_eq!(tremolo.next(&ctx, 0.5, params.clone()), 0.5);
+    }
+}
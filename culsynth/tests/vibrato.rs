@@ -0,0 +1,70 @@
+//! Verify that a [Vibrato] sweeps a note by the expected depth either side,
+//! with one full cycle every `sample_rate / rate` samples.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Vibrato, VibratoParams};
+#[cfg(feature = "fixed")]
+use culsynth::{LfoFreqFxP, NoteFxP, ScalarFxP};
+
+/// 5Hz at 48kHz
+const PERIOD: usize = 9600;
+
+/// Find the samples where `notes` rises through `center`, with hysteresis
+/// of `band` to ignore any jitter
+fn rising(notes: &[f64], center: f64, band: f64) -> Vec<usize> {
+    let mut low = false;
+    let mut ret = Vec::new();
+    for (i, x) in notes.iter().enumerate() {
+        if *x < center - band {
+            low = true;
+        } else if *x >= center && low {
+            low = false;
+            ret.push(i);
+        }
+    }
+    ret
+}
+
+/// Check that `notes` sweeps 50 cents either side of 60, within `tol`, with
+/// the expected period
+fn check_vibrato(notes: &[f64], tol: f64) {
+    let max = notes.iter().copied().fold(f64::MIN, f64::max);
+    let min = notes.iter().copied().fold(f64::MAX, f64::min);
+    assert!((max - 60.5).abs() < tol, "max: {}", max);
+    assert!((min - 59.5).abs() < tol, "min: {}", min);
+    let cycles = rising(notes, 60., 0.25);
+    assert_eq!(cycles.len(), 4);
+    for w in cycles.windows(2) {
+        assert!((w[1] - w[0]).abs_diff(PERIOD) <= 2, "{:?}", w);
+    }
+}
+
+#[test]
+fn vibrato_float() {
+    let ctx = Context::new(48000f64);
+    let mut vibrato = Vibrato::<f64>::new();
+    let params = VibratoParams::<f64> {
+        rate: 5.,
+        depth: 0.5,
+    };
+    let notes: Vec<f64> =
+        (0..5 * PERIOD).map(|_| vibrato.next(&ctx, 60., params.clone())).collect();
+    check_vibrato(&notes, 0.001);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn vibrato_fixed() {
+    let ctx = ContextFxP::new_480();
+    let mut vibrato = Vibrato::<i16>::new();
+    let params = VibratoParams::<i16> {
+        rate: LfoFreqFxP::lit("5"),
+        depth: ScalarFxP::lit("0.5"),
+    };
+    let notes: Vec<f64> = (0..5 * PERIOD)
+        .map(|_| vibrato.next(&ctx, NoteFxP::lit("60"), params.clone()).to_num())
+        .collect();
+    check_vibrato(&notes, 0.01);
+}
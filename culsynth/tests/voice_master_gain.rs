@@ -0,0 +1,125 @@
+//! Verify that routing a modulation source to [ModDest::MasterGain] shapes the
+//! output of a voice after the VCA.
+//!
+//! Env1 is routed to the master gain with a depth of -1, so as the envelope
+//! rises from 0 to its sustain level of 1, the output should be attenuated
+//! from unity gain down to -12dB, on top of the (much faster) VCA envelope.
+//! The panner after it should leave a centered voice at unity gain.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Pan, PanParams};
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+#[cfg(feature = "fixed")]
+use culsynth::SampleFxP;
+use culsynth::{DspFormat, DspType, EnvParamFxP, IScalarFxP, NoteFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 44100;
+/// Number of samples in each window when measuring the output level
+const WINDOW: usize = 441;
+const NUM_WINDOWS: usize = 100;
+
+fn params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16>::default();
+    params.oscs_p.primary.saw = ScalarFxP::MAX;
+    params.ring_p.mix_a = ScalarFxP::MAX;
+    params.filt_p.cutoff = NoteFxP::lit("127");
+    params.filt_p.low_mix = ScalarFxP::MAX;
+    params.amp_env_p.attack = EnvParamFxP::lit("0.001");
+    params.env1_p.attack = EnvParamFxP::lit("0.2");
+    params
+}
+
+fn matrix() -> ModMatrix<i16> {
+    let mut matrix = ModMatrix::<i16>::default();
+    matrix.rows[ModSrc::Env1 as usize].1[0] = (ModDest::MasterGain, IScalarFxP::NEG_ONE);
+    matrix
+}
+
+/// Returns the peak level of the left channel for each window
+fn run<T: DspFormat>(ctx: &T::Context, matrix: &ModMatrix<T>, params: VoiceParams<T>) -> Vec<f32> {
+    let mut voice = Voice::<T>::new();
+    let input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let ch_input = VoiceChannelInput::<T>::default();
+    let mut matrix = Some(matrix);
+    (0..NUM_WINDOWS)
+        .map(|_| {
+            (0..WINDOW)
+                .map(|_| {
+                    let out = voice.next(ctx, matrix.take(), &input, &ch_input, params.clone());
+                    T::sample_to_float(out.left).abs()
+                })
+                .fold(0f32, f32::max)
+        })
+        .collect()
+}
+
+fn check_gain_envelope(modulated: &[f32], unmodulated: &[f32]) {
+    let ratios: Vec<f32> = modulated.iter().zip(unmodulated).map(|(m, u)| m / u).collect();
+    // Env1 has barely started rising in the first window...
+    assert!(ratios[0] > 0.9, "initial gain {}", ratios[0]);
+    // ...is monotonically attenuating the output as it rises...
+    for pair in ratios.windows(2) {
+        assert!(pair[1] <= pair[0] + 0.01, "gain not decreasing: {:?}", pair);
+    }
+    // ...and ends up at -12dB at the sustain level
+    let expected = 10f32.powf(-12f32 / 20f32);
+    let last = ratios[NUM_WINDOWS - 1];
+    assert!((last - expected).abs() < 0.02, "final gain {}", last);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn env1_master_gain_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let modulated = run(&ctx, &matrix(), params());
+    let unmodulated = run(&ctx, &ModMatrix::default(), params());
+    check_gain_envelope(&modulated, &unmodulated);
+}
+
+#[test]
+fn env1_master_gain_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    let modulated = run::<f32>(&ctx, &(&matrix()).into(), (&params()).into());
+    let unmodulated = run::<f32>(&ctx, &ModMatrix::default(), (&params()).into());
+    check_gain_envelope(&modulated, &unmodulated);
+}
+
+/// Pan a constant signal, returning the (left, right) output
+fn pan<T: DspFormat>(ctx: &T::Context, signal: T::Sample, pan: T::IScalar) -> (f32, f32) {
+    let params = PanParams::<T> {
+        gain: T::IScalar::zero(),
+        pan,
+    };
+    let out = Pan::<T>::new().next(ctx, signal, params);
+    (T::sample_to_float(out.left), T::sample_to_float(out.right))
+}
+
+// A centered voice is as loud in each channel as it was before it was
+// panned, and panning hard to one side boosts that side by 3dB
+#[test]
+#[cfg(feature = "fixed")]
+fn pan_center_is_unity_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let (left, right) = pan::<i16>(&ctx, SampleFxP::lit("0.5"), IScalarFxP::ZERO);
+    assert!((left - 0.5).abs() < 0.001 && (right - 0.5).abs() < 0.001);
+    let (left, right) = pan::<i16>(&ctx, SampleFxP::lit("0.5"), IScalarFxP::NEG_ONE);
+    assert!((left - 0.5 * 2f32.sqrt()).abs() < 0.002 && right.abs() < 0.001);
+}
+
+#[test]
+fn pan_center_is_unity_float() {
+    let ctx = Context::<f32>::new(SAMPLE_RATE as f32);
+    // Without libm the float pan law uses approximate trig functions
+    let (left, right) = pan::<f32>(&ctx, 0.5, 0.);
+    assert!((left - 0.5).abs() < 0.001 && (right - 0.5).abs() < 0.001);
+    let (left, right) = pan::<f32>(&ctx, 0.5, 1.);
+    assert!(left.abs() < 0.001 && (right - 0.5 * 2f32.sqrt()).abs() < 0.002);
+}
@@ -0,0 +1,88 @@
+//! Verify that [Voice::is_silent] becomes true once a released voice has
+//! faded below the silence threshold of the context, and not before.
+//!
+//! With a release time of `t`, each sample of the release moves `2/k` of the
+//! remaining distance towards zero, where `k = 1 + t * sample_rate / 2`, so
+//! the release takes `ln(threshold) / ln(1 - 2/k)` samples to fall from full
+//! scale to the threshold.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, EnvParamFxP, ScalarFxP};
+
+const SAMPLE_RATE: u32 = 48000;
+/// Samples to hold the gate before releasing
+const HOLD: usize = 4800;
+const RELEASE: &str = "0.01";
+
+fn params() -> VoiceParams<i16> {
+    let mut params = VoiceParams::<i16>::default();
+    params.amp_env_p.attack = EnvParamFxP::lit("0.001");
+    params.amp_env_p.sustain = ScalarFxP::MAX;
+    params.amp_env_p.release = EnvParamFxP::lit(RELEASE);
+    params
+}
+
+/// The expected number of samples for the release to reach `threshold`
+fn expected_samples(threshold: f64) -> f64 {
+    let k = 1f64 + EnvParamFxP::lit(RELEASE).to_num::<f64>() * (SAMPLE_RATE / 2) as f64;
+    threshold.ln() / (1f64 - 2f64 / k).ln()
+}
+
+/// Hold a note, then release it, returning the number of samples after the
+/// release until the voice is silent
+fn samples_to_silence<T: DspFormat>(ctx: &T::Context, params: VoiceParams<T>) -> usize {
+    let mut voice = Voice::<T>::new();
+    let mut input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let ch_input = VoiceChannelInput::<T>::default();
+    for _ in 0..HOLD {
+        let _ = voice.next(ctx, None, &input, &ch_input, params.clone());
+        assert!(!voice.is_silent(ctx));
+    }
+    input.gate = false;
+    (1..SAMPLE_RATE as usize)
+        .find(|_| {
+            let _ = voice.next(ctx, None, &input, &ch_input, params.clone());
+            voice.is_silent(ctx)
+        })
+        .expect("voice never became silent")
+}
+
+fn check_samples(samples: usize, threshold: f64) {
+    let expected = expected_samples(threshold);
+    assert!(
+        (samples as f64 - expected).abs() < expected * 0.1 + 2f64,
+        "{} {}",
+        samples,
+        expected
+    );
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn release_becomes_silent_fixed() {
+    let ctx = ContextFxP::maybe_create(SAMPLE_RATE).unwrap();
+    let samples = samples_to_silence(&ctx, params());
+    check_samples(samples, ctx.silence_threshold().to_num());
+    // A higher threshold is reached sooner
+    let ctx = ctx.with_silence_threshold(ScalarFxP::lit("0.5"));
+    let samples = samples_to_silence(&ctx, params());
+    check_samples(samples, 0.5);
+}
+
+#[test]
+fn release_becomes_silent_float() {
+    let ctx = Context::new(SAMPLE_RATE as f64);
+    let samples = samples_to_silence::<f64>(&ctx, (&params()).into());
+    check_samples(samples, ctx.silence_threshold());
+    let ctx = ctx.with_silence_threshold(0.5);
+    let samples = samples_to_silence::<f64>(&ctx, (&params()).into());
+    check_samples(samples, 0.5);
+}
@@ -0,0 +1,79 @@
+//! Verify that a voice restored from a [VoiceState] captured mid-note picks
+//! up exactly where the original voice left off.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{LfoOptions, LfoParams, LfoWave};
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
+use culsynth::{DspFormat, DspType, IScalarFxP, LfoFreqFxP};
+
+/// Samples to run before capturing the state
+const WARMUP: usize = 4800;
+/// Samples to compare after restoring the state
+const COMPARE: usize = 100;
+
+/// A patch exercising the random number generators: analog drift, and a
+/// sample and hold LFO modulating the filter cutoff
+fn patch() -> (VoiceParams<i16>, ModMatrix<i16>) {
+    let params = VoiceParams::<i16> {
+        analog_drift: true,
+        lfo1_p: LfoParams {
+            freq: LfoFreqFxP::lit("100"),
+            opts: LfoOptions::new(LfoWave::SampleHold, true, false),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let mut matrix = ModMatrix::<i16>::default();
+    for (src, slots) in matrix.rows.iter_mut() {
+        if matches!(src, ModSrc::Lfo1) {
+            slots[0] = (ModDest::FiltCutoff, IScalarFxP::lit("0.5"));
+        }
+    }
+    (params, matrix)
+}
+
+fn check_resume<T: DspFormat>(ctx: &T::Context, params: VoiceParams<T>, matrix: ModMatrix<T>) {
+    let input = VoiceInput::<T> {
+        note: T::default_note(),
+        velocity: T::Scalar::one(),
+        gate: true,
+        ..Default::default()
+    };
+    let ch_input = VoiceChannelInput::<T>::default();
+    let mut voice = Voice::<T>::new_with_seeds(1, 2);
+    let _ = voice.next(ctx, Some(&matrix), &input, &ch_input, params.clone());
+    for _ in 1..WARMUP {
+        let _ = voice.next(ctx, None, &input, &ch_input, params.clone());
+    }
+    let state = voice.dump_state();
+    // The modulation matrix isn't part of the voice state
+    let mut restored = Voice::<T>::new();
+    restored.load_state(&state);
+    for i in 0..COMPARE {
+        let m = if i == 0 { Some(&matrix) } else { None };
+        let expected = voice.next(ctx, m, &input, &ch_input, params.clone());
+        let out = restored.next(ctx, m, &input, &ch_input, params.clone());
+        assert!(
+            T::sample_to_float(out.left) == T::sample_to_float(expected.left)
+                && T::sample_to_float(out.right) == T::sample_to_float(expected.right),
+            "sample {}",
+            i
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn resume_fixed() {
+    let (params, matrix) = patch();
+    check_resume::<i16>(&ContextFxP::new_480(), params, matrix);
+}
+
+#[test]
+fn resume_float() {
+    let (params, matrix) = patch();
+    check_resume::<f32>(&Context::new(48000f32), (&params).into(), (&matrix).into());
+}
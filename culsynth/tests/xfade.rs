@@ -0,0 +1,66 @@
+//! Verify that [Xfade] follows a constant-power law: the gains applied to
+//! each input always satisfy `gain_a^2 + gain_b^2 = 1`, so each input is
+//! attenuated by 3dB (to about 0.707) at the midpoint.
+
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{Device, Xfade, XfadeInput};
+#[cfg(feature = "fixed")]
+use culsynth::ScalarFxP;
+use culsynth::{DspFormat, DspType, IScalarFxP};
+use std::f64::consts::FRAC_1_SQRT_2;
+
+const LEVEL: IScalarFxP = IScalarFxP::lit("0.5");
+const STEPS: usize = 16;
+
+/// The gains applied to the `a` and `b` inputs at a given mix
+fn gains<T: DspFormat>(ctx: &T::Context, mix: T::Scalar) -> (f64, f64) {
+    let mut xfade = Xfade::<T>::new();
+    let level = T::sample_from_fixed(LEVEL);
+    let zero = T::Sample::zero();
+    let mut gain = |a, b| {
+        let out = xfade.next(ctx, XfadeInput { a, b }, mix);
+        T::sample_to_float(out) as f64 / LEVEL.to_num::<f64>()
+    };
+    (gain(level, zero), gain(zero, level))
+}
+
+/// Check the gains from a fade in [STEPS] steps from a mix of 0 to 1
+fn check_constant_power(fade: &[(f64, f64)], tolerance: f64) {
+    for (gain_a, gain_b) in fade {
+        let power = gain_a * gain_a + gain_b * gain_b;
+        assert!((power - 1.0).abs() < tolerance, "{} {}", gain_a, gain_b);
+    }
+    // The fade is monotonic, from all `a`, through -3dB each at the
+    // midpoint, to all `b`
+    assert!(fade.windows(2).all(|w| w[1].0 <= w[0].0 && w[1].1 >= w[0].1));
+    let (first, mid, last) = (fade[0], fade[STEPS / 2], fade[STEPS]);
+    assert!((first.0 - 1.0).abs() < tolerance && first.1.abs() < tolerance);
+    for gain in [mid.0, mid.1] {
+        assert!((gain - FRAC_1_SQRT_2).abs() < tolerance, "{}", gain);
+    }
+    assert!(last.0.abs() < tolerance && (last.1 - 1.0).abs() < tolerance);
+}
+
+#[test]
+#[cfg(feature = "fixed")]
+fn constant_power_fixed() {
+    let ctx = ContextFxP::default();
+    let fade: Vec<_> = (0..=STEPS)
+        .map(|i| {
+            gains::<i16>(
+                &ctx,
+                ScalarFxP::saturating_from_num(i as f32 / STEPS as f32),
+            )
+        })
+        .collect();
+    check_constant_power(&fade, 0.005);
+}
+
+#[test]
+fn constant_power_float() {
+    let ctx = Context::<f64>::default();
+    let fade: Vec<_> = (0..=STEPS).map(|i| gains::<f64>(&ctx, i as f64 / STEPS as f64)).collect();
+    check_constant_power(&fade, 1e-3);
+}
@@ -10,9 +10,12 @@ pub fn run(voices: &mut [&mut Voice; NUM_VOICES]) -> ! {
     let ch_input = VoiceChannelInput::default();
 
     let params = VoiceParams::default();
+    // Each voice keeps a copy of the matrix, so only pass it in when it changes
+    let mut matrix = Some(&matrix);
     loop {
         for voice in voices.iter_mut() {
-            let smp = voice.next(&CONTEXT, &matrix, &input, &ch_input, params.clone());
+            let smp = voice.next(&CONTEXT, matrix, &input, &ch_input, params.clone());
         }
+        matrix = None;
     }
 }
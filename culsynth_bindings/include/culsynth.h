@@ -113,6 +113,33 @@ int32_t culsynth_osc_f32_process(
     float* saw
 );
 
+void* culsynth_ringmod_i16_new();
+void culsynth_ringmod_i16_free(void*);
+int32_t culsynth_ringmod_i16_process(
+    void* ringmod,
+    uint32_t sample_rate,
+    uint32_t samples,
+    const int16_t* signal_a,
+    const int16_t* signal_b,
+    const uint16_t* mix_a,
+    const uint16_t* mix_b,
+    const uint16_t* mix_mod,
+    int16_t* out
+);
+void* culsynth_ringmod_f32_new();
+void culsynth_ringmod_f32_free(void*);
+int32_t culsynth_ringmod_f32_process(
+    void* ringmod,
+    uint32_t sample_rate,
+    uint32_t samples,
+    const float* signal_a,
+    const float* signal_b,
+    const float* mix_a,
+    const float* mix_b,
+    const float* mix_mod,
+    float* out
+);
+
 #ifdef __cplusplus
 }
 
@@ -280,6 +307,50 @@ namespace culsynth {
                 tune, shape, sin, tri, sq, saw);
         }
     };
+
+    class RingMod {
+        void* ffi;
+        RingMod(const RingMod&);
+        RingMod& operator=(const RingMod&);
+    public:
+        RingMod() : ffi(culsynth_ringmod_f32_new()) {}
+        ~RingMod() { culsynth_ringmod_f32_free(ffi); }
+        int32_t process(
+            uint32_t sample_rate,
+            uint32_t samples,
+            const float* signal_a,
+            const float* signal_b,
+            const float* mix_a,
+            const float* mix_b,
+            const float* mix_mod,
+            float* out)
+        {
+            return culsynth_ringmod_f32_process(ffi, sample_rate, samples,
+                signal_a, signal_b, mix_a, mix_b, mix_mod, out);
+        }
+    };
+
+    class RingModFxP {
+        void* ffi;
+        RingModFxP(const RingModFxP&);
+        RingModFxP& operator=(const RingModFxP&);
+    public:
+        RingModFxP() : ffi(culsynth_ringmod_i16_new()) {}
+        ~RingModFxP() { culsynth_ringmod_i16_free(ffi); }
+        int32_t process(
+            uint32_t sample_rate,
+            uint32_t samples,
+            const int16_t* signal_a,
+            const int16_t* signal_b,
+            const uint16_t* mix_a,
+            const uint16_t* mix_b,
+            const uint16_t* mix_mod,
+            int16_t* out)
+        {
+            return culsynth_ringmod_i16_process(ffi, sample_rate, samples,
+                signal_a, signal_b, mix_a, mix_b, mix_mod, out);
+        }
+    };
 }
 #endif
-#endif
\ No newline at end of file
+#endif
@@ -233,7 +233,7 @@ pub unsafe extern "C" fn culsynth_env_f32_process(
         .with_decay(d.iter().copied())
         .with_sustain(s.iter().copied())
         .with_release(r.iter().copied());
-    let ctx = Context::<f32> { sample_rate: sr };
+    let ctx = Context::<f32>::new(sr);
     let out = (*p).process(&ctx, g.iter().map(|x| *x != 0), paramiter);
     let mut processed = 0i32;
     for (o, smp) in zip(PtrIterator::new(signal), out) {
@@ -344,7 +344,7 @@ pub unsafe extern "C" fn culsynth_filt_f32_process(
     let params = new_filt_param_iter()
         .with_cutoff(c.iter().copied())
         .with_resonance(r.iter().copied());
-    let ctx = Context::<f32> { sample_rate: sr };
+    let ctx = Context::<f32>::new(sr);
     let out = (*p).process(&ctx, i.iter().copied(), params);
     let mut processed = 0i32;
     for (l, (b, (h, o))) in zip(low, zip(band, zip(high, out))) {
@@ -463,7 +463,7 @@ pub unsafe extern "C" fn culsynth_osc_f32_process(
     let params = new_osc_param_iter()
         .with_tune(tune_s.iter().copied())
         .with_shape(shape_s.iter().copied());
-    let ctx = Context::<f32> { sample_rate: sr };
+    let ctx = Context::<f32>::new(sr);
     let out = (*p).process(&ctx, note_s.iter().copied(), params);
     let mut processed = 0i32;
     for (n, (t, (q, (s, o)))) in zip(sin, zip(tri, zip(sq, zip(saw, out)))) {
@@ -475,3 +475,217 @@ pub unsafe extern "C" fn culsynth_osc_f32_process(
     }
     processed
 }
+
+#[no_mangle]
+pub extern "C" fn culsynth_ringmod_i16_new() -> *mut RingMod<i16> {
+    Box::into_raw(Box::default())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn culsynth_ringmod_i16_free(p: *mut RingMod<i16>) {
+    if !p.is_null() {
+        let _ = Box::from_raw(p);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn culsynth_ringmod_i16_process(
+    p: *mut RingMod<i16>,
+    sr: u32,
+    samples: u32,
+    signal_a: *const i16,
+    signal_b: *const i16,
+    mix_a: *const u16,
+    mix_b: *const u16,
+    mix_mod: *const u16,
+    out: *mut i16,
+) -> i32 {
+    if p.is_null()
+        || signal_a.is_null()
+        || signal_b.is_null()
+        || mix_a.is_null()
+        || mix_b.is_null()
+        || mix_mod.is_null()
+        || out.is_null()
+    {
+        return -1;
+    }
+    let ctx = match contextfxp_from_u32(sr) {
+        Some(x) => x,
+        None => return -1,
+    };
+    let a = core::slice::from_raw_parts(signal_a.cast::<SampleFxP>(), samples as usize);
+    let b = core::slice::from_raw_parts(signal_b.cast::<SampleFxP>(), samples as usize);
+    let ma = core::slice::from_raw_parts(mix_a.cast::<ScalarFxP>(), samples as usize);
+    let mb = core::slice::from_raw_parts(mix_b.cast::<ScalarFxP>(), samples as usize);
+    let mm = core::slice::from_raw_parts(mix_mod.cast::<ScalarFxP>(), samples as usize);
+    let input = new_ringmod_input_iter()
+        .with_signal_a(a.iter().copied())
+        .with_signal_b(b.iter().copied());
+    let params = new_ringmod_param_iter()
+        .with_mix_a(ma.iter().copied())
+        .with_mix_b(mb.iter().copied())
+        .with_mix_mod(mm.iter().copied());
+    let mut processed = 0i32;
+    for (o, smp) in zip(PtrIterator::new(out), (*p).process(&ctx, input, params)) {
+        *o = smp.to_bits();
+        processed += 1;
+    }
+    processed
+}
+
+#[no_mangle]
+pub extern "C" fn culsynth_ringmod_f32_new() -> *mut RingMod<f32> {
+    Box::into_raw(Box::default())
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn culsynth_ringmod_f32_free(p: *mut RingMod<f32>) {
+    if !p.is_null() {
+        let _ = Box::from_raw(p);
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn culsynth_ringmod_f32_process(
+    p: *mut RingMod<f32>,
+    sr: f32,
+    samples: u32,
+    signal_a: *const f32,
+    signal_b: *const f32,
+    mix_a: *const f32,
+    mix_b: *const f32,
+    mix_mod: *const f32,
+    out: *mut f32,
+) -> i32 {
+    if p.is_null()
+        || signal_a.is_null()
+        || signal_b.is_null()
+        || mix_a.is_null()
+        || mix_b.is_null()
+        || mix_mod.is_null()
+        || out.is_null()
+    {
+        return -1;
+    }
+    let a = core::slice::from_raw_parts(signal_a, samples as usize);
+    let b = core::slice::from_raw_parts(signal_b, samples as usize);
+    let ma = core::slice::from_raw_parts(mix_a, samples as usize);
+    let mb = core::slice::from_raw_parts(mix_b, samples as usize);
+    let mm = core::slice::from_raw_parts(mix_mod, samples as usize);
+    let input = new_ringmod_input_iter()
+        .with_signal_a(a.iter().copied())
+        .with_signal_b(b.iter().copied());
+    let params = new_ringmod_param_iter()
+        .with_mix_a(ma.iter().copied())
+        .with_mix_b(mb.iter().copied())
+        .with_mix_mod(mm.iter().copied());
+    let mut processed = 0i32;
+    for (o, smp) in zip(
+        PtrIterator::new(out),
+        (*p).process(&Context::new(sr), input, params),
+    ) {
+        *o = smp;
+        processed += 1;
+    }
+    processed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A safe wrapper around the fixed point filter FFI, called exactly as C
+    /// code would (i.e. through raw pointers to plain integers)
+    struct FfiFiltI16 {
+        ptr: *mut Filt<i16>,
+    }
+
+    impl FfiFiltI16 {
+        fn new() -> Self {
+            Self {
+                ptr: culsynth_filt_i16_new(),
+            }
+        }
+        /// Returns the (low, band, high) outputs, or None on error
+        fn process(
+            &mut self,
+            sr: u32,
+            input: &[i16],
+            cutoff: &[u16],
+            resonance: &[u16],
+        ) -> Option<(Vec<i16>, Vec<i16>, Vec<i16>)> {
+            let len = input.len();
+            assert!(cutoff.len() == len && resonance.len() == len);
+            let (mut low, mut band, mut high) = (vec![0; len], vec![0; len], vec![0; len]);
+            let processed = unsafe {
+                culsynth_filt_i16_process(
+                    self.ptr,
+                    sr,
+                    len as u32,
+                    input.as_ptr(),
+                    cutoff.as_ptr(),
+                    resonance.as_ptr(),
+                    low.as_mut_ptr(),
+                    band.as_mut_ptr(),
+                    high.as_mut_ptr(),
+                )
+            };
+            if processed < 0 {
+                return None;
+            }
+            low.truncate(processed as usize);
+            band.truncate(processed as usize);
+            high.truncate(processed as usize);
+            Some((low, band, high))
+        }
+    }
+
+    impl Drop for FfiFiltI16 {
+        fn drop(&mut self) {
+            unsafe { culsynth_filt_i16_free(self.ptr) }
+        }
+    }
+
+    /// A simple LCG, so the test is deterministic without pulling in `rand`
+    fn noise(seed: &mut u32) -> u16 {
+        *seed = seed.wrapping_mul(1664525).wrapping_add(1013904223);
+        (*seed >> 16) as u16
+    }
+
+    #[test]
+    fn filt_i16_ffi_matches_rust() {
+        const SAMPLES: usize = 4096;
+        let mut seed = 1;
+        let input: Vec<i16> = (0..SAMPLES).map(|_| noise(&mut seed) as i16).collect();
+        // Sweep the full range of cutoffs and resonances
+        let cutoff: Vec<u16> = (0..SAMPLES).map(|i| (i * 8) as u16).collect();
+        let resonance: Vec<u16> = (0..SAMPLES).map(|_| noise(&mut seed)).collect();
+
+        let mut ffi = FfiFiltI16::new();
+        let (low, band, high) = ffi.process(SR_480_VAL, &input, &cutoff, &resonance).unwrap();
+        assert_eq!(low.len(), SAMPLES);
+
+        let ctx = ContextFxP::new_480();
+        let mut filt = Filt::<i16>::new();
+        for i in 0..SAMPLES {
+            let out = filt.next(
+                &ctx,
+                SampleFxP::from_bits(input[i]),
+                FiltParams {
+                    cutoff: NoteFxP::from_bits(cutoff[i]),
+                    resonance: Resonance::new(ScalarFxP::from_bits(resonance[i])),
+                },
+            );
+            assert_eq!(low[i], out.low.to_bits(), "low @ {}", i);
+            assert_eq!(band[i], out.band.to_bits(), "band @ {}", i);
+            assert_eq!(high[i], out.high.to_bits(), "high @ {}", i);
+        }
+    }
+
+    #[test]
+    fn filt_i16_ffi_rejects_bad_sample_rate() {
+        let mut ffi = FfiFiltI16::new();
+        assert!(ffi.process(u32::MAX, &[0], &[0], &[0]).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//! A debugging aid to catch memory allocation on the audio thread.
+//!
+//! Allocating can block (e.g. on a lock inside the allocator, or while the
+//! OS maps in more memory), so an allocation while processing audio risks
+//! xruns.  With the `no_alloc_guard` feature enabled in a debug build, the
+//! plugin installs [GuardedAlloc] as the global allocator, which counts every
+//! allocation made by a thread while it holds an [AudioThreadScope].
+//!
+//! A global allocator must not unwind, and logging from inside the allocator
+//! could deadlock on the logger's own allocations, so violations are only
+//! counted when they happen.  They are reported with `nih_error!` when the
+//! scope ends.
+
+use nih_plug::nih_error;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+thread_local! {
+    /// Set while this thread holds an [AudioThreadScope]
+    static AUDIO_THREAD: Cell<bool> = const { Cell::new(false) };
+    /// The number of allocations made by this thread inside an
+    /// [AudioThreadScope]
+    static VIOLATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+/// The number of allocations the current thread has made inside an
+/// [AudioThreadScope]
+pub fn violations() -> usize {
+    VIOLATIONS.with(|count| count.get())
+}
+
+/// Marks the current thread as processing audio for as long as this exists,
+/// and reports any allocations made in the meantime when it is dropped
+pub struct AudioThreadScope {
+    was_set: bool,
+    start: usize,
+}
+
+impl AudioThreadScope {
+    /// Start processing audio on the current thread
+    pub fn enter() -> Self {
+        Self {
+            was_set: AUDIO_THREAD.with(|flag| flag.replace(true)),
+            start: violations(),
+        }
+    }
+}
+
+impl Drop for AudioThreadScope {
+    fn drop(&mut self) {
+        AUDIO_THREAD.with(|flag| flag.set(self.was_set));
+        let count = violations() - self.start;
+        if count > 0 {
+            nih_error!("{} allocation(s) on the audio thread", count);
+        }
+    }
+}
+
+/// Count an allocation if the current thread is processing audio.  During
+/// thread teardown the thread locals may already be gone, but then the
+/// thread isn't processing audio anyway.
+fn check_alloc() {
+    if AUDIO_THREAD.try_with(|flag| flag.get()).unwrap_or(false) {
+        let _ = VIOLATIONS.try_with(|count| count.set(count.get() + 1));
+    }
+}
+
+/// A wrapper around the system allocator that counts allocations made on
+/// the audio thread (see [AudioThreadScope])
+pub struct GuardedAlloc;
+
+unsafe impl GlobalAlloc for GuardedAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        check_alloc();
+        System.alloc(layout)
+    }
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        check_alloc();
+        System.alloc_zeroed(layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        check_alloc();
+        System.realloc(ptr, layout, new_size)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[cfg(all(test, feature = "fixed"))]
+mod tests {
+    use super::*;
+    use crate::voicealloc::{MonoSynth, PolySynth, VoiceAllocator};
+    use culsynth::context::ContextFxP;
+    use culsynth::voice::modulation::ModMatrix;
+    use culsynth::voice::VoiceParams;
+
+    /// Stand in for the body of `Plugin::process`
+    fn mock_process(synth: &mut dyn VoiceAllocator, params: &VoiceParams<i16>, alloc: bool) {
+        let _scope = AudioThreadScope::enter();
+        synth.note_on(60, 100);
+        let mut outs = [(0f32, 0f32); 2];
+        for _ in 0..64 {
+            synth.next_multi(params, Some(&ModMatrix::default()), &mut outs);
+        }
+        synth.note_off(60, 0);
+        if alloc {
+            std::hint::black_box(vec![0u8; 64]);
+        }
+    }
+
+    #[test]
+    fn guard_counts_audio_thread_allocations() {
+        let params = VoiceParams::<i16>::default();
+        let mut poly = PolySynth::<i16>::new(ContextFxP::new_480(), 4);
+        let mut mono = MonoSynth::<i16>::new(ContextFxP::new_480());
+        let start = violations();
+        mock_process(&mut poly, &params, false);
+        mock_process(&mut mono, &params, false);
+        assert_eq!(violations(), start);
+        mock_process(&mut poly, &params, true);
+        assert_eq!(violations(), start + 1);
+        // Allocations outside of the scope are fine
+        std::hint::black_box(vec![0u8; 64]);
+        assert_eq!(violations(), start + 1);
+    }
+}
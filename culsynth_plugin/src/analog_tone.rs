@@ -0,0 +1,116 @@
+//! This module contains the gentle high frequency rolloff optionally applied
+//! to the main output (see [AnalogTone])
+
+/// The frequency at which the rolloff is -3dB at full strength, in Hz
+pub const ANALOG_TONE_CUTOFF_HZ: f32 = 16000f32;
+
+/// The highest cutoff frequency used, as a fraction of the sample rate.  At
+/// low sample rates the cutoff is lowered to this, to keep it below Nyquist.
+const MAX_CUTOFF_RATIO: f32 = 0.45f32;
+
+/// A stereo one pole lowpass at [ANALOG_TONE_CUTOFF_HZ], blended with the dry
+/// signal, to take the edge off of the top octave like an analog output
+/// stage.
+///
+/// The lowpass is designed with the bilinear transform, so it is exactly
+/// -3dB at the cutoff at any sample rate.  An amount of 1 applies the full
+/// lowpass, and lower amounts mix in the dry signal, giving a high shelf
+/// that flattens out at `1 - amount` towards Nyquist.
+pub struct AnalogTone {
+    // The lowpass gain coefficient, g/(1+g) for g = tan(pi*fc/fs)
+    coeff: f32,
+    // The integrator state for each channel
+    state: (f32, f32),
+}
+
+impl AnalogTone {
+    /// Create a rolloff filter for the given sample rate
+    pub fn new(sample_rate: f32) -> Self {
+        let cutoff = ANALOG_TONE_CUTOFF_HZ.min(MAX_CUTOFF_RATIO * sample_rate);
+        let g = (core::f32::consts::PI * cutoff / sample_rate).tan();
+        Self {
+            coeff: g / (1f32 + g),
+            state: (0f32, 0f32),
+        }
+    }
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.state = (0f32, 0f32);
+    }
+    /// Filter the next stereo frame, with `amount` between 0 (no effect) and
+    /// 1 (the full lowpass)
+    pub fn next(&mut self, (left, right): (f32, f32), amount: f32) -> (f32, f32) {
+        let amount = amount.clamp(0f32, 1f32);
+        let coeff = self.coeff;
+        let filter = |smp: f32, state: &mut f32| {
+            let v = (smp - *state) * coeff;
+            let lowpass = v + *state;
+            *state = lowpass + v;
+            smp + amount * (lowpass - smp)
+        };
+        (
+            filter(left, &mut self.state.0),
+            filter(right, &mut self.state.1),
+        )
+    }
+}
+
+impl Default for AnalogTone {
+    fn default() -> Self {
+        Self::new(44100f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000f32;
+
+    /// The gain (in dB) of the filter for a sine wave at `freq`, measured
+    /// as the ratio of output to input power after the filter has settled
+    fn gain_db(freq: f32, amount: f32) -> f32 {
+        let mut tone = AnalogTone::new(SAMPLE_RATE);
+        let omega = core::f32::consts::TAU * freq / SAMPLE_RATE;
+        let (power_in, power_out) = (0..48000)
+            .map(|i| (i as f32 * omega).sin())
+            .map(|smp| (smp, tone.next((smp, -smp), amount)))
+            .skip(24000)
+            .fold(
+                (0f32, 0f32),
+                |(power_in, power_out), (smp, (left, right))| {
+                    assert_eq!(left, -right);
+                    (power_in + smp * smp, power_out + left * left)
+                },
+            );
+        10f32 * (power_out / power_in).log10()
+    }
+
+    #[test]
+    fn rolloff_at_cutoff() {
+        let gain = gain_db(ANALOG_TONE_CUTOFF_HZ, 1f32);
+        assert!((gain + 3.01f32).abs() < 0.1f32, "{}", gain);
+        // The audible range is (mostly) untouched...
+        let gain = gain_db(1000f32, 1f32);
+        assert!(gain.abs() < 0.05f32, "{}", gain);
+        // ...and a partial amount is gentler
+        let gain = gain_db(ANALOG_TONE_CUTOFF_HZ, 0.5f32);
+        assert!(gain < -0.5f32 && gain > -3f32, "{}", gain);
+    }
+
+    #[test]
+    fn off_is_bypass() {
+        let mut tone = AnalogTone::new(SAMPLE_RATE);
+        for i in 0..100 {
+            let smp = (i as f32 * 2f32).sin();
+            assert_eq!(tone.next((smp, smp), 0f32), (smp, smp));
+        }
+    }
+
+    #[test]
+    fn low_sample_rate() {
+        // The cutoff is kept below Nyquist
+        let tone = AnalogTone::new(32000f32);
+        assert!(tone.coeff.is_finite() && tone.coeff < 1f32);
+    }
+}
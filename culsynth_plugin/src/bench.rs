@@ -0,0 +1,127 @@
+//! A simple wall clock benchmark of the synth engine.
+//!
+//! This gives users a rough idea of how many voices their system can process
+//! in real time before committing to a voice count.  It isn't a substitute
+//! for a proper profiler, but is cheap enough to run from the GUI.
+
+use crate::pluginparams::CulSynthParams;
+use crate::voicealloc::SynthConfig;
+use crate::VoiceMode;
+use culsynth::voice::modulation::ModMatrix;
+use culsynth::voice::VoiceParams;
+use std::hint::black_box;
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+/// The number of buffers processed by a [BenchmarkRunner]
+const GUI_BENCH_BUFFERS: usize = 1000;
+/// The buffer size used by a [BenchmarkRunner]
+const GUI_BENCH_BUFFER_SIZE: usize = 256;
+
+/// Process `n_buffers` buffers of `buffer_size` samples at sample rate `sr`
+/// with a fresh floating point polysynth of `voice_count` voices, all playing
+/// at once with the default patch, and return the total processing time.
+pub fn benchmark(voice_count: usize, buffer_size: usize, n_buffers: usize, sr: u32) -> Duration {
+    let params = CulSynthParams::default();
+    let mut synth = SynthConfig::new(sr)
+        .with_voice_mode(VoiceMode::Poly16)
+        .with_voice_count(voice_count)
+        .build()
+        .expect("Floating point synths support any sample rate");
+    for i in 0..voice_count {
+        // Spread the notes out from C2, staying in the valid MIDI range
+        synth.note_on((36 + i % 92) as u8, 100);
+    }
+    let start = Instant::now();
+    for _ in 0..n_buffers {
+        // Like the plugin, update the mod matrix once per buffer and the
+        // other parameters every sample
+        let matrix: ModMatrix<i16> = (&params.modmatrix).into();
+        for smp in 0..buffer_size {
+            let voice_params: VoiceParams<i16> = (&params).into();
+            let matrix = if smp == 0 { Some(&matrix) } else { None };
+            black_box(synth.next(&voice_params, matrix));
+        }
+    }
+    start.elapsed()
+}
+
+/// The time taken to process `samples` samples at sample rate `sr`, as a
+/// percentage of the real time duration of those samples.  Anything over
+/// 100% can't keep up with the audio device.
+pub fn realtime_percent(elapsed: Duration, samples: usize, sr: u32) -> f32 {
+    let realtime = samples as f64 / sr as f64;
+    (100f64 * elapsed.as_secs_f64() / realtime) as f32
+}
+
+/// Runs a [benchmark] on a background thread, so the GUI stays responsive
+#[derive(Default)]
+pub struct BenchmarkRunner {
+    running: Option<Receiver<f32>>,
+    result: Option<f32>,
+}
+
+impl BenchmarkRunner {
+    /// Start a benchmark of `voice_count` voices at sample rate `sr`, unless
+    /// one is already running
+    pub fn start(&mut self, voice_count: usize, sr: u32) {
+        if self.running.is_some() {
+            return;
+        }
+        let (tx, rx) = channel();
+        std::thread::spawn(move || {
+            let elapsed = benchmark(voice_count, GUI_BENCH_BUFFER_SIZE, GUI_BENCH_BUFFERS, sr);
+            let samples = GUI_BENCH_BUFFER_SIZE * GUI_BENCH_BUFFERS;
+            let _ = tx.send(realtime_percent(elapsed, samples, sr));
+        });
+        self.running = Some(rx);
+        self.result = None;
+    }
+    /// Is a benchmark currently running?  This also collects the result of
+    /// a benchmark that has completed.
+    pub fn running(&mut self) -> bool {
+        if let Some(rx) = &self.running {
+            match rx.try_recv() {
+                Ok(result) => {
+                    self.result = Some(result);
+                    self.running = None;
+                }
+                Err(TryRecvError::Disconnected) => self.running = None,
+                Err(TryRecvError::Empty) => {}
+            }
+        }
+        self.running.is_some()
+    }
+    /// The result of the last completed benchmark, as a percentage of real
+    /// time (see [realtime_percent])
+    pub fn result(&self) -> Option<f32> {
+        self.result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn benchmark_is_fast_enough() {
+        let elapsed = benchmark(16, 64, 1000, 48000);
+        assert!(elapsed < Duration::from_secs(5), "{:?}", elapsed);
+    }
+
+    #[test]
+    fn realtime() {
+        let percent = realtime_percent(Duration::from_millis(250), 48000, 48000);
+        assert!((percent - 25f32).abs() < 1e-3);
+    }
+
+    #[test]
+    fn runner_collects_result() {
+        let mut runner = BenchmarkRunner::default();
+        runner.start(1, 48000);
+        while runner.running() {
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        assert!(runner.result().is_some_and(|x| x > 0f32));
+    }
+}
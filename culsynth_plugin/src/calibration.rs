@@ -0,0 +1,77 @@
+//! This module contains the calibration tone, which replaces the output of
+//! the synth with a sine wave at a precise frequency and level for checking
+//! signal chains and levels (see [TestTone])
+
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::context::{Context, GenericContext};
+use culsynth::devices::{Device, TestTone, TestToneParams};
+#[cfg(feature = "fixed")]
+use culsynth::{FrequencyFxP, ScalarFxP};
+
+/// Generates the calibration tone using the same number format as the synth
+/// engine, so it is quantized the same way as the voices would be
+#[derive(Clone, Default)]
+pub struct CalibrationTone {
+    #[cfg(feature = "fixed")]
+    fixed: TestTone<i16>,
+    float: TestTone<f32>,
+}
+
+impl CalibrationTone {
+    /// Convert a peak level in dBFS to a linear gain, clamped to 0dBFS
+    pub fn gain_from_dbfs(dbfs: f32) -> f32 {
+        10f32.powf(dbfs.min(0f32) / 20f32)
+    }
+    /// Get the next sample of a tone at `freq` Hz with a peak level of
+    /// `dbfs`, for the engine using the context `ctx`
+    pub fn next(&mut self, ctx: &dyn GenericContext, freq: f32, dbfs: f32) -> f32 {
+        let gain = Self::gain_from_dbfs(dbfs);
+        let sample_rate = ctx.sample_rate();
+        #[cfg(feature = "fixed")]
+        if ctx.is_fixed_point() {
+            if let Some(ctx) = ContextFxP::maybe_create(sample_rate) {
+                let params = TestToneParams {
+                    freq: FrequencyFxP::saturating_from_num(freq),
+                    level: ScalarFxP::saturating_from_num(gain),
+                };
+                return self.fixed.next(&ctx, (), params).to_num();
+            }
+        }
+        let params = TestToneParams { freq, level: gain };
+        self.float.next(&Context::new(sample_rate as f32), (), params)
+    }
+    /// Restart the tone at zero phase
+    pub fn reset(&mut self) {
+        #[cfg(feature = "fixed")]
+        self.fixed.reset();
+        self.float.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn peak(ctx: &dyn GenericContext, dbfs: f32) -> f32 {
+        let mut tone = CalibrationTone::default();
+        (0..ctx.sample_rate())
+            .map(|_| tone.next(ctx, 1000f32, dbfs).abs())
+            .fold(0f32, f32::max)
+    }
+
+    #[test]
+    fn peak_matches_dbfs() {
+        #[cfg(feature = "fixed")]
+        let fixed = ContextFxP::new_480();
+        let float = Context::new(48000f32);
+        for dbfs in [-6f32, -18f32, -20f32] {
+            let gain = CalibrationTone::gain_from_dbfs(dbfs);
+            #[cfg(feature = "fixed")]
+            assert!((peak(&fixed, dbfs) - gain).abs() <= 1f32 / 8192f32);
+            assert!((peak(&float, dbfs) - gain).abs() <= 1e-5);
+        }
+        assert_eq!(CalibrationTone::gain_from_dbfs(0f32), 1f32);
+        assert_eq!(CalibrationTone::gain_from_dbfs(6f32), 1f32);
+    }
+}
@@ -0,0 +1,151 @@
+//! This module contains the user editable table routing incoming MIDI CCs to
+//! synth parameters (see [CcMap]).  It is saved with the plugin state, so a
+//! hardware controller works the same way in every host, without relying on
+//! the host's MIDI learn.
+
+use nih_plug::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::pluginparams::CulSynthParams;
+use crate::randomize::ParamChange;
+
+/// A single entry in a [CcMap]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct CcMapping {
+    /// The parameter controlled, identified by the CC that controls it by
+    /// default (see [CulSynthParams::param_from_cc]).
+    pub target: u8,
+    /// Ignore the CC until it reaches the parameter's current value, so that
+    /// the parameter doesn't jump when the controller doesn't match it
+    pub soft_takeover: bool,
+    // The last value received for the CC
+    #[serde(skip)]
+    last_cc: Option<u8>,
+    // The last value this mapping set the parameter to.  Soft takeover has
+    // picked up the parameter while it still has this value.
+    #[serde(skip)]
+    last_set: Option<i32>,
+}
+
+/// A table routing MIDI CCs to parameters.  A mapped CC overrides its
+/// default assignment in [culsynth::voice::cc].
+#[derive(Default, Clone, Debug, Serialize, Deserialize)]
+pub struct CcMap {
+    mappings: BTreeMap<u8, CcMapping>,
+}
+
+impl CcMap {
+    /// All of the parameters that a CC may be mapped to, along with the CC
+    /// that identifies them (see [CcMapping::target])
+    pub fn targets(params: &CulSynthParams) -> impl Iterator<Item = (u8, &IntParam)> {
+        (0..128u8).filter_map(|cc| params.param_from_cc(cc).map(|param| (cc, param)))
+    }
+    /// The mappings in the table, in order of CC number
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &CcMapping)> {
+        self.mappings.iter().map(|(cc, mapping)| (*cc, mapping))
+    }
+    /// Is `cc` in the table?
+    pub fn is_mapped(&self, cc: u8) -> bool {
+        self.mappings.contains_key(&cc)
+    }
+    /// Map `cc` to the parameter identified by `target`, replacing any
+    /// existing mapping for `cc`
+    pub fn insert(&mut self, cc: u8, target: u8, soft_takeover: bool) {
+        let mapping = CcMapping {
+            target,
+            soft_takeover,
+            last_cc: None,
+            last_set: None,
+        };
+        self.mappings.insert(cc, mapping);
+    }
+    /// Remove the mapping for `cc`, if there is one
+    pub fn remove(&mut self, cc: u8) {
+        self.mappings.remove(&cc);
+    }
+    /// Enable or disable soft takeover for `cc`
+    pub fn set_soft_takeover(&mut self, cc: u8, soft_takeover: bool) {
+        if let Some(mapping) = self.mappings.get_mut(&cc) {
+            mapping.soft_takeover = soft_takeover;
+        }
+    }
+    /// The change to make to `params` for a CC message, if `cc` is mapped.
+    ///
+    /// With soft takeover, this returns None until the CC either comes within
+    /// one step of the parameter's value or crosses it.  If the parameter is
+    /// changed some other way, it must be picked up again.
+    pub fn apply<'a>(
+        &mut self,
+        cc: u8,
+        value: u8,
+        params: &'a CulSynthParams,
+    ) -> Option<ParamChange<'a>> {
+        let mapping = self.mappings.get_mut(&cc)?;
+        let param = params.param_from_cc(mapping.target)?;
+        let current = param.value();
+        let last_cc = mapping.last_cc.replace(value);
+        if mapping.soft_takeover && mapping.last_set != Some(current) {
+            let target = param.preview_normalized(current);
+            let offset = |value: u8| value as f32 / 127f32 - target;
+            let near = offset(value).abs() <= 1f32 / 127f32;
+            let crossed =
+                last_cc.is_some_and(|last| (offset(last) < 0f32) != (offset(value) < 0f32));
+            if !(near || crossed) {
+                return None;
+            }
+        }
+        let plain = param.preview_plain(value as f32 / 127f32);
+        mapping.last_set = Some(plain);
+        Some(ParamChange::Int(param, plain))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use culsynth::voice::cc;
+
+    /// The plain value set by a change, and the parameter it applies to
+    fn int_change<'a>(change: Option<ParamChange<'a>>) -> Option<(&'a IntParam, i32)> {
+        match change {
+            Some(ParamChange::Int(param, value)) => Some((param, value)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn cc_sets_mapped_param() {
+        let params = CulSynthParams::default();
+        let mut map = CcMap::default();
+        map.insert(1, cc::FILT_RESONANCE, false);
+        assert!(map.is_mapped(1) && !map.is_mapped(2));
+        let (param, value) = int_change(map.apply(1, 127, &params)).unwrap();
+        assert!(std::ptr::eq(param, &params.filt.res));
+        assert_eq!(value, param.preview_plain(1f32));
+        let (_, value) = int_change(map.apply(1, 0, &params)).unwrap();
+        assert_eq!(value, param.preview_plain(0f32));
+        assert!(map.apply(2, 0, &params).is_none());
+        map.remove(1);
+        assert!(map.apply(1, 0, &params).is_none());
+    }
+
+    #[test]
+    fn soft_takeover() {
+        // The resonance defaults to 0, so the CC picks it up once it comes
+        // back down to the bottom of its range...
+        let params = CulSynthParams::default();
+        let mut map = CcMap::default();
+        map.insert(7, cc::FILT_RESONANCE, true);
+        assert!(map.apply(7, 100, &params).is_none());
+        assert!(map.apply(7, 50, &params).is_none());
+        assert!(int_change(map.apply(7, 1, &params)).is_some());
+        // ...and the cutoff defaults to the top, so the CC picks it up when
+        // it crosses the parameter's value
+        map.insert(7, cc::FILT_CUTOFF, true);
+        assert!(map.apply(7, 0, &params).is_none());
+        assert!(map.apply(7, 64, &params).is_none());
+        let (param, _) = int_change(map.apply(7, 127, &params)).unwrap();
+        assert!(std::ptr::eq(param, &params.filt.cutoff));
+    }
+}
@@ -0,0 +1,287 @@
+//! Diagnostic event logging for the audio thread.
+//!
+//! The audio thread can't print or write to a file without risking xruns,
+//! so events are instead pushed to a fixed size, lock-free ring buffer
+//! ([DiagLog]) and written out by a background thread.  Pushing an event
+//! never blocks or allocates - if the buffer is full, the event is dropped
+//! and counted instead.
+//!
+//! Logging is disabled unless the `CULSYNTH_DIAG` environment variable is set
+//! when the plugin is initialized (see [init_from_env]).  If it is set to a
+//! path, the log is written to that file, otherwise it is written to stderr.
+
+use culsynth::util::min_size;
+use std::cell::UnsafeCell;
+use std::io::Write;
+use std::sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use std::sync::atomic::{AtomicBool, AtomicUsize};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+/// The environment variable that enables diagnostic logging
+pub const DIAG_ENV_VAR: &str = "CULSYNTH_DIAG";
+
+/// The number of entries in the global [DiagLog]
+const DIAG_CAPACITY: usize = 1024;
+
+/// How long the background thread waits between draining the log
+const DRAIN_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The type of a [DiagEntry]
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[repr(u8)]
+pub enum DiagKind {
+    /// A note was started.  The data is the note and velocity.
+    #[default]
+    NoteOn,
+    /// A note was released.  The data is the note and velocity.
+    NoteOff,
+    /// A sounding voice was stolen for a new note.  The data is the index of
+    /// the voice, the note it was playing, and the new note.
+    VoiceSteal,
+    /// A filter output saturated.  The data is the index of the voice.
+    FilterOverflow,
+    /// A floating point device reset its state after it became NaN or
+    /// infinite (see [culsynth::voice::Voice::take_state_reset]).  There is
+    /// no data.
+    StateReset,
+}
+
+impl DiagKind {
+    /// The name of this kind of entry
+    pub fn to_str(&self) -> &'static str {
+        match self {
+            Self::NoteOn => "NoteOn",
+            Self::NoteOff => "NoteOff",
+            Self::VoiceSteal => "VoiceSteal",
+            Self::FilterOverflow => "FilterOverflow",
+            Self::StateReset => "StateReset",
+        }
+    }
+}
+
+/// A single diagnostic event
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct DiagEntry {
+    /// The time of the event, in microseconds since the log was created
+    pub timestamp: u64,
+    /// The type of event
+    pub kind: DiagKind,
+    /// Data specific to the type of event (see [DiagKind])
+    pub data: [u8; 16],
+}
+
+impl std::fmt::Display for DiagEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let len = match self.kind {
+            DiagKind::NoteOn | DiagKind::NoteOff => 2,
+            DiagKind::VoiceSteal => 3,
+            DiagKind::FilterOverflow => 1,
+            DiagKind::StateReset => 0,
+        };
+        write!(
+            f,
+            "[{:>12}us] {} {:?}",
+            self.timestamp,
+            self.kind.to_str(),
+            &self.data[..len]
+        )
+    }
+}
+
+/// A lock-free ring buffer of [DiagEntry]s, with a single consumer.
+///
+/// Producers never block: if the buffer is full, or another thread is in
+/// the middle of pushing an entry, the entry is dropped and counted (see
+/// [DiagLog::take_dropped]).
+pub struct DiagLog {
+    entries: Box<[UnsafeCell<DiagEntry>]>,
+    /// The index of the next entry to write
+    head: AtomicUsize,
+    /// The index of the next entry to read
+    tail: AtomicUsize,
+    /// Set while an entry is being pushed
+    pushing: AtomicBool,
+    dropped: AtomicUsize,
+    start: Instant,
+}
+
+// Entries are only written by the producer holding `pushing` before `head`
+// is published, and only read by the consumer before `tail` is published.
+unsafe impl Sync for DiagLog {}
+
+impl DiagLog {
+    /// Create a new log holding up to `capacity` entries, or `None` if
+    /// `capacity` is not a power of two
+    pub fn new(capacity: usize) -> Option<Self> {
+        if !capacity.is_power_of_two() {
+            return None;
+        }
+        Some(Self {
+            entries: (0..capacity).map(|_| UnsafeCell::new(DiagEntry::default())).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            pushing: AtomicBool::new(false),
+            dropped: AtomicUsize::new(0),
+            start: Instant::now(),
+        })
+    }
+    /// The maximum number of entries this log can hold
+    pub fn capacity(&self) -> usize {
+        self.entries.len()
+    }
+    /// Record an event of type `kind`, with the given data (truncated to 16
+    /// bytes).  Returns false if the entry was dropped.
+    pub fn push(&self, kind: DiagKind, data: &[u8]) -> bool {
+        if self.pushing.swap(true, Acquire) {
+            self.dropped.fetch_add(1, Relaxed);
+            return false;
+        }
+        let head = self.head.load(Relaxed);
+        let pushed = if head.wrapping_sub(self.tail.load(Acquire)) < self.capacity() {
+            let mut entry = DiagEntry {
+                timestamp: self.start.elapsed().as_micros() as u64,
+                kind,
+                data: [0; 16],
+            };
+            let len = min_size(&[data.len(), entry.data.len()]);
+            entry.data[..len].copy_from_slice(&data[..len]);
+            // Safety: the consumer doesn't read this slot until head is
+            // advanced past it, and `pushing` excludes any other producer
+            unsafe {
+                *self.entries[head & (self.capacity() - 1)].get() = entry;
+            }
+            self.head.store(head.wrapping_add(1), Release);
+            true
+        } else {
+            self.dropped.fetch_add(1, Relaxed);
+            false
+        };
+        self.pushing.store(false, Release);
+        pushed
+    }
+    /// Remove the oldest entry from the log.  This must only be called from
+    /// one thread at a time.
+    pub fn pop(&self) -> Option<DiagEntry> {
+        let tail = self.tail.load(Relaxed);
+        if tail == self.head.load(Acquire) {
+            return None;
+        }
+        // Safety: the producer doesn't write this slot until tail is
+        // advanced past it
+        let entry = unsafe { *self.entries[tail & (self.capacity() - 1)].get() };
+        self.tail.store(tail.wrapping_add(1), Release);
+        Some(entry)
+    }
+    /// The number of entries dropped since the last call to this function
+    pub fn take_dropped(&self) -> usize {
+        self.dropped.swap(0, Relaxed)
+    }
+    /// Write all of the entries in the log to `out`, followed by a note of
+    /// how many were dropped (if any)
+    pub fn drain_to(&self, out: &mut dyn Write) -> std::io::Result<()> {
+        while let Some(entry) = self.pop() {
+            writeln!(out, "{}", entry)?;
+        }
+        let dropped = self.take_dropped();
+        if dropped > 0 {
+            writeln!(out, "({} diagnostic entries dropped)", dropped)?;
+        }
+        out.flush()
+    }
+}
+
+static DIAG_LOG: OnceLock<DiagLog> = OnceLock::new();
+
+/// Enable diagnostic logging if the `CULSYNTH_DIAG` environment variable is
+/// set, starting a background thread to drain the log.  This does nothing if
+/// logging has already been enabled.
+pub fn init_from_env() {
+    let Some(dest) = std::env::var_os(DIAG_ENV_VAR) else {
+        return;
+    };
+    let mut created = false;
+    let log = DIAG_LOG.get_or_init(|| {
+        created = true;
+        DiagLog::new(DIAG_CAPACITY).unwrap()
+    });
+    if !created {
+        return;
+    }
+    let file = if dest.is_empty() || dest == "1" {
+        None
+    } else {
+        std::fs::File::create(&dest).ok()
+    };
+    let mut out: Box<dyn Write + Send> = match file {
+        Some(file) => Box::new(file),
+        None => Box::new(std::io::stderr()),
+    };
+    std::thread::spawn(move || loop {
+        if log.drain_to(out.as_mut()).is_err() {
+            return;
+        }
+        std::thread::sleep(DRAIN_INTERVAL);
+    });
+}
+
+/// Record an event in the global log, if diagnostic logging is enabled
+pub fn record(kind: DiagKind, data: &[u8]) {
+    if let Some(log) = DIAG_LOG.get() {
+        log.push(kind, data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn capacity() {
+        assert!(DiagLog::new(1000).is_none());
+        let log = DiagLog::new(4).unwrap();
+        for i in 0..6u8 {
+            assert_eq!(log.push(DiagKind::NoteOn, &[i, 100]), i < 4);
+        }
+        assert_eq!(log.take_dropped(), 2);
+        assert_eq!(log.pop().unwrap().data[..2], [0, 100]);
+        assert!(log.push(DiagKind::NoteOff, &[4]));
+        let notes: Vec<u8> = std::iter::from_fn(|| log.pop()).map(|e| e.data[0]).collect();
+        assert_eq!(notes, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn slow_consumer_does_not_block_producer() {
+        const NUM_ENTRIES: usize = 4096;
+        let log = Arc::new(DiagLog::new(64).unwrap());
+        let done = Arc::new(AtomicBool::new(false));
+        let consumer = {
+            let (log, done) = (log.clone(), done.clone());
+            std::thread::spawn(move || {
+                let mut popped = Vec::new();
+                while !done.load(Acquire) {
+                    popped.extend(log.pop());
+                    std::thread::sleep(Duration::from_millis(10));
+                }
+                popped.extend(std::iter::from_fn(|| log.pop()));
+                popped
+            })
+        };
+        let mut pushed = 0;
+        for i in 0..NUM_ENTRIES {
+            pushed += log.push(DiagKind::NoteOn, &i.to_le_bytes()) as usize;
+        }
+        // The consumer only takes one entry every 10ms, so the producer must
+        // have run ahead and dropped entries rather than waiting for it
+        let dropped = log.take_dropped();
+        assert_eq!(pushed + dropped, NUM_ENTRIES);
+        assert!(dropped >= NUM_ENTRIES - 2 * log.capacity(), "{}", dropped);
+        done.store(true, Release);
+        let popped = consumer.join().unwrap();
+        assert_eq!(popped.len(), pushed);
+        // What did get through arrives in order
+        let index = |e: &DiagEntry| usize::from_le_bytes(e.data[..8].try_into().unwrap());
+        assert!(popped.windows(2).all(|w| index(&w[0]) < index(&w[1])));
+    }
+}
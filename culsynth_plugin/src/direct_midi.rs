@@ -0,0 +1,172 @@
+//! Direct MIDI input from a system MIDI port, bypassing the host.
+//!
+//! This allows the plugin (and especially the standalone build) to be played
+//! from a hardware controller without routing MIDI through a DAW.  Messages
+//! from the selected port are converted to [NoteEvent]s and sent over the same
+//! channel as the onscreen keyboard, so the audio thread processes them just
+//! like events from the host.
+//!
+//! Port access is abstracted behind [MidiInputPorts] so that the selector can
+//! be tested without any MIDI hardware; [MidirPorts] is the implementation
+//! backed by the `midir` crate.
+
+use crate::voicealloc::NoteEvent;
+use crate::ContextReader;
+use culsynth::util::MidiEvent;
+use nih_plug::prelude::*;
+use nih_plug_egui::egui;
+use std::sync::mpsc::SyncSender;
+
+/// A callback invoked with each raw MIDI message received on a port
+pub type MidiCallback = Box<dyn FnMut(&[u8]) + Send>;
+
+/// A source of MIDI input ports
+pub trait MidiInputPorts {
+    /// An open connection to a port, which is closed when dropped
+    type Connection;
+    /// The names of the currently available input ports
+    fn port_names(&mut self) -> Vec<String>;
+    /// Connect to the port at index `port` in [MidiInputPorts::port_names].
+    /// `callback` is called (on a background thread) for each message.
+    fn connect(
+        &mut self,
+        port: usize,
+        callback: MidiCallback,
+    ) -> Result<Self::Connection, &'static str>;
+}
+
+/// System MIDI input ports, via `midir`
+#[derive(Default)]
+pub struct MidirPorts;
+
+impl MidirPorts {
+    fn input() -> Result<midir::MidiInput, &'static str> {
+        midir::MidiInput::new(crate::NAME).map_err(|_| "Could not initialize MIDI input")
+    }
+}
+
+impl MidiInputPorts for MidirPorts {
+    type Connection = midir::MidiInputConnection<()>;
+    fn port_names(&mut self) -> Vec<String> {
+        let Ok(input) = Self::input() else {
+            return Vec::new();
+        };
+        input.ports().iter().filter_map(|port| input.port_name(port).ok()).collect()
+    }
+    fn connect(
+        &mut self,
+        port: usize,
+        mut callback: MidiCallback,
+    ) -> Result<Self::Connection, &'static str> {
+        let input = Self::input()?;
+        let port = input.ports().get(port).cloned().ok_or("No such MIDI port")?;
+        // midir reads the port on its own thread and invokes our callback
+        input
+            .connect(
+                &port,
+                "culsynth-direct-midi",
+                move |_timestamp, bytes, _| callback(bytes),
+                (),
+            )
+            .map_err(|_| "Could not connect to MIDI port")
+    }
+}
+
+/// Lists the available MIDI input ports and manages the connection to the
+/// selected one.  While a port is connected, the plugin is in direct MIDI
+/// mode and ignores MIDI from the host.
+pub struct MidiPortSelector<P: MidiInputPorts> {
+    ports: P,
+    names: Vec<String>,
+    selected: Option<usize>,
+    connection: Option<P::Connection>,
+    midi_tx: SyncSender<NoteEvent>,
+}
+
+impl<P: MidiInputPorts> MidiPortSelector<P> {
+    /// Create a new selector, which sends events from the selected port to
+    /// `midi_tx`.  No port is initially selected.
+    pub fn new(mut ports: P, midi_tx: SyncSender<NoteEvent>) -> Self {
+        let names = ports.port_names();
+        Self {
+            ports,
+            names,
+            selected: None,
+            connection: None,
+            midi_tx,
+        }
+    }
+    /// Update the list of available ports.  If the connected port is still
+    /// available the connection is kept, and the selection follows the port
+    /// to its new index in the list; otherwise the port is disconnected.
+    pub fn refresh(&mut self) {
+        let selected_name = self.selected.and_then(|idx| self.names.get(idx).cloned());
+        self.names = self.ports.port_names();
+        self.selected = selected_name.and_then(|name| self.names.iter().position(|n| *n == name));
+        if self.selected.is_none() {
+            self.connection = None;
+        }
+    }
+    /// The names of the available ports, as of the last refresh
+    pub fn port_names(&self) -> &[String] {
+        &self.names
+    }
+    /// The index of the connected port, if any
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+    /// Connect to the port at index `port`, or disconnect if `None`.  Any
+    /// existing connection is closed first.
+    pub fn select(&mut self, port: Option<usize>) -> Result<(), &'static str> {
+        self.connection = None;
+        self.selected = None;
+        let Some(port) = port else {
+            return Ok(());
+        };
+        let midi_tx = self.midi_tx.clone();
+        let callback = Box::new(move |bytes: &[u8]| {
+            let Some((&status, data)) = bytes.split_first() else {
+                return;
+            };
+            let data_byte = |idx: usize| data.get(idx).copied().unwrap_or(0);
+            let event = MidiEvent::parse(status, data_byte(0), data_byte(1));
+            if let Some(event) = event.and_then(NoteEvent::from_midi) {
+                if let Err(e) = midi_tx.try_send(event) {
+                    nih_error!("{}", e);
+                }
+            }
+        });
+        self.connection = Some(self.ports.connect(port, callback)?);
+        self.selected = Some(port);
+        Ok(())
+    }
+    /// Draw the port selection combo box in the settings panel, and update
+    /// the plugin's direct MIDI mode to match the selection
+    pub fn draw(&mut self, ui: &mut egui::Ui, context: &ContextReader) {
+        const NONE: &str = "None (MIDI from host)";
+        let mut new_selection = self.selected;
+        ui.horizontal(|ui| {
+            ui.label("Direct MIDI Input");
+            let selected_text =
+                self.selected.and_then(|idx| self.names.get(idx)).map_or(NONE, String::as_str);
+            egui::ComboBox::from_id_source("MidiPortSelector")
+                .selected_text(selected_text)
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut new_selection, None, NONE);
+                    for (idx, name) in self.names.iter().enumerate() {
+                        ui.selectable_value(&mut new_selection, Some(idx), name);
+                    }
+                });
+            if ui.button("Refresh").clicked() {
+                self.refresh();
+                new_selection = self.selected;
+            }
+        });
+        if new_selection != self.selected {
+            if let Err(e) = self.select(new_selection) {
+                nih_error!("{}", e);
+            }
+        }
+        context.set_direct_midi(self.selected.is_some());
+    }
+}
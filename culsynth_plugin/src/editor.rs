@@ -1,15 +1,24 @@
+use crate::bench::BenchmarkRunner;
+use crate::ccmap::CcMap;
 use crate::pluginparams::{
-    CulSynthParams, EnvPluginParams, FiltPluginParams, LfoPluginParams, ModMatrixPluginParams,
-    OscPluginParams, RingModPluginParams,
+    ChordPluginParams, CompressorPluginParams, CulSynthParams, EnvPluginParams, FiltPluginParams,
+    LfoPluginParams, LimiterPluginParams, ModMatrixPluginParams, OscPluginParams,
+    RingModPluginParams,
+};
+use crate::randomize::{PatchRandomizer, PatchSection};
+use crate::theme;
+use crate::voicealloc::{
+    MonoMode, NoteEvent, Scale, SpreadMode, SynthConfig, VoiceAllocator, PITCH_CLASSES,
 };
-use crate::voicealloc::{MonoSynth, PolySynth, VoiceAllocator};
 use crate::{ContextReader, VoiceMode};
-use culsynth::context::{Context, ContextFxP};
-use culsynth::devices::LfoWave;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::{EnvStage, LfoWave, OscRatio, ResetMode};
 use culsynth::voice::modulation::{ModDest, ModSrc};
 use egui::widgets;
 use nih_plug::prelude::*;
-use nih_plug_egui::{create_egui_editor, egui, EguiState};
+use nih_plug_egui::resizable_window::ResizableWindow;
+use nih_plug_egui::{create_egui_editor, egui, widgets as nih_widgets, EguiState};
 use std::sync::{
     mpsc::{Receiver, SyncSender},
     Arc, Mutex,
@@ -20,20 +29,40 @@ mod param_widget;
 use param_widget::{param_slider, ParamWidget};
 
 // Makes sense to also define this here, makes it a bit easier to keep track of
+// (the window is resizable, and the size the user chooses is persisted along
+// with the rest of the editor state)
 pub(crate) fn default_state() -> Arc<EguiState> {
     EguiState::from_size(1000, 800)
 }
 
+/// The smallest size the editor window can be resized to
+const MIN_SIZE: egui::Vec2 = egui::vec2(480f32, 320f32);
+
+/// The width taken up by a separator between two sections of the main
+/// controls, not including the item spacing on either side (this is the
+/// default spacing of an [egui::Separator])
+const SEPARATOR_SPACING: f32 = 6f32;
+
 /// Struct to hold the global state information for the plugin editor (GUI).
 struct CulSynthEditor {
     params: Arc<CulSynthParams>,
-    midi_channel: SyncSender<i8>,
+    midi_channel: SyncSender<NoteEvent>,
     synth_channel: SyncSender<Box<dyn VoiceAllocator>>,
     cc_receiver: Mutex<Receiver<(u8, u8)>>,
     context: ContextReader,
     kbd_panel: kbd::KbdPanel,
+    benchmark: BenchmarkRunner,
+    randomizer: PatchRandomizer,
+    #[cfg(feature = "midir")]
+    midi_ports: crate::direct_midi::MidiPortSelector<crate::direct_midi::MidirPorts>,
     nrpn: u16,
     show_mod_matrix: bool,
+    modmatrix_text: String,
+    modmatrix_error: Option<&'static str>,
+    show_mod_monitor: bool,
+    show_cc_map: bool,
+    /// The CC number and target of the next mapping to add to the CC map
+    new_cc_mapping: (u8, u8),
     show_settings: bool,
     show_about: bool,
 }
@@ -41,25 +70,37 @@ struct CulSynthEditor {
 impl CulSynthEditor {
     pub fn new(
         p: Arc<CulSynthParams>,
-        midi_tx: SyncSender<i8>,
+        midi_tx: SyncSender<NoteEvent>,
         synth_tx: SyncSender<Box<dyn VoiceAllocator>>,
         cc_rx: Receiver<(u8, u8)>,
         ctx: ContextReader,
     ) -> Self {
         CulSynthEditor {
+            #[cfg(feature = "midir")]
+            midi_ports: crate::direct_midi::MidiPortSelector::new(
+                Default::default(),
+                midi_tx.clone(),
+            ),
             params: p,
             midi_channel: midi_tx,
             synth_channel: synth_tx,
             cc_receiver: Mutex::new(cc_rx),
             context: ctx,
             kbd_panel: Default::default(),
+            benchmark: Default::default(),
+            randomizer: Default::default(),
             show_mod_matrix: false,
+            modmatrix_text: String::new(),
+            modmatrix_error: None,
+            show_mod_monitor: false,
+            show_cc_map: false,
+            new_cc_mapping: (0, culsynth::voice::cc::FILT_CUTOFF),
             show_settings: false,
             show_about: false,
             nrpn: 0,
         }
     }
-    fn draw_status_bar(&mut self, egui_ctx: &egui::Context) {
+    fn draw_status_bar(&mut self, egui_ctx: &egui::Context, setter: &ParamSetter) {
         egui::TopBottomPanel::top("status")
             .frame(egui::Frame::none().fill(egui::Color32::from_gray(32)))
             .max_height(20f32)
@@ -74,9 +115,18 @@ impl CulSynthEditor {
                         if ui.button("Mod Matrix").clicked() {
                             self.show_mod_matrix = true;
                         }
+                        if ui.button("Mod Monitor").clicked() {
+                            self.show_mod_monitor = true;
+                        }
+                        if ui.button("CC Map").clicked() {
+                            self.show_cc_map = true;
+                        }
                         if ui.button("About").clicked() {
                             self.show_about = true;
                         }
+                        ui.menu_button("Randomize", |ui| {
+                            self.draw_randomize_menu(ui, setter);
+                        });
                     });
                     columns[0].expand_to_include_x(third);
                     columns[1].expand_to_include_x(width - third);
@@ -103,39 +153,118 @@ impl CulSynthEditor {
                 });
             });
     }
+    fn draw_randomize_menu(&mut self, ui: &mut egui::Ui, setter: &ParamSetter) {
+        for section in PatchSection::sections() {
+            let mut locked = self.randomizer.is_locked(*section);
+            if ui.checkbox(&mut locked, format!("Lock {}", section.to_str())).changed() {
+                self.randomizer.set_locked(*section, locked);
+            }
+        }
+        ui.separator();
+        if ui.button("Randomize Patch").clicked() {
+            self.randomizer.randomize(&self.params, setter);
+            ui.close_menu();
+        }
+    }
     fn draw_main_controls(&mut self, setter: &ParamSetter, ui: &mut egui::Ui) {
         ui.spacing_mut().slider_width = 130f32;
+        let snapshot = self.context.voice_snapshot();
+        // Keep the active envelope stages up to date while a note is playing
+        if snapshot.env_vca_stage != EnvStage::Idle || snapshot.env_vcf_stage != EnvStage::Idle {
+            ui.ctx().request_repaint();
+        }
+        let osc2 = param_widget::osc_with_sync(
+            &self.params.osc2,
+            &self.params.osc_sync,
+            &self.params.osc_ratio,
+        );
+        let filt = param_widget::filt_with_response(&self.params.filt, self.context.get());
+        let env_vcf = param_widget::env_with_stage(&self.params.env_vcf, snapshot.env_vcf_stage);
+        let env_vca = param_widget::env_with_stage(&self.params.env_vca, snapshot.env_vca_stage);
         ui.vertical(|ui| {
-            ui.horizontal(|ui| {
-                self.params.osc1.draw_on(ui, setter, "Oscillator 1");
-                ui.separator();
-                param_widget::osc_with_sync(&self.params.osc2, &self.params.osc_sync).draw_on(
-                    ui,
-                    setter,
-                    "Oscillator 2",
-                );
-                ui.separator();
-                self.params.ringmod.draw_on(ui, setter, "Mixer/Ring Modulator");
-            });
+            Self::draw_reflow(
+                ui,
+                setter,
+                &[
+                    (&self.params.osc1, "Oscillator 1"),
+                    (&osc2, "Oscillator 2"),
+                    (&self.params.ringmod, "Mixer/Ring Modulator"),
+                ],
+            );
             ui.separator();
-            ui.horizontal(|ui| {
-                self.params.filt.draw_on(ui, setter, "Filter");
-                ui.separator();
-                self.params.lfo1.draw_on(ui, setter, "LFO 1");
-                ui.separator();
-                self.params.lfo2.draw_on(ui, setter, "LFO 2");
-            });
+            Self::draw_reflow(
+                ui,
+                setter,
+                &[
+                    (&filt, "Filter"),
+                    (&self.params.lfo1, "LFO 1"),
+                    (&self.params.lfo2, "LFO 2"),
+                ],
+            );
             ui.separator();
-            ui.horizontal(|ui| {
-                self.params.env_vcf.draw_on(ui, setter, "Filter Envelope");
-                ui.separator();
-                self.params.env_vca.draw_on(ui, setter, "Amplifier Envelope");
-                ui.separator();
-                self.params.env1.draw_on(ui, setter, "Mod Envelope 1");
+            Self::draw_reflow(
+                ui,
+                setter,
+                &[
+                    (&env_vcf, "Filter Envelope"),
+                    (&env_vca, "Amplifier Envelope"),
+                    (&self.params.env1, "Mod Envelope 1"),
+                    (&self.params.env2, "Mod Envelope 2"),
+                ],
+            );
+        });
+    }
+    /// Draw `sections` side by side, wrapping them onto as many rows as it
+    /// takes for each row to fit in the available width.
+    ///
+    /// The width of a section isn't known until it has been drawn, so the
+    /// rows are planned using the widths from the last frame (kept in egui's
+    /// memory).  If any of them changed, another frame is requested so that
+    /// the layout settles straight away.
+    fn draw_reflow(ui: &mut egui::Ui, setter: &ParamSetter, sections: &[(&dyn ParamWidget, &str)]) {
+        let gap = SEPARATOR_SPACING + 2f32 * ui.spacing().item_spacing.x;
+        let available = ui.available_width();
+        let ids: Vec<egui::Id> = sections.iter().map(|(_, label)| ui.id().with(label)).collect();
+        let widths: Vec<f32> = ids
+            .iter()
+            .map(|id| ui.data(|data| data.get_temp(*id)).unwrap_or(0f32))
+            .collect();
+        let mut rows: Vec<Vec<usize>> = Vec::new();
+        let mut row_width = 0f32;
+        for (i, width) in widths.iter().enumerate() {
+            match rows.last_mut() {
+                Some(row) if row_width + gap + width <= available => {
+                    row.push(i);
+                    row_width += gap + width;
+                }
+                _ => {
+                    rows.push(vec![i]);
+                    row_width = *width;
+                }
+            }
+        }
+        let mut changed = false;
+        for (row_num, row) in rows.iter().enumerate() {
+            if row_num > 0 {
                 ui.separator();
-                self.params.env2.draw_on(ui, setter, "Mod Envelope 2");
+            }
+            ui.horizontal(|ui| {
+                for (col, &i) in row.iter().enumerate() {
+                    if col > 0 {
+                        ui.separator();
+                    }
+                    let (widget, label) = sections[i];
+                    let rect = ui.vertical(|ui| widget.draw_on(ui, setter, label)).response.rect;
+                    if (rect.width() - widths[i]).abs() > 0.5f32 {
+                        ui.data_mut(|data| data.insert_temp(ids[i], rect.width()));
+                        changed = true;
+                    }
+                }
             });
-        });
+        }
+        if changed {
+            ui.ctx().request_repaint();
+        }
     }
     fn draw_settings(
         ui: &mut egui::Ui,
@@ -146,7 +275,10 @@ impl CulSynthEditor {
         let (sr, fixed_point) = context.get();
         let context_strs = ["32 bit float", "16 bit fixed"];
         let fixed_point_idx: usize = if fixed_point { 1 } else { 0 };
-        let fixed_context = ContextFxP::maybe_create(sr);
+        #[cfg(feature = "fixed")]
+        let fixed_supported = ContextFxP::maybe_create(sr).is_some();
+        #[cfg(not(feature = "fixed"))]
+        let fixed_supported = false;
         let mut new_is_fixed = fixed_point;
         let mut new_voice_mode = voice_mode;
         ui.vertical(|ui| {
@@ -161,19 +293,17 @@ impl CulSynthEditor {
                 }
             });
             */
-            ui.label(format!(
-                "Sample Rate: {}.{} kHz",
-                sr / 1000,
-                (sr % 1000) / 100
-            ));
+            ui.label(context.status_line());
             ui.horizontal(|ui| {
                 egui::ComboBox::from_id_source("FloatFixedSelect")
                     .selected_text(context_strs[fixed_point_idx])
                     .show_ui(ui, |ui| {
                         ui.selectable_value(&mut new_is_fixed, false, context_strs[0]);
-                        ui.add_enabled_ui(fixed_context.is_some(), |ui| {
-                            ui.selectable_value(&mut new_is_fixed, true, context_strs[1]);
-                        });
+                        if cfg!(feature = "fixed") {
+                            ui.add_enabled_ui(fixed_supported, |ui| {
+                                ui.selectable_value(&mut new_is_fixed, true, context_strs[1]);
+                            });
+                        }
                     });
                 egui::ComboBox::from_id_source("MonoPoly")
                     .selected_text(voice_mode.to_str())
@@ -188,31 +318,290 @@ impl CulSynthEditor {
                             VoiceMode::Poly16,
                             VoiceMode::Poly16.to_str(),
                         );
+                        ui.selectable_value(
+                            &mut new_voice_mode,
+                            VoiceMode::Chord,
+                            VoiceMode::Chord.to_str(),
+                        );
                     });
             });
         });
         if new_is_fixed != fixed_point || new_voice_mode != voice_mode {
-            if new_is_fixed {
-                fixed_context.map(|ctx| {
-                    let ret: Box<dyn VoiceAllocator> = match new_voice_mode {
-                        VoiceMode::Mono => Box::new(MonoSynth::<i16>::new(ctx)),
-                        VoiceMode::Poly16 => Box::new(PolySynth::<i16>::new(ctx, 16)),
-                    };
-                    ret
-                })
-            } else {
-                Some(match new_voice_mode {
-                    VoiceMode::Mono => Box::new(MonoSynth::<f32>::new(Context::new(sr as f32))),
-                    VoiceMode::Poly16 => {
-                        Box::new(PolySynth::<f32>::new(Context::new(sr as f32), 16))
-                    }
-                })
-            }
+            SynthConfig::new(sr)
+                .with_voice_mode(new_voice_mode)
+                .with_fixed_point(new_is_fixed)
+                .build()
         } else {
             None
         }
     }
-    fn draw_modmatrix(matrix: &ModMatrixPluginParams, ui: &mut egui::Ui, setter: &ParamSetter) {
+    fn draw_mono_mode(param: &IntParam, ui: &mut egui::Ui, setter: &ParamSetter) {
+        ui.horizontal(|ui| {
+            ui.label("Mono Mode");
+            let cur_mode = param.value();
+            for mode in MonoMode::modes() {
+                if ui.selectable_label(cur_mode == *mode as i32, mode.to_str()).clicked() {
+                    setter.begin_set_parameter(param);
+                    setter.set_parameter(param, *mode as i32);
+                    setter.end_set_parameter(param);
+                }
+            }
+        });
+    }
+    fn draw_poly_spread(params: &CulSynthParams, ui: &mut egui::Ui, setter: &ParamSetter) {
+        ui.horizontal(|ui| {
+            ui.label("Stereo Spread");
+            ui.add(nih_widgets::ParamSlider::for_param(
+                &params.poly_spread,
+                setter,
+            ));
+            let cur_mode = params.poly_spread_mode.value();
+            for mode in SpreadMode::modes() {
+                if ui.selectable_label(cur_mode == *mode as i32, mode.to_str()).clicked() {
+                    setter.begin_set_parameter(&params.poly_spread_mode);
+                    setter.set_parameter(&params.poly_spread_mode, *mode as i32);
+                    setter.end_set_parameter(&params.poly_spread_mode);
+                }
+            }
+        });
+    }
+    fn draw_dephase(params: &CulSynthParams, ui: &mut egui::Ui, setter: &ParamSetter) {
+        ui.horizontal(|ui| {
+            let mut dephase = params.dephase.value();
+            if ui.checkbox(&mut dephase, "De-phase Voices").changed() {
+                Self::set_bool_param(&params.dephase, setter, dephase);
+            }
+            ui.add_enabled_ui(dephase, |ui| {
+                let mut randomize = params.phase_randomize.value();
+                if ui.checkbox(&mut randomize, "Random Phase").changed() {
+                    Self::set_bool_param(&params.phase_randomize, setter, randomize);
+                }
+            });
+        });
+    }
+    fn draw_chord_settings(chord: &ChordPluginParams, ui: &mut egui::Ui, setter: &ParamSetter) {
+        ui.label("Chord Memory");
+        egui::Grid::new("ChordSettings").show(ui, |ui| {
+            ui.label("Notes");
+            ui.add(nih_widgets::ParamSlider::for_param(&chord.size, setter));
+            ui.end_row();
+            for idx in 0..chord.size() {
+                ui.label(format!("Note {}", idx + 1));
+                ui.add(nih_widgets::ParamSlider::for_param(chord.note(idx), setter));
+                ui.end_row();
+            }
+        });
+    }
+    fn draw_scale_settings(param: &IntParam, ui: &mut egui::Ui, setter: &ParamSetter) {
+        let set_mask = |mask: u16| {
+            setter.begin_set_parameter(param);
+            setter.set_parameter(param, mask as i32);
+            setter.end_set_parameter(param);
+        };
+        let mask = param.value() as u16;
+        ui.horizontal(|ui| {
+            ui.label("Scale Quantize");
+            for scale in Scale::scales() {
+                if ui.selectable_label(mask == scale.mask(), scale.to_str()).clicked() {
+                    set_mask(scale.mask());
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            for (idx, name) in PITCH_CLASSES.iter().enumerate() {
+                let mut enabled = mask & (1 << idx) != 0;
+                if ui.checkbox(&mut enabled, *name).changed() {
+                    set_mask(mask ^ (1 << idx));
+                }
+            }
+        });
+    }
+    fn draw_voice_capture(ui: &mut egui::Ui, params: &CulSynthParams, context: &ContextReader) {
+        ui.horizontal(|ui| {
+            if ui.button("Capture Voice State").clicked() {
+                context.request_voice_capture();
+            }
+            let captured = params.voice_state.read().is_ok_and(|saved| saved.voice.is_some());
+            if captured {
+                ui.label("Voice state saved with the plugin state");
+            }
+        });
+    }
+    fn draw_test_tone(ui: &mut egui::Ui, context: &ContextReader) {
+        egui::CollapsingHeader::new("Calibration Tone").show(ui, |ui| {
+            let (mut enabled, mut freq, mut dbfs) = context.test_tone();
+            let mut changed =
+                ui.checkbox(&mut enabled, "Replace the output with a test tone").changed();
+            ui.horizontal(|ui| {
+                changed |= ui
+                    .add(
+                        egui::DragValue::new(&mut freq).clamp_range(20f32..=16000f32).suffix(" Hz"),
+                    )
+                    .changed();
+                changed |= ui
+                    .add(egui::DragValue::new(&mut dbfs).clamp_range(-60f32..=0f32).suffix(" dBFS"))
+                    .changed();
+            });
+            if changed {
+                context.set_test_tone(enabled, freq, dbfs);
+            }
+        });
+    }
+    fn draw_benchmark(ui: &mut egui::Ui, context: &ContextReader, bench: &mut BenchmarkRunner) {
+        let voice_count = SynthConfig::DEFAULT_VOICE_COUNT;
+        ui.horizontal(|ui| {
+            let running = bench.running();
+            if ui.add_enabled(!running, egui::Button::new("CPU Benchmark")).clicked() {
+                bench.start(voice_count, context.sample_rate());
+            }
+            if running {
+                ui.spinner();
+                ui.ctx().request_repaint();
+            } else if let Some(percent) = bench.result() {
+                ui.label(format!(
+                    "{} voices: {:.1}% of real time",
+                    voice_count, percent
+                ));
+            }
+        });
+    }
+    fn draw_sidechain_settings(
+        params: &CulSynthParams,
+        context: &ContextReader,
+        ui: &mut egui::Ui,
+        setter: &ParamSetter,
+    ) {
+        ui.label("Sidechain Envelope Follower");
+        egui::Grid::new("SidechainSettings").show(ui, |ui| {
+            ui.label("Attack");
+            ui.add(nih_widgets::ParamSlider::for_param(
+                &params.sidechain_attack,
+                setter,
+            ));
+            ui.end_row();
+            ui.label("Release");
+            ui.add(nih_widgets::ParamSlider::for_param(
+                &params.sidechain_release,
+                setter,
+            ));
+            ui.end_row();
+            ui.label("Detection");
+            let mut rms = params.sidechain_rms.value();
+            if ui.checkbox(&mut rms, "RMS").changed() {
+                Self::set_bool_param(&params.sidechain_rms, setter, rms);
+            }
+            ui.end_row();
+            ui.label("Level");
+            ui.add(widgets::ProgressBar::new(
+                context.sidechain_level().to_num::<f32>(),
+            ));
+            ui.end_row();
+        });
+    }
+    fn draw_analog_tone(params: &CulSynthParams, ui: &mut egui::Ui, setter: &ParamSetter) {
+        ui.horizontal(|ui| {
+            ui.label("Analog Tone");
+            ui.add(nih_widgets::ParamSlider::for_param(
+                &params.analog_tone,
+                setter,
+            ));
+        })
+        .response
+        .on_hover_text("Gently roll off the highest frequencies, like an analog output stage");
+    }
+    fn draw_compressor_settings(
+        params: &CompressorPluginParams,
+        context: &ContextReader,
+        ui: &mut egui::Ui,
+        setter: &ParamSetter,
+    ) {
+        let mut enable = params.enable.value();
+        if ui.checkbox(&mut enable, "Output Compressor").changed() {
+            Self::set_bool_param(&params.enable, setter, enable);
+        }
+        egui::Grid::new("CompressorSettings").show(ui, |ui| {
+            for (label, param) in [
+                ("Threshold", &params.threshold),
+                ("Ratio", &params.ratio),
+                ("Attack", &params.attack),
+                ("Release", &params.release),
+                ("Makeup", &params.makeup),
+            ] {
+                ui.label(label);
+                ui.add(nih_widgets::ParamSlider::for_param(param, setter));
+                ui.end_row();
+            }
+            // Show up to 24dB of gain reduction on the meter
+            let reduction = context.compressor_reduction();
+            ui.label("Reduction");
+            ui.add(
+                widgets::ProgressBar::new((reduction / 24f32).clamp(0f32, 1f32))
+                    .text(format!("{:.1} dB", reduction)),
+            );
+            ui.end_row();
+        });
+    }
+    fn draw_limiter_settings(
+        params: &LimiterPluginParams,
+        context: &ContextReader,
+        ui: &mut egui::Ui,
+        setter: &ParamSetter,
+    ) {
+        let mut enable = params.enable.value();
+        if ui.checkbox(&mut enable, "Output Limiter").changed() {
+            Self::set_bool_param(&params.enable, setter, enable);
+        }
+        egui::Grid::new("LimiterSettings").show(ui, |ui| {
+            for (label, param) in [
+                ("Ceiling", &params.ceiling),
+                ("Lookahead", &params.lookahead),
+                ("Release", &params.release),
+            ] {
+                ui.label(label);
+                ui.add(nih_widgets::ParamSlider::for_param(param, setter));
+                ui.end_row();
+            }
+            // Show up to 24dB of gain reduction on the meter
+            let reduction = context.limiter_reduction();
+            ui.label("Reduction");
+            ui.add(
+                widgets::ProgressBar::new((reduction / 24f32).clamp(0f32, 1f32))
+                    .text(format!("{:.1} dB", reduction)),
+            );
+            ui.end_row();
+        });
+    }
+    /// Draw the modulation matrix.  `text` holds routing pasted by the user
+    /// to be loaded with [ModMatrixPluginParams::from_text], and `error` is
+    /// the reason the last paste failed, if it did.
+    fn draw_modmatrix(
+        matrix: &ModMatrixPluginParams,
+        text: &mut String,
+        error: &mut Option<&'static str>,
+        ui: &mut egui::Ui,
+        setter: &ParamSetter,
+    ) {
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!matrix.is_empty(), |ui| {
+                if ui.button("Clear Matrix").clicked() {
+                    matrix.clear_all(setter);
+                }
+                if ui.button("Copy").on_hover_text("Copy the routing as text").clicked() {
+                    ui.output_mut(|output| output.copied_text = matrix.to_text());
+                }
+            });
+            ui.add(egui::TextEdit::singleline(text).hint_text("LFO 1:FiltCutoff=0.5, ..."));
+            let paste = ui.add_enabled(!text.trim().is_empty(), egui::Button::new("Paste"));
+            if paste.on_hover_text("Replace the routing with this text").clicked() {
+                *error = matrix.from_text(text, setter).err();
+                if error.is_none() {
+                    text.clear();
+                }
+            }
+        });
+        if let Some(error) = error {
+            ui.colored_label(egui::Color32::RED, *error);
+        }
         egui::Grid::new("MODMATRIX").show(ui, |ui| {
             ui.label("");
             ui.label("Slot A");
@@ -232,7 +621,9 @@ impl CulSynthEditor {
                             .selected_text(dest.to_str())
                             .show_ui(ui, |ui| {
                                 let sec = row.is_secondary();
-                                for value in ModDest::elements_secondary_if(sec) {
+                                for value in ModDest::elements_secondary_if(sec)
+                                    .filter(|dest| src.can_modulate(*dest))
+                                {
                                     ui.selectable_value(&mut dest, value, value.to_str());
                                 }
                             });
@@ -248,15 +639,97 @@ impl CulSynthEditor {
             }
         });
     }
+    fn draw_mod_monitor(context: &ContextReader, ui: &mut egui::Ui) {
+        let snapshot = context.voice_snapshot();
+        egui::Grid::new("MODMONITOR").show(ui, |ui| {
+            for (label, value) in [
+                ("Filter Cutoff", snapshot.eff_cutoff),
+                ("Filter Resonance", snapshot.eff_resonance),
+                ("Note", snapshot.eff_note),
+                ("VCA Envelope", snapshot.env_vca_level),
+                ("VCF Envelope", snapshot.env_vcf_level),
+                ("LFO 1", snapshot.lfo1_value),
+                ("LFO 2", snapshot.lfo2_value),
+            ] {
+                ui.label(label);
+                ui.label(format!("{:.3}", value));
+                ui.end_row();
+            }
+        });
+    }
+    fn draw_cc_map(params: &CulSynthParams, new_mapping: &mut (u8, u8), ui: &mut egui::Ui) {
+        let Ok(mut cc_map) = params.cc_map.write() else {
+            return;
+        };
+        let name = |target: u8| params.param_from_cc(target).map_or("", |param| param.name());
+        let mappings: Vec<_> = cc_map
+            .iter()
+            .map(|(cc, mapping)| (cc, mapping.target, mapping.soft_takeover))
+            .collect();
+        egui::Grid::new("CcMap").show(ui, |ui| {
+            ui.label("CC");
+            ui.label("Parameter");
+            ui.label("Soft Takeover");
+            ui.end_row();
+            for (cc, target, mut soft_takeover) in mappings {
+                ui.label(cc.to_string());
+                ui.label(name(target));
+                if ui.checkbox(&mut soft_takeover, "").changed() {
+                    cc_map.set_soft_takeover(cc, soft_takeover);
+                }
+                if ui.button("Remove").clicked() {
+                    cc_map.remove(cc);
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+            let (cc, target) = new_mapping;
+            ui.label("CC");
+            ui.add(egui::DragValue::new(cc).clamp_range(0..=127));
+            egui::ComboBox::from_id_source("CcMapTarget")
+                .selected_text(name(*target))
+                .show_ui(ui, |ui| {
+                    for (value, param) in CcMap::targets(params) {
+                        ui.selectable_value(target, value, param.name());
+                    }
+                });
+            if ui.button("Add").clicked() {
+                cc_map.insert(*cc, *target, false);
+            }
+        });
+    }
     fn set_bool_param(param: &BoolParam, setter: &ParamSetter, value: bool) {
         setter.begin_set_parameter(param);
         setter.set_parameter(param, value);
         setter.end_set_parameter(param);
     }
+    /// Set a [ResetMode] parameter from a (boolean) retrigger CC, keeping
+    /// hard reset if retriggering is already enabled
+    fn set_reset_param(param: &IntParam, setter: &ParamSetter, retrigger: bool) {
+        let value = match (retrigger, ResetMode::try_from(param.value() as u8)) {
+            (false, _) => ResetMode::None,
+            (true, Ok(ResetMode::Hard)) => ResetMode::Hard,
+            (true, _) => ResetMode::Soft,
+        };
+        setter.begin_set_parameter(param);
+        setter.set_parameter(param, value as i32);
+        setter.end_set_parameter(param);
+    }
     fn process_ccs(&mut self, setter: &ParamSetter) {
         use culsynth::voice::cc;
         let cc_rx = self.cc_receiver.get_mut().unwrap();
         while let Ok((cc, value)) = cc_rx.try_recv() {
+            // The user's CC map overrides the default assignments
+            if let Ok(mut cc_map) = self.params.cc_map.write() {
+                if cc_map.is_mapped(cc) {
+                    if let Some(change) = cc_map.apply(cc, value, &self.params) {
+                        change.apply(setter);
+                    }
+                    continue;
+                }
+            }
             let value_bool = value > 64;
             match cc {
                 control_change::NON_REGISTERED_PARAMETER_NUMBER_MSB => {
@@ -271,13 +744,13 @@ impl CulSynthEditor {
                     Self::set_bool_param(&self.params.lfo1.bipolar, setter, value_bool);
                 }
                 cc::LFO1_RETRIGGER => {
-                    Self::set_bool_param(&self.params.lfo1.retrigger, setter, value_bool);
+                    Self::set_reset_param(&self.params.lfo1.reset, setter, value_bool);
                 }
                 cc::LFO2_BIPOLAR => {
                     Self::set_bool_param(&self.params.lfo2.bipolar, setter, value_bool);
                 }
                 cc::LFO2_RETRIGGER => {
-                    Self::set_bool_param(&self.params.lfo2.retrigger, setter, value_bool);
+                    Self::set_reset_param(&self.params.lfo2.reset, setter, value_bool);
                 }
                 cc::OSC_SYNC => {
                     Self::set_bool_param(&self.params.osc_sync, setter, value_bool);
@@ -297,21 +770,48 @@ impl CulSynthEditor {
     /// Draw the editor panel
     pub fn update(&mut self, egui_ctx: &egui::Context, setter: &ParamSetter) {
         self.process_ccs(setter);
-        self.draw_status_bar(egui_ctx);
+        // Follow the theme parameters, which may be changed by loading state
+        self.apply_theme(egui_ctx);
+        self.draw_status_bar(egui_ctx, setter);
         for midi_evt in self.kbd_panel.show(egui_ctx) {
-            if let Err(e) = self.midi_channel.try_send(midi_evt) {
+            if let Err(e) = self.midi_channel.try_send(NoteEvent::from_kbd(midi_evt)) {
                 nih_error!("{}", e);
             }
         }
-        egui::CentralPanel::default().show(egui_ctx, |ui| {
-            self.draw_main_controls(setter, ui);
-        });
+        let egui_state = self.params.editor_state.clone();
+        ResizableWindow::new("main")
+            .min_size(MIN_SIZE)
+            .show(egui_ctx, &egui_state, |ui| {
+                // Scroll if the window is too short for the wrapped rows
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    self.draw_main_controls(setter, ui);
+                });
+            });
         egui::Window::new("Modulation Matrix").open(&mut self.show_mod_matrix).show(
             egui_ctx,
             |ui| {
-                Self::draw_modmatrix(&self.params.modmatrix, ui, setter);
+                Self::draw_modmatrix(
+                    &self.params.modmatrix,
+                    &mut self.modmatrix_text,
+                    &mut self.modmatrix_error,
+                    ui,
+                    setter,
+                );
             },
         );
+        egui::Window::new("Modulation Monitor").open(&mut self.show_mod_monitor).show(
+            egui_ctx,
+            |ui| {
+                Self::draw_mod_monitor(&self.context, ui);
+                // Keep the displayed values live while the monitor is open
+                egui_ctx.request_repaint();
+            },
+        );
+        egui::Window::new("MIDI CC Map")
+            .open(&mut self.show_cc_map)
+            .show(egui_ctx, |ui| {
+                Self::draw_cc_map(&self.params, &mut self.new_cc_mapping, ui);
+            });
         egui::Window::new("Settings")
             .open(&mut self.show_settings)
             .show(egui_ctx, |ui| {
@@ -320,6 +820,68 @@ impl CulSynthEditor {
                         nih_log!("{}", e);
                     }
                 }
+                #[cfg(feature = "midir")]
+                self.midi_ports.draw(ui, &self.context);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let mut dark_mode = self.params.dark_mode.value();
+                    if ui.checkbox(&mut dark_mode, "Dark Mode").changed() {
+                        Self::set_bool_param(&self.params.dark_mode, setter, dark_mode);
+                    }
+                    let mut high_contrast = self.params.high_contrast.value();
+                    if ui.checkbox(&mut high_contrast, "High Contrast").changed() {
+                        Self::set_bool_param(&self.params.high_contrast, setter, high_contrast);
+                    }
+                });
+                ui.separator();
+                let mut raw_osc = self.params.raw_osc.value();
+                if ui
+                    .checkbox(&mut raw_osc, "Raw Oscillator Monitor (bypass filter/VCA)")
+                    .changed()
+                {
+                    Self::set_bool_param(&self.params.raw_osc, setter, raw_osc);
+                }
+                let mut analog_drift = self.params.analog_drift.value();
+                if ui.checkbox(&mut analog_drift, "Analog Drift").changed() {
+                    Self::set_bool_param(&self.params.analog_drift, setter, analog_drift);
+                }
+                let mut phase_reset = self.params.phase_reset.value();
+                if ui.checkbox(&mut phase_reset, "Reset Oscillator Phase on Note On").changed() {
+                    Self::set_bool_param(&self.params.phase_reset, setter, phase_reset);
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Stretch Tuning");
+                    ui.add(nih_widgets::ParamSlider::for_param(
+                        &self.params.stretch_tuning,
+                        setter,
+                    ));
+                });
+                if self.context.voice_mode() != VoiceMode::Poly16 {
+                    Self::draw_mono_mode(&self.params.mono_mode, ui, setter);
+                } else {
+                    Self::draw_poly_spread(&self.params, ui, setter);
+                    Self::draw_dephase(&self.params, ui, setter);
+                }
+                if self.context.voice_mode() == VoiceMode::Chord {
+                    ui.separator();
+                    Self::draw_chord_settings(&self.params.chord, ui, setter);
+                }
+                ui.separator();
+                Self::draw_scale_settings(&self.params.scale_mask, ui, setter);
+                ui.separator();
+                Self::draw_voice_capture(ui, &self.params, &self.context);
+                ui.separator();
+                Self::draw_test_tone(ui, &self.context);
+                ui.separator();
+                Self::draw_benchmark(ui, &self.context, &mut self.benchmark);
+                ui.separator();
+                Self::draw_sidechain_settings(&self.params, &self.context, ui, setter);
+                ui.separator();
+                Self::draw_analog_tone(&self.params, ui, setter);
+                ui.separator();
+                Self::draw_compressor_settings(&self.params.compressor, &self.context, ui, setter);
+                ui.separator();
+                Self::draw_limiter_settings(&self.params.limiter, &self.context, ui, setter);
             });
         egui::Window::new("About").open(&mut self.show_about).collapsible(false).show(
             egui_ctx,
@@ -336,7 +898,15 @@ impl CulSynthEditor {
             },
         );
     }
+    fn apply_theme(&self, egui_ctx: &egui::Context) {
+        theme::apply(
+            egui_ctx,
+            self.params.dark_mode.value(),
+            self.params.high_contrast.value(),
+        );
+    }
     pub fn initialize(&mut self, egui_ctx: &egui::Context) {
+        self.apply_theme(egui_ctx);
         let mut fonts = egui::FontDefinitions::default();
         fonts.font_data.insert(
             "culsynth_noto_sans_math".to_owned(),
@@ -371,7 +941,7 @@ impl CulSynthEditor {
 
 pub fn create(
     params: Arc<CulSynthParams>,
-    midi_tx: SyncSender<i8>,
+    midi_tx: SyncSender<NoteEvent>,
     synth_tx: SyncSender<Box<dyn VoiceAllocator>>,
     cc_rx: Receiver<(u8, u8)>,
     context: ContextReader,
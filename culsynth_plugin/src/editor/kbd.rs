@@ -27,31 +27,107 @@ fn key_to_notenum(k: egui::Key) -> Option<i8> {
     }
 }
 
+/// Tracks the notes held by each input source (the mouse and the computer
+/// keyboard), so that a note is sent exactly one Note On when it is first
+/// pressed and exactly one Note Off once every source has released it.
+///
+/// Events are pushed in the same format as [KbdPanel::show]
+#[derive(Default)]
+struct NoteTracker {
+    /// The note held down with the mouse, if any
+    mouse: Option<i8>,
+    /// The notes held down on the computer keyboard
+    keys: Vec<i8>,
+}
+
+impl NoteTracker {
+    /// Is `note` held by any source?
+    fn is_held(&self, note: i8) -> bool {
+        self.mouse == Some(note) || self.keys.contains(&note)
+    }
+    /// Move the mouse to `note` (or off the keyboard, if `None`).  When
+    /// dragging across keys, the previous note is released before the new
+    /// note is pressed.
+    fn set_mouse(&mut self, note: Option<i8>, events: &mut Vec<i8>) {
+        if note == self.mouse {
+            return;
+        }
+        if let Some(k) = std::mem::replace(&mut self.mouse, note) {
+            if !self.is_held(k) {
+                events.push(k + (-128));
+            }
+        }
+        if let Some(k) = note {
+            if !self.keys.contains(&k) {
+                events.push(k);
+            }
+        }
+    }
+    /// Press `note` on the computer keyboard.  Duplicate presses are ignored.
+    fn key_down(&mut self, note: i8, events: &mut Vec<i8>) {
+        if self.keys.contains(&note) {
+            return;
+        }
+        if !self.is_held(note) {
+            events.push(note);
+        }
+        self.keys.push(note);
+    }
+    /// Release `note` on the computer keyboard.  Releasing a note that is not
+    /// held is ignored.
+    fn key_up(&mut self, note: i8, events: &mut Vec<i8>) {
+        if let Some(idx) = self.keys.iter().position(|k| *k == note) {
+            self.keys.swap_remove(idx);
+            if !self.is_held(note) {
+                events.push(note + (-128));
+            }
+        }
+    }
+    /// Release every held note, e.g. because the window lost focus and no
+    /// further release events can be expected
+    fn release_all(&mut self, events: &mut Vec<i8>) {
+        self.set_mouse(None, events);
+        while let Some(note) = self.keys.last() {
+            self.key_up(*note, events);
+        }
+    }
+}
+
 /// A keyboard panel that provides a UI to provide note events to the synth
 /// without having a MIDI controller.  It will currently draw itself as an
 /// [egui::TopBottomPanel]
 #[derive(Default)]
 pub struct KbdPanel {
-    last_note: Option<i8>,
+    notes: NoteTracker,
 }
 
 impl KbdPanel {
     /// Helper function to handle keyboard input
-    #[rustfmt::skip]
     fn handle_kbd_input(&mut self, ui: &egui::Ui, events: &mut Vec<i8>) {
-        ui.input(|i| {
+        let focused = ui.input(|i| {
             for evt in i.events.iter() {
-                if let egui::Event::Key{key, pressed, repeat, ..} = evt {
-                    if *repeat { continue; }
-                    if let Some(mut k) = key_to_notenum(*key) {
-                        if !(*pressed) {
-                            k += -128; //Note off
+                if let egui::Event::Key {
+                    key,
+                    pressed,
+                    repeat: false,
+                    ..
+                } = evt
+                {
+                    if let Some(k) = key_to_notenum(*key) {
+                        if *pressed {
+                            self.notes.key_down(k, events);
+                        } else {
+                            self.notes.key_up(k, events);
                         }
-                        events.push(k);
                     }
                 }
             }
+            i.focused
         });
+        // We won't see the key up events if the window loses focus
+        if !focused {
+            self.notes.release_all(events);
+        }
     }
     /// Internal helper function to generate the egui shape for the border of the key
     /// (for stroke) and two rects for the body of the key (for fill and mouse pointer)
@@ -218,24 +294,7 @@ impl KbdPanel {
             match keyboard {
                 Ok(kbd) => {
                     let new_note = Self::draw_kbd(kbd, ui);
-                    // now send the MIDI events if required:
-                    if new_note != self.last_note {
-                        match self.last_note {
-                            None => {}
-                            Some(k) => {
-                                // note off the last note
-                                ret.push(k + (-128));
-                            }
-                        }
-                        match new_note {
-                            None => {}
-                            Some(k) => {
-                                // note on the new note
-                                ret.push(k);
-                            }
-                        }
-                    }
-                    self.last_note = new_note;
+                    self.notes.set_mouse(new_note, &mut ret);
                 }
                 Err(s) => {
                     nih_log!("{}", s);
@@ -245,3 +304,48 @@ impl KbdPanel {
         ret
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glissando_releases_before_pressing() {
+        let mut notes = NoteTracker::default();
+        let mut events = Vec::new();
+        notes.set_mouse(Some(60), &mut events);
+        notes.set_mouse(Some(60), &mut events);
+        notes.set_mouse(Some(62), &mut events);
+        notes.set_mouse(None, &mut events);
+        assert_eq!(events, [60, 60 + (-128), 62, 62 + (-128)]);
+    }
+
+    #[test]
+    fn shared_notes_are_released_once() {
+        let mut notes = NoteTracker::default();
+        let mut events = Vec::new();
+        notes.key_down(60, &mut events);
+        notes.key_down(60, &mut events);
+        notes.set_mouse(Some(60), &mut events);
+        notes.key_up(60, &mut events);
+        notes.key_up(60, &mut events);
+        assert_eq!(events, [60]);
+        notes.set_mouse(None, &mut events);
+        assert_eq!(events, [60, 60 + (-128)]);
+    }
+
+    #[test]
+    fn release_all_leaves_nothing_held() {
+        let mut notes = NoteTracker::default();
+        let mut events = Vec::new();
+        notes.key_down(60, &mut events);
+        notes.key_down(64, &mut events);
+        notes.set_mouse(Some(64), &mut events);
+        notes.set_mouse(Some(67), &mut events);
+        notes.release_all(&mut events);
+        let mut offs: Vec<i8> = events.iter().filter(|k| **k < 0).map(|k| k - (-128)).collect();
+        offs.sort();
+        assert_eq!(offs, [60, 64, 67]);
+        assert!(!notes.is_held(60) && !notes.is_held(64) && !notes.is_held(67));
+    }
+}
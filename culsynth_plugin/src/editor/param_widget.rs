@@ -39,7 +39,8 @@ struct ParamSlider<'a> {
     param: &'a IntParam,
     setter: &'a ParamSetter<'a>,
     slider: egui::widgets::Slider<'a>,
-    label: egui::widgets::Label,
+    label: &'a str,
+    highlight: bool,
 }
 
 impl<'a> ParamSlider<'a> {
@@ -71,9 +72,15 @@ impl<'a> ParamSlider<'a> {
             param,
             setter,
             slider,
-            label: egui::widgets::Label::new(label),
+            label,
+            highlight: false,
         }
     }
+    /// Highlight the label of this slider (e.g. to show it is active)
+    pub fn highlighted(mut self, highlight: bool) -> Self {
+        self.highlight = highlight;
+        self
+    }
 }
 
 impl<'a> egui::Widget for ParamSlider<'a> {
@@ -83,7 +90,12 @@ impl<'a> egui::Widget for ParamSlider<'a> {
         let resp = ui.vertical(move |ui| {
             ui.set_min_width(SLIDER_WIDTH);
             let resp = ui.add(self.slider.vertical());
-            if ui.add(self.label.sense(egui::Sense::click())).double_clicked() {
+            let mut text = egui::RichText::new(self.label);
+            if self.highlight {
+                text = text.strong().color(ui.visuals().selection.stroke.color);
+            }
+            let label = egui::widgets::Label::new(text);
+            if ui.add(label.sense(egui::Sense::click())).double_clicked() {
                 setter.begin_set_parameter(param);
                 setter.set_parameter(param, param.default_plain_value());
                 setter.end_set_parameter(param);
@@ -143,6 +155,7 @@ fn draw_osc(
             ui.add(ParamSlider::new(setter, &osc.tri, TRI_CHARSTR));
             ui.add(ParamSlider::new(setter, &osc.sq, SQ_CHARSTR));
             ui.add(ParamSlider::new(setter, &osc.saw, SAW_CHARSTR));
+            ui.add(ParamSlider::new(setter, &osc.morph, "MPH"));
         });
     });
     sync_clicked
@@ -157,27 +170,62 @@ impl ParamWidget for OscPluginParams {
 pub struct OscPluginParamsWithSync<'a> {
     osc: &'a OscPluginParams,
     param: &'a BoolParam,
+    ratio: &'a IntParam,
 }
 
 /// Draw an oscillator as well as a button to enable/disable oscillator sync
+/// and a selector for the [OscRatio] locking it to the other oscillator
 pub fn osc_with_sync<'a>(
     osc: &'a OscPluginParams,
     sync: &'a BoolParam,
+    ratio: &'a IntParam,
 ) -> OscPluginParamsWithSync<'a> {
-    OscPluginParamsWithSync { osc, param: sync }
+    OscPluginParamsWithSync {
+        osc,
+        param: sync,
+        ratio,
+    }
 }
 
 impl<'a> ParamWidget for OscPluginParamsWithSync<'a> {
     fn draw_on(&self, ui: &mut egui::Ui, setter: &ParamSetter, label: &str) {
-        let sync_on = self.param.value();
-        if draw_osc(self.osc, ui, setter, label, true, sync_on) {
-            setter.begin_set_parameter(self.param);
-            setter.set_parameter(self.param, !sync_on);
-            setter.end_set_parameter(self.param);
-        }
+        ui.vertical(|ui| {
+            let sync_on = self.param.value();
+            if draw_osc(self.osc, ui, setter, label, true, sync_on) {
+                setter.begin_set_parameter(self.param);
+                setter.set_parameter(self.param, !sync_on);
+                setter.end_set_parameter(self.param);
+            }
+            ui.horizontal(|ui| {
+                ui.label("Ratio");
+                let cur_ratio = self.ratio.value();
+                for ratio in OscRatio::ratios() {
+                    if ui.selectable_label(cur_ratio == *ratio as i32, ratio.to_str()).clicked() {
+                        setter.begin_set_parameter(self.ratio);
+                        setter.set_parameter(self.ratio, *ratio as i32);
+                        setter.end_set_parameter(self.ratio);
+                    }
+                }
+            });
+        });
     }
 }
 
+/// Draw a selector for a [ResetMode] parameter
+fn draw_reset_mode(ui: &mut egui::Ui, setter: &ParamSetter, param: &IntParam) {
+    ui.vertical(|ui| {
+        ui.label("Reset");
+        let cur_mode = param.value();
+        for mode in ResetMode::modes() {
+            if ui.selectable_label(cur_mode == *mode as i32, mode.to_str()).clicked() {
+                setter.begin_set_parameter(param);
+                setter.set_parameter(param, *mode as i32);
+                setter.end_set_parameter(param);
+            }
+        }
+    });
+}
+
 impl ParamWidget for LfoPluginParams {
     fn draw_on(&self, ui: &mut egui::Ui, setter: &ParamSetter, label: &str) {
         ui.vertical(|ui| {
@@ -186,6 +234,7 @@ impl ParamWidget for LfoPluginParams {
                 ui.horizontal(|ui| {
                     ui.add(ParamSlider::new(setter, &self.rate, "Rate"));
                     ui.add(ParamSlider::new(setter, &self.depth, "Depth"));
+                    ui.add(ParamSlider::new(setter, &self.slew, "Slew"));
                 });
                 ui.vertical(|ui| {
                     let cur_wave = self.wave.value();
@@ -200,17 +249,18 @@ impl ParamWidget for LfoPluginParams {
                         }
                     }
                 });
+                draw_reset_mode(ui, setter, &self.reset);
                 ui.vertical(|ui| {
-                    if ui.selectable_label(self.retrigger.value(), "Retrigger").clicked() {
-                        setter.begin_set_parameter(&self.retrigger);
-                        setter.set_parameter(&self.retrigger, !self.retrigger.value());
-                        setter.end_set_parameter(&self.retrigger);
-                    }
                     if ui.selectable_label(self.bipolar.value(), "Bipolar").clicked() {
                         setter.begin_set_parameter(&self.bipolar);
                         setter.set_parameter(&self.bipolar, !self.bipolar.value());
                         setter.end_set_parameter(&self.bipolar);
                     }
+                    if ui.selectable_label(self.invert.value(), "Invert").clicked() {
+                        setter.begin_set_parameter(&self.invert);
+                        setter.set_parameter(&self.invert, !self.invert.value());
+                        setter.end_set_parameter(&self.invert);
+                    }
                 });
             });
         });
@@ -230,34 +280,156 @@ impl ParamWidget for RingModPluginParams {
     }
 }
 
+/// The size of the filter's frequency response plot
+const FILT_RESPONSE_SIZE: egui::Vec2 = egui::vec2(160f32, 48f32);
+/// The range of gains shown on the filter's frequency response plot, in dB
+const FILT_RESPONSE_RANGE_DB: (f32, f32) = (-36f32, 18f32);
+/// The number of (logarithmically spaced) frequencies plotted
+const FILT_RESPONSE_POINTS: usize = 64;
+
+/// Internal function to plot the frequency response of a filter from 20Hz to
+/// 20kHz (or Nyquist), with a line at 0dB
+fn draw_filt_response(
+    filt: &FiltPluginParams,
+    ui: &mut egui::Ui,
+    sample_rate: u32,
+    fixed_point: bool,
+) {
+    let (response, painter) = ui.allocate_painter(FILT_RESPONSE_SIZE, egui::Sense::hover());
+    let rect = response.rect;
+    let (min_freq, max_freq) = (20f32, 20000f32.min(sample_rate as f32 / 2f32));
+    let last = (FILT_RESPONSE_POINTS - 1) as f32;
+    let freqs: Vec<f32> = (0..FILT_RESPONSE_POINTS)
+        .map(|i| min_freq * (max_freq / min_freq).powf(i as f32 / last))
+        .collect();
+    let (min_db, max_db) = FILT_RESPONSE_RANGE_DB;
+    let to_y = |db: f32| {
+        rect.bottom() - rect.height() * (db.clamp(min_db, max_db) - min_db) / (max_db - min_db)
+    };
+    let points = filt
+        .response_db(sample_rate, fixed_point, &freqs)
+        .into_iter()
+        .enumerate()
+        .map(|(i, db)| egui::pos2(rect.left() + rect.width() * i as f32 / last, to_y(db)))
+        .collect();
+    let visuals = ui.visuals();
+    let border = visuals.widgets.noninteractive.bg_stroke;
+    painter.rect_stroke(rect, 0f32, border);
+    painter.hline(rect.x_range(), to_y(0f32), border);
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5f32, visuals.selection.stroke.color),
+    ));
+}
+
+/// Internal function to draw a filter UI, with a plot of its frequency
+/// response if the context (sample rate and fixed point) is provided
+fn draw_filt(
+    filt: &FiltPluginParams,
+    ui: &mut egui::Ui,
+    setter: &ParamSetter,
+    label: &str,
+    context: Option<(u32, bool)>,
+) {
+    ui.vertical(|ui| {
+        ui.label(label);
+        ui.horizontal(|ui| {
+            ui.add(ParamSlider::new(setter, &filt.cutoff, "Cut"));
+            ui.add(ParamSlider::new(setter, &filt.res, "Res"));
+            ui.add(ParamSlider::new(setter, &filt.kbd, "Kbd"));
+            ui.add(ParamSlider::new(setter, &filt.vel, "Vel"));
+            ui.add(ParamSlider::new(setter, &filt.env, "Env"));
+            ui.add(ParamSlider::new(setter, &filt.low, "Low"));
+            ui.add(ParamSlider::new(setter, &filt.band, "Band"));
+            ui.add(ParamSlider::new(setter, &filt.high, "High"));
+        });
+        ui.horizontal(|ui| {
+            if ui.selectable_label(filt.show_q.value(), "Show Q").clicked() {
+                setter.begin_set_parameter(&filt.show_q);
+                setter.set_parameter(&filt.show_q, !filt.show_q.value());
+                setter.end_set_parameter(&filt.show_q);
+            }
+            if filt.show_q.value() {
+                ui.label(format!("Q = {:.2}", filt.q()));
+            }
+        });
+        if let Some((sample_rate, fixed_point)) = context {
+            draw_filt_response(filt, ui, sample_rate, fixed_point);
+        }
+    });
+}
+
 impl ParamWidget for FiltPluginParams {
     fn draw_on(&self, ui: &mut egui::Ui, setter: &ParamSetter, label: &str) {
-        ui.vertical(|ui| {
-            ui.label(label);
-            ui.horizontal(|ui| {
-                ui.add(ParamSlider::new(setter, &self.cutoff, "Cut"));
-                ui.add(ParamSlider::new(setter, &self.res, "Res"));
-                ui.add(ParamSlider::new(setter, &self.kbd, "Kbd"));
-                ui.add(ParamSlider::new(setter, &self.vel, "Vel"));
-                ui.add(ParamSlider::new(setter, &self.env, "Env"));
-                ui.add(ParamSlider::new(setter, &self.low, "Low"));
-                ui.add(ParamSlider::new(setter, &self.band, "Band"));
-                ui.add(ParamSlider::new(setter, &self.high, "High"));
-            });
-        });
+        draw_filt(self, ui, setter, label, None);
     }
 }
 
-impl ParamWidget for EnvPluginParams {
+pub struct FiltPluginParamsWithResponse<'a> {
+    filt: &'a FiltPluginParams,
+    context: (u32, bool),
+}
+
+/// Draw a filter with a plot of its frequency response, for the given
+/// context (a tuple of the sample rate and whether it is fixed point)
+pub fn filt_with_response(
+    filt: &FiltPluginParams,
+    context: (u32, bool),
+) -> FiltPluginParamsWithResponse {
+    FiltPluginParamsWithResponse { filt, context }
+}
+
+impl<'a> ParamWidget for FiltPluginParamsWithResponse<'a> {
     fn draw_on(&self, ui: &mut egui::Ui, setter: &ParamSetter, label: &str) {
-        ui.vertical(|ui| {
-            ui.label(label);
-            ui.horizontal(|ui| {
-                ui.add(ParamSlider::new(setter, &self.a, "A"));
-                ui.add(ParamSlider::new(setter, &self.d, "D"));
-                ui.add(ParamSlider::new(setter, &self.s, "S"));
-                ui.add(ParamSlider::new(setter, &self.r, "R"));
-            });
+        draw_filt(self.filt, ui, setter, label, Some(self.context));
+    }
+}
+
+/// Internal function to draw an envelope UI, highlighting the active segment
+/// if `stage` is provided
+fn draw_env(
+    env: &EnvPluginParams,
+    ui: &mut egui::Ui,
+    setter: &ParamSetter,
+    label: &str,
+    stage: Option<EnvStage>,
+) {
+    ui.vertical(|ui| {
+        ui.label(label);
+        ui.horizontal(|ui| {
+            for (param, name, active) in [
+                (&env.a, "A", EnvStage::Attack),
+                (&env.peak, "P", EnvStage::Attack),
+                (&env.d, "D", EnvStage::Decay),
+                (&env.s, "S", EnvStage::Sustain),
+                (&env.r, "R", EnvStage::Release),
+            ] {
+                let slider = ParamSlider::new(setter, param, name);
+                ui.add(slider.highlighted(stage == Some(active)));
+            }
+            draw_reset_mode(ui, setter, &env.reset);
         });
+    });
+}
+
+impl ParamWidget for EnvPluginParams {
+    fn draw_on(&self, ui: &mut egui::Ui, setter: &ParamSetter, label: &str) {
+        draw_env(self, ui, setter, label, None);
+    }
+}
+
+pub struct EnvPluginParamsWithStage<'a> {
+    env: &'a EnvPluginParams,
+    stage: EnvStage,
+}
+
+/// Draw an envelope, highlighting the segment corresponding to `stage`
+pub fn env_with_stage(env: &EnvPluginParams, stage: EnvStage) -> EnvPluginParamsWithStage {
+    EnvPluginParamsWithStage { env, stage }
+}
+
+impl<'a> ParamWidget for EnvPluginParamsWithStage<'a> {
+    fn draw_on(&self, ui: &mut egui::Ui, setter: &ParamSetter, label: &str) {
+        draw_env(self.env, ui, setter, label, Some(self.stage));
     }
 }
@@ -1,22 +1,54 @@
 //! This contains all the code required to generate the actual plugins using the `nih-plug`
 //! framework.  Most of GUI code is in the [editor] module.
 use culsynth::context::GenericContext;
+use culsynth::devices::EnvStage;
 use std::sync::atomic::Ordering::Relaxed;
-use std::sync::atomic::{AtomicI32, AtomicU32, AtomicUsize};
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicU16, AtomicU32, AtomicU8, AtomicUsize};
 use std::sync::mpsc::{Receiver, SyncSender};
 use std::sync::Arc;
 
 use wmidi::MidiMessage;
 
+#[cfg(all(feature = "no_alloc_guard", debug_assertions))]
+pub mod alloc_guard;
+
+/// Count allocations on the audio thread (see [alloc_guard])
+#[cfg(all(feature = "no_alloc_guard", debug_assertions))]
+#[global_allocator]
+static ALLOCATOR: alloc_guard::GuardedAlloc = alloc_guard::GuardedAlloc;
+
+pub mod bench;
+
+pub mod diag;
+
+#[cfg(feature = "midir")]
+pub mod direct_midi;
+
 mod editor;
 
 mod fixedparam;
 
+mod sidechain;
+
+mod calibration;
+
+mod ccmap;
+
+mod analog_tone;
+
+mod limiter;
+
+mod randomize;
+
+mod theme;
+
 pub mod pluginparams;
 use pluginparams::CulSynthParams;
 
-mod voicealloc;
-use voicealloc::{PolySynth, VoiceAllocator};
+pub mod snapshot;
+
+pub mod voicealloc;
+use voicealloc::{MonoMode, NoteEventQueue, SavedVoice, SpreadMode, SynthConfig, VoiceAllocator};
 
 #[cfg(not(target_family = "wasm"))]
 pub mod nih;
@@ -47,6 +79,7 @@ pub enum VoiceMode {
     #[default]
     Mono,
     Poly16,
+    Chord,
 }
 
 impl VoiceMode {
@@ -54,6 +87,73 @@ impl VoiceMode {
         match self {
             Self::Mono => "Mono",
             Self::Poly16 => "Poly16",
+            Self::Chord => "Chord",
+        }
+    }
+}
+
+/// The post-modulation state of the most recently triggered voice, for
+/// display within the GUI
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct VoiceSnapshot {
+    /// The filter cutoff, as a MIDI note number
+    pub eff_cutoff: f32,
+    /// The filter resonance, from 0 to 1
+    pub eff_resonance: f32,
+    /// The note played by oscillator 1, as a MIDI note number
+    pub eff_note: f32,
+    /// The output level of the VCA envelope, from 0 to 1
+    pub env_vca_level: f32,
+    /// The output level of the VCF envelope, from 0 to 1
+    pub env_vcf_level: f32,
+    /// The stage of the VCA envelope
+    pub env_vca_stage: EnvStage,
+    /// The stage of the VCF envelope
+    pub env_vcf_stage: EnvStage,
+    /// The output of LFO 1
+    pub lfo1_value: f32,
+    /// The output of LFO 2
+    pub lfo2_value: f32,
+}
+
+/// A [VoiceSnapshot] that can be shared between the audio and GUI threads,
+/// storing each value as the bits of an `f32` (or each [EnvStage] as a `u8`)
+#[derive(Default)]
+struct AtomicVoiceSnapshot {
+    eff_cutoff: AtomicU32,
+    eff_resonance: AtomicU32,
+    eff_note: AtomicU32,
+    env_vca_level: AtomicU32,
+    env_vcf_level: AtomicU32,
+    env_vca_stage: AtomicU8,
+    env_vcf_stage: AtomicU8,
+    lfo1_value: AtomicU32,
+    lfo2_value: AtomicU32,
+}
+
+impl AtomicVoiceSnapshot {
+    fn store(&self, snapshot: &VoiceSnapshot) {
+        self.eff_cutoff.store(snapshot.eff_cutoff.to_bits(), Relaxed);
+        self.eff_resonance.store(snapshot.eff_resonance.to_bits(), Relaxed);
+        self.eff_note.store(snapshot.eff_note.to_bits(), Relaxed);
+        self.env_vca_level.store(snapshot.env_vca_level.to_bits(), Relaxed);
+        self.env_vcf_level.store(snapshot.env_vcf_level.to_bits(), Relaxed);
+        self.env_vca_stage.store(snapshot.env_vca_stage as u8, Relaxed);
+        self.env_vcf_stage.store(snapshot.env_vcf_stage as u8, Relaxed);
+        self.lfo1_value.store(snapshot.lfo1_value.to_bits(), Relaxed);
+        self.lfo2_value.store(snapshot.lfo2_value.to_bits(), Relaxed);
+    }
+    fn load(&self) -> VoiceSnapshot {
+        VoiceSnapshot {
+            eff_cutoff: f32::from_bits(self.eff_cutoff.load(Relaxed)),
+            eff_resonance: f32::from_bits(self.eff_resonance.load(Relaxed)),
+            eff_note: f32::from_bits(self.eff_note.load(Relaxed)),
+            env_vca_level: f32::from_bits(self.env_vca_level.load(Relaxed)),
+            env_vcf_level: f32::from_bits(self.env_vcf_level.load(Relaxed)),
+            env_vca_stage: self.env_vca_stage.load(Relaxed).try_into().unwrap_or_default(),
+            env_vcf_stage: self.env_vcf_stage.load(Relaxed).try_into().unwrap_or_default(),
+            lfo1_value: f32::from_bits(self.lfo1_value.load(Relaxed)),
+            lfo2_value: f32::from_bits(self.lfo2_value.load(Relaxed)),
         }
     }
 }
@@ -61,7 +161,26 @@ impl VoiceMode {
 struct PluginContext {
     sample_rate: AtomicI32,
     bufsz: AtomicUsize,
+    /// The block size most recently used by the host
+    host_bufsz: AtomicUsize,
     voice_mode: AtomicU32,
+    sidechain_level: AtomicU16,
+    /// The gain reduction of the output compressor, in dB (as f32 bits)
+    comp_reduction: AtomicU32,
+    /// The gain reduction of the output limiter, in dB (as f32 bits)
+    limit_reduction: AtomicU32,
+    voice_snapshot: AtomicVoiceSnapshot,
+    /// Set while a MIDI port is connected directly, to ignore host events
+    direct_midi: AtomicBool,
+    /// Set by the GUI to capture the state of a voice, and cleared by the
+    /// audio thread once it has been captured
+    capture_voice: AtomicBool,
+    /// Set by the GUI to replace the output with a calibration tone
+    test_tone: AtomicBool,
+    /// The frequency of the calibration tone, in Hz (as f32 bits)
+    test_tone_freq: AtomicU32,
+    /// The peak level of the calibration tone, in dBFS (as f32 bits)
+    test_tone_level: AtomicU32,
 }
 
 impl Default for PluginContext {
@@ -69,11 +188,38 @@ impl Default for PluginContext {
         Self {
             sample_rate: AtomicI32::new(-44100),
             bufsz: AtomicUsize::new(2048),
+            host_bufsz: AtomicUsize::new(0),
             voice_mode: AtomicU32::new(0),
+            sidechain_level: AtomicU16::new(0),
+            comp_reduction: AtomicU32::new(0),
+            limit_reduction: AtomicU32::new(0),
+            voice_snapshot: Default::default(),
+            direct_midi: AtomicBool::new(false),
+            capture_voice: AtomicBool::new(false),
+            test_tone: AtomicBool::new(false),
+            test_tone_freq: AtomicU32::new(1000f32.to_bits()),
+            test_tone_level: AtomicU32::new((-18f32).to_bits()),
         }
     }
 }
 
+impl PluginContext {
+    /// Record the block size used by the host, for display in the GUI
+    fn set_host_block_size(&self, n: usize) {
+        self.host_bufsz.store(n, Relaxed);
+    }
+    /// The frequency (in Hz) and peak level (in dBFS) of the calibration
+    /// tone, or None if it is disabled
+    fn test_tone(&self) -> Option<(f32, f32)> {
+        self.test_tone.load(Relaxed).then(|| {
+            (
+                f32::from_bits(self.test_tone_freq.load(Relaxed)),
+                f32::from_bits(self.test_tone_level.load(Relaxed)),
+            )
+        })
+    }
+}
+
 pub struct ContextReader {
     context: Arc<PluginContext>,
 }
@@ -100,8 +246,94 @@ impl ContextReader {
     pub fn bufsz(&self) -> usize {
         self.context.bufsz.load(Relaxed)
     }
+    /// The block size most recently used by the host, or zero if the plugin
+    /// hasn't been initialized yet
+    pub fn host_block_size(&self) -> usize {
+        self.context.host_bufsz.load(Relaxed)
+    }
+    /// A summary of the sample rate and host block size, for display
+    pub fn status_line(&self) -> String {
+        let sr = self.sample_rate();
+        format!(
+            "Sample Rate: {}.{} kHz, Block Size: {}",
+            sr / 1000,
+            (sr % 1000) / 100,
+            self.host_block_size()
+        )
+    }
     pub fn voice_mode(&self) -> VoiceMode {
         let mode_u32 = self.context.voice_mode.load(Relaxed);
         unsafe { std::mem::transmute((mode_u32 & 0xFF) as u8) }
     }
+    /// Get the current level of the sidechain envelope follower
+    pub fn sidechain_level(&self) -> culsynth::ScalarFxP {
+        culsynth::ScalarFxP::from_bits(self.context.sidechain_level.load(Relaxed))
+    }
+    /// Get the gain reduction of the output compressor, in dB
+    pub fn compressor_reduction(&self) -> f32 {
+        f32::from_bits(self.context.comp_reduction.load(Relaxed))
+    }
+    /// Get the gain reduction of the output limiter, in dB
+    pub fn limiter_reduction(&self) -> f32 {
+        f32::from_bits(self.context.limit_reduction.load(Relaxed))
+    }
+    /// Get the post-modulation state of the most recently triggered voice
+    pub fn voice_snapshot(&self) -> VoiceSnapshot {
+        self.context.voice_snapshot.load()
+    }
+    /// Is the plugin in direct MIDI mode (i.e. ignoring MIDI from the host)?
+    pub fn direct_midi(&self) -> bool {
+        self.context.direct_midi.load(Relaxed)
+    }
+    /// Enter or leave direct MIDI mode.  This is set by the GUI when it
+    /// connects to a MIDI port.
+    pub fn set_direct_midi(&self, direct: bool) {
+        self.context.direct_midi.store(direct, Relaxed);
+    }
+    /// Ask the audio thread to capture the state of the most recently
+    /// triggered voice into the saved plugin state
+    pub fn request_voice_capture(&self) {
+        self.context.capture_voice.store(true, Relaxed);
+    }
+    /// Get the calibration tone settings, as a tuple of (enabled, frequency
+    /// in Hz, peak level in dBFS)
+    pub fn test_tone(&self) -> (bool, f32, f32) {
+        (
+            self.context.test_tone.load(Relaxed),
+            f32::from_bits(self.context.test_tone_freq.load(Relaxed)),
+            f32::from_bits(self.context.test_tone_level.load(Relaxed)),
+        )
+    }
+    /// Replace the output of the synth with a calibration tone at `freq` Hz
+    /// and a peak level of `dbfs`, or restore the output if not `enabled`
+    pub fn set_test_tone(&self, enabled: bool, freq: f32, dbfs: f32) {
+        self.context.test_tone_freq.store(freq.to_bits(), Relaxed);
+        self.context.test_tone_level.store(dbfs.to_bits(), Relaxed);
+        self.context.test_tone.store(enabled, Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_line_tracks_reinitialization() {
+        let reader = ContextReader {
+            context: Arc::new(Default::default()),
+        };
+        reader.context.sample_rate.store(48000, Relaxed);
+        reader.context.set_host_block_size(512);
+        assert_eq!(
+            reader.status_line(),
+            "Sample Rate: 48.0 kHz, Block Size: 512"
+        );
+        // e.g. the host reinitializing the plugin with new settings
+        reader.context.sample_rate.store(-44100, Relaxed);
+        reader.context.set_host_block_size(64);
+        assert_eq!(
+            reader.status_line(),
+            "Sample Rate: 44.1 kHz, Block Size: 64"
+        );
+    }
 }
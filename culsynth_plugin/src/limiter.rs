@@ -0,0 +1,248 @@
+//! This module contains the lookahead brickwall limiter applied to the main
+//! output (see [LookaheadLimiter])
+
+use std::collections::VecDeque;
+
+/// The longest lookahead supported by the limiter, in milliseconds
+pub const MAX_LOOKAHEAD_MS: f32 = 10f32;
+
+/// Parameters for a [LookaheadLimiter].  The lookahead is set separately
+/// (see [LookaheadLimiter::set_lookahead]), since it changes the latency.
+#[derive(Clone)]
+pub struct LimiterParams {
+    /// The highest output level, as a linear gain (so 1 is 0dBFS)
+    pub ceiling: f32,
+    /// The release time constant, in seconds
+    pub release: f32,
+}
+
+/// A stereo lookahead brickwall limiter.
+///
+/// The output is delayed by the lookahead time, and the gain reduction
+/// needed for each input sample is held for that long, so the gain has
+/// already come down by the time a peak reaches the output.  The held gain
+/// is smoothed by a moving average over the same window, so it ramps down
+/// smoothly (without distorting the waveform) and never rises above the
+/// gain needed for any sample in the window, so there is no overshoot.  The
+/// gain then recovers with a one pole release.
+///
+/// With no lookahead, this is a feed-forward limiter with an instant attack.
+///
+/// The buffers are allocated by [LookaheadLimiter::new], so processing never
+/// allocates and is safe on the audio thread.
+pub struct LookaheadLimiter {
+    sample_rate: f32,
+    // The lookahead (and latency), in samples
+    lookahead: usize,
+    max_lookahead: usize,
+    // The last `lookahead` input frames
+    delay: VecDeque<(f32, f32)>,
+    // Candidates for the minimum gain needed over the window, as (index,
+    // gain) pairs in increasing order of both
+    held: VecDeque<(u64, f32)>,
+    // The last `lookahead + 1` released gains, and their sum
+    window: VecDeque<f32>,
+    sum: f64,
+    // The held gain, after the release
+    gain: f32,
+    // The gain applied to the last output frame
+    output_gain: f32,
+    index: u64,
+}
+
+impl LookaheadLimiter {
+    /// Create a limiter for the given sample rate, allocating enough buffer
+    /// space for up to [MAX_LOOKAHEAD_MS] of lookahead
+    pub fn new(sample_rate: f32) -> Self {
+        let max_lookahead = (MAX_LOOKAHEAD_MS * sample_rate / 1000f32).ceil() as usize;
+        let mut ret = Self {
+            sample_rate,
+            lookahead: 0,
+            max_lookahead,
+            delay: VecDeque::with_capacity(max_lookahead),
+            held: VecDeque::with_capacity(max_lookahead + 1),
+            window: VecDeque::with_capacity(max_lookahead + 1),
+            sum: 0f64,
+            gain: 1f32,
+            output_gain: 1f32,
+            index: 0,
+        };
+        ret.reset();
+        ret
+    }
+    /// The lookahead (and latency) of the limiter, in samples
+    pub fn latency(&self) -> usize {
+        self.lookahead
+    }
+    /// Change the lookahead to `lookahead` seconds (limited to
+    /// [MAX_LOOKAHEAD_MS]).  If this changes the lookahead in samples, the
+    /// limiter is reset, and this returns true so the new latency may be
+    /// reported to the host.
+    pub fn set_lookahead(&mut self, lookahead: f32) -> bool {
+        let samples = (lookahead.max(0f32) * self.sample_rate).round() as usize;
+        let samples = samples.min(self.max_lookahead);
+        if samples == self.lookahead {
+            return false;
+        }
+        self.lookahead = samples;
+        self.reset();
+        true
+    }
+    /// Clear the delay line and release any gain reduction
+    pub fn reset(&mut self) {
+        self.delay.clear();
+        self.delay.resize(self.lookahead, (0f32, 0f32));
+        self.held.clear();
+        self.window.clear();
+        self.window.resize(self.lookahead + 1, 1f32);
+        self.sum = (self.lookahead + 1) as f64;
+        self.gain = 1f32;
+        self.output_gain = 1f32;
+    }
+    /// The current gain reduction, in decibels.  This is positive when the
+    /// signal is being limited.
+    pub fn gain_reduction(&self) -> f32 {
+        -20f32 * self.output_gain.log10()
+    }
+    /// Limit the next stereo frame, returning the frame from `lookahead`
+    /// samples ago.  The same gain is applied to both channels, so the
+    /// stereo image does not shift.
+    pub fn next(&mut self, (left, right): (f32, f32), params: &LimiterParams) -> (f32, f32) {
+        let ceiling = params.ceiling.clamp(f32::MIN_POSITIVE, 1f32);
+        let peak = left.abs().max(right.abs());
+        let needed = if peak > ceiling { ceiling / peak } else { 1f32 };
+        // Hold the lowest gain needed over the window.  Each buffer is
+        // emptied before it is pushed to, so it never grows past the
+        // capacity allocated in new().
+        while self
+            .held
+            .front()
+            .is_some_and(|(idx, _)| idx + (self.lookahead as u64) < self.index)
+        {
+            self.held.pop_front();
+        }
+        while self.held.back().is_some_and(|(_, gain)| *gain >= needed) {
+            self.held.pop_back();
+        }
+        self.held.push_back((self.index, needed));
+        let held = self.held.front().map_or(1f32, |(_, gain)| *gain);
+        self.index += 1;
+        // Release, but never above the held gain
+        let release = params.release.max(0f32) * self.sample_rate;
+        self.gain += (1f32 - self.gain) / (release + 1f32);
+        self.gain = self.gain.min(held);
+        // Smooth the attack over the window
+        if let Some(old) = self.window.pop_front() {
+            self.sum -= old as f64;
+        }
+        self.window.push_back(self.gain);
+        self.sum += self.gain as f64;
+        self.output_gain = (self.sum / (self.lookahead + 1) as f64) as f32;
+        // With no lookahead, the delay line is empty
+        let (left, right) = match self.delay.pop_front() {
+            Some(delayed) => {
+                self.delay.push_back((left, right));
+                delayed
+            }
+            None => (left, right),
+        };
+        // Clip any remaining rounding error in the average
+        let limit = |smp: f32| (smp * self.output_gain).clamp(-ceiling, ceiling);
+        (limit(left), limit(right))
+    }
+}
+
+impl Default for LookaheadLimiter {
+    fn default() -> Self {
+        Self::new(44100f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000f32;
+
+    const PARAMS: LimiterParams = LimiterParams {
+        ceiling: 0.5f32,
+        release: 0.05f32,
+    };
+
+    /// A quiet sine wave with a sudden full scale transient at sample 1000
+    fn input(i: usize) -> f32 {
+        (i as f32 * 0.05f32).sin() * if i < 1000 { 0.25f32 } else { 1f32 }
+    }
+
+    /// Run the input through the limiter, returning the output and the gain
+    /// applied to each sample (before the final clip)
+    fn run(lookahead_ms: f32) -> (LookaheadLimiter, Vec<f32>, Vec<f32>) {
+        let mut limiter = LookaheadLimiter::new(SAMPLE_RATE);
+        limiter.set_lookahead(lookahead_ms / 1000f32);
+        let (out, gains) = (0..4800)
+            .map(|i| {
+                let out = limiter.next((input(i), -input(i)), &PARAMS).0;
+                (out, limiter.output_gain)
+            })
+            .unzip();
+        (limiter, out, gains)
+    }
+
+    #[test]
+    fn transient_without_overshoot() {
+        let (limiter, out, gains) = run(2f32);
+        let latency = limiter.latency();
+        assert_eq!(latency, 96);
+        // The quiet signal passes through unchanged, just delayed...
+        for i in 0..1000 - latency {
+            assert!((out[i + latency] - input(i)).abs() < 1e-6, "{}", i);
+        }
+        // ...until the gain starts to come down ahead of the transient...
+        assert!(gains[1000 + latency - 1] < 1f32);
+        // ...so it is caught without relying on the final clip
+        for (i, gain) in gains[latency..].iter().enumerate() {
+            assert!(input(i).abs() * gain <= 0.5f32 + 1e-6, "{} {}", i, gain);
+        }
+        // The limiter doesn't duck much more than it needs to
+        let peak = out[1000 + latency..].iter().fold(0f32, |x, y| x.max(y.abs()));
+        assert!(peak > 0.45f32, "{}", peak);
+        assert!(limiter.gain_reduction() > 5f32);
+    }
+
+    #[test]
+    fn zero_lookahead() {
+        // This is just a feed-forward limiter
+        let (limiter, out, gains) = run(0f32);
+        assert_eq!(limiter.latency(), 0);
+        assert_eq!(out[10], input(10));
+        for (i, gain) in gains.iter().enumerate() {
+            assert!(input(i).abs() * gain <= 0.5f32 + 1e-6, "{} {}", i, gain);
+        }
+    }
+
+    #[test]
+    fn lookahead_is_limited() {
+        let mut limiter = LookaheadLimiter::new(SAMPLE_RATE);
+        assert!(limiter.set_lookahead(1f32));
+        assert_eq!(limiter.latency(), 480);
+        assert!(!limiter.set_lookahead(0.02f32));
+        assert!(limiter.set_lookahead(0f32));
+        assert_eq!(limiter.latency(), 0);
+    }
+
+    #[test]
+    fn max_lookahead_never_reallocates() {
+        let mut limiter = LookaheadLimiter::new(SAMPLE_RATE);
+        limiter.set_lookahead(MAX_LOOKAHEAD_MS / 1000f32);
+        let capacities =
+            |l: &LookaheadLimiter| (l.delay.capacity(), l.held.capacity(), l.window.capacity());
+        let before = capacities(&limiter);
+        // A falling input keeps every gain in the window as a candidate, so
+        // the held gains fill up too
+        for i in 0..4800 {
+            let smp = 8f32 - i as f32 / 1000f32;
+            limiter.next((smp, smp), &PARAMS);
+        }
+        assert_eq!(capacities(&limiter), before);
+    }
+}
@@ -1,29 +1,32 @@
+use crate::analog_tone::AnalogTone;
+use crate::calibration::CalibrationTone;
+use crate::diag::{self, DiagKind};
+use crate::limiter::{LimiterParams, LookaheadLimiter};
+use crate::sidechain::SidechainFollower;
 use crate::*;
-use culsynth::context::Context;
+use culsynth::devices::{Compressor, CompressorParams};
+use culsynth::util::min_size;
 use culsynth::voice::VoiceParams;
 use nih_plug::prelude::*;
 use std::sync::atomic::Ordering::Relaxed;
 
 use std::sync::{mpsc::sync_channel, Arc};
 
+/// The maximum number of stereo output buses (including the main output)
+/// that voices may be routed to
+const MAX_OUTPUT_BUSES: usize = 4;
+
 /// Contains all of the global state for the plugin
 pub struct CulSynthPlugin {
     params: Arc<CulSynthParams>,
 
     /// Used by the GUI thread to send MIDI events to the audio thread when,
-    /// for example, a user presses a key on a on screen virtual keyboard.
-    ///
-    /// The MIDI event is packaged as an i8, where a positive integer indicates
-    /// a "Note On" for that note number, and a negative integer indicates a
-    /// "Note Off" at the absolute value of the integer
-    midi_tx: SyncSender<i8>,
+    /// for example, a user presses a key on a on screen virtual keyboard, or
+    /// a note is played on a MIDI port selected in the settings.
+    midi_tx: SyncSender<voicealloc::NoteEvent>,
 
     /// Used by the audio thread to receive MIDI events from the GUI thread.
-    ///
-    /// The MIDI event is packaged as an i8, where a positive integer indicates
-    /// a "Note On" for that note number, and a negative integer indicates a
-    /// "Note Off" at the absolute value of the integer
-    midi_rx: Receiver<i8>,
+    midi_rx: Receiver<voicealloc::NoteEvent>,
 
     /// Used by the GUI thread to replace the current synth engine
     synth_tx: SyncSender<Box<dyn VoiceAllocator>>,
@@ -42,6 +45,32 @@ pub struct CulSynthPlugin {
     cc_rx: Option<Receiver<(u8, u8)>>,
 
     context: Arc<PluginContext>,
+
+    /// Envelope follower for the (optional) sidechain input
+    sidechain: SidechainFollower,
+
+    /// Gentle high frequency rolloff for the main output, before the
+    /// compressor
+    analog_tone: AnalogTone,
+
+    /// Compressor for the main output
+    compressor: Compressor<f32>,
+
+    /// Lookahead limiter for the main output, after the compressor
+    limiter: LookaheadLimiter,
+
+    /// The latency most recently reported to the host, in samples
+    latency: u32,
+
+    /// MIDI events for the current buffer, to be applied at the correct sample
+    events: NoteEventQueue,
+
+    /// Calibration tone, which replaces the output when enabled in the GUI
+    test_tone: CalibrationTone,
+
+    /// Set once a device state reset has been logged, so it is only logged
+    /// the first time
+    state_reset_logged: bool,
 }
 
 impl CulSynthPlugin {
@@ -58,6 +87,12 @@ impl CulSynthPlugin {
         self.update_sample_rate(sr, fixed);
         self.context.voice_mode.store(mode as u32, Relaxed);
     }
+    /// The block size the engine would prefer to process.  The engine itself
+    /// works one sample at a time, so this is only a hint: nih_plug has no
+    /// way to pass it on to the host, so it is just logged at startup.
+    fn preferred_process_size(&self) -> Option<u32> {
+        Some(256)
+    }
     fn get_context_reader(&mut self) -> ContextReader {
         ContextReader {
             context: self.context.clone(),
@@ -67,7 +102,7 @@ impl CulSynthPlugin {
 
 impl Default for CulSynthPlugin {
     fn default() -> Self {
-        let (midi_tx, midi_rx) = sync_channel::<i8>(32);
+        let (midi_tx, midi_rx) = sync_channel::<voicealloc::NoteEvent>(32);
         let (cc_tx, cc_rx) = sync_channel::<(u8, u8)>(32);
         let (synth_tx, synth_rx) = sync_channel::<Box<dyn VoiceAllocator>>(1);
         Self {
@@ -80,6 +115,14 @@ impl Default for CulSynthPlugin {
             cc_rx: Some(cc_rx),
             voices: None,
             context: Arc::new(Default::default()),
+            sidechain: Default::default(),
+            analog_tone: Default::default(),
+            compressor: Default::default(),
+            limiter: Default::default(),
+            latency: 0,
+            events: NoteEventQueue::new(),
+            test_tone: Default::default(),
+            state_reset_logged: false,
         }
     }
 }
@@ -95,10 +138,33 @@ impl Plugin for CulSynthPlugin {
     const AUDIO_IO_LAYOUTS: &'static [AudioIOLayout] = &[
         AudioIOLayout {
             main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
             ..nih_plug::audio_setup::AudioIOLayout::const_default()
         },
         AudioIOLayout {
             main_output_channels: std::num::NonZeroU32::new(1),
+            aux_input_ports: &[new_nonzero_u32(1)],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                ..PortNames::const_default()
+            },
+            ..AudioIOLayout::const_default()
+        },
+        // Multi-out: voices are round-robined across the main output and
+        // the auxiliary outputs, so that they can be processed separately
+        AudioIOLayout {
+            main_output_channels: NonZeroU32::new(2),
+            aux_input_ports: &[new_nonzero_u32(2)],
+            aux_output_ports: &[new_nonzero_u32(2); MAX_OUTPUT_BUSES - 1],
+            names: PortNames {
+                aux_inputs: &["Sidechain"],
+                aux_outputs: &["Voices 2", "Voices 3", "Voices 4"],
+                ..PortNames::const_default()
+            },
             ..AudioIOLayout::const_default()
         },
     ];
@@ -113,6 +179,10 @@ impl Plugin for CulSynthPlugin {
         self.params.clone()
     }
 
+    fn filter_state(state: &mut PluginState) {
+        pluginparams::migrate_state(&mut state.params);
+    }
+
     fn editor(&mut self, _async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         let cc_rx = match self.cc_rx.take() {
             Some(x) => x,
@@ -135,7 +205,7 @@ impl Plugin for CulSynthPlugin {
         &mut self,
         _audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
         // TODO
         nih_log!(
@@ -143,6 +213,10 @@ impl Plugin for CulSynthPlugin {
             buffer_config.sample_rate,
             buffer_config.max_buffer_size,
         );
+        if let Some(preferred) = self.preferred_process_size() {
+            nih_log!("Preferred block size is {} samples", preferred);
+        }
+        crate::diag::init_from_env();
         if culsynth::USE_LIBM {
             nih_log!("Using libm for floating-point math");
         } else {
@@ -150,20 +224,41 @@ impl Plugin for CulSynthPlugin {
         }
         // JACK doesn't seem to honor max_buffer_size, so allocate more...
         let bufsz = std::cmp::max(buffer_config.max_buffer_size, 2048) as usize;
-        let voice_alloc: Box<dyn VoiceAllocator> = Box::new(PolySynth::<f32>::new(
-            Context::new(buffer_config.sample_rate),
-            16,
-        ));
+        let sample_rate = buffer_config.sample_rate as u32;
+        // If the host is just changing the sample rate, try to update the
+        // existing engine in place so that sounding notes aren't cut off
+        let mut voice_alloc = self.voices.take();
+        if !voice_alloc.as_mut().is_some_and(|v| v.set_sample_rate(sample_rate)) {
+            voice_alloc = SynthConfig::new(sample_rate).with_voice_mode(VoiceMode::Poly16).build();
+        }
+        let Some(mut voice_alloc) = voice_alloc else {
+            return false;
+        };
+        // Resume from a voice state that was just loaded with the plugin state
+        if let Ok(mut saved) = self.params.voice_state.write() {
+            if std::mem::take(&mut saved.pending) {
+                if let Some(state) = &saved.voice {
+                    if !voice_alloc.restore_voice(state) {
+                        nih_log!("Saved voice state does not match the synth engine");
+                    }
+                }
+            }
+        }
+        self.analog_tone = AnalogTone::new(buffer_config.sample_rate);
+        // Allocate the limiter's delay line here, rather than on the audio
+        // thread
+        self.limiter = LookaheadLimiter::new(buffer_config.sample_rate);
+        self.limiter.set_lookahead(self.params.limiter.lookahead.value() / 1000.);
+        self.latency = if self.params.limiter.enable.value() {
+            self.limiter.latency() as u32
+        } else {
+            0
+        };
+        context.set_latency_samples(self.latency);
         let ctx = voice_alloc.get_context();
-        self.update_context(
-            ctx,
-            if voice_alloc.is_poly() {
-                VoiceMode::Poly16
-            } else {
-                VoiceMode::Mono
-            },
-        );
+        self.update_context(ctx, voice_alloc.voice_mode());
         self.context.bufsz.store(bufsz, Relaxed);
+        self.context.set_host_block_size(buffer_config.max_buffer_size as usize);
         self.voices = Some(voice_alloc);
         true
     }
@@ -171,72 +266,171 @@ impl Plugin for CulSynthPlugin {
     fn process(
         &mut self,
         buffer: &mut Buffer,
-        _aux: &mut AuxiliaryBuffers,
+        aux: &mut AuxiliaryBuffers,
         context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
+        #[cfg(all(feature = "no_alloc_guard", debug_assertions))]
+        let _alloc_guard = crate::alloc_guard::AudioThreadScope::enter();
         if let Ok(synth) = self.synth_rx.try_recv() {
-            self.update_context(
-                synth.get_context(),
-                if synth.is_poly() {
-                    VoiceMode::Poly16
-                } else {
-                    VoiceMode::Mono
-                },
-            );
+            self.update_context(synth.get_context(), synth.voice_mode());
             self.voices = Some(synth);
         }
         let voices = match self.voices {
             Some(ref mut x) => x,
             None => return ProcessStatus::Error("Uninitialized"),
         };
-        while let Ok(note) = self.midi_rx.try_recv() {
-            if note < 0 {
-                voices.note_off((note - (-128)) as u8, 0);
-            } else {
-                voices.note_on(note as u8, 100);
+        // Events from the GUI (and any MIDI port it has connected to) are
+        // applied at the start of the buffer
+        let dispatcher: &mut SyncSender<(u8, u8)> = &mut self.cc_tx;
+        self.events.set_scale(self.params.scale_mask.value() as u16);
+        while let Ok(event) = self.midi_rx.try_recv() {
+            self.events.push_or_apply(0, event, voices.as_mut(), dispatcher);
+        }
+        // In direct MIDI mode, the host's events are ignored
+        let direct_midi = self.context.direct_midi.load(Relaxed);
+        while let Some(event) = context.next_event() {
+            if direct_midi {
+                continue;
+            }
+            if let Some(note_event) = convert_event(&event) {
+                self.events
+                    .push_or_apply(event.timing(), note_event, voices.as_mut(), dispatcher);
             }
         }
         assert!(buffer.samples() <= self.context.bufsz.load(Relaxed));
+        self.context.set_host_block_size(buffer.samples());
 
+        // If the sidechain bus isn't connected, let the follower fall silent
+        // and stop the voices modulating with the last level seen
+        let sidechain = aux.inputs.first().map(|sc| sc.as_slice_immutable());
+        if sidechain.is_none() {
+            self.sidechain.reset();
+            voices.sidechain(culsynth::ScalarFxP::ZERO);
+        }
+        self.sidechain.set_times(
+            self.params.sidechain_attack.value(),
+            self.params.sidechain_release.value(),
+            voices.get_context().sample_rate() as f32,
+        );
+        self.sidechain.set_rms(self.params.sidechain_rms.value());
+        voices.set_mono_mode(
+            MonoMode::try_from(self.params.mono_mode.value() as u8).unwrap_or_default(),
+        );
+        voices.set_poly_spread(
+            culsynth::ScalarFxP::saturating_from_num(self.params.poly_spread.value()),
+            SpreadMode::try_from(self.params.poly_spread_mode.value() as u8).unwrap_or_default(),
+        );
+        voices.set_dephase(
+            self.params.dephase.value(),
+            self.params.phase_randomize.value(),
+        );
+        let chord = self.params.chord.offsets();
+        voices.set_chord(&chord[..self.params.chord.size()]);
+        let tone = self.params.analog_tone.value();
+        if tone == 0. {
+            self.analog_tone.reset();
+        }
+        let compress = self.params.compressor.enable.value();
+        if !compress {
+            self.compressor = Default::default();
+        }
+        let comp_ctx = culsynth::context::Context::new(voices.get_context().sample_rate() as f32);
+        let comp_params = CompressorParams::from(&self.params.compressor);
+        let limit = self.params.limiter.enable.value();
+        self.limiter.set_lookahead(self.params.limiter.lookahead.value() / 1000.);
+        if !limit {
+            self.limiter.reset();
+        }
+        // The limiter only adds latency while it is enabled
+        let latency = if limit {
+            self.limiter.latency() as u32
+        } else {
+            0
+        };
+        if latency != self.latency {
+            self.latency = latency;
+            context.set_latency_samples(latency);
+        }
+        let limit_params = LimiterParams::from(&self.params.limiter);
+        let test_tone = self.context.test_tone();
+        if test_tone.is_none() {
+            self.test_tone.reset();
+        }
+
+        // Voices are only split up if the host has connected the multi-out
+        // layout; otherwise everything is summed to the main output
+        let num_buses = min_size(&[1 + aux.outputs.len(), MAX_OUTPUT_BUSES]);
         let smps = buffer.iter_samples();
-        let dispatcher: &mut SyncSender<(u8, u8)> = &mut self.cc_tx;
         let mut matrix = Some((&self.params.modmatrix).into());
-        // Replace ProcessContext with a MidiReceiver
-        let mut next_event = context.next_event();
         for (smpid, ch_smps) in smps.enumerate() {
             let params: VoiceParams<i16> = self.params.as_ref().into();
-            // Process MIDI events:
-            while let Some(event) = next_event {
-                if event.timing() > smpid as u32 {
-                    break;
-                }
-                match event {
-                    nih_plug::midi::NoteEvent::NoteOn { note, velocity, .. } => {
-                        voices.note_on(note, (velocity * 127f32) as u8);
-                    }
-                    nih_plug::midi::NoteEvent::NoteOff { note, velocity, .. } => {
-                        voices.note_off(note, (velocity * 127f32) as u8);
-                    }
-                    nih_plug::midi::NoteEvent::MidiCC { cc, value, .. } => {
-                        // nih-plug guarantees that cc will be < 127, so panic is appropriate
-                        let cc = wmidi::ControlFunction(wmidi::U7::new(cc).unwrap());
-                        voices.handle_cc(cc, (value * 127f32) as u8, dispatcher);
-                    }
-                    nih_plug::midi::NoteEvent::MidiChannelPressure { pressure, .. } => {
-                        voices.aftertouch((pressure * 127f32) as u8);
-                    }
-                    nih_plug::midi::NoteEvent::MidiPitchBend { value, .. } => {
-                        voices.pitch_bend((((value - 0.5) * (i16::MAX as f32)) as i16) << 1);
-                    }
-                    _ => (),
-                }
-                next_event = context.next_event();
+            // Process MIDI events due at this sample:
+            self.events.apply_due(smpid as u32, voices.as_mut(), dispatcher);
+            if let Some(sc) = sidechain {
+                self.sidechain.next(sc.iter().map(|ch| ch[smpid]));
+                voices.sidechain(self.sidechain.level_fixed());
+            }
+            let mut outs = [(0f32, 0f32); MAX_OUTPUT_BUSES];
+            let outs = &mut outs[..num_buses];
+            if let Some((freq, dbfs)) = test_tone {
+                // The tone bypasses the voices and the whole output stage
+                // entirely
+                let smp = self.test_tone.next(voices.get_context(), freq, dbfs);
+                outs[0] = (smp, smp);
+            } else {
+                voices.next_multi(&params, matrix.take().as_ref(), outs);
+            }
+            if tone > 0. && test_tone.is_none() {
+                outs[0] = self.analog_tone.next(outs[0], tone);
+            }
+            if compress && test_tone.is_none() {
+                let (left, right) = outs[0];
+                outs[0] = self.compressor.next_stereo(&comp_ctx, left, right, comp_params.clone());
+            }
+            if limit && test_tone.is_none() {
+                outs[0] = self.limiter.next(outs[0], &limit_params);
+            }
+            let num_channels = ch_smps.len();
+            write_frame(ch_smps.into_iter(), num_channels, outs[0]);
+            for (bus, frame) in aux.outputs.iter_mut().zip(&outs[1..]) {
+                let channels = bus.as_slice();
+                let num_channels = channels.len();
+                write_frame(
+                    channels.iter_mut().map(|ch| &mut ch[smpid]),
+                    num_channels,
+                    *frame,
+                );
+            }
+        }
+        // Don't drop any events timestamped past the end of the buffer
+        self.events.flush(voices.as_mut(), dispatcher);
+        if voices.take_state_reset() {
+            diag::record(DiagKind::StateReset, &[]);
+            if !std::mem::replace(&mut self.state_reset_logged, true) {
+                nih_log!("Reset a device state that became NaN or infinite");
             }
-            let out = voices.next(&params, matrix.take().as_ref());
-            for smp in ch_smps {
-                *smp = out;
+        }
+        if self.context.capture_voice.swap(false, Relaxed) {
+            // Don't block the audio thread if the state is being saved
+            if let (Some(voice), Ok(mut saved)) =
+                (voices.capture_voice(), self.params.voice_state.try_write())
+            {
+                *saved = SavedVoice {
+                    voice: Some(voice),
+                    pending: false,
+                };
             }
         }
+        self.context.voice_snapshot.store(&voices.voice_snapshot());
+        self.context
+            .sidechain_level
+            .store(self.sidechain.level_fixed().to_bits(), Relaxed);
+        self.context
+            .comp_reduction
+            .store(self.compressor.gain_reduction().to_bits(), Relaxed);
+        self.context
+            .limit_reduction
+            .store(self.limiter.gain_reduction().to_bits(), Relaxed);
         // To save resources, a plugin can (and probably should!) only perform expensive
         // calculations that are only displayed on the GUI while the GUI is open
         if self.params.editor_state.is_open() {
@@ -246,6 +440,53 @@ impl Plugin for CulSynthPlugin {
     }
 }
 
+/// Write a stereo `(left, right)` frame to the `num_channels` samples in
+/// `channels`, downmixing if there is only a single channel
+fn write_frame<'a>(
+    channels: impl Iterator<Item = &'a mut f32>,
+    num_channels: usize,
+    (left, right): (f32, f32),
+) {
+    for (ch, smp) in channels.enumerate() {
+        *smp = match (num_channels, ch) {
+            // Downmix for mono output
+            (1, _) => (left + right) / 2.,
+            (_, 0) => left,
+            (_, 1) => right,
+            _ => 0.,
+        };
+    }
+}
+
+/// Convert a MIDI event from the host into a [voicealloc::NoteEvent], if it
+/// is one that the synth handles
+fn convert_event(event: &PluginNoteEvent<CulSynthPlugin>) -> Option<voicealloc::NoteEvent> {
+    use nih_plug::midi::NoteEvent as HostEvent;
+    use voicealloc::NoteEvent;
+    match *event {
+        HostEvent::NoteOn { note, velocity, .. } => Some(NoteEvent::NoteOn {
+            note,
+            velocity: (velocity * 127f32) as u8,
+        }),
+        HostEvent::NoteOff { note, velocity, .. } => Some(NoteEvent::NoteOff {
+            note,
+            velocity: (velocity * 127f32) as u8,
+        }),
+        HostEvent::MidiCC { cc, value, .. } => Some(NoteEvent::Cc {
+            // nih-plug guarantees that cc will be < 127, so panic is appropriate
+            cc: wmidi::ControlFunction(wmidi::U7::new(cc).unwrap()),
+            value: (value * 127f32) as u8,
+        }),
+        HostEvent::MidiChannelPressure { pressure, .. } => {
+            Some(NoteEvent::Aftertouch((pressure * 127f32) as u8))
+        }
+        HostEvent::MidiPitchBend { value, .. } => Some(NoteEvent::PitchBend(
+            (((value - 0.5) * (i16::MAX as f32)) as i16) << 1,
+        )),
+        _ => None,
+    }
+}
+
 impl ClapPlugin for CulSynthPlugin {
     const CLAP_ID: &'static str = crate::ID;
     const CLAP_DESCRIPTION: Option<&'static str> = crate::DESCRIPTION;
@@ -1,17 +1,29 @@
+use culsynth::context::Context;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::devices::StretchTuningParams;
+use culsynth::devices::SyncedMixOscsParams;
+use culsynth::devices::{resonance_to_q, LfoOptions, LfoWave, OscRatio, ResetMode};
+use culsynth::devices::{CompressorParams, COMP_MAKEUP_RANGE_DB, COMP_THRESHOLD_RANGE_DB};
 use culsynth::devices::{EnvParams, LfoParams, MixOscParams, ModFiltParams, RingModParams};
-use culsynth::devices::{LfoOptions, LfoWave, SyncedMixOscsParams};
-use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc};
+use culsynth::devices::{Filt, FiltParams, FiltResponse, Resonance};
+use culsynth::voice::modulation::{ModDest, ModMatrix, ModSrc, MOD_SLOTS};
 use culsynth::voice::VoiceParams;
 use culsynth::{EnvParamFxP, IScalarFxP, LfoFreqFxP, NoteFxP, ScalarFxP, SignedNoteFxP};
 use nih_plug::prelude::*;
+use nih_plug::wrapper::state::ParamValue;
 use nih_plug_egui::EguiState;
 
-use std::sync::Arc;
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
 
+use crate::ccmap::CcMap;
 use crate::fixedparam::{
     new_fixed_param, new_fixed_param_env, new_fixed_param_freq, new_fixed_param_lfo,
     new_fixed_param_percent,
 };
+use crate::limiter::{LimiterParams, MAX_LOOKAHEAD_MS};
+use crate::voicealloc::{MonoMode, SavedVoice, Scale, SpreadMode, CHROMATIC_MASK, MAX_CHORD_NOTES};
 
 /// Contains all of the parameters for an oscillator within the plugin
 #[derive(Params)]
@@ -38,6 +50,10 @@ pub struct OscPluginParams {
 
     #[id = "saw"]
     pub saw: IntParam,
+
+    /// Triangle to sawtooth morph
+    #[id = "morph"]
+    pub morph: IntParam,
 }
 
 impl Default for OscPluginParams {
@@ -61,6 +77,7 @@ impl Default for OscPluginParams {
             saw: new_fixed_param_percent("Saw", ScalarFxP::MAX),
             sq: new_fixed_param_percent("Square", ScalarFxP::ZERO),
             tri: new_fixed_param_percent("Triangle", ScalarFxP::ZERO),
+            morph: new_fixed_param_percent("Morph", ScalarFxP::ZERO),
         }
     }
 }
@@ -76,10 +93,26 @@ impl From<&OscPluginParams> for MixOscParams<i16> {
             sq: ScalarFxP::from_bits(value.sq.smoothed.next() as u16),
             tri: ScalarFxP::from_bits(value.tri.smoothed.next() as u16),
             saw: ScalarFxP::from_bits(value.saw.smoothed.next() as u16),
+            morph: ScalarFxP::from_bits(value.morph.smoothed.next() as u16),
         }
     }
 }
 
+/// Create a parameter selecting a modulator's [ResetMode]
+fn new_reset_mode_param(name: String) -> IntParam {
+    IntParam::new(
+        name,
+        ResetMode::Soft as i32,
+        IntRange::Linear {
+            min: ResetMode::None as i32,
+            max: ResetMode::Hard as i32,
+        },
+    )
+    .with_value_to_string(Arc::new(|x| {
+        ResetMode::try_from(x as u8).unwrap_or_default().to_str().to_owned()
+    }))
+}
+
 /// Contains all of the parameters for an LFO within the plugin
 #[derive(Params)]
 pub struct LfoPluginParams {
@@ -92,11 +125,19 @@ pub struct LfoPluginParams {
     #[id = "wave"]
     pub wave: IntParam,
 
+    /// Keeps the id of the boolean retrigger parameter this replaced, so
+    /// host automation still finds it (see [migrate_state] for old presets)
     #[id = "retrigger"]
-    pub retrigger: BoolParam,
+    pub reset: IntParam,
 
     #[id = "bipolar"]
     pub bipolar: BoolParam,
+
+    #[id = "invert"]
+    pub invert: BoolParam,
+
+    #[id = "slew"]
+    pub slew: IntParam,
 }
 
 impl LfoPluginParams {
@@ -112,19 +153,22 @@ impl LfoPluginParams {
             ),
             rate: new_fixed_param_lfo(name.to_owned() + " Rate", LfoFreqFxP::ONE),
             depth: new_fixed_param_percent(name.to_owned() + " Depth", ScalarFxP::MAX),
-            retrigger: BoolParam::new(name.to_owned() + " Retrigger", true),
+            reset: new_reset_mode_param(name.to_owned() + " Reset"),
             bipolar: BoolParam::new(name.to_owned() + " Bipolar", true),
+            invert: BoolParam::new(name.to_owned() + " Invert", false),
+            slew: new_fixed_param_env(name.to_owned() + " Slew", EnvParamFxP::ZERO),
         }
     }
 }
 
 impl From<&LfoPluginParams> for LfoOptions {
     fn from(param: &LfoPluginParams) -> Self {
-        LfoOptions::new(
+        LfoOptions::new_with_reset(
             LfoWave::try_from(param.wave.value() as u8).unwrap_or_default(),
             param.bipolar.value(),
-            param.retrigger.value(),
+            ResetMode::try_from(param.reset.value() as u8).unwrap_or_default(),
         )
+        .with_invert(param.invert.value())
     }
 }
 
@@ -134,11 +178,14 @@ impl From<&LfoPluginParams> for LfoParams<i16> {
             freq: LfoFreqFxP::from_bits(value.rate.smoothed.next() as u16),
             depth: ScalarFxP::from_bits(value.depth.smoothed.next() as u16),
             opts: value.into(),
+            slew: EnvParamFxP::from_bits(value.slew.smoothed.next() as u16),
         }
     }
 }
 
-/// Contains all of the parameters for an oscillator within the plugin
+/// Contains the mixer levels of the oscillators and the ring modulator.  As
+/// with the other fixed point parameters, changes are ramped over 50ms (one
+/// step per sample) so automating the mix doesn't pop.
 #[derive(Params)]
 pub struct RingModPluginParams {
     #[id = "vol_o1"]
@@ -197,6 +244,10 @@ pub struct FiltPluginParams {
 
     #[id = "hi"]
     pub high: IntParam,
+
+    /// Display the resonance as a Q factor in the editor
+    #[id = "showq"]
+    pub show_q: BoolParam,
 }
 
 impl Default for FiltPluginParams {
@@ -210,10 +261,61 @@ impl Default for FiltPluginParams {
             low: new_fixed_param_percent("Filter Low Pass", ScalarFxP::MAX),
             band: new_fixed_param_percent("Filter Band Pass", ScalarFxP::ZERO),
             high: new_fixed_param_percent("Filter High Pass", ScalarFxP::ZERO),
+            show_q: BoolParam::new("Filter Show Q", false).non_automatable(),
         }
     }
 }
 
+impl FiltPluginParams {
+    /// The current resonance, as a Q factor (see [resonance_to_q])
+    pub fn q(&self) -> f32 {
+        let res = ScalarFxP::from_bits(self.res.value() as u16).to_num::<f32>();
+        resonance_to_q(res.min(<f32 as culsynth::Float>::RES_MAX))
+    }
+    /// The gain (in dB) of the filter at each of `freqs` (in Hz), with its
+    /// outputs mixed as set by these parameters and ignoring modulation.
+    ///
+    /// This is calculated in the same number format as the synth, so it
+    /// matches the processed response (see [Filt::response]).
+    pub fn response_db(&self, sample_rate: u32, fixed_point: bool, freqs: &[f32]) -> Vec<f32> {
+        let params = FiltParams::<i16> {
+            cutoff: NoteFxP::from_bits(self.cutoff.value() as u16),
+            resonance: Resonance::new(ScalarFxP::from_bits(self.res.value() as u16)),
+        };
+        let mix = |param: &IntParam| ScalarFxP::from_bits(param.value() as u16).to_num::<f32>();
+        let (low, band, high) = (mix(&self.low), mix(&self.band), mix(&self.high));
+        let fixed = if fixed_point {
+            fixed_filt_response(&params, sample_rate, freqs)
+        } else {
+            None
+        };
+        let responses = fixed.unwrap_or_else(|| {
+            let ctx = Context::<f32>::new(sample_rate as f32);
+            let params = (&params).into();
+            freqs.iter().map(|freq| Filt::<f32>::response(&ctx, &params, *freq)).collect()
+        });
+        responses.iter().map(|response| response.mix_db(low, band, high)).collect()
+    }
+}
+
+/// The response of the fixed point filter at each of `freqs`, or `None` if
+/// the fixed point engine doesn't support `sample_rate`
+#[cfg(feature = "fixed")]
+fn fixed_filt_response(
+    params: &FiltParams<i16>,
+    sample_rate: u32,
+    freqs: &[f32],
+) -> Option<Vec<FiltResponse>> {
+    let ctx = ContextFxP::maybe_create(sample_rate)?;
+    Some(freqs.iter().map(|freq| Filt::<i16>::response(&ctx, params, *freq)).collect())
+}
+
+/// Float-only builds don't include the fixed point engine
+#[cfg(not(feature = "fixed"))]
+fn fixed_filt_response(_: &FiltParams<i16>, _: u32, _: &[f32]) -> Option<Vec<FiltResponse>> {
+    None
+}
+
 impl From<&FiltPluginParams> for ModFiltParams<i16> {
     fn from(value: &FiltPluginParams) -> Self {
         ModFiltParams {
@@ -235,23 +337,34 @@ pub struct EnvPluginParams {
     #[id = "a"]
     pub a: IntParam,
 
+    #[id = "p"]
+    pub peak: IntParam,
+
     #[id = "d"]
     pub d: IntParam,
 
-    #[id = "s"]
+    /// The sustain level relative to the attack peak.  This had the id "s"
+    /// while it was an absolute level, so presets saved with the old meaning
+    /// are converted by [migrate_state] rather than misread.
+    #[id = "sl"]
     pub s: IntParam,
 
     #[id = "r"]
     pub r: IntParam,
+
+    #[id = "reset"]
+    pub reset: IntParam,
 }
 
 impl EnvPluginParams {
     fn new(name: &str) -> Self {
         Self {
             a: new_fixed_param_env(name.to_owned() + " Attack", EnvParamFxP::lit("0.1")),
+            peak: new_fixed_param_percent(name.to_owned() + " Attack Peak", ScalarFxP::MAX),
             d: new_fixed_param_env(name.to_owned() + " Decay", EnvParamFxP::lit("0.1")),
             s: new_fixed_param_percent(name.to_owned() + " Sustain", ScalarFxP::MAX),
             r: new_fixed_param_env(name.to_owned() + " Release", EnvParamFxP::lit("0.1")),
+            reset: new_reset_mode_param(name.to_owned() + " Reset"),
         }
     }
 }
@@ -260,9 +373,11 @@ impl From<&EnvPluginParams> for EnvParams<i16> {
     fn from(value: &EnvPluginParams) -> Self {
         EnvParams {
             attack: EnvParamFxP::from_bits(value.a.smoothed.next() as u16),
+            attack_peak: ScalarFxP::from_bits(value.peak.smoothed.next() as u16),
             decay: EnvParamFxP::from_bits(value.d.smoothed.next() as u16),
             sustain: ScalarFxP::from_bits(value.s.smoothed.next() as u16),
             release: EnvParamFxP::from_bits(value.r.smoothed.next() as u16),
+            reset: ResetMode::try_from(value.reset.value() as u8).unwrap_or_default(),
         }
     }
 }
@@ -300,17 +415,14 @@ impl ModMatrixRowParams {
                 ModDest::try_from(string).map(|x| x as i32).ok()
             }))
     }
-    fn new(name: &str, is_secondary: bool) -> Self {
-        let rng = if is_secondary {
-            IntRange::Linear {
-                min: ModDest::min() as i32,
-                max: ModDest::max_secondary() as i32,
-            }
-        } else {
-            IntRange::Linear {
-                min: ModDest::min() as i32,
-                max: ModDest::max() as i32,
-            }
+    fn new(name: &str, src: ModSrc) -> Self {
+        let is_secondary = src.is_secondary();
+        // The secondary destinations aren't a contiguous range, so every row
+        // has the full range.  Routes a row can't use are left out of the
+        // editor and ignored by the modulation matrix.
+        let rng = IntRange::Linear {
+            min: ModDest::min() as i32,
+            max: ModDest::max() as i32,
         };
         Self {
             a: Self::make_param(name.to_owned() + " A", rng),
@@ -380,6 +492,14 @@ pub struct ModMatrixPluginParams {
     pub lfo1: ModMatrixRowParams,
     #[nested(id_prefix = "M_L2_", group = "L2Mod")]
     pub lfo2: ModMatrixRowParams,
+    #[nested(id_prefix = "M_SC_", group = "SCMod")]
+    pub sidechain: ModMatrixRowParams,
+    #[nested(id_prefix = "M_EF_", group = "EFMod")]
+    pub env_vcf: ModMatrixRowParams,
+    #[nested(id_prefix = "M_EA_", group = "EAMod")]
+    pub env_vca: ModMatrixRowParams,
+    #[nested(id_prefix = "M_EFS_", group = "EFSMod")]
+    pub env_vcf_scaled: ModMatrixRowParams,
 }
 
 impl Default for ModMatrixPluginParams {
@@ -391,13 +511,17 @@ impl Default for ModMatrixPluginParams {
 impl ModMatrixPluginParams {
     pub fn new() -> Self {
         Self {
-            velocity: ModMatrixRowParams::new("MM Velocity", false),
-            aftertouch: ModMatrixRowParams::new("MM Aftertouch", false),
-            modwheel: ModMatrixRowParams::new("MM Modwheel", false),
-            env1: ModMatrixRowParams::new("MM Env 1", false),
-            env2: ModMatrixRowParams::new("MM Env 2", true),
-            lfo1: ModMatrixRowParams::new("MM LFO 1", false),
-            lfo2: ModMatrixRowParams::new("MM LFO 2", true),
+            velocity: ModMatrixRowParams::new("MM Velocity", ModSrc::Velocity),
+            aftertouch: ModMatrixRowParams::new("MM Aftertouch", ModSrc::Aftertouch),
+            modwheel: ModMatrixRowParams::new("MM Modwheel", ModSrc::ModWheel),
+            env1: ModMatrixRowParams::new("MM Env 1", ModSrc::Env1),
+            env2: ModMatrixRowParams::new("MM Env 2", ModSrc::Env2),
+            lfo1: ModMatrixRowParams::new("MM LFO 1", ModSrc::Lfo1),
+            lfo2: ModMatrixRowParams::new("MM LFO 2", ModSrc::Lfo2),
+            sidechain: ModMatrixRowParams::new("MM Sidechain", ModSrc::Sidechain),
+            env_vcf: ModMatrixRowParams::new("MM VCF Env", ModSrc::EnvFilt),
+            env_vca: ModMatrixRowParams::new("MM VCA Env", ModSrc::EnvAmp),
+            env_vcf_scaled: ModMatrixRowParams::new("MM VCF Env Scaled", ModSrc::EnvFiltScaled),
         }
     }
     pub fn row(&self, src: ModSrc) -> &ModMatrixRowParams {
@@ -409,8 +533,99 @@ impl ModMatrixPluginParams {
             ModSrc::Env2 => &self.env2,
             ModSrc::Lfo1 => &self.lfo1,
             ModSrc::Lfo2 => &self.lfo2,
+            ModSrc::Sidechain => &self.sidechain,
+            ModSrc::EnvFilt => &self.env_vcf,
+            ModSrc::EnvAmp => &self.env_vca,
+            ModSrc::EnvFiltScaled => &self.env_vcf_scaled,
+        }
+    }
+    /// Iterate over the (destination, magnitude) parameters of every slot in
+    /// every row of the matrix
+    pub fn slots(&self) -> impl Iterator<Item = (&IntParam, &IntParam)> + '_ {
+        ModSrc::ELEM.into_iter().flat_map(move |src| self.row(src).iter())
+    }
+    /// Returns true if no slot in the matrix has a destination or magnitude
+    pub fn is_empty(&self) -> bool {
+        self.slots()
+            .all(|(dest, mag)| dest.value() == ModDest::Null as i32 && mag.value() == 0)
+    }
+    /// Clear all of the modulation routing, setting every slot to no
+    /// destination with zero magnitude
+    pub fn clear_all(&self, setter: &ParamSetter) {
+        for (dest, mag) in self.slots() {
+            for (param, value) in [(dest, ModDest::Null as i32), (mag, 0)] {
+                setter.begin_set_parameter(param);
+                setter.set_parameter(param, value);
+                setter.end_set_parameter(param);
+            }
+        }
+    }
+    /// A compact, human readable description of the modulation routing, for
+    /// sharing (e.g. "LFO 1:FiltCutoff=0.5, Envelope 1:Osc1Shape=-0.3").
+    /// Slots without a destination are left out.  This can be loaded back
+    /// with [ModMatrixPluginParams::from_text].
+    pub fn to_text(&self) -> String {
+        modmatrix_to_text(&ModMatrix::from(self))
+    }
+    /// Replace all of the modulation routing with the routes described by
+    /// `text` (see [ModMatrixPluginParams::to_text]).  The whole text is
+    /// checked before any parameter is changed, so on an error the matrix is
+    /// left as it was.
+    pub fn from_text(&self, text: &str, setter: &ParamSetter) -> Result<(), &'static str> {
+        let matrix = modmatrix_from_text(text)?;
+        for (src, entries) in matrix.rows {
+            for ((dest, mag), (dest_param, mag_param)) in entries.iter().zip(self.row(src).iter()) {
+                let values = [
+                    (dest_param, *dest as i32),
+                    (mag_param, mag.to_bits() as i32),
+                ];
+                for (param, value) in values {
+                    setter.begin_set_parameter(param);
+                    setter.set_parameter(param, value);
+                    setter.end_set_parameter(param);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Format each route in `matrix` as `source:destination=depth`, separated by
+/// commas.  The depth is printed exactly, so the text round trips.
+fn modmatrix_to_text(matrix: &ModMatrix<i16>) -> String {
+    let mut routes = Vec::new();
+    for (src, entries) in matrix.rows.iter() {
+        for (dest, mag) in entries.iter().filter(|(dest, _)| *dest != ModDest::Null) {
+            routes.push(format!("{}:{}={}", src.to_str(), dest.to_str(), mag));
+        }
+    }
+    routes.join(", ")
+}
+
+/// Parse the output of [modmatrix_to_text], rejecting unknown names, routes
+/// that are not allowed (see [ModSrc::can_modulate]), and sources with more
+/// than [MOD_SLOTS] routes.  Depths outside of -1 to 1 are saturated.
+fn modmatrix_from_text(text: &str) -> Result<ModMatrix<i16>, &'static str> {
+    let mut matrix = ModMatrix::<i16>::default();
+    let mut used = [0usize; ModSrc::numel()];
+    for route in text.split(',').map(str::trim).filter(|route| !route.is_empty()) {
+        let (src, rest) = route.split_once(':').ok_or("Expected source:destination=depth")?;
+        let (dest, mag) = rest.split_once('=').ok_or("Expected source:destination=depth")?;
+        let src = ModSrc::try_from(src.trim()).map_err(|_| "Unknown modulation source")?;
+        let dest = ModDest::try_from(dest.trim()).map_err(|_| "Unknown modulation destination")?;
+        let mag =
+            IScalarFxP::saturating_from_str(mag.trim()).map_err(|_| "Invalid modulation depth")?;
+        if !src.can_modulate(dest) {
+            return Err("Modulation route not allowed");
+        }
+        let slot = used[src as usize];
+        if slot >= MOD_SLOTS {
+            return Err("Too many routes from one modulation source");
         }
+        matrix.rows[src as usize].1[slot] = (dest, mag);
+        used[src as usize] += 1;
     }
+    Ok(matrix)
 }
 
 impl From<&ModMatrixPluginParams> for ModMatrix<i16> {
@@ -432,6 +647,226 @@ impl From<&ModMatrixPluginParams> for ModMatrix<i16> {
     }
 }
 
+/// The chord played by each note in [VoiceMode::Chord](crate::VoiceMode)
+#[derive(Params)]
+pub struct ChordPluginParams {
+    /// The number of notes in the chord
+    #[id = "size"]
+    pub size: IntParam,
+    #[id = "n1"]
+    pub note1: IntParam,
+    #[id = "n2"]
+    pub note2: IntParam,
+    #[id = "n3"]
+    pub note3: IntParam,
+    #[id = "n4"]
+    pub note4: IntParam,
+    #[id = "n5"]
+    pub note5: IntParam,
+    #[id = "n6"]
+    pub note6: IntParam,
+    #[id = "n7"]
+    pub note7: IntParam,
+    #[id = "n8"]
+    pub note8: IntParam,
+}
+
+impl Default for ChordPluginParams {
+    fn default() -> Self {
+        // A major triad
+        const DEFAULT_CHORD: [i32; MAX_CHORD_NOTES] = [0, 4, 7, 0, 0, 0, 0, 0];
+        let make_param = |idx: usize| {
+            IntParam::new(
+                format!("Chord Note {}", idx + 1),
+                DEFAULT_CHORD[idx],
+                IntRange::Linear { min: -24, max: 24 },
+            )
+            .with_unit(" st")
+            .non_automatable()
+        };
+        Self {
+            size: IntParam::new(
+                "Chord Size",
+                3,
+                IntRange::Linear {
+                    min: 1,
+                    max: MAX_CHORD_NOTES as i32,
+                },
+            )
+            .non_automatable(),
+            note1: make_param(0),
+            note2: make_param(1),
+            note3: make_param(2),
+            note4: make_param(3),
+            note5: make_param(4),
+            note6: make_param(5),
+            note7: make_param(6),
+            note8: make_param(7),
+        }
+    }
+}
+
+impl ChordPluginParams {
+    /// The offset of the `idx`th note of the chord, in semitones
+    pub fn note(&self, idx: usize) -> &IntParam {
+        [
+            &self.note1,
+            &self.note2,
+            &self.note3,
+            &self.note4,
+            &self.note5,
+            &self.note6,
+            &self.note7,
+            &self.note8,
+        ][idx]
+    }
+    /// The offsets of every note of the chord.  Only the first [Self::size]
+    /// notes are played.
+    pub fn offsets(&self) -> [i8; MAX_CHORD_NOTES] {
+        std::array::from_fn(|idx| self.note(idx).value() as i8)
+    }
+    /// The number of notes in the chord
+    pub fn size(&self) -> usize {
+        self.size.value() as usize
+    }
+}
+
+/// The compressor applied to the main output
+#[derive(Params)]
+pub struct CompressorPluginParams {
+    #[id = "on"]
+    pub enable: BoolParam,
+
+    /// Threshold, in dBFS
+    #[id = "thr"]
+    pub threshold: FloatParam,
+
+    /// Compression ratio (i.e. r:1)
+    #[id = "ratio"]
+    pub ratio: FloatParam,
+
+    /// Attack time, in milliseconds
+    #[id = "atk"]
+    pub attack: FloatParam,
+
+    /// Release time, in milliseconds
+    #[id = "rel"]
+    pub release: FloatParam,
+
+    /// Makeup gain, in dB
+    #[id = "makeup"]
+    pub makeup: FloatParam,
+}
+
+impl Default for CompressorPluginParams {
+    fn default() -> Self {
+        Self {
+            enable: BoolParam::new("Compressor", false),
+            threshold: FloatParam::new(
+                "Compressor Threshold",
+                -12.,
+                FloatRange::Linear {
+                    min: -(COMP_THRESHOLD_RANGE_DB as f32),
+                    max: 0.,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            ratio: FloatParam::new(
+                "Compressor Ratio",
+                4.,
+                FloatRange::Skewed {
+                    min: 1.,
+                    max: 20.,
+                    factor: FloatRange::skew_factor(-1.),
+                },
+            )
+            .with_unit(":1")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            attack: new_time_param_ms("Compressor Attack", 5f32),
+            release: new_time_param_ms("Compressor Release", 100f32),
+            makeup: FloatParam::new(
+                "Compressor Makeup",
+                0.,
+                FloatRange::Linear {
+                    min: 0.,
+                    max: COMP_MAKEUP_RANGE_DB as f32,
+                },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+        }
+    }
+}
+
+impl From<&CompressorPluginParams> for CompressorParams<f32> {
+    fn from(value: &CompressorPluginParams) -> Self {
+        CompressorParams {
+            threshold: -value.threshold.value() / COMP_THRESHOLD_RANGE_DB as f32,
+            ratio: 1. - 1. / value.ratio.value(),
+            attack: value.attack.value() / 1000.,
+            release: value.release.value() / 1000.,
+            makeup: value.makeup.value() / COMP_MAKEUP_RANGE_DB as f32,
+        }
+    }
+}
+
+/// The lookahead brickwall limiter applied to the main output, after the
+/// compressor
+#[derive(Params)]
+pub struct LimiterPluginParams {
+    #[id = "on"]
+    pub enable: BoolParam,
+
+    /// The highest output level, in dBFS
+    #[id = "ceil"]
+    pub ceiling: FloatParam,
+
+    /// Lookahead time (and latency), in milliseconds
+    #[id = "look"]
+    pub lookahead: FloatParam,
+
+    /// Release time, in milliseconds
+    #[id = "rel"]
+    pub release: FloatParam,
+}
+
+impl Default for LimiterPluginParams {
+    fn default() -> Self {
+        Self {
+            enable: BoolParam::new("Limiter", false),
+            ceiling: FloatParam::new(
+                "Limiter Ceiling",
+                -0.3,
+                FloatRange::Linear { min: -12., max: 0. },
+            )
+            .with_unit(" dB")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            lookahead: FloatParam::new(
+                "Limiter Lookahead",
+                1.5,
+                FloatRange::Linear {
+                    min: 0.,
+                    max: MAX_LOOKAHEAD_MS,
+                },
+            )
+            .non_automatable()
+            .with_unit(" ms")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            release: new_time_param_ms("Limiter Release", 50f32),
+        }
+    }
+}
+
+impl From<&LimiterPluginParams> for LimiterParams {
+    fn from(value: &LimiterPluginParams) -> Self {
+        LimiterParams {
+            ceiling: 10f32.powf(value.ceiling.value() / 20.),
+            release: value.release.value() / 1000.,
+        }
+    }
+}
+
 /// Holds all of the plugin parameters
 #[derive(Params)]
 pub struct CulSynthParams {
@@ -440,9 +875,23 @@ pub struct CulSynthParams {
     #[persist = "editor-state"]
     pub editor_state: Arc<EguiState>,
 
+    /// The voice state captured with "Capture Voice State", saved together
+    /// with the parameter state so playback can resume from the same point
+    #[persist = "voice-state"]
+    pub voice_state: RwLock<SavedVoice>,
+
+    /// The user's MIDI CC assignments, saved together with the parameter
+    /// state so they work the same way in every host
+    #[persist = "cc-map"]
+    pub cc_map: RwLock<CcMap>,
+
     #[id = "osync"]
     pub osc_sync: BoolParam,
 
+    /// The [OscRatio] locking oscillator 2 to oscillator 1
+    #[id = "oratio"]
+    pub osc_ratio: IntParam,
+
     #[nested(id_prefix = "o1", group = "osc1")]
     pub osc1: OscPluginParams,
 
@@ -475,6 +924,84 @@ pub struct CulSynthParams {
 
     #[nested(group = "Mod")]
     pub modmatrix: ModMatrixPluginParams,
+
+    /// The [MonoMode] used by monophonic synths
+    #[id = "monomd"]
+    pub mono_mode: IntParam,
+
+    #[nested(id_prefix = "chd", group = "chord")]
+    pub chord: ChordPluginParams,
+
+    /// The 12 bit mask of pitch classes that incoming notes are quantized to
+    /// (see [Scale::mask])
+    #[id = "scale"]
+    pub scale_mask: IntParam,
+
+    /// How far polyphonic voices are spread across the stereo field, from 0
+    /// (centered) to 1
+    #[id = "pspread"]
+    pub poly_spread: FloatParam,
+
+    /// The [SpreadMode] used to position polyphonic voices
+    #[id = "pspmd"]
+    pub poly_spread_mode: IntParam,
+
+    /// Bypass the filter and VCA to monitor the raw oscillator mix
+    #[id = "rawosc"]
+    pub raw_osc: BoolParam,
+
+    /// Slowly drift the pitch of each voice, like an analog synth
+    #[id = "drift"]
+    pub analog_drift: BoolParam,
+
+    /// Reset the oscillator phases at the start of each note
+    #[id = "phrst"]
+    pub phase_reset: BoolParam,
+
+    /// Start each polyphonic voice's oscillators at a different phase when
+    /// the phase is reset
+    #[id = "dephs"]
+    pub dephase: BoolParam,
+
+    /// Use a random phase for each note, rather than spreading the voices
+    /// evenly, when [CulSynthParams::dephase] is set
+    #[id = "phrnd"]
+    pub phase_randomize: BoolParam,
+
+    /// Stretch tuning, in cents per octave away from A4
+    #[id = "stretch"]
+    pub stretch_tuning: FloatParam,
+
+    /// Attack time of the sidechain envelope follower, in milliseconds
+    #[id = "scatk"]
+    pub sidechain_attack: FloatParam,
+
+    /// Release time of the sidechain envelope follower, in milliseconds
+    #[id = "screl"]
+    pub sidechain_release: FloatParam,
+
+    /// Follow the RMS level of the sidechain, rather than its peak level
+    #[id = "scrms"]
+    pub sidechain_rms: BoolParam,
+
+    /// The strength of the gentle high frequency rolloff on the main output
+    /// (see [crate::analog_tone::AnalogTone]), from 0 (off) to 1
+    #[id = "tone"]
+    pub analog_tone: FloatParam,
+
+    #[nested(id_prefix = "cmp", group = "comp")]
+    pub compressor: CompressorPluginParams,
+
+    #[nested(id_prefix = "lim", group = "limit")]
+    pub limiter: LimiterPluginParams,
+
+    /// Use the dark editor theme (rather than the light theme)
+    #[id = "dark"]
+    pub dark_mode: BoolParam,
+
+    /// Draw the editor's text and borders with maximum contrast
+    #[id = "hicon"]
+    pub high_contrast: BoolParam,
 }
 
 impl CulSynthParams {
@@ -523,11 +1050,35 @@ impl CulSynthParams {
     }
 }
 
+fn new_time_param_ms(name: &str, default_ms: f32) -> FloatParam {
+    FloatParam::new(
+        name,
+        default_ms,
+        FloatRange::Skewed {
+            min: 0.1,
+            max: 2000.,
+            factor: FloatRange::skew_factor(-2.),
+        },
+    )
+    .with_unit(" ms")
+    .with_value_to_string(formatters::v2s_f32_rounded(1))
+}
+
 impl Default for CulSynthParams {
     fn default() -> Self {
         Self {
             editor_state: crate::editor::default_state(),
+            voice_state: Default::default(),
+            cc_map: Default::default(),
             osc_sync: BoolParam::new("Oscillator Sync", false),
+            osc_ratio: IntParam::new(
+                "Oscillator Ratio",
+                OscRatio::Free as i32,
+                IntRange::Linear {
+                    min: OscRatio::Free as i32,
+                    max: OscRatio::SevenHalves as i32,
+                },
+            ),
             osc1: Default::default(),
             osc2: Default::default(),
             ringmod: Default::default(),
@@ -539,6 +1090,82 @@ impl Default for CulSynthParams {
             env1: EnvPluginParams::new("Mod Envelope 1"),
             env2: EnvPluginParams::new("Mod Envelope 2"),
             modmatrix: ModMatrixPluginParams::new(),
+            mono_mode: IntParam::new(
+                "Mono Mode",
+                MonoMode::SingleTrigger as i32,
+                IntRange::Linear {
+                    min: MonoMode::SingleTrigger as i32,
+                    max: MonoMode::Retrigger as i32,
+                },
+            )
+            .non_automatable()
+            .with_value_to_string(Arc::new(|x| {
+                MonoMode::try_from(x as u8).unwrap_or_default().to_str().to_owned()
+            })),
+            chord: Default::default(),
+            scale_mask: IntParam::new(
+                "Scale Quantize",
+                CHROMATIC_MASK as i32,
+                IntRange::Linear {
+                    min: 0,
+                    max: CHROMATIC_MASK as i32,
+                },
+            )
+            .non_automatable()
+            .with_value_to_string(Arc::new(|x| match Scale::from_mask(x as u16) {
+                Some(scale) => scale.to_str().to_owned(),
+                None => "Custom".to_owned(),
+            })),
+            poly_spread: FloatParam::new(
+                "Poly Spread",
+                0.,
+                FloatRange::Linear { min: 0., max: 1. },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            poly_spread_mode: IntParam::new(
+                "Poly Spread Mode",
+                SpreadMode::Pitch as i32,
+                IntRange::Linear {
+                    min: SpreadMode::Pitch as i32,
+                    max: SpreadMode::RoundRobin as i32,
+                },
+            )
+            .non_automatable()
+            .with_value_to_string(Arc::new(|x| {
+                SpreadMode::try_from(x as u8).unwrap_or_default().to_str().to_owned()
+            })),
+            raw_osc: BoolParam::new("Raw Oscillator Monitor", false),
+            analog_drift: BoolParam::new("Analog Drift", false),
+            phase_reset: BoolParam::new("Oscillator Phase Reset", false),
+            dephase: BoolParam::new("Voice De-phase", false),
+            phase_randomize: BoolParam::new("Randomize Voice Phase", false),
+            stretch_tuning: FloatParam::new(
+                "Stretch Tuning",
+                0.,
+                FloatRange::Linear {
+                    min: -25.,
+                    max: 25.,
+                },
+            )
+            .with_unit(" ct/oct")
+            .with_value_to_string(formatters::v2s_f32_rounded(1)),
+            sidechain_attack: new_time_param_ms("Sidechain Attack", 5f32),
+            sidechain_release: new_time_param_ms("Sidechain Release", 100f32),
+            sidechain_rms: BoolParam::new("Sidechain RMS Detection", false),
+            analog_tone: FloatParam::new(
+                "Analog Tone",
+                0.,
+                FloatRange::Linear { min: 0., max: 1. },
+            )
+            .with_unit("%")
+            .with_value_to_string(formatters::v2s_f32_percentage(0))
+            .with_string_to_value(formatters::s2v_f32_percentage()),
+            compressor: Default::default(),
+            limiter: Default::default(),
+            dark_mode: BoolParam::new("Dark Mode", true).non_automatable().hide(),
+            high_contrast: BoolParam::new("High Contrast", false).non_automatable().hide(),
         }
     }
 }
@@ -550,6 +1177,7 @@ impl From<&CulSynthParams> for VoiceParams<i16> {
                 primary: MixOscParams::from(&value.osc1),
                 secondary: MixOscParams::from(&value.osc2),
                 sync: value.osc_sync.value(),
+                ratio: OscRatio::try_from(value.osc_ratio.value() as u8).unwrap_or_default(),
             },
             ring_p: RingModParams::from(&value.ringmod),
             filt_p: ModFiltParams::from(&value.filt),
@@ -559,6 +1187,324 @@ impl From<&CulSynthParams> for VoiceParams<i16> {
             lfo2_p: LfoParams::from(&value.lfo2),
             env1_p: EnvParams::from(&value.env1),
             env2_p: EnvParams::from(&value.env2),
+            raw_osc: value.raw_osc.value(),
+            drift_p: Default::default(),
+            analog_drift: value.analog_drift.value(),
+            phase_reset: value.phase_reset.value(),
+            stretch_p: StretchTuningParams {
+                stretch: IScalarFxP::saturating_from_num(value.stretch_tuning.value() / 100.),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Migrate the parameter values of a preset saved by an older version of the
+/// plugin to the current parameters.  This runs before the host's state is
+/// loaded (see [Plugin::filter_state]).
+pub fn migrate_state(params: &mut BTreeMap<String, ParamValue>) {
+    // The LFO retrigger toggle became a ResetMode selector with the same id
+    for id in ["lf1retrigger", "lf2retrigger"] {
+        if let Some(ParamValue::Bool(retrigger)) = params.get(id) {
+            let mode = if *retrigger {
+                ResetMode::Soft
+            } else {
+                ResetMode::None
+            };
+            params.insert(id.to_owned(), ParamValue::I32(mode as i32));
+        }
+    }
+    // The envelope sustain level became relative to the attack peak
+    for prefix in ["envA", "envF", "env1", "env2"] {
+        let (old_id, peak_id) = (prefix.to_owned() + "s", prefix.to_owned() + "p");
+        if let Some(ParamValue::I32(sustain)) = params.remove(&old_id) {
+            let attack_peak = match params.get(&peak_id) {
+                Some(ParamValue::I32(peak)) => ScalarFxP::from_bits(*peak as u16),
+                _ => ScalarFxP::MAX,
+            };
+            let env = EnvParams {
+                attack_peak,
+                sustain: ScalarFxP::from_bits(sustain as u16),
+                ..Default::default()
+            }
+            .from_absolute_sustain();
+            params.insert(peak_id, ParamValue::I32(env.attack_peak.to_bits() as i32));
+            params.insert(
+                prefix.to_owned() + "sl",
+                ParamValue::I32(env.sustain.to_bits() as i32),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_mod_matrix_is_empty() {
+        let matrix = ModMatrixPluginParams::new();
+        assert_eq!(matrix.slots().count(), ModSrc::numel() * 4);
+        assert!(matrix.is_empty());
+    }
+
+    /// Stands in for the host, recording the value of each parameter it is
+    /// asked to set by name
+    #[derive(Default)]
+    struct RecordingContext {
+        values: std::sync::Mutex<std::collections::HashMap<String, f32>>,
+    }
+
+    impl GuiContext for RecordingContext {
+        fn plugin_api(&self) -> PluginApi {
+            PluginApi::Standalone
+        }
+        fn request_resize(&self) -> bool {
+            false
+        }
+        unsafe fn raw_begin_set_parameter(&self, _param: ParamPtr) {}
+        unsafe fn raw_set_parameter_normalized(&self, param: ParamPtr, normalized: f32) {
+            let name = param.name().to_owned();
+            self.values.lock().unwrap().insert(name, normalized);
+        }
+        unsafe fn raw_end_set_parameter(&self, _param: ParamPtr) {}
+        fn get_state(&self) -> PluginState {
+            panic!("the mod matrix tests never save the plugin state")
+        }
+        fn set_state(&self, _state: PluginState) {}
+    }
+
+    #[test]
+    fn mod_matrix_clear_all() {
+        let mut matrix = ModMatrixPluginParams::new();
+        let routes = [
+            (
+                ModSrc::Velocity,
+                ModDest::FiltCutoff,
+                IScalarFxP::lit("0.5"),
+            ),
+            (
+                ModSrc::ModWheel,
+                ModDest::Osc1Shape,
+                IScalarFxP::lit("-0.25"),
+            ),
+            (ModSrc::Env2, ModDest::Osc2Morph, IScalarFxP::MIN),
+            (ModSrc::Lfo1, ModDest::Pan, IScalarFxP::MAX),
+            (ModSrc::EnvAmp, ModDest::RingMod, IScalarFxP::lit("0.125")),
+        ];
+        // Fill the first slot of each row with a route
+        for (src, dest, mag) in routes {
+            let row = match src {
+                ModSrc::Velocity => &mut matrix.velocity,
+                ModSrc::ModWheel => &mut matrix.modwheel,
+                ModSrc::Env2 => &mut matrix.env2,
+                ModSrc::Lfo1 => &mut matrix.lfo1,
+                ModSrc::EnvAmp => &mut matrix.env_vca,
+                _ => unreachable!(),
+            };
+            let rng = IntRange::Linear {
+                min: ModDest::min() as i32,
+                max: ModDest::max() as i32,
+            };
+            row.a = IntParam::new(src.to_str().to_owned() + " A", dest as i32, rng);
+            row.a_magnitude = new_fixed_param(src.to_str().to_owned() + " A Mag", mag);
+        }
+        assert!(!matrix.is_empty());
+        let filled: Vec<_> = matrix
+            .slots()
+            .filter(|(dest, _)| dest.value() != ModDest::Null as i32)
+            .map(|(dest, mag)| (dest.value(), mag.value()))
+            .collect();
+        let expected: Vec<_> = routes
+            .iter()
+            .map(|(_, dest, mag)| (*dest as i32, mag.to_bits() as i32))
+            .collect();
+        assert_eq!(filled, expected);
+        // Clearing sets every slot of every row to no destination and zero
+        // magnitude
+        let context = RecordingContext::default();
+        matrix.clear_all(&ParamSetter::new(&context));
+        let values = context.values.lock().unwrap();
+        assert_eq!(values.len(), matrix.slots().count() * 2);
+        for (dest, mag) in matrix.slots() {
+            assert_eq!(
+                dest.preview_plain(values[dest.name()]),
+                ModDest::Null as i32
+            );
+            assert_eq!(mag.preview_plain(values[mag.name()]), 0);
+        }
+    }
+
+    #[test]
+    fn mod_matrix_rows_match_sources() {
+        let matrix = ModMatrixPluginParams::new();
+        for src in ModSrc::elements() {
+            let row = matrix.row(*src);
+            assert_eq!(row.is_secondary(), src.is_secondary());
+            // Every destination the editor offers for this row is in range
+            // for its parameters
+            let (dest_param, _) = row.slot(0);
+            for dest in ModDest::elements_secondary_if(row.is_secondary()) {
+                let value = dest as i32;
+                assert_eq!(
+                    dest_param.preview_plain(dest_param.preview_normalized(value)),
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn mod_matrix_text_round_trip() {
+        let mut matrix = ModMatrix::<i16>::default();
+        matrix.rows[ModSrc::Lfo1 as usize].1[0] = (ModDest::FiltCutoff, IScalarFxP::lit("0.5"));
+        matrix.rows[ModSrc::Lfo1 as usize].1[2] = (ModDest::Pan, IScalarFxP::from_bits(-1));
+        matrix.rows[ModSrc::Env2 as usize].1[3] = (ModDest::Osc1Shape, IScalarFxP::MIN);
+        matrix.rows[ModSrc::Velocity as usize].1[1] = (ModDest::Lfo1Rate, IScalarFxP::ZERO);
+        let text = modmatrix_to_text(&matrix);
+        assert_eq!(
+            text,
+            "Velocity:Lfo1Rate=0, Envelope 2:Osc1Shape=-1, \
+             LFO 1:FiltCutoff=0.5, LFO 1:Pan=-0.00003"
+        );
+        // Each source has the same routes in the same order, though the empty
+        // slots are moved to the end of each row
+        let routes = |matrix: &ModMatrix<i16>| {
+            matrix.rows.map(|(_, entries)| {
+                entries
+                    .into_iter()
+                    .filter(|(dest, _)| *dest != ModDest::Null)
+                    .collect::<Vec<_>>()
+            })
+        };
+        let parsed = modmatrix_from_text(&text).unwrap();
+        assert!(routes(&parsed) == routes(&matrix));
+        assert_eq!(modmatrix_to_text(&parsed), text);
+        assert_eq!(modmatrix_to_text(&modmatrix_from_text("").unwrap()), "");
+    }
+
+    #[test]
+    fn mod_matrix_text_errors() {
+        for text in [
+            "LFO 1:FiltCutoff",
+            "LFO 1=FiltCutoff:0.5",
+            "LFO 3:FiltCutoff=0.5",
+            "LFO 1:Cutoff=0.5",
+            "LFO 1:FiltCutoff=half",
+            "LFO 1:Lfo1Rate=0.5",
+            "LFO 2:Lfo2Rate=0.5",
+            "Velocity:Pan=0.1, Velocity:Pan=0.2, Velocity:Pan=0.3, Velocity:Pan=0.4, Velocity:Pan=0.5",
+        ] {
+            assert!(modmatrix_from_text(text).is_err(), "{}", text);
+        }
+        // Whitespace is ignored, and depths saturate
+        let matrix = modmatrix_from_text(" Mod Wheel : Pan = 2 ,").unwrap();
+        let depth = matrix.get_modulation(ModSrc::ModWheel, ModDest::Pan);
+        assert_eq!(depth, Some(IScalarFxP::MAX));
+    }
+
+    #[test]
+    fn filter_response_matches_format() {
+        // The default is a low pass at the top of the range
+        let filt = FiltPluginParams::default();
+        let freqs = [100f32, 1000f32, 10000f32, 20000f32];
+        let fixed = filt.response_db(48000, true, &freqs);
+        let float = filt.response_db(48000, false, &freqs);
+        for (fixed, float) in fixed.iter().zip(float.iter()) {
+            assert!((fixed - float).abs() < 0.5f32, "{} {}", fixed, float);
+        }
+        assert!(fixed[0].abs() < 0.1f32);
+        assert!(fixed[3] < -6f32);
+        // Fixed point isn't supported at every sample rate
+        assert_eq!(filt.response_db(96000, true, &freqs).len(), freqs.len());
+    }
+
+    #[test]
+    fn lfo_retrigger_migrates_to_reset_mode() {
+        let mut params = BTreeMap::new();
+        params.insert("lf1retrigger".to_owned(), ParamValue::Bool(true));
+        params.insert("lf2retrigger".to_owned(), ParamValue::Bool(false));
+        migrate_state(&mut params);
+        let soft = ResetMode::Soft as i32;
+        let none = ResetMode::None as i32;
+        assert!(matches!(params["lf1retrigger"], ParamValue::I32(x) if x == soft));
+        assert!(matches!(params["lf2retrigger"], ParamValue::I32(x) if x == none));
+        // Presets saved with a reset mode are left alone
+        params.insert(
+            "lf1retrigger".to_owned(),
+            ParamValue::I32(ResetMode::Hard as i32),
+        );
+        migrate_state(&mut params);
+        let hard = ResetMode::Hard as i32;
+        assert!(matches!(params["lf1retrigger"], ParamValue::I32(x) if x == hard));
+    }
+
+    #[test]
+    #[cfg(feature = "fixed")]
+    fn absolute_sustain_migrates_to_same_envelope() {
+        use culsynth::context::ContextFxP;
+        use culsynth::devices::{Device, Env};
+        // A preset saved while the sustain was an absolute level: the VCA
+        // envelope peaks at 80% and sustains at 50%, and the VCF envelope
+        // sustains above its peak (so its attack rose to the sustain level)
+        let mut params = BTreeMap::new();
+        let level = |x: &str| ParamValue::I32(ScalarFxP::lit(x).to_bits() as i32);
+        params.insert("envAp".to_owned(), level("0.8"));
+        params.insert("envAs".to_owned(), level("0.5"));
+        params.insert("envFp".to_owned(), level("0.2"));
+        params.insert("envFs".to_owned(), level("0.5"));
+        migrate_state(&mut params);
+        assert!(!params.contains_key("envAs") && !params.contains_key("envFs"));
+        let ctx = ContextFxP::new_480();
+        for (prefix, peak) in [("envA", 0.8f32), ("envF", 0.5f32)] {
+            let value = |id: &str| match params[&(prefix.to_owned() + id)] {
+                ParamValue::I32(x) => ScalarFxP::from_bits(x as u16),
+                _ => panic!("{}{} is not an integer", prefix, id),
+            };
+            let env_params = EnvParams {
+                attack: EnvParamFxP::lit("0.01"),
+                attack_peak: value("p"),
+                decay: EnvParamFxP::lit("0.01"),
+                sustain: value("sl"),
+                ..Default::default()
+            };
+            // The migrated envelope still peaks and settles where it did
+            let mut env = Env::<i16>::default();
+            let out: Vec<f32> =
+                (0..24000).map(|_| env.next(&ctx, true, env_params.clone()).to_num()).collect();
+            let max = out.iter().copied().fold(0f32, f32::max);
+            assert!((max - peak).abs() < 0.03 * peak, "{} {}", prefix, max);
+            let last = out[out.len() - 1];
+            assert!((last - 0.5).abs() < 0.002, "{} {}", prefix, last);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "fixed")]
+    fn mixer_level_step_is_ramped() {
+        use culsynth::context::ContextFxP;
+        use culsynth::devices::{Device, RingMod, RingModInput};
+        use culsynth::SampleFxP;
+        let ctx = ContextFxP::new_480();
+        let mixer = RingModPluginParams::default();
+        let full = ScalarFxP::MAX.to_bits() as i32;
+        mixer.mix_a.smoothed.reset(full);
+        // Step the level of oscillator 1 from full to silent
+        mixer.mix_a.smoothed.set_target(48000f32, 0);
+        let mut ringmod = RingMod::<i16>::default();
+        let input = RingModInput {
+            signal_a: SampleFxP::lit("0.5"),
+            signal_b: SampleFxP::ZERO,
+        };
+        let out: Vec<_> =
+            (0..4800).map(|_| ringmod.next(&ctx, input.clone(), (&mixer).into())).collect();
+        // The output ramps down over ~50ms rather than jumping
+        assert!(out[0] > SampleFxP::lit("0.49"));
+        assert!(out[1200] > SampleFxP::lit("0.2") && out[1200] < SampleFxP::lit("0.3"));
+        assert_eq!(out[4799], SampleFxP::ZERO);
+        for pair in out.windows(2) {
+            assert!(pair[1] <= pair[0]);
+            assert!(pair[0] - pair[1] < SampleFxP::lit("0.001"));
         }
     }
 }
@@ -0,0 +1,441 @@
+//! This module contains the patch randomizer, which sets the parameters of
+//! the synth to random values for inspiration (see [PatchRandomizer]).
+//!
+//! Rather than choosing uniformly from the full range of each parameter, each
+//! one has its own range (and sometimes depends on others), chosen so that the
+//! result is always audible and rarely harsh.
+
+use culsynth::devices::{LfoWave, OscRatio};
+use culsynth::voice::modulation::{ModDest, ModSrc};
+use culsynth::{EnvParamFxP, IScalarFxP, LfoFreqFxP, NoteFxP, ScalarFxP};
+use nih_plug::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::pluginparams::{
+    CulSynthParams, EnvPluginParams, FiltPluginParams, LfoPluginParams, ModMatrixPluginParams,
+    OscPluginParams, RingModPluginParams,
+};
+
+/// The sections of a patch that can be locked, so that the [PatchRandomizer]
+/// leaves them as they are
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum PatchSection {
+    /// Both oscillators, including oscillator sync and the frequency ratio
+    #[default]
+    Oscillators,
+    /// The oscillator mixer and ring modulator
+    Mixer,
+    /// The filter
+    Filter,
+    /// The filter and amplifier envelopes
+    Envelopes,
+    /// The LFOs and modulation envelopes
+    Modulators,
+    /// The modulation matrix
+    ModMatrix,
+}
+
+impl PatchSection {
+    const ELEM: [PatchSection; 6] = [
+        Self::Oscillators,
+        Self::Mixer,
+        Self::Filter,
+        Self::Envelopes,
+        Self::Modulators,
+        Self::ModMatrix,
+    ];
+    /// Returns a slice to all of the possible PatchSections
+    pub const fn sections() -> &'static [PatchSection] {
+        &Self::ELEM
+    }
+    /// Provides the name of the section
+    pub const fn to_str(&self) -> &'static str {
+        [
+            "Oscillators",
+            "Mixer",
+            "Filter",
+            "Envelopes",
+            "Modulators",
+            "Mod Matrix",
+        ][*self as usize]
+    }
+}
+
+/// A new value for a single parameter
+pub enum ParamChange<'a> {
+    /// Set an integer (or fixed point) parameter to a plain value
+    Int(&'a IntParam, i32),
+    /// Set a boolean parameter
+    Bool(&'a BoolParam, bool),
+}
+
+impl ParamChange<'_> {
+    /// Make this change through `setter`
+    pub fn apply(&self, setter: &ParamSetter) {
+        match self {
+            Self::Int(param, value) => {
+                setter.begin_set_parameter(*param);
+                setter.set_parameter(*param, *value);
+                setter.end_set_parameter(*param);
+            }
+            Self::Bool(param, value) => {
+                setter.begin_set_parameter(*param);
+                setter.set_parameter(*param, *value);
+                setter.end_set_parameter(*param);
+            }
+        }
+    }
+}
+
+/// The plain value of a [ScalarFxP] parameter between `min` and `max`
+fn scalar(rng: &mut impl Rng, min: f32, max: f32) -> i32 {
+    ScalarFxP::saturating_from_num(rng.gen_range(min..=max)).to_bits() as i32
+}
+
+/// The plain value of a [NoteFxP] parameter (e.g. the filter cutoff) between
+/// the MIDI notes `min` and `max`
+fn note(rng: &mut impl Rng, min: f32, max: f32) -> i32 {
+    NoteFxP::saturating_from_num(rng.gen_range(min..=max)).to_bits() as i32
+}
+
+/// The plain value of an envelope time parameter between `min` and `max`
+/// seconds.  This is skewed towards shorter times, which are more common and
+/// easier to tell apart.
+fn env_time(rng: &mut impl Rng, min: f32, max: f32) -> i32 {
+    let x = rng.gen::<f32>();
+    EnvParamFxP::saturating_from_num(min + (max - min) * x * x).to_bits() as i32
+}
+
+/// The plain value of an LFO rate parameter between `min` and `max` Hz,
+/// spread evenly in pitch rather than frequency
+fn lfo_rate(rng: &mut impl Rng, min: f32, max: f32) -> i32 {
+    let rate = min * (max / min).powf(rng.gen::<f32>());
+    LfoFreqFxP::saturating_from_num(rate).to_bits() as i32
+}
+
+fn randomize_osc<'a>(
+    osc: &'a OscPluginParams,
+    courses: &[i32],
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    out.push(ParamChange::Int(
+        &osc.course,
+        *courses.choose(rng).unwrap_or(&0),
+    ));
+    // Detune by up to 10 cents (the fine tuning is in 512ths of a semitone)
+    out.push(ParamChange::Int(&osc.fine, rng.gen_range(-51..=51)));
+    out.push(ParamChange::Int(&osc.shape, scalar(rng, 0f32, 0.5f32)));
+    out.push(ParamChange::Int(&osc.morph, scalar(rng, 0f32, 1f32)));
+    // One main waveform, so the oscillator is never silent, with some of the
+    // others mixed in quietly
+    let main = rng.gen_range(0..4);
+    for (i, wave) in [&osc.sin, &osc.sq, &osc.tri, &osc.saw].into_iter().enumerate() {
+        let level = if i == main {
+            scalar(rng, 0.6f32, 1f32)
+        } else if rng.gen_bool(0.3) {
+            scalar(rng, 0f32, 0.4f32)
+        } else {
+            0
+        };
+        out.push(ParamChange::Int(wave, level));
+    }
+}
+
+fn randomize_oscillators<'a>(
+    params: &'a CulSynthParams,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    randomize_osc(&params.osc1, &[0, 0, 0, -12, 12], rng, out);
+    randomize_osc(&params.osc2, &[0, 0, -12, 12, 7, -5, 19, 24], rng, out);
+    out.push(ParamChange::Bool(&params.osc_sync, rng.gen_bool(0.2)));
+    let ratio = if rng.gen_bool(0.7) {
+        OscRatio::Free
+    } else {
+        *OscRatio::ratios().choose(rng).unwrap_or(&OscRatio::Free)
+    };
+    out.push(ParamChange::Int(&params.osc_ratio, ratio as i32));
+}
+
+fn randomize_mixer<'a>(
+    ringmod: &'a RingModPluginParams,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    // Oscillator 1 is always audible, with oscillator 2 and the ring
+    // modulator (which is harsh at high levels) mixed in
+    out.push(ParamChange::Int(&ringmod.mix_a, scalar(rng, 0.5f32, 1f32)));
+    let mix_b = if rng.gen_bool(0.6) {
+        scalar(rng, 0.3f32, 1f32)
+    } else {
+        0
+    };
+    out.push(ParamChange::Int(&ringmod.mix_b, mix_b));
+    let mix_mod = if rng.gen_bool(0.2) {
+        scalar(rng, 0.1f32, 0.5f32)
+    } else {
+        0
+    };
+    out.push(ParamChange::Int(&ringmod.mix_mod, mix_mod));
+}
+
+fn randomize_filter<'a>(
+    filt: &'a FiltPluginParams,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    let full = ScalarFxP::MAX.to_bits() as i32;
+    // Mostly low pass.  The cutoff range depends on the mode, so that a band
+    // or high pass filter doesn't remove most of the sound.
+    let (low, band, high, min_cutoff, max_cutoff) = match rng.gen_range(0..20) {
+        0..=14 => (full, 0, 0, 48f32, 120f32),
+        15..=17 => (scalar(rng, 0.3f32, 0.7f32), full, 0, 55f32, 100f32),
+        _ => (0, scalar(rng, 0f32, 0.5f32), full, 36f32, 84f32),
+    };
+    out.push(ParamChange::Int(&filt.low, low));
+    out.push(ParamChange::Int(&filt.band, band));
+    out.push(ParamChange::Int(&filt.high, high));
+    let cutoff = note(rng, min_cutoff, max_cutoff);
+    out.push(ParamChange::Int(&filt.cutoff, cutoff));
+    // Strong resonance at a high cutoff is piercing rather than musical
+    let max_res = if cutoff > NoteFxP::lit("96").to_bits() as i32 {
+        0.5f32
+    } else {
+        0.8f32
+    };
+    out.push(ParamChange::Int(&filt.res, scalar(rng, 0f32, max_res)));
+    out.push(ParamChange::Int(&filt.env, scalar(rng, 0f32, 0.7f32)));
+    out.push(ParamChange::Int(&filt.kbd, scalar(rng, 0f32, 1f32)));
+    out.push(ParamChange::Int(&filt.vel, scalar(rng, 0f32, 0.5f32)));
+}
+
+/// Randomize an envelope with times up to `max_time` seconds and a sustain of
+/// at least `min_sustain`, leaving the reset mode unchanged
+fn randomize_env<'a>(
+    env: &'a EnvPluginParams,
+    max_time: f32,
+    min_sustain: f32,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    out.push(ParamChange::Int(
+        &env.a,
+        env_time(rng, 0f32, max_time / 2f32),
+    ));
+    out.push(ParamChange::Int(&env.d, env_time(rng, 0.05f32, max_time)));
+    out.push(ParamChange::Int(&env.s, scalar(rng, min_sustain, 1f32)));
+    out.push(ParamChange::Int(&env.r, env_time(rng, 0.02f32, max_time)));
+}
+
+fn randomize_envelopes<'a>(
+    params: &'a CulSynthParams,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    // The amplifier envelope always peaks at full level and sustains at an
+    // audible level, so that held notes are never silent
+    randomize_env(&params.env_vca, 2f32, 0.4f32, rng, out);
+    let full = ScalarFxP::MAX.to_bits() as i32;
+    out.push(ParamChange::Int(&params.env_vca.peak, full));
+    randomize_env(&params.env_vcf, 2f32, 0f32, rng, out);
+    out.push(ParamChange::Int(
+        &params.env_vcf.peak,
+        scalar(rng, 0.5f32, 1f32),
+    ));
+}
+
+fn randomize_lfo<'a>(lfo: &'a LfoPluginParams, rng: &mut impl Rng, out: &mut Vec<ParamChange<'a>>) {
+    out.push(ParamChange::Int(&lfo.rate, lfo_rate(rng, 0.1f32, 10f32)));
+    out.push(ParamChange::Int(&lfo.depth, scalar(rng, 0.2f32, 1f32)));
+    let wave = rng.gen_range(LfoWave::Sine as i32..=LfoWave::SampleGlide as i32);
+    out.push(ParamChange::Int(&lfo.wave, wave));
+    out.push(ParamChange::Bool(&lfo.bipolar, rng.gen_bool(0.7)));
+    out.push(ParamChange::Bool(&lfo.invert, rng.gen_bool(0.2)));
+    let slew = if rng.gen_bool(0.3) {
+        env_time(rng, 0f32, 0.05f32)
+    } else {
+        0
+    };
+    out.push(ParamChange::Int(&lfo.slew, slew));
+}
+
+fn randomize_modulators<'a>(
+    params: &'a CulSynthParams,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    randomize_lfo(&params.lfo1, rng, out);
+    randomize_lfo(&params.lfo2, rng, out);
+    for env in [&params.env1, &params.env2] {
+        randomize_env(env, 3f32, 0f32, rng, out);
+        out.push(ParamChange::Int(&env.peak, scalar(rng, 0.5f32, 1f32)));
+    }
+}
+
+/// The plain value of a modulation depth for `dest`.  Pitch is only modulated
+/// a little (a depth of 1 is 32 semitones for the course tuning), and the
+/// master gain is never modulated down, which could silence the voice.
+fn mod_depth(rng: &mut impl Rng, dest: ModDest) -> i32 {
+    let (min, max) = match dest {
+        ModDest::Osc1Course | ModDest::Osc2Course => (0.005f32, 0.04f32),
+        ModDest::Osc1Fine | ModDest::Osc2Fine => (0.05f32, 0.5f32),
+        _ => (0.1f32, 0.6f32),
+    };
+    let mut depth = rng.gen_range(min..=max);
+    if !matches!(dest, ModDest::MasterGain) && rng.gen_bool(0.3) {
+        depth = -depth;
+    }
+    IScalarFxP::saturating_from_num(depth).to_bits() as i32
+}
+
+/// Replace the modulation matrix with between one and four random routes.
+/// Each route is to a destination its source may modulate (see
+/// [ModSrc::can_modulate]), so there are never any cycles.
+fn randomize_modmatrix<'a>(
+    matrix: &'a ModMatrixPluginParams,
+    rng: &mut impl Rng,
+    out: &mut Vec<ParamChange<'a>>,
+) {
+    let mut slots = [[(ModDest::Null, 0); 4]; ModSrc::numel()];
+    for _ in 0..rng.gen_range(1..=4) {
+        let src = *ModSrc::elements().choose(rng).unwrap_or(&ModSrc::Velocity);
+        let row = matrix.row(src);
+        let dests: Vec<ModDest> = ModDest::elements_secondary_if(row.is_secondary())
+            .filter(|dest| !matches!(dest, ModDest::Null) && src.can_modulate(*dest))
+            .collect();
+        let free = slots[src as usize].iter().position(|(dest, _)| matches!(dest, ModDest::Null));
+        if let (Some(slot), Some(dest)) = (free, dests.choose(rng)) {
+            slots[src as usize][slot] = (*dest, mod_depth(rng, *dest));
+        }
+    }
+    for src in ModSrc::elements() {
+        let row = matrix.row(*src);
+        for (i, (dest, depth)) in slots[*src as usize].iter().enumerate() {
+            let (dest_param, depth_param) = row.slot(i);
+            out.push(ParamChange::Int(dest_param, *dest as i32));
+            out.push(ParamChange::Int(depth_param, *depth));
+        }
+    }
+}
+
+/// Sets the patch to random (but musically sensible) values, optionally
+/// leaving some sections of the patch unchanged
+#[derive(Default)]
+pub struct PatchRandomizer {
+    locked: [bool; PatchSection::ELEM.len()],
+}
+
+impl PatchRandomizer {
+    /// Is `section` left unchanged when randomizing?
+    pub fn is_locked(&self, section: PatchSection) -> bool {
+        self.locked[section as usize]
+    }
+    /// Lock or unlock `section`
+    pub fn set_locked(&mut self, section: PatchSection, locked: bool) {
+        self.locked[section as usize] = locked;
+    }
+    /// Randomize every unlocked section of the patch.  Each parameter is set
+    /// through `setter`, just as if it had been changed in the editor, so the
+    /// host can undo the changes.
+    pub fn randomize(&self, params: &CulSynthParams, setter: &ParamSetter) {
+        for change in self.changes(params, &mut rand::thread_rng()) {
+            change.apply(setter);
+        }
+    }
+    /// The changes to `params` that randomize every unlocked section, using
+    /// random numbers from `rng`
+    pub fn changes<'a>(
+        &self,
+        params: &'a CulSynthParams,
+        rng: &mut impl Rng,
+    ) -> Vec<ParamChange<'a>> {
+        let mut out = Vec::new();
+        for section in PatchSection::sections() {
+            if self.is_locked(*section) {
+                continue;
+            }
+            match section {
+                PatchSection::Oscillators => randomize_oscillators(params, rng, &mut out),
+                PatchSection::Mixer => randomize_mixer(&params.ringmod, rng, &mut out),
+                PatchSection::Filter => randomize_filter(&params.filt, rng, &mut out),
+                PatchSection::Envelopes => randomize_envelopes(params, rng, &mut out),
+                PatchSection::Modulators => randomize_modulators(params, rng, &mut out),
+                PatchSection::ModMatrix => randomize_modmatrix(&params.modmatrix, rng, &mut out),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// The new value of `param` in `changes`, if it was changed
+    fn int_value(changes: &[ParamChange], param: &IntParam) -> Option<i32> {
+        changes.iter().find_map(|change| match change {
+            ParamChange::Int(p, value) if std::ptr::eq(*p, param) => Some(*value),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn random_patches_are_sensible() {
+        let params = CulSynthParams::default();
+        let randomizer = PatchRandomizer::default();
+        for seed in 0..500 {
+            let changes = randomizer.changes(&params, &mut StdRng::seed_from_u64(seed));
+            for change in &changes {
+                if let ParamChange::Int(param, value) = change {
+                    let clamped = param.preview_plain(param.preview_normalized(*value));
+                    assert_eq!(clamped, *value, "{} out of range", param.name());
+                }
+            }
+            // Never silent...
+            let half = ScalarFxP::lit("0.5").to_bits() as i32;
+            assert!(int_value(&changes, &params.ringmod.mix_a).unwrap() >= half);
+            assert!(int_value(&changes, &params.env_vca.s).unwrap() >= 4 * half / 5);
+            let osc1 = &params.osc1;
+            assert!(
+                [&osc1.sin, &osc1.sq, &osc1.tri, &osc1.saw].into_iter().any(|wave| int_value(
+                    &changes, wave
+                )
+                .unwrap()
+                    >= half)
+            );
+            // ...and every modulation route is allowed
+            for src in ModSrc::elements() {
+                for (dest, _) in params.modmatrix.row(*src).iter() {
+                    let dest = int_value(&changes, dest).unwrap() as u16;
+                    let dest = ModDest::try_from(dest).unwrap();
+                    assert!(
+                        src.can_modulate(dest),
+                        "{} -> {}",
+                        src.to_str(),
+                        dest.to_str()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn locked_sections_are_unchanged() {
+        let params = CulSynthParams::default();
+        let mut randomizer = PatchRandomizer::default();
+        for section in PatchSection::sections() {
+            randomizer.set_locked(*section, *section != PatchSection::Mixer);
+        }
+        let changes = randomizer.changes(&params, &mut StdRng::seed_from_u64(0));
+        let ringmod = &params.ringmod;
+        assert_eq!(changes.len(), 3);
+        for param in [&ringmod.mix_a, &ringmod.mix_b, &ringmod.mix_mod] {
+            assert!(int_value(&changes, param).is_some());
+        }
+    }
+}
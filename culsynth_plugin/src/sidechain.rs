@@ -0,0 +1,174 @@
+//! This module contains the envelope follower used to turn an external
+//! sidechain audio signal into a modulation source ([ModSrc::Sidechain])
+//!
+//! [ModSrc::Sidechain]: culsynth::voice::modulation::ModSrc::Sidechain
+
+use culsynth::ScalarFxP;
+
+/// A simple envelope follower, using a one-pole smoother with separate
+/// attack and release times.
+///
+/// By default this follows the peak level of the input.  In RMS mode, the
+/// smoother follows the squared input instead, so the level tracks the
+/// loudness of the signal (e.g. a full scale sine wave has a level of about
+/// 0.707) and is less sensitive to short peaks.
+#[derive(Clone, Default)]
+pub struct SidechainFollower {
+    level: f32,
+    // The smoothed peak level, or mean square level in RMS mode
+    state: f32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    rms: bool,
+}
+
+impl SidechainFollower {
+    /// Create a new follower with the given attack/release times (in
+    /// milliseconds) at the given sample rate
+    pub fn new(attack_ms: f32, release_ms: f32, sample_rate: f32) -> Self {
+        let mut ret = Self::default();
+        ret.set_times(attack_ms, release_ms, sample_rate);
+        ret
+    }
+    /// Calculate the one-pole coefficient for a time constant of `ms`
+    /// milliseconds at the sample rate `sample_rate`
+    fn coeff(ms: f32, sample_rate: f32) -> f32 {
+        let samples = ms * sample_rate / 1000f32;
+        if samples < 1f32 {
+            0f32
+        } else {
+            (-1f32 / samples).exp()
+        }
+    }
+    /// Update the attack/release times (in milliseconds)
+    pub fn set_times(&mut self, attack_ms: f32, release_ms: f32, sample_rate: f32) {
+        self.attack_coeff = Self::coeff(attack_ms, sample_rate);
+        self.release_coeff = Self::coeff(release_ms, sample_rate);
+    }
+    /// Switch between following the RMS level (if `rms` is true) and the
+    /// peak level of the input.  The current level carries over.
+    pub fn set_rms(&mut self, rms: bool) {
+        if rms != self.rms {
+            self.rms = rms;
+            self.state = if rms {
+                self.level * self.level
+            } else {
+                self.level
+            };
+        }
+    }
+    /// Process the next frame of sidechain input (one sample per channel),
+    /// returning the current level, normalized from 0 to 1
+    pub fn next(&mut self, frame: impl IntoIterator<Item = f32>) -> f32 {
+        let peak = frame.into_iter().fold(0f32, |acc, x| acc.max(x.abs())).min(1f32);
+        let input = if self.rms { peak * peak } else { peak };
+        let coeff = if input > self.state {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.state = input + coeff * (self.state - input);
+        self.level = if self.rms {
+            self.state.sqrt()
+        } else {
+            self.state
+        };
+        self.level
+    }
+    /// The current level of the follower, normalized from 0 to 1
+    pub fn level(&self) -> f32 {
+        self.level
+    }
+    /// The current level of the follower, as a [ScalarFxP]
+    pub fn level_fixed(&self) -> ScalarFxP {
+        ScalarFxP::saturating_from_num(self.level)
+    }
+    /// Reset the follower to silence (e.g. if the sidechain is disconnected)
+    pub fn reset(&mut self) {
+        self.level = 0f32;
+        self.state = 0f32;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_sine_envelope() {
+        let sr = 48000f32;
+        let mut follower = SidechainFollower::new(1f32, 50f32, sr);
+        let sine =
+            |n: usize, amp: f32| amp * (n as f32 * 440f32 * std::f32::consts::TAU / sr).sin();
+        // 100ms of a half-amplitude sine should settle near 0.5
+        for n in 0..4800 {
+            follower.next([sine(n, 0.5)]);
+        }
+        assert!((follower.level() - 0.5).abs() < 0.05);
+        // Jumping to full scale should be tracked quickly by the attack
+        for n in 0..4800 {
+            follower.next([sine(n, 1.0)]);
+        }
+        assert!(follower.level() > 0.9);
+        // ...and silence should decay away with the release
+        for _ in 0..48000 {
+            follower.next([0f32]);
+        }
+        assert!(follower.level() < 0.01);
+        assert_eq!(follower.level_fixed(), ScalarFxP::ZERO);
+    }
+
+    #[test]
+    fn tracks_time_constants() {
+        let sr = 48000f32;
+        let (attack_ms, release_ms) = (10f32, 100f32);
+        let samples = |ms: f32| (ms * sr / 1000f32) as usize;
+        let mut follower = SidechainFollower::new(attack_ms, release_ms, sr);
+        // After one time constant, a step should be ~63% of the way there...
+        for _ in 0..samples(attack_ms) {
+            follower.next([1f32, -1f32]);
+        }
+        let expected = 1f32 - (-1f32).exp();
+        assert!(
+            (follower.level() - expected).abs() < 0.01,
+            "{}",
+            follower.level()
+        );
+        for _ in 0..samples(10f32 * attack_ms) {
+            follower.next([1f32, -1f32]);
+        }
+        // ...and release back down to ~37% after one release time constant
+        for _ in 0..samples(release_ms) {
+            follower.next([0f32, 0f32]);
+        }
+        let expected = (-1f32).exp();
+        assert!(
+            (follower.level() - expected).abs() < 0.01,
+            "{}",
+            follower.level()
+        );
+    }
+
+    #[test]
+    fn rms_level() {
+        let sr = 48000f32;
+        let mut follower = SidechainFollower::new(10f32, 10f32, sr);
+        follower.set_rms(true);
+        let sine = |n: usize| (n as f32 * 440f32 * std::f32::consts::TAU / sr).sin();
+        for n in 0..48000 {
+            follower.next([sine(n)]);
+        }
+        // The RMS of a sine wave is 1/sqrt(2) of its peak
+        let level = follower.level();
+        assert!(
+            (level - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.03,
+            "{}",
+            level
+        );
+        // Switching modes picks up from the current level
+        follower.set_rms(false);
+        assert_eq!(follower.level(), level);
+        follower.next([0f32]);
+        assert!(follower.level() < level);
+    }
+}
@@ -0,0 +1,281 @@
+//! A plain, read-only view of the current patch, for external editors and
+//! visualizers (see [CulSynthParams::snapshot]).
+//!
+//! All values are in real units (seconds, Hz, semitones, or a level from 0 to
+//! 1), and enumerated settings are given by name, so the snapshot can be used
+//! (or serialized) without any knowledge of the fixed point representation
+//! used by the plugin parameters.
+
+use culsynth::devices::{LfoWave, OscRatio, ResetMode};
+use culsynth::voice::modulation::{ModDest, ModSrc, MOD_SLOTS};
+use culsynth::{EnvParamFxP, IScalarFxP, LfoFreqFxP, NoteFxP, ScalarFxP};
+use serde::Serialize;
+
+use crate::pluginparams::*;
+
+/// Convert the value of a percentage parameter to a level from 0 to 1
+fn scalar(value: i32) -> f32 {
+    ScalarFxP::from_bits(value as u16).to_num()
+}
+
+/// Convert the value of an envelope time parameter to seconds
+fn env_time(value: i32) -> f32 {
+    EnvParamFxP::from_bits(value as u16).to_num()
+}
+
+/// The name of the [ResetMode] selected by a parameter
+fn reset_mode(value: i32) -> &'static str {
+    ResetMode::try_from(value as u8).unwrap_or_default().to_str()
+}
+
+/// A snapshot of an [OscPluginParams]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct OscSnapshot {
+    /// Tuning, in semitones
+    pub tune: f32,
+    pub shape: f32,
+    pub sin: f32,
+    pub sq: f32,
+    pub tri: f32,
+    pub saw: f32,
+    pub morph: f32,
+}
+
+impl From<&OscPluginParams> for OscSnapshot {
+    fn from(value: &OscPluginParams) -> Self {
+        Self {
+            tune: value.course.value() as f32 + value.fine.value() as f32 / 512f32,
+            shape: scalar(value.shape.value()),
+            sin: scalar(value.sin.value()),
+            sq: scalar(value.sq.value()),
+            tri: scalar(value.tri.value()),
+            saw: scalar(value.saw.value()),
+            morph: scalar(value.morph.value()),
+        }
+    }
+}
+
+/// A snapshot of a [RingModPluginParams]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct RingModSnapshot {
+    pub mix_a: f32,
+    pub mix_b: f32,
+    pub mix_mod: f32,
+}
+
+impl From<&RingModPluginParams> for RingModSnapshot {
+    fn from(value: &RingModPluginParams) -> Self {
+        Self {
+            mix_a: scalar(value.mix_a.value()),
+            mix_b: scalar(value.mix_b.value()),
+            mix_mod: scalar(value.mix_mod.value()),
+        }
+    }
+}
+
+/// A snapshot of a [FiltPluginParams]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct FiltSnapshot {
+    /// Cutoff frequency, in Hz
+    pub cutoff: f32,
+    pub resonance: f32,
+    pub kbd_tracking: f32,
+    pub vel_mod: f32,
+    pub env_mod: f32,
+    pub low_mix: f32,
+    pub band_mix: f32,
+    pub high_mix: f32,
+}
+
+impl From<&FiltPluginParams> for FiltSnapshot {
+    fn from(value: &FiltPluginParams) -> Self {
+        let cutoff = NoteFxP::from_bits(value.cutoff.value() as u16);
+        Self {
+            cutoff: culsynth::midi_note_to_frequency(cutoff).to_num(),
+            resonance: scalar(value.res.value()),
+            kbd_tracking: scalar(value.kbd.value()),
+            vel_mod: scalar(value.vel.value()),
+            env_mod: scalar(value.env.value()),
+            low_mix: scalar(value.low.value()),
+            band_mix: scalar(value.band.value()),
+            high_mix: scalar(value.high.value()),
+        }
+    }
+}
+
+/// A snapshot of an [EnvPluginParams].  Times are in seconds.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EnvSnapshot {
+    pub attack: f32,
+    pub attack_peak: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+    /// The name of the [ResetMode]
+    pub reset: &'static str,
+}
+
+impl From<&EnvPluginParams> for EnvSnapshot {
+    fn from(value: &EnvPluginParams) -> Self {
+        Self {
+            attack: env_time(value.a.value()),
+            attack_peak: scalar(value.peak.value()),
+            decay: env_time(value.d.value()),
+            sustain: scalar(value.s.value()),
+            release: env_time(value.r.value()),
+            reset: reset_mode(value.reset.value()),
+        }
+    }
+}
+
+/// A snapshot of a [LfoPluginParams]
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct LfoSnapshot {
+    /// Rate, in Hz
+    pub rate: f32,
+    pub depth: f32,
+    /// The name of the [LfoWave]
+    pub wave: &'static str,
+    /// The name of the [ResetMode]
+    pub reset: &'static str,
+    pub bipolar: bool,
+    pub invert: bool,
+    /// Slew time, in seconds
+    pub slew: f32,
+}
+
+impl From<&LfoPluginParams> for LfoSnapshot {
+    fn from(value: &LfoPluginParams) -> Self {
+        Self {
+            rate: LfoFreqFxP::from_bits(value.rate.value() as u16).to_num(),
+            depth: scalar(value.depth.value()),
+            wave: LfoWave::try_from(value.wave.value() as u8).unwrap_or_default().to_str(),
+            reset: reset_mode(value.reset.value()),
+            bipolar: value.bipolar.value(),
+            invert: value.invert.value(),
+            slew: env_time(value.slew.value()),
+        }
+    }
+}
+
+/// A single slot in a row of the modulation matrix
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ModSlotSnapshot {
+    /// The name of the [ModDest]
+    pub dest: &'static str,
+    /// Modulation depth, from -1 to 1
+    pub depth: f32,
+}
+
+/// A row of the modulation matrix
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ModRowSnapshot {
+    /// The name of the [ModSrc]
+    pub src: &'static str,
+    pub slots: [ModSlotSnapshot; MOD_SLOTS],
+}
+
+/// A snapshot of every parameter of the synth
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct PatchSnapshot {
+    pub osc_sync: bool,
+    /// The name of the [OscRatio] locking oscillator 2 to oscillator 1
+    pub osc_ratio: &'static str,
+    pub osc1: OscSnapshot,
+    pub osc2: OscSnapshot,
+    pub ringmod: RingModSnapshot,
+    pub filt: FiltSnapshot,
+    pub env_vca: EnvSnapshot,
+    pub env_vcf: EnvSnapshot,
+    pub lfo1: LfoSnapshot,
+    pub lfo2: LfoSnapshot,
+    pub env1: EnvSnapshot,
+    pub env2: EnvSnapshot,
+    /// The modulation matrix, with one row per [ModSrc]
+    pub modmatrix: [ModRowSnapshot; ModSrc::numel()],
+    pub raw_osc: bool,
+    pub analog_drift: bool,
+    pub phase_reset: bool,
+    pub dephase: bool,
+    pub phase_randomize: bool,
+    /// Stretch tuning, in cents per octave
+    pub stretch_tuning: f32,
+    /// Attack time of the sidechain envelope follower, in milliseconds
+    pub sidechain_attack: f32,
+    /// Release time of the sidechain envelope follower, in milliseconds
+    pub sidechain_release: f32,
+    /// True if the sidechain envelope follower follows the RMS level, rather
+    /// than the peak level
+    pub sidechain_rms: bool,
+}
+
+impl CulSynthParams {
+    /// Take a snapshot of the current value of every parameter.
+    ///
+    /// This reads the target value of each parameter, so it doesn't disturb
+    /// any smoothing in progress and may be called from any thread.
+    pub fn snapshot(&self) -> PatchSnapshot {
+        PatchSnapshot {
+            osc_sync: self.osc_sync.value(),
+            osc_ratio: OscRatio::try_from(self.osc_ratio.value() as u8)
+                .unwrap_or_default()
+                .to_str(),
+            osc1: (&self.osc1).into(),
+            osc2: (&self.osc2).into(),
+            ringmod: (&self.ringmod).into(),
+            filt: (&self.filt).into(),
+            env_vca: (&self.env_vca).into(),
+            env_vcf: (&self.env_vcf).into(),
+            lfo1: (&self.lfo1).into(),
+            lfo2: (&self.lfo2).into(),
+            env1: (&self.env1).into(),
+            env2: (&self.env2).into(),
+            modmatrix: ModSrc::ELEM.map(|src| {
+                let row = self.modmatrix.row(src);
+                ModRowSnapshot {
+                    src: src.to_str(),
+                    slots: core::array::from_fn(|i| {
+                        let (dest, depth) = row.slot(i);
+                        ModSlotSnapshot {
+                            dest: ModDest::try_from(dest.value() as u16)
+                                .unwrap_or_default()
+                                .to_str(),
+                            depth: IScalarFxP::from_bits(depth.value() as i16).to_num(),
+                        }
+                    }),
+                }
+            }),
+            raw_osc: self.raw_osc.value(),
+            analog_drift: self.analog_drift.value(),
+            phase_reset: self.phase_reset.value(),
+            dephase: self.dephase.value(),
+            phase_randomize: self.phase_randomize.value(),
+            stretch_tuning: self.stretch_tuning.value(),
+            sidechain_attack: self.sidechain_attack.value(),
+            sidechain_release: self.sidechain_release.value(),
+            sidechain_rms: self.sidechain_rms.value(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_snapshot() {
+        let snapshot = CulSynthParams::default().snapshot();
+        assert_eq!(snapshot.osc1.saw, ScalarFxP::MAX.to_num::<f32>());
+        assert_eq!(snapshot.osc1.tune, 0f32);
+        assert!((snapshot.filt.cutoff - 12543.9).abs() < 10f32);
+        assert!((snapshot.env_vca.attack - 0.1).abs() < 0.001);
+        assert_eq!(snapshot.env_vca.reset, ResetMode::Soft.to_str());
+        assert_eq!(snapshot.lfo1.wave, LfoWave::Sine.to_str());
+        assert_eq!(snapshot.modmatrix[0].src, ModSrc::Velocity.to_str());
+        assert!(snapshot
+            .modmatrix
+            .iter()
+            .flat_map(|row| &row.slots)
+            .all(|slot| slot.depth == 0f32));
+    }
+}
@@ -0,0 +1,68 @@
+//! This module contains the editor's themes: dark or light, each with an
+//! optional high contrast variant for accessibility
+
+use nih_plug_egui::egui::{self, Color32, Stroke};
+
+/// The minimum width of widget and window borders in high contrast mode
+const HIGH_CONTRAST_STROKE: f32 = 1.5f32;
+
+/// The visuals for the dark or light theme.  High contrast mode draws all
+/// text and borders in white on black (or black on white).
+pub fn visuals(dark_mode: bool, high_contrast: bool) -> egui::Visuals {
+    let mut visuals = if dark_mode {
+        egui::Visuals::dark()
+    } else {
+        egui::Visuals::light()
+    };
+    if high_contrast {
+        let (fg, bg) = if dark_mode {
+            (Color32::WHITE, Color32::BLACK)
+        } else {
+            (Color32::BLACK, Color32::WHITE)
+        };
+        visuals.override_text_color = Some(fg);
+        visuals.window_fill = bg;
+        visuals.panel_fill = bg;
+        visuals.window_stroke = Stroke::new(HIGH_CONTRAST_STROKE, fg);
+        let widgets = &mut visuals.widgets;
+        for widget in [
+            &mut widgets.noninteractive,
+            &mut widgets.inactive,
+            &mut widgets.hovered,
+            &mut widgets.active,
+            &mut widgets.open,
+        ] {
+            widget.bg_stroke = Stroke::new(widget.bg_stroke.width.max(HIGH_CONTRAST_STROKE), fg);
+            widget.fg_stroke.color = fg;
+        }
+    }
+    visuals
+}
+
+/// Switch `egui_ctx` to the given theme, if it isn't using it already
+pub fn apply(egui_ctx: &egui::Context, dark_mode: bool, high_contrast: bool) {
+    let visuals = visuals(dark_mode, high_contrast);
+    if egui_ctx.style().visuals != visuals {
+        egui_ctx.set_visuals(visuals);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn apply_theme() {
+        let egui_ctx = egui::Context::default();
+        apply(&egui_ctx, false, false);
+        assert!(!egui_ctx.style().visuals.dark_mode);
+        assert_eq!(egui_ctx.style().visuals.override_text_color, None);
+        apply(&egui_ctx, true, false);
+        assert!(egui_ctx.style().visuals.dark_mode);
+        apply(&egui_ctx, true, true);
+        let style = egui_ctx.style();
+        assert!(style.visuals.dark_mode);
+        assert_eq!(style.visuals.override_text_color, Some(Color32::WHITE));
+        assert!(style.visuals.widgets.inactive.bg_stroke.width >= HIGH_CONTRAST_STROKE);
+    }
+}
@@ -4,10 +4,17 @@
 
 use std::sync::mpsc::SyncSender;
 
-use culsynth::context::GenericContext;
+#[cfg(feature = "fixed")]
+use culsynth::context::ContextFxP;
+use culsynth::context::{Context, GenericContext};
+use culsynth::devices::EnvStage;
 use culsynth::voice::modulation::ModMatrix;
-use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams};
-use culsynth::{IScalarFxP, NoteFxP, ScalarFxP, SignedNoteFxP};
+use culsynth::voice::{Voice, VoiceChannelInput, VoiceInput, VoiceParams, VoiceState};
+use culsynth::{DspFormat, IScalarFxP, NoteFxP, ScalarFxP, SignedNoteFxP};
+
+use crate::{VoiceMode, VoiceSnapshot};
+
+use serde::{Deserialize, Serialize};
 
 use wmidi::MidiMessage;
 
@@ -37,6 +44,9 @@ pub trait VoiceAllocator: Send {
     /// process a change in the aftertouch value
     fn aftertouch(&mut self, v: u8);
     /// For the sample at the current index (see [VoiceAllocator::sample_tick]),
+    /// process a change in the level of the sidechain envelope follower
+    fn sidechain(&mut self, v: ScalarFxP);
+    /// For the sample at the current index (see [VoiceAllocator::sample_tick]),
     /// process a change in pitch bend value
     fn pitch_bend(&mut self, v: i16);
     /// Get the current pitch bend range, in semitones
@@ -47,12 +57,69 @@ pub trait VoiceAllocator: Send {
     /// `set_pitch_bend_range(2, 2)` will set the pitch wheel to bend up/down
     /// a whole step.
     fn set_pitch_bend_range(&mut self, low: i8, high: i8);
-    /// Get the next sample
-    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> f32;
+    /// Get the next stereo sample, as a `(left, right)` pair
+    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> (f32, f32);
+    /// Get the next stereo sample for each of several output buses, as
+    /// `(left, right)` pairs, for hosts that provide separate outputs.
+    ///
+    /// Voices are assigned to the outputs round-robin, so voice `i` is written
+    /// to `outs[i % outs.len()]`.  The default implementation writes the output
+    /// of [VoiceAllocator::next] to the first bus and silences the rest.
+    fn next_multi(
+        &mut self,
+        params: &VoiceParams<i16>,
+        matrix: Option<&ModMatrix<i16>>,
+        outs: &mut [(f32, f32)],
+    ) {
+        if let Some((first, rest)) = outs.split_first_mut() {
+            *first = self.next(params, matrix);
+            rest.fill((0., 0.));
+        }
+    }
+    /// Get the post-modulation state of the most recently triggered voice
+    fn voice_snapshot(&self) -> VoiceSnapshot;
+    /// Capture the internal state of the most recently triggered voice, if
+    /// this voice allocator supports it
+    fn capture_voice(&self) -> Option<CapturedVoice> {
+        None
+    }
+    /// Restore the most recently triggered voice from a [CapturedVoice].
+    ///
+    /// Returns false, leaving the voice unchanged, if the state was captured
+    /// from a synth engine using a different format (fixed/floating point)
+    fn restore_voice(&mut self, _state: &CapturedVoice) -> bool {
+        false
+    }
+    /// Returns true if any voice has reset a device state that became NaN or
+    /// infinite since the last call (see [Voice::take_state_reset])
+    fn take_state_reset(&mut self) -> bool;
     /// Get the process context for this voice allocator.
     fn get_context(&self) -> &dyn GenericContext;
-    /// Is this Voice Allocator polyphonic?
-    fn is_poly(&self) -> bool;
+    /// Change the sample rate of this voice allocator in place, preserving
+    /// the state of all voices (e.g. if the host switches audio devices)
+    ///
+    /// Returns false, leaving the sample rate unchanged, if the new sample
+    /// rate is not supported (e.g. by fixed point logic).  In this case, the
+    /// synth engine must be rebuilt instead (see [SynthConfig]).
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool;
+    /// The [VoiceMode] implemented by this VoiceAllocator
+    fn voice_mode(&self) -> VoiceMode;
+    /// Set the chord played for each note, as offsets in semitones from the
+    /// incoming note.  This is ignored unless in [VoiceMode::Chord].
+    fn set_chord(&mut self, _offsets: &[i8]) {}
+    /// Set how a monophonic synth triggers its envelopes.  This is ignored
+    /// by polyphonic synths.
+    fn set_mono_mode(&mut self, _mode: MonoMode) {}
+    /// Set how far, from 0 (centered) to 1 (the full stereo field), a
+    /// polyphonic synth spreads its voices, and how it assigns each voice a
+    /// position.  This is ignored by monophonic synths.
+    fn set_poly_spread(&mut self, _spread: ScalarFxP, _mode: SpreadMode) {}
+    /// Set whether a polyphonic synth starts each voice's oscillators at a
+    /// different phase (see [culsynth::voice::OscPhaseOffset]), and whether
+    /// that phase is random rather than spread evenly across the voices.
+    /// This only has an effect when the oscillator phase is reset on each
+    /// note, and is ignored by monophonic synths.
+    fn set_dephase(&mut self, _dephase: bool, _randomize: bool) {}
     /// Get the MIDI channel associated with this VoiceAllocator, or None for all channels
     fn get_channel(&self) -> Option<wmidi::Channel>;
     /// Handle a MIDI control change message:
@@ -84,8 +151,652 @@ pub trait VoiceAllocator: Send {
     }
 }
 
+/// The configuration used to construct a synth engine (i.e. a
+/// [VoiceAllocator]).
+///
+/// This follows the builder pattern:
+///
+/// ```no_compile
+/// let synth = SynthConfig::new(48000)
+///     .with_voice_mode(VoiceMode::Poly16)
+///     .with_fixed_point(true)
+///     .build();
+/// ```
+#[derive(Clone, Copy)]
+pub struct SynthConfig {
+    sample_rate: u32,
+    voice_mode: VoiceMode,
+    voice_count: usize,
+    fixed_point: bool,
+}
+
+impl SynthConfig {
+    /// The default number of voices for a polyphonic synth
+    pub const DEFAULT_VOICE_COUNT: usize = 16;
+    /// Create a new configuration for a floating point monosynth with the
+    /// given sample rate
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            voice_mode: VoiceMode::Mono,
+            voice_count: Self::DEFAULT_VOICE_COUNT,
+            fixed_point: false,
+        }
+    }
+    /// Set the voice mode (mono/poly)
+    pub fn with_voice_mode(mut self, voice_mode: VoiceMode) -> Self {
+        self.voice_mode = voice_mode;
+        self
+    }
+    /// Set the number of voices to use in polyphonic mode.  This is ignored
+    /// for monophonic synths.  A synth always has at least one voice, so a
+    /// count of zero is treated as one.
+    pub fn with_voice_count(mut self, voice_count: usize) -> Self {
+        self.voice_count = voice_count.max(1);
+        self
+    }
+    /// Use fixed point (true) or floating point (false) logic
+    pub fn with_fixed_point(mut self, fixed_point: bool) -> Self {
+        self.fixed_point = fixed_point;
+        self
+    }
+    /// The sample rate of the synth
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+    /// The voice mode (mono/poly) of the synth
+    pub fn voice_mode(&self) -> VoiceMode {
+        self.voice_mode
+    }
+    /// The number of voices used in polyphonic mode
+    pub fn voice_count(&self) -> usize {
+        self.voice_count
+    }
+    /// Whether the synth will use fixed point logic
+    pub fn fixed_point(&self) -> bool {
+        self.fixed_point
+    }
+    /// Build the synth engine described by this configuration.
+    ///
+    /// Returns `None` if the configuration is not valid (e.g. a fixed point
+    /// synth at an unsupported sample rate, or in a build without the
+    /// `fixed` feature)
+    pub fn build(&self) -> Option<Box<dyn VoiceAllocator>> {
+        if self.fixed_point {
+            self.build_fixed()
+        } else {
+            let ctx = Context::new(self.sample_rate as f32);
+            Some(match self.voice_mode {
+                VoiceMode::Mono => Box::new(MonoSynth::<f32>::from_config(self, ctx)),
+                VoiceMode::Poly16 => Box::new(PolySynth::<f32>::from_config(self, ctx)),
+                VoiceMode::Chord => Box::new(ChordSynth::<f32>::from_config(self, ctx)),
+            })
+        }
+    }
+    #[cfg(feature = "fixed")]
+    fn build_fixed(&self) -> Option<Box<dyn VoiceAllocator>> {
+        let ctx = ContextFxP::maybe_create(self.sample_rate)?;
+        Some(match self.voice_mode {
+            VoiceMode::Mono => Box::new(MonoSynth::<i16>::from_config(self, ctx)),
+            VoiceMode::Poly16 => Box::new(PolySynth::<i16>::from_config(self, ctx)),
+            VoiceMode::Chord => Box::new(ChordSynth::<i16>::from_config(self, ctx)),
+        })
+    }
+    /// Float-only builds don't include the fixed point engine
+    #[cfg(not(feature = "fixed"))]
+    fn build_fixed(&self) -> Option<Box<dyn VoiceAllocator>> {
+        None
+    }
+}
+
+/// Read-only information about a single voice of a synth engine, e.g. for
+/// per-voice metering
+#[derive(Clone, Copy)]
+pub struct VoiceInfo {
+    /// The index of the voice within the synth engine
+    pub index: usize,
+    /// The MIDI note number assigned to the voice
+    pub note: u8,
+    /// The MIDI velocity the note was played with
+    pub velocity: u8,
+    /// Is the note still held?
+    pub gate: bool,
+    /// The stage of the VCA envelope
+    pub env_stage: EnvStage,
+    /// The output level of the VCA envelope, from 0 to 1
+    pub env_level: f32,
+}
+
+/// The internal state of a single voice (see [VoiceState]), captured from
+/// either a fixed or floating point synth engine
+#[derive(Clone, Serialize, Deserialize)]
+pub enum CapturedVoice {
+    #[cfg(feature = "fixed")]
+    Fixed(VoiceState<i16>),
+    Float(VoiceState<f32>),
+}
+
+/// A [DspFormat] whose [VoiceState] can be stored in a [CapturedVoice]
+pub trait CaptureFormat: DspFormat {
+    fn capture(state: VoiceState<Self>) -> CapturedVoice;
+    /// Returns `None` if `captured` is from a different format
+    fn captured(captured: &CapturedVoice) -> Option<&VoiceState<Self>>;
+}
+
+#[cfg(feature = "fixed")]
+impl CaptureFormat for i16 {
+    fn capture(state: VoiceState<i16>) -> CapturedVoice {
+        CapturedVoice::Fixed(state)
+    }
+    fn captured(captured: &CapturedVoice) -> Option<&VoiceState<i16>> {
+        match captured {
+            CapturedVoice::Fixed(state) => Some(state),
+            CapturedVoice::Float(_) => None,
+        }
+    }
+}
+
+impl CaptureFormat for f32 {
+    fn capture(state: VoiceState<f32>) -> CapturedVoice {
+        CapturedVoice::Float(state)
+    }
+    fn captured(captured: &CapturedVoice) -> Option<&VoiceState<f32>> {
+        match captured {
+            CapturedVoice::Float(state) => Some(state),
+            #[cfg(feature = "fixed")]
+            CapturedVoice::Fixed(_) => None,
+        }
+    }
+}
+
+/// Restore `voice` from `state`, if it was captured in the same format
+fn restore_voice<T: CaptureFormat>(voice: &mut Voice<T>, state: &CapturedVoice) -> bool {
+    match T::captured(state) {
+        Some(state) => {
+            voice.load_state(state);
+            true
+        }
+        None => false,
+    }
+}
+
+/// A voice state captured from the GUI, which is saved with the plugin state
+#[derive(Default, Serialize, Deserialize)]
+pub struct SavedVoice {
+    pub voice: Option<CapturedVoice>,
+    /// Set when this is loaded from the saved plugin state, until the voice
+    /// has been restored
+    #[serde(skip_serializing, default = "restore_on_load")]
+    pub pending: bool,
+}
+
+fn restore_on_load() -> bool {
+    true
+}
+
+fn snapshot_voice<T: DspFormat>(voice: &Voice<T>) -> VoiceSnapshot {
+    let monitor = voice.monitor();
+    VoiceSnapshot {
+        eff_cutoff: T::note_to_float(monitor.cutoff),
+        eff_resonance: T::scalar_to_float(monitor.resonance),
+        eff_note: T::note_to_float(monitor.note),
+        env_vca_level: T::scalar_to_float(monitor.env_vca),
+        env_vcf_level: T::scalar_to_float(monitor.env_vcf),
+        env_vca_stage: monitor.env_vca_stage,
+        env_vcf_stage: monitor.env_vcf_stage,
+        lfo1_value: T::sample_to_float(monitor.lfo1),
+        lfo2_value: T::sample_to_float(monitor.lfo2),
+    }
+}
+
+mod events;
+pub use events::{NoteEvent, NoteEventQueue};
+
+mod scale;
+pub use scale::{Scale, ScaleQuantizer, CHROMATIC_MASK, PITCH_CLASSES};
+
 mod monosynth;
-pub use monosynth::MonoSynth;
+pub use monosynth::{MonoMode, MonoSynth, MONO_TAIL_VOICES};
 
 mod polysynth;
-pub use polysynth::PolySynth;
+pub use polysynth::{PolySynth, SpreadMode};
+
+mod chordsynth;
+pub use chordsynth::{ChordSynth, MAX_CHORD_NOTES};
+
+#[cfg(all(test, feature = "fixed"))]
+mod tests {
+    use super::*;
+    use culsynth::context::ContextFxP;
+    use culsynth::voice::modulation::{ModDest, ModSrc};
+    use std::sync::mpsc::sync_channel;
+
+    #[test]
+    fn snapshot_reflects_modulation() {
+        let mut synth = MonoSynth::<i16>::new(ContextFxP::new_480());
+        let params = VoiceParams::<i16>::default();
+        let mut matrix = ModMatrix::<i16>::default();
+        matrix.rows[ModSrc::ModWheel as usize].1[0] = (ModDest::FiltCutoff, IScalarFxP::MAX);
+        let (mut dispatcher, _rx) = sync_channel::<(u8, u8)>(1);
+        synth.handle_cc(
+            wmidi::ControlFunction::MODULATION_WHEEL,
+            127,
+            &mut dispatcher,
+        );
+        synth.note_on(60, 100);
+        let mut matrix = Some(matrix);
+        for _ in 0..64 {
+            synth.next(&params, matrix.take().as_ref());
+        }
+        let snapshot = synth.voice_snapshot();
+        // The default cutoff is 0, so this is all modulation from the modwheel
+        assert!(snapshot.eff_cutoff > 100f32);
+        assert_eq!(snapshot.eff_note, 60f32);
+    }
+
+    /// Parameters for a pure sine tone through a fully open filter
+    fn audible_params() -> VoiceParams<i16> {
+        let mut params = VoiceParams::<i16>::default();
+        params.oscs_p.primary.sin = ScalarFxP::MAX;
+        params.ring_p.mix_a = ScalarFxP::MAX;
+        params.filt_p.cutoff = NoteFxP::lit("127");
+        params.filt_p.low_mix = ScalarFxP::MAX;
+        params
+    }
+
+    /// Measure the frequency of the output of `synth` over `secs` seconds by
+    /// counting rising zero crossings
+    fn measure_freq(synth: &mut dyn VoiceAllocator, params: &VoiceParams<i16>, secs: f32) -> f32 {
+        let n = (synth.get_context().sample_rate() as f32 * secs) as usize;
+        let mut last = 0f32;
+        let mut crossings = 0;
+        for _ in 0..n {
+            let (left, _) = synth.next(params, None);
+            if last < 0. && left >= 0. {
+                crossings += 1;
+            }
+            last = left;
+        }
+        crossings as f32 / secs
+    }
+
+    #[test]
+    fn sample_rate_change_preserves_pitch() {
+        let mut params = audible_params();
+        params.amp_env_p.attack = culsynth::EnvParamFxP::lit("0.001");
+        for fixed in [true, false] {
+            let mut synth = SynthConfig::new(44100).with_fixed_point(fixed).build().unwrap();
+            synth.note_on(69, 100);
+            // Let the envelope settle before measuring
+            measure_freq(synth.as_mut(), &params, 0.1);
+            let before = measure_freq(synth.as_mut(), &params, 0.5);
+            assert!(synth.set_sample_rate(48000));
+            assert_eq!(synth.get_context().sample_rate(), 48000);
+            let after = measure_freq(synth.as_mut(), &params, 0.5);
+            assert!((before - 440.).abs() < 4., "{} before SR change", before);
+            assert!((after - before).abs() < 4., "{} after SR change", after);
+            // Fixed point only supports a few sample rates
+            assert_eq!(synth.set_sample_rate(96000), !fixed);
+        }
+    }
+
+    #[test]
+    fn zero_voice_count_keeps_one_voice() {
+        let config = SynthConfig::new(48000).with_voice_mode(VoiceMode::Poly16).with_voice_count(0);
+        assert_eq!(config.voice_count(), 1);
+        let mut synth = config.build().unwrap();
+        synth.note_on(69, 100);
+        let params = VoiceParams::<i16>::default();
+        synth.next(&params, Some(&ModMatrix::default()));
+        assert!(synth.voice_snapshot().env_vca_stage == EnvStage::Attack);
+    }
+
+    #[test]
+    fn active_voice_info_tracks_notes() {
+        let mut synth = PolySynth::<i16>::new(ContextFxP::new_480(), 4);
+        let params = VoiceParams::<i16>::default();
+        assert_eq!(synth.active_voice_info().count(), 0);
+        synth.note_on(60, 127);
+        synth.note_on(64, 64);
+        synth.next(&params, Some(&ModMatrix::default()));
+        let voices: Vec<_> = synth.active_voice_info().collect();
+        assert_eq!(voices.len(), 2);
+        assert_eq!((voices[0].note, voices[0].velocity), (60, 127));
+        assert_eq!((voices[1].note, voices[1].velocity), (64, 64));
+        assert!(voices.iter().all(|v| v.gate && v.env_stage == EnvStage::Attack));
+        // A released voice is still active until its envelope finishes
+        synth.note_off(60, 0);
+        synth.next(&params, None);
+        let released = synth.active_voice_info().find(|v| v.note == 60).unwrap();
+        assert!(!released.gate);
+        assert!(released.env_stage == EnvStage::Release);
+        for _ in 0..48000 {
+            synth.next(&params, None);
+        }
+        let voices: Vec<_> = synth.active_voice_info().collect();
+        assert_eq!(voices.len(), 1);
+        assert_eq!(voices[0].note, 64);
+    }
+
+    /// Play a low and a high note with the given stereo spread, returning
+    /// the (left, right) power of each note
+    fn spread_power(spread: ScalarFxP, mode: SpreadMode) -> [(f32, f32); 2] {
+        let params = audible_params();
+        let mut synth = PolySynth::<i16>::new(ContextFxP::new_480(), 2);
+        synth.set_poly_spread(spread, mode);
+        synth.note_on(36, 127);
+        synth.note_on(84, 127);
+        // With two buses, each voice (and so each note) has its own output
+        let mut power = [(0f32, 0f32); 2];
+        let mut matrix = Some(ModMatrix::default());
+        for _ in 0..4800 {
+            let mut outs = [(0f32, 0f32); 2];
+            synth.next_multi(&params, matrix.take().as_ref(), &mut outs);
+            for (p, (left, right)) in power.iter_mut().zip(outs) {
+                p.0 += left * left;
+                p.1 += right * right;
+            }
+        }
+        power
+    }
+
+    #[test]
+    fn poly_spread_pans_notes_apart() {
+        let [low, high] = spread_power(ScalarFxP::ZERO, SpreadMode::Pitch);
+        assert!(low.0 > 0. && (low.0 - low.1).abs() < low.0 * 0.01);
+        assert!(high.0 > 0. && (high.0 - high.1).abs() < high.0 * 0.01);
+        for mode in SpreadMode::modes() {
+            let [low, high] = spread_power(ScalarFxP::MAX, *mode);
+            assert!(low.0 > 4. * low.1, "{:?}: low note {:?}", mode, low);
+            assert!(high.1 > 4. * high.0, "{:?}: high note {:?}", mode, high);
+        }
+    }
+
+    #[test]
+    fn queued_note_starts_at_offset() {
+        let mut params = audible_params();
+        params.amp_env_p.attack = culsynth::EnvParamFxP::lit("0.001");
+        let mut synth = SynthConfig::new(48000).build().unwrap();
+        let (mut dispatcher, _rx) = sync_channel::<(u8, u8)>(1);
+        let mut events = NoteEventQueue::new();
+        events.push(
+            128,
+            NoteEvent::NoteOn {
+                note: 69,
+                velocity: 100,
+            },
+        );
+        let mut out = Vec::new();
+        for smp in 0..256u32 {
+            events.apply_due(smp, synth.as_mut(), &mut dispatcher);
+            out.push(synth.next(&params, None).0);
+        }
+        assert!(events.is_empty());
+        assert!(out[..128].iter().all(|x| *x == 0f32));
+        assert!(out[128..].iter().any(|x| *x != 0f32));
+    }
+
+    #[test]
+    fn chromatic_run_quantized_to_major() {
+        let major = Scale::Major.mask();
+        let mut quantizer = ScaleQuantizer::new();
+        quantizer.set_mask(major);
+        let mut outputs = Vec::new();
+        for note in 60..72u8 {
+            let velocity = 100;
+            let NoteEvent::NoteOn { note: on, .. } =
+                quantizer.map(NoteEvent::NoteOn { note, velocity })
+            else {
+                panic!("note on changed type");
+            };
+            assert!(major & (1 << (on % 12)) != 0, "{} -> {}", note, on);
+            assert!(on.abs_diff(note) <= 1, "{} -> {}", note, on);
+            // The note off releases the same note, even if the scale changed
+            quantizer.set_mask(CHROMATIC_MASK);
+            let NoteEvent::NoteOff { note: off, .. } =
+                quantizer.map(NoteEvent::NoteOff { note, velocity })
+            else {
+                panic!("note off changed type");
+            };
+            assert_eq!(on, off);
+            quantizer.set_mask(major);
+            outputs.push(on);
+        }
+        assert_eq!(outputs, [60, 60, 62, 62, 64, 65, 65, 67, 67, 69, 69, 71]);
+        // Quantization is off by default
+        assert_eq!(ScaleQuantizer::default().quantize(61), 61);
+    }
+
+    #[test]
+    fn poly_voices_round_robin_to_outputs() {
+        let params = audible_params();
+        let mut synth = PolySynth::<i16>::new(ContextFxP::new_480(), 8);
+        // Voices 0 and 1 go to the first two of four outputs
+        synth.note_on(60, 100);
+        synth.note_on(67, 100);
+        let mut matrix = Some(ModMatrix::default());
+        let mut heard = [false; 4];
+        for _ in 0..4800 {
+            let mut outs = [(0f32, 0f32); 4];
+            synth.next_multi(&params, matrix.take().as_ref(), &mut outs);
+            for (heard, (left, right)) in heard.iter_mut().zip(outs) {
+                *heard |= left != 0f32 || right != 0f32;
+            }
+        }
+        assert_eq!(heard, [true, true, false, false]);
+        // Voice 4 wraps around to the first output (voices 0-3 are used and
+        // released before they make a sound)
+        let mut synth = PolySynth::<i16>::new(ContextFxP::new_480(), 8);
+        for _ in 0..4 {
+            synth.note_on(60, 100);
+            synth.note_off(60, 0);
+        }
+        synth.note_on(72, 100);
+        let mut matrix = Some(ModMatrix::default());
+        let mut heard = [false; 4];
+        for _ in 0..4800 {
+            let mut outs = [(0f32, 0f32); 4];
+            synth.next_multi(&params, matrix.take().as_ref(), &mut outs);
+            for (heard, (left, right)) in heard.iter_mut().zip(outs) {
+                *heard |= left != 0f32 || right != 0f32;
+            }
+        }
+        assert_eq!(heard, [true, false, false, false]);
+    }
+
+    #[test]
+    fn mono_trills_never_stick() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut synth = MonoSynth::<i16>::new(ContextFxP::new_480());
+        let params = VoiceParams::<i16>::default();
+        let mut held = Vec::<u8>::new();
+        for _ in 0..10000 {
+            // A small range of notes makes overlapping trills likely
+            let note = rng.gen_range(60..66);
+            if rng.gen_bool(0.5) {
+                synth.note_on(note, 100);
+                held.retain(|n| *n != note);
+                held.push(note);
+            } else {
+                synth.note_off(note, 0);
+                held.retain(|n| *n != note);
+            }
+            assert!(synth.held_notes().eq(held.iter().copied()));
+            synth.next(&params, None);
+            if let Some(note) = held.last() {
+                assert_eq!(synth.voice_snapshot().eff_note, *note as f32);
+            }
+        }
+        // Release everything in a random order
+        while !held.is_empty() {
+            let note = held.swap_remove(rng.gen_range(0..held.len()));
+            synth.note_off(note, 0);
+        }
+        assert_eq!(synth.held_notes().count(), 0);
+        for _ in 0..48000 {
+            synth.next(&params, None);
+        }
+        let snapshot = synth.voice_snapshot();
+        assert_eq!(snapshot.env_vca_stage, EnvStage::Idle);
+        assert!(snapshot.env_vca_level < 0.001);
+    }
+
+    #[test]
+    fn chord_plays_and_releases_every_note() {
+        let mut synth = ChordSynth::<i16>::new(ContextFxP::new_480());
+        let params = VoiceParams::<i16>::default();
+        assert_eq!(synth.chord(), ChordSynth::<i16>::DEFAULT_CHORD);
+        synth.set_chord(&[0, 7, 12]);
+        synth.note_on(60, 100);
+        synth.next(&params, Some(&ModMatrix::default()));
+        assert_eq!(synth.held_notes().collect::<Vec<_>>(), [60, 67, 72]);
+        // Changing the chord while a note is held still releases every note
+        // that was played
+        synth.set_chord(&[0, 3]);
+        synth.note_off(60, 0);
+        assert_eq!(synth.held_notes().count(), 0);
+        // Notes outside the MIDI range are skipped
+        synth.note_on(126, 100);
+        assert_eq!(synth.held_notes().collect::<Vec<_>>(), [126]);
+    }
+
+    /// Parameters for an audible tone with a long release, so that release
+    /// tails are easy to observe
+    fn long_release_params() -> VoiceParams<i16> {
+        let mut params = audible_params();
+        params.amp_env_p.attack = culsynth::EnvParamFxP::lit("0.001");
+        params.amp_env_p.sustain = ScalarFxP::MAX;
+        params.amp_env_p.release = culsynth::EnvParamFxP::lit("1");
+        params
+    }
+
+    #[test]
+    fn chord_shrink_finishes_releases() {
+        let params = long_release_params();
+        // The energy of a released triad, while setting the chord every
+        // sample (as the plugin does every buffer)
+        let release_energy = |chord: &[i8]| {
+            let mut synth = ChordSynth::<i16>::new(ContextFxP::new_480());
+            synth.set_chord(&[0, 4, 7]);
+            synth.next(&params, Some(&ModMatrix::default()));
+            synth.note_on(60, 100);
+            for _ in 0..4800 {
+                synth.next(&params, None);
+            }
+            synth.note_off(60, 0);
+            let mut energy = 0f32;
+            for _ in 0..4800 {
+                synth.set_chord(chord);
+                let (left, _) = synth.next(&params, None);
+                energy += left * left;
+            }
+            energy
+        };
+        // Shrinking the chord doesn't cut off (or freeze) the released notes
+        let full = release_energy(&[0, 4, 7]);
+        let shrunk = release_energy(&[0]);
+        assert!(shrunk > 0.9 * full, "{} {}", shrunk, full);
+    }
+
+    #[test]
+    fn mono_retrigger_keeps_release_tails() {
+        let params = long_release_params();
+        let mut synth = MonoSynth::<i16>::new(ContextFxP::new_480());
+        synth.next(&params, Some(&ModMatrix::default()));
+        // Classic mono mode cuts off the previous note
+        synth.note_on(60, 100);
+        for _ in 0..4800 {
+            synth.next(&params, None);
+        }
+        synth.note_on(64, 100);
+        for _ in 0..4800 {
+            synth.next(&params, None);
+        }
+        assert_eq!(synth.release_tails().count(), 0);
+
+        let mut synth = MonoSynth::<i16>::new(ContextFxP::new_480());
+        synth.set_mono_mode(MonoMode::Retrigger);
+        synth.next(&params, Some(&ModMatrix::default()));
+        synth.note_on(60, 100);
+        for _ in 0..4800 {
+            synth.next(&params, None);
+        }
+        // Legato: the previous note is still held, but is released on a tail
+        synth.note_on(64, 100);
+        for _ in 0..4800 {
+            synth.next(&params, None);
+        }
+        let tails: Vec<_> = synth.release_tails().collect();
+        assert_eq!(tails.len(), 1);
+        assert_eq!(tails[0].note, 60);
+        assert_eq!(tails[0].env_stage, EnvStage::Release);
+        assert!(tails[0].env_level > 0.1, "{}", tails[0].env_level);
+        // The new note is playing on the main voice
+        assert_eq!(synth.voice_snapshot().eff_note, 64f32);
+        assert!(synth.voice_snapshot().env_vca_level > 0.5);
+        // When the tails run out, the oldest is stolen
+        synth.note_on(65, 100);
+        for _ in 0..480 {
+            synth.next(&params, None);
+        }
+        synth.note_on(67, 100);
+        synth.next(&params, None);
+        let mut notes: Vec<_> = synth.release_tails().map(|t| t.note).collect();
+        notes.sort();
+        assert_eq!(notes, [64, 65]);
+    }
+
+    /// Render a short phrase from a fresh synth in blocks of `block` samples,
+    /// passing the modulation matrix at the start of each block like the
+    /// plugin does
+    fn render_in_blocks(synth: &mut dyn VoiceAllocator, block: usize) -> Vec<(f32, f32)> {
+        const LEN: usize = 24000;
+        let mut params = long_release_params();
+        params.amp_env_p.release = culsynth::EnvParamFxP::lit("0.01");
+        let matrix = ModMatrix::<i16>::default();
+        let mut out = Vec::with_capacity(LEN);
+        for start in (0..LEN).step_by(block) {
+            let mut matrix = Some(&matrix);
+            for smp in start..LEN.min(start + block) {
+                // Leave plenty of time for voices to fall silent, so that
+                // later notes are played by voices that were skipped
+                match smp {
+                    0 => synth.note_on(60, 100),
+                    2000 => synth.note_off(60, 0),
+                    9000 => synth.note_on(67, 100),
+                    9500 => synth.note_on(72, 100),
+                    12000 => synth.note_off(67, 0),
+                    12500 => synth.note_off(72, 0),
+                    _ => (),
+                }
+                out.push(synth.next(&params, matrix.take()));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn output_is_independent_of_block_size() {
+        let poly = || PolySynth::<i16>::new(ContextFxP::new_480(), 4);
+        let mono = || {
+            let mut synth = MonoSynth::<i16>::new(ContextFxP::new_480());
+            synth.set_mono_mode(MonoMode::Retrigger);
+            synth
+        };
+        let reference = render_in_blocks(&mut poly(), 1);
+        let mono_reference = render_in_blocks(&mut mono(), 1);
+        for block in [64, 256, 1000] {
+            assert!(
+                reference == render_in_blocks(&mut poly(), block),
+                "{}",
+                block
+            );
+            assert!(
+                mono_reference == render_in_blocks(&mut mono(), block),
+                "{}",
+                block
+            );
+        }
+    }
+}
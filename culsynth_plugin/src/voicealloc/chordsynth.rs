@@ -0,0 +1,178 @@
+use super::*;
+
+use culsynth::DspFormat;
+
+/// The maximum number of notes in a chord
+pub const MAX_CHORD_NOTES: usize = 8;
+
+/// A chord memory synth, which plays a chord of several notes from each
+/// incoming note.
+///
+/// Each note of the chord is played by its own [MonoSynth], at a fixed
+/// offset (in semitones) from the incoming note, and their outputs are
+/// summed.  The offsets are set by [VoiceAllocator::set_chord], and default
+/// to a major triad.
+pub struct ChordSynth<T: DspFormat> {
+    monos: Vec<MonoSynth<T>>,
+    offsets: [i8; MAX_CHORD_NOTES],
+    size: usize,
+    /// The number of [MonoSynth]s that may be sounding, which can be more
+    /// than `size` if the chord was larger when a note was played.  This is
+    /// only lowered once the [MonoSynth]s above `size` are silent.
+    used: usize,
+    /// Each held incoming note, with the note played by each [MonoSynth]
+    /// (or `None` if the note was out of range).  This allows notes to be
+    /// released correctly even if the chord is changed while they are held.
+    held: Vec<(u8, [Option<u8>; MAX_CHORD_NOTES])>,
+}
+
+impl<T: DspFormat> ChordSynth<T> {
+    /// The default chord, a major triad
+    pub const DEFAULT_CHORD: [i8; 3] = [0, 4, 7];
+    pub fn new(ctx: T::Context) -> Self {
+        let mut retval = Self {
+            monos: std::iter::repeat_with(|| MonoSynth::new(ctx.clone()))
+                .take(MAX_CHORD_NOTES)
+                .collect(),
+            offsets: [0; MAX_CHORD_NOTES],
+            size: 0,
+            used: 0,
+            held: Vec::with_capacity(128),
+        };
+        retval.set_chord(&Self::DEFAULT_CHORD);
+        retval
+    }
+    /// Construct a new chord synth from a [SynthConfig].  The voice mode and
+    /// voice count are ignored.
+    pub fn from_config(_config: &SynthConfig, ctx: T::Context) -> Self {
+        Self::new(ctx)
+    }
+    /// The offsets of each note in the current chord, in semitones
+    pub fn chord(&self) -> &[i8] {
+        &self.offsets[..self.size]
+    }
+    /// The notes currently held (i.e. with an open gate), across every note
+    /// of the chord
+    pub fn held_notes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.held.iter().flat_map(|(_, notes)| notes.iter().flatten().copied())
+    }
+}
+
+impl<T: DspFormat> VoiceAllocator for ChordSynth<T>
+where
+    for<'a> ModMatrix<T>: From<&'a ModMatrix<i16>>,
+    for<'a> VoiceInput<T>: From<&'a VoiceInput<i16>>,
+    for<'a> VoiceChannelInput<T>: From<&'a VoiceChannelInput<i16>>,
+    for<'a> VoiceParams<T>: From<&'a VoiceParams<i16>>,
+    T: CaptureFormat,
+{
+    fn note_on(&mut self, note: u8, velocity: u8) {
+        // A repeated note on (without a note off) replaces the old chord
+        self.note_off(note, 0);
+        let mut notes = [None; MAX_CHORD_NOTES];
+        for (i, offset) in self.chord().iter().enumerate() {
+            notes[i] = u8::try_from(note as i16 + *offset as i16).ok().filter(|n| *n < 128);
+        }
+        for (mono, chord_note) in self.monos.iter_mut().zip(notes) {
+            if let Some(chord_note) = chord_note {
+                mono.note_on(chord_note, velocity);
+            }
+        }
+        self.used = self.used.max(self.size);
+        self.held.push((note, notes));
+    }
+    fn note_off(&mut self, note: u8, velocity: u8) {
+        let Some(idx) = self.held.iter().position(|(n, _)| *n == note) else {
+            return;
+        };
+        let (_, notes) = self.held.remove(idx);
+        for (mono, chord_note) in self.monos.iter_mut().zip(notes) {
+            if let Some(chord_note) = chord_note {
+                mono.note_off(chord_note, velocity);
+            }
+        }
+    }
+    fn set_chord(&mut self, offsets: &[i8]) {
+        let size = offsets.len().min(MAX_CHORD_NOTES);
+        self.offsets[..size].copy_from_slice(&offsets[..size]);
+        self.size = size;
+    }
+    fn set_mono_mode(&mut self, mode: MonoMode) {
+        self.monos.iter_mut().for_each(|mono| mono.set_mono_mode(mode));
+    }
+    fn get_channel(&self) -> Option<wmidi::Channel> {
+        None //TODO
+    }
+    fn aftertouch(&mut self, value: u8) {
+        self.monos.iter_mut().for_each(|mono| mono.aftertouch(value));
+    }
+    fn sidechain(&mut self, value: ScalarFxP) {
+        self.monos.iter_mut().for_each(|mono| mono.sidechain(value));
+    }
+    fn pitch_bend(&mut self, v: i16) {
+        self.monos.iter_mut().for_each(|mono| mono.pitch_bend(v));
+    }
+    fn get_pitch_bend_range(&self) -> (i8, i8) {
+        self.monos[0].get_pitch_bend_range()
+    }
+    fn set_pitch_bend_range(&mut self, low: i8, high: i8) {
+        self.monos.iter_mut().for_each(|mono| mono.set_pitch_bend_range(low, high));
+    }
+    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> (f32, f32) {
+        let (mut left, mut right) = (0f32, 0f32);
+        for mono in &mut self.monos[..self.used] {
+            let out = mono.next(params, matrix);
+            left += out.0;
+            right += out.1;
+        }
+        // Stop processing the notes above the current chord once they have
+        // finished releasing
+        while self.used > self.size && self.monos[self.used - 1].is_silent() {
+            self.used -= 1;
+        }
+        (left, right)
+    }
+    fn voice_snapshot(&self) -> VoiceSnapshot {
+        self.monos[0].voice_snapshot()
+    }
+    fn capture_voice(&self) -> Option<CapturedVoice> {
+        self.monos[0].capture_voice()
+    }
+    fn restore_voice(&mut self, state: &CapturedVoice) -> bool {
+        self.monos[0].restore_voice(state)
+    }
+    fn take_state_reset(&mut self) -> bool {
+        self.monos
+            .iter_mut()
+            .fold(false, |reset, mono| mono.take_state_reset() || reset)
+    }
+    fn get_context(&self) -> &dyn GenericContext {
+        self.monos[0].get_context()
+    }
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool {
+        // The monosynths all share the same sample rate, so either all or
+        // none of them support the new one
+        self.monos.iter_mut().all(|mono| mono.set_sample_rate(sample_rate))
+    }
+    fn voice_mode(&self) -> VoiceMode {
+        VoiceMode::Chord
+    }
+    fn handle_cc(
+        &mut self,
+        cc: wmidi::ControlFunction,
+        value: u8,
+        dispatcher: &mut dyn MidiCcHandler,
+    ) {
+        match cc {
+            wmidi::ControlFunction::MODULATION_WHEEL
+            | wmidi::ControlFunction::MODULATION_WHEEL_LSB => {
+                for mono in &mut self.monos {
+                    mono.handle_cc(cc, value, dispatcher);
+                }
+            }
+            _ => {
+                let _ = dispatcher.handle_cc(cc, value);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use super::*;
+use crate::diag::{self, DiagKind};
+use culsynth::util::MidiEvent;
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+/// Preallocate room for this many events, so the queue never allocates on
+/// the audio thread (see [NoteEventQueue::push_or_apply])
+const EVENT_CAPACITY: usize = 256;
+
+/// An event for a [VoiceAllocator]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum NoteEvent {
+    /// Note on, with a MIDI note number and velocity
+    NoteOn { note: u8, velocity: u8 },
+    /// Note off, with a MIDI note number and velocity
+    NoteOff { note: u8, velocity: u8 },
+    /// MIDI control change
+    Cc {
+        cc: wmidi::ControlFunction,
+        value: u8,
+    },
+    /// Channel aftertouch
+    Aftertouch(u8),
+    /// Pitch bend (see [VoiceAllocator::pitch_bend])
+    PitchBend(i16),
+}
+
+impl NoteEvent {
+    /// Convert a MIDI message (e.g. from a hardware MIDI port) into a
+    /// `NoteEvent`, if it is one that the synth handles.  Values are scaled
+    /// the same way as events from the host.
+    pub fn from_midi(event: MidiEvent) -> Option<Self> {
+        match event {
+            MidiEvent::NoteOn { note, velocity, .. } => Some(Self::NoteOn { note, velocity }),
+            MidiEvent::NoteOff { note, velocity, .. } => Some(Self::NoteOff { note, velocity }),
+            MidiEvent::ControlChange { cc, value, .. } => Some(Self::Cc {
+                cc: wmidi::ControlFunction(wmidi::U7::new(cc).ok()?),
+                value,
+            }),
+            MidiEvent::ChannelPressure { pressure, .. } => Some(Self::Aftertouch(pressure)),
+            MidiEvent::PitchBend { amount, .. } => Some(Self::PitchBend(amount << 2)),
+            MidiEvent::PolyPressure { .. } => None,
+        }
+    }
+    /// Convert a note from the onscreen keyboard, where a positive integer
+    /// indicates a "Note On" for that note number, and a negative integer
+    /// indicates a "Note Off" for the note number `note + 128`
+    pub fn from_kbd(note: i8) -> Self {
+        if note < 0 {
+            Self::NoteOff {
+                note: (note - (-128)) as u8,
+                velocity: 0,
+            }
+        } else {
+            Self::NoteOn {
+                note: note as u8,
+                velocity: 100,
+            }
+        }
+    }
+    /// Apply this event to `voices`
+    pub fn apply(self, voices: &mut dyn VoiceAllocator, dispatcher: &mut dyn MidiCcHandler) {
+        match self {
+            Self::NoteOn { note, velocity } => {
+                diag::record(DiagKind::NoteOn, &[note, velocity]);
+                voices.note_on(note, velocity)
+            }
+            Self::NoteOff { note, velocity } => {
+                diag::record(DiagKind::NoteOff, &[note, velocity]);
+                voices.note_off(note, velocity)
+            }
+            Self::Cc { cc, value } => voices.handle_cc(cc, value, dispatcher),
+            Self::Aftertouch(value) => voices.aftertouch(value),
+            Self::PitchBend(value) => voices.pitch_bend(value),
+        }
+    }
+}
+
+/// A [NoteEvent] waiting in a [NoteEventQueue].  Events are ordered by their
+/// sample offset, and then by the order in which they were queued.
+struct QueuedEvent {
+    sample_offset: u32,
+    seq: u64,
+    event: NoteEvent,
+}
+
+impl PartialEq for QueuedEvent {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for QueuedEvent {}
+
+impl PartialOrd for QueuedEvent {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueuedEvent {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.sample_offset, self.seq).cmp(&(other.sample_offset, other.seq))
+    }
+}
+
+/// A queue of [NoteEvent]s, each timestamped with the sample (within the
+/// current buffer) that it should be applied at, so that note transients
+/// start on exactly the right sample.
+///
+/// Notes are quantized to the scale set by [NoteEventQueue::set_scale] as
+/// they are applied.
+pub struct NoteEventQueue {
+    heap: BinaryHeap<Reverse<QueuedEvent>>,
+    seq: u64,
+    scale: ScaleQuantizer,
+}
+
+impl Default for NoteEventQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NoteEventQueue {
+    /// Constructor
+    pub fn new() -> Self {
+        Self {
+            heap: BinaryHeap::with_capacity(EVENT_CAPACITY),
+            seq: 0,
+            scale: ScaleQuantizer::new(),
+        }
+    }
+    /// Quantize notes applied from now on to the scale given by `mask` (see
+    /// [Scale::mask]), or pass them through unchanged with [CHROMATIC_MASK]
+    pub fn set_scale(&mut self, mask: u16) {
+        self.scale.set_mask(mask);
+    }
+    /// Queue `event` to be applied at the sample `sample_offset`.  Events at
+    /// the same offset are applied in the order they were queued.
+    pub fn push(&mut self, sample_offset: u32, event: NoteEvent) {
+        self.heap.push(Reverse(QueuedEvent {
+            sample_offset,
+            seq: self.seq,
+            event,
+        }));
+        self.seq = self.seq.wrapping_add(1);
+    }
+    /// Queue `event` like [NoteEventQueue::push], unless the queue is full.
+    /// Rather than growing the queue (which would allocate), this first
+    /// applies any events due at or before `sample_offset` to make room, and
+    /// if that isn't enough, applies `event` immediately since it is due
+    /// before everything still in the queue.
+    pub fn push_or_apply(
+        &mut self,
+        sample_offset: u32,
+        event: NoteEvent,
+        voices: &mut dyn VoiceAllocator,
+        dispatcher: &mut dyn MidiCcHandler,
+    ) {
+        if self.heap.len() >= EVENT_CAPACITY {
+            self.apply_due(sample_offset, voices, dispatcher);
+        }
+        if self.heap.len() >= EVENT_CAPACITY {
+            self.scale.map(event).apply(voices, dispatcher);
+        } else {
+            self.push(sample_offset, event);
+        }
+    }
+    /// The number of events waiting in the queue
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+    /// Returns true if there are no events waiting in the queue
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+    /// Apply (and remove) all events due at or before the sample
+    /// `sample_offset`.  Call this before calculating each sample.
+    pub fn apply_due(
+        &mut self,
+        sample_offset: u32,
+        voices: &mut dyn VoiceAllocator,
+        dispatcher: &mut dyn MidiCcHandler,
+    ) {
+        while self.heap.peek().is_some_and(|evt| evt.0.sample_offset <= sample_offset) {
+            if let Some(Reverse(queued)) = self.heap.pop() {
+                self.scale.map(queued.event).apply(voices, dispatcher);
+            }
+        }
+    }
+    /// Apply any remaining events, e.g. at the end of a buffer, so that
+    /// events with timestamps past the end of the buffer are not lost
+    pub fn flush(&mut self, voices: &mut dyn VoiceAllocator, dispatcher: &mut dyn MidiCcHandler) {
+        self.apply_due(u32::MAX, voices, dispatcher);
+        self.seq = 0;
+    }
+}
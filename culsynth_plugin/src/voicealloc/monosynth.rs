@@ -3,7 +3,74 @@ use rand::random;
 
 use culsynth::{voice::VoiceInput, DspFormat};
 
-/// A monosynth utilizing fixed point logic internally
+/// The maximum number of held notes, which is every MIDI note
+const MAX_HELD_NOTES: usize = 128;
+/// The number of voices used to play the release tails of previous notes
+pub const MONO_TAIL_VOICES: usize = 2;
+
+/// How a [MonoSynth] triggers its envelopes when a new note is played
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum MonoMode {
+    /// Classic monosynth behavior: the envelopes are only triggered when a
+    /// note is played with no other notes held, and cut off the release of
+    /// the previous note
+    #[default]
+    SingleTrigger,
+    /// Like [MonoMode::SingleTrigger], but a note played after every note
+    /// has been released leaves the release of the previous note sounding
+    /// on a tail voice
+    Fingered,
+    /// Every new note triggers the envelopes, and the previous note (held or
+    /// not) is released on a tail voice
+    Retrigger,
+}
+
+impl MonoMode {
+    const ELEM: [MonoMode; 3] = [Self::SingleTrigger, Self::Fingered, Self::Retrigger];
+    /// Returns a slice to all of the possible MonoModes
+    pub const fn modes() -> &'static [MonoMode] {
+        &Self::ELEM
+    }
+    /// Provides the name of the mode
+    pub const fn to_str(&self) -> &'static str {
+        ["Single Trigger", "Fingered", "Retrigger"][*self as usize]
+    }
+}
+
+impl TryFrom<u8> for MonoMode {
+    type Error = &'static str;
+    fn try_from(value: u8) -> Result<Self, &'static str> {
+        Self::ELEM
+            .get(value as usize)
+            .copied()
+            .ok_or("Conversion of u8 to MonoMode Overflowed")
+    }
+}
+
+/// A voice playing out the release of a previous note
+#[derive(Default, Clone)]
+struct MonoTail<T: DspFormat> {
+    voice: Voice<T>,
+    note: NoteFxP,
+    velocity: ScalarFxP,
+    /// Set when a new modulation matrix arrived while the tail was silent
+    matrix_stale: bool,
+}
+
+/// A monophonic synth with a single [Voice].
+///
+/// The synth tracks every held note (with its velocity) in the order it was
+/// pressed, and always plays the most recently pressed note that is still
+/// held (last note priority).  Releasing the playing note returns to the
+/// previous held note without retriggering the envelopes, and the gate is
+/// only closed once every held note has been released, regardless of the
+/// order in which they were released.
+///
+/// Depending on the [MonoMode], the release of the previous note may be
+/// handed off to one of a small pool of tail voices when a new note is
+/// triggered, rather than being cut off.  If every tail voice is busy, the
+/// oldest tail is stolen.
 #[derive(Default, Clone)]
 pub struct MonoSynth<T: DspFormat> {
     voice: Voice<T>,
@@ -15,7 +82,15 @@ pub struct MonoSynth<T: DspFormat> {
     velocity: ScalarFxP,
     aftertouch: ScalarFxP,
     modwheel: ScalarFxP,
+    sidechain: ScalarFxP,
     gate: bool,
+    held: Vec<(u8, ScalarFxP)>,
+    mode: MonoMode,
+    tails: [MonoTail<T>; MONO_TAIL_VOICES],
+    next_tail: usize,
+    /// Set if the voice was swapped in from a tail that missed a new
+    /// modulation matrix
+    matrix_stale: bool,
 }
 
 impl<T: DspFormat> MonoSynth<T> {
@@ -29,8 +104,83 @@ impl<T: DspFormat> MonoSynth<T> {
             velocity: ScalarFxP::ZERO,
             aftertouch: ScalarFxP::ZERO,
             modwheel: ScalarFxP::ZERO,
+            sidechain: ScalarFxP::ZERO,
             pitch_bend: SignedNoteFxP::ZERO,
             pitch_range: (2i16.into(), 2i16.into()),
+            held: Vec::with_capacity(MAX_HELD_NOTES),
+            mode: MonoMode::default(),
+            tails: std::array::from_fn(|_| MonoTail {
+                voice: Voice::new_with_seeds(random(), random()),
+                ..Default::default()
+            }),
+            next_tail: 0,
+            matrix_stale: false,
+        }
+    }
+    /// Construct a new monosynth from a [SynthConfig].  The voice mode and
+    /// voice count are ignored.
+    pub fn from_config(_config: &SynthConfig, ctx: T::Context) -> Self {
+        Self::new(ctx)
+    }
+    /// The notes currently held, in the order they were pressed
+    pub fn held_notes(&self) -> impl Iterator<Item = u8> + '_ {
+        self.held.iter().map(|(note, _)| *note)
+    }
+    /// The current [MonoMode]
+    pub fn mono_mode(&self) -> MonoMode {
+        self.mode
+    }
+    /// Returns true if the gate is closed and neither the voice nor any of
+    /// the tail voices are still releasing, as of the last call to
+    /// [VoiceAllocator::next]
+    pub fn is_silent(&self) -> bool {
+        !self.gate
+            && self.voice.is_silent(&self.ctx)
+            && self.tails.iter().all(|tail| tail.voice.is_silent(&self.ctx))
+    }
+    /// Iterate over the tail voices that are still releasing a previous
+    /// note.  This reflects the state of the voices as of the last call to
+    /// [VoiceAllocator::next].
+    pub fn release_tails(&self) -> impl Iterator<Item = VoiceInfo> + '_ {
+        self.tails.iter().enumerate().filter_map(|(index, tail)| {
+            if tail.voice.is_silent(&self.ctx) {
+                return None;
+            }
+            let monitor = tail.voice.monitor();
+            Some(VoiceInfo {
+                index,
+                note: tail.note.to_num(),
+                velocity: (tail.velocity.to_bits() >> 9) as u8,
+                gate: false,
+                env_stage: monitor.env_vca_stage,
+                env_level: T::scalar_to_float(monitor.env_vca),
+            })
+        })
+    }
+    /// Hand the note currently playing off to the oldest tail voice so it
+    /// can finish releasing, and take that tail's voice to play the next
+    /// note.  The swapped in voice was released, so the next note will
+    /// retrigger the envelopes.
+    fn release_to_tail(&mut self) {
+        if !self.gate && self.voice.is_silent(&self.ctx) {
+            return;
+        }
+        let tail = &mut self.tails[self.next_tail];
+        std::mem::swap(&mut self.voice, &mut tail.voice);
+        self.matrix_stale = std::mem::take(&mut tail.matrix_stale);
+        tail.note = self.note;
+        tail.velocity = self.velocity;
+        self.next_tail = (self.next_tail + 1) % MONO_TAIL_VOICES;
+    }
+    /// Play the most recently pressed held note, or close the gate if there
+    /// are no held notes
+    fn update_note(&mut self) {
+        if let Some((note, velocity)) = self.held.last() {
+            self.note = NoteFxP::from_num(*note);
+            self.velocity = *velocity;
+            self.gate = true;
+        } else {
+            self.gate = false;
         }
     }
 }
@@ -41,17 +191,25 @@ where
     for<'a> VoiceInput<T>: From<&'a VoiceInput<i16>>,
     for<'a> VoiceChannelInput<T>: From<&'a VoiceChannelInput<i16>>,
     for<'a> VoiceParams<T>: From<&'a VoiceParams<i16>>,
+    T: CaptureFormat,
 {
     fn note_on(&mut self, note: u8, velocity: u8) {
-        self.note = NoteFxP::from_num(note);
-        self.gate = true;
-        self.velocity = ScalarFxP::from_bits((velocity as u16) << 9);
+        match self.mode {
+            MonoMode::SingleTrigger => {}
+            MonoMode::Fingered if self.gate => {}
+            MonoMode::Fingered | MonoMode::Retrigger => self.release_to_tail(),
+        }
+        // A repeated note on (without a note off) moves the note to the top
+        self.held.retain(|(n, _)| *n != note);
+        if self.held.len() >= MAX_HELD_NOTES {
+            self.held.remove(0);
+        }
+        self.held.push((note, ScalarFxP::from_bits((velocity as u16) << 9)));
+        self.update_note();
     }
     fn note_off(&mut self, note: u8, _velocity: u8) {
-        if self.note == note {
-            self.gate = false;
-            //self.velocity = ScalarFxP::from_bits((velocity as u16) << 9);
-        }
+        self.held.retain(|(n, _)| *n != note);
+        self.update_note();
     }
     fn get_channel(&self) -> Option<wmidi::Channel> {
         None //TODO
@@ -59,6 +217,9 @@ where
     fn aftertouch(&mut self, value: u8) {
         self.aftertouch = ScalarFxP::from_bits((value as u16) << 9);
     }
+    fn sidechain(&mut self, value: ScalarFxP) {
+        self.sidechain = value;
+    }
     fn pitch_bend(&mut self, v: i16) {
         if v < 0 {
             self.pitch_bend =
@@ -80,15 +241,17 @@ where
             fixed::types::I16F0::from_num(high),
         );
     }
-    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> f32 {
+    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> (f32, f32) {
         let ch_input = &VoiceChannelInput::<i16> {
             aftertouch: self.aftertouch,
             modwheel: self.modwheel,
+            sidechain: self.sidechain,
         };
         let input = &VoiceInput::<i16> {
             note: self.note.add_signed(self.pitch_bend),
             gate: self.gate,
             velocity: self.velocity,
+            pan: IScalarFxP::ZERO,
         };
         // Handle matrix conversion, if required
         let matrix_param = if let Some(matrix) = matrix {
@@ -97,19 +260,76 @@ where
         } else {
             None
         };
-        T::sample_to_float(self.voice.next(
+        let voice_matrix = if std::mem::take(&mut self.matrix_stale) {
+            Some(&self.matrix)
+        } else {
+            matrix_param
+        };
+        let out = self.voice.next(
             &self.ctx,
-            matrix_param,
+            voice_matrix,
             &input.into(),
             &ch_input.into(),
             params.into(),
-        )) / 4. //Rescale from 0dB to -6dB to avoid DAWs going into the red
+        );
+        let (mut left, mut right) = (T::sample_to_float(out.left), T::sample_to_float(out.right));
+        for tail in &mut self.tails {
+            // Skip tails that have finished releasing, and pass them any new
+            // modulation matrix when they next sound (see PolySynth)
+            tail.matrix_stale |= matrix_param.is_some();
+            if tail.voice.is_silent(&self.ctx) {
+                continue;
+            }
+            let matrix_param = if std::mem::take(&mut tail.matrix_stale) {
+                Some(&self.matrix)
+            } else {
+                None
+            };
+            let input = &VoiceInput::<i16> {
+                note: tail.note.add_signed(self.pitch_bend),
+                gate: false,
+                velocity: tail.velocity,
+                pan: IScalarFxP::ZERO,
+            };
+            let out = tail.voice.next(
+                &self.ctx,
+                matrix_param,
+                &input.into(),
+                &ch_input.into(),
+                params.into(),
+            );
+            left += T::sample_to_float(out.left);
+            right += T::sample_to_float(out.right);
+        }
+        //Rescale from 0dB to -6dB to avoid DAWs going into the red
+        (left / 4., right / 4.)
+    }
+    fn voice_snapshot(&self) -> VoiceSnapshot {
+        snapshot_voice(&self.voice)
+    }
+    fn capture_voice(&self) -> Option<CapturedVoice> {
+        Some(T::capture(self.voice.dump_state()))
+    }
+    fn restore_voice(&mut self, state: &CapturedVoice) -> bool {
+        restore_voice(&mut self.voice, state)
+    }
+    fn take_state_reset(&mut self) -> bool {
+        let tails = self.tails.iter_mut();
+        tails.fold(self.voice.take_state_reset(), |reset, tail| {
+            tail.voice.take_state_reset() || reset
+        })
     }
     fn get_context(&self) -> &dyn GenericContext {
         <T::Context as culsynth::context::GetContext>::get_context(&self.ctx)
     }
-    fn is_poly(&self) -> bool {
-        false
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool {
+        self.ctx.set_sample_rate(sample_rate)
+    }
+    fn voice_mode(&self) -> VoiceMode {
+        VoiceMode::Mono
+    }
+    fn set_mono_mode(&mut self, mode: MonoMode) {
+        self.mode = mode;
     }
     fn handle_cc(
         &mut self,
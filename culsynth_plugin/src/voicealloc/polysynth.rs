@@ -1,24 +1,89 @@
 use std::collections::VecDeque;
 
 use super::*;
-use culsynth::DspFormat;
+use crate::diag::{self, DiagKind};
+use culsynth::voice::OscPhaseOffset;
+use culsynth::{DspFormat, DspType};
 use nih_plug::nih_error;
 use rand::random;
 
+/// How a [PolySynth] spreads its voices across the stereo field
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum SpreadMode {
+    /// Pan each voice by the pitch of its note, with low notes to the left
+    /// and high notes to the right
+    #[default]
+    Pitch,
+    /// Pan each new voice to the next of a fixed sequence of positions,
+    /// alternating between the left and right
+    RoundRobin,
+}
+
+impl SpreadMode {
+    const ELEM: [SpreadMode; 2] = [Self::Pitch, Self::RoundRobin];
+    /// Returns a slice to all of the possible SpreadModes
+    pub const fn modes() -> &'static [SpreadMode] {
+        &Self::ELEM
+    }
+    /// Provides the name of the mode
+    pub const fn to_str(&self) -> &'static str {
+        ["Pitch", "Round Robin"][*self as usize]
+    }
+}
+
+impl TryFrom<u8> for SpreadMode {
+    type Error = &'static str;
+    fn try_from(value: u8) -> Result<Self, &'static str> {
+        Self::ELEM
+            .get(value as usize)
+            .copied()
+            .ok_or("Conversion of u8 to SpreadMode Overflowed")
+    }
+}
+
+/// The pan positions used by [SpreadMode::RoundRobin], in order
+const ROUND_ROBIN_PAN: [IScalarFxP; 8] = [
+    IScalarFxP::lit("-1"),
+    IScalarFxP::MAX,
+    IScalarFxP::lit("-0.5"),
+    IScalarFxP::lit("0.5"),
+    IScalarFxP::lit("-0.75"),
+    IScalarFxP::lit("0.75"),
+    IScalarFxP::lit("-0.25"),
+    IScalarFxP::lit("0.25"),
+];
+
+/// The note at the center of the stereo field with [SpreadMode::Pitch]
+const SPREAD_CENTER_NOTE: i16 = 60;
+/// The number of semitones from [SPREAD_CENTER_NOTE] to a hard left/right
+/// pan with [SpreadMode::Pitch]
+const SPREAD_SEMITONES: i16 = 48;
+
 struct PolySynthVoice<T: DspFormat> {
     voice: Voice<T>,
     vel: ScalarFxP,
     note: NoteFxP,
     gate: bool,
+    /// The position of this voice in the stereo field at full spread
+    pan: IScalarFxP,
+    /// Set when a new modulation matrix arrived while the voice was silent,
+    /// so it must be passed to the voice the next time it sounds
+    matrix_stale: bool,
+    /// The oscillator phase this voice starts its notes at when de-phasing
+    phase_offset: OscPhaseOffset,
 }
 
 impl<T: DspFormat> PolySynthVoice<T> {
-    fn new() -> Self {
+    fn new(index: usize, num_voices: usize) -> Self {
         Self {
             voice: Voice::new_with_seeds(random(), random()),
             note: NoteFxP::from_num(69), //A440
             gate: false,
             vel: ScalarFxP::ZERO,
+            pan: IScalarFxP::ZERO,
+            matrix_stale: false,
+            phase_offset: OscPhaseOffset::new(index, num_voices, random()),
         }
     }
 }
@@ -28,17 +93,25 @@ pub struct PolySynth<T: DspFormat> {
     matrix: ModMatrix<T>,
     active_voices: VecDeque<usize>,
     inactive_voices: VecDeque<usize>,
+    last_voice: usize,
     pitch_bend_range: (fixed::types::I16F0, fixed::types::I16F0),
     pitch_bend: SignedNoteFxP,
     aftertouch: ScalarFxP,
     modwheel: ScalarFxP,
+    sidechain: ScalarFxP,
+    spread: ScalarFxP,
+    spread_mode: SpreadMode,
+    /// The index into [ROUND_ROBIN_PAN] for the next voice
+    round_robin: usize,
+    dephase: bool,
+    phase_randomize: bool,
     ctx: T::Context,
 }
 
 impl<T: DspFormat> PolySynth<T> {
     pub fn new(context: T::Context, num_voices: usize) -> Self {
-        let voices = std::iter::repeat_with(|| PolySynthVoice::<T>::new())
-            .take(num_voices)
+        let voices = (0..num_voices)
+            .map(|i| PolySynthVoice::<T>::new(i, num_voices))
             .collect::<Box<[_]>>();
         let mut active_voices = VecDeque::<usize>::new();
         let mut inactive_voices = VecDeque::<usize>::new();
@@ -52,19 +125,70 @@ impl<T: DspFormat> PolySynth<T> {
             matrix: Default::default(),
             active_voices,
             inactive_voices,
+            last_voice: 0,
             pitch_bend: SignedNoteFxP::ZERO,
             pitch_bend_range: (2i16.into(), 2i16.into()),
             aftertouch: ScalarFxP::ZERO,
             modwheel: ScalarFxP::ZERO,
+            sidechain: ScalarFxP::ZERO,
+            spread: ScalarFxP::ZERO,
+            spread_mode: SpreadMode::default(),
+            round_robin: 0,
+            dephase: false,
+            phase_randomize: false,
             ctx: context,
         }
     }
+    /// Construct a new polysynth from a [SynthConfig].  The voice mode is
+    /// ignored.
+    pub fn from_config(config: &SynthConfig, context: T::Context) -> Self {
+        Self::new(context, config.voice_count())
+    }
+    /// Iterate over all of the voices that are currently sounding - that is,
+    /// voices with a held note or an envelope that has not finished releasing.
+    ///
+    /// This borrows the voices, so it does not allocate, and reflects the
+    /// state of the voices as of the last call to [VoiceAllocator::next].
+    pub fn active_voice_info(&self) -> impl Iterator<Item = VoiceInfo> + '_ {
+        self.voices.iter().enumerate().filter_map(|(index, v)| {
+            let monitor = v.voice.monitor();
+            if !v.gate && v.voice.is_silent(&self.ctx) {
+                return None;
+            }
+            Some(VoiceInfo {
+                index,
+                note: v.note.to_num(),
+                velocity: (v.vel.to_bits() >> 9) as u8,
+                gate: v.gate,
+                env_stage: monitor.env_vca_stage,
+                env_level: T::scalar_to_float(monitor.env_vca),
+            })
+        })
+    }
     fn note_on_i(&mut self, voice_index: usize, note: u8, vel: u8) {
         self.active_voices.push_back(voice_index);
+        self.last_voice = voice_index;
         let voice = &mut self.voices[voice_index];
         voice.note = NoteFxP::from_num(note);
         voice.vel = ScalarFxP::from_bits((vel as u16) << 9);
         voice.gate = true;
+        voice.pan = match self.spread_mode {
+            SpreadMode::Pitch => {
+                let offset = fixed::types::I16F16::from_num(note as i16 - SPREAD_CENTER_NOTE);
+                IScalarFxP::saturating_from_num(offset / SPREAD_SEMITONES as i32)
+            }
+            SpreadMode::RoundRobin => {
+                let pan = ROUND_ROBIN_PAN[self.round_robin];
+                self.round_robin = (self.round_robin + 1) % ROUND_ROBIN_PAN.len();
+                pan
+            }
+        };
+        let phase = if self.dephase {
+            voice.phase_offset.offset::<T>(self.phase_randomize)
+        } else {
+            T::Scalar::zero()
+        };
+        voice.voice.set_initial_phase(phase);
     }
 }
 
@@ -74,11 +198,14 @@ where
     for<'a> VoiceInput<T>: From<&'a VoiceInput<i16>>,
     for<'a> VoiceChannelInput<T>: From<&'a VoiceChannelInput<i16>>,
     for<'a> VoiceParams<T>: From<&'a VoiceParams<i16>>,
+    T: CaptureFormat,
 {
     fn note_on(&mut self, note: u8, velocity: u8) {
         if let Some(i) = self.inactive_voices.pop_front() {
             self.note_on_i(i, note, velocity);
         } else if let Some(i) = self.active_voices.pop_front() {
+            let old_note = self.voices[i].note.to_num::<u8>();
+            diag::record(DiagKind::VoiceSteal, &[i as u8, old_note, note]);
             self.note_on_i(i, note, velocity);
         } else {
             nih_error!("Unable to steal voice");
@@ -102,6 +229,9 @@ where
     fn aftertouch(&mut self, value: u8) {
         self.aftertouch = ScalarFxP::from_bits((value as u16) << 9);
     }
+    fn sidechain(&mut self, value: ScalarFxP) {
+        self.sidechain = value;
+    }
     fn handle_cc(
         &mut self,
         cc: wmidi::ControlFunction,
@@ -141,40 +271,100 @@ where
             fixed::types::I16F0::from_num(high),
         );
     }
-    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> f32 {
-        let mut out = 0f32;
+    fn next(&mut self, params: &VoiceParams<i16>, matrix: Option<&ModMatrix<i16>>) -> (f32, f32) {
+        let mut out = [(0f32, 0f32)];
+        self.next_multi(params, matrix, &mut out);
+        out[0]
+    }
+    fn next_multi(
+        &mut self,
+        params: &VoiceParams<i16>,
+        matrix: Option<&ModMatrix<i16>>,
+        outs: &mut [(f32, f32)],
+    ) {
+        if outs.is_empty() {
+            return;
+        }
+        outs.fill((0., 0.));
         // Handle matrix conversion into a different format, if required
-        let matrix_param = if let Some(matrix) = matrix {
+        if let Some(matrix) = matrix {
             self.matrix = matrix.into();
-            Some(&self.matrix)
-        } else {
-            None
-        };
+            self.voices.iter_mut().for_each(|v| v.matrix_stale = true);
+        }
         let ch_in = &VoiceChannelInput::<i16> {
             aftertouch: self.aftertouch,
             modwheel: self.modwheel,
+            sidechain: self.sidechain,
         };
-        for v in self.voices.iter_mut() {
+        let num_outs = outs.len();
+        for (i, v) in self.voices.iter_mut().enumerate() {
+            // Skip voices that have finished releasing.  They pick up any new
+            // modulation matrix when they next sound, rather than being run
+            // for a sample whenever one arrives, so that their state (and so
+            // the output) doesn't depend on the host's buffer size.
+            if !v.gate && v.voice.is_silent(&self.ctx) {
+                continue;
+            }
+            let matrix_param = if std::mem::take(&mut v.matrix_stale) {
+                Some(&self.matrix)
+            } else {
+                None
+            };
             let input = &VoiceInput::<i16> {
                 note: v.note.add_signed(self.pitch_bend),
                 gate: v.gate,
                 velocity: v.vel,
+                pan: v.pan.saturating_mul(IScalarFxP::saturating_from_num(self.spread)),
             };
-            out += T::sample_to_float(v.voice.next(
+            let out = v.voice.next(
                 &self.ctx,
                 matrix_param,
                 &input.into(),
                 &ch_in.into(),
                 params.into(),
-            ));
+            );
+            let (left, right) = &mut outs[i % num_outs];
+            // Signal is a hair hot (0dB), so attenuate it just a bit...
+            *left += T::sample_to_float(out.left) / 8.;
+            *right += T::sample_to_float(out.right) / 8.;
         }
-        // Signal is a hair hot (0dB), so attenuate it just a bit...
-        out / 8.
+    }
+    fn voice_snapshot(&self) -> VoiceSnapshot {
+        self.voices
+            .get(self.last_voice)
+            .map(|v| snapshot_voice(&v.voice))
+            .unwrap_or_default()
+    }
+    fn capture_voice(&self) -> Option<CapturedVoice> {
+        let voice = self.voices.get(self.last_voice)?;
+        Some(T::capture(voice.voice.dump_state()))
+    }
+    fn restore_voice(&mut self, state: &CapturedVoice) -> bool {
+        self.voices
+            .get_mut(self.last_voice)
+            .is_some_and(|v| restore_voice(&mut v.voice, state))
+    }
+    fn take_state_reset(&mut self) -> bool {
+        // Take the flag from every voice, not just up to the first reset
+        self.voices
+            .iter_mut()
+            .fold(false, |reset, v| v.voice.take_state_reset() || reset)
     }
     fn get_context(&self) -> &dyn GenericContext {
         <T::Context as culsynth::context::GetContext>::get_context(&self.ctx)
     }
-    fn is_poly(&self) -> bool {
-        true
+    fn set_sample_rate(&mut self, sample_rate: u32) -> bool {
+        self.ctx.set_sample_rate(sample_rate)
+    }
+    fn set_poly_spread(&mut self, spread: ScalarFxP, mode: SpreadMode) {
+        self.spread = spread;
+        self.spread_mode = mode;
+    }
+    fn set_dephase(&mut self, dephase: bool, randomize: bool) {
+        self.dephase = dephase;
+        self.phase_randomize = randomize;
+    }
+    fn voice_mode(&self) -> VoiceMode {
+        VoiceMode::Poly16
     }
 }
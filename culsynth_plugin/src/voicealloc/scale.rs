@@ -0,0 +1,112 @@
+use super::*;
+
+/// A scale mask with every pitch class enabled, so notes are unchanged
+pub const CHROMATIC_MASK: u16 = 0xFFF;
+
+/// The names of the pitch classes in a scale mask, starting from C
+pub const PITCH_CLASSES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+
+/// Preset scales for a [ScaleQuantizer], all starting on C
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Scale {
+    /// Every note is in the scale (no quantization)
+    #[default]
+    Chromatic,
+    /// The major (Ionian) scale
+    Major,
+    /// The natural minor (Aeolian) scale
+    Minor,
+    /// The major pentatonic scale
+    Pentatonic,
+}
+
+impl Scale {
+    const ELEM: [Scale; 4] = [Self::Chromatic, Self::Major, Self::Minor, Self::Pentatonic];
+    /// Returns a slice to all of the preset scales
+    pub const fn scales() -> &'static [Scale] {
+        &Self::ELEM
+    }
+    /// Provides the name of the scale
+    pub const fn to_str(&self) -> &'static str {
+        ["Chromatic", "Major", "Minor", "Pentatonic"][*self as usize]
+    }
+    /// The 12 bit mask of the pitch classes in this scale, where bit `n` is
+    /// set if the note `n` semitones above C is in the scale
+    pub const fn mask(&self) -> u16 {
+        [CHROMATIC_MASK, 0xAB5, 0x5AD, 0x295][*self as usize]
+    }
+    /// The preset scale with the given mask, if there is one
+    pub fn from_mask(mask: u16) -> Option<Self> {
+        Self::ELEM.into_iter().find(|scale| scale.mask() == mask)
+    }
+}
+
+/// Snaps incoming notes to the nearest note in a scale, given as a 12 bit
+/// mask of pitch classes (see [Scale::mask]).  A note halfway between two
+/// scale notes snaps down.
+///
+/// The note each key was quantized to is remembered until the key is
+/// released, so changing the scale while notes are held never leaves a note
+/// stuck.  Several keys may snap to the same note, in which case releasing
+/// any of them releases that note.
+pub struct ScaleQuantizer {
+    mask: u16,
+    sounding: [u8; 128],
+}
+
+impl Default for ScaleQuantizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScaleQuantizer {
+    /// Constructor, with quantization disabled (a chromatic scale)
+    pub fn new() -> Self {
+        Self {
+            mask: CHROMATIC_MASK,
+            sounding: core::array::from_fn(|note| note as u8),
+        }
+    }
+    /// Change the scale used for new notes.  An empty mask is treated as a
+    /// chromatic scale.
+    pub fn set_mask(&mut self, mask: u16) {
+        self.mask = match mask & CHROMATIC_MASK {
+            0 => CHROMATIC_MASK,
+            mask => mask,
+        };
+    }
+    /// The nearest note to `note` in the scale
+    pub fn quantize(&self, note: u8) -> u8 {
+        let in_scale = |note: u8| note <= 127 && self.mask & (1 << (note % 12)) != 0;
+        (0..12u8)
+            .flat_map(|dist| [note.checked_sub(dist), note.checked_add(dist)])
+            .flatten()
+            .find(|note| in_scale(*note))
+            .unwrap_or(note)
+    }
+    /// Quantize a note on, or release the note that a key was quantized to
+    /// for a note off.  Other events pass through unchanged.
+    pub fn map(&mut self, event: NoteEvent) -> NoteEvent {
+        match event {
+            NoteEvent::NoteOn { note, velocity } => {
+                let quantized = self.quantize(note);
+                if let Some(sounding) = self.sounding.get_mut(note as usize) {
+                    *sounding = quantized;
+                }
+                NoteEvent::NoteOn {
+                    note: quantized,
+                    velocity,
+                }
+            }
+            NoteEvent::NoteOff { note, velocity } => NoteEvent::NoteOff {
+                note: self.sounding.get(note as usize).copied().unwrap_or(note),
+                velocity,
+            },
+            event => event,
+        }
+    }
+}
@@ -0,0 +1,149 @@
+//! Verify that notes played on a direct MIDI port reach the voice allocator,
+//! using mock ports in place of `midir` and the MIDI hardware.
+
+#![cfg(all(feature = "midir", feature = "fixed"))]
+
+use culsynth::context::ContextFxP;
+use culsynth::voice::VoiceParams;
+use culsynth_plugin::direct_midi::{MidiCallback, MidiInputPorts, MidiPortSelector};
+use culsynth_plugin::voicealloc::{NoteEvent, NoteEventQueue, PolySynth, VoiceAllocator};
+use std::sync::mpsc::sync_channel;
+use std::sync::{Arc, Mutex};
+
+type Connected = Arc<Mutex<Option<MidiCallback>>>;
+
+/// Mock ports, where the test plays the role of the MIDI hardware by calling
+/// the callback of the connected port, and can plug and unplug ports
+#[derive(Clone)]
+struct MockPorts {
+    names: Arc<Mutex<Vec<String>>>,
+    connected: Connected,
+}
+
+impl MockPorts {
+    fn new(names: &[&str]) -> Self {
+        Self {
+            names: Arc::new(Mutex::new(names.iter().map(|s| s.to_string()).collect())),
+            connected: Connected::default(),
+        }
+    }
+    fn set_names(&self, names: &[&str]) {
+        *self.names.lock().unwrap() = names.iter().map(|s| s.to_string()).collect();
+    }
+    fn is_connected(&self) -> bool {
+        self.connected.lock().unwrap().is_some()
+    }
+    fn play(&self, bytes: &[u8]) {
+        if let Some(callback) = self.connected.lock().unwrap().as_mut() {
+            callback(bytes);
+        }
+    }
+}
+
+/// Disconnects the port when dropped, like a real connection
+struct MockConnection(Connected);
+
+impl Drop for MockConnection {
+    fn drop(&mut self) {
+        *self.0.lock().unwrap() = None;
+    }
+}
+
+impl MidiInputPorts for MockPorts {
+    type Connection = MockConnection;
+    fn port_names(&mut self) -> Vec<String> {
+        self.names.lock().unwrap().clone()
+    }
+    fn connect(
+        &mut self,
+        port: usize,
+        callback: MidiCallback,
+    ) -> Result<MockConnection, &'static str> {
+        if port >= self.names.lock().unwrap().len() {
+            return Err("No such MIDI port");
+        }
+        *self.connected.lock().unwrap() = Some(callback);
+        Ok(MockConnection(self.connected.clone()))
+    }
+}
+
+#[test]
+fn port_notes_reach_voices() {
+    let ports = MockPorts::new(&["Keyboard", "Drum Pads"]);
+    let (midi_tx, midi_rx) = sync_channel(32);
+    let mut selector = MidiPortSelector::new(ports.clone(), midi_tx);
+    assert_eq!(selector.port_names().len(), 2);
+    assert!(selector.select(Some(2)).is_err());
+    assert_eq!(selector.selected(), None);
+    selector.select(Some(0)).unwrap();
+    // Note on for A4 on channel 1, then an unhandled message (clock)
+    ports.play(&[0x90, 69, 100]);
+    ports.play(&[0xF8]);
+
+    // Process the events the same way as the audio thread:
+    let mut synth = PolySynth::<i16>::new(ContextFxP::new_480(), 4);
+    let (mut dispatcher, _rx) = sync_channel::<(u8, u8)>(1);
+    let mut events = NoteEventQueue::new();
+    while let Ok(event) = midi_rx.try_recv() {
+        events.push(0, event);
+    }
+    assert_eq!(events.len(), 1);
+    events.apply_due(0, &mut synth, &mut dispatcher);
+    synth.next(&VoiceParams::default(), None);
+    let voices: Vec<_> = synth.active_voice_info().collect();
+    assert_eq!(voices.len(), 1);
+    assert_eq!(
+        (voices[0].note, voices[0].velocity, voices[0].gate),
+        (69, 100, true)
+    );
+
+    // A note on with zero velocity is a note off
+    ports.play(&[0x90, 69, 0]);
+    let event = midi_rx.try_recv().unwrap();
+    assert_eq!(
+        event,
+        NoteEvent::NoteOff {
+            note: 69,
+            velocity: 0
+        }
+    );
+    event.apply(&mut synth, &mut dispatcher);
+    synth.next(&VoiceParams::default(), None);
+    assert!(!synth.active_voice_info().next().unwrap().gate);
+
+    // Nothing is received once disconnected
+    selector.select(None).unwrap();
+    assert_eq!(selector.selected(), None);
+    ports.play(&[0x90, 69, 100]);
+    assert!(midi_rx.try_recv().is_err());
+}
+
+#[test]
+fn refresh_follows_selected_port() {
+    let ports = MockPorts::new(&["Keyboard", "Drum Pads"]);
+    let (midi_tx, midi_rx) = sync_channel(32);
+    let mut selector = MidiPortSelector::new(ports.clone(), midi_tx);
+    selector.select(Some(1)).unwrap();
+
+    // Plugging in another port moves the selection, but keeps the connection
+    ports.set_names(&["USB Hub", "Keyboard", "Drum Pads"]);
+    selector.refresh();
+    assert_eq!(selector.selected(), Some(2));
+    assert_eq!(selector.port_names()[2], "Drum Pads");
+    ports.play(&[0x90, 36, 127]);
+    assert_eq!(
+        midi_rx.try_recv().unwrap(),
+        NoteEvent::NoteOn {
+            note: 36,
+            velocity: 127
+        }
+    );
+
+    // Unplugging the selected port disconnects it
+    ports.set_names(&["Keyboard"]);
+    selector.refresh();
+    assert_eq!(selector.selected(), None);
+    assert!(!ports.is_connected());
+    ports.play(&[0x90, 36, 127]);
+    assert!(midi_rx.try_recv().is_err());
+}